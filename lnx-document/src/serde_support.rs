@@ -6,7 +6,7 @@ use serde::{Deserialize, Deserializer};
 
 use crate::value::{DynamicDocument, Value};
 
-impl<'de> Deserialize<'de> for DynamicDocument<'de> {
+impl<'de: 'a, 'a> Deserialize<'de> for DynamicDocument<'a> {
     #[inline]
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -39,7 +39,7 @@ impl<'de> Deserialize<'de> for DynamicDocument<'de> {
     }
 }
 
-impl<'de> Deserialize<'de> for Value<'de> {
+impl<'de: 'a, 'a> Deserialize<'de> for Value<'a> {
     #[inline]
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
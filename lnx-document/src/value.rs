@@ -5,7 +5,7 @@ use std::ops::{Deref, DerefMut};
 
 use tantivy::schema::FacetParseError;
 use time::formatting::Formattable;
-use time::OffsetDateTime;
+use time::{OffsetDateTime, UtcOffset};
 
 use crate::wrappers::{Bytes, Text};
 use crate::{FieldType, UserDisplayType};
@@ -328,9 +328,20 @@ impl DateTime {
     pub fn format(
         &self,
         format: &(impl Formattable + ?Sized),
+    ) -> anyhow::Result<String> {
+        self.format_with_offset(format, UtcOffset::UTC)
+    }
+
+    #[inline]
+    /// Formats the datetime into a given format, rendered in the given UTC offset.
+    pub fn format_with_offset(
+        &self,
+        format: &(impl Formattable + ?Sized),
+        offset: UtcOffset,
     ) -> anyhow::Result<String> {
         OffsetDateTime::from_unix_timestamp_nanos(self.micros as i128 * 1000)
             .map_err(|_| anyhow::anyhow!("Cannot format datetime as is beyond what the format supports rendering"))?
+            .to_offset(offset)
             .format(format)
             .map_err(|e| anyhow::anyhow!("Cannot format datetime with the given format: {e}"))
     }
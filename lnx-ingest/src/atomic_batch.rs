@@ -0,0 +1,186 @@
+use lnx_document::DynamicDocument;
+use serde::{Deserialize, Serialize};
+use tantivy::indexer::UserOperation;
+use tantivy::schema::DocumentAccess;
+use tantivy::{IndexWriter, Opstamp};
+
+use crate::error::IngestError;
+use crate::upsert::PrimaryKey;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+/// A single operation within an atomic batch.
+pub enum BatchOperation<'a> {
+    /// Adds (or replaces) a document by its primary key.
+    Add {
+        #[serde(borrow)]
+        document: DynamicDocument<'a>,
+    },
+    /// Deletes a document by its primary key.
+    Delete { id: serde_json::Value },
+}
+
+#[derive(Debug, Deserialize)]
+/// A group of operations on an index which either all become visible or none do.
+pub struct AtomicBatchRequest<'a> {
+    #[serde(borrow)]
+    pub operations: Vec<BatchOperation<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// An operation within a batch which failed validation.
+pub struct BatchOperationError {
+    /// The position of the operation within the batch, starting at `0`.
+    pub operation: usize,
+    /// The reason the operation is invalid.
+    pub error: String,
+}
+
+/// Validates every operation within the batch, producing the writer operations to apply it.
+///
+/// Each document is converted via `convert`, i.e. by the index's transform pipeline.
+/// If any operation is invalid the whole batch is rejected with the errors of every
+/// invalid operation, so nothing from the batch is applied.
+pub fn prepare_atomic_batch<'a, D: DocumentAccess>(
+    key: &PrimaryKey,
+    request: AtomicBatchRequest<'a>,
+    mut convert: impl FnMut(DynamicDocument<'a>) -> Result<D, String>,
+) -> Result<Vec<UserOperation<D>>, IngestError> {
+    let mut operations = Vec::with_capacity(request.operations.len() * 2);
+    let mut errors = Vec::new();
+
+    for (operation, op) in request.operations.into_iter().enumerate() {
+        let result = match op {
+            BatchOperation::Add { document } => key.term(&document).and_then(|term| {
+                let document = convert(document)?;
+                operations.extend(key.delete_operations(term));
+                operations.push(UserOperation::Add(document));
+                Ok(())
+            }),
+            BatchOperation::Delete { id } => key.term_for_json(&id).map(|term| {
+                operations.extend(key.delete_operations(term));
+            }),
+        };
+
+        if let Err(error) = result {
+            errors.push(BatchOperationError { operation, error });
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(IngestError::BatchRejected(errors));
+    }
+
+    Ok(operations)
+}
+
+/// Applies a prepared batch to the index and commits it.
+///
+/// The operations are submitted as a single group so they become visible in the same
+/// commit, if the commit fails the writer is rolled back so none of the batch is applied.
+/// The writer must not be shared with other writes while the batch is applied, otherwise
+/// their pending changes are committed (or rolled back) along with the batch.
+pub fn apply_atomic_batch<D: DocumentAccess>(
+    writer: &mut IndexWriter<D>,
+    operations: Vec<UserOperation<D>>,
+) -> Result<Opstamp, IngestError> {
+    let result = writer.run(operations).and_then(|_| writer.commit());
+
+    match result {
+        Ok(opstamp) => Ok(opstamp),
+        Err(e) => {
+            writer.rollback()?;
+            Err(e.into())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lnx_document::Value;
+    use tantivy::schema::{Schema, SchemaBuilder, STRING, TEXT};
+    use tantivy::Document;
+
+    use super::*;
+
+    fn test_schema() -> Schema {
+        let mut schema = SchemaBuilder::new();
+        schema.add_text_field("id", STRING);
+        schema.add_text_field("title", TEXT);
+        schema.build()
+    }
+
+    fn primary_key() -> PrimaryKey {
+        PrimaryKey::from_schema(&test_schema(), "id").unwrap()
+    }
+
+    fn to_document(
+        schema: &Schema,
+        document: DynamicDocument,
+    ) -> Result<Document, String> {
+        let mut converted = Document::default();
+        for (key, value) in document.0 {
+            let field = schema.get_field(&key).map_err(|e| e.to_string())?;
+            match value {
+                Value::Str(text) => converted.add_text(field, text),
+                other => return Err(format!("Unexpected value {other:?}")),
+            }
+        }
+        Ok(converted)
+    }
+
+    #[test]
+    fn test_prepare_atomic_batch() {
+        let request: AtomicBatchRequest = serde_json::from_str(
+            r#"{"operations": [
+                {"op": "add", "document": {"id": "a", "title": "Hello"}},
+                {"op": "delete", "id": "b"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let schema = test_schema();
+        let operations = prepare_atomic_batch(&primary_key(), request, |doc| {
+            to_document(&schema, doc)
+        })
+        .unwrap();
+        assert_eq!(operations.len(), 3);
+        assert!(matches!(operations[0], UserOperation::Delete(_)));
+        assert!(matches!(
+            &operations[1],
+            UserOperation::Add(document) if document.len() == 2
+        ));
+        assert!(matches!(operations[2], UserOperation::Delete(_)));
+    }
+
+    #[test]
+    fn test_invalid_batch_is_rejected() {
+        let request: AtomicBatchRequest = serde_json::from_str(
+            r#"{"operations": [
+                {"op": "add", "document": {"id": "a"}},
+                {"op": "add", "document": {"title": "missing id"}},
+                {"op": "add", "document": {"id": "c"}},
+                {"op": "delete", "id": null}
+            ]}"#,
+        )
+        .unwrap();
+
+        let schema = test_schema();
+        let result = prepare_atomic_batch(&primary_key(), request, |doc| {
+            if doc
+                .iter()
+                .any(|(_, value)| format!("{value:?}").contains('c'))
+            {
+                return Err("Rejected by transform".to_string());
+            }
+            to_document(&schema, doc)
+        });
+
+        let Err(IngestError::BatchRejected(errors)) = result else {
+            panic!("Expected the batch to be rejected");
+        };
+        let failed = errors.iter().map(|e| e.operation).collect::<Vec<_>>();
+        assert_eq!(failed, [1, 2, 3]);
+        assert_eq!(errors[1].error, "Rejected by transform");
+    }
+}
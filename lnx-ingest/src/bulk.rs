@@ -0,0 +1,99 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::dead_letter::RejectedDocument;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The outcome of ingesting a single line of a bulk request.
+pub struct BulkItemResult {
+    /// The line number of the document, starting at `1`.
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The reason the document was rejected, if it was rejected.
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// Any problems with a document which was still ingested, i.e. values which
+    /// were truncated by the token limits of their field.
+    pub warnings: Vec<String>,
+}
+
+/// The value returned by a writer for each document it accepts.
+///
+/// Writers can return `()`, or the warnings about the document which are reported
+/// in its [BulkItemResult].
+pub trait WriteOutcome {
+    /// Converts the outcome into the warnings about the document.
+    fn into_warnings(self) -> Vec<String>;
+}
+
+impl WriteOutcome for () {
+    fn into_warnings(self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl WriteOutcome for Vec<String> {
+    fn into_warnings(self) -> Vec<String> {
+        self
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+/// The outcome of a bulk ingestion request.
+pub struct BulkResponse {
+    /// The number of documents which were ingested.
+    pub succeeded: usize,
+    /// The number of documents which were rejected.
+    pub failed: usize,
+    /// The outcome of each line within the request.
+    pub items: Vec<BulkItemResult>,
+    #[serde(skip)]
+    /// The source of each rejected document, these can be recorded in the
+    /// index's `DeadLetterStore` so they can be fixed and replayed.
+    pub rejected: Vec<RejectedDocument>,
+}
+
+impl BulkResponse {
+    /// Records a document being successfully ingested.
+    pub fn record_success(&mut self, line: usize) {
+        self.record_success_with_warnings(line, Vec::new());
+    }
+
+    /// Records a document being successfully ingested with the given warnings.
+    pub fn record_success_with_warnings(&mut self, line: usize, warnings: Vec<String>) {
+        self.succeeded += 1;
+        self.items.push(BulkItemResult {
+            line,
+            error: None,
+            warnings,
+        });
+    }
+
+    /// Records a document being rejected.
+    pub fn record_failure(&mut self, line: usize, error: impl Display) {
+        self.failed += 1;
+        self.items.push(BulkItemResult {
+            line,
+            error: Some(error.to_string()),
+            warnings: Vec::new(),
+        });
+    }
+
+    /// Records a document being rejected, retaining its source.
+    pub fn record_rejection(&mut self, line: usize, source: &str, error: impl Display) {
+        let error = error.to_string();
+        self.rejected.push(RejectedDocument {
+            line,
+            source: source.to_string(),
+            error: error.clone(),
+        });
+        self.record_failure(line, error);
+    }
+
+    #[inline]
+    /// Returns if any of the documents were rejected.
+    pub fn has_errors(&self) -> bool {
+        self.failed > 0
+    }
+}
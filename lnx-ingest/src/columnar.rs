@@ -0,0 +1,322 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io::Read;
+
+use arrow::array::{Array, AsArray, GenericListArray, OffsetSizeTrait, RecordBatch};
+use arrow::datatypes::{
+    ArrowNativeType,
+    DataType,
+    Date32Type,
+    Date64Type,
+    Float32Type,
+    Float64Type,
+    Int16Type,
+    Int32Type,
+    Int64Type,
+    Int8Type,
+    TimeUnit,
+    TimestampMicrosecondType,
+    TimestampMillisecondType,
+    TimestampNanosecondType,
+    TimestampSecondType,
+    UInt16Type,
+    UInt32Type,
+    UInt64Type,
+    UInt8Type,
+};
+use arrow::error::ArrowError;
+use arrow::ipc::reader::StreamReader;
+use bytes::Bytes;
+use lnx_document::{DateTime, DynamicDocument, Value};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+use crate::bulk::BulkResponse;
+use crate::error::IngestError;
+
+/// The number of rows decoded from a parquet file at a time.
+const PARQUET_BATCH_SIZE: usize = 8192;
+
+/// Ingests every row within a parquet file, passing each row to the writer as a document.
+///
+/// Rows are numbered from `1` in the produced `BulkResponse`.
+pub fn ingest_parquet<E: Display>(
+    body: Bytes,
+    writer: impl FnMut(DynamicDocument<'_>) -> Result<(), E>,
+) -> Result<BulkResponse, IngestError> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(body)?
+        .with_batch_size(PARQUET_BATCH_SIZE)
+        .build()?;
+    ingest_record_batches(reader, writer)
+}
+
+/// Ingests every row within an Arrow IPC stream, passing each row to the writer as a document.
+///
+/// Rows are numbered from `1` in the produced `BulkResponse`.
+pub fn ingest_arrow_ipc<R: Read, E: Display>(
+    reader: R,
+    writer: impl FnMut(DynamicDocument<'_>) -> Result<(), E>,
+) -> Result<BulkResponse, IngestError> {
+    let reader = StreamReader::try_new(reader, None)?;
+    ingest_record_batches(reader, writer)
+}
+
+/// Ingests the rows of a set of record batches.
+///
+/// Rows rejected by the writer are recorded as failures without stopping the
+/// remaining rows from being ingested, but a batch which cannot be decoded, or
+/// which contains a column type that cannot be converted, fails the entire request.
+pub fn ingest_record_batches<E: Display>(
+    batches: impl IntoIterator<Item = Result<RecordBatch, ArrowError>>,
+    mut writer: impl FnMut(DynamicDocument<'_>) -> Result<(), E>,
+) -> Result<BulkResponse, IngestError> {
+    let mut response = BulkResponse::default();
+    let mut row = 0;
+
+    for batch in batches {
+        let batch = batch?;
+        check_supported_columns(&batch)?;
+
+        for document in record_batch_to_documents(&batch) {
+            row += 1;
+            match writer(document) {
+                Ok(()) => response.record_success(row),
+                Err(e) => response.record_failure(row, e),
+            }
+        }
+    }
+
+    Ok(response)
+}
+
+/// Converts each row of a record batch into a document.
+///
+/// String values are borrowed directly from the batch rather than copied.
+///
+/// # Panics
+/// If the batch contains a column type which is not supported, see
+/// `check_supported_columns`.
+fn record_batch_to_documents(batch: &RecordBatch) -> Vec<DynamicDocument<'_>> {
+    let schema = batch.schema();
+    let mut documents = Vec::with_capacity(batch.num_rows());
+
+    for row in 0..batch.num_rows() {
+        let document = schema
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .map(|(field, column)| {
+                let key = Cow::Owned(field.name().clone());
+                (key, value_at(column.as_ref(), row))
+            })
+            .collect();
+        documents.push(DynamicDocument(document));
+    }
+
+    documents
+}
+
+/// Ensures every column of the batch can be converted into a document value.
+fn check_supported_columns(batch: &RecordBatch) -> Result<(), IngestError> {
+    for field in batch.schema().fields() {
+        if !is_supported(field.data_type()) {
+            return Err(IngestError::UnsupportedColumn {
+                column: field.name().clone(),
+                data_type: field.data_type().to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn is_supported(data_type: &DataType) -> bool {
+    match data_type {
+        DataType::List(field) | DataType::LargeList(field) => {
+            is_supported(field.data_type())
+        },
+        DataType::Struct(fields) => {
+            fields.iter().all(|field| is_supported(field.data_type()))
+        },
+        DataType::Null
+        | DataType::Boolean
+        | DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64
+        | DataType::Float32
+        | DataType::Float64
+        | DataType::Utf8
+        | DataType::LargeUtf8
+        | DataType::Binary
+        | DataType::LargeBinary
+        | DataType::Date32
+        | DataType::Date64
+        | DataType::Timestamp(_, _) => true,
+        _ => false,
+    }
+}
+
+/// Converts a single value of an array into a document value.
+fn value_at(array: &dyn Array, row: usize) -> Value<'_> {
+    if array.is_null(row) {
+        return Value::Null;
+    }
+
+    match array.data_type() {
+        DataType::Null => Value::Null,
+        DataType::Boolean => Value::Bool(array.as_boolean().value(row)),
+        DataType::Int8 => Value::I64(array.as_primitive::<Int8Type>().value(row) as i64),
+        DataType::Int16 => {
+            Value::I64(array.as_primitive::<Int16Type>().value(row) as i64)
+        },
+        DataType::Int32 => {
+            Value::I64(array.as_primitive::<Int32Type>().value(row) as i64)
+        },
+        DataType::Int64 => Value::I64(array.as_primitive::<Int64Type>().value(row)),
+        DataType::UInt8 => {
+            Value::U64(array.as_primitive::<UInt8Type>().value(row) as u64)
+        },
+        DataType::UInt16 => {
+            Value::U64(array.as_primitive::<UInt16Type>().value(row) as u64)
+        },
+        DataType::UInt32 => {
+            Value::U64(array.as_primitive::<UInt32Type>().value(row) as u64)
+        },
+        DataType::UInt64 => Value::U64(array.as_primitive::<UInt64Type>().value(row)),
+        DataType::Float32 => {
+            Value::F64(array.as_primitive::<Float32Type>().value(row) as f64)
+        },
+        DataType::Float64 => Value::F64(array.as_primitive::<Float64Type>().value(row)),
+        DataType::Utf8 => Value::Str(Cow::Borrowed(array.as_string::<i32>().value(row))),
+        DataType::LargeUtf8 => {
+            Value::Str(Cow::Borrowed(array.as_string::<i64>().value(row)))
+        },
+        DataType::Binary => Value::Bytes(array.as_binary::<i32>().value(row).to_vec()),
+        DataType::LargeBinary => {
+            Value::Bytes(array.as_binary::<i64>().value(row).to_vec())
+        },
+        DataType::Date32 => {
+            let days = array.as_primitive::<Date32Type>().value(row) as i64;
+            datetime_value(DateTime::from_secs(days * 86_400))
+        },
+        DataType::Date64 => {
+            let millis = array.as_primitive::<Date64Type>().value(row);
+            datetime_value(DateTime::from_millis(millis))
+        },
+        DataType::Timestamp(unit, _) => {
+            let dt = match unit {
+                TimeUnit::Second => DateTime::from_secs(
+                    array.as_primitive::<TimestampSecondType>().value(row),
+                ),
+                TimeUnit::Millisecond => DateTime::from_millis(
+                    array.as_primitive::<TimestampMillisecondType>().value(row),
+                ),
+                TimeUnit::Microsecond => DateTime::from_micros(
+                    array.as_primitive::<TimestampMicrosecondType>().value(row),
+                ),
+                TimeUnit::Nanosecond => DateTime::from_micros(
+                    array.as_primitive::<TimestampNanosecondType>().value(row) / 1000,
+                ),
+            };
+            datetime_value(dt)
+        },
+        DataType::List(_) => list_value(array.as_list::<i32>(), row),
+        DataType::LargeList(_) => list_value(array.as_list::<i64>(), row),
+        DataType::Struct(fields) => {
+            let array = array.as_struct();
+            let object = fields
+                .iter()
+                .zip(array.columns())
+                .map(|(field, column)| {
+                    (
+                        Cow::Borrowed(field.name().as_str()),
+                        value_at(column.as_ref(), row),
+                    )
+                })
+                .collect();
+            Value::Object(object)
+        },
+        other => unreachable!("Unsupported column type {other}"),
+    }
+}
+
+fn list_value<O: OffsetSizeTrait>(array: &GenericListArray<O>, row: usize) -> Value<'_> {
+    // The child values are accessed directly rather than through `array.value(row)`
+    // so that strings can be borrowed from the batch rather than a temporary slice.
+    let offsets = array.value_offsets();
+    let start = offsets[row].as_usize();
+    let end = offsets[row + 1].as_usize();
+
+    let values = array.values().as_ref();
+    Value::Array((start..end).map(|i| value_at(values, i)).collect())
+}
+
+fn datetime_value<'a>(dt: Option<DateTime>) -> Value<'a> {
+    dt.map(Value::DateTime).unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::{Int64Array, ListArray, StringArray, TimestampSecondArray};
+    use arrow::buffer::OffsetBuffer;
+    use arrow::datatypes::{Field, Schema};
+
+    use super::*;
+
+    #[test]
+    fn test_record_batch_to_documents() {
+        let tags = Arc::new(
+            ListArray::try_new(
+                Arc::new(Field::new("item", DataType::Utf8, true)),
+                OffsetBuffer::new(vec![0, 2, 2].into()),
+                Arc::new(StringArray::from(vec!["a", "b"])),
+                None,
+            )
+            .unwrap(),
+        );
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("title", DataType::Utf8, true),
+                Field::new("views", DataType::Int64, false),
+                Field::new(
+                    "created_at",
+                    DataType::Timestamp(TimeUnit::Second, None),
+                    false,
+                ),
+                Field::new("tags", tags.data_type().clone(), false),
+            ])),
+            vec![
+                Arc::new(StringArray::from(vec![Some("Hello"), None])),
+                Arc::new(Int64Array::from(vec![12, -1])),
+                Arc::new(TimestampSecondArray::from(vec![1_000, 2_000])),
+                tags,
+            ],
+        )
+        .unwrap();
+
+        let documents = record_batch_to_documents(&batch);
+        assert_eq!(documents.len(), 2);
+        assert_eq!(
+            documents[0].0,
+            vec![
+                (Cow::Borrowed("title"), Value::from("Hello")),
+                (Cow::Borrowed("views"), Value::I64(12)),
+                (
+                    Cow::Borrowed("created_at"),
+                    Value::DateTime(DateTime::from_secs(1_000).unwrap())
+                ),
+                (
+                    Cow::Borrowed("tags"),
+                    Value::Array(vec![Value::from("a"), Value::from("b")])
+                ),
+            ],
+        );
+        assert_eq!(documents[1].0[0].1, Value::Null);
+        assert_eq!(documents[1].0[3].1, Value::Array(vec![]));
+    }
+}
@@ -0,0 +1,212 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Read;
+
+use csv::{ReaderBuilder, StringRecord};
+use lnx_document::{DynamicDocument, Value};
+use lnx_transforms::TypeCast;
+
+use crate::bulk::{BulkResponse, WriteOutcome};
+use crate::error::IngestError;
+
+#[derive(Default)]
+/// How the columns of a CSV body map onto the fields of a document.
+pub struct CsvMapping {
+    columns: HashMap<String, CsvColumn>,
+    empty_as_null: bool,
+    delimiter: Option<u8>,
+}
+
+#[derive(Default)]
+struct CsvColumn {
+    field: Option<String>,
+    cast: Option<TypeCast>,
+}
+
+impl CsvMapping {
+    /// Maps the column with the given header onto a differently named field.
+    ///
+    /// By default each column is mapped onto the field with the same name as its header.
+    pub fn with_field_name(
+        mut self,
+        header: impl Into<String>,
+        field: impl Into<String>,
+    ) -> Self {
+        self.columns.entry(header.into()).or_default().field = Some(field.into());
+        self
+    }
+
+    /// Casts the values of the column with the given header to a specific type.
+    ///
+    /// By default values are passed on as strings and are cast to the field's
+    /// type by the index's transform pipeline.
+    pub fn with_column_type(
+        mut self,
+        header: impl Into<String>,
+        cast: TypeCast,
+    ) -> Self {
+        self.columns.entry(header.into()).or_default().cast = Some(cast);
+        self
+    }
+
+    /// Treats empty values as `null` rather than an empty string.
+    pub fn with_empty_as_null(mut self, empty_as_null: bool) -> Self {
+        self.empty_as_null = empty_as_null;
+        self
+    }
+
+    /// Sets the delimiter between values, defaults to `,`.
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Converts a record into a document using the resolved columns.
+    fn to_document<'a>(
+        &'a self,
+        columns: &[(&'a str, Option<&'a TypeCast>)],
+        record: &'a StringRecord,
+    ) -> Result<DynamicDocument<'a>, String> {
+        let mut document = Vec::with_capacity(record.len());
+        for (&(field, cast), value) in columns.iter().zip(record.iter()) {
+            let value = match cast {
+                _ if value.is_empty() && self.empty_as_null => Value::Null,
+                Some(cast) => cast
+                    .try_cast_str(value)
+                    .map_err(|e| format!("Invalid value for column {field:?}: {e}"))?,
+                None => Value::Str(Cow::Borrowed(value)),
+            };
+            document.push((Cow::Borrowed(field), value));
+        }
+        Ok(DynamicDocument(document))
+    }
+}
+
+/// Ingests every row within a CSV stream, passing each row to the writer as a document.
+///
+/// The first row must be a header row which is used to map each column onto a field.
+/// Rows which cannot be parsed, or which are rejected by the writer, are recorded
+/// as failures without stopping the rest of the stream from being ingested.
+pub fn ingest_csv<R, W, E>(
+    reader: R,
+    mapping: &CsvMapping,
+    mut writer: impl FnMut(DynamicDocument<'_>) -> Result<W, E>,
+) -> Result<BulkResponse, IngestError>
+where
+    R: Read,
+    W: WriteOutcome,
+    E: Display,
+{
+    let mut builder = ReaderBuilder::new();
+    if let Some(delimiter) = mapping.delimiter {
+        builder.delimiter(delimiter);
+    }
+    let mut reader = builder.from_reader(reader);
+
+    let headers = reader.headers()?.clone();
+    let columns = headers
+        .iter()
+        .map(|header| match mapping.columns.get(header) {
+            Some(column) => (
+                column.field.as_deref().unwrap_or(header),
+                column.cast.as_ref(),
+            ),
+            None => (header, None),
+        })
+        .collect::<Vec<_>>();
+
+    let mut response = BulkResponse::default();
+    let mut record = StringRecord::new();
+    let mut line = 1;
+    loop {
+        let result = reader.read_record(&mut record);
+        line = record
+            .position()
+            .map(|pos| pos.line() as usize)
+            .unwrap_or(line + 1);
+
+        match result {
+            Ok(false) => break,
+            Ok(true) => {},
+            Err(e) if e.is_io_error() => return Err(e.into()),
+            Err(e) => {
+                let line = e.position().map_or(line, |pos| pos.line() as usize);
+                response.record_failure(line, e);
+                continue;
+            },
+        }
+
+        match mapping.to_document(&columns, &record).map(&mut writer) {
+            Ok(Ok(outcome)) => {
+                response.record_success_with_warnings(line, outcome.into_warnings())
+            },
+            Ok(Err(e)) => response.record_failure(line, e),
+            Err(e) => response.record_failure(line, e),
+        }
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_ingest_csv() {
+        let body = concat!(
+            "title,views,author\n",
+            "Hello,12,bob\n",
+            "World,,\n",
+            "Invalid,abc,tim\n",
+            "Short\n",
+        );
+
+        let mapping = CsvMapping::default()
+            .with_field_name("views", "view_count")
+            .with_column_type("views", TypeCast::U64)
+            .with_empty_as_null(true);
+
+        let mut documents = Vec::new();
+        let response = ingest_csv(Cursor::new(body), &mapping, |doc| {
+            documents.push(format!("{:?}", doc.0));
+            Ok::<_, String>(())
+        })
+        .unwrap();
+
+        assert_eq!(response.succeeded, 2);
+        assert_eq!(response.failed, 2);
+        assert_eq!(
+            documents[0],
+            format!(
+                "{:?}",
+                vec![
+                    (Cow::Borrowed("title"), Value::from("Hello")),
+                    (Cow::Borrowed("view_count"), Value::U64(12)),
+                    (Cow::Borrowed("author"), Value::from("bob")),
+                ]
+            ),
+        );
+        assert_eq!(
+            documents[1],
+            format!(
+                "{:?}",
+                vec![
+                    (Cow::Borrowed("title"), Value::from("World")),
+                    (Cow::Borrowed("view_count"), Value::Null),
+                    (Cow::Borrowed("author"), Value::Null),
+                ]
+            ),
+        );
+
+        let lines = response
+            .items
+            .iter()
+            .map(|item| item.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,208 @@
+use lnx_metastore::Metastore;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::error::IngestError;
+
+const DEAD_LETTER_DATABASE: &str = "lnx_dead_letters";
+
+/// The maximum number of rejected documents retained per index.
+///
+/// Once exceeded the oldest rejected documents are discarded.
+pub const MAX_DEAD_LETTERS_PER_INDEX: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A document which was rejected during ingestion.
+pub struct RejectedDocument {
+    /// The line (or row) of the document within the request, starting at `1`.
+    pub line: usize,
+    /// The raw source of the document.
+    pub source: String,
+    /// The reason the document was rejected.
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A rejected document held in the dead-letter store.
+pub struct DeadLetter {
+    /// The unique ID of the entry within the index's dead-letter store.
+    pub id: u64,
+    /// When the document was rejected, as a unix timestamp in microseconds.
+    pub rejected_at: i64,
+    #[serde(flatten)]
+    pub document: RejectedDocument,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct DeadLetterQueue {
+    next_id: u64,
+    entries: Vec<DeadLetter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// A page of rejected documents.
+pub struct DeadLetterPage {
+    /// The total number of rejected documents held for the index.
+    pub total: usize,
+    /// The rejected documents within the page, oldest first.
+    pub items: Vec<DeadLetter>,
+}
+
+/// A per-index store of documents rejected during ingestion along with their errors,
+/// so they can be inspected (i.e. via `GET /indexes/:index/_rejected`), fixed and
+/// replayed instead of being silently lost.
+pub struct DeadLetterStore {
+    metastore: Metastore,
+    lock: Mutex<()>,
+}
+
+impl DeadLetterStore {
+    /// Opens the dead-letter store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(DEAD_LETTER_DATABASE)?;
+        Ok(Self {
+            metastore,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Records a set of rejected documents for the index.
+    pub fn record(
+        &self,
+        index: &str,
+        documents: Vec<RejectedDocument>,
+    ) -> Result<(), IngestError> {
+        if documents.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = self.lock.lock();
+        let mut queue = self.load(index)?;
+        let rejected_at =
+            (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000) as i64;
+        queue.push(documents, rejected_at);
+        self.save(index, &queue)
+    }
+
+    /// Lists the rejected documents of the index, oldest first.
+    pub fn list(
+        &self,
+        index: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<DeadLetterPage, IngestError> {
+        let queue = self.load(index)?;
+        let total = queue.entries.len();
+        let items = queue.entries.into_iter().skip(offset).take(limit).collect();
+        Ok(DeadLetterPage { total, items })
+    }
+
+    /// Removes a set of entries from the index's store, i.e. once they have been replayed.
+    ///
+    /// Returns the number of entries removed.
+    pub fn remove(&self, index: &str, ids: &[u64]) -> Result<usize, IngestError> {
+        let _guard = self.lock.lock();
+        let mut queue = self.load(index)?;
+
+        let before = queue.entries.len();
+        queue.entries.retain(|entry| !ids.contains(&entry.id));
+        let removed = before - queue.entries.len();
+
+        if removed > 0 {
+            self.save(index, &queue)?;
+        }
+        Ok(removed)
+    }
+
+    /// Removes every entry from the index's store.
+    pub fn clear(&self, index: &str) -> Result<(), IngestError> {
+        let _guard = self.lock.lock();
+        self.metastore.del(&index)?;
+        Ok(())
+    }
+
+    fn load(&self, index: &str) -> Result<DeadLetterQueue, IngestError> {
+        let Some(source) = self.metastore.get::<_, String>(index)? else {
+            return Ok(DeadLetterQueue::default());
+        };
+
+        serde_json::from_str(&source).map_err(|e| {
+            IngestError::Metastore(anyhow::anyhow!("Corrupted dead-letter store: {e}"))
+        })
+    }
+
+    fn save(&self, index: &str, queue: &DeadLetterQueue) -> Result<(), IngestError> {
+        let source = serde_json::to_string(queue).map_err(anyhow::Error::from)?;
+        self.metastore.put(&index, &source)?;
+        Ok(())
+    }
+}
+
+impl DeadLetterQueue {
+    fn push(&mut self, documents: Vec<RejectedDocument>, rejected_at: i64) {
+        for document in documents {
+            self.entries.push(DeadLetter {
+                id: self.next_id,
+                rejected_at,
+                document,
+            });
+            self.next_id += 1;
+        }
+
+        let excess = self
+            .entries
+            .len()
+            .saturating_sub(MAX_DEAD_LETTERS_PER_INDEX);
+        self.entries.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(line: usize) -> RejectedDocument {
+        RejectedDocument {
+            line,
+            source: format!("{{\"line\": {line}"),
+            error: "EOF while parsing an object".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_dead_letter_queue_is_bounded() {
+        let mut queue = DeadLetterQueue::default();
+        queue.push(vec![rejected(1), rejected(2)], 0);
+        assert_eq!(queue.entries.len(), 2);
+        assert_eq!(queue.entries[1].id, 1);
+
+        let documents = (0..MAX_DEAD_LETTERS_PER_INDEX).map(rejected).collect();
+        queue.push(documents, 1);
+        assert_eq!(queue.entries.len(), MAX_DEAD_LETTERS_PER_INDEX);
+        assert_eq!(
+            queue.entries[0].id, 2,
+            "The oldest entries should be dropped"
+        );
+    }
+
+    #[test]
+    fn test_dead_letter_serializes_flat() {
+        let entry = DeadLetter {
+            id: 3,
+            rejected_at: 10,
+            document: rejected(7),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            serde_json::json!({
+                "id": 3,
+                "rejected_at": 10,
+                "line": 7,
+                "source": "{\"line\": 7",
+                "error": "EOF while parsing an object",
+            }),
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use tantivy::collector::Count;
+use tantivy::query::Query;
+use tantivy::schema::DocumentAccess;
+use tantivy::{IndexWriter, Opstamp, Searcher};
+
+use crate::error::IngestError;
+
+#[derive(Debug, Deserialize)]
+/// A request to delete every document matching a query.
+pub struct DeleteByQueryRequest<'a> {
+    #[serde(borrow)]
+    /// The query matching the documents to delete, this is the same DSL used by search.
+    pub query: lnx_query::QueryKind<'a>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+/// The outcome of a delete-by-query operation.
+pub struct DeleteByQueryResult {
+    /// The number of documents which matched the query when the delete was submitted.
+    pub deleted: u64,
+    #[serde(skip)]
+    /// The opstamp of the delete operation.
+    ///
+    /// The documents are removed once a commit including this opstamp completes.
+    pub opstamp: Opstamp,
+}
+
+/// Deletes every document matching the query.
+///
+/// The deleted count is taken from the searcher at the time the delete is submitted,
+/// documents added after the searcher was opened but before the commit will also
+/// be deleted if they match.
+pub fn delete_by_query<D: DocumentAccess>(
+    writer: &IndexWriter<D>,
+    searcher: &Searcher,
+    query: Box<dyn Query>,
+) -> Result<DeleteByQueryResult, IngestError> {
+    let deleted = searcher.search(query.as_ref(), &Count)? as u64;
+    let opstamp = writer.delete_query(query)?;
+    Ok(DeleteByQueryResult { deleted, opstamp })
+}
+
+#[cfg(test)]
+mod tests {
+    use lnx_query::QueryContext;
+    use tantivy::schema::{SchemaBuilder, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_delete_by_query() {
+        let mut schema = SchemaBuilder::new();
+        let tag = schema.add_text_field("tag", STRING);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for value in ["spam", "spam", "ham"] {
+            writer.add_document(doc!(tag => value)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = index.reader().unwrap();
+        let request: DeleteByQueryRequest = serde_json::from_str(
+            r#"{"query": {"term": {"field": "tag", "value": "spam"}}}"#,
+        )
+        .unwrap();
+        let query = request
+            .query
+            .build(&QueryContext::new(index.schema()))
+            .unwrap();
+
+        let result = delete_by_query(&writer, &reader.searcher(), query).unwrap();
+        assert_eq!(result.deleted, 2);
+        writer.commit().unwrap();
+
+        reader.reload().unwrap();
+        assert_eq!(reader.searcher().num_docs(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+use std::io::{self, BufRead, BufReader, Read};
+
+use crate::error::IngestError;
+
+/// The default maximum size (in bytes) a compressed body can decompress to.
+pub const DEFAULT_MAX_DECODED_BYTES: u64 = 4 << 30;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The encoding of a request body, as set by the `Content-Encoding` header.
+pub enum ContentEncoding {
+    /// The body is not compressed.
+    Identity,
+    /// The body is gzip compressed.
+    Gzip,
+    /// The body is zstd compressed.
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Parses the value of a `Content-Encoding` header.
+    ///
+    /// A missing header is treated as `identity`, only a single encoding is supported.
+    pub fn from_header(value: Option<&str>) -> Result<Self, IngestError> {
+        let Some(value) = value else {
+            return Ok(Self::Identity);
+        };
+
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => Ok(Self::Identity),
+            "gzip" | "x-gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            other => Err(IngestError::InvalidRequest(format!(
+                "Unsupported content encoding {other:?}, expected `gzip` or `zstd`"
+            ))),
+        }
+    }
+
+    /// Wraps the body in a streaming decoder for the encoding.
+    ///
+    /// The body is decompressed as it is read, so it can be passed directly to the
+    /// NDJSON or CSV readers without buffering the decompressed body. Reading fails
+    /// once the decompressed body exceeds `max_decoded_bytes`.
+    pub fn decode<'a, R>(
+        self,
+        body: R,
+        max_decoded_bytes: u64,
+    ) -> Result<Box<dyn BufRead + 'a>, IngestError>
+    where
+        R: BufRead + 'a,
+    {
+        let reader: Box<dyn BufRead + 'a> = match self {
+            Self::Identity => return Ok(Box::new(body)),
+            Self::Gzip => {
+                let decoder = flate2::bufread::MultiGzDecoder::new(body);
+                Box::new(BufReader::new(LimitedReader::new(
+                    decoder,
+                    max_decoded_bytes,
+                )))
+            },
+            Self::Zstd => {
+                let decoder = zstd::stream::read::Decoder::with_buffer(body)?;
+                Box::new(BufReader::new(LimitedReader::new(
+                    decoder,
+                    max_decoded_bytes,
+                )))
+            },
+        };
+
+        Ok(reader)
+    }
+}
+
+/// A reader which fails once more than `limit` bytes have been read,
+/// protecting against bodies which decompress to an excessive size.
+struct LimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    limit: u64,
+}
+
+impl<R> LimitedReader<R> {
+    fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            limit,
+        }
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            // Check if there is any more data before failing, the body may end exactly at the limit.
+            let mut probe = [0; 1];
+            return match self.inner.read(&mut probe)? {
+                0 => Ok(0),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Decompressed body exceeds the limit of {} bytes",
+                        self.limit
+                    ),
+                )),
+            };
+        }
+
+        let max = buf
+            .len()
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        let n = self.inner.read(&mut buf[..max])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+    use crate::ndjson::ingest_ndjson;
+
+    const BODY: &str = "{\"title\": \"Hello\"}\n{\"title\": \"World\"}\n";
+
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        let mut encoder =
+            flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn read_all(
+        encoding: ContentEncoding,
+        body: Vec<u8>,
+        limit: u64,
+    ) -> io::Result<String> {
+        let mut reader = encoding.decode(Cursor::new(body), limit).unwrap();
+        let mut decoded = String::new();
+        reader.read_to_string(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn test_parse_content_encoding() {
+        assert_eq!(
+            ContentEncoding::from_header(None).unwrap(),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            ContentEncoding::from_header(Some("GZIP")).unwrap(),
+            ContentEncoding::Gzip
+        );
+        assert_eq!(
+            ContentEncoding::from_header(Some("zstd")).unwrap(),
+            ContentEncoding::Zstd
+        );
+        assert!(ContentEncoding::from_header(Some("br")).is_err());
+    }
+
+    #[test]
+    fn test_decode_gzip_and_zstd() {
+        let limit = DEFAULT_MAX_DECODED_BYTES;
+        assert_eq!(
+            read_all(ContentEncoding::Gzip, gzip(BODY.as_bytes()), limit).unwrap(),
+            BODY
+        );
+
+        let compressed = zstd::encode_all(BODY.as_bytes(), 0).unwrap();
+        assert_eq!(
+            read_all(ContentEncoding::Zstd, compressed, limit).unwrap(),
+            BODY
+        );
+
+        let reader = ContentEncoding::Gzip
+            .decode(Cursor::new(gzip(BODY.as_bytes())), limit)
+            .unwrap();
+        let response = ingest_ndjson(reader, |_| Ok::<_, String>(())).unwrap();
+        assert_eq!(response.succeeded, 2);
+    }
+
+    #[test]
+    fn test_decoded_size_limit() {
+        let len = BODY.len() as u64;
+        assert!(read_all(ContentEncoding::Gzip, gzip(BODY.as_bytes()), len).is_ok());
+        assert!(
+            read_all(ContentEncoding::Gzip, gzip(BODY.as_bytes()), len - 1).is_err()
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::io;
+
+use crate::atomic_batch::BatchOperationError;
+
+#[derive(Debug, thiserror::Error)]
+/// An error which prevents a request payload from being ingested.
+pub enum IngestError {
+    #[error("Unable to read request body: {0}")]
+    /// The request body could not be read.
+    Io(#[from] io::Error),
+    #[error("Unable to read CSV body: {0}")]
+    /// The CSV body could not be read, i.e. the header row is invalid.
+    Csv(#[from] csv::Error),
+    #[cfg(feature = "columnar")]
+    #[error("Unable to read Arrow body: {0}")]
+    /// The Arrow IPC body or record batch could not be decoded.
+    Arrow(#[from] arrow::error::ArrowError),
+    #[cfg(feature = "columnar")]
+    #[error("Unable to read parquet body: {0}")]
+    /// The parquet body could not be decoded.
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[cfg(feature = "kafka")]
+    #[error("Kafka error: {0}")]
+    /// The Kafka consumer failed to poll messages or commit offsets.
+    Kafka(#[from] rdkafka::error::KafkaError),
+    #[cfg(feature = "object-store")]
+    #[error("Object store error: {0}")]
+    /// Files could not be listed or downloaded from the object store.
+    ObjectStore(#[from] object_store::Error),
+    #[error("Batch rejected, {} operations failed validation", .0.len())]
+    /// An atomic batch was rejected as some of its operations are invalid.
+    BatchRejected(Vec<BatchOperationError>),
+    #[error(
+        "Write queue is full, {queued_bytes} of {max_queued_bytes} bytes are queued"
+    )]
+    /// The index's write queue does not have space for the write.
+    QueueFull {
+        queued_bytes: usize,
+        max_queued_bytes: usize,
+    },
+    #[error("Write queue is closed, the index is shutting down")]
+    /// The index's write queue is no longer accepting writes.
+    QueueClosed,
+    #[error("Version conflict, expected version {expected} but the current version is {current}")]
+    /// The version precondition of a write failed.
+    VersionConflict { expected: u64, current: u64 },
+    #[error("Unable to access the metastore: {0}")]
+    /// Data persisted in the metastore could not be read or written.
+    Metastore(#[from] anyhow::Error),
+    #[error("Invalid ingest pipeline: {0}")]
+    /// An ingest pipeline definition could not be compiled.
+    InvalidPipeline(String),
+    #[error("Invalid request: {0}")]
+    /// The request itself is malformed or exceeds a limit.
+    InvalidRequest(String),
+    #[error("Invalid ingestion source: {0}")]
+    /// The source documents are being ingested from is invalid.
+    InvalidSource(String),
+    #[error("Idempotency key {key:?} cannot be used: {reason}")]
+    /// The idempotency key provided with a write request cannot be used.
+    IdempotencyConflict { key: String, reason: String },
+    #[error("Field {field:?} cannot be used as a primary key: {reason}")]
+    /// The field configured as the primary key cannot be used as one.
+    InvalidPrimaryKey { field: String, reason: String },
+    #[error("Field {field:?} cannot be used as an expiry field: {reason}")]
+    /// The field configured to hold document expiry timestamps cannot be used as one.
+    InvalidTtlField { field: String, reason: String },
+    #[error("Column {column:?} has the unsupported type {data_type}")]
+    /// A column within a columnar body cannot be converted into a document value.
+    UnsupportedColumn { column: String, data_type: String },
+    #[error("Invalid query: {0}")]
+    /// The query provided to select documents could not be compiled.
+    Query(#[from] lnx_query::QueryError),
+    #[error("Unable to update the index: {0}")]
+    /// The operation could not be applied to the index.
+    Index(#[from] tantivy::TantivyError),
+}
+
+impl IngestError {
+    /// Creates a new idempotency conflict error.
+    pub(crate) fn idempotency_conflict(
+        key: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self::IdempotencyConflict {
+            key: key.into(),
+            reason: reason.into(),
+        }
+    }
+}
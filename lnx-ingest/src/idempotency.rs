@@ -0,0 +1,227 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::error::IngestError;
+
+/// The default window completed operations are remembered for.
+pub const DEFAULT_IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// The default maximum number of idempotency keys remembered at once.
+pub const DEFAULT_MAX_IDEMPOTENCY_KEYS: u64 = 100_000;
+/// The maximum length of an idempotency key.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+#[derive(Clone)]
+enum KeyState {
+    /// The operation is currently being executed.
+    InProgress { fingerprint: u64 },
+    /// The operation completed with the given response.
+    Completed {
+        fingerprint: u64,
+        response: Arc<serde_json::Value>,
+    },
+}
+
+impl KeyState {
+    fn fingerprint(&self) -> u64 {
+        match self {
+            Self::InProgress { fingerprint } => *fingerprint,
+            Self::Completed { fingerprint, .. } => *fingerprint,
+        }
+    }
+}
+
+/// What to do with a write request carrying an `Idempotency-Key`.
+pub enum IdempotencyOutcome {
+    /// The key has not been seen before, the operation should be executed
+    /// and its response recorded via [IdempotencyGuard::complete].
+    Execute(IdempotencyGuard),
+    /// The operation has already been completed, the recorded response
+    /// should be returned without executing the operation again.
+    Replay(Arc<serde_json::Value>),
+}
+
+#[derive(Clone)]
+/// Records the results of completed write operations by their idempotency key,
+/// so retried requests (i.e. after a client timeout) don't double-index documents.
+///
+/// Keys are scoped to the index and are remembered for the configured window.
+pub struct IdempotencyStore {
+    keys: moka::sync::Cache<String, KeyState>,
+}
+
+impl Default for IdempotencyStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_WINDOW, DEFAULT_MAX_IDEMPOTENCY_KEYS)
+    }
+}
+
+impl IdempotencyStore {
+    /// Creates a new store remembering up to `max_keys` keys for the given window.
+    pub fn new(window: Duration, max_keys: u64) -> Self {
+        let keys = moka::sync::Cache::builder()
+            .thread_pool_enabled(false)
+            .max_capacity(max_keys)
+            .time_to_live(window)
+            .build();
+
+        Self { keys }
+    }
+
+    /// Begins an operation with the given idempotency key and request body.
+    ///
+    /// Returns an error if the operation is already in progress or if
+    /// the key has previously been used with a different request body.
+    pub fn begin(
+        &self,
+        index: &str,
+        key: &str,
+        body: &[u8],
+    ) -> Result<IdempotencyOutcome, IngestError> {
+        if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+            return Err(IngestError::idempotency_conflict(
+                key,
+                format!(
+                    "keys must be between 1 and {MAX_IDEMPOTENCY_KEY_LENGTH} bytes long"
+                ),
+            ));
+        }
+
+        let scoped_key = format!("{index}/{key}");
+        let fingerprint = fingerprint(body);
+        let entry = self
+            .keys
+            .entry(scoped_key.clone())
+            .or_insert_with(|| KeyState::InProgress { fingerprint });
+
+        if entry.is_fresh() {
+            return Ok(IdempotencyOutcome::Execute(IdempotencyGuard {
+                store: self.clone(),
+                key: scoped_key,
+                fingerprint,
+                completed: false,
+            }));
+        }
+
+        let state = entry.into_value();
+        if state.fingerprint() != fingerprint {
+            return Err(IngestError::idempotency_conflict(
+                key,
+                "the key was already used for a different request",
+            ));
+        }
+
+        match state {
+            KeyState::InProgress { .. } => Err(IngestError::idempotency_conflict(
+                key,
+                "a request with the same key is still in progress",
+            )),
+            KeyState::Completed { response, .. } => {
+                Ok(IdempotencyOutcome::Replay(response))
+            },
+        }
+    }
+}
+
+/// A guard for an operation being executed under an idempotency key.
+///
+/// If the guard is dropped without the operation completing the key
+/// is released, allowing the request to be retried.
+pub struct IdempotencyGuard {
+    store: IdempotencyStore,
+    key: String,
+    fingerprint: u64,
+    completed: bool,
+}
+
+impl IdempotencyGuard {
+    /// Records the response of the completed operation.
+    ///
+    /// Any retries of the request within the window will be given this response.
+    pub fn complete(mut self, response: impl Serialize) -> Result<(), IngestError> {
+        let response = serde_json::to_value(response).map_err(|e| {
+            IngestError::idempotency_conflict(
+                &self.key,
+                format!("unable to record response: {e}"),
+            )
+        })?;
+
+        let state = KeyState::Completed {
+            fingerprint: self.fingerprint,
+            response: Arc::new(response),
+        };
+        self.store.keys.insert(self.key.clone(), state);
+        self.completed = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for IdempotencyGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.store.keys.invalidate(&self.key);
+        }
+    }
+}
+
+fn fingerprint(body: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execute(outcome: IdempotencyOutcome) -> IdempotencyGuard {
+        match outcome {
+            IdempotencyOutcome::Execute(guard) => guard,
+            IdempotencyOutcome::Replay(_) => panic!("Expected the operation to execute"),
+        }
+    }
+
+    #[test]
+    fn test_completed_operation_is_replayed() {
+        let store = IdempotencyStore::default();
+        let body = br#"{"title": "Hello"}"#;
+
+        let guard = execute(store.begin("products", "abc", body).unwrap());
+        guard.complete(serde_json::json!({"succeeded": 1})).unwrap();
+
+        let outcome = store.begin("products", "abc", body).unwrap();
+        let IdempotencyOutcome::Replay(response) = outcome else {
+            panic!("Expected the response to be replayed");
+        };
+        assert_eq!(*response, serde_json::json!({"succeeded": 1}));
+
+        // Keys are scoped to the index.
+        execute(store.begin("other", "abc", body).unwrap());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let store = IdempotencyStore::default();
+
+        let guard = execute(store.begin("products", "abc", b"a").unwrap());
+        assert!(store.begin("products", "abc", b"a").is_err());
+        guard.complete(()).unwrap();
+
+        assert!(store.begin("products", "abc", b"b").is_err());
+        assert!(store.begin("products", "", b"a").is_err());
+    }
+
+    #[test]
+    fn test_dropped_guard_releases_key() {
+        let store = IdempotencyStore::default();
+
+        let guard = execute(store.begin("products", "abc", b"a").unwrap());
+        drop(guard);
+
+        execute(store.begin("products", "abc", b"a").unwrap());
+    }
+}
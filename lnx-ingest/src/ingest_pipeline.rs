@@ -0,0 +1,821 @@
+use std::borrow::Cow;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use lnx_document::{DynamicDocument, KeyValues, UserDisplayType, Value};
+use lnx_metastore::Metastore;
+use lnx_transforms::DateTimeParser;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::IngestError;
+
+const PIPELINES_DATABASE: &str = "lnx_ingest_pipelines";
+
+/// The grok patterns which can be referenced via `%{NAME}` or `%{NAME:field}`.
+const GROK_PATTERNS: &[(&str, &str)] = &[
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("INT", r"[+-]?\d+"),
+    ("NUMBER", r"[+-]?\d+(?:\.\d+)?"),
+    ("BASE16NUM", r"(?:0[xX])?[0-9A-Fa-f]+"),
+    (
+        "UUID",
+        r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}",
+    ),
+    ("IPV4", r"(?:\d{1,3}\.){3}\d{1,3}"),
+    ("IPV6", r"[0-9A-Fa-f:]*:[0-9A-Fa-f:.]+"),
+    (
+        "IP",
+        r"(?:(?:\d{1,3}\.){3}\d{1,3}|[0-9A-Fa-f:]*:[0-9A-Fa-f:.]+)",
+    ),
+    (
+        "HOSTNAME",
+        r"\b[0-9A-Za-z][0-9A-Za-z-]{0,62}(?:\.[0-9A-Za-z][0-9A-Za-z-]{0,62})*\.?\b",
+    ),
+    ("USER", r"[a-zA-Z0-9._-]+"),
+    ("PATH", r"(?:/[^/\s]*)+"),
+    ("URIPATHPARAM", r"/[^\s?#]*(?:\?[^\s#]*)?"),
+    ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*""#),
+    (
+        "LOGLEVEL",
+        r"(?i:trace|debug|info|notice|warn(?:ing)?|err(?:or)?|crit(?:ical)?|fatal|severe|emerg(?:ency)?)",
+    ),
+    (
+        "TIMESTAMP_ISO8601",
+        r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?",
+    ),
+    ("HTTPDATE", r"\d{2}/\w{3}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4}"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// A single processor within an ingest pipeline.
+///
+/// Processors operate on top-level fields of the document, if the field is missing the
+/// processor fails unless `ignore_missing` is set.
+pub enum ProcessorConfig {
+    /// Renames a field.
+    Rename {
+        field: String,
+        target: String,
+        #[serde(default)]
+        ignore_missing: bool,
+    },
+    /// Removes a set of fields.
+    Remove {
+        fields: Vec<String>,
+        #[serde(default)]
+        ignore_missing: bool,
+    },
+    /// Lowercases a string field, or each string within an array.
+    Lowercase {
+        field: String,
+        #[serde(default)]
+        ignore_missing: bool,
+    },
+    /// Extracts fields from a string using grok patterns, i.e. `%{IP:client} %{WORD:method}`.
+    ///
+    /// The first matching pattern is used, captures can be converted with a
+    /// type suffix, i.e. `%{NUMBER:bytes:int}` or `%{NUMBER:duration:float}`.
+    Grok {
+        field: String,
+        patterns: Vec<String>,
+        #[serde(default)]
+        ignore_missing: bool,
+    },
+    /// Extracts fields from a string using the named capture groups of a regex.
+    RegexExtract {
+        field: String,
+        pattern: String,
+        #[serde(default)]
+        ignore_missing: bool,
+    },
+    /// Parses a string or timestamp into a `datetime`.
+    ///
+    /// Formats can be `rfc3339`, `rfc2822`, `unix_seconds`, `unix_millis`,
+    /// `unix_micros` or a custom format description, i.e. `[year]-[month]-[day]`.
+    DateParse {
+        field: String,
+        #[serde(default)]
+        target: Option<String>,
+        formats: Vec<String>,
+        #[serde(default)]
+        ignore_missing: bool,
+    },
+    /// Looks up the location of an ip address, adding it as an object to the `target` field.
+    GeoipLookup {
+        field: String,
+        #[serde(default = "default_geoip_target")]
+        target: String,
+        #[serde(default)]
+        ignore_missing: bool,
+    },
+}
+
+fn default_geoip_target() -> String {
+    "geoip".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The definition of an ingest pipeline.
+pub struct PipelineConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// A description of what the pipeline does.
+    pub description: Option<String>,
+    /// The processors ran on each document, in order.
+    pub processors: Vec<ProcessorConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The location of an ip address.
+pub struct GeoIpInfo {
+    pub country_iso_code: Option<String>,
+    pub country_name: Option<String>,
+    pub city_name: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// A database used to look up the location of ip addresses.
+pub trait GeoIpLookup: Send + Sync + 'static {
+    /// Looks up the location of the given ip address.
+    fn lookup(&self, ip: IpAddr) -> Option<GeoIpInfo>;
+}
+
+#[cfg(feature = "geoip")]
+impl<S: AsRef<[u8]> + Send + Sync + 'static> GeoIpLookup for maxminddb::Reader<S> {
+    fn lookup(&self, ip: IpAddr) -> Option<GeoIpInfo> {
+        let city: maxminddb::geoip2::City = maxminddb::Reader::lookup(self, ip).ok()?;
+        let english_name = |names: Option<std::collections::BTreeMap<&str, &str>>| {
+            names.and_then(|names| names.get("en").map(|name| name.to_string()))
+        };
+
+        let country = city.country;
+        let location = city.location;
+        Some(GeoIpInfo {
+            country_iso_code: country
+                .as_ref()
+                .and_then(|c| c.iso_code.map(String::from)),
+            country_name: country.and_then(|c| english_name(c.names)),
+            city_name: city.city.and_then(|c| english_name(c.names)),
+            latitude: location.as_ref().and_then(|l| l.latitude),
+            longitude: location.and_then(|l| l.longitude),
+        })
+    }
+}
+
+/// A compiled ingest pipeline, ran on documents before they are passed to the writer.
+pub struct IngestPipeline {
+    processors: Vec<Processor>,
+}
+
+enum Processor {
+    Rename {
+        field: String,
+        target: String,
+        ignore_missing: bool,
+    },
+    Remove {
+        fields: Vec<String>,
+        ignore_missing: bool,
+    },
+    Lowercase {
+        field: String,
+        ignore_missing: bool,
+    },
+    Extract {
+        field: String,
+        patterns: Vec<Extractor>,
+        ignore_missing: bool,
+    },
+    DateParse {
+        field: String,
+        target: String,
+        parser: DateTimeParser,
+        ignore_missing: bool,
+    },
+    GeoipLookup {
+        field: String,
+        target: String,
+        database: Arc<dyn GeoIpLookup>,
+        ignore_missing: bool,
+    },
+}
+
+#[derive(Debug, Copy, Clone)]
+enum CaptureType {
+    Str,
+    Int,
+    Float,
+}
+
+struct Extractor {
+    regex: Regex,
+    /// The target field and type of each capture group, by group index.
+    captures: Vec<(usize, String, CaptureType)>,
+}
+
+impl IngestPipeline {
+    /// Compiles a pipeline from its definition.
+    ///
+    /// A `geoip` database must be provided if the pipeline contains a `geoip_lookup` processor.
+    pub fn compile(
+        config: &PipelineConfig,
+        geoip: Option<Arc<dyn GeoIpLookup>>,
+    ) -> Result<Self, IngestError> {
+        let processors = config
+            .processors
+            .iter()
+            .enumerate()
+            .map(|(idx, processor)| {
+                compile_processor(processor, geoip.as_ref()).map_err(|e| {
+                    IngestError::InvalidPipeline(format!("Processor {idx}: {e}"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { processors })
+    }
+
+    /// Runs every processor on the document, in order.
+    pub fn run<'a>(
+        &self,
+        document: DynamicDocument<'a>,
+    ) -> Result<DynamicDocument<'a>, String> {
+        let mut object = document.0;
+        for processor in self.processors.iter() {
+            processor.apply(&mut object)?;
+        }
+        Ok(DynamicDocument(object))
+    }
+}
+
+fn compile_processor(
+    config: &ProcessorConfig,
+    geoip: Option<&Arc<dyn GeoIpLookup>>,
+) -> Result<Processor, String> {
+    let processor = match config.clone() {
+        ProcessorConfig::Rename {
+            field,
+            target,
+            ignore_missing,
+        } => Processor::Rename {
+            field,
+            target,
+            ignore_missing,
+        },
+        ProcessorConfig::Remove {
+            fields,
+            ignore_missing,
+        } => Processor::Remove {
+            fields,
+            ignore_missing,
+        },
+        ProcessorConfig::Lowercase {
+            field,
+            ignore_missing,
+        } => Processor::Lowercase {
+            field,
+            ignore_missing,
+        },
+        ProcessorConfig::Grok {
+            field,
+            patterns,
+            ignore_missing,
+        } => {
+            if patterns.is_empty() {
+                return Err("grok processors require at least one pattern".to_string());
+            }
+
+            let patterns = patterns
+                .iter()
+                .map(|pattern| compile_grok(pattern))
+                .collect::<Result<Vec<_>, _>>()?;
+            Processor::Extract {
+                field,
+                patterns,
+                ignore_missing,
+            }
+        },
+        ProcessorConfig::RegexExtract {
+            field,
+            pattern,
+            ignore_missing,
+        } => {
+            let regex = Regex::new(&pattern).map_err(|e| e.to_string())?;
+            let captures = regex
+                .capture_names()
+                .enumerate()
+                .filter_map(|(idx, name)| {
+                    Some((idx, name?.to_string(), CaptureType::Str))
+                })
+                .collect::<Vec<_>>();
+            if captures.is_empty() {
+                return Err("the pattern has no named capture groups".to_string());
+            }
+
+            Processor::Extract {
+                field,
+                patterns: vec![Extractor { regex, captures }],
+                ignore_missing,
+            }
+        },
+        ProcessorConfig::DateParse {
+            field,
+            target,
+            formats,
+            ignore_missing,
+        } => {
+            if formats.is_empty() {
+                return Err(
+                    "date_parse processors require at least one format".to_string()
+                );
+            }
+
+            let parser =
+                DateTimeParser::from_formats(&formats).map_err(|e| e.to_string())?;
+
+            Processor::DateParse {
+                target: target.unwrap_or_else(|| field.clone()),
+                field,
+                parser,
+                ignore_missing,
+            }
+        },
+        ProcessorConfig::GeoipLookup {
+            field,
+            target,
+            ignore_missing,
+        } => {
+            let database = geoip
+                .cloned()
+                .ok_or_else(|| "no geoip database is configured".to_string())?;
+            Processor::GeoipLookup {
+                field,
+                target,
+                database,
+                ignore_missing,
+            }
+        },
+    };
+
+    Ok(processor)
+}
+
+/// Compiles a grok pattern into a regex.
+fn compile_grok(pattern: &str) -> Result<Extractor, String> {
+    let mut regex = String::from("^");
+    let mut fields = Vec::new();
+    let mut rest = pattern;
+
+    while let Some(start) = rest.find("%{") {
+        regex.push_str(&rest[..start]);
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed grok reference in {pattern:?}"))?;
+        let reference = &rest[start + 2..start + end];
+        rest = &rest[start + end + 1..];
+
+        let mut parts = reference.splitn(3, ':');
+        let name = parts.next().unwrap_or_default();
+        let (_, sub_pattern) = GROK_PATTERNS
+            .iter()
+            .find(|(pattern_name, _)| *pattern_name == name)
+            .ok_or_else(|| format!("Unknown grok pattern {name:?}"))?;
+
+        match parts.next() {
+            Some(field) => {
+                let kind = match parts.next() {
+                    None => CaptureType::Str,
+                    Some("int") => CaptureType::Int,
+                    Some("float") => CaptureType::Float,
+                    Some(other) => {
+                        return Err(format!("Unknown grok capture type {other:?}"))
+                    },
+                };
+                fields.push((field.to_string(), kind));
+                regex.push('(');
+                regex.push_str(sub_pattern);
+                regex.push(')');
+            },
+            None => {
+                regex.push_str("(?:");
+                regex.push_str(sub_pattern);
+                regex.push(')');
+            },
+        }
+    }
+    regex.push_str(rest);
+    regex.push('$');
+
+    let regex = Regex::new(&regex).map_err(|e| e.to_string())?;
+    let captures = fields
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (field, kind))| (idx + 1, field, kind))
+        .collect();
+
+    Ok(Extractor { regex, captures })
+}
+
+impl Processor {
+    fn apply<'a>(&self, object: &mut KeyValues<'a>) -> Result<(), String> {
+        match self {
+            Processor::Rename {
+                field,
+                target,
+                ignore_missing,
+            } => {
+                let Some(pos) = find(object, field, *ignore_missing)? else {
+                    return Ok(());
+                };
+                let (_, value) = object.remove(pos);
+                set(object, target, value);
+            },
+            Processor::Remove {
+                fields,
+                ignore_missing,
+            } => {
+                for field in fields {
+                    if find(object, field, *ignore_missing)?.is_some() {
+                        object.retain(|(key, _)| key != field);
+                    }
+                }
+            },
+            Processor::Lowercase {
+                field,
+                ignore_missing,
+            } => {
+                let Some(pos) = find(object, field, *ignore_missing)? else {
+                    return Ok(());
+                };
+                let value = &mut object[pos].1;
+                lowercase(value)
+                    .map_err(|e| format!("Cannot lowercase {field:?}: {e}"))?;
+            },
+            Processor::Extract {
+                field,
+                patterns,
+                ignore_missing,
+            } => {
+                let Some(pos) = find(object, field, *ignore_missing)? else {
+                    return Ok(());
+                };
+                let Value::Str(text) = &object[pos].1 else {
+                    return Err(format!(
+                        "Cannot extract from {field:?} as it is not a string"
+                    ));
+                };
+
+                let extracted = extract(patterns, text).ok_or_else(|| {
+                    format!("Field {field:?} does not match any pattern")
+                })?;
+                for (target, value) in extracted {
+                    set(object, &target, value);
+                }
+            },
+            Processor::DateParse {
+                field,
+                target,
+                parser,
+                ignore_missing,
+            } => {
+                let Some(pos) = find(object, field, *ignore_missing)? else {
+                    return Ok(());
+                };
+                let value = match object[pos].1.clone() {
+                    Value::U64(ts) => Value::I64(ts as i64),
+                    other => other,
+                };
+                let dt = parser
+                    .try_parse_typed(value)
+                    .map_err(|e| format!("Cannot parse {field:?}: {e}"))?;
+                set(object, target, Value::DateTime(dt));
+            },
+            Processor::GeoipLookup {
+                field,
+                target,
+                database,
+                ignore_missing,
+            } => {
+                let Some(pos) = find(object, field, *ignore_missing)? else {
+                    return Ok(());
+                };
+                let ip = match &object[pos].1 {
+                    Value::Str(ip) => ip
+                        .parse::<IpAddr>()
+                        .map_err(|_| format!("Field {field:?} is not a valid ip"))?,
+                    Value::IpAddr(ip) => match ip.to_ipv4_mapped() {
+                        Some(ip) => IpAddr::V4(ip),
+                        None => IpAddr::V6(*ip),
+                    },
+                    other => {
+                        return Err(format!(
+                            "Cannot look up {field:?} as it is of type {}",
+                            other.type_name()
+                        ))
+                    },
+                };
+
+                if let Some(info) = database.lookup(ip) {
+                    set(object, target, geoip_value(info));
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the position of a field, returning an error if the field is missing
+/// and missing fields are not ignored.
+fn find(
+    object: &KeyValues,
+    field: &str,
+    ignore_missing: bool,
+) -> Result<Option<usize>, String> {
+    match object.iter().position(|(key, _)| key == field) {
+        Some(pos) => Ok(Some(pos)),
+        None if ignore_missing => Ok(None),
+        None => Err(format!("Document is missing the field {field:?}")),
+    }
+}
+
+/// Sets a field, replacing any existing values.
+fn set<'a>(object: &mut KeyValues<'a>, field: &str, value: Value<'a>) {
+    object.retain(|(key, _)| key != field);
+    object.push((Cow::Owned(field.to_string()), value));
+}
+
+fn lowercase(value: &mut Value) -> Result<(), String> {
+    match value {
+        Value::Str(text) => {
+            if text.chars().any(char::is_uppercase) {
+                *text = Cow::Owned(text.to_lowercase());
+            }
+            Ok(())
+        },
+        Value::Array(values) => values.iter_mut().try_for_each(lowercase),
+        Value::Null => Ok(()),
+        other => Err(format!("expected a string not {}", other.type_name())),
+    }
+}
+
+fn extract<'a>(patterns: &[Extractor], text: &str) -> Option<Vec<(String, Value<'a>)>> {
+    for pattern in patterns {
+        let Some(captures) = pattern.regex.captures(text) else {
+            continue;
+        };
+
+        let mut extracted = Vec::with_capacity(pattern.captures.len());
+        for (idx, field, kind) in pattern.captures.iter() {
+            let Some(capture) = captures.get(*idx) else {
+                continue;
+            };
+            let capture = capture.as_str();
+
+            let value = match kind {
+                CaptureType::Str => Value::Str(Cow::Owned(capture.to_string())),
+                CaptureType::Int => match capture.parse::<i64>() {
+                    Ok(v) if v >= 0 => Value::U64(v as u64),
+                    Ok(v) => Value::I64(v),
+                    Err(_) => Value::Str(Cow::Owned(capture.to_string())),
+                },
+                CaptureType::Float => capture
+                    .parse::<f64>()
+                    .map(Value::F64)
+                    .unwrap_or_else(|_| Value::Str(Cow::Owned(capture.to_string()))),
+            };
+            extracted.push((field.clone(), value));
+        }
+
+        return Some(extracted);
+    }
+
+    None
+}
+
+fn geoip_value<'a>(info: GeoIpInfo) -> Value<'a> {
+    let mut object = KeyValues::new();
+    let mut push = |key: &'static str, value: Option<Value<'a>>| {
+        if let Some(value) = value {
+            object.push((Cow::Borrowed(key), value));
+        }
+    };
+
+    push("country_iso_code", info.country_iso_code.map(Value::from));
+    push("country_name", info.country_name.map(Value::from));
+    push("city_name", info.city_name.map(Value::from));
+    push("latitude", info.latitude.map(Value::F64));
+    push("longitude", info.longitude.map(Value::F64));
+
+    Value::Object(object)
+}
+
+/// Persists named ingest pipeline definitions.
+///
+/// Pipelines are attached to an index as its default pipeline or
+/// selected per request via the `pipeline` parameter.
+pub struct PipelineStore {
+    metastore: Metastore,
+}
+
+impl PipelineStore {
+    /// Opens the pipeline store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(PIPELINES_DATABASE)?;
+        Ok(Self { metastore })
+    }
+
+    /// Saves a named pipeline, replacing any existing pipeline with the same name.
+    ///
+    /// The pipeline is compiled first so invalid pipelines are rejected when they are defined.
+    pub fn put(
+        &self,
+        name: &str,
+        config: &PipelineConfig,
+        geoip: Option<Arc<dyn GeoIpLookup>>,
+    ) -> Result<(), IngestError> {
+        IngestPipeline::compile(config, geoip)?;
+
+        let source = serde_json::to_string(config)
+            .map_err(|e| IngestError::InvalidPipeline(e.to_string()))?;
+        self.metastore.put(&name, &source)?;
+        Ok(())
+    }
+
+    /// Gets a named pipeline if it exists.
+    pub fn get(&self, name: &str) -> Result<Option<PipelineConfig>, IngestError> {
+        let Some(source) = self.metastore.get::<_, String>(name)? else {
+            return Ok(None);
+        };
+
+        let config = serde_json::from_str(&source)
+            .map_err(|e| IngestError::InvalidPipeline(e.to_string()))?;
+        Ok(Some(config))
+    }
+
+    /// Removes a named pipeline.
+    pub fn remove(&self, name: &str) -> Result<(), IngestError> {
+        self.metastore.del(&name)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(config: &str, document: &str) -> Result<String, String> {
+        let config: PipelineConfig = serde_json::from_str(config).unwrap();
+        let pipeline = IngestPipeline::compile(&config, None).unwrap();
+        let document: DynamicDocument = serde_json::from_str(document).unwrap();
+        pipeline
+            .run(document)
+            .map(|document| format!("{:?}", document.0))
+    }
+
+    fn doc(document: &str) -> String {
+        let document: DynamicDocument = serde_json::from_str(document).unwrap();
+        format!("{:?}", document.0)
+    }
+
+    #[test]
+    fn test_rename_remove_lowercase() {
+        let config = r#"{"processors": [
+            {"type": "rename", "field": "msg", "target": "message"},
+            {"type": "remove", "fields": ["internal", "missing"], "ignore_missing": true},
+            {"type": "lowercase", "field": "tags"}
+        ]}"#;
+
+        let processed = run(
+            config,
+            r#"{"msg": "Hello", "internal": true, "tags": ["A", "b"]}"#,
+        );
+        assert_eq!(
+            processed.unwrap(),
+            doc(r#"{"tags": ["a", "b"], "message": "Hello"}"#)
+        );
+
+        assert!(run(config, r#"{"tags": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_grok() {
+        let config = r#"{"processors": [{
+            "type": "grok",
+            "field": "line",
+            "patterns": ["%{IP:client} %{WORD:method} %{URIPATHPARAM:path} %{NUMBER:bytes:int}"]
+        }]}"#;
+
+        let processed = run(config, r#"{"line": "55.3.244.1 GET /index.html 15824"}"#);
+        assert_eq!(
+            processed.unwrap(),
+            doc(
+                r#"{"line": "55.3.244.1 GET /index.html 15824", "client": "55.3.244.1", "method": "GET", "path": "/index.html", "bytes": 15824}"#
+            ),
+        );
+
+        assert!(run(config, r#"{"line": "not a log line"}"#).is_err());
+    }
+
+    #[test]
+    fn test_regex_extract() {
+        let config = r#"{"processors": [{
+            "type": "regex_extract",
+            "field": "email",
+            "pattern": "^(?P<user>[^@]+)@(?P<domain>.+)$"
+        }]}"#;
+
+        let processed = run(config, r#"{"email": "bob@example.com"}"#);
+        assert_eq!(
+            processed.unwrap(),
+            doc(
+                r#"{"email": "bob@example.com", "user": "bob", "domain": "example.com"}"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_date_parse() {
+        let config = r#"{"processors": [{
+            "type": "date_parse",
+            "field": "ts",
+            "target": "timestamp",
+            "formats": ["rfc3339", "unix_seconds", "[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory]"]
+        }]}"#;
+
+        for ts in [
+            r#""2023-01-01T00:00:00Z""#,
+            "1672531200",
+            r#""2023-01-01 00:00:00 +00""#,
+        ] {
+            let processed = run(config, &format!(r#"{{"ts": {ts}}}"#)).unwrap();
+            assert!(processed.contains("DateTime"), "{ts} should parse");
+        }
+
+        assert!(run(config, r#"{"ts": "yesterday"}"#).is_err());
+    }
+
+    #[test]
+    fn test_invalid_pipelines() {
+        let invalid = [
+            r#"{"processors": [{"type": "grok", "field": "a", "patterns": ["%{NOPE:a}"]}]}"#,
+            r#"{"processors": [{"type": "grok", "field": "a", "patterns": []}]}"#,
+            r#"{"processors": [{"type": "regex_extract", "field": "a", "pattern": "(a)"}]}"#,
+            r#"{"processors": [{"type": "date_parse", "field": "a", "formats": ["[nope]"]}]}"#,
+            r#"{"processors": [{"type": "geoip_lookup", "field": "a"}]}"#,
+        ];
+
+        for config in invalid {
+            let config: PipelineConfig = serde_json::from_str(config).unwrap();
+            assert!(matches!(
+                IngestPipeline::compile(&config, None),
+                Err(IngestError::InvalidPipeline(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_geoip_lookup() {
+        struct StaticLookup;
+
+        impl GeoIpLookup for StaticLookup {
+            fn lookup(&self, _ip: IpAddr) -> Option<GeoIpInfo> {
+                Some(GeoIpInfo {
+                    country_iso_code: Some("GB".to_string()),
+                    country_name: None,
+                    city_name: None,
+                    latitude: Some(51.5),
+                    longitude: Some(-0.12),
+                })
+            }
+        }
+
+        let config: PipelineConfig = serde_json::from_str(
+            r#"{"processors": [{"type": "geoip_lookup", "field": "ip"}]}"#,
+        )
+        .unwrap();
+        let pipeline =
+            IngestPipeline::compile(&config, Some(Arc::new(StaticLookup))).unwrap();
+
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"ip": "81.2.69.142"}"#).unwrap();
+        let processed = pipeline.run(document).unwrap();
+        assert_eq!(
+            format!("{:?}", processed.0),
+            doc(
+                r#"{"ip": "81.2.69.142", "geoip": {"country_iso_code": "GB", "latitude": 51.5, "longitude": -0.12}}"#
+            ),
+        );
+
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"ip": "nope"}"#).unwrap();
+        assert!(pipeline.run(document).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::Duration;
+
+use lnx_document::DynamicDocument;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::message::OwnedMessage;
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use serde::Deserialize;
+
+use crate::bulk::BulkResponse;
+use crate::error::IngestError;
+
+#[derive(Debug, Clone, Deserialize)]
+/// The Kafka topic an index tails documents from.
+pub struct KafkaSourceConfig {
+    /// The comma separated list of bootstrap brokers, i.e. `localhost:9092`.
+    pub brokers: String,
+    /// The topic to consume documents from.
+    pub topic: String,
+    /// The consumer group the index consumes as.
+    pub group_id: String,
+    #[serde(default = "default_max_batch_size")]
+    /// The maximum number of messages ingested before the index is committed.
+    pub max_batch_size: usize,
+    #[serde(default = "default_poll_timeout_ms")]
+    /// How long to wait for new messages before ending the current batch.
+    pub poll_timeout_ms: u64,
+    #[serde(default)]
+    /// Any additional librdkafka properties, i.e. `security.protocol`.
+    pub properties: BTreeMap<String, String>,
+}
+
+fn default_max_batch_size() -> usize {
+    10_000
+}
+
+fn default_poll_timeout_ms() -> u64 {
+    500
+}
+
+/// A batch of messages polled from the topic.
+pub struct KafkaBatch {
+    messages: Vec<OwnedMessage>,
+}
+
+impl KafkaBatch {
+    #[inline]
+    /// The number of messages within the batch.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    #[inline]
+    /// Returns if the batch contains no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Parses each message payload as a JSON document.
+    pub fn documents(
+        &self,
+    ) -> impl Iterator<Item = Result<DynamicDocument<'_>, String>> + '_ {
+        self.messages
+            .iter()
+            .map(|message| parse_payload(message.payload()))
+    }
+
+    /// The offsets to commit once the batch has been ingested.
+    fn next_offsets(&self) -> BTreeMap<(&str, i32), i64> {
+        next_offsets(
+            self.messages
+                .iter()
+                .map(|msg| (msg.topic(), msg.partition(), msg.offset())),
+        )
+    }
+}
+
+/// Consumes documents from a Kafka topic on behalf of an index.
+///
+/// Offsets are never committed automatically, they are only committed via
+/// [KafkaSource::commit] once the batch has been committed to the index so
+/// documents are never lost if the engine stops mid-batch.
+pub struct KafkaSource {
+    config: KafkaSourceConfig,
+    consumer: BaseConsumer,
+}
+
+impl KafkaSource {
+    /// Creates a new consumer and subscribes to the configured topic.
+    pub fn connect(config: KafkaSourceConfig) -> Result<Self, IngestError> {
+        let mut client = ClientConfig::new();
+        for (key, value) in config.properties.iter() {
+            client.set(key, value);
+        }
+
+        let consumer: BaseConsumer = client
+            .set("bootstrap.servers", &config.brokers)
+            .set("group.id", &config.group_id)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .create()?;
+        consumer.subscribe(&[&config.topic])?;
+
+        Ok(Self { config, consumer })
+    }
+
+    #[inline]
+    /// The config of the source.
+    pub fn config(&self) -> &KafkaSourceConfig {
+        &self.config
+    }
+
+    /// Polls the next batch of messages from the topic.
+    ///
+    /// The batch ends once `max_batch_size` messages have been received
+    /// or no new messages arrive within the poll timeout.
+    pub fn poll_batch(&self) -> Result<KafkaBatch, IngestError> {
+        let timeout = Duration::from_millis(self.config.poll_timeout_ms);
+        let mut messages = Vec::new();
+
+        while messages.len() < self.config.max_batch_size {
+            match self.consumer.poll(timeout) {
+                Some(message) => messages.push(message?.detach()),
+                None => break,
+            }
+        }
+
+        Ok(KafkaBatch { messages })
+    }
+
+    /// Commits the offsets of every message within the batch.
+    pub fn commit(&self, batch: &KafkaBatch) -> Result<(), IngestError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let mut offsets = TopicPartitionList::new();
+        for ((topic, partition), offset) in batch.next_offsets() {
+            offsets.add_partition_offset(topic, partition, Offset::Offset(offset))?;
+        }
+        self.consumer.commit(&offsets, CommitMode::Sync)?;
+
+        Ok(())
+    }
+}
+
+/// Ingests the next batch of messages from the topic, passing each document to the writer.
+///
+/// Once every document has been passed to the writer, `commit_index` is called to commit
+/// the index and only if that succeeds are the consumer offsets committed.
+/// Messages which cannot be parsed, or which are rejected by the writer, are recorded as
+/// failures and are not retried.
+pub fn ingest_kafka_batch<E>(
+    source: &KafkaSource,
+    mut writer: impl FnMut(DynamicDocument<'_>) -> Result<(), E>,
+    commit_index: impl FnOnce() -> Result<(), IngestError>,
+) -> Result<BulkResponse, IngestError>
+where
+    E: Display,
+{
+    let batch = source.poll_batch()?;
+    let mut response = BulkResponse::default();
+    if batch.is_empty() {
+        return Ok(response);
+    }
+
+    for (idx, document) in batch.documents().enumerate() {
+        match document.map(&mut writer) {
+            Ok(Ok(())) => response.record_success(idx + 1),
+            Ok(Err(e)) => response.record_failure(idx + 1, e),
+            Err(e) => response.record_failure(idx + 1, e),
+        }
+    }
+
+    commit_index()?;
+    source.commit(&batch)?;
+
+    Ok(response)
+}
+
+/// Parses a message payload as a JSON document.
+fn parse_payload(payload: Option<&[u8]>) -> Result<DynamicDocument<'_>, String> {
+    let payload = payload.ok_or_else(|| "Message has no payload".to_string())?;
+    serde_json::from_slice(payload).map_err(|e| format!("Invalid JSON document: {e}"))
+}
+
+/// Gets the next offset to consume from each partition.
+fn next_offsets<'a>(
+    messages: impl Iterator<Item = (&'a str, i32, i64)>,
+) -> BTreeMap<(&'a str, i32), i64> {
+    let mut offsets = BTreeMap::new();
+    for (topic, partition, offset) in messages {
+        let next = offsets.entry((topic, partition)).or_insert(offset + 1);
+        *next = (*next).max(offset + 1);
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_offsets() {
+        let messages = [
+            ("docs", 0, 4),
+            ("docs", 1, 10),
+            ("docs", 0, 5),
+            ("docs", 0, 3),
+            ("other", 0, 1),
+        ];
+
+        let offsets = next_offsets(messages.into_iter());
+        let expected = BTreeMap::from_iter([
+            (("docs", 0), 6),
+            (("docs", 1), 11),
+            (("other", 0), 2),
+        ]);
+        assert_eq!(offsets, expected);
+    }
+
+    #[test]
+    fn test_parse_payload() {
+        let document = parse_payload(Some(br#"{"title": "Hello"}"#)).unwrap();
+        assert_eq!(document.len(), 1);
+
+        assert!(parse_payload(None).is_err());
+        assert!(parse_payload(Some(b"not json")).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+mod atomic_batch;
+mod bulk;
+#[cfg(feature = "columnar")]
+mod columnar;
+mod csv;
+mod dead_letter;
+mod delete_by_query;
+mod encoding;
+mod error;
+mod idempotency;
+mod ingest_pipeline;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod mget;
+mod ndjson;
+#[cfg(feature = "object-store")]
+mod object_source;
+mod patch;
+mod prepare;
+mod refresh;
+mod reindex;
+mod tasks;
+mod ttl;
+mod update_by_query;
+mod upsert;
+mod versions;
+mod write_queue;
+
+pub use self::atomic_batch::{
+    apply_atomic_batch,
+    prepare_atomic_batch,
+    AtomicBatchRequest,
+    BatchOperation,
+    BatchOperationError,
+};
+pub use self::bulk::{BulkItemResult, BulkResponse, WriteOutcome};
+#[cfg(feature = "columnar")]
+pub use self::columnar::{ingest_arrow_ipc, ingest_parquet, ingest_record_batches};
+pub use self::csv::{ingest_csv, CsvMapping};
+pub use self::dead_letter::{
+    DeadLetter,
+    DeadLetterPage,
+    DeadLetterStore,
+    RejectedDocument,
+    MAX_DEAD_LETTERS_PER_INDEX,
+};
+pub use self::delete_by_query::{
+    delete_by_query,
+    DeleteByQueryRequest,
+    DeleteByQueryResult,
+};
+pub use self::encoding::{ContentEncoding, DEFAULT_MAX_DECODED_BYTES};
+pub use self::error::IngestError;
+pub use self::idempotency::{
+    IdempotencyGuard,
+    IdempotencyOutcome,
+    IdempotencyStore,
+    DEFAULT_IDEMPOTENCY_WINDOW,
+    DEFAULT_MAX_IDEMPOTENCY_KEYS,
+    MAX_IDEMPOTENCY_KEY_LENGTH,
+};
+pub use self::ingest_pipeline::{
+    GeoIpInfo,
+    GeoIpLookup,
+    IngestPipeline,
+    PipelineConfig,
+    PipelineStore,
+    ProcessorConfig,
+};
+#[cfg(feature = "kafka")]
+pub use self::kafka::{ingest_kafka_batch, KafkaBatch, KafkaSource, KafkaSourceConfig};
+pub use self::mget::{
+    multi_get,
+    MultiGetItem,
+    MultiGetRequest,
+    MultiGetResponse,
+    MAX_MULTI_GET_IDS,
+};
+pub use self::ndjson::{
+    ingest_ndjson,
+    NdjsonLine,
+    NdjsonReader,
+    DEFAULT_MAX_LINE_LENGTH,
+};
+#[cfg(feature = "object-store")]
+pub use self::object_source::{
+    ingest_object_store,
+    FileFormat,
+    ObjectFileResult,
+    ObjectIngestRequest,
+    ObjectIngestResult,
+};
+pub use self::patch::{merge_patch, ArrayMergeMode};
+pub use self::prepare::DocumentPreparer;
+pub use self::refresh::{CommitPolicy, CommitTracker, Refresh};
+pub use self::reindex::{
+    reindex,
+    ReindexDest,
+    ReindexRequest,
+    ReindexResult,
+    ReindexSource,
+};
+pub use self::tasks::{
+    TaskHandle,
+    TaskId,
+    TaskInfo,
+    TaskRegistry,
+    TaskStatus,
+    MAX_FINISHED_TASKS,
+};
+pub use self::ttl::{spawn_expiry_reaper, ExpiryReaper, ReaperHandle, TtlPolicy};
+pub use self::update_by_query::{
+    apply_updates,
+    update_by_query,
+    FieldUpdate,
+    UpdateByQueryRequest,
+    UpdateByQueryResult,
+    DEFAULT_UPDATE_BATCH_SIZE,
+};
+pub use self::upsert::{upsert_operations, PrimaryKey};
+pub use self::versions::{Version, VersionPrecondition, VersionStore};
+pub use self::write_queue::{
+    QueuedWrite,
+    SubmittedWrite,
+    WriteQueue,
+    WriteQueueConfig,
+    WriteQueueReceiver,
+};
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use tantivy::collector::TopDocs;
+use tantivy::query::TermQuery;
+use tantivy::schema::{DocumentAccess, IndexRecordOption};
+use tantivy::{DocAddress, Searcher};
+
+use crate::error::IngestError;
+use crate::upsert::PrimaryKey;
+
+/// The maximum number of documents which can be fetched in a single request.
+pub const MAX_MULTI_GET_IDS: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+/// A request to fetch a set of documents by their primary key.
+pub struct MultiGetRequest {
+    /// The primary keys of the documents to fetch.
+    pub ids: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The outcome of fetching a single document.
+pub struct MultiGetItem<D> {
+    /// The requested primary key.
+    pub id: serde_json::Value,
+    /// If a document with the primary key exists.
+    pub found: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The stored document, if it was found.
+    pub document: Option<D>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The reason the document could not be fetched, i.e. the key is invalid.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The documents fetched by a multi-get request, in the order they were requested.
+pub struct MultiGetResponse<D> {
+    pub items: Vec<MultiGetItem<D>>,
+}
+
+/// Fetches a set of documents by their primary key in a single round trip.
+///
+/// Each matching document is fetched via `load`, keys which do not match any
+/// document are reported as not found rather than failing the whole request.
+pub fn multi_get<D: DocumentAccess>(
+    searcher: &Searcher,
+    key: &PrimaryKey,
+    request: MultiGetRequest,
+    mut load: impl FnMut(DocAddress) -> Result<D, String>,
+) -> Result<MultiGetResponse<D>, IngestError> {
+    if request.ids.len() > MAX_MULTI_GET_IDS {
+        return Err(IngestError::InvalidRequest(format!(
+            "Cannot fetch {} documents, the maximum is {MAX_MULTI_GET_IDS}",
+            request.ids.len(),
+        )));
+    }
+
+    let mut items = Vec::with_capacity(request.ids.len());
+    for id in request.ids {
+        let item = match fetch(searcher, key, &id, &mut load)? {
+            Ok(document) => MultiGetItem {
+                id,
+                found: document.is_some(),
+                document,
+                error: None,
+            },
+            Err(e) => MultiGetItem {
+                id,
+                found: false,
+                document: None,
+                error: Some(e),
+            },
+        };
+        items.push(item);
+    }
+
+    Ok(MultiGetResponse { items })
+}
+
+fn fetch<D: DocumentAccess>(
+    searcher: &Searcher,
+    key: &PrimaryKey,
+    id: &serde_json::Value,
+    load: &mut impl FnMut(DocAddress) -> Result<D, String>,
+) -> Result<Result<Option<D>, String>, IngestError> {
+    let term = match key.term_for_json(id) {
+        Ok(term) => term,
+        Err(e) => return Ok(Err(e)),
+    };
+
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let hits = searcher.search(&query, &TopDocs::with_limit(1))?;
+
+    Ok(hits.first().map(|(_, address)| load(*address)).transpose())
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, STORED, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_multi_get() {
+        let mut schema = SchemaBuilder::new();
+        let id = schema.add_text_field("id", STRING | STORED);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        writer.add_document(doc!(id => "a")).unwrap();
+        writer.add_document(doc!(id => "b")).unwrap();
+        writer.commit().unwrap();
+
+        let key = PrimaryKey::from_schema(&index.schema(), "id").unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let request: MultiGetRequest =
+            serde_json::from_str(r#"{"ids": ["b", "missing", "a", null]}"#).unwrap();
+
+        let response = multi_get(&searcher, &key, request, |address| {
+            searcher.doc(address).map_err(|e| e.to_string())
+        })
+        .unwrap();
+
+        let found = response
+            .items
+            .iter()
+            .map(|item| (item.found, item.error.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [(true, false), (false, false), (true, false), (false, true)]
+        );
+        assert_eq!(response.items[0].document, Some(doc!(id => "b")));
+        assert_eq!(response.items[2].document, Some(doc!(id => "a")));
+    }
+}
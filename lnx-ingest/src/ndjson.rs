@@ -0,0 +1,287 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, Read};
+
+use lnx_document::DynamicDocument;
+
+use crate::bulk::{BulkResponse, WriteOutcome};
+use crate::error::IngestError;
+
+/// The default maximum length (in bytes) of a single NDJSON line.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 10 << 20;
+
+/// A single line read from an NDJSON stream.
+pub struct NdjsonLine<'a> {
+    /// The line number, starting at `1`.
+    pub line: usize,
+    /// The raw text of the line, this is empty if the line exceeded the maximum length
+    /// or is not valid UTF-8.
+    pub source: &'a str,
+    /// The parsed document or the reason the line is invalid.
+    pub document: Result<DynamicDocument<'a>, String>,
+}
+
+/// Streams documents from a newline-delimited JSON reader.
+///
+/// Only a single line is buffered at any one time, blank lines are skipped.
+pub struct NdjsonReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    line: usize,
+    max_line_length: usize,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    /// Creates a new reader over the given NDJSON stream.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            line: 0,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+        }
+    }
+
+    /// Sets the maximum length (in bytes) of a single line.
+    ///
+    /// Longer lines are rejected and skipped without being buffered.
+    pub fn with_max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Reads the next document from the stream.
+    ///
+    /// Returns `None` once the end of the stream is reached.
+    pub fn next_document(&mut self) -> Result<Option<NdjsonLine<'_>>, IngestError> {
+        loop {
+            self.buffer.clear();
+            self.line += 1;
+
+            let limit = self.max_line_length as u64 + 1;
+            let n = self
+                .reader
+                .by_ref()
+                .take(limit)
+                .read_until(b'\n', &mut self.buffer)?;
+            if n == 0 {
+                return Ok(None);
+            }
+
+            if self.buffer.len() > self.max_line_length && !self.buffer.ends_with(b"\n")
+            {
+                self.skip_line()?;
+                let error = format!(
+                    "Line exceeds the maximum length of {} bytes",
+                    self.max_line_length,
+                );
+                return Ok(Some(NdjsonLine {
+                    line: self.line,
+                    source: "",
+                    document: Err(error),
+                }));
+            }
+
+            if self.buffer.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            // Invalid UTF-8 only rejects the line rather than the rest of the stream.
+            let source = match std::str::from_utf8(&self.buffer) {
+                Ok(source) => source,
+                Err(e) => {
+                    return Ok(Some(NdjsonLine {
+                        line: self.line,
+                        source: "",
+                        document: Err(format!("Line is not valid UTF-8: {e}")),
+                    }))
+                },
+            };
+
+            let document = serde_json::from_str(source).map_err(|e| e.to_string());
+            return Ok(Some(NdjsonLine {
+                line: self.line,
+                source: source.trim_end(),
+                document,
+            }));
+        }
+    }
+
+    /// Discards the remainder of the current line.
+    fn skip_line(&mut self) -> io::Result<()> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(pos) => {
+                    self.reader.consume(pos + 1);
+                    return Ok(());
+                },
+                None => {
+                    let len = buf.len();
+                    self.reader.consume(len);
+                },
+            }
+        }
+    }
+}
+
+/// Ingests every document within an NDJSON stream, passing each document to the writer.
+///
+/// Documents which cannot be parsed, or which are rejected by the writer, are recorded
+/// as failures without stopping the rest of the stream from being ingested.
+pub fn ingest_ndjson<R, W, E>(
+    reader: R,
+    mut writer: impl FnMut(DynamicDocument<'_>) -> Result<W, E>,
+) -> Result<BulkResponse, IngestError>
+where
+    R: BufRead,
+    W: WriteOutcome,
+    E: Display,
+{
+    let mut reader = NdjsonReader::new(reader);
+    let mut response = BulkResponse::default();
+
+    while let Some(NdjsonLine {
+        line,
+        source,
+        document,
+    }) = reader.next_document()?
+    {
+        match document.map(&mut writer) {
+            Ok(Ok(outcome)) => {
+                response.record_success_with_warnings(line, outcome.into_warnings())
+            },
+            Ok(Err(e)) => response.record_rejection(line, source, e),
+            Err(e) => response.record_rejection(line, source, e),
+        }
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_ingest_ndjson() {
+        let body = concat!(
+            "{\"title\": \"Hello\"}\n",
+            "\n",
+            "{\"title\": \n",
+            "{\"title\": \"reject me\"}\n",
+            "{\"title\": \"World\"}",
+        );
+
+        let mut titles = Vec::new();
+        let response = ingest_ndjson(Cursor::new(body), |doc| {
+            let (_, title) = &doc.0[0];
+            let title = format!("{title:?}");
+            if title.contains("reject me") {
+                return Err("Rejected by writer");
+            }
+            titles.push(title);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(response.succeeded, 2);
+        assert_eq!(response.failed, 2);
+        assert_eq!(titles.len(), 2);
+
+        let lines = response
+            .items
+            .iter()
+            .map(|item| item.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [1, 3, 4, 5]);
+        assert!(response.items[1].error.is_some());
+        assert_eq!(
+            response.items[2].error.as_deref(),
+            Some("Rejected by writer")
+        );
+
+        let sources = response
+            .rejected
+            .iter()
+            .map(|rejected| rejected.source.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(sources, ["{\"title\":", "{\"title\": \"reject me\"}"]);
+    }
+
+    #[test]
+    fn test_ingest_ndjson_warnings() {
+        let body = "{\"title\": \"short\"}\n{\"title\": \"very long\"}\n";
+        let response = ingest_ndjson(Cursor::new(body), |doc| {
+            let title = format!("{:?}", doc.0[0].1);
+            let mut warnings = Vec::new();
+            if title.contains("long") {
+                warnings.push("Field \"title\" was truncated".to_string());
+            }
+            Ok::<_, String>(warnings)
+        })
+        .unwrap();
+
+        assert_eq!(response.succeeded, 2);
+        assert!(response.items[0].warnings.is_empty());
+        assert_eq!(
+            response.items[1].warnings,
+            ["Field \"title\" was truncated"]
+        );
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["items"][0].get("warnings").is_none());
+        assert!(json["items"][1].get("warnings").is_some());
+    }
+
+    #[test]
+    fn test_max_line_length() {
+        let body = concat!(
+            "{\"title\": \"This line is far too long\"}\n",
+            "{\"a\": 1}\n",
+        );
+
+        let mut reader = NdjsonReader::new(Cursor::new(body)).with_max_line_length(16);
+
+        let line = reader.next_document().unwrap().unwrap();
+        assert_eq!(line.line, 1);
+        assert!(line.document.is_err());
+
+        let line = reader.next_document().unwrap().unwrap();
+        assert_eq!(line.line, 2);
+        assert!(line.document.is_ok());
+
+        assert!(reader.next_document().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_utf8_line() {
+        let mut body = b"{\"title\": \"Hello\"}\n".to_vec();
+        body.extend_from_slice(b"{\"title\": \"\xff\xfe\"}\n");
+        body.extend_from_slice(b"{\"title\": \"World\"}\n");
+
+        let response =
+            ingest_ndjson(Cursor::new(body), |_| Ok::<_, String>(())).unwrap();
+        assert_eq!(response.succeeded, 2);
+        assert_eq!(response.failed, 1);
+        assert_eq!(response.items[1].line, 2);
+        assert!(response.items[1].error.is_some());
+
+        // A line cut off mid-character by the length limit is rejected on its own.
+        let body = "{\"a\": \"éé\"}\n{\"a\": 1}\n";
+        let mut reader = NdjsonReader::new(Cursor::new(body)).with_max_line_length(9);
+
+        let line = reader.next_document().unwrap().unwrap();
+        assert_eq!(line.line, 1);
+        assert!(line.document.is_err());
+
+        let line = reader.next_document().unwrap().unwrap();
+        assert_eq!(line.line, 2);
+        assert!(line.document.is_ok());
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::Duration;
+
+use bytes::Bytes;
+use exponential_backoff::Backoff;
+use futures::{StreamExt, TryStreamExt};
+use lnx_document::DynamicDocument;
+use object_store::path::Path;
+use object_store::ObjectStore;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::bulk::BulkResponse;
+use crate::csv::{ingest_csv, CsvMapping};
+use crate::error::IngestError;
+use crate::ndjson::ingest_ndjson;
+use crate::tasks::TaskHandle;
+
+const MIN_RETRY_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// The format of a file being ingested.
+pub enum FileFormat {
+    /// Newline-delimited JSON documents.
+    Ndjson,
+    /// CSV with a header row.
+    Csv,
+    /// Parquet, this requires the `columnar` feature.
+    Parquet,
+}
+
+impl FileFormat {
+    /// Detects the format of a file from its extension.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let (_, extension) = path.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "ndjson" | "jsonl" | "json" => Some(Self::Ndjson),
+            "csv" => Some(Self::Csv),
+            "parquet" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// A request to ingest every file under a prefix within an object store.
+pub struct ObjectIngestRequest {
+    /// The location of the files, i.e. `s3://bucket/prefix` or `gs://bucket/prefix`.
+    pub url: String,
+    #[serde(default)]
+    /// The format of every file, by default the format is detected from each file's
+    /// extension and files with an unknown extension are skipped.
+    pub format: Option<FileFormat>,
+    #[serde(default = "default_concurrency")]
+    /// The maximum number of files downloaded concurrently.
+    pub concurrency: usize,
+    #[serde(default = "default_max_retries")]
+    /// The maximum number of times a failed download is retried.
+    pub max_retries: u32,
+    #[serde(default)]
+    /// Additional options for the store, i.e. `aws_region` or credentials.
+    pub options: BTreeMap<String, String>,
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The outcome of ingesting a single file.
+pub struct ObjectFileResult {
+    /// The path of the file within the store.
+    pub path: String,
+    /// The number of documents which were ingested.
+    pub succeeded: usize,
+    /// The number of documents which were rejected.
+    pub failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The reason the file could not be ingested, if it could not be ingested.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+/// The outcome of an object store ingestion job.
+pub struct ObjectIngestResult {
+    /// The number of documents which were ingested.
+    pub succeeded: usize,
+    /// The number of documents which were rejected.
+    pub failed: usize,
+    /// The outcome of each file.
+    pub files: Vec<ObjectFileResult>,
+}
+
+/// Lists every file under the request's prefix and ingests them, passing each document to the writer.
+///
+/// Files are downloaded concurrently (up to `concurrency` at once) with failed downloads
+/// being retried with an exponential backoff, each downloaded file is then parsed as
+/// NDJSON, CSV (using the given mapping) or Parquet. Files which cannot be downloaded or
+/// parsed are recorded as failures without stopping the rest of the files from being ingested.
+pub async fn ingest_object_store<E: Display>(
+    request: &ObjectIngestRequest,
+    mapping: &CsvMapping,
+    task: Option<&TaskHandle>,
+    mut writer: impl FnMut(DynamicDocument<'_>) -> Result<(), E>,
+) -> Result<ObjectIngestResult, IngestError> {
+    let url = Url::parse(&request.url).map_err(|e| {
+        IngestError::InvalidSource(format!("Invalid url {:?}: {e}", request.url))
+    })?;
+    let (store, prefix) = object_store::parse_url_opts(&url, request.options.iter())?;
+
+    let files = store
+        .list(Some(&prefix))
+        .await?
+        .try_filter_map(|meta| async move {
+            let format = request
+                .format
+                .or_else(|| FileFormat::from_path(meta.location.as_ref()));
+            Ok(format.map(|format| (meta.location, format)))
+        })
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let total = files.len() as u64;
+    if let Some(task) = task {
+        task.set_progress(0, Some(total));
+    }
+
+    let store = store.as_ref();
+    let mut downloads = futures::stream::iter(files)
+        .map(|(path, format)| async move {
+            let body = download(store, &path, request.max_retries).await;
+            (path, format, body)
+        })
+        .buffer_unordered(request.concurrency.max(1));
+
+    let mut result = ObjectIngestResult::default();
+    while let Some((path, format, body)) = downloads.next().await {
+        let outcome =
+            body.and_then(|body| ingest_file(format, body, mapping, &mut writer));
+
+        let file = match outcome {
+            Ok(response) => {
+                result.succeeded += response.succeeded;
+                result.failed += response.failed;
+                ObjectFileResult {
+                    path: path.to_string(),
+                    succeeded: response.succeeded,
+                    failed: response.failed,
+                    error: None,
+                }
+            },
+            Err(e) => ObjectFileResult {
+                path: path.to_string(),
+                succeeded: 0,
+                failed: 0,
+                error: Some(e.to_string()),
+            },
+        };
+        result.files.push(file);
+
+        if let Some(task) = task {
+            task.set_progress(result.files.len() as u64, Some(total));
+        }
+    }
+
+    Ok(result)
+}
+
+/// Downloads a file, retrying with an exponential backoff if the download fails.
+async fn download(
+    store: &dyn ObjectStore,
+    path: &Path,
+    max_retries: u32,
+) -> Result<Bytes, IngestError> {
+    let backoff = Backoff::new(max_retries, MIN_RETRY_BACKOFF, MAX_RETRY_BACKOFF);
+    let mut delays = backoff.into_iter();
+
+    loop {
+        let error = match store.get(path).await {
+            Ok(response) => match response.bytes().await {
+                Ok(body) => return Ok(body),
+                Err(e) => e,
+            },
+            Err(e) => e,
+        };
+
+        match delays.next() {
+            Some(delay) => {
+                tracing::warn!(
+                    path = %path,
+                    error = ?error,
+                    "Failed to download file, retrying",
+                );
+                tokio::time::sleep(delay).await;
+            },
+            None => return Err(error.into()),
+        }
+    }
+}
+
+/// Ingests a single downloaded file.
+fn ingest_file<E: Display>(
+    format: FileFormat,
+    body: Bytes,
+    mapping: &CsvMapping,
+    writer: impl FnMut(DynamicDocument<'_>) -> Result<(), E>,
+) -> Result<BulkResponse, IngestError> {
+    match format {
+        FileFormat::Ndjson => ingest_ndjson(body.as_ref(), writer),
+        FileFormat::Csv => ingest_csv(body.as_ref(), mapping, writer),
+        #[cfg(feature = "columnar")]
+        FileFormat::Parquet => crate::columnar::ingest_parquet(body, writer),
+        #[cfg(not(feature = "columnar"))]
+        FileFormat::Parquet => Err(IngestError::InvalidSource(
+            "Parquet files require the `columnar` feature".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            FileFormat::from_path("logs/2023/01.ndjson"),
+            Some(FileFormat::Ndjson)
+        );
+        assert_eq!(FileFormat::from_path("a.JSONL"), Some(FileFormat::Ndjson));
+        assert_eq!(FileFormat::from_path("data.csv"), Some(FileFormat::Csv));
+        assert_eq!(
+            FileFormat::from_path("part-0001.parquet"),
+            Some(FileFormat::Parquet)
+        );
+        assert_eq!(FileFormat::from_path("README"), None);
+        assert_eq!(FileFormat::from_path("archive.tar.gz"), None);
+    }
+}
@@ -0,0 +1,143 @@
+use lnx_document::{DynamicDocument, KeyValues, Value};
+use serde::Deserialize;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How arrays within a patch are merged into the existing document.
+pub enum ArrayMergeMode {
+    #[default]
+    /// Arrays within the patch replace the existing array, as per RFC 7396.
+    Replace,
+    /// Arrays within the patch are appended to the existing array.
+    Append,
+}
+
+/// Applies a JSON merge-patch (RFC 7396) to a document.
+///
+/// - Objects within the patch are merged recursively into the existing object.
+/// - `null` values within the patch remove the key from the document.
+/// - Any other value replaces the existing value, arrays can optionally be
+///   appended to the existing array instead via [ArrayMergeMode::Append].
+pub fn merge_patch<'a>(
+    document: DynamicDocument<'a>,
+    patch: DynamicDocument<'a>,
+    arrays: ArrayMergeMode,
+) -> DynamicDocument<'a> {
+    let mut target = document.0;
+    merge_object(&mut target, patch.0, arrays);
+    DynamicDocument(target)
+}
+
+fn merge_object<'a>(
+    target: &mut KeyValues<'a>,
+    patch: KeyValues<'a>,
+    arrays: ArrayMergeMode,
+) {
+    for (key, value) in patch {
+        if matches!(value, Value::Null) {
+            target.retain(|(existing, _)| existing != &key);
+            continue;
+        }
+
+        let Some(pos) = target.iter().position(|(existing, _)| existing == &key) else {
+            target.push((key, strip_nulls(value)));
+            continue;
+        };
+
+        let existing = &mut target[pos].1;
+        match (existing, value) {
+            (Value::Object(existing), Value::Object(patch)) => {
+                merge_object(existing, patch, arrays);
+            },
+            (Value::Array(existing), Value::Array(values))
+                if arrays == ArrayMergeMode::Append =>
+            {
+                existing.extend(values);
+            },
+            (existing, value) => *existing = strip_nulls(value),
+        }
+    }
+}
+
+/// Removes any `null` values from objects within a value which is being
+/// added to the document, as a merge-patch never sets a value to `null`.
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(patch) => {
+            let mut object = Vec::with_capacity(patch.len());
+            merge_object(&mut object, patch, ArrayMergeMode::Replace);
+            Value::Object(object)
+        },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(document: &str, patch: &str, arrays: ArrayMergeMode) -> String {
+        let document: DynamicDocument = serde_json::from_str(document).unwrap();
+        let patch: DynamicDocument = serde_json::from_str(patch).unwrap();
+        let patched = merge_patch(document, patch, arrays);
+        format!("{:?}", patched.0)
+    }
+
+    fn doc(document: &str) -> String {
+        let document: DynamicDocument = serde_json::from_str(document).unwrap();
+        format!("{:?}", document.0)
+    }
+
+    #[test]
+    fn test_merge_patch_rfc_examples() {
+        let replace = ArrayMergeMode::Replace;
+        assert_eq!(
+            apply(r#"{"a": "b"}"#, r#"{"a": "c"}"#, replace),
+            doc(r#"{"a": "c"}"#)
+        );
+        assert_eq!(
+            apply(r#"{"a": "b"}"#, r#"{"b": "c"}"#, replace),
+            doc(r#"{"a": "b", "b": "c"}"#),
+        );
+        assert_eq!(apply(r#"{"a": "b"}"#, r#"{"a": null}"#, replace), doc("{}"));
+        assert_eq!(
+            apply(r#"{"a": "b", "b": "c"}"#, r#"{"a": null}"#, replace),
+            doc(r#"{"b": "c"}"#),
+        );
+        assert_eq!(
+            apply(r#"{"a": ["b"]}"#, r#"{"a": "c"}"#, replace),
+            doc(r#"{"a": "c"}"#),
+        );
+        assert_eq!(
+            apply(
+                r#"{"a": {"b": "c"}}"#,
+                r#"{"a": {"b": "d", "c": null}}"#,
+                replace
+            ),
+            doc(r#"{"a": {"b": "d"}}"#),
+        );
+        assert_eq!(
+            apply(r#"{"e": null}"#, r#"{"a": 1}"#, replace),
+            doc(r#"{"e": null, "a": 1}"#),
+        );
+        assert_eq!(
+            apply("{}", r#"{"a": {"bb": {"ccc": null}}}"#, replace),
+            doc(r#"{"a": {"bb": {}}}"#),
+        );
+    }
+
+    #[test]
+    fn test_merge_patch_arrays() {
+        let document = r#"{"tags": ["a", "b"], "title": "Hello"}"#;
+        let patch = r#"{"tags": ["c"]}"#;
+
+        assert_eq!(
+            apply(document, patch, ArrayMergeMode::Replace),
+            doc(r#"{"tags": ["c"], "title": "Hello"}"#),
+        );
+        assert_eq!(
+            apply(document, patch, ArrayMergeMode::Append),
+            doc(r#"{"tags": ["a", "b", "c"], "title": "Hello"}"#),
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use lnx_document::DynamicDocument;
+use lnx_schema::presence::apply_field_presence;
+use lnx_schema::schema::IndexSchema;
+use tantivy::schema::Schema;
+
+use crate::ingest_pipeline::IngestPipeline;
+
+/// Prepares ingested documents for indexing.
+///
+/// Each document is passed through the index's ingest pipeline (if it has one)
+/// followed by the transformations defined by the index's schema, the prepared
+/// document is then ready to be converted and passed to the writer.
+pub struct DocumentPreparer {
+    definition: IndexSchema,
+    schema: Schema,
+    pipeline: Option<IngestPipeline>,
+}
+
+impl DocumentPreparer {
+    /// Creates a new preparer for the index schema and the tantivy schema built from it.
+    pub fn new(definition: IndexSchema, schema: Schema) -> Self {
+        Self {
+            definition,
+            schema,
+            pipeline: None,
+        }
+    }
+
+    /// Runs the given ingest pipeline on each document before the schema is applied.
+    pub fn with_pipeline(mut self, pipeline: IngestPipeline) -> Self {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    /// The index schema documents are prepared for.
+    pub fn definition(&self) -> &IndexSchema {
+        &self.definition
+    }
+
+    /// The tantivy schema built from the index schema.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Prepares the document for indexing.
+    ///
+    /// The `null` values of each field are handled according to its null handling,
+    /// then missing fields are filled in with their default values, or the document
+    /// is rejected if a required field is missing. Values, including defaults, are
+    /// then copied into their `copy_to` targets before the field presence of the
+    /// document is recorded, so presence reflects the document as it is indexed.
+    pub fn prepare<'a>(
+        &self,
+        document: DynamicDocument<'a>,
+    ) -> Result<DynamicDocument<'a>, String> {
+        let mut document = match self.pipeline.as_ref() {
+            Some(pipeline) => pipeline.run(document)?,
+            None => document,
+        };
+
+        self.definition.apply_null_handling(&mut document);
+        self.definition
+            .apply_defaults(&mut document)
+            .map_err(|e| e.to_string())?;
+        self.definition.apply_copy_to(&mut document);
+        apply_field_presence(&self.schema, &mut document);
+
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lnx_document::Value;
+    use lnx_query::{QueryContext, QueryKind};
+    use lnx_schema::presence::FIELD_PRESENCE_FIELD;
+    use tantivy::collector::Count;
+    use tantivy::schema::Field;
+    use tantivy::{Document, Index, Searcher};
+
+    use super::*;
+    use crate::ingest_ndjson;
+    use crate::ingest_pipeline::PipelineConfig;
+
+    /// Converts the document into a tantivy document, object values are skipped
+    /// as the tests only check their presence.
+    fn to_document(
+        schema: &Schema,
+        document: DynamicDocument,
+    ) -> Result<Document, String> {
+        fn add_value(doc: &mut Document, field: Field, value: Value) {
+            match value {
+                Value::Str(text) => doc.add_text(field, text),
+                Value::U64(v) => doc.add_u64(field, v),
+                Value::I64(v) => doc.add_i64(field, v),
+                Value::Array(values) => {
+                    for value in values {
+                        add_value(doc, field, value);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let mut converted = Document::default();
+        for (key, value) in document.0 {
+            let field = schema.get_field(&key).map_err(|e| e.to_string())?;
+            add_value(&mut converted, field, value);
+        }
+        Ok(converted)
+    }
+
+    /// Ingests the NDJSON body into a new in-memory index via the preparer.
+    fn ingest(preparer: &DocumentPreparer, body: &str) -> Searcher {
+        let schema = preparer.schema();
+        let index = Index::create_in_ram(schema.clone());
+        let mut writer = index.writer(15_000_000).unwrap();
+
+        let response = ingest_ndjson(Cursor::new(body), |document| {
+            let document = to_document(schema, preparer.prepare(document)?)?;
+            writer.add_document(document).map_err(|e| e.to_string())?;
+            Ok::<_, String>(())
+        })
+        .unwrap();
+        assert_eq!(response.failed, 0, "{response:?}");
+
+        writer.commit().unwrap();
+        index.reader().unwrap().searcher()
+    }
+
+    fn count(searcher: &Searcher, ctx: &QueryContext, query: &str) -> usize {
+        let query: QueryKind = serde_json::from_str(query).unwrap();
+        searcher.search(&query.build(ctx).unwrap(), &Count).unwrap()
+    }
+
+    #[test]
+    fn test_prepare_records_field_presence() {
+        let definition: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {
+                    "title": {"type": "text"},
+                    "subtitle": {"type": "text"},
+                    "meta": {"type": "dynamic"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let (schema, _) = definition.build().unwrap();
+
+        let body = concat!(
+            "{\"title\": \"first\", \"meta\": {\"author\": \"bob\"}}\n",
+            "{\"title\": \"second\", \"subtitle\": null}\n",
+            "{\"title\": \"third\", \"subtitle\": \"present\"}\n",
+        );
+        let preparer = DocumentPreparer::new(definition, schema.clone());
+        let searcher = ingest(&preparer, body);
+
+        let presence = schema.get_field(FIELD_PRESENCE_FIELD).unwrap();
+        let ctx = QueryContext::new(schema).with_field_presence_field(presence);
+        assert_eq!(
+            count(&searcher, &ctx, r#"{"exists": {"field": "title"}}"#),
+            3
+        );
+        assert_eq!(
+            count(&searcher, &ctx, r#"{"exists": {"field": "subtitle"}}"#),
+            1
+        );
+        assert_eq!(
+            count(&searcher, &ctx, r#"{"missing": {"field": "subtitle"}}"#),
+            2
+        );
+        assert_eq!(
+            count(&searcher, &ctx, r#"{"exists": {"field": "meta.author"}}"#),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_prepare_applies_copy_to() {
+        let definition: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {
+                    "title": {"type": "text", "copy_to": ["all"]},
+                    "views": {"type": "u64", "copy_to": ["all"]},
+                    "all": {"type": "text", "stored": false}
+                }
+            }"#,
+        )
+        .unwrap();
+        let (schema, _) = definition.build().unwrap();
+
+        let body = concat!(
+            "{\"title\": \"Hello world\", \"views\": 3}\n",
+            "{\"title\": \"Goodbye\"}\n",
+            "{\"views\": 5}\n",
+        );
+        let preparer = DocumentPreparer::new(definition, schema.clone());
+        let searcher = ingest(&preparer, body);
+
+        let presence = schema.get_field(FIELD_PRESENCE_FIELD).unwrap();
+        let ctx = QueryContext::new(schema).with_field_presence_field(presence);
+        let term = |value: &str| {
+            let query = format!(r#"{{"term": {{"field": "all", "value": "{value}"}}}}"#);
+            count(&searcher, &ctx, &query)
+        };
+        assert_eq!(term("world"), 1);
+        assert_eq!(term("goodbye"), 1);
+        assert_eq!(term("3"), 1);
+        assert_eq!(term("5"), 1);
+        assert_eq!(count(&searcher, &ctx, r#"{"exists": {"field": "all"}}"#), 3);
+    }
+
+    #[test]
+    fn test_prepare_applies_defaults() {
+        let definition: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {
+                    "title": {"type": "text", "required": true},
+                    "status": {"type": "string", "default": "draft", "copy_to": ["all"]},
+                    "all": {"type": "text", "stored": false}
+                }
+            }"#,
+        )
+        .unwrap();
+        let (schema, _) = definition.build().unwrap();
+
+        let body = concat!(
+            "{\"title\": \"first\"}\n",
+            "{\"title\": \"second\", \"status\": null}\n",
+            "{\"title\": \"third\", \"status\": \"published\"}\n",
+        );
+        let preparer = DocumentPreparer::new(definition, schema.clone());
+        let searcher = ingest(&preparer, body);
+
+        let ctx = QueryContext::new(schema);
+        let term = |field: &str, value: &str| {
+            let query =
+                format!(r#"{{"term": {{"field": "{field}", "value": "{value}"}}}}"#);
+            count(&searcher, &ctx, &query)
+        };
+        assert_eq!(term("status", "draft"), 2);
+        assert_eq!(term("status", "published"), 1);
+        assert_eq!(term("all", "draft"), 2);
+
+        let body = "{\"status\": \"draft\"}\n";
+        let response = ingest_ndjson(Cursor::new(body), |document| {
+            preparer.prepare(document).map(|_| ())
+        })
+        .unwrap();
+        assert_eq!(response.failed, 1);
+        assert_eq!(
+            response.items[0].error.as_deref(),
+            Some("Document is missing the required field \"title\""),
+        );
+    }
+
+    #[test]
+    fn test_prepare_applies_null_handling() {
+        let definition: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {
+                    "title": {"type": "text"},
+                    "author": {"type": "string", "null_handling": "index"},
+                    "status": {
+                        "type": "string",
+                        "null_handling": "default",
+                        "default": "active"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let (schema, _) = definition.build().unwrap();
+
+        let body = concat!(
+            "{\"title\": \"first\", \"author\": null, \"status\": null}\n",
+            "{\"title\": \"second\", \"author\": \"bob\", \"status\": \"draft\"}\n",
+            "{\"title\": \"third\"}\n",
+        );
+        let preparer = DocumentPreparer::new(definition, schema.clone());
+        let searcher = ingest(&preparer, body);
+
+        let presence = schema.get_field(FIELD_PRESENCE_FIELD).unwrap();
+        let ctx = QueryContext::new(schema)
+            .with_field_presence_field(presence)
+            .with_null_handling(preparer.definition());
+        let query = r#"{"term": {"field": "author", "value": null}}"#;
+        assert_eq!(count(&searcher, &ctx, query), 1);
+        // Both the null and the missing status are replaced by the default.
+        let query = r#"{"term": {"field": "status", "value": "active"}}"#;
+        assert_eq!(count(&searcher, &ctx, query), 2);
+        let query = r#"{"exists": {"field": "author"}}"#;
+        assert_eq!(count(&searcher, &ctx, query), 2);
+    }
+
+    #[test]
+    fn test_prepare_runs_pipeline_first() {
+        let definition: IndexSchema = serde_json::from_str(
+            r#"{"fields": {"title": {"type": "text"}, "subtitle": {"type": "text"}}}"#,
+        )
+        .unwrap();
+        let (schema, _) = definition.build().unwrap();
+
+        let config: PipelineConfig = serde_json::from_str(
+            r#"{"processors": [{"type": "rename", "field": "name", "target": "subtitle"}]}"#,
+        )
+        .unwrap();
+        let preparer = DocumentPreparer::new(definition, schema.clone())
+            .with_pipeline(IngestPipeline::compile(&config, None).unwrap());
+
+        let body = "{\"title\": \"first\", \"name\": \"renamed\"}\n";
+        let searcher = ingest(&preparer, body);
+
+        let presence = schema.get_field(FIELD_PRESENCE_FIELD).unwrap();
+        let ctx = QueryContext::new(schema).with_field_presence_field(presence);
+        assert_eq!(
+            count(&searcher, &ctx, r#"{"exists": {"field": "subtitle"}}"#),
+            1
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use serde::Deserialize;
+use tantivy::Opstamp;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+/// Controls when the changes made by a write request become visible to search.
+pub enum Refresh {
+    #[default]
+    #[serde(rename = "false")]
+    /// Return immediately, the changes become visible on the next commit.
+    False,
+    #[serde(rename = "wait_for")]
+    /// Wait until the next commit makes the changes visible before returning.
+    WaitFor,
+    #[serde(rename = "true")]
+    /// Commit the index immediately so the changes are visible once the request returns.
+    True,
+}
+
+impl FromStr for Refresh {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "false" => Ok(Self::False),
+            "wait_for" => Ok(Self::WaitFor),
+            // `?refresh` without a value behaves the same as `?refresh=true`.
+            "true" | "" => Ok(Self::True),
+            other => Err(format!(
+                "Invalid refresh value {other:?}, expected `false`, `wait_for` or `true`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+/// The per-index commit settings.
+pub struct CommitPolicy {
+    #[serde(default)]
+    /// How often (in milliseconds) pending changes are committed automatically.
+    ///
+    /// If not set the index is only committed when a request asks for it via
+    /// `refresh=true` or `refresh=wait_for`, allowing writers to batch commits.
+    pub auto_commit_interval_ms: Option<u64>,
+}
+
+impl CommitPolicy {
+    #[inline]
+    /// The interval pending changes are committed at automatically.
+    pub fn auto_commit_interval(&self) -> Option<Duration> {
+        self.auto_commit_interval_ms.map(Duration::from_millis)
+    }
+}
+
+struct CommitState {
+    /// The opstamp of the latest write.
+    last_write: Opstamp,
+    /// The opstamp of the latest commit.
+    last_commit: Opstamp,
+    /// When the index was last committed.
+    last_commit_at: Instant,
+    /// If a request has asked for the pending changes to be committed.
+    commit_requested: bool,
+}
+
+/// Tracks the writes and commits of an index to decide when it should be committed
+/// and to allow `refresh=wait_for` requests to wait for their changes to be committed.
+pub struct CommitTracker {
+    policy: CommitPolicy,
+    state: Mutex<CommitState>,
+    committed: Condvar,
+}
+
+impl CommitTracker {
+    /// Creates a new tracker for an index with the given opstamp of its last commit.
+    pub fn new(policy: CommitPolicy, committed_opstamp: Opstamp) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(CommitState {
+                last_write: committed_opstamp,
+                last_commit: committed_opstamp,
+                last_commit_at: Instant::now(),
+                commit_requested: false,
+            }),
+            committed: Condvar::new(),
+        }
+    }
+
+    #[inline]
+    /// The commit settings of the index.
+    pub fn policy(&self) -> &CommitPolicy {
+        &self.policy
+    }
+
+    /// Records a write operation with the given opstamp.
+    pub fn record_write(&self, opstamp: Opstamp) {
+        let mut state = self.state.lock();
+        state.last_write = state.last_write.max(opstamp);
+    }
+
+    /// Requests the pending changes be committed as soon as possible.
+    pub fn request_commit(&self) {
+        self.state.lock().commit_requested = true;
+    }
+
+    /// Returns if there are uncommitted changes which are due to be committed.
+    ///
+    /// Changes are due once a commit has been requested or the auto-commit interval has elapsed.
+    pub fn is_commit_due(&self) -> bool {
+        let state = self.state.lock();
+        if state.last_write <= state.last_commit {
+            return false;
+        }
+
+        let interval_elapsed = self
+            .policy
+            .auto_commit_interval()
+            .map_or(false, |interval| state.last_commit_at.elapsed() >= interval);
+
+        state.commit_requested || interval_elapsed
+    }
+
+    /// Records a commit of the index, waking any requests waiting for their changes to be committed.
+    pub fn mark_committed(&self, opstamp: Opstamp) {
+        let mut state = self.state.lock();
+        state.last_commit = state.last_commit.max(opstamp);
+        state.last_commit_at = Instant::now();
+        state.commit_requested = false;
+        drop(state);
+
+        self.committed.notify_all();
+    }
+
+    /// Waits until the given opstamp has been committed.
+    ///
+    /// If the index has no auto-commit interval, a commit is requested so
+    /// the request is not left waiting indefinitely.
+    ///
+    /// Returns `false` if the timeout elapsed before the opstamp was committed.
+    pub fn wait_for(&self, opstamp: Opstamp, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock();
+
+        if self.policy.auto_commit_interval().is_none() {
+            state.commit_requested = true;
+        }
+
+        while state.last_commit < opstamp {
+            if self.committed.wait_until(&mut state, deadline).timed_out() {
+                return state.last_commit >= opstamp;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_refresh() {
+        assert_eq!("false".parse(), Ok(Refresh::False));
+        assert_eq!("wait_for".parse(), Ok(Refresh::WaitFor));
+        assert_eq!("true".parse(), Ok(Refresh::True));
+        assert_eq!("".parse(), Ok(Refresh::True));
+        assert!("yes".parse::<Refresh>().is_err());
+    }
+
+    #[test]
+    fn test_commit_due() {
+        let policy = CommitPolicy {
+            auto_commit_interval_ms: Some(0),
+        };
+        let tracker = CommitTracker::new(policy, 0);
+        assert!(!tracker.is_commit_due(), "Nothing has been written");
+
+        tracker.record_write(1);
+        assert!(tracker.is_commit_due(), "The interval has elapsed");
+
+        tracker.mark_committed(1);
+        assert!(!tracker.is_commit_due(), "Everything has been committed");
+
+        let tracker = CommitTracker::new(CommitPolicy::default(), 0);
+        tracker.record_write(1);
+        assert!(!tracker.is_commit_due(), "No commit has been requested");
+        tracker.request_commit();
+        assert!(tracker.is_commit_due());
+    }
+
+    #[test]
+    fn test_wait_for() {
+        let tracker = Arc::new(CommitTracker::new(CommitPolicy::default(), 0));
+        tracker.record_write(5);
+
+        assert!(!tracker.wait_for(5, Duration::from_millis(10)));
+        assert!(tracker.is_commit_due(), "Waiting should request a commit");
+
+        let committer = tracker.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            committer.mark_committed(5);
+        });
+
+        assert!(tracker.wait_for(5, Duration::from_secs(5)));
+        handle.join().unwrap();
+    }
+}
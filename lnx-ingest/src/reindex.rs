@@ -0,0 +1,172 @@
+use std::fmt::Display;
+
+use lnx_document::DynamicDocument;
+use serde::{Deserialize, Serialize};
+use tantivy::query::Query;
+use tantivy::{DocAddress, Searcher};
+
+use crate::error::IngestError;
+use crate::ingest_pipeline::IngestPipeline;
+use crate::tasks::TaskHandle;
+use crate::update_by_query::{rewrite_matching, DEFAULT_UPDATE_BATCH_SIZE};
+
+#[derive(Debug, Deserialize)]
+/// A request to copy documents from one index into another.
+pub struct ReindexRequest<'a> {
+    #[serde(borrow)]
+    /// The index documents are copied from.
+    pub source: ReindexSource<'a>,
+    /// The index documents are copied into.
+    pub dest: ReindexDest,
+    #[serde(default = "default_batch_size")]
+    /// The number of documents written to the destination per batch.
+    pub batch_size: usize,
+    #[serde(default)]
+    /// Throttles the reindex to at most this many documents per second.
+    pub max_docs_per_second: Option<f64>,
+}
+
+fn default_batch_size() -> usize {
+    DEFAULT_UPDATE_BATCH_SIZE
+}
+
+#[derive(Debug, Deserialize)]
+/// The source of a reindex.
+pub struct ReindexSource<'a> {
+    /// The name of the source index.
+    pub index: String,
+    #[serde(default, borrow)]
+    /// Only copy documents matching this query, by default every document is copied.
+    pub query: Option<lnx_query::QueryKind<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+/// The destination of a reindex.
+pub struct ReindexDest {
+    /// The name of the destination index.
+    pub index: String,
+    #[serde(default)]
+    /// The ingest pipeline used to transform each document before it is written.
+    pub pipeline: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+/// The outcome of a reindex.
+pub struct ReindexResult {
+    /// The number of documents in the source index which matched the query.
+    pub total: u64,
+    /// The number of documents written to the destination index.
+    pub indexed: u64,
+    /// The number of documents which could not be copied.
+    pub failed: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// The reasons documents could not be copied.
+    pub errors: Vec<String>,
+}
+
+/// Copies every document in the source index matching the query into the destination.
+///
+/// Each matching document is fetched from the source via `load`, transformed by the
+/// `pipeline` if one is given and passed to the destination `writer` in batches of
+/// `batch_size`. Combined with an alias swap this allows an index to be migrated to
+/// a new schema without downtime.
+///
+/// Documents which cannot be loaded or transformed, or batches rejected by the writer,
+/// are recorded as failures without stopping the rest of the documents from being copied.
+#[allow(clippy::too_many_arguments)]
+pub fn reindex<'a, E: Display>(
+    source: &Searcher,
+    query: &dyn Query,
+    pipeline: Option<&IngestPipeline>,
+    batch_size: usize,
+    max_docs_per_second: Option<f64>,
+    task: Option<&TaskHandle>,
+    load: impl FnMut(DocAddress) -> Result<DynamicDocument<'a>, String>,
+    writer: impl FnMut(Vec<DynamicDocument<'a>>) -> Result<(), E>,
+) -> Result<ReindexResult, IngestError> {
+    let transform = |document: DynamicDocument<'a>| match pipeline {
+        Some(pipeline) => pipeline.run(document),
+        None => Ok(document),
+    };
+
+    let result = rewrite_matching(
+        source,
+        query,
+        batch_size,
+        max_docs_per_second,
+        task,
+        load,
+        transform,
+        writer,
+    )?;
+
+    Ok(ReindexResult {
+        total: result.matched,
+        indexed: result.updated,
+        failed: result.failed,
+        errors: result.errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, STORED, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::ingest_pipeline::PipelineConfig;
+
+    #[test]
+    fn test_reindex() {
+        let mut schema = SchemaBuilder::new();
+        let id = schema.add_text_field("id", STRING | STORED);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for i in 0..10 {
+            writer.add_document(doc!(id => i.to_string())).unwrap();
+        }
+        writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        let config: PipelineConfig = serde_json::from_str(
+            r#"{"processors": [{"type": "rename", "field": "id", "target": "key"}]}"#,
+        )
+        .unwrap();
+        let pipeline = IngestPipeline::compile(&config, None).unwrap();
+
+        let mut written = Vec::new();
+        let start = Instant::now();
+        let result = reindex(
+            &searcher,
+            &AllQuery,
+            Some(&pipeline),
+            4,
+            Some(200.0),
+            None,
+            |address| {
+                if address.doc_id == 3 {
+                    return Err("Cannot load document".to_string());
+                }
+                Ok(serde_json::from_str(r#"{"id": "doc"}"#).unwrap())
+            },
+            |batch| {
+                written.extend(batch.into_iter().map(|doc| doc.0[0].0.to_string()));
+                Ok::<_, String>(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(result.total, 10);
+        assert_eq!(result.indexed, 9);
+        assert_eq!(result.failed, 1);
+        assert!(written.iter().all(|key| key == "key"));
+        assert!(
+            start.elapsed() >= Duration::from_millis(40),
+            "The reindex should be throttled"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use serde::Serialize;
+
+/// The maximum number of finished tasks retained for status lookups.
+///
+/// Once exceeded the oldest finished tasks are discarded.
+pub const MAX_FINISHED_TASKS: usize = 1024;
+
+/// The unique ID of a background task.
+pub type TaskId = u64;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+/// The current status of a background task.
+pub enum TaskStatus {
+    /// The task has not started yet.
+    Queued,
+    /// The task is currently running.
+    Running {
+        /// The number of items processed so far.
+        processed: u64,
+        /// The total number of items to process, if known.
+        total: Option<u64>,
+    },
+    /// The task completed successfully.
+    Succeeded {
+        /// The result produced by the task.
+        result: serde_json::Value,
+    },
+    /// The task failed.
+    Failed {
+        /// The reason the task failed.
+        error: String,
+    },
+}
+
+impl TaskStatus {
+    #[inline]
+    /// Returns if the task has finished, either successfully or not.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded { .. } | Self::Failed { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// Information about a background task.
+pub struct TaskInfo {
+    /// The unique ID of the task.
+    pub id: TaskId,
+    /// The kind of task, i.e. `delete_by_query`.
+    pub kind: &'static str,
+    /// The index the task is operating on.
+    pub index: String,
+    #[serde(flatten)]
+    /// The current status of the task.
+    pub status: TaskStatus,
+}
+
+#[derive(Default)]
+struct TaskRegistryInner {
+    next_id: AtomicU64,
+    tasks: Mutex<BTreeMap<TaskId, TaskInfo>>,
+    finished: Condvar,
+}
+
+#[derive(Clone, Default)]
+/// Tracks the status of long-running background tasks so they can be
+/// polled by their ID, i.e. via `GET /tasks/:id`.
+pub struct TaskRegistry {
+    inner: Arc<TaskRegistryInner>,
+}
+
+impl TaskRegistry {
+    /// Registers a new queued task, returning the handle used to report its progress.
+    pub fn create(&self, kind: &'static str, index: impl Into<String>) -> TaskHandle {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let info = TaskInfo {
+            id,
+            kind,
+            index: index.into(),
+            status: TaskStatus::Queued,
+        };
+        self.inner.tasks.lock().insert(id, info);
+
+        TaskHandle {
+            id,
+            registry: self.clone(),
+        }
+    }
+
+    /// Gets the current information of a task.
+    pub fn get(&self, id: TaskId) -> Option<TaskInfo> {
+        self.inner.tasks.lock().get(&id).cloned()
+    }
+
+    /// Waits for a task to finish, returning its information once it
+    /// has finished or the timeout has elapsed.
+    pub fn wait(&self, id: TaskId, timeout: Duration) -> Option<TaskInfo> {
+        let deadline = Instant::now() + timeout;
+        let mut tasks = self.inner.tasks.lock();
+
+        loop {
+            let info = tasks.get(&id)?;
+            if info.status.is_finished() {
+                return Some(info.clone());
+            }
+
+            if self
+                .inner
+                .finished
+                .wait_until(&mut tasks, deadline)
+                .timed_out()
+            {
+                return tasks.get(&id).cloned();
+            }
+        }
+    }
+
+    /// Gets the information of every task on the index.
+    pub fn list(&self, index: &str) -> Vec<TaskInfo> {
+        self.inner
+            .tasks
+            .lock()
+            .values()
+            .filter(|info| info.index == index)
+            .cloned()
+            .collect()
+    }
+
+    fn set_status(&self, id: TaskId, status: TaskStatus) {
+        let mut tasks = self.inner.tasks.lock();
+        let is_finished = status.is_finished();
+
+        if let Some(info) = tasks.get_mut(&id) {
+            // A finished task never goes back to running.
+            if !info.status.is_finished() {
+                info.status = status;
+            }
+        }
+
+        if is_finished {
+            prune_finished_tasks(&mut tasks);
+            drop(tasks);
+            self.inner.finished.notify_all();
+        }
+    }
+}
+
+/// Removes the oldest finished tasks until at most `MAX_FINISHED_TASKS` remain.
+fn prune_finished_tasks(tasks: &mut BTreeMap<TaskId, TaskInfo>) {
+    let num_finished = tasks
+        .values()
+        .filter(|info| info.status.is_finished())
+        .count();
+    let mut num_to_remove = num_finished.saturating_sub(MAX_FINISHED_TASKS);
+    if num_to_remove == 0 {
+        return;
+    }
+
+    // Task IDs are sequential so the map is ordered from oldest to newest.
+    tasks.retain(|_, info| {
+        if num_to_remove > 0 && info.status.is_finished() {
+            num_to_remove -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+/// The handle used by a running task to report its progress.
+///
+/// If the handle is dropped before the task reports its outcome
+/// the task is marked as failed.
+pub struct TaskHandle {
+    id: TaskId,
+    registry: TaskRegistry,
+}
+
+impl TaskHandle {
+    #[inline]
+    /// The unique ID of the task.
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Reports the progress of the task.
+    pub fn set_progress(&self, processed: u64, total: Option<u64>) {
+        self.registry
+            .set_status(self.id, TaskStatus::Running { processed, total });
+    }
+
+    /// Marks the task as successfully completed with the given result.
+    pub fn succeed(self, result: impl Serialize) {
+        let status = match serde_json::to_value(result) {
+            Ok(result) => TaskStatus::Succeeded { result },
+            Err(e) => TaskStatus::Failed {
+                error: format!("Unable to serialize task result: {e}"),
+            },
+        };
+        self.registry.set_status(self.id, status);
+    }
+
+    /// Marks the task as failed.
+    pub fn fail(self, error: impl ToString) {
+        let status = TaskStatus::Failed {
+            error: error.to_string(),
+        };
+        self.registry.set_status(self.id, status);
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        let status = TaskStatus::Failed {
+            error: "The task was aborted before it completed".to_string(),
+        };
+        // This is a no-op if the task has already finished.
+        self.registry.set_status(self.id, status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_lifecycle() {
+        let registry = TaskRegistry::default();
+
+        let task = registry.create("delete_by_query", "products");
+        let id = task.id();
+        assert_eq!(registry.get(id).unwrap().status, TaskStatus::Queued);
+
+        task.set_progress(5, Some(10));
+        assert_eq!(
+            registry.get(id).unwrap().status,
+            TaskStatus::Running {
+                processed: 5,
+                total: Some(10)
+            },
+        );
+
+        task.succeed(serde_json::json!({"deleted": 10}));
+        let info = registry.get(id).unwrap();
+        assert!(info.status.is_finished());
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            serde_json::json!({
+                "id": id,
+                "kind": "delete_by_query",
+                "index": "products",
+                "status": "succeeded",
+                "result": {"deleted": 10},
+            }),
+        );
+    }
+
+    #[test]
+    fn test_dropped_task_fails() {
+        let registry = TaskRegistry::default();
+
+        let task = registry.create("reindex", "products");
+        let id = task.id();
+        drop(task);
+
+        assert!(matches!(
+            registry.get(id).unwrap().status,
+            TaskStatus::Failed { .. }
+        ));
+        assert_eq!(registry.list("products").len(), 1);
+        assert!(registry.list("other").is_empty());
+    }
+
+    #[test]
+    fn test_wait_for_task() {
+        let registry = TaskRegistry::default();
+
+        let task = registry.create("ingest", "products");
+        let id = task.id();
+        let info = registry.wait(id, Duration::from_millis(10)).unwrap();
+        assert_eq!(info.status, TaskStatus::Queued);
+
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            task.succeed(());
+        });
+
+        let info = registry.wait(id, Duration::from_secs(5)).unwrap();
+        assert!(info.status.is_finished());
+        assert!(registry.wait(id + 1, Duration::from_secs(5)).is_none());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_prune_finished_tasks() {
+        let registry = TaskRegistry::default();
+
+        let running = registry.create("reindex", "products");
+        running.set_progress(0, None);
+        for _ in 0..MAX_FINISHED_TASKS + 5 {
+            registry.create("reindex", "products").succeed(());
+        }
+
+        assert_eq!(registry.list("products").len(), MAX_FINISHED_TASKS + 1);
+        assert!(registry.get(running.id()).is_some());
+        assert!(registry.get(2).is_none());
+    }
+}
@@ -0,0 +1,247 @@
+use std::ops::Bound;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use lnx_document::DateTime;
+use serde::Deserialize;
+use tantivy::query::{Query, RangeQuery};
+use tantivy::schema::{DocumentAccess, FieldType, Schema};
+use tantivy::{IndexWriter, Searcher};
+
+use crate::delete_by_query::{delete_by_query, DeleteByQueryResult};
+use crate::error::IngestError;
+
+#[derive(Debug, Clone, Deserialize)]
+/// The per-index document expiry settings.
+pub struct TtlPolicy {
+    /// The `datetime` field holding the time each document expires at.
+    ///
+    /// Documents without a value for the field never expire.
+    pub field: String,
+    #[serde(default = "default_reap_interval_secs")]
+    /// How often (in seconds) expired documents are deleted.
+    pub reap_interval_secs: u64,
+}
+
+fn default_reap_interval_secs() -> u64 {
+    60
+}
+
+impl TtlPolicy {
+    #[inline]
+    /// The interval expired documents are deleted at.
+    pub fn reap_interval(&self) -> Duration {
+        Duration::from_secs(self.reap_interval_secs.max(1))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Deletes documents whose expiry timestamp has passed.
+pub struct ExpiryReaper {
+    policy: TtlPolicy,
+}
+
+impl ExpiryReaper {
+    /// Creates a new reaper for the given policy.
+    ///
+    /// The expiry field must be a fast `datetime` field.
+    pub fn from_schema(schema: &Schema, policy: TtlPolicy) -> Result<Self, IngestError> {
+        let invalid = |reason: &str| IngestError::InvalidTtlField {
+            field: policy.field.clone(),
+            reason: reason.to_string(),
+        };
+
+        let field = schema
+            .get_field(&policy.field)
+            .map_err(|_| invalid("the field does not exist"))?;
+        let entry = schema.get_field_entry(field);
+
+        if !matches!(entry.field_type(), FieldType::Date(_)) {
+            return Err(invalid("only datetime fields can hold an expiry timestamp"));
+        }
+
+        if !entry.is_fast() {
+            return Err(invalid("the field is not a fast field"));
+        }
+
+        Ok(Self { policy })
+    }
+
+    #[inline]
+    /// The expiry settings of the index.
+    pub fn policy(&self) -> &TtlPolicy {
+        &self.policy
+    }
+
+    /// Builds a query matching every document which has expired as of `now`.
+    pub fn expired_query(&self, now: DateTime) -> Box<dyn Query> {
+        let query = RangeQuery::new_date_bounds(
+            self.policy.field.clone(),
+            Bound::Unbounded,
+            Bound::Included(now.as_tantivy_value()),
+        );
+        Box::new(query)
+    }
+
+    /// Deletes every document which has expired as of `now`.
+    ///
+    /// The documents are removed once the writer is next committed.
+    pub fn reap<D: DocumentAccess>(
+        &self,
+        writer: &IndexWriter<D>,
+        searcher: &Searcher,
+        now: DateTime,
+    ) -> Result<DeleteByQueryResult, IngestError> {
+        delete_by_query(writer, searcher, self.expired_query(now))
+    }
+}
+
+/// A handle to a running background reaper, the reaper is stopped once the handle is dropped.
+pub struct ReaperHandle {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for ReaperHandle {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the reaper and tells it to stop.
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Spawns a background thread which calls `reap` every `interval` until the handle is dropped.
+///
+/// Errors returned by `reap` are logged and the reaper retries on the next tick.
+pub fn spawn_expiry_reaper(
+    name: impl Into<String>,
+    interval: Duration,
+    mut reap: impl FnMut() -> Result<DeleteByQueryResult, IngestError> + Send + 'static,
+) -> Result<ReaperHandle, IngestError> {
+    let name = name.into();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let thread = std::thread::Builder::new()
+        .name(format!("lnx-ttl-reaper-{name}"))
+        .spawn(move || loop {
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {},
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            match reap() {
+                Ok(result) if result.deleted > 0 => {
+                    tracing::info!(
+                        index = %name,
+                        deleted = result.deleted,
+                        "Deleted expired documents",
+                    );
+                },
+                Ok(_) => {},
+                Err(e) => {
+                    tracing::error!(
+                        index = %name,
+                        error = ?e,
+                        "Failed to delete expired documents",
+                    );
+                },
+            }
+        })?;
+
+    Ok(ReaperHandle {
+        stop: Some(stop),
+        thread: Some(thread),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tantivy::collector::Count;
+    use tantivy::schema::{SchemaBuilder, FAST, INDEXED};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    fn policy(field: &str) -> TtlPolicy {
+        TtlPolicy {
+            field: field.to_string(),
+            reap_interval_secs: 60,
+        }
+    }
+
+    #[test]
+    fn test_reaper_from_schema() {
+        let mut schema = SchemaBuilder::new();
+        schema.add_date_field("expires_at", INDEXED | FAST);
+        schema.add_date_field("created_at", INDEXED);
+        schema.add_u64_field("ttl", INDEXED | FAST);
+        let schema = schema.build();
+
+        assert!(ExpiryReaper::from_schema(&schema, policy("expires_at")).is_ok());
+        assert!(ExpiryReaper::from_schema(&schema, policy("created_at")).is_err());
+        assert!(ExpiryReaper::from_schema(&schema, policy("ttl")).is_err());
+        assert!(ExpiryReaper::from_schema(&schema, policy("missing")).is_err());
+    }
+
+    #[test]
+    fn test_reap_expired_documents() {
+        let mut schema = SchemaBuilder::new();
+        let expires_at = schema.add_date_field("expires_at", INDEXED | FAST);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for secs in [10, 20, 30] {
+            let ts = DateTime::from_secs(secs).unwrap();
+            writer
+                .add_document(doc!(expires_at => ts.as_tantivy_value()))
+                .unwrap();
+        }
+        writer.add_document(doc!()).unwrap();
+        writer.commit().unwrap();
+
+        let reaper =
+            ExpiryReaper::from_schema(&index.schema(), policy("expires_at")).unwrap();
+        let reader = index.reader().unwrap();
+
+        let now = DateTime::from_secs(20).unwrap();
+        let result = reaper.reap(&writer, &reader.searcher(), now).unwrap();
+        assert_eq!(result.deleted, 2);
+
+        writer.commit().unwrap();
+        reader.reload().unwrap();
+        let remaining = reader
+            .searcher()
+            .search(&tantivy::query::AllQuery, &Count)
+            .unwrap();
+        assert_eq!(remaining, 2);
+    }
+
+    #[test]
+    fn test_reaper_stops_on_drop() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+
+        let handle = spawn_expiry_reaper("test", Duration::from_millis(5), move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Ok(DeleteByQueryResult {
+                deleted: 0,
+                opstamp: 0,
+            })
+        })
+        .unwrap();
+
+        std::thread::sleep(Duration::from_millis(50));
+        drop(handle);
+
+        let num_calls = calls.load(Ordering::Relaxed);
+        assert!(num_calls > 0);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(calls.load(Ordering::Relaxed), num_calls);
+    }
+}
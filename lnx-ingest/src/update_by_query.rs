@@ -0,0 +1,345 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use lnx_document::{DynamicDocument, KeyValues, UserDisplayType, Value};
+use serde::{Deserialize, Serialize};
+use tantivy::collector::DocSetCollector;
+use tantivy::query::Query;
+use tantivy::{DocAddress, Searcher};
+
+use crate::error::IngestError;
+use crate::tasks::TaskHandle;
+
+/// The default number of documents re-indexed per batch.
+pub const DEFAULT_UPDATE_BATCH_SIZE: usize = 1_000;
+
+#[derive(Debug, Deserialize)]
+/// A request to update every document matching a query.
+pub struct UpdateByQueryRequest<'a> {
+    #[serde(borrow)]
+    /// The query matching the documents to update, this is the same DSL used by search.
+    pub query: lnx_query::QueryKind<'a>,
+    #[serde(borrow)]
+    /// The updates applied to each matching document, in order.
+    pub updates: Vec<FieldUpdate<'a>>,
+    #[serde(default = "default_batch_size")]
+    /// The number of documents re-indexed per batch.
+    pub batch_size: usize,
+}
+
+fn default_batch_size() -> usize {
+    DEFAULT_UPDATE_BATCH_SIZE
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+/// A declarative update applied to a single field of a document.
+///
+/// Fields within objects can be targeted with a dotted path, i.e. `meta.views`.
+pub enum FieldUpdate<'a> {
+    /// Sets the field to the given value, replacing any existing value.
+    Set {
+        field: String,
+        #[serde(borrow)]
+        value: Value<'a>,
+    },
+    /// Increments a numeric field by the given amount, a missing field is set to the amount.
+    Increment {
+        field: String,
+        #[serde(borrow)]
+        by: Value<'a>,
+    },
+    /// Removes the field from the document.
+    Remove { field: String },
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+/// The outcome of an update-by-query operation.
+pub struct UpdateByQueryResult {
+    /// The number of documents which matched the query.
+    pub matched: u64,
+    /// The number of documents which were updated and re-indexed.
+    pub updated: u64,
+    /// The number of documents which could not be updated.
+    pub failed: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// The reasons documents could not be updated.
+    pub errors: Vec<String>,
+}
+
+/// Applies a set of updates to a document, in order.
+pub fn apply_updates<'a>(
+    document: &mut DynamicDocument<'a>,
+    updates: &[FieldUpdate<'a>],
+) -> Result<(), String> {
+    for update in updates {
+        match update {
+            FieldUpdate::Set { field, value } => {
+                let (object, key) = resolve_parent(&mut document.0, field, true)?;
+                set_value(object, key, value.clone());
+            },
+            FieldUpdate::Increment { field, by } => {
+                let (object, key) = resolve_parent(&mut document.0, field, true)?;
+                let zero = Value::U64(0);
+                let existing = object
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map_or(&zero, |(_, value)| value);
+                let incremented = increment(existing, by)
+                    .map_err(|e| format!("Cannot increment field {field:?}: {e}"))?;
+                set_value(object, key, incremented);
+            },
+            FieldUpdate::Remove { field } => {
+                if let Ok((object, key)) = resolve_parent(&mut document.0, field, false)
+                {
+                    object.retain(|(k, _)| k != key);
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the object containing the last key of a dotted path.
+///
+/// Missing intermediate objects are created if `create` is `true`.
+fn resolve_parent<'a, 'p, 'doc>(
+    object: &'doc mut KeyValues<'a>,
+    path: &'p str,
+    create: bool,
+) -> Result<(&'doc mut KeyValues<'a>, &'p str), String> {
+    let Some((head, rest)) = path.split_once('.') else {
+        return Ok((object, path));
+    };
+
+    let pos = match object.iter().position(|(k, _)| k == head) {
+        Some(pos) => pos,
+        None if create => {
+            object.push((Cow::Owned(head.to_string()), Value::Object(Vec::new())));
+            object.len() - 1
+        },
+        None => return Err(format!("Field {head:?} does not exist")),
+    };
+
+    match &mut object[pos].1 {
+        Value::Object(inner) => resolve_parent(inner, rest, create),
+        other => Err(format!(
+            "Field {head:?} is of type {} not an object",
+            other.type_name()
+        )),
+    }
+}
+
+/// Sets the value of a key in place, removing any duplicate entries of the key.
+fn set_value<'a>(object: &mut KeyValues<'a>, key: &str, value: Value<'a>) {
+    let Some(pos) = object.iter().position(|(k, _)| k == key) else {
+        object.push((Cow::Owned(key.to_string()), value));
+        return;
+    };
+
+    object[pos].1 = value;
+
+    let mut idx = 0;
+    object.retain(|(k, _)| {
+        let keep = idx <= pos || k != key;
+        idx += 1;
+        keep
+    });
+}
+
+/// Adds two numeric values together, preserving integer types where possible.
+fn increment<'a>(value: &Value<'a>, by: &Value<'a>) -> Result<Value<'a>, String> {
+    let overflow = || "the result overflows".to_string();
+
+    let result = match (value, by) {
+        (Value::U64(a), Value::U64(b)) => {
+            Value::U64(a.checked_add(*b).ok_or_else(overflow)?)
+        },
+        (Value::I64(a), Value::I64(b)) => {
+            Value::I64(a.checked_add(*b).ok_or_else(overflow)?)
+        },
+        (Value::U64(a), Value::I64(b)) | (Value::I64(b), Value::U64(a)) => {
+            let a = i64::try_from(*a).map_err(|_| overflow())?;
+            Value::I64(a.checked_add(*b).ok_or_else(overflow)?)
+        },
+        (Value::F64(a), Value::F64(b)) => Value::F64(a + b),
+        (Value::F64(a), Value::U64(b)) | (Value::U64(b), Value::F64(a)) => {
+            Value::F64(a + *b as f64)
+        },
+        (Value::F64(a), Value::I64(b)) | (Value::I64(b), Value::F64(a)) => {
+            Value::F64(a + *b as f64)
+        },
+        (value, by) => {
+            return Err(format!(
+                "cannot add a {} to a {}",
+                by.type_name(),
+                value.type_name()
+            ))
+        },
+    };
+
+    Ok(result)
+}
+
+/// Updates every document matching the query, re-indexing them in batches.
+///
+/// Each matching document is fetched via `load`, the updates are applied and
+/// the updated documents are passed to the `writer` in batches of `batch_size`.
+/// The writer is expected to upsert each document on its primary key so the
+/// original document is replaced.
+///
+/// Documents which cannot be loaded or updated, or batches rejected by the writer,
+/// are recorded as failures without stopping the rest of the documents from being updated.
+pub fn update_by_query<'a, E: Display>(
+    searcher: &Searcher,
+    query: &dyn Query,
+    updates: &[FieldUpdate<'a>],
+    batch_size: usize,
+    task: Option<&TaskHandle>,
+    load: impl FnMut(DocAddress) -> Result<DynamicDocument<'a>, String>,
+    writer: impl FnMut(Vec<DynamicDocument<'a>>) -> Result<(), E>,
+) -> Result<UpdateByQueryResult, IngestError> {
+    let transform = |mut document: DynamicDocument<'a>| {
+        apply_updates(&mut document, updates)?;
+        Ok(document)
+    };
+
+    rewrite_matching(
+        searcher, query, batch_size, None, task, load, transform, writer,
+    )
+}
+
+/// Loads every document matching the query, transforms it and passes
+/// the transformed documents to the writer in batches.
+///
+/// If `max_docs_per_second` is set, the rewrite is throttled to that rate.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn rewrite_matching<'a, E: Display>(
+    searcher: &Searcher,
+    query: &dyn Query,
+    batch_size: usize,
+    max_docs_per_second: Option<f64>,
+    task: Option<&TaskHandle>,
+    mut load: impl FnMut(DocAddress) -> Result<DynamicDocument<'a>, String>,
+    mut transform: impl FnMut(DynamicDocument<'a>) -> Result<DynamicDocument<'a>, String>,
+    mut writer: impl FnMut(Vec<DynamicDocument<'a>>) -> Result<(), E>,
+) -> Result<UpdateByQueryResult, IngestError> {
+    let mut addresses = searcher
+        .search(query, &DocSetCollector)?
+        .into_iter()
+        .collect::<Vec<_>>();
+    addresses.sort_unstable();
+
+    let total = addresses.len() as u64;
+    let mut result = UpdateByQueryResult {
+        matched: total,
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    for chunk in addresses.chunks(batch_size.max(1)) {
+        let mut batch = Vec::with_capacity(chunk.len());
+        for address in chunk {
+            match load(*address).and_then(&mut transform) {
+                Ok(document) => batch.push(document),
+                Err(e) => {
+                    result.failed += 1;
+                    result.errors.push(e);
+                },
+            }
+        }
+
+        let num_docs = batch.len() as u64;
+        match writer(batch) {
+            Ok(()) => result.updated += num_docs,
+            Err(e) => {
+                result.failed += num_docs;
+                result.errors.push(e.to_string());
+            },
+        }
+
+        let processed = result.updated + result.failed;
+        if let Some(task) = task {
+            task.set_progress(processed, Some(total));
+        }
+
+        if let Some(rate) = max_docs_per_second.filter(|rate| *rate > 0.0) {
+            let target = Duration::from_secs_f64(processed as f64 / rate);
+            if let Some(delay) = target.checked_sub(start.elapsed()) {
+                std::thread::sleep(delay);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(document: &str, updates: &str) -> Result<String, String> {
+        let mut document: DynamicDocument = serde_json::from_str(document).unwrap();
+        let updates: Vec<FieldUpdate> = serde_json::from_str(updates).unwrap();
+        apply_updates(&mut document, &updates)?;
+        Ok(format!("{:?}", document.0))
+    }
+
+    fn doc(document: &str) -> String {
+        let document: DynamicDocument = serde_json::from_str(document).unwrap();
+        format!("{:?}", document.0)
+    }
+
+    #[test]
+    fn test_set_and_remove() {
+        let updated = update(
+            r#"{"title": "Hello", "status": "draft", "meta": {"author": "bob"}}"#,
+            r#"[
+                {"op": "set", "field": "status", "value": "published"},
+                {"op": "remove", "field": "meta.author"},
+                {"op": "set", "field": "meta.tags.primary", "value": "news"},
+                {"op": "remove", "field": "missing.field"}
+            ]"#,
+        );
+        assert_eq!(
+            updated.unwrap(),
+            doc(
+                r#"{"title": "Hello", "status": "published", "meta": {"tags": {"primary": "news"}}}"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_increment() {
+        let updated = update(
+            r#"{"views": 10, "score": 1.5, "balance": 5}"#,
+            r#"[
+                {"op": "increment", "field": "views", "by": 1},
+                {"op": "increment", "field": "score", "by": 1},
+                {"op": "increment", "field": "balance", "by": -10},
+                {"op": "increment", "field": "likes", "by": 3}
+            ]"#,
+        );
+        assert_eq!(
+            updated.unwrap(),
+            doc(r#"{"views": 11, "score": 2.5, "balance": -5, "likes": 3}"#),
+        );
+    }
+
+    #[test]
+    fn test_increment_invalid() {
+        let updated = update(
+            r#"{"title": "Hello"}"#,
+            r#"[{"op": "increment", "field": "title", "by": 1}]"#,
+        );
+        assert!(updated.is_err());
+
+        let updated = update(
+            r#"{"title": "Hello"}"#,
+            r#"[{"op": "set", "field": "title.inner", "value": 1}]"#,
+        );
+        assert!(updated.is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use lnx_document::{DynamicDocument, UserDisplayType, Value};
+use lnx_transforms::{TypeCast, NESTED_PARENT_FIELD};
+use tantivy::indexer::UserOperation;
+use tantivy::schema::{DocumentAccess, Field, FieldType, Schema};
+use tantivy::Term;
+
+use crate::error::IngestError;
+
+#[derive(Debug, Clone)]
+/// The primary key of an index.
+///
+/// Adding a document with the same key as an existing document replaces
+/// the existing document rather than creating a duplicate.
+pub struct PrimaryKey {
+    name: String,
+    field: Field,
+    cast: PrimaryKeyType,
+    /// The hidden field holding the key of a nested child document's parent.
+    parent_field: Option<Field>,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum PrimaryKeyType {
+    Str,
+    U64,
+    I64,
+}
+
+impl PrimaryKey {
+    /// Creates a new primary key from a field within the schema.
+    ///
+    /// The field must be an indexed `string`, `u64` or `i64` field, string keys should
+    /// use the `raw` tokenizer so the key is matched exactly.
+    pub fn from_schema(schema: &Schema, name: &str) -> Result<Self, IngestError> {
+        let invalid = |reason: &str| IngestError::InvalidPrimaryKey {
+            field: name.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let field = schema
+            .get_field(name)
+            .map_err(|_| invalid("the field does not exist"))?;
+        let entry = schema.get_field_entry(field);
+
+        if !entry.is_indexed() {
+            return Err(invalid("the field is not indexed"));
+        }
+
+        let cast = match entry.field_type() {
+            FieldType::Str(_) => PrimaryKeyType::Str,
+            FieldType::U64(_) => PrimaryKeyType::U64,
+            FieldType::I64(_) => PrimaryKeyType::I64,
+            _ => return Err(invalid("only string, u64 and i64 fields can be used")),
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            field,
+            cast,
+            parent_field: schema.get_field(NESTED_PARENT_FIELD).ok(),
+        })
+    }
+
+    #[inline]
+    /// The name of the primary key field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    /// The primary key field.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Extracts the primary key of the document as a term.
+    pub fn term(&self, document: &DynamicDocument) -> Result<Term, String> {
+        let value = document
+            .0
+            .iter()
+            .find(|(key, _)| key == &self.name)
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| {
+                format!("Document is missing the primary key {:?}", self.name)
+            })?;
+
+        self.term_for_value(value)
+    }
+
+    /// Casts a primary key provided as JSON, i.e. within a request, to a term.
+    pub fn term_for_json(&self, value: &serde_json::Value) -> Result<Term, String> {
+        let value = match value {
+            serde_json::Value::String(id) => Value::Str(id.as_str().into()),
+            serde_json::Value::Number(id) => match (id.as_u64(), id.as_i64()) {
+                (Some(id), _) => Value::U64(id),
+                (None, Some(id)) => Value::I64(id),
+                _ => return Err(format!("Invalid primary key {id}")),
+            },
+            other => return Err(format!("Invalid primary key {other}")),
+        };
+
+        self.term_for_value(value)
+    }
+
+    /// Casts a primary key value to a term.
+    pub fn term_for_value(&self, value: Value) -> Result<Term, String> {
+        let cast = match self.cast {
+            PrimaryKeyType::Str => TypeCast::String,
+            PrimaryKeyType::U64 => TypeCast::U64,
+            PrimaryKeyType::I64 => TypeCast::I64,
+        };
+
+        let term = match cast.try_cast_value(value) {
+            Ok(Value::Str(key)) => Term::from_field_text(self.field, &key),
+            Ok(Value::U64(key)) => Term::from_field_u64(self.field, key),
+            Ok(Value::I64(key)) => Term::from_field_i64(self.field, key),
+            Ok(Value::Null) | Err(_) => {
+                return Err(format!(
+                    "Invalid primary key {:?}, expected a single `{}` value",
+                    self.name,
+                    cast.type_name(),
+                ))
+            },
+            Ok(_) => unreachable!(),
+        };
+
+        Ok(term)
+    }
+
+    /// The parent key recorded on the nested child documents of the document with
+    /// the given key, see [lnx_transforms::split_nested_documents].
+    pub fn nested_parent_key(&self, key: &Term) -> Option<String> {
+        match self.cast {
+            PrimaryKeyType::Str => key.as_str().map(ToString::to_string),
+            PrimaryKeyType::U64 => key.as_u64().map(|key| key.to_string()),
+            PrimaryKeyType::I64 => key.as_i64().map(|key| key.to_string()),
+        }
+    }
+
+    /// Produces the writer operations to delete the document with the given key.
+    ///
+    /// If the index has nested documents the children of the document are deleted
+    /// along with it, otherwise they would be joined onto the next parent in the
+    /// segment once the deleted parent is merged away.
+    pub fn delete_operations<D: DocumentAccess>(
+        &self,
+        key: Term,
+    ) -> Vec<UserOperation<D>> {
+        let parent = self.parent_field.and_then(|field| {
+            let parent_key = self.nested_parent_key(&key)?;
+            Some(Term::from_field_text(field, &parent_key))
+        });
+
+        let mut operations = vec![UserOperation::Delete(key)];
+        operations.extend(parent.map(UserOperation::Delete));
+        operations
+    }
+}
+
+/// Produces the writer operations to upsert a set of documents by their primary key.
+///
+/// Each document is preceded by a delete of its key (and of its nested children), the
+/// operations must be submitted to the writer as a single group (`IndexWriter::run`)
+/// so the delete and add become visible in the same commit. If a key appears multiple
+/// times the last document wins.
+pub fn upsert_operations<D: DocumentAccess>(
+    key: &PrimaryKey,
+    documents: impl IntoIterator<Item = (Term, D)>,
+) -> Vec<UserOperation<D>> {
+    let documents = documents.into_iter();
+    let mut operations = Vec::with_capacity(documents.size_hint().0 * 2);
+
+    for (term, document) in documents {
+        operations.extend(key.delete_operations(term));
+        operations.push(UserOperation::Add(document));
+    }
+
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use lnx_query::{QueryContext, SearchRequest};
+    use lnx_transforms::{NESTED_CHILD_FIELD, NESTED_PATH_FIELD};
+    use tantivy::collector::Count;
+    use tantivy::schema::{SchemaBuilder, FAST, INDEXED, STRING, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    fn test_schema() -> Schema {
+        let mut schema = SchemaBuilder::new();
+        schema.add_text_field("id", STRING);
+        schema.add_u64_field("num_id", INDEXED | FAST);
+        schema.add_f64_field("score", INDEXED);
+        schema.add_text_field("title", TEXT);
+        schema.build()
+    }
+
+    #[test]
+    fn test_primary_key_from_schema() {
+        let schema = test_schema();
+        assert!(PrimaryKey::from_schema(&schema, "id").is_ok());
+        assert!(PrimaryKey::from_schema(&schema, "num_id").is_ok());
+        assert!(matches!(
+            PrimaryKey::from_schema(&schema, "score"),
+            Err(IngestError::InvalidPrimaryKey { .. })
+        ));
+        assert!(PrimaryKey::from_schema(&schema, "missing").is_err());
+    }
+
+    #[test]
+    fn test_primary_key_term() {
+        let schema = test_schema();
+        let key = PrimaryKey::from_schema(&schema, "num_id").unwrap();
+
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"num_id": "42", "title": "Hello"}"#).unwrap();
+        assert_eq!(
+            key.term(&document).unwrap(),
+            Term::from_field_u64(key.field(), 42)
+        );
+
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"title": "Hello"}"#).unwrap();
+        assert!(key.term(&document).is_err());
+
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"num_id": -1}"#).unwrap();
+        assert!(key.term(&document).is_err());
+    }
+
+    #[test]
+    fn test_upsert_operations() {
+        let schema = test_schema();
+        let title = schema.get_field("title").unwrap();
+        let key = PrimaryKey::from_schema(&schema, "num_id").unwrap();
+        let operations = upsert_operations(
+            &key,
+            [
+                (Term::from_field_u64(key.field(), 1), doc!(title => "a")),
+                (Term::from_field_u64(key.field(), 1), doc!(title => "b")),
+            ],
+        );
+
+        assert_eq!(operations.len(), 4);
+        assert!(matches!(operations[0], UserOperation::Delete(_)));
+        assert!(matches!(
+            &operations[3],
+            UserOperation::Add(document) if document == &doc!(title => "b")
+        ));
+    }
+
+    #[test]
+    fn test_delete_nested_block() {
+        let mut schema = SchemaBuilder::new();
+        let id = schema.add_u64_field("id", INDEXED);
+        let author = schema.add_text_field("comments.author", STRING);
+        let path = schema.add_text_field(NESTED_PATH_FIELD, STRING);
+        let child = schema.add_u64_field(NESTED_CHILD_FIELD, FAST | INDEXED);
+        let parent = schema.add_text_field(NESTED_PARENT_FIELD, STRING);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for (parent_id, comments) in [(1u64, &["bob", "tim"][..]), (2, &["alice"])] {
+            for comment_author in comments {
+                writer
+                    .add_document(doc!(
+                        path => "comments",
+                        child => 1u64,
+                        parent => parent_id.to_string(),
+                        author => *comment_author,
+                    ))
+                    .unwrap();
+            }
+            writer.add_document(doc!(id => parent_id)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let key = PrimaryKey::from_schema(&index.schema(), "id").unwrap();
+        let operations = key.delete_operations(Term::from_field_u64(id, 1));
+        assert_eq!(operations.len(), 2);
+        writer.run(operations).unwrap();
+        writer.commit().unwrap();
+
+        // Merging removes the deleted parent, any of its children which were left
+        // behind would now be joined onto the next parent in the segment.
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        writer.merge(&segment_ids).wait().unwrap();
+
+        let reader = index.reader().unwrap();
+        reader.reload().unwrap();
+        let searcher = reader.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+
+        let ctx = QueryContext::new(index.schema());
+        let count = |comment_author: &str| {
+            let json = format!(
+                r#"{{"query": {{"nested": {{
+                    "path": "comments",
+                    "query": {{"term": {{"field": "comments.author", "value": "{comment_author}"}}}}
+                }}}}}}"#
+            );
+            let request: SearchRequest = serde_json::from_str(&json).unwrap();
+            let query = request.build_query(&ctx).unwrap();
+            searcher.search(&query, &Count).unwrap()
+        };
+        assert_eq!(count("bob"), 0);
+        assert_eq!(count("alice"), 1);
+    }
+}
@@ -0,0 +1,132 @@
+use lnx_metastore::Metastore;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tantivy::Term;
+
+use crate::error::IngestError;
+
+const VERSIONS_DATABASE: &str = "lnx_document_versions";
+
+/// The version of a document, incremented on every write to its primary key.
+///
+/// A version of `0` means the key has never been written to.
+pub type Version = u64;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+/// The precondition of a write, used for optimistic concurrency control.
+pub struct VersionPrecondition {
+    #[serde(default, alias = "if_seq_no")]
+    /// The write is only applied if the document is currently at this version,
+    /// `0` only applies the write if the document does not exist yet.
+    pub if_version: Option<Version>,
+}
+
+/// Tracks a monotonically increasing version per primary key so writes can
+/// be made conditional on the version of the document they are replacing.
+///
+/// Versions are persisted in the metastore so they survive restarts, the version
+/// is incremented when the write is accepted, so a write which later fails leaves
+/// a gap in the versions rather than reusing one.
+pub struct VersionStore {
+    metastore: Metastore,
+    lock: Mutex<()>,
+}
+
+impl VersionStore {
+    /// Opens the version store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(VERSIONS_DATABASE)?;
+        Ok(Self {
+            metastore,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Gets the current version of a document.
+    pub fn current(&self, index: &str, key: &Term) -> Result<Version, IngestError> {
+        let key = version_key(index, key);
+        let version = self.metastore.get::<_, Version>(&key.as_slice())?;
+        Ok(version.unwrap_or(0))
+    }
+
+    /// Checks the precondition against the current version of the document,
+    /// incrementing the version if the write can go ahead.
+    ///
+    /// Returns the new version of the document or a conflict if the precondition failed.
+    pub fn check_and_increment(
+        &self,
+        index: &str,
+        key: &Term,
+        precondition: VersionPrecondition,
+    ) -> Result<Version, IngestError> {
+        let key = version_key(index, key);
+
+        let _guard = self.lock.lock();
+        let current = self
+            .metastore
+            .get::<_, Version>(&key.as_slice())?
+            .unwrap_or(0);
+        let next = check_precondition(current, precondition)?;
+        self.metastore.put(&key.as_slice(), &next)?;
+
+        Ok(next)
+    }
+}
+
+/// Checks the precondition of a write against the current version, returning the next version.
+fn check_precondition(
+    current: Version,
+    precondition: VersionPrecondition,
+) -> Result<Version, IngestError> {
+    if let Some(expected) = precondition.if_version {
+        if expected != current {
+            return Err(IngestError::VersionConflict { expected, current });
+        }
+    }
+
+    Ok(current + 1)
+}
+
+fn version_key(index: &str, key: &Term) -> Vec<u8> {
+    let term = key.serialized_term();
+    let mut buffer = Vec::with_capacity(index.len() + 1 + term.len());
+    buffer.extend_from_slice(index.as_bytes());
+    buffer.push(0);
+    buffer.extend_from_slice(term);
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn precondition(if_version: Option<Version>) -> VersionPrecondition {
+        VersionPrecondition { if_version }
+    }
+
+    #[test]
+    fn test_check_precondition() {
+        assert_eq!(check_precondition(0, precondition(None)).unwrap(), 1);
+        assert_eq!(check_precondition(0, precondition(Some(0))).unwrap(), 1);
+        assert_eq!(check_precondition(4, precondition(Some(4))).unwrap(), 5);
+
+        assert!(matches!(
+            check_precondition(5, precondition(Some(4))),
+            Err(IngestError::VersionConflict {
+                expected: 4,
+                current: 5
+            })
+        ));
+        assert!(check_precondition(3, precondition(Some(0))).is_err());
+    }
+
+    #[test]
+    fn test_parse_precondition() {
+        let parsed: VersionPrecondition =
+            serde_json::from_str(r#"{"if_seq_no": 3}"#).unwrap();
+        assert_eq!(parsed, precondition(Some(3)));
+
+        let parsed: VersionPrecondition = serde_json::from_str("{}").unwrap();
+        assert_eq!(parsed, precondition(None));
+    }
+}
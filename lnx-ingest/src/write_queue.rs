@@ -0,0 +1,239 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::error::IngestError;
+use crate::tasks::{TaskHandle, TaskId, TaskRegistry};
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+/// The per-index write queue settings.
+pub struct WriteQueueConfig {
+    #[serde(default = "default_max_queued_bytes")]
+    /// The maximum size (in bytes) of the writes held in the queue.
+    ///
+    /// Writes submitted once the queue is full are rejected.
+    pub max_queued_bytes: usize,
+    #[serde(default = "default_saturation_bytes")]
+    /// The size (in bytes) of the writes held in the queue at which it is considered saturated.
+    pub saturation_bytes: usize,
+}
+
+impl Default for WriteQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_queued_bytes: default_max_queued_bytes(),
+            saturation_bytes: default_saturation_bytes(),
+        }
+    }
+}
+
+fn default_max_queued_bytes() -> usize {
+    256 << 20
+}
+
+fn default_saturation_bytes() -> usize {
+    32 << 20
+}
+
+/// A write waiting in the queue to be applied by the indexer.
+///
+/// The memory reserved by the write is released once it is dropped.
+pub struct QueuedWrite<T> {
+    /// The write to apply.
+    pub payload: T,
+    /// The task tracking the write, the indexer reports the outcome of the write to it.
+    pub task: TaskHandle,
+    _reservation: Reservation,
+}
+
+struct Reservation {
+    queued_bytes: Arc<AtomicUsize>,
+    size: usize,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.queued_bytes.fetch_sub(self.size, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A write which was accepted by the queue.
+pub struct SubmittedWrite {
+    /// The ID of the task tracking the write.
+    pub task_id: TaskId,
+    /// If the queue is saturated, in which case the caller should return
+    /// the task ID (`202 Accepted`) rather than waiting for the write to complete.
+    pub saturated: bool,
+}
+
+#[derive(Clone)]
+/// A per-index queue of writes with bounded memory.
+///
+/// Requests submit their writes to the queue rather than writing to the index
+/// directly, so a slow disk applies backpressure to writers instead of stalling
+/// the HTTP workers. Each write is tracked by a task which can be polled via its ID.
+pub struct WriteQueue<T> {
+    index: String,
+    config: WriteQueueConfig,
+    tasks: TaskRegistry,
+    queued_bytes: Arc<AtomicUsize>,
+    tx: flume::Sender<QueuedWrite<T>>,
+}
+
+impl<T> WriteQueue<T> {
+    /// Creates a new queue for the index, returning the queue and the receiver
+    /// the indexer consumes writes from.
+    pub fn new(
+        index: impl Into<String>,
+        config: WriteQueueConfig,
+        tasks: TaskRegistry,
+    ) -> (Self, WriteQueueReceiver<T>) {
+        let (tx, rx) = flume::unbounded();
+        let queue = Self {
+            index: index.into(),
+            config,
+            tasks,
+            queued_bytes: Arc::new(AtomicUsize::new(0)),
+            tx,
+        };
+
+        (queue, WriteQueueReceiver { rx })
+    }
+
+    #[inline]
+    /// The size (in bytes) of the writes currently held in the queue.
+    pub fn queued_bytes(&self) -> usize {
+        self.queued_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Submits a write of the given size (in bytes) to the queue.
+    ///
+    /// Returns an error if the queue does not have space for the write.
+    pub fn submit(
+        &self,
+        payload: T,
+        size: usize,
+    ) -> Result<SubmittedWrite, IngestError> {
+        let max = self.config.max_queued_bytes;
+        if size > max {
+            return Err(IngestError::InvalidRequest(format!(
+                "Write of {size} bytes exceeds the maximum queue size of {max} bytes"
+            )));
+        }
+
+        let queued = self
+            .queued_bytes
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |queued| {
+                queued.checked_add(size).filter(|total| *total <= max)
+            })
+            .map_err(|queued| IngestError::QueueFull {
+                queued_bytes: queued,
+                max_queued_bytes: max,
+            })?;
+
+        let reservation = Reservation {
+            queued_bytes: self.queued_bytes.clone(),
+            size,
+        };
+        let task = self.tasks.create("ingest", self.index.clone());
+        let task_id = task.id();
+
+        let write = QueuedWrite {
+            payload,
+            task,
+            _reservation: reservation,
+        };
+        if self.tx.send(write).is_err() {
+            return Err(IngestError::QueueClosed);
+        }
+
+        Ok(SubmittedWrite {
+            task_id,
+            saturated: queued + size > self.config.saturation_bytes,
+        })
+    }
+}
+
+/// The receiving end of a write queue, consumed by the index's indexer.
+pub struct WriteQueueReceiver<T> {
+    rx: flume::Receiver<QueuedWrite<T>>,
+}
+
+impl<T> WriteQueueReceiver<T> {
+    /// Waits for the next write in the queue.
+    ///
+    /// Returns `None` once every handle to the queue has been dropped.
+    pub fn recv(&self) -> Option<QueuedWrite<T>> {
+        self.rx.recv().ok()
+    }
+
+    /// Gets the next write in the queue if one is available.
+    pub fn try_recv(&self) -> Option<QueuedWrite<T>> {
+        self.rx.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskStatus;
+
+    fn config() -> WriteQueueConfig {
+        WriteQueueConfig {
+            max_queued_bytes: 100,
+            saturation_bytes: 50,
+        }
+    }
+
+    #[test]
+    fn test_write_queue_backpressure() {
+        let tasks = TaskRegistry::default();
+        let (queue, receiver) = WriteQueue::new("products", config(), tasks.clone());
+
+        let first = queue.submit("a", 40).unwrap();
+        assert!(!first.saturated);
+        let second = queue.submit("b", 40).unwrap();
+        assert!(second.saturated);
+        assert_eq!(queue.queued_bytes(), 80);
+
+        assert!(matches!(
+            queue.submit("c", 40),
+            Err(IngestError::QueueFull { .. })
+        ));
+        assert!(matches!(
+            queue.submit("d", 101),
+            Err(IngestError::InvalidRequest(_))
+        ));
+
+        let QueuedWrite {
+            payload,
+            task,
+            _reservation,
+        } = receiver.recv().unwrap();
+        assert_eq!(payload, "a");
+        task.succeed(());
+        drop(_reservation);
+        assert_eq!(queue.queued_bytes(), 40);
+        assert!(matches!(
+            tasks.get(first.task_id).unwrap().status,
+            TaskStatus::Succeeded { .. }
+        ));
+
+        assert!(queue.submit("c", 40).is_ok());
+    }
+
+    #[test]
+    fn test_closed_queue() {
+        let (queue, receiver) =
+            WriteQueue::new("products", config(), TaskRegistry::default());
+        drop(receiver);
+
+        assert!(matches!(
+            queue.submit("a", 1),
+            Err(IngestError::QueueClosed)
+        ));
+        assert_eq!(queue.queued_bytes(), 0);
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashSet;
+
+use lnx_tools::consistent_hash;
+use serde::{Deserialize, Serialize};
+use tantivy::{DocId, SegmentReader};
+
+use super::column::resolve_column;
+use super::AggregationKey;
+use crate::column::{ColumnType, FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+/// The smallest supported precision.
+const MIN_PRECISION: u8 = 4;
+/// The largest supported precision, which uses 256KB of registers.
+const MAX_PRECISION: u8 = 18;
+
+fn default_precision() -> u8 {
+    14
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Approximates the number of distinct values of a fast field, i.e. the number of
+/// unique users within the matching events.
+///
+/// Values are counted with a HyperLogLog++ sketch, small cardinalities are counted
+/// exactly until the sketch would use less memory.
+pub struct CardinalityAggregation {
+    /// The fast field to count the distinct values of.
+    pub field: String,
+    #[serde(default = "default_precision")]
+    /// The number of bits used to pick a register, between `4` and `18`, the
+    /// standard error is roughly `1.04 / sqrt(2^precision)`.
+    ///
+    /// Defaults to `14`, which is accurate to within about `0.8%`.
+    pub precision: u8,
+}
+
+impl CardinalityAggregation {
+    pub(crate) fn compile(
+        self,
+        ctx: &QueryContext,
+    ) -> Result<CompiledCardinality, QueryError> {
+        let column = resolve_column(ctx, &self.field, "cardinality")?;
+
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&self.precision) {
+            return Err(QueryError::Invalid(format!(
+                "The precision of a cardinality aggregation must be between {MIN_PRECISION} and {MAX_PRECISION}, got {}",
+                self.precision
+            )));
+        }
+
+        Ok(CompiledCardinality {
+            column,
+            precision: self.precision,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The result of a `cardinality` aggregation.
+pub struct CardinalityResult {
+    /// The approximate number of distinct values.
+    pub value: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledCardinality {
+    column: FastColumn,
+    precision: u8,
+}
+
+impl CompiledCardinality {
+    pub(crate) fn for_segment(&self, reader: &SegmentReader) -> SegmentCardinality {
+        SegmentCardinality {
+            column: self.column.open(reader),
+            ordinals: self.column.kind() == ColumnType::Str,
+            seen: HashSet::new(),
+            sketch: self.empty(),
+            values: Vec::new(),
+        }
+    }
+
+    /// A sketch which has seen no values.
+    pub(crate) fn empty(&self) -> HyperLogLog {
+        HyperLogLog::new(self.precision)
+    }
+
+    pub(crate) fn finalize(&self, sketch: HyperLogLog) -> CardinalityResult {
+        CardinalityResult {
+            value: sketch.estimate(),
+        }
+    }
+}
+
+#[derive(Clone)]
+/// Counts the distinct values of a single segment.
+pub(crate) struct SegmentCardinality {
+    column: Option<SegmentColumn>,
+    /// If the raw values are term ordinals, which are only meaningful within the
+    /// segment so they are collected and hashed by their value once harvested.
+    ordinals: bool,
+    seen: HashSet<u64>,
+    sketch: HyperLogLog,
+    values: Vec<u64>,
+}
+
+impl SegmentCardinality {
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        let Some(column) = self.column.as_ref() else {
+            return;
+        };
+
+        column.raw_values(doc, &mut self.values);
+        for raw in self.values.iter() {
+            if self.ordinals {
+                self.seen.insert(*raw);
+            } else {
+                self.sketch.insert(consistent_hash(raw.to_le_bytes()));
+            }
+        }
+    }
+
+    pub(crate) fn harvest(mut self) -> HyperLogLog {
+        if let Some(column) = self.column.as_ref() {
+            for raw in self.seen {
+                if let Some(AggregationKey::Str(value)) = column.key(raw) {
+                    self.sketch.insert(consistent_hash(value));
+                }
+            }
+        }
+        self.sketch
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A HyperLogLog++ sketch over 64-bit hashes.
+///
+/// The sketch starts sparse, holding the exact set of hashes, and switches to dense
+/// registers once the set would use more memory than the registers.
+pub struct HyperLogLog {
+    precision: u8,
+    sparse: Option<HashSet<u64>>,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new(precision: u8) -> Self {
+        Self {
+            precision,
+            sparse: Some(HashSet::new()),
+            registers: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn num_registers(&self) -> usize {
+        1 << self.precision
+    }
+
+    fn insert(&mut self, hash: u64) {
+        match self.sparse.as_mut() {
+            Some(sparse) => {
+                sparse.insert(hash);
+                if sparse.len() > self.num_registers() / 8 {
+                    self.densify();
+                }
+            },
+            None => self.insert_dense(hash),
+        }
+    }
+
+    fn densify(&mut self) {
+        let Some(sparse) = self.sparse.take() else {
+            return;
+        };
+
+        self.registers = vec![0; self.num_registers()];
+        for hash in sparse {
+            self.insert_dense(hash);
+        }
+    }
+
+    #[inline]
+    fn insert_dense(&mut self, hash: u64) {
+        let index = (hash >> (64 - self.precision)) as usize;
+        // A sentinel bit bounds the rank when the remaining bits are all zero.
+        let remaining = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = remaining.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: HyperLogLog) {
+        match other.sparse {
+            Some(sparse) => {
+                for hash in sparse {
+                    self.insert(hash);
+                }
+            },
+            None => {
+                self.densify();
+                for (register, other) in self.registers.iter_mut().zip(other.registers) {
+                    *register = (*register).max(other);
+                }
+            },
+        }
+    }
+
+    /// The approximate number of distinct hashes inserted.
+    fn estimate(&self) -> u64 {
+        if let Some(sparse) = self.sparse.as_ref() {
+            return sparse.len() as u64;
+        }
+
+        let m = self.num_registers() as f64;
+        let alpha = match self.num_registers() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+
+        let mut sum = 0.0;
+        let mut zeros = 0;
+        for register in self.registers.iter() {
+            sum += 1.0 / (1u64 << register) as f64;
+            if *register == 0 {
+                zeros += 1;
+            }
+        }
+
+        let raw = alpha * m * m / sum;
+        // Small cardinalities are estimated from the empty registers instead, as the
+        // raw estimate is heavily biased until most registers are set.
+        if raw <= 2.5 * m && zeros > 0 {
+            return (m * (m / zeros as f64).ln()).round() as u64;
+        }
+        raw.round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, FAST, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::aggregations::{aggregation_collector, Aggregation, AggregationResult};
+
+    #[test]
+    fn test_hyperloglog_estimate() {
+        let mut sketch = HyperLogLog::new(14);
+        for value in 0..1_000u64 {
+            sketch.insert(consistent_hash(value.to_le_bytes()));
+            sketch.insert(consistent_hash(value.to_le_bytes()));
+        }
+        assert!(sketch.sparse.is_some());
+        assert_eq!(sketch.estimate(), 1_000);
+
+        let mut merged = HyperLogLog::new(14);
+        for part in 0..4u64 {
+            let mut sketch = HyperLogLog::new(14);
+            for value in (0..200_000u64).filter(|value| value % 4 == part) {
+                sketch.insert(consistent_hash(value.to_le_bytes()));
+            }
+            merged.merge(sketch);
+        }
+        assert!(merged.sparse.is_none());
+        let estimate = merged.estimate() as f64;
+        assert!(
+            (estimate - 200_000.0).abs() < 200_000.0 * 0.03,
+            "estimated {estimate} distinct values"
+        );
+    }
+
+    #[test]
+    fn test_cardinality_aggregation() {
+        let mut schema = SchemaBuilder::new();
+        let user = schema.add_text_field("user", STRING | FAST);
+        let status = schema.add_u64_field("status", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for (name, code) in [("alice", 200u64), ("bob", 404), ("alice", 200)] {
+            writer
+                .add_document(doc!(user => name, status => code))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        for (name, code) in [("carol", 500u64), ("bob", 200)] {
+            writer
+                .add_document(doc!(user => name, status => code))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let aggregations: BTreeMap<String, Aggregation> = serde_json::from_str(
+            r#"{
+                "users": {"cardinality": {"field": "user"}},
+                "statuses": {"cardinality": {"field": "status", "precision": 10}}
+            }"#,
+        )
+        .unwrap();
+        let collector = aggregation_collector(&ctx, aggregations).unwrap();
+        let (count, results) = searcher.search(&AllQuery, &(Count, collector)).unwrap();
+        assert_eq!(count, 5);
+        assert_eq!(
+            results["users"],
+            AggregationResult::Cardinality(CardinalityResult { value: 3 })
+        );
+        assert_eq!(
+            results["statuses"],
+            AggregationResult::Cardinality(CardinalityResult { value: 3 })
+        );
+
+        let aggregations: BTreeMap<String, Aggregation> = serde_json::from_str(
+            r#"{"users": {"cardinality": {"field": "user", "precision": 20}}}"#,
+        )
+        .unwrap();
+        assert!(aggregation_collector(&ctx, aggregations).is_err());
+    }
+}
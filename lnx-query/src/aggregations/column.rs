@@ -0,0 +1,69 @@
+use super::AggregationKey;
+use crate::column::{FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+/// Resolves a fast field used by an aggregation.
+pub(crate) fn resolve_column(
+    ctx: &QueryContext,
+    name: &str,
+    aggregation: &'static str,
+) -> Result<FastColumn, QueryError> {
+    let (_, entry) = ctx.resolve_field(name)?;
+
+    let Some(column) = FastColumn::new(entry) else {
+        return Err(QueryError::unsupported(
+            name,
+            aggregation,
+            "only string, numeric, boolean and datetime fields can be aggregated",
+        ));
+    };
+
+    if !entry.is_fast() {
+        return Err(QueryError::unsupported(
+            name,
+            aggregation,
+            "the field is not a fast field",
+        ));
+    }
+
+    Ok(column)
+}
+
+/// Resolves a numeric fast field used by an aggregation.
+pub(crate) fn resolve_numeric_column(
+    ctx: &QueryContext,
+    name: &str,
+    aggregation: &'static str,
+) -> Result<FastColumn, QueryError> {
+    let column = resolve_column(ctx, name, aggregation)?;
+    if !column.is_numeric() {
+        return Err(QueryError::unsupported(
+            name,
+            aggregation,
+            "only numeric fields can be aggregated",
+        ));
+    }
+    Ok(column)
+}
+
+impl SegmentColumn {
+    /// Converts a raw value produced by [SegmentColumn::raw_values] into its key.
+    pub(crate) fn key(&self, raw: u64) -> Option<AggregationKey> {
+        let key = match self {
+            Self::U64(_) => AggregationKey::U64(raw),
+            Self::I64(_) => AggregationKey::I64(raw as i64),
+            Self::F64(_) => AggregationKey::F64(f64::from_bits(raw)),
+            Self::Bool(_) => AggregationKey::Bool(raw != 0),
+            Self::Date(_) => AggregationKey::I64(raw as i64),
+            Self::Str(column) => {
+                let mut text = String::new();
+                match column.ord_to_str(raw, &mut text) {
+                    Ok(true) => AggregationKey::Str(text),
+                    _ => return None,
+                }
+            },
+        };
+        Some(key)
+    }
+}
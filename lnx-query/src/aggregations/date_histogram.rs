@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tantivy::{DocId, SegmentReader};
+use time::format_description::well_known::Rfc3339;
+use time::UtcOffset;
+
+use super::column::resolve_column;
+use super::histogram::{collect_buckets, IntermediateHistogram};
+use super::{
+    AggregationResults,
+    CompiledAggregations,
+    SegmentAggregations,
+    SegmentBucket,
+};
+use crate::column::{ColumnType, FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::date_math::{
+    add_micros,
+    is_date_math,
+    micros_to_offset_datetime,
+    resolve_date_math,
+    truncate_micros,
+    Unit,
+};
+use crate::datetime_output::parse_utc_offset;
+use crate::error::QueryError;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The range a date histogram returns buckets for, even if they have no documents.
+///
+/// Bounds are datetimes or date math expressions, i.e. `now-7d/d`.
+pub struct DateHistogramBounds {
+    /// The datetime the first bucket must contain.
+    pub min: String,
+    /// The datetime the last bucket must contain.
+    pub max: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Counts the documents within each interval of time of a datetime fast field, i.e.
+/// the number of events per hour for a dashboard.
+///
+/// Exactly one of `calendar_interval` or `fixed_interval` must be set. A document
+/// with several values is counted once in each bucket its values fall within.
+pub struct DateHistogramAggregation {
+    /// The datetime fast field to aggregate.
+    pub field: String,
+    #[serde(default)]
+    /// A calendar aware interval, one of `minute`, `hour`, `day`, `week`, `month`
+    /// or `year`, where weeks start on Monday and months vary in length.
+    pub calendar_interval: Option<String>,
+    #[serde(default)]
+    /// A fixed length interval of milliseconds (`ms`), seconds (`s`), minutes (`m`),
+    /// hours (`h`) or days (`d`), i.e. `30m` or `12h`.
+    pub fixed_interval: Option<String>,
+    #[serde(default)]
+    /// The UTC offset buckets are aligned to, i.e. `+02:00` starts each day bucket at
+    /// local midnight. Bucket keys are rendered in the same offset.
+    ///
+    /// Defaults to `UTC`.
+    pub time_zone: Option<String>,
+    #[serde(default)]
+    /// The minimum number of documents a bucket must have to be returned, when `0`
+    /// the empty buckets between the first and last bucket are filled in.
+    ///
+    /// Defaults to `0`.
+    pub min_doc_count: u64,
+    #[serde(default)]
+    /// Extends the filled in buckets to cover the given range.
+    pub extended_bounds: Option<DateHistogramBounds>,
+}
+
+impl DateHistogramAggregation {
+    pub(crate) fn compile(
+        self,
+        ctx: &QueryContext,
+        sub: CompiledAggregations,
+    ) -> Result<CompiledDateHistogram, QueryError> {
+        let column = resolve_column(ctx, &self.field, "date_histogram")?;
+        if column.kind() != ColumnType::Date {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "date_histogram",
+                "only datetime fields can be aggregated",
+            ));
+        }
+
+        let interval = match (self.calendar_interval, self.fixed_interval) {
+            (Some(name), None) => Unit::from_name(&name)
+                .map(DateInterval::Calendar)
+                .map_err(QueryError::Invalid)?,
+            (None, Some(value)) => DateInterval::Fixed(parse_fixed_interval(&value)?),
+            _ => {
+                return Err(QueryError::Invalid(
+                    "A date histogram must set exactly one of `calendar_interval` or `fixed_interval`"
+                        .to_string(),
+                ))
+            },
+        };
+
+        let offset = match self.time_zone.as_deref() {
+            Some(timezone) => parse_utc_offset(timezone)?,
+            None => UtcOffset::UTC,
+        };
+
+        let extended_bounds = match self.extended_bounds {
+            Some(bounds) => {
+                let min = parse_bound(ctx, &self.field, &bounds.min)?;
+                let max = parse_bound(ctx, &self.field, &bounds.max)?;
+                if min > max {
+                    return Err(QueryError::Invalid(
+                        "The min of a date histogram's extended bounds must not be after its max"
+                            .to_string(),
+                    ));
+                }
+
+                let start = |micros| {
+                    interval.start(micros, offset).ok_or_else(|| {
+                        QueryError::invalid_value(
+                            &self.field,
+                            "the extended bounds are beyond the supported datetime range",
+                        )
+                    })
+                };
+                Some((start(min)?, start(max)?))
+            },
+            None => None,
+        };
+
+        Ok(CompiledDateHistogram {
+            column,
+            interval,
+            offset,
+            min_doc_count: self.min_doc_count,
+            extended_bounds,
+            sub,
+        })
+    }
+}
+
+fn parse_bound(ctx: &QueryContext, field: &str, value: &str) -> Result<i64, QueryError> {
+    let dt = if is_date_math(value) {
+        resolve_date_math(ctx, value, false)
+            .map_err(|e| QueryError::invalid_value(field, e))?
+    } else {
+        ctx.parse_datetime(value)
+            .map_err(|e| QueryError::invalid_value(field, e.to_string()))?
+    };
+    Ok(dt.as_micros())
+}
+
+/// Parses a fixed interval, i.e. `90s` or `12h`, into microseconds.
+fn parse_fixed_interval(value: &str) -> Result<i64, QueryError> {
+    let invalid = || {
+        QueryError::Invalid(format!(
+            "Invalid fixed interval {value:?}, expected a positive amount of `ms`, `s`, `m`, `h` or `d`, i.e. `30m`"
+        ))
+    };
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let unit_micros = match unit {
+        "ms" => 1_000,
+        "s" => 1_000_000,
+        "m" => 60_000_000,
+        "h" => 3_600_000_000,
+        "d" => 86_400_000_000,
+        _ => return Err(invalid()),
+    };
+
+    match amount.checked_mul(unit_micros) {
+        Some(micros) if micros > 0 => Ok(micros),
+        _ => Err(invalid()),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The interval of time covered by each bucket.
+enum DateInterval {
+    /// A calendar unit, which varies in length.
+    Calendar(Unit),
+    /// A fixed number of microseconds.
+    Fixed(i64),
+}
+
+impl DateInterval {
+    /// The start of the bucket containing the timestamp.
+    fn start(&self, micros: i64, offset: UtcOffset) -> Option<i64> {
+        match self {
+            Self::Calendar(unit) => truncate_micros(micros, *unit, offset),
+            Self::Fixed(width) => {
+                let shift = offset.whole_seconds() as i64 * 1_000_000;
+                let local = micros.checked_add(shift)?;
+                (local.div_euclid(*width) * width).checked_sub(shift)
+            },
+        }
+    }
+
+    /// The start of the bucket after the bucket starting at the given timestamp.
+    fn next(&self, start: i64, offset: UtcOffset) -> Option<i64> {
+        match self {
+            Self::Calendar(unit) => add_micros(start, *unit, 1, offset),
+            Self::Fixed(width) => start.checked_add(*width),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// A single bucket of a `date_histogram` aggregation.
+pub struct DateHistogramBucket {
+    /// The start of the bucket as a UNIX timestamp in milliseconds.
+    pub key: i64,
+    /// The start of the bucket formatted as RFC 3339 in the aggregation's time zone.
+    pub key_as_string: String,
+    /// The number of matching documents with a value within the bucket.
+    pub doc_count: u64,
+    #[serde(flatten)]
+    /// The results of the sub-aggregations over the documents of the bucket.
+    pub aggregations: AggregationResults,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The result of a `date_histogram` aggregation.
+pub struct DateHistogramResult {
+    /// The buckets ordered by their key.
+    pub buckets: Vec<DateHistogramBucket>,
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledDateHistogram {
+    column: FastColumn,
+    interval: DateInterval,
+    offset: UtcOffset,
+    min_doc_count: u64,
+    extended_bounds: Option<(i64, i64)>,
+    sub: CompiledAggregations,
+}
+
+impl CompiledDateHistogram {
+    pub(crate) fn for_segment(&self, reader: &SegmentReader) -> SegmentDateHistogram {
+        SegmentDateHistogram {
+            column: self.column.open(reader),
+            interval: self.interval,
+            offset: self.offset,
+            buckets: HashMap::new(),
+            values: Vec::new(),
+            keys: Vec::new(),
+            sub: self.sub.for_segment(reader),
+        }
+    }
+
+    pub(crate) fn finalize(
+        &self,
+        intermediate: IntermediateHistogram,
+    ) -> tantivy::Result<DateHistogramResult> {
+        let buckets = intermediate
+            .into_buckets(self.min_doc_count, self.extended_bounds, |start| {
+                self.interval.next(start, self.offset)
+            })?
+            .into_iter()
+            .map(|(start, intermediate)| {
+                Ok(DateHistogramBucket {
+                    key: start.div_euclid(1_000),
+                    key_as_string: micros_to_offset_datetime(start, self.offset)
+                        .and_then(|dt| dt.format(&Rfc3339).ok())
+                        .unwrap_or_default(),
+                    doc_count: intermediate.doc_count,
+                    aggregations: intermediate.finalize(&self.sub)?,
+                })
+            })
+            .collect::<tantivy::Result<_>>()?;
+
+        Ok(DateHistogramResult { buckets })
+    }
+}
+
+#[derive(Clone)]
+/// Counts the documents within each bucket of a single segment, keyed by the start
+/// of the bucket in microseconds.
+pub(crate) struct SegmentDateHistogram {
+    column: Option<SegmentColumn>,
+    interval: DateInterval,
+    offset: UtcOffset,
+    buckets: HashMap<i64, SegmentBucket>,
+    values: Vec<u64>,
+    keys: Vec<i64>,
+    sub: SegmentAggregations,
+}
+
+impl SegmentDateHistogram {
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        let Some(column) = self.column.as_ref() else {
+            return;
+        };
+
+        column.raw_values(doc, &mut self.values);
+        self.keys.clear();
+        for micros in self.values.iter() {
+            if let Some(start) = self.interval.start(*micros as i64, self.offset) {
+                self.keys.push(start);
+            }
+        }
+        collect_buckets(&mut self.buckets, &mut self.keys, &self.sub, doc);
+    }
+
+    pub(crate) fn harvest(self) -> IntermediateHistogram {
+        IntermediateHistogram::from_buckets(self.buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, FAST};
+    use tantivy::{doc, DateTime, Index};
+
+    use super::*;
+    use crate::aggregations::{aggregation_collector, Aggregation, AggregationResult};
+
+    #[test]
+    fn test_parse_fixed_interval() {
+        assert_eq!(parse_fixed_interval("90s").unwrap(), 90_000_000);
+        assert_eq!(parse_fixed_interval("12h").unwrap(), 12 * 3_600_000_000);
+        assert_eq!(parse_fixed_interval("250ms").unwrap(), 250_000);
+        assert!(parse_fixed_interval("0d").is_err());
+        assert!(parse_fixed_interval("1w").is_err());
+        assert!(parse_fixed_interval("h").is_err());
+    }
+
+    #[test]
+    fn test_date_histogram_aggregation() {
+        let mut schema = SchemaBuilder::new();
+        let timestamp = schema.add_date_field("timestamp", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        // 2023-01-30T22:30:00Z, 2023-01-31T10:00:00Z, 2023-02-02T23:15:00Z
+        let mut writer = index.writer(15_000_000).unwrap();
+        for secs in [1_675_117_800, 1_675_159_200] {
+            writer
+                .add_document(doc!(timestamp => DateTime::from_timestamp_secs(secs)))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        writer
+            .add_document(
+                doc!(timestamp => DateTime::from_timestamp_secs(1_675_379_700)),
+            )
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let histogram = |json: &str| {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            let collector = aggregation_collector(&ctx, aggregations).unwrap();
+            let (count, mut results) =
+                searcher.search(&AllQuery, &(Count, collector)).unwrap();
+            assert_eq!(count, 3);
+            let Some(AggregationResult::DateHistogram(result)) = results.remove("agg")
+            else {
+                panic!("expected a date histogram result");
+            };
+            result
+                .buckets
+                .into_iter()
+                .map(|bucket| (bucket.key_as_string, bucket.doc_count))
+                .collect::<Vec<_>>()
+        };
+
+        let buckets = histogram(
+            r#"{"agg": {"date_histogram": {"field": "timestamp", "calendar_interval": "day"}}}"#,
+        );
+        assert_eq!(
+            buckets,
+            [
+                ("2023-01-30T00:00:00Z".to_string(), 1),
+                ("2023-01-31T00:00:00Z".to_string(), 1),
+                ("2023-02-01T00:00:00Z".to_string(), 0),
+                ("2023-02-02T00:00:00Z".to_string(), 1),
+            ]
+        );
+
+        // Shifting to UTC+02:00 moves the late evening events into the next local day.
+        let buckets = histogram(
+            r#"{"agg": {"date_histogram": {"field": "timestamp", "calendar_interval": "day", "time_zone": "+02:00", "min_doc_count": 1}}}"#,
+        );
+        assert_eq!(
+            buckets,
+            [
+                ("2023-01-31T00:00:00+02:00".to_string(), 2),
+                ("2023-02-03T00:00:00+02:00".to_string(), 1),
+            ]
+        );
+
+        let buckets = histogram(
+            r#"{"agg": {"date_histogram": {"field": "timestamp", "calendar_interval": "month", "extended_bounds": {"min": "2022-12-15T00:00:00Z", "max": "2023-03-01T00:00:00Z"}}}}"#,
+        );
+        assert_eq!(
+            buckets,
+            [
+                ("2022-12-01T00:00:00Z".to_string(), 0),
+                ("2023-01-01T00:00:00Z".to_string(), 2),
+                ("2023-02-01T00:00:00Z".to_string(), 1),
+                ("2023-03-01T00:00:00Z".to_string(), 0),
+            ]
+        );
+
+        let buckets = histogram(
+            r#"{"agg": {"date_histogram": {"field": "timestamp", "fixed_interval": "12h", "min_doc_count": 1}}}"#,
+        );
+        assert_eq!(
+            buckets,
+            [
+                ("2023-01-30T12:00:00Z".to_string(), 1),
+                ("2023-01-31T00:00:00Z".to_string(), 1),
+                ("2023-02-02T12:00:00Z".to_string(), 1),
+            ]
+        );
+
+        for json in [
+            r#"{"agg": {"date_histogram": {"field": "timestamp"}}}"#,
+            r#"{"agg": {"date_histogram": {"field": "timestamp", "calendar_interval": "day", "fixed_interval": "1d"}}}"#,
+            r#"{"agg": {"date_histogram": {"field": "timestamp", "calendar_interval": "fortnight"}}}"#,
+            r#"{"agg": {"date_histogram": {"field": "timestamp", "fixed_interval": "1d", "time_zone": "Mars/Olympus"}}}"#,
+        ] {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            assert!(aggregation_collector(&ctx, aggregations).is_err());
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tantivy::{DocId, SegmentReader, TantivyError};
+
+use super::column::resolve_numeric_column;
+use super::{
+    AggregationResults,
+    CompiledAggregations,
+    IntermediateBucket,
+    SegmentAggregations,
+    SegmentBucket,
+    MAX_AGGREGATION_BUCKETS,
+};
+use crate::column::{FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The range a numeric histogram returns buckets for, even if they have no documents.
+pub struct HistogramBounds {
+    /// The value the first bucket must contain.
+    pub min: f64,
+    /// The value the last bucket must contain.
+    pub max: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Counts the documents within fixed width ranges of a numeric fast field, i.e.
+/// products grouped by price in steps of `50`.
+///
+/// The key of each bucket is its lower bound, `floor((value - offset) / interval)`
+/// multiplied by the interval plus the offset. A document with several values is
+/// counted once in each bucket its values fall within.
+pub struct HistogramAggregation {
+    /// The numeric fast field to aggregate.
+    pub field: String,
+    /// The width of each bucket.
+    pub interval: f64,
+    #[serde(default)]
+    /// Shifts the bucket boundaries, i.e. an offset of `5` with an interval of `10`
+    /// produces the buckets `5`, `15`, `25`, ...
+    ///
+    /// Defaults to `0`.
+    pub offset: f64,
+    #[serde(default)]
+    /// The minimum number of documents a bucket must have to be returned, when `0`
+    /// the empty buckets between the first and last bucket are filled in.
+    ///
+    /// Defaults to `0`.
+    pub min_doc_count: u64,
+    #[serde(default)]
+    /// Extends the filled in buckets to cover the given range.
+    pub extended_bounds: Option<HistogramBounds>,
+}
+
+impl HistogramAggregation {
+    pub(crate) fn compile(
+        self,
+        ctx: &QueryContext,
+        sub: CompiledAggregations,
+    ) -> Result<CompiledHistogram, QueryError> {
+        let column = resolve_numeric_column(ctx, &self.field, "histogram")?;
+
+        if !self.interval.is_finite() || self.interval <= 0.0 {
+            return Err(QueryError::Invalid(format!(
+                "The interval of a histogram must be a positive number, got {}",
+                self.interval
+            )));
+        }
+        if !self.offset.is_finite() {
+            return Err(QueryError::Invalid(
+                "The offset of a histogram must be a finite number".to_string(),
+            ));
+        }
+
+        let grid = HistogramGrid {
+            interval: self.interval,
+            offset: self.offset,
+        };
+        let extended_bounds = match self.extended_bounds {
+            Some(bounds) if bounds.min.is_finite() && bounds.max.is_finite() => {
+                if bounds.min > bounds.max {
+                    return Err(QueryError::Invalid(
+                        "The min of a histogram's extended bounds must not be greater than its max"
+                            .to_string(),
+                    ));
+                }
+                Some((grid.bucket(bounds.min), grid.bucket(bounds.max)))
+            },
+            Some(_) => {
+                return Err(QueryError::Invalid(
+                    "The extended bounds of a histogram must be finite numbers"
+                        .to_string(),
+                ))
+            },
+            None => None,
+        };
+
+        Ok(CompiledHistogram {
+            column,
+            grid,
+            min_doc_count: self.min_doc_count,
+            extended_bounds,
+            sub,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// A single bucket of a `histogram` aggregation.
+pub struct HistogramBucket {
+    /// The lower bound of the bucket.
+    pub key: f64,
+    /// The number of matching documents with a value within the bucket.
+    pub doc_count: u64,
+    #[serde(flatten)]
+    /// The results of the sub-aggregations over the documents of the bucket.
+    pub aggregations: AggregationResults,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The result of a `histogram` aggregation.
+pub struct HistogramResult {
+    /// The buckets ordered by their key.
+    pub buckets: Vec<HistogramBucket>,
+}
+
+#[derive(Debug, Copy, Clone)]
+/// Maps values to the index of the bucket they fall within.
+struct HistogramGrid {
+    interval: f64,
+    offset: f64,
+}
+
+impl HistogramGrid {
+    #[inline]
+    fn bucket(&self, value: f64) -> i64 {
+        ((value - self.offset) / self.interval).floor() as i64
+    }
+
+    #[inline]
+    fn key(&self, bucket: i64) -> f64 {
+        bucket as f64 * self.interval + self.offset
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledHistogram {
+    column: FastColumn,
+    grid: HistogramGrid,
+    min_doc_count: u64,
+    extended_bounds: Option<(i64, i64)>,
+    sub: CompiledAggregations,
+}
+
+impl CompiledHistogram {
+    pub(crate) fn for_segment(&self, reader: &SegmentReader) -> SegmentHistogram {
+        SegmentHistogram {
+            column: self.column.open(reader),
+            grid: self.grid,
+            buckets: HashMap::new(),
+            values: Vec::new(),
+            keys: Vec::new(),
+            sub: self.sub.for_segment(reader),
+        }
+    }
+
+    pub(crate) fn finalize(
+        &self,
+        intermediate: IntermediateHistogram,
+    ) -> tantivy::Result<HistogramResult> {
+        let buckets = intermediate
+            .into_buckets(self.min_doc_count, self.extended_bounds, |bucket| {
+                bucket.checked_add(1)
+            })?
+            .into_iter()
+            .map(|(bucket, intermediate)| {
+                Ok(HistogramBucket {
+                    key: self.grid.key(bucket),
+                    doc_count: intermediate.doc_count,
+                    aggregations: intermediate.finalize(&self.sub)?,
+                })
+            })
+            .collect::<tantivy::Result<_>>()?;
+
+        Ok(HistogramResult { buckets })
+    }
+}
+
+#[derive(Clone)]
+/// Counts the documents within each bucket of a single segment.
+pub(crate) struct SegmentHistogram {
+    column: Option<SegmentColumn>,
+    grid: HistogramGrid,
+    buckets: HashMap<i64, SegmentBucket>,
+    values: Vec<f64>,
+    keys: Vec<i64>,
+    sub: SegmentAggregations,
+}
+
+impl SegmentHistogram {
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        let Some(column) = self.column.as_ref() else {
+            return;
+        };
+
+        column.numeric_values(doc, &mut self.values);
+        self.keys.clear();
+        self.keys.extend(
+            self.values
+                .iter()
+                .filter(|value| value.is_finite())
+                .map(|value| self.grid.bucket(*value)),
+        );
+        collect_buckets(&mut self.buckets, &mut self.keys, &self.sub, doc);
+    }
+
+    pub(crate) fn harvest(self) -> IntermediateHistogram {
+        IntermediateHistogram::from_buckets(self.buckets)
+    }
+}
+
+/// Collects a document once in each of the buckets with the given keys, creating
+/// the buckets from the segment's sub-aggregations as needed.
+pub(super) fn collect_buckets(
+    buckets: &mut HashMap<i64, SegmentBucket>,
+    keys: &mut Vec<i64>,
+    sub: &SegmentAggregations,
+    doc: DocId,
+) {
+    if keys.len() > 1 {
+        keys.sort_unstable();
+        keys.dedup();
+    }
+    for key in keys.iter() {
+        buckets
+            .entry(*key)
+            .or_insert_with(|| SegmentBucket::new(sub))
+            .collect(doc);
+    }
+}
+
+#[derive(Default)]
+/// The buckets of a histogram, keyed by the bucket's position so they can be merged
+/// across segments.
+pub struct IntermediateHistogram {
+    buckets: BTreeMap<i64, IntermediateBucket>,
+}
+
+impl IntermediateHistogram {
+    pub(super) fn from_buckets(buckets: HashMap<i64, SegmentBucket>) -> Self {
+        Self {
+            buckets: buckets
+                .into_iter()
+                .map(|(key, bucket)| (key, bucket.harvest()))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: IntermediateHistogram) {
+        for (key, bucket) in other.buckets {
+            match self.buckets.get_mut(&key) {
+                Some(existing) => existing.merge(bucket),
+                None => {
+                    self.buckets.insert(key, bucket);
+                },
+            }
+        }
+    }
+
+    /// Returns the buckets in order, when `min_doc_count` is `0` the empty buckets
+    /// between the first and last bucket, or the bounds, are filled in by stepping
+    /// from one bucket to the `next`.
+    pub(super) fn into_buckets(
+        mut self,
+        min_doc_count: u64,
+        bounds: Option<(i64, i64)>,
+        next: impl Fn(i64) -> Option<i64>,
+    ) -> tantivy::Result<Vec<(i64, IntermediateBucket)>> {
+        let too_many_buckets = || {
+            TantivyError::InvalidArgument(format!(
+                "The histogram produces more than {MAX_AGGREGATION_BUCKETS} buckets, use a larger interval"
+            ))
+        };
+
+        if min_doc_count > 0 {
+            let buckets = self
+                .buckets
+                .into_iter()
+                .filter(|(_, bucket)| bucket.doc_count >= min_doc_count)
+                .collect::<Vec<_>>();
+            if buckets.len() > MAX_AGGREGATION_BUCKETS {
+                return Err(too_many_buckets());
+            }
+            return Ok(buckets);
+        }
+
+        let first = self.buckets.keys().next().copied();
+        let last = self.buckets.keys().next_back().copied();
+        let (first, last) = match (first, last, bounds) {
+            (Some(first), Some(last), Some((min, max))) => {
+                (first.min(min), last.max(max))
+            },
+            (Some(first), Some(last), None) => (first, last),
+            (None, None, Some(bounds)) => bounds,
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut buckets = Vec::new();
+        let mut bucket = Some(first);
+        while let Some(current) = bucket.filter(|current| *current <= last) {
+            if buckets.len() == MAX_AGGREGATION_BUCKETS {
+                return Err(too_many_buckets());
+            }
+            let intermediate = self.buckets.remove(&current).unwrap_or_default();
+            buckets.push((current, intermediate));
+            bucket = next(current);
+        }
+
+        Ok(buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, FAST};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::aggregations::{aggregation_collector, Aggregation, AggregationResult};
+
+    #[test]
+    fn test_histogram_aggregation() {
+        let mut schema = SchemaBuilder::new();
+        let price = schema.add_f64_field("price", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for value in [3.0, 12.5, 19.9] {
+            writer.add_document(doc!(price => value)).unwrap();
+        }
+        writer.commit().unwrap();
+        writer.add_document(doc!(price => 41.0)).unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let histogram = |json: &str| {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            let collector = aggregation_collector(&ctx, aggregations).unwrap();
+            let (count, mut results) =
+                searcher.search(&AllQuery, &(Count, collector)).unwrap();
+            assert_eq!(count, 4);
+            let Some(AggregationResult::Histogram(result)) = results.remove("agg")
+            else {
+                panic!("expected a histogram result");
+            };
+            result
+                .buckets
+                .into_iter()
+                .map(|bucket| (bucket.key, bucket.doc_count))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            histogram(r#"{"agg": {"histogram": {"field": "price", "interval": 10}}}"#),
+            [(0.0, 1), (10.0, 2), (20.0, 0), (30.0, 0), (40.0, 1)]
+        );
+        assert_eq!(
+            histogram(
+                r#"{"agg": {"histogram": {"field": "price", "interval": 10, "offset": 5, "min_doc_count": 1}}}"#
+            ),
+            [(-5.0, 1), (5.0, 1), (15.0, 1), (35.0, 1)]
+        );
+        assert_eq!(
+            histogram(
+                r#"{"agg": {"histogram": {"field": "price", "interval": 20, "extended_bounds": {"min": -20, "max": 60}}}}"#
+            ),
+            [(-20.0, 0), (0.0, 3), (20.0, 0), (40.0, 1), (60.0, 0)]
+        );
+
+        for json in [
+            r#"{"agg": {"histogram": {"field": "price", "interval": 0}}}"#,
+            r#"{"agg": {"histogram": {"field": "price", "interval": 1, "extended_bounds": {"min": 5, "max": 1}}}}"#,
+        ] {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            assert!(aggregation_collector(&ctx, aggregations).is_err());
+        }
+
+        let aggregations: BTreeMap<String, Aggregation> = serde_json::from_str(
+            r#"{"agg": {"histogram": {"field": "price", "interval": 0.001}}}"#,
+        )
+        .unwrap();
+        let collector = aggregation_collector(&ctx, aggregations).unwrap();
+        assert!(searcher.search(&AllQuery, &collector).is_err());
+    }
+}
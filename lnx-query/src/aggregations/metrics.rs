@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use tantivy::{DocId, SegmentReader};
+
+use super::column::resolve_numeric_column;
+use super::AggregationResult;
+use crate::column::{FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Computes a statistic over the values of a numeric fast field, i.e. the average
+/// price of the matching products.
+///
+/// Every value of a multi-valued field is included.
+pub struct MetricAggregation {
+    /// The numeric fast field to aggregate.
+    pub field: String,
+    #[serde(default)]
+    /// The value used for documents without a value, which are otherwise ignored.
+    pub missing: Option<f64>,
+}
+
+impl MetricAggregation {
+    pub(crate) fn compile(
+        self,
+        ctx: &QueryContext,
+        kind: MetricKind,
+    ) -> Result<CompiledMetric, QueryError> {
+        let column = resolve_numeric_column(ctx, &self.field, kind.name())?;
+
+        if let Some(missing) = self.missing {
+            if !missing.is_finite() {
+                return Err(QueryError::invalid_value(
+                    &self.field,
+                    "the missing value must be a finite number",
+                ));
+            }
+        }
+
+        Ok(CompiledMetric {
+            column,
+            kind,
+            missing: self.missing,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The statistic computed by a metric aggregation.
+pub(crate) enum MetricKind {
+    Stats,
+    Min,
+    Max,
+    Sum,
+    Avg,
+}
+
+impl MetricKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Stats => "stats",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Sum => "sum",
+            Self::Avg => "avg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The result of a `stats` aggregation.
+pub struct StatsResult {
+    /// The number of values.
+    pub count: u64,
+    /// The smallest value, if there are any values.
+    pub min: Option<f64>,
+    /// The largest value, if there are any values.
+    pub max: Option<f64>,
+    /// The sum of all values.
+    pub sum: f64,
+    /// The average of all values, if there are any values.
+    pub avg: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The result of a `min`, `max`, `sum` or `avg` aggregation.
+pub struct MetricResult {
+    /// The computed value, `null` if there are no values to compute it from, except
+    /// for a `sum` which is `0`.
+    pub value: Option<f64>,
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledMetric {
+    column: FastColumn,
+    kind: MetricKind,
+    missing: Option<f64>,
+}
+
+impl CompiledMetric {
+    pub(crate) fn for_segment(&self, reader: &SegmentReader) -> SegmentMetric {
+        SegmentMetric {
+            column: self.column.open(reader),
+            missing: self.missing,
+            stats: IntermediateStats::default(),
+            values: Vec::new(),
+        }
+    }
+
+    pub(crate) fn finalize(&self, stats: IntermediateStats) -> AggregationResult {
+        let value = match self.kind {
+            MetricKind::Stats => {
+                return AggregationResult::Stats(StatsResult {
+                    count: stats.count,
+                    min: stats.min,
+                    max: stats.max,
+                    sum: stats.sum,
+                    avg: stats.avg(),
+                })
+            },
+            MetricKind::Min => stats.min,
+            MetricKind::Max => stats.max,
+            MetricKind::Sum => Some(stats.sum),
+            MetricKind::Avg => stats.avg(),
+        };
+        AggregationResult::Metric(MetricResult { value })
+    }
+}
+
+#[derive(Clone)]
+/// Accumulates the values of a single segment.
+pub(crate) struct SegmentMetric {
+    column: Option<SegmentColumn>,
+    missing: Option<f64>,
+    stats: IntermediateStats,
+    values: Vec<f64>,
+}
+
+impl SegmentMetric {
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        match self.column.as_ref() {
+            Some(column) => column.numeric_values(doc, &mut self.values),
+            None => self.values.clear(),
+        }
+
+        if self.values.is_empty() {
+            if let Some(missing) = self.missing {
+                self.stats.add(missing);
+            }
+            return;
+        }
+
+        for value in self.values.iter() {
+            self.stats.add(*value);
+        }
+    }
+
+    pub(crate) fn harvest(self) -> IntermediateStats {
+        self.stats
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+/// The count, bounds and sum of the values seen so far, which can be merged across
+/// segments.
+pub struct IntermediateStats {
+    count: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+    sum: f64,
+}
+
+impl IntermediateStats {
+    fn avg(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    pub(crate) fn merge(&mut self, other: IntermediateStats) {
+        self.count += other.count;
+        self.sum += other.sum;
+        for value in [other.min, other.max].into_iter().flatten() {
+            self.min = Some(self.min.map_or(value, |min| min.min(value)));
+            self.max = Some(self.max.map_or(value, |max| max.max(value)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tantivy::collector::TopDocs;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, FAST, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::aggregations::{aggregation_collector, Aggregation, AggregationResult};
+
+    #[test]
+    fn test_metric_aggregations() {
+        let mut schema = SchemaBuilder::new();
+        let name = schema.add_text_field("name", STRING);
+        let price = schema.add_i64_field("price", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for value in [10i64, -4] {
+            writer
+                .add_document(doc!(name => "shoe", price => value))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        writer
+            .add_document(doc!(name => "sock", price => 30i64))
+            .unwrap();
+        writer.add_document(doc!(name => "gift card")).unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let aggregations: BTreeMap<String, Aggregation> = serde_json::from_str(
+            r#"{
+                "stats": {"stats": {"field": "price"}},
+                "min": {"min": {"field": "price"}},
+                "max": {"max": {"field": "price"}},
+                "sum": {"sum": {"field": "price"}},
+                "avg": {"avg": {"field": "price", "missing": 0}}
+            }"#,
+        )
+        .unwrap();
+        let collector = aggregation_collector(&ctx, aggregations).unwrap();
+        let (hits, results) = searcher
+            .search(&AllQuery, &(TopDocs::with_limit(10), collector))
+            .unwrap();
+        assert_eq!(hits.len(), 4);
+
+        assert_eq!(
+            results["stats"],
+            AggregationResult::Stats(StatsResult {
+                count: 3,
+                min: Some(-4.0),
+                max: Some(30.0),
+                sum: 36.0,
+                avg: Some(12.0),
+            })
+        );
+        let value = |name: &str| match &results[name] {
+            AggregationResult::Metric(result) => result.value,
+            other => panic!("expected a metric result, got {other:?}"),
+        };
+        assert_eq!(value("min"), Some(-4.0));
+        assert_eq!(value("max"), Some(30.0));
+        assert_eq!(value("sum"), Some(36.0));
+        assert_eq!(value("avg"), Some(9.0));
+
+        let aggregations: BTreeMap<String, Aggregation> =
+            serde_json::from_str(r#"{"avg": {"avg": {"field": "name"}}}"#).unwrap();
+        assert!(aggregation_collector(&ctx, aggregations).is_err());
+    }
+
+    #[test]
+    fn test_metric_aggregations_without_values() {
+        let mut schema = SchemaBuilder::new();
+        let name = schema.add_text_field("name", STRING);
+        schema.add_f64_field("price", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer.add_document(doc!(name => "gift card")).unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let aggregations: BTreeMap<String, Aggregation> = serde_json::from_str(
+            r#"{"min": {"min": {"field": "price"}}, "sum": {"sum": {"field": "price"}}}"#,
+        )
+        .unwrap();
+        let collector = aggregation_collector(&ctx, aggregations).unwrap();
+        let results = searcher.search(&AllQuery, &collector).unwrap();
+
+        assert_eq!(
+            results["min"],
+            AggregationResult::Metric(MetricResult { value: None })
+        );
+        assert_eq!(
+            results["sum"],
+            AggregationResult::Metric(MetricResult { value: Some(0.0) })
+        );
+    }
+}
@@ -0,0 +1,727 @@
+//! Aggregations computed over the documents matching a search, i.e. the value
+//! counts used to build faceted navigation or the histograms of a dashboard.
+//!
+//! Aggregations are named within a request, i.e. `{"brands": {"terms": {"field": "brand"}}}`,
+//! and compiled into an [AggregationCollector] which can be combined with the
+//! collector gathering the hits, so both are computed in a single pass over the
+//! matching documents.
+//!
+//! Each segment produces an intermediate result which is merged across segments
+//! before the final results, i.e. the top buckets, are computed.
+//!
+//! Bucket aggregations can nest further aggregations via `aggs`, which are computed
+//! over the documents of each bucket, i.e. the average price per brand per week:
+//! `{"brands": {"terms": {"field": "brand"}, "aggs": {"weekly": {...}}}}`. Each
+//! bucket of a segment starts from a copy of the sub-aggregations bound to the
+//! segment, and the buckets of each level are merged across segments before the
+//! tree is finalized from the top down.
+
+mod cardinality;
+mod column;
+mod date_histogram;
+mod histogram;
+mod metrics;
+mod percentiles;
+mod terms;
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+pub use self::cardinality::{CardinalityAggregation, CardinalityResult};
+use self::cardinality::{CompiledCardinality, HyperLogLog, SegmentCardinality};
+use self::date_histogram::{CompiledDateHistogram, SegmentDateHistogram};
+pub use self::date_histogram::{
+    DateHistogramAggregation,
+    DateHistogramBounds,
+    DateHistogramBucket,
+    DateHistogramResult,
+};
+use self::histogram::{CompiledHistogram, IntermediateHistogram, SegmentHistogram};
+pub use self::histogram::{
+    HistogramAggregation,
+    HistogramBounds,
+    HistogramBucket,
+    HistogramResult,
+};
+use self::metrics::{CompiledMetric, IntermediateStats, MetricKind, SegmentMetric};
+pub use self::metrics::{MetricAggregation, MetricResult, StatsResult};
+use self::percentiles::{CompiledPercentiles, SegmentPercentiles, TDigest};
+pub use self::percentiles::{
+    PercentileValue,
+    PercentilesAggregation,
+    PercentilesResult,
+};
+pub use self::terms::{BucketOrder, TermsAggregation, TermsBucket, TermsResult};
+use self::terms::{CompiledTerms, IntermediateTerms, SegmentTerms};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+/// The maximum number of buckets a single aggregation may return.
+pub const MAX_AGGREGATION_BUCKETS: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+/// A single aggregation within a request, along with the aggregations nested
+/// within each of its buckets.
+pub struct Aggregation {
+    #[serde(flatten)]
+    /// The kind of aggregation.
+    pub kind: AggregationKind,
+    #[serde(default, alias = "aggs")]
+    /// Aggregations computed over the documents of each bucket, keyed by their name.
+    ///
+    /// Only `terms`, `histogram` and `date_histogram` aggregations have buckets.
+    pub aggregations: BTreeMap<String, Aggregation>,
+}
+
+impl Aggregation {
+    fn compile(self, ctx: &QueryContext) -> Result<CompiledAggregation, QueryError> {
+        let is_bucket = matches!(
+            self.kind,
+            AggregationKind::Terms(_)
+                | AggregationKind::Histogram(_)
+                | AggregationKind::DateHistogram(_)
+        );
+        if !is_bucket && !self.aggregations.is_empty() {
+            return Err(QueryError::Invalid(
+                "Sub-aggregations can only be nested within terms, histogram and date_histogram aggregations"
+                    .to_string(),
+            ));
+        }
+
+        let sub = CompiledAggregations::compile(ctx, self.aggregations)?;
+        match self.kind {
+            AggregationKind::Terms(aggregation) => aggregation
+                .compile(ctx, sub)
+                .map(CompiledAggregation::Terms),
+            AggregationKind::Histogram(aggregation) => aggregation
+                .compile(ctx, sub)
+                .map(CompiledAggregation::Histogram),
+            AggregationKind::DateHistogram(aggregation) => aggregation
+                .compile(ctx, sub)
+                .map(CompiledAggregation::DateHistogram),
+            AggregationKind::Stats(aggregation) => aggregation
+                .compile(ctx, MetricKind::Stats)
+                .map(CompiledAggregation::Metric),
+            AggregationKind::Min(aggregation) => aggregation
+                .compile(ctx, MetricKind::Min)
+                .map(CompiledAggregation::Metric),
+            AggregationKind::Max(aggregation) => aggregation
+                .compile(ctx, MetricKind::Max)
+                .map(CompiledAggregation::Metric),
+            AggregationKind::Sum(aggregation) => aggregation
+                .compile(ctx, MetricKind::Sum)
+                .map(CompiledAggregation::Metric),
+            AggregationKind::Avg(aggregation) => aggregation
+                .compile(ctx, MetricKind::Avg)
+                .map(CompiledAggregation::Metric),
+            AggregationKind::Percentiles(aggregation) => aggregation
+                .compile(ctx)
+                .map(CompiledAggregation::Percentiles),
+            AggregationKind::Cardinality(aggregation) => aggregation
+                .compile(ctx)
+                .map(CompiledAggregation::Cardinality),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// The kind of an aggregation.
+///
+/// Aggregations are externally tagged, i.e. `{"terms": {"field": "brand"}}`.
+pub enum AggregationKind {
+    /// Counts the documents with each distinct value of a field.
+    Terms(TermsAggregation),
+    /// Counts the documents within fixed width ranges of a numeric field.
+    Histogram(HistogramAggregation),
+    /// Counts the documents within each interval of time of a datetime field.
+    DateHistogram(DateHistogramAggregation),
+    /// Computes the count, min, max, sum and average of a numeric field.
+    Stats(MetricAggregation),
+    /// Computes the smallest value of a numeric field.
+    Min(MetricAggregation),
+    /// Computes the largest value of a numeric field.
+    Max(MetricAggregation),
+    /// Computes the sum of the values of a numeric field.
+    Sum(MetricAggregation),
+    /// Computes the average of the values of a numeric field.
+    Avg(MetricAggregation),
+    /// Approximates the percentiles of a numeric field.
+    Percentiles(PercentilesAggregation),
+    /// Approximates the number of distinct values of a field.
+    Cardinality(CardinalityAggregation),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+/// The result of a single aggregation.
+pub enum AggregationResult {
+    /// The result of a `terms` aggregation.
+    Terms(TermsResult),
+    /// The result of a `histogram` aggregation.
+    Histogram(HistogramResult),
+    /// The result of a `date_histogram` aggregation.
+    DateHistogram(DateHistogramResult),
+    /// The result of a `stats` aggregation.
+    Stats(StatsResult),
+    /// The result of a `min`, `max`, `sum` or `avg` aggregation.
+    Metric(MetricResult),
+    /// The result of a `percentiles` aggregation.
+    Percentiles(PercentilesResult),
+    /// The result of a `cardinality` aggregation.
+    Cardinality(CardinalityResult),
+}
+
+/// The results of each aggregation of a request, keyed by their name.
+pub type AggregationResults = BTreeMap<String, AggregationResult>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+/// The key of a bucket, the value of the aggregated field.
+pub enum AggregationKey {
+    /// A string value.
+    Str(String),
+    /// An unsigned integer value.
+    U64(u64),
+    /// A signed integer value.
+    I64(i64),
+    /// A float value.
+    F64(f64),
+    /// A boolean value.
+    Bool(bool),
+}
+
+impl AggregationKey {
+    /// Orders keys of different types, numbers of any type are compared by value.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Bool(_) => 0,
+            Self::U64(_) | Self::I64(_) | Self::F64(_) => 1,
+            Self::Str(_) => 2,
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match self {
+            Self::U64(v) => *v as f64,
+            Self::I64(v) => *v as f64,
+            Self::F64(v) => *v,
+            Self::Bool(v) => *v as u8 as f64,
+            Self::Str(_) => f64::NAN,
+        }
+    }
+}
+
+impl Ord for AggregationKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Str(a), Self::Str(b)) => a.cmp(b),
+            (Self::U64(a), Self::U64(b)) => a.cmp(b),
+            (Self::I64(a), Self::I64(b)) => a.cmp(b),
+            (Self::F64(a), Self::F64(b)) => a.total_cmp(b),
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::U64(a), Self::I64(b)) => (*a as i128).cmp(&(*b as i128)),
+            (Self::I64(a), Self::U64(b)) => (*a as i128).cmp(&(*b as i128)),
+            (a, b) if a.rank() == 1 && b.rank() == 1 => {
+                a.as_f64().total_cmp(&b.as_f64())
+            },
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+impl PartialOrd for AggregationKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for AggregationKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for AggregationKey {}
+
+/// Compiles the named aggregations of a request into a collector.
+pub fn aggregation_collector(
+    ctx: &QueryContext,
+    aggregations: BTreeMap<String, Aggregation>,
+) -> Result<AggregationCollector, QueryError> {
+    let aggregations = CompiledAggregations::compile(ctx, aggregations)?;
+    Ok(AggregationCollector {
+        aggregations: Arc::new(aggregations),
+    })
+}
+
+#[derive(Debug, Default)]
+/// The compiled aggregations of a single level of the aggregation tree, ordered
+/// by their name.
+pub(crate) struct CompiledAggregations {
+    aggregations: Vec<(String, CompiledAggregation)>,
+}
+
+impl CompiledAggregations {
+    fn compile(
+        ctx: &QueryContext,
+        aggregations: BTreeMap<String, Aggregation>,
+    ) -> Result<Self, QueryError> {
+        let aggregations = aggregations
+            .into_iter()
+            .map(|(name, aggregation)| Ok((name, aggregation.compile(ctx)?)))
+            .collect::<Result<Vec<_>, QueryError>>()?;
+        Ok(Self { aggregations })
+    }
+
+    /// Binds the aggregations to the columns of a segment.
+    pub(crate) fn for_segment(&self, reader: &SegmentReader) -> SegmentAggregations {
+        let aggregations = self
+            .aggregations
+            .iter()
+            .map(|(_, aggregation)| aggregation.for_segment(reader))
+            .collect();
+        SegmentAggregations { aggregations }
+    }
+
+    /// Computes the final results from the merged intermediate results, aggregations
+    /// without an intermediate result are finalized as if they saw no documents.
+    pub(crate) fn finalize(
+        &self,
+        intermediates: Vec<IntermediateAggregation>,
+    ) -> tantivy::Result<AggregationResults> {
+        let mut intermediates = intermediates.into_iter();
+        let mut results = AggregationResults::new();
+        for (name, aggregation) in self.aggregations.iter() {
+            let intermediate = match intermediates.next() {
+                Some(intermediate) => intermediate,
+                None => aggregation.empty(),
+            };
+            results.insert(name.clone(), aggregation.finalize(intermediate)?);
+        }
+        Ok(results)
+    }
+}
+
+#[derive(Clone, Default)]
+/// The aggregations of a single level of the aggregation tree bound to a segment.
+pub(crate) struct SegmentAggregations {
+    aggregations: Vec<SegmentAggregation>,
+}
+
+impl SegmentAggregations {
+    #[inline]
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        for aggregation in self.aggregations.iter_mut() {
+            aggregation.collect(doc);
+        }
+    }
+
+    pub(crate) fn harvest(self) -> Vec<IntermediateAggregation> {
+        self.aggregations
+            .into_iter()
+            .map(SegmentAggregation::harvest)
+            .collect()
+    }
+}
+
+/// Merges the intermediate results of the same level of the aggregation tree, an
+/// empty set of results is one which saw no documents.
+pub(crate) fn merge_intermediates(
+    merged: &mut Vec<IntermediateAggregation>,
+    other: Vec<IntermediateAggregation>,
+) {
+    if merged.is_empty() {
+        *merged = other;
+        return;
+    }
+
+    for (merged, other) in merged.iter_mut().zip(other) {
+        merged.merge(other);
+    }
+}
+
+#[derive(Clone)]
+/// A bucket of a bucket aggregation within a single segment, along with its
+/// sub-aggregations.
+pub(crate) struct SegmentBucket {
+    doc_count: u64,
+    sub: SegmentAggregations,
+}
+
+impl SegmentBucket {
+    /// Creates an empty bucket from the sub-aggregations bound to the segment.
+    pub(crate) fn new(sub: &SegmentAggregations) -> Self {
+        Self {
+            doc_count: 0,
+            sub: sub.clone(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        self.doc_count += 1;
+        self.sub.collect(doc);
+    }
+
+    pub(crate) fn harvest(self) -> IntermediateBucket {
+        IntermediateBucket {
+            doc_count: self.doc_count,
+            sub: self.sub.harvest(),
+        }
+    }
+}
+
+#[derive(Default)]
+/// The document count and intermediate sub-aggregation results of a bucket.
+pub(crate) struct IntermediateBucket {
+    pub(crate) doc_count: u64,
+    sub: Vec<IntermediateAggregation>,
+}
+
+impl IntermediateBucket {
+    pub(crate) fn merge(&mut self, other: IntermediateBucket) {
+        self.doc_count += other.doc_count;
+        merge_intermediates(&mut self.sub, other.sub);
+    }
+
+    /// Finalizes the sub-aggregations of the bucket.
+    pub(crate) fn finalize(
+        self,
+        sub: &CompiledAggregations,
+    ) -> tantivy::Result<AggregationResults> {
+        sub.finalize(self.sub)
+    }
+}
+
+#[derive(Debug)]
+/// A compiled aggregation.
+enum CompiledAggregation {
+    Terms(CompiledTerms),
+    Histogram(CompiledHistogram),
+    DateHistogram(CompiledDateHistogram),
+    Metric(CompiledMetric),
+    Percentiles(CompiledPercentiles),
+    Cardinality(CompiledCardinality),
+}
+
+impl CompiledAggregation {
+    fn for_segment(&self, reader: &SegmentReader) -> SegmentAggregation {
+        match self {
+            Self::Terms(terms) => SegmentAggregation::Terms(terms.for_segment(reader)),
+            Self::Histogram(histogram) => {
+                SegmentAggregation::Histogram(histogram.for_segment(reader))
+            },
+            Self::DateHistogram(histogram) => {
+                SegmentAggregation::DateHistogram(histogram.for_segment(reader))
+            },
+            Self::Metric(metric) => {
+                SegmentAggregation::Metric(metric.for_segment(reader))
+            },
+            Self::Percentiles(percentiles) => {
+                SegmentAggregation::Percentiles(percentiles.for_segment(reader))
+            },
+            Self::Cardinality(cardinality) => {
+                SegmentAggregation::Cardinality(cardinality.for_segment(reader))
+            },
+        }
+    }
+
+    /// The intermediate result of an aggregation which saw no segments.
+    fn empty(&self) -> IntermediateAggregation {
+        match self {
+            Self::Terms(_) => {
+                IntermediateAggregation::Terms(IntermediateTerms::default())
+            },
+            Self::Histogram(_) | Self::DateHistogram(_) => {
+                IntermediateAggregation::Histogram(IntermediateHistogram::default())
+            },
+            Self::Metric(_) => {
+                IntermediateAggregation::Stats(IntermediateStats::default())
+            },
+            Self::Percentiles(percentiles) => {
+                IntermediateAggregation::Percentiles(percentiles.empty())
+            },
+            Self::Cardinality(cardinality) => {
+                IntermediateAggregation::Cardinality(cardinality.empty())
+            },
+        }
+    }
+
+    fn finalize(
+        &self,
+        intermediate: IntermediateAggregation,
+    ) -> tantivy::Result<AggregationResult> {
+        let result = match (self, intermediate) {
+            (Self::Terms(terms), IntermediateAggregation::Terms(intermediate)) => {
+                AggregationResult::Terms(terms.finalize(intermediate)?)
+            },
+            (
+                Self::Histogram(histogram),
+                IntermediateAggregation::Histogram(intermediate),
+            ) => AggregationResult::Histogram(histogram.finalize(intermediate)?),
+            (
+                Self::DateHistogram(histogram),
+                IntermediateAggregation::Histogram(intermediate),
+            ) => AggregationResult::DateHistogram(histogram.finalize(intermediate)?),
+            (Self::Metric(metric), IntermediateAggregation::Stats(stats)) => {
+                metric.finalize(stats)
+            },
+            (
+                Self::Percentiles(percentiles),
+                IntermediateAggregation::Percentiles(digest),
+            ) => AggregationResult::Percentiles(percentiles.finalize(digest)),
+            (
+                Self::Cardinality(cardinality),
+                IntermediateAggregation::Cardinality(sketch),
+            ) => AggregationResult::Cardinality(cardinality.finalize(sketch)),
+            _ => unreachable!("intermediate results always match their aggregation"),
+        };
+        Ok(result)
+    }
+}
+
+#[derive(Clone)]
+/// An aggregation bound to the columns of a single segment.
+enum SegmentAggregation {
+    Terms(SegmentTerms),
+    Histogram(SegmentHistogram),
+    DateHistogram(SegmentDateHistogram),
+    Metric(SegmentMetric),
+    Percentiles(SegmentPercentiles),
+    Cardinality(SegmentCardinality),
+}
+
+impl SegmentAggregation {
+    fn collect(&mut self, doc: DocId) {
+        match self {
+            Self::Terms(terms) => terms.collect(doc),
+            Self::Histogram(histogram) => histogram.collect(doc),
+            Self::DateHistogram(histogram) => histogram.collect(doc),
+            Self::Metric(metric) => metric.collect(doc),
+            Self::Percentiles(percentiles) => percentiles.collect(doc),
+            Self::Cardinality(cardinality) => cardinality.collect(doc),
+        }
+    }
+
+    fn harvest(self) -> IntermediateAggregation {
+        match self {
+            Self::Terms(terms) => IntermediateAggregation::Terms(terms.harvest()),
+            Self::Histogram(histogram) => {
+                IntermediateAggregation::Histogram(histogram.harvest())
+            },
+            Self::DateHistogram(histogram) => {
+                IntermediateAggregation::Histogram(histogram.harvest())
+            },
+            Self::Metric(metric) => IntermediateAggregation::Stats(metric.harvest()),
+            Self::Percentiles(percentiles) => {
+                IntermediateAggregation::Percentiles(percentiles.harvest())
+            },
+            Self::Cardinality(cardinality) => {
+                IntermediateAggregation::Cardinality(cardinality.harvest())
+            },
+        }
+    }
+}
+
+/// The result of an aggregation over a single segment, which can be merged with
+/// the results of the other segments.
+pub enum IntermediateAggregation {
+    Terms(IntermediateTerms),
+    Histogram(IntermediateHistogram),
+    Stats(IntermediateStats),
+    Percentiles(TDigest),
+    Cardinality(HyperLogLog),
+}
+
+impl IntermediateAggregation {
+    fn merge(&mut self, other: IntermediateAggregation) {
+        match (self, other) {
+            (Self::Terms(terms), Self::Terms(other)) => terms.merge(other),
+            (Self::Histogram(histogram), Self::Histogram(other)) => {
+                histogram.merge(other)
+            },
+            (Self::Stats(stats), Self::Stats(other)) => stats.merge(other),
+            (Self::Percentiles(digest), Self::Percentiles(other)) => digest.merge(other),
+            (Self::Cardinality(sketch), Self::Cardinality(other)) => sketch.merge(other),
+            _ => unreachable!("intermediate results always match their aggregation"),
+        }
+    }
+}
+
+/// Computes the aggregations of a request over all matching documents.
+///
+/// This can be combined with other collectors via a tuple, i.e.
+/// `(TopDocs::with_limit(10), aggregations)`.
+pub struct AggregationCollector {
+    aggregations: Arc<CompiledAggregations>,
+}
+
+impl Collector for AggregationCollector {
+    type Fruit = AggregationResults;
+    type Child = AggregationSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(AggregationSegmentCollector {
+            aggregations: self.aggregations.for_segment(reader),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<Vec<IntermediateAggregation>>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut merged = Vec::new();
+        for fruit in segment_fruits {
+            merge_intermediates(&mut merged, fruit);
+        }
+        self.aggregations.finalize(merged)
+    }
+}
+
+/// The per-segment collector of an [AggregationCollector].
+pub struct AggregationSegmentCollector {
+    aggregations: SegmentAggregations,
+}
+
+impl SegmentCollector for AggregationSegmentCollector {
+    type Fruit = Vec<IntermediateAggregation>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.aggregations.collect(doc);
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.aggregations.harvest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, FAST, STRING};
+    use tantivy::{doc, DateTime, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_sub_aggregations() {
+        let mut schema = SchemaBuilder::new();
+        let brand = schema.add_text_field("brand", STRING | FAST);
+        let timestamp = schema.add_date_field("timestamp", FAST);
+        let price = schema.add_f64_field("price", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        // 2023-01-30T22:30:00Z and 2023-01-31T10:00:00Z
+        let (evening, morning) = (1_675_117_800, 1_675_159_200);
+        let mut writer = index.writer(15_000_000).unwrap();
+        for (name, secs, value) in [
+            ("acme", evening, 10.0),
+            ("acme", morning, 20.0),
+            ("globex", evening, 5.0),
+        ] {
+            writer
+                .add_document(doc!(
+                    brand => name,
+                    timestamp => DateTime::from_timestamp_secs(secs),
+                    price => value,
+                ))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        for (name, value) in [("acme", 40.0), ("initech", 7.0)] {
+            writer
+                .add_document(doc!(
+                    brand => name,
+                    timestamp => DateTime::from_timestamp_secs(morning),
+                    price => value,
+                ))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let aggregations: BTreeMap<String, Aggregation> = serde_json::from_str(
+            r#"{
+                "brands": {
+                    "terms": {"field": "brand", "size": 1, "other_bucket_key": "other"},
+                    "aggs": {
+                        "daily": {
+                            "date_histogram": {
+                                "field": "timestamp",
+                                "calendar_interval": "day",
+                                "extended_bounds": {"min": "2023-01-30T00:00:00Z", "max": "2023-02-01T00:00:00Z"}
+                            },
+                            "aggs": {"price": {"avg": {"field": "price"}}}
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let collector = aggregation_collector(&ctx, aggregations).unwrap();
+        let (count, results) = searcher.search(&AllQuery, &(Count, collector)).unwrap();
+        assert_eq!(count, 5);
+
+        let daily = |first: f64, second: f64, second_count: u64| {
+            json!({"buckets": [
+                {
+                    "key": 1_675_036_800_000i64,
+                    "key_as_string": "2023-01-30T00:00:00Z",
+                    "doc_count": 1,
+                    "price": {"value": first},
+                },
+                {
+                    "key": 1_675_123_200_000i64,
+                    "key_as_string": "2023-01-31T00:00:00Z",
+                    "doc_count": second_count,
+                    "price": {"value": second},
+                },
+                {
+                    "key": 1_675_209_600_000i64,
+                    "key_as_string": "2023-02-01T00:00:00Z",
+                    "doc_count": 0,
+                    "price": {"value": null},
+                },
+            ]})
+        };
+        assert_eq!(
+            serde_json::to_value(&results).unwrap(),
+            json!({
+                "brands": {
+                    "buckets": [
+                        {"key": "acme", "doc_count": 3, "daily": daily(10.0, 30.0, 2)},
+                        {"key": "other", "doc_count": 2, "daily": daily(5.0, 7.0, 1)},
+                    ],
+                    "sum_other_doc_count": 2,
+                }
+            })
+        );
+
+        for json in [
+            r#"{"agg": {"avg": {"field": "price"}, "aggs": {"brands": {"terms": {"field": "brand"}}}}}"#,
+            r#"{"agg": {"terms": {"field": "brand"}, "aggs": {"price": {"avg": {"field": "brand"}}}}}"#,
+        ] {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            assert!(aggregation_collector(&ctx, aggregations).is_err());
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use tantivy::{DocId, SegmentReader};
+
+use super::column::resolve_numeric_column;
+use crate::column::{FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+/// The maximum number of percentiles a single aggregation may compute.
+const MAX_PERCENTS: usize = 100;
+
+fn default_percents() -> Vec<f64> {
+    vec![1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0]
+}
+
+fn default_compression() -> f64 {
+    100.0
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Approximates the percentiles of the values of a numeric fast field, i.e. the
+/// 95th and 99th percentile request latency for a dashboard.
+///
+/// Values are summarised with a t-digest, which is most accurate for the extreme
+/// percentiles and merges cheaply across segments.
+pub struct PercentilesAggregation {
+    /// The numeric fast field to aggregate.
+    pub field: String,
+    #[serde(default = "default_percents")]
+    /// The percentiles to compute, between `0` and `100`.
+    ///
+    /// Defaults to `[1, 5, 25, 50, 75, 95, 99]`.
+    pub percents: Vec<f64>,
+    #[serde(default = "default_compression")]
+    /// Trades memory for accuracy, higher values keep more centroids, between `10`
+    /// and `1000`.
+    ///
+    /// Defaults to `100`.
+    pub compression: f64,
+    #[serde(default)]
+    /// The value used for documents without a value, which are otherwise ignored.
+    pub missing: Option<f64>,
+}
+
+impl PercentilesAggregation {
+    pub(crate) fn compile(
+        self,
+        ctx: &QueryContext,
+    ) -> Result<CompiledPercentiles, QueryError> {
+        let column = resolve_numeric_column(ctx, &self.field, "percentiles")?;
+
+        if self.percents.is_empty() || self.percents.len() > MAX_PERCENTS {
+            return Err(QueryError::Invalid(format!(
+                "A percentiles aggregation must compute between 1 and {MAX_PERCENTS} percentiles"
+            )));
+        }
+        if let Some(percent) = self
+            .percents
+            .iter()
+            .find(|percent| !(0.0..=100.0).contains(*percent))
+        {
+            return Err(QueryError::Invalid(format!(
+                "Percentiles must be between 0 and 100, got {percent}"
+            )));
+        }
+        if !(10.0..=1000.0).contains(&self.compression) {
+            return Err(QueryError::Invalid(format!(
+                "The compression of a percentiles aggregation must be between 10 and 1000, got {}",
+                self.compression
+            )));
+        }
+        if let Some(missing) = self.missing {
+            if !missing.is_finite() {
+                return Err(QueryError::invalid_value(
+                    &self.field,
+                    "the missing value must be a finite number",
+                ));
+            }
+        }
+
+        Ok(CompiledPercentiles {
+            column,
+            percents: self.percents,
+            compression: self.compression,
+            missing: self.missing,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// A single percentile of a `percentiles` aggregation.
+pub struct PercentileValue {
+    /// The requested percentile, i.e. `99`.
+    pub key: f64,
+    /// The approximate value at the percentile, `null` if there are no values.
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The result of a `percentiles` aggregation.
+pub struct PercentilesResult {
+    /// The percentiles in the order they were requested.
+    pub values: Vec<PercentileValue>,
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledPercentiles {
+    column: FastColumn,
+    percents: Vec<f64>,
+    compression: f64,
+    missing: Option<f64>,
+}
+
+impl CompiledPercentiles {
+    pub(crate) fn for_segment(&self, reader: &SegmentReader) -> SegmentPercentiles {
+        SegmentPercentiles {
+            column: self.column.open(reader),
+            missing: self.missing,
+            digest: self.empty(),
+            values: Vec::new(),
+        }
+    }
+
+    /// A digest which has seen no values.
+    pub(crate) fn empty(&self) -> TDigest {
+        TDigest::new(self.compression)
+    }
+
+    pub(crate) fn finalize(&self, mut digest: TDigest) -> PercentilesResult {
+        digest.compress();
+        let values = self
+            .percents
+            .iter()
+            .map(|percent| PercentileValue {
+                key: *percent,
+                value: digest.quantile(percent / 100.0),
+            })
+            .collect();
+        PercentilesResult { values }
+    }
+}
+
+#[derive(Clone)]
+/// Summarises the values of a single segment.
+pub(crate) struct SegmentPercentiles {
+    column: Option<SegmentColumn>,
+    missing: Option<f64>,
+    digest: TDigest,
+    values: Vec<f64>,
+}
+
+impl SegmentPercentiles {
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        match self.column.as_ref() {
+            Some(column) => column.numeric_values(doc, &mut self.values),
+            None => self.values.clear(),
+        }
+
+        if self.values.is_empty() {
+            if let Some(missing) = self.missing {
+                self.digest.add(missing);
+            }
+            return;
+        }
+
+        for value in self.values.iter() {
+            if value.is_finite() {
+                self.digest.add(*value);
+            }
+        }
+    }
+
+    pub(crate) fn harvest(mut self) -> TDigest {
+        self.digest.compress();
+        self.digest
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+#[derive(Debug, Clone)]
+/// A merging t-digest, which summarises a distribution as a set of weighted
+/// centroids that are kept small near the tails so extreme quantiles stay accurate.
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    fn new(compression: f64) -> Self {
+        Self {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() >= (self.compression * 10.0) as usize {
+            self.compress();
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: TDigest) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.buffer.extend(other.buffer);
+        self.centroids.extend(other.centroids);
+        self.compress();
+    }
+
+    /// Merges the buffered values into the centroids.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() && self.centroids.len() <= 1 {
+            return;
+        }
+
+        let mut pending = std::mem::take(&mut self.centroids);
+        pending.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+        );
+        pending.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total = pending.iter().map(|centroid| centroid.weight).sum::<f64>();
+        let mut pending = pending.into_iter();
+        let Some(mut current) = pending.next() else {
+            return;
+        };
+
+        let mut weight_before = 0.0;
+        for centroid in pending {
+            let weight = current.weight + centroid.weight;
+            // The k1 scale function bounds each centroid's weight by its quantile.
+            let q = (weight_before + weight / 2.0) / total;
+            let limit = 4.0 * total * q * (1.0 - q) / self.compression;
+            if weight <= limit {
+                current.mean +=
+                    (centroid.mean - current.mean) * centroid.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_before += current.weight;
+                self.centroids.push(current);
+                current = centroid;
+            }
+        }
+        self.centroids.push(current);
+    }
+
+    /// The approximate value at the quantile, between `0` and `1`, of a compressed
+    /// digest.
+    fn quantile(&self, q: f64) -> Option<f64> {
+        let first = self.centroids.first()?;
+        let last = self.centroids.last()?;
+        if self.centroids.len() == 1 {
+            return Some(first.mean);
+        }
+
+        let total = self
+            .centroids
+            .iter()
+            .map(|centroid| centroid.weight)
+            .sum::<f64>();
+        let target = q * total;
+
+        // Each centroid's mean sits at the middle of its weight, values between the
+        // centres of neighbouring centroids are interpolated.
+        let first_center = first.weight / 2.0;
+        if target <= first_center {
+            let ratio = target / first_center;
+            return Some(self.min + (first.mean - self.min) * ratio);
+        }
+
+        let mut center = first_center;
+        for pair in self.centroids.windows(2) {
+            let next_center = center + (pair[0].weight + pair[1].weight) / 2.0;
+            if target <= next_center {
+                let ratio = (target - center) / (next_center - center);
+                return Some(pair[0].mean + (pair[1].mean - pair[0].mean) * ratio);
+            }
+            center = next_center;
+        }
+
+        let remaining = total - center;
+        if remaining <= 0.0 {
+            return Some(last.mean);
+        }
+        let ratio = (target - center) / remaining;
+        Some(last.mean + (self.max - last.mean) * ratio.min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, FAST};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::aggregations::{aggregation_collector, Aggregation, AggregationResult};
+
+    #[test]
+    fn test_tdigest_quantiles() {
+        let mut digest = TDigest::new(100.0);
+        for value in 1..=100 {
+            digest.add(value as f64);
+        }
+        digest.compress();
+        assert_eq!(digest.quantile(0.5), Some(50.5));
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(1.0), Some(100.0));
+
+        // Digests merged from shuffled parts stay close to the exact quantiles.
+        let mut merged = TDigest::new(100.0);
+        for part in 0..10 {
+            let mut digest = TDigest::new(100.0);
+            for value in (0..100_000).filter(|value| value % 10 == part) {
+                digest.add(value as f64);
+            }
+            digest.compress();
+            merged.merge(digest);
+        }
+        assert!(merged.centroids.len() < 1_000);
+        for q in [0.01, 0.5, 0.95, 0.999] {
+            let value = merged.quantile(q).unwrap();
+            let expected = q * 100_000.0;
+            assert!(
+                (value - expected).abs() < 100_000.0 * 0.005,
+                "quantile {q} was {value}, expected {expected}"
+            );
+        }
+
+        assert_eq!(TDigest::new(100.0).quantile(0.5), None);
+    }
+
+    #[test]
+    fn test_percentiles_aggregation() {
+        let mut schema = SchemaBuilder::new();
+        let latency = schema.add_u64_field("latency", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for value in 1..=50u64 {
+            writer.add_document(doc!(latency => value)).unwrap();
+        }
+        writer.commit().unwrap();
+        for value in 51..=100u64 {
+            writer.add_document(doc!(latency => value)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let aggregations: BTreeMap<String, Aggregation> = serde_json::from_str(
+            r#"{"latency": {"percentiles": {"field": "latency", "percents": [50, 99]}}}"#,
+        )
+        .unwrap();
+        let collector = aggregation_collector(&ctx, aggregations).unwrap();
+        let (count, results) = searcher.search(&AllQuery, &(Count, collector)).unwrap();
+        assert_eq!(count, 100);
+        assert_eq!(
+            results["latency"],
+            AggregationResult::Percentiles(PercentilesResult {
+                values: vec![
+                    PercentileValue {
+                        key: 50.0,
+                        value: Some(50.5),
+                    },
+                    PercentileValue {
+                        key: 99.0,
+                        value: Some(99.5),
+                    },
+                ],
+            })
+        );
+
+        for json in [
+            r#"{"agg": {"percentiles": {"field": "latency", "percents": [101]}}}"#,
+            r#"{"agg": {"percentiles": {"field": "latency", "percents": []}}}"#,
+            r#"{"agg": {"percentiles": {"field": "latency", "compression": 1}}}"#,
+        ] {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            assert!(aggregation_collector(&ctx, aggregations).is_err());
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tantivy::{DocId, SegmentReader};
+
+use super::column::resolve_column;
+use super::{
+    AggregationKey,
+    AggregationResults,
+    CompiledAggregations,
+    IntermediateBucket,
+    SegmentAggregations,
+    SegmentBucket,
+    MAX_AGGREGATION_BUCKETS,
+};
+use crate::column::{ColumnType, FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+fn default_size() -> usize {
+    10
+}
+
+fn default_min_doc_count() -> u64 {
+    1
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// The order buckets are returned in, ties are broken by the key in ascending order.
+pub enum BucketOrder {
+    #[default]
+    /// The buckets with the most documents first.
+    CountDesc,
+    /// The buckets with the fewest documents first.
+    CountAsc,
+    /// The buckets ordered by their key, smallest first.
+    KeyAsc,
+    /// The buckets ordered by their key, largest first.
+    KeyDesc,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Counts the documents with each distinct value of a string, numeric or boolean
+/// fast field, returning the top `size` values, i.e. the brands of the matching
+/// products for faceted navigation.
+///
+/// A document with several values for the field is counted once in each of their
+/// buckets, documents without a value are not counted.
+pub struct TermsAggregation {
+    /// The fast field to count the values of.
+    pub field: String,
+    #[serde(default = "default_size")]
+    /// The number of buckets to return.
+    ///
+    /// Defaults to `10`.
+    pub size: usize,
+    #[serde(default)]
+    /// The order of the buckets, which also decides which buckets are the top
+    /// `size` buckets.
+    ///
+    /// Defaults to `count_desc`.
+    pub order: BucketOrder,
+    #[serde(default = "default_min_doc_count")]
+    /// The minimum number of documents a bucket must have to be returned.
+    ///
+    /// Defaults to `1`.
+    pub min_doc_count: u64,
+    #[serde(default)]
+    /// The key of an extra bucket appended after the top buckets counting the
+    /// documents of every other value, i.e. `"Other"`. Its sub-aggregations are
+    /// computed over the documents of every other value.
+    ///
+    /// The count is always returned as `sum_other_doc_count`.
+    pub other_bucket_key: Option<String>,
+}
+
+impl TermsAggregation {
+    pub(crate) fn compile(
+        self,
+        ctx: &QueryContext,
+        sub: CompiledAggregations,
+    ) -> Result<CompiledTerms, QueryError> {
+        let column = resolve_column(ctx, &self.field, "terms")?;
+        if column.kind() == ColumnType::Date {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "terms",
+                "datetime fields must be aggregated with a date_histogram",
+            ));
+        }
+
+        if self.size == 0 || self.size > MAX_AGGREGATION_BUCKETS {
+            return Err(QueryError::Invalid(format!(
+                "The size of a terms aggregation must be between 1 and {MAX_AGGREGATION_BUCKETS}, got {}",
+                self.size
+            )));
+        }
+
+        Ok(CompiledTerms {
+            column,
+            size: self.size,
+            order: self.order,
+            min_doc_count: self.min_doc_count,
+            other_bucket_key: self.other_bucket_key,
+            sub,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// A single value of a `terms` aggregation.
+pub struct TermsBucket {
+    /// The value of the field.
+    pub key: AggregationKey,
+    /// The number of matching documents with the value.
+    pub doc_count: u64,
+    #[serde(flatten)]
+    /// The results of the sub-aggregations over the documents of the bucket.
+    pub aggregations: AggregationResults,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The result of a `terms` aggregation.
+pub struct TermsResult {
+    /// The top buckets in order, followed by the other bucket if requested.
+    pub buckets: Vec<TermsBucket>,
+    /// The number of documents counted in buckets which were not returned.
+    pub sum_other_doc_count: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledTerms {
+    column: FastColumn,
+    size: usize,
+    order: BucketOrder,
+    min_doc_count: u64,
+    other_bucket_key: Option<String>,
+    sub: CompiledAggregations,
+}
+
+impl CompiledTerms {
+    pub(crate) fn for_segment(&self, reader: &SegmentReader) -> SegmentTerms {
+        SegmentTerms {
+            column: self.column.open(reader),
+            buckets: HashMap::new(),
+            values: Vec::new(),
+            sub: self.sub.for_segment(reader),
+        }
+    }
+
+    pub(crate) fn finalize(
+        &self,
+        intermediate: IntermediateTerms,
+    ) -> tantivy::Result<TermsResult> {
+        let mut buckets = intermediate.buckets.into_iter().collect::<Vec<_>>();
+
+        // The keys are already in ascending order, so a stable sort keeps ties ordered.
+        match self.order {
+            BucketOrder::CountDesc => {
+                buckets.sort_by(|(_, a), (_, b)| b.doc_count.cmp(&a.doc_count))
+            },
+            BucketOrder::CountAsc => {
+                buckets.sort_by(|(_, a), (_, b)| a.doc_count.cmp(&b.doc_count))
+            },
+            BucketOrder::KeyAsc => {},
+            BucketOrder::KeyDesc => buckets.reverse(),
+        }
+
+        let mut other = IntermediateBucket::default();
+        let mut top = Vec::with_capacity(self.size.min(buckets.len()));
+        for (key, bucket) in buckets {
+            if top.len() < self.size && bucket.doc_count >= self.min_doc_count {
+                top.push(TermsBucket {
+                    key,
+                    doc_count: bucket.doc_count,
+                    aggregations: bucket.finalize(&self.sub)?,
+                });
+            } else if self.other_bucket_key.is_some() {
+                other.merge(bucket);
+            } else {
+                other.doc_count += bucket.doc_count;
+            }
+        }
+
+        let sum_other_doc_count = other.doc_count;
+        if let Some(key) = self.other_bucket_key.as_ref() {
+            top.push(TermsBucket {
+                key: AggregationKey::Str(key.clone()),
+                doc_count: sum_other_doc_count,
+                aggregations: other.finalize(&self.sub)?,
+            });
+        }
+
+        Ok(TermsResult {
+            buckets: top,
+            sum_other_doc_count,
+        })
+    }
+}
+
+#[derive(Clone)]
+/// Counts the values of a single segment by their raw value.
+pub(crate) struct SegmentTerms {
+    column: Option<SegmentColumn>,
+    buckets: HashMap<u64, SegmentBucket>,
+    values: Vec<u64>,
+    sub: SegmentAggregations,
+}
+
+impl SegmentTerms {
+    pub(crate) fn collect(&mut self, doc: DocId) {
+        let Some(column) = self.column.as_ref() else {
+            return;
+        };
+
+        column.raw_values(doc, &mut self.values);
+        for value in self.values.iter() {
+            self.buckets
+                .entry(*value)
+                .or_insert_with(|| SegmentBucket::new(&self.sub))
+                .collect(doc);
+        }
+    }
+
+    pub(crate) fn harvest(self) -> IntermediateTerms {
+        let mut intermediate = IntermediateTerms::default();
+        if let Some(column) = self.column.as_ref() {
+            for (raw, bucket) in self.buckets {
+                if let Some(key) = column.key(raw) {
+                    intermediate.insert(key, bucket.harvest());
+                }
+            }
+        }
+        intermediate
+    }
+}
+
+#[derive(Default)]
+/// The buckets of each value of a `terms` aggregation, keyed by the value itself so
+/// they can be merged across segments.
+pub struct IntermediateTerms {
+    buckets: BTreeMap<AggregationKey, IntermediateBucket>,
+}
+
+impl IntermediateTerms {
+    fn insert(&mut self, key: AggregationKey, bucket: IntermediateBucket) {
+        match self.buckets.get_mut(&key) {
+            Some(existing) => existing.merge(bucket),
+            None => {
+                self.buckets.insert(key, bucket);
+            },
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: IntermediateTerms) {
+        for (key, bucket) in other.buckets {
+            self.insert(key, bucket);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, FAST, STRING, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::aggregations::{aggregation_collector, Aggregation, AggregationResult};
+
+    #[test]
+    fn test_terms_aggregation() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let brand = schema.add_text_field("brand", STRING | FAST);
+        let rating = schema.add_u64_field("rating", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for (name, rating_value) in [("acme", 5u64), ("acme", 4), ("globex", 5)] {
+            writer
+                .add_document(
+                    doc!(title => "shoe", brand => name, rating => rating_value),
+                )
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        for (name, rating_value) in [("initech", 3u64), ("acme", 5)] {
+            writer
+                .add_document(
+                    doc!(title => "shoe", brand => name, rating => rating_value),
+                )
+                .unwrap();
+        }
+        writer.add_document(doc!(title => "shoe")).unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let aggregate = |json: &str| {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            let collector = aggregation_collector(&ctx, aggregations).unwrap();
+            let (hits, mut results) = searcher
+                .search(&AllQuery, &(TopDocs::with_limit(1), collector))
+                .unwrap();
+            assert_eq!(hits.len(), 1);
+            let Some(AggregationResult::Terms(result)) = results.remove("agg") else {
+                panic!("expected a terms result");
+            };
+            result
+                .buckets
+                .into_iter()
+                .map(|bucket| {
+                    (
+                        serde_json::to_string(&bucket.key).unwrap(),
+                        bucket.doc_count,
+                    )
+                })
+                .chain([("other".to_string(), result.sum_other_doc_count)])
+                .collect::<Vec<_>>()
+        };
+
+        let buckets = aggregate(r#"{"agg": {"terms": {"field": "brand", "size": 2}}}"#);
+        assert_eq!(
+            buckets,
+            [
+                (r#""acme""#.to_string(), 3),
+                (r#""globex""#.to_string(), 1),
+                ("other".to_string(), 1),
+            ]
+        );
+
+        let buckets = aggregate(
+            r#"{"agg": {"terms": {"field": "rating", "order": "key_desc", "size": 1, "other_bucket_key": "rest"}}}"#,
+        );
+        assert_eq!(
+            buckets,
+            [
+                ("5".to_string(), 3),
+                (r#""rest""#.to_string(), 2),
+                ("other".to_string(), 2),
+            ]
+        );
+
+        let buckets = aggregate(
+            r#"{"agg": {"terms": {"field": "brand", "order": "count_asc", "min_doc_count": 2}}}"#,
+        );
+        assert_eq!(
+            buckets,
+            [(r#""acme""#.to_string(), 3), ("other".to_string(), 2)]
+        );
+
+        for json in [
+            r#"{"agg": {"terms": {"field": "title"}}}"#,
+            r#"{"agg": {"terms": {"field": "missing"}}}"#,
+        ] {
+            let aggregations: BTreeMap<String, Aggregation> =
+                serde_json::from_str(json).unwrap();
+            assert!(aggregation_collector(&ctx, aggregations).is_err());
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use lnx_schema::similarity::Bm25Params;
+use tantivy::fieldnorm::FieldNormReader;
+use tantivy::postings::{Postings, SegmentPostings};
+use tantivy::query::{
+    BooleanQuery,
+    BoostQuery,
+    EmptyScorer,
+    EnableScoring,
+    Explanation,
+    Query,
+    Scorer,
+    TermQuery,
+    Weight,
+};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
+
+/// Rewrites the term queries of a parsed query which target a field with custom
+/// BM25 parameters so they are scored with those parameters, wrapping any query
+/// on a field within `boosts` with the field's boost.
+///
+/// Field boosts of fields with custom parameters must be applied here rather than
+/// by the query parser, as the parser's boost wrapper cannot be rewritten.
+/// Phrase queries and explicitly boosted clauses, i.e. `title:rust^2`, keep
+/// the default parameters.
+pub(crate) fn rescore_bm25(
+    query: Box<dyn Query>,
+    params: &HashMap<Field, Bm25Params>,
+    boosts: &HashMap<Field, Score>,
+) -> Box<dyn Query> {
+    if let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() {
+        let clauses = boolean_query
+            .clauses()
+            .iter()
+            .map(|(occur, clause)| {
+                (*occur, rescore_bm25(clause.box_clone(), params, boosts))
+            })
+            .collect();
+        return Box::new(BooleanQuery::new(clauses));
+    }
+
+    let mut field = None;
+    query.query_terms(&mut |term, _| {
+        field.get_or_insert(term.field());
+    });
+    let Some(field) = field else {
+        return query;
+    };
+
+    let query = match (query.downcast_ref::<TermQuery>(), params.get(&field)) {
+        (Some(term_query), Some(&params)) => Box::new(Bm25TermQuery {
+            term: term_query.term().clone(),
+            params,
+        }),
+        _ => query,
+    };
+
+    match boosts.get(&field) {
+        Some(&boost) => Box::new(BoostQuery::new(query, boost)),
+        None => query,
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Matches documents containing a term, scored by BM25 with custom parameters.
+pub(crate) struct Bm25TermQuery {
+    term: Term,
+    params: Bm25Params,
+}
+
+impl Query for Bm25TermQuery {
+    fn weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        let mut weight = Bm25TermWeight {
+            term: self.term.clone(),
+            params: self.params,
+            idf: 0.0,
+            average_fieldnorm: 1.0,
+        };
+
+        let searcher = match enable_scoring.searcher() {
+            Some(searcher) if enable_scoring.is_scoring_enabled() => searcher,
+            _ => return Ok(Box::new(weight)),
+        };
+
+        let mut total_num_docs = 0;
+        let mut total_num_tokens = 0;
+        for reader in searcher.segment_readers() {
+            total_num_docs += reader.max_doc() as u64;
+            total_num_tokens +=
+                reader.inverted_index(self.term.field())?.total_num_tokens();
+        }
+
+        // The same statistics as tantivy's own BM25 weight, so the default
+        // parameters produce the same scores as a regular term query.
+        let num_docs = total_num_docs as Score;
+        let doc_freq = searcher.doc_freq(&self.term)? as Score;
+        weight.idf = (1.0 + (num_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln();
+        if total_num_docs > 0 {
+            weight.average_fieldnorm = total_num_tokens as Score / num_docs;
+        }
+
+        Ok(Box::new(weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        visitor(&self.term, false);
+    }
+}
+
+struct Bm25TermWeight {
+    term: Term,
+    params: Bm25Params,
+    idf: Score,
+    average_fieldnorm: Score,
+}
+
+impl Bm25TermWeight {
+    fn bm25_scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Option<Bm25TermScorer>> {
+        let field = self.term.field();
+        let postings = reader
+            .inverted_index(field)?
+            .read_postings(&self.term, IndexRecordOption::WithFreqs)?;
+        let Some(postings) = postings else {
+            return Ok(None);
+        };
+
+        let fieldnorms = match reader.fieldnorms_readers().get_field(field)? {
+            Some(fieldnorms) => fieldnorms,
+            None => FieldNormReader::constant(reader.max_doc(), 1),
+        };
+
+        Ok(Some(Bm25TermScorer {
+            postings,
+            fieldnorms,
+            params: self.params,
+            weight: boost * self.idf * (1.0 + self.params.k1),
+            average_fieldnorm: self.average_fieldnorm,
+        }))
+    }
+}
+
+impl Weight for Bm25TermWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        match self.bm25_scorer(reader, boost)? {
+            Some(scorer) => Ok(Box::new(scorer)),
+            None => Ok(Box::new(EmptyScorer)),
+        }
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let scorer = self.bm25_scorer(reader, 1.0)?;
+        let Some(mut scorer) = scorer.filter(|scorer| scorer.doc() <= doc) else {
+            return Err(does_not_match(doc));
+        };
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+
+        let mut explanation = Explanation::new(
+            format!("Bm25(k1={}, b={})", self.params.k1, self.params.b),
+            scorer.score(),
+        );
+        explanation.add_const("idf", self.idf);
+        explanation.add_const("tf", scorer.postings.term_freq() as Score);
+        explanation.add_const("fieldnorm", scorer.fieldnorms.fieldnorm(doc) as Score);
+        explanation.add_const("average fieldnorm", self.average_fieldnorm);
+
+        Ok(explanation)
+    }
+}
+
+fn does_not_match(doc: DocId) -> TantivyError {
+    TantivyError::InvalidArgument(format!("Document #({doc}) does not match"))
+}
+
+struct Bm25TermScorer {
+    postings: SegmentPostings,
+    fieldnorms: FieldNormReader,
+    params: Bm25Params,
+    weight: Score,
+    average_fieldnorm: Score,
+}
+
+impl DocSet for Bm25TermScorer {
+    fn advance(&mut self) -> DocId {
+        self.postings.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.postings.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.postings.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings.size_hint()
+    }
+}
+
+impl Scorer for Bm25TermScorer {
+    fn score(&mut self) -> Score {
+        let Bm25Params { k1, b } = self.params;
+        let term_freq = self.postings.term_freq() as Score;
+        let fieldnorm = self.fieldnorms.fieldnorm(self.postings.doc()) as Score;
+
+        let norm = k1 * (1.0 - b + b * fieldnorm / self.average_fieldnorm);
+        self.weight * term_freq / (term_freq + norm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::query::Occur;
+    use tantivy::schema::{SchemaBuilder, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_bm25_term_query() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer.add_document(doc!(title => "rust")).unwrap();
+        writer
+            .add_document(doc!(
+                title => "rust is a language and a search engine written in rust"
+            ))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        let term = Term::from_field_text(title, "rust");
+        let top_doc =
+            |query: &dyn Query| searcher.search(query, &TopDocs::with_limit(2)).unwrap();
+
+        // The default parameters must score exactly like tantivy's own term query.
+        let expected =
+            top_doc(&TermQuery::new(term.clone(), IndexRecordOption::WithFreqs));
+        let query = Bm25TermQuery {
+            term: term.clone(),
+            params: Bm25Params::default(),
+        };
+        let scores = top_doc(&query);
+        for ((expected, _), (score, _)) in expected.iter().zip(scores.iter()) {
+            assert!((expected - score).abs() < 1e-5);
+        }
+        assert_eq!(scores[0].1.doc_id, 0);
+
+        // Without length normalization the repeated term of the longer title wins.
+        let query = Bm25TermQuery {
+            term,
+            params: Bm25Params { k1: 1.2, b: 0.0 },
+        };
+        assert_eq!(top_doc(&query)[0].1.doc_id, 1);
+    }
+
+    #[test]
+    fn test_rescore_bm25() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let body = schema.add_text_field("body", TEXT);
+
+        let term_query = |field: Field| -> (Occur, Box<dyn Query>) {
+            let term = Term::from_field_text(field, "rust");
+            let query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+            (Occur::Should, Box::new(query))
+        };
+        let query = BooleanQuery::new(vec![term_query(title), term_query(body)]);
+
+        let params = HashMap::from_iter([(title, Bm25Params { k1: 1.2, b: 0.2 })]);
+        let boosts = HashMap::from_iter([(title, 2.0)]);
+        let query = rescore_bm25(Box::new(query), &params, &boosts);
+
+        let query = query.downcast_ref::<BooleanQuery>().unwrap();
+        assert!(query.clauses()[0].1.is::<BoostQuery>());
+        assert!(query.clauses()[1].1.is::<TermQuery>());
+    }
+}
@@ -0,0 +1,165 @@
+use std::net::{IpAddr, Ipv6Addr};
+use std::ops::Bound;
+
+use serde::Deserialize;
+use tantivy::query::{Query, RangeQuery as TantivyRangeQuery};
+use tantivy::schema::{FieldType, Type};
+use tantivy::Term;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+#[derive(Debug, Deserialize)]
+/// Matches documents where an ip field falls within the given CIDR block.
+///
+/// Both IPv4 (`192.168.0.0/16`) and IPv6 (`2001:db8::/32`) blocks are supported,
+/// IPv4 blocks match the IPv6-mapped form of addresses which is how they are indexed.
+pub struct CidrQuery {
+    /// The ip field to match against.
+    pub field: String,
+    /// The CIDR block to match.
+    pub cidr: String,
+}
+
+impl CidrQuery {
+    /// Compiles the CIDR block into a tantivy range query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let (field, entry) = ctx.resolve_field(&self.field)?;
+
+        if !matches!(entry.field_type(), FieldType::IpAddr(_)) {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "cidr",
+                "only ip fields can be matched against a CIDR block",
+            ));
+        }
+
+        if !entry.is_fast() {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "cidr",
+                "the field is not a fast field",
+            ));
+        }
+
+        let (lower, upper) = parse_cidr(&self.cidr)
+            .map_err(|msg| QueryError::invalid_value(&self.field, msg))?;
+
+        // tantivy resolves the field by name again, so the resolved name of the
+        // field is used rather than an alias.
+        let query = TantivyRangeQuery::new_term_bounds(
+            entry.name().to_string(),
+            Type::IpAddr,
+            &Bound::Included(Term::from_field_ip_addr(field, lower)),
+            &Bound::Included(Term::from_field_ip_addr(field, upper)),
+        );
+
+        Ok(Box::new(query))
+    }
+}
+
+/// Parses a CIDR block into the first and last IPv6 address within the block.
+fn parse_cidr(cidr: &str) -> Result<(Ipv6Addr, Ipv6Addr), String> {
+    let (addr, prefix) = cidr.trim().split_once('/').ok_or_else(|| {
+        format!("Invalid CIDR block {cidr:?}, expected `<ip>/<prefix>`")
+    })?;
+
+    let addr = addr
+        .parse::<IpAddr>()
+        .map_err(|_| format!("Invalid CIDR block {cidr:?}, {addr:?} is not an ip"))?;
+    let prefix = prefix
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid CIDR block {cidr:?}, invalid prefix length"))?;
+
+    let (addr, prefix) = match addr {
+        IpAddr::V4(addr) if prefix <= 32 => (addr.to_ipv6_mapped(), prefix + 96),
+        IpAddr::V6(addr) if prefix <= 128 => (addr, prefix),
+        _ => {
+            return Err(format!(
+                "Invalid CIDR block {cidr:?}, prefix length is out of range"
+            ))
+        },
+    };
+
+    let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+    let lower = u128::from(addr) & mask;
+    let upper = lower | !mask;
+
+    Ok((Ipv6Addr::from(lower), Ipv6Addr::from(upper)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use tantivy::collector::Count;
+    use tantivy::schema::{SchemaBuilder, FAST, INDEXED};
+    use tantivy::{Document, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_ipv4_cidr() {
+        let (lower, upper) = parse_cidr("192.168.12.7/16").unwrap();
+        assert_eq!(lower, Ipv4Addr::new(192, 168, 0, 0).to_ipv6_mapped());
+        assert_eq!(upper, Ipv4Addr::new(192, 168, 255, 255).to_ipv6_mapped());
+
+        let (lower, upper) = parse_cidr("10.0.0.1/32").unwrap();
+        assert_eq!(lower, Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped());
+        assert_eq!(upper, Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped());
+
+        let (lower, upper) = parse_cidr("0.0.0.0/0").unwrap();
+        assert_eq!(lower, Ipv4Addr::new(0, 0, 0, 0).to_ipv6_mapped());
+        assert_eq!(upper, Ipv4Addr::new(255, 255, 255, 255).to_ipv6_mapped());
+    }
+
+    #[test]
+    fn test_parse_ipv6_cidr() {
+        let (lower, upper) = parse_cidr("2001:db8::/32").unwrap();
+        assert_eq!(lower, "2001:db8::".parse::<Ipv6Addr>().unwrap());
+        assert_eq!(
+            upper,
+            "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"
+                .parse::<Ipv6Addr>()
+                .unwrap()
+        );
+
+        let (lower, upper) = parse_cidr("::/0").unwrap();
+        assert_eq!(lower, Ipv6Addr::UNSPECIFIED);
+        assert_eq!(upper, Ipv6Addr::from(u128::MAX));
+    }
+
+    #[test]
+    fn test_parse_invalid_cidr() {
+        assert!(parse_cidr("192.168.0.0").is_err());
+        assert!(parse_cidr("192.168.0.0/33").is_err());
+        assert!(parse_cidr("2001:db8::/129").is_err());
+        assert!(parse_cidr("hello/8").is_err());
+        assert!(parse_cidr("10.0.0.0/abc").is_err());
+    }
+
+    #[test]
+    fn test_cidr_alias_search() {
+        let mut schema = SchemaBuilder::new();
+        let client_ip = schema.add_ip_addr_field("client_ip", FAST | INDEXED);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for addr in [[10, 0, 0, 1], [10, 0, 12, 7], [192, 168, 0, 1]] {
+            let mut doc = Document::default();
+            doc.add_ip_addr(client_ip, Ipv4Addr::from(addr).to_ipv6_mapped());
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(index.schema()).with_field_alias("ip", "client_ip");
+        let query = CidrQuery {
+            field: "ip".to_string(),
+            cidr: "10.0.0.0/8".to_string(),
+        };
+        let query = query.build(&ctx).unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        assert_eq!(searcher.search(&query, &Count).unwrap(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+use tantivy::columnar::{Column, StrColumn};
+use tantivy::schema::{FieldEntry, FieldType};
+use tantivy::{DocId, SegmentReader};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The type of the values within a fast field column.
+pub(crate) enum ColumnType {
+    U64,
+    I64,
+    F64,
+    Bool,
+    Date,
+    Str,
+}
+
+impl ColumnType {
+    /// Returns the column type of the given field type, if it can be read as a column.
+    pub(crate) fn of(field_type: &FieldType) -> Option<Self> {
+        let kind = match field_type {
+            FieldType::U64(_) => Self::U64,
+            FieldType::I64(_) => Self::I64,
+            FieldType::F64(_) => Self::F64,
+            FieldType::Bool(_) => Self::Bool,
+            FieldType::Date(_) => Self::Date,
+            FieldType::Str(_) => Self::Str,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A fast field read at query time, i.e. by an aggregation, a score function
+/// or a runtime field.
+///
+/// The column is opened separately within each segment via [FastColumn::open].
+pub(crate) struct FastColumn {
+    name: String,
+    kind: ColumnType,
+}
+
+impl FastColumn {
+    /// Creates a column reading the given field.
+    ///
+    /// Returns `None` if the type of the field cannot be read as a column, the
+    /// caller is responsible for checking the field is a fast field.
+    pub(crate) fn new(entry: &FieldEntry) -> Option<Self> {
+        let kind = ColumnType::of(entry.field_type())?;
+        Some(Self {
+            name: entry.name().to_string(),
+            kind,
+        })
+    }
+
+    #[inline]
+    /// The name of the field the column reads.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    /// The type of the values within the column.
+    pub(crate) fn kind(&self) -> ColumnType {
+        self.kind
+    }
+
+    #[inline]
+    /// Returns if the column holds `u64`, `i64` or `f64` values.
+    pub(crate) fn is_numeric(&self) -> bool {
+        matches!(
+            self.kind,
+            ColumnType::U64 | ColumnType::I64 | ColumnType::F64
+        )
+    }
+
+    #[inline]
+    /// Returns if the column holds `datetime` values.
+    pub(crate) fn is_date(&self) -> bool {
+        self.kind == ColumnType::Date
+    }
+
+    /// Opens the column within the given segment, if the segment has any values.
+    pub(crate) fn open(&self, reader: &SegmentReader) -> Option<SegmentColumn> {
+        let fast_fields = reader.fast_fields();
+        let name = self.name.as_str();
+        match self.kind {
+            ColumnType::U64 => fast_fields.u64(name).ok().map(SegmentColumn::U64),
+            ColumnType::I64 => fast_fields.i64(name).ok().map(SegmentColumn::I64),
+            ColumnType::F64 => fast_fields.f64(name).ok().map(SegmentColumn::F64),
+            ColumnType::Bool => fast_fields.bool(name).ok().map(SegmentColumn::Bool),
+            ColumnType::Date => fast_fields.date(name).ok().map(SegmentColumn::Date),
+            ColumnType::Str => {
+                fast_fields.str(name).ok().flatten().map(SegmentColumn::Str)
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+/// The column of a fast field within a single segment.
+pub(crate) enum SegmentColumn {
+    U64(Column<u64>),
+    I64(Column<i64>),
+    F64(Column<f64>),
+    Bool(Column<bool>),
+    Date(Column<tantivy::DateTime>),
+    Str(StrColumn),
+}
+
+impl SegmentColumn {
+    /// The first value of the document as a float, `datetime` values are timestamps
+    /// in microseconds.
+    ///
+    /// `bool` and string columns have no float values.
+    pub(crate) fn first(&self, doc: DocId) -> Option<f64> {
+        match self {
+            Self::U64(column) => column.first(doc).map(|v| v as f64),
+            Self::I64(column) => column.first(doc).map(|v| v as f64),
+            Self::F64(column) => column.first(doc),
+            Self::Date(column) => column
+                .first(doc)
+                .map(|dt| dt.into_timestamp_micros() as f64),
+            Self::Bool(_) | Self::Str(_) => None,
+        }
+    }
+
+    /// Pushes the distinct raw values of the document, string values are pushed as
+    /// their term ordinal within the segment.
+    ///
+    /// Raw values are only comparable within the same segment.
+    pub(crate) fn raw_values(&self, doc: DocId, values: &mut Vec<u64>) {
+        values.clear();
+        match self {
+            Self::U64(column) => values.extend(column.values_for_doc(doc)),
+            Self::I64(column) => {
+                values.extend(column.values_for_doc(doc).map(|v| v as u64))
+            },
+            Self::F64(column) => {
+                values.extend(column.values_for_doc(doc).map(f64::to_bits))
+            },
+            Self::Bool(column) => {
+                values.extend(column.values_for_doc(doc).map(|v| v as u64))
+            },
+            Self::Date(column) => values.extend(
+                column
+                    .values_for_doc(doc)
+                    .map(|dt| dt.into_timestamp_micros() as u64),
+            ),
+            Self::Str(column) => values.extend(column.ords().values_for_doc(doc)),
+        }
+
+        // A document is only counted once per value.
+        if values.len() > 1 {
+            values.sort_unstable();
+            values.dedup();
+        }
+    }
+
+    /// Pushes the values of the document as floats, non-numeric columns have no values.
+    pub(crate) fn numeric_values(&self, doc: DocId, values: &mut Vec<f64>) {
+        values.clear();
+        match self {
+            Self::U64(column) => {
+                values.extend(column.values_for_doc(doc).map(|v| v as f64))
+            },
+            Self::I64(column) => {
+                values.extend(column.values_for_doc(doc).map(|v| v as f64))
+            },
+            Self::F64(column) => values.extend(column.values_for_doc(doc)),
+            Self::Bool(_) | Self::Date(_) | Self::Str(_) => {},
+        }
+    }
+}
@@ -0,0 +1,631 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use lnx_document::{DateTime, UserDisplayType, Value};
+use lnx_schema::analysis::SynonymMap;
+use lnx_schema::flattened::flattened_token;
+use lnx_schema::null_handling::NULL_TOKEN;
+use lnx_schema::schema::IndexSchema;
+use lnx_schema::similarity::Bm25Params;
+use lnx_transforms::{
+    BytesEncoding,
+    DateTimeFormat,
+    DateTimeParser,
+    TimestampResolution,
+    TypeCast,
+};
+use tantivy::query::QueryParser;
+use tantivy::schema::{Field, FieldEntry, FieldType, Schema};
+use tantivy::tokenizer::{TextAnalyzer, TokenStream, TokenizerManager};
+use tantivy::Term;
+use time::OffsetDateTime;
+
+use crate::curation::Curations;
+use crate::error::QueryError;
+use crate::ltr::LtrModels;
+use crate::query_string::Operator;
+
+/// The default maximum size (in bytes) of a compiled regex automaton.
+pub const DEFAULT_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// The context queries are compiled within.
+///
+/// This holds the schema of the index being searched
+/// and any options which affect how user provided values
+/// are interpreted.
+pub struct QueryContext {
+    schema: Schema,
+    tokenizers: TokenizerManager,
+    default_fields: Vec<Field>,
+    default_operator: Operator,
+    default_fuzziness: Option<u8>,
+    field_presence_field: Option<Field>,
+    document_boost_field: Option<Field>,
+    document_id_field: Option<Field>,
+    curations: Option<Arc<Curations>>,
+    ltr_models: Option<Arc<LtrModels>>,
+    datetime_parser: DateTimeParser,
+    bytes_encodings: HashMap<String, BytesEncoding>,
+    flattened_fields: HashSet<String>,
+    indexed_null_fields: HashSet<String>,
+    normalized_fields: HashSet<String>,
+    aliases: HashMap<String, String>,
+    regex_size_limit: usize,
+    now: Option<DateTime>,
+    query_synonyms: Option<Arc<SynonymMap>>,
+    bm25_params: HashMap<String, Bm25Params>,
+    field_boosts: HashMap<String, f32>,
+}
+
+impl QueryContext {
+    /// Creates a new query context for a given schema.
+    ///
+    /// By default datetime values can be provided as either a
+    /// RFC 3339 or RFC 2822 formatted string, or a unix timestamp in seconds.
+    ///
+    /// Free-text queries which do not target any specific fields will search
+    /// all indexed text fields in the schema.
+    pub fn new(schema: Schema) -> Self {
+        let default_fields = schema
+            .fields()
+            .filter(|(_, entry)| {
+                matches!(entry.field_type(), FieldType::Str(_)) && entry.is_indexed()
+            })
+            .map(|(field, _)| field)
+            .collect();
+
+        let datetime_parser = DateTimeParser::default()
+            .with_timestamp_resolution(TimestampResolution::Seconds)
+            .with_format(DateTimeFormat::Rfc3339)
+            .with_format(DateTimeFormat::Rfc2822);
+
+        Self {
+            schema,
+            tokenizers: TokenizerManager::default(),
+            default_fields,
+            default_operator: Operator::Or,
+            default_fuzziness: None,
+            field_presence_field: None,
+            document_boost_field: None,
+            document_id_field: None,
+            curations: None,
+            ltr_models: None,
+            datetime_parser,
+            bytes_encodings: HashMap::new(),
+            flattened_fields: HashSet::new(),
+            indexed_null_fields: HashSet::new(),
+            normalized_fields: HashSet::new(),
+            aliases: HashMap::new(),
+            regex_size_limit: DEFAULT_REGEX_SIZE_LIMIT,
+            now: None,
+            query_synonyms: None,
+            bm25_params: HashMap::new(),
+            field_boosts: HashMap::new(),
+        }
+    }
+
+    /// Replaces the tokenizers used to analyze free-text queries.
+    ///
+    /// This should be the same tokenizer manager that is used by the index.
+    pub fn with_tokenizers(mut self, tokenizers: TokenizerManager) -> Self {
+        self.tokenizers = tokenizers;
+        self
+    }
+
+    /// Sets the fields searched by free-text queries which do not
+    /// explicitly target any fields.
+    pub fn with_default_fields<S: AsRef<str>>(
+        mut self,
+        fields: &[S],
+    ) -> Result<Self, QueryError> {
+        self.default_fields = fields
+            .iter()
+            .map(|name| self.resolve_text_field(name.as_ref(), "query_string"))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self)
+    }
+
+    /// Sets the operator used to combine free-text terms when a query does not
+    /// specify one.
+    pub fn with_default_operator(mut self, operator: Operator) -> Self {
+        self.default_operator = operator;
+        self
+    }
+
+    /// Sets the fuzziness of free-text terms when a query does not specify one.
+    pub fn with_default_fuzziness(mut self, fuzziness: u8) -> Self {
+        self.default_fuzziness = Some(fuzziness);
+        self
+    }
+
+    /// Sets the field which tracks the presence of fields on each document.
+    ///
+    /// This is required in order to use `exists` and `missing` queries.
+    pub fn with_field_presence_field(mut self, field: Field) -> Self {
+        self.field_presence_field = Some(field);
+        self
+    }
+
+    /// Sets the `f64` fast field holding the boost of each document.
+    ///
+    /// The boost of a document is multiplied into its score by
+    /// [SearchRequest::build_query](crate::SearchRequest::build_query),
+    /// documents without a boost keep their score.
+    pub fn with_document_boost_field(mut self, field: Field) -> Self {
+        self.document_boost_field = Some(field);
+        self
+    }
+
+    /// Sets the indexed `u64` field holding the unique ID of each document.
+    ///
+    /// This is required in order to pin documents via [Curations].
+    pub fn with_document_id_field(mut self, field: Field) -> Self {
+        self.document_id_field = Some(field);
+        self
+    }
+
+    /// Sets the curated results of the index which are applied to matching
+    /// query strings by [SearchRequest::build_query](crate::SearchRequest::build_query).
+    pub fn with_curations(mut self, curations: Arc<Curations>) -> Self {
+        self.curations = Some(curations);
+        self
+    }
+
+    /// Sets the learning to rank models of the index which can be used to rescore
+    /// the top hits of a search.
+    pub fn with_ltr_models(mut self, models: Arc<LtrModels>) -> Self {
+        self.ltr_models = Some(models);
+        self
+    }
+
+    /// Replaces the parser used to interpret datetime values within queries.
+    pub fn with_datetime_parser(mut self, parser: DateTimeParser) -> Self {
+        self.datetime_parser = parser;
+        self
+    }
+
+    /// Sets the encoding values of the given `bytes` field are provided in.
+    ///
+    /// Fields without an encoding expect base64 values.
+    pub fn with_bytes_encoding(mut self, field: &str, encoding: BytesEncoding) -> Self {
+        self.bytes_encodings.insert(field.to_string(), encoding);
+        self
+    }
+
+    /// Marks the given field as a `flattened` object field.
+    ///
+    /// Keys within the field can then be matched by term queries using their
+    /// dotted path, i.e. `attrs.color`.
+    pub fn with_flattened_field(mut self, field: &str) -> Self {
+        self.flattened_fields.insert(field.to_string());
+        self
+    }
+
+    /// Marks the given field as indexing `null` values as the null token.
+    ///
+    /// Term queries with a `null` value then match documents where the field is null.
+    pub fn with_indexed_nulls(mut self, field: &str) -> Self {
+        self.indexed_null_fields.insert(field.to_string());
+        self
+    }
+
+    /// Marks every field of the index schema which indexes `null` values, see
+    /// [QueryContext::with_indexed_nulls].
+    pub fn with_null_handling(self, definition: &IndexSchema) -> Self {
+        definition
+            .indexed_null_fields()
+            .fold(self, |ctx, field| ctx.with_indexed_nulls(field))
+    }
+
+    /// Marks the given `string` field as normalizing its keywords.
+    ///
+    /// Term query values on the field are then normalized by the field's tokenizer,
+    /// i.e. lowercased, so they match the indexed keywords.
+    pub fn with_normalized_field(mut self, field: &str) -> Self {
+        self.normalized_fields.insert(field.to_string());
+        self
+    }
+
+    /// Adds an alias which resolves to the given field when referenced by queries.
+    pub fn with_field_alias(mut self, alias: &str, field: &str) -> Self {
+        self.aliases.insert(alias.to_string(), field.to_string());
+        self
+    }
+
+    /// Sets the maximum size (in bytes) a compiled regex or wildcard
+    /// pattern is allowed to grow to.
+    ///
+    /// Patterns which exceed this limit are rejected rather than executed.
+    pub fn with_regex_size_limit(mut self, limit: usize) -> Self {
+        self.regex_size_limit = limit;
+        self
+    }
+
+    /// Pins the time `now` resolves to within date math expressions.
+    ///
+    /// This should be set once per request and re-used when paginating through
+    /// results so that relative ranges like `now-7d` stay consistent between pages.
+    pub fn with_now(mut self, now: DateTime) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Sets the synonyms which are expanded within free-text queries.
+    ///
+    /// These are the query-time synonym sets of the index, changing them does not
+    /// require a reindex.
+    pub fn with_query_synonyms(mut self, synonyms: Arc<SynonymMap>) -> Self {
+        self.query_synonyms = Some(synonyms);
+        self
+    }
+
+    /// Sets the BM25 parameters used to score free-text matches on the given field.
+    ///
+    /// These are the `bm25` options of the index's schema, fields without parameters
+    /// are scored with tantivy's defaults.
+    pub fn with_field_bm25(mut self, field: &str, params: Bm25Params) -> Self {
+        self.bm25_params.insert(field.to_string(), params);
+        self
+    }
+
+    /// Sets the default boost applied to free-text matches on the given field.
+    ///
+    /// These are the `boost` options of the index's schema, queries can override
+    /// them per field.
+    pub fn with_field_boost(mut self, field: &str, boost: f32) -> Self {
+        self.field_boosts.insert(field.to_string(), boost);
+        self
+    }
+
+    #[inline]
+    /// The schema of the index queries are compiled for.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    #[inline]
+    /// The fields searched by free-text queries which do not target any fields.
+    pub fn default_fields(&self) -> &[Field] {
+        &self.default_fields
+    }
+
+    #[inline]
+    /// The operator used to combine free-text terms by default.
+    pub fn default_operator(&self) -> Operator {
+        self.default_operator
+    }
+
+    #[inline]
+    /// The fuzziness of free-text terms by default.
+    pub fn default_fuzziness(&self) -> Option<u8> {
+        self.default_fuzziness
+    }
+
+    #[inline]
+    /// The field which tracks the presence of fields on each document.
+    pub fn field_presence_field(&self) -> Option<Field> {
+        self.field_presence_field
+    }
+
+    #[inline]
+    /// The fast field holding the boost of each document.
+    pub fn document_boost_field(&self) -> Option<Field> {
+        self.document_boost_field
+    }
+
+    #[inline]
+    /// The field holding the unique ID of each document.
+    pub fn document_id_field(&self) -> Option<Field> {
+        self.document_id_field
+    }
+
+    #[inline]
+    /// The curated results of the index.
+    pub fn curations(&self) -> Option<&Curations> {
+        self.curations.as_deref()
+    }
+
+    #[inline]
+    /// The learning to rank models of the index.
+    pub fn ltr_models(&self) -> Option<&LtrModels> {
+        self.ltr_models.as_deref()
+    }
+
+    #[inline]
+    /// The maximum size (in bytes) of a compiled regex automaton.
+    pub fn regex_size_limit(&self) -> usize {
+        self.regex_size_limit
+    }
+
+    #[inline]
+    /// The synonyms which are expanded within free-text queries.
+    pub fn query_synonyms(&self) -> Option<&SynonymMap> {
+        self.query_synonyms.as_deref()
+    }
+
+    #[inline]
+    /// The BM25 parameters of each field which does not use the defaults.
+    pub fn field_bm25_params(&self) -> &HashMap<String, Bm25Params> {
+        &self.bm25_params
+    }
+
+    #[inline]
+    /// The default boost of each field which has one.
+    pub fn field_boosts(&self) -> &HashMap<String, f32> {
+        &self.field_boosts
+    }
+
+    /// The time `now` resolves to within date math expressions.
+    ///
+    /// This is the pinned time if one is set, otherwise the current time.
+    pub fn now(&self) -> DateTime {
+        self.now.unwrap_or_else(|| {
+            let micros = OffsetDateTime::now_utc().unix_timestamp_nanos() / 1000;
+            DateTime::from_micros(micros as i64).unwrap_or(DateTime::MAX)
+        })
+    }
+
+    /// Resolves a field name to the name of the concrete field if it is an alias.
+    pub fn resolve_alias<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// Resolves a field name or alias to its field ID and entry within the schema.
+    pub fn resolve_field(&self, name: &str) -> Result<(Field, &FieldEntry), QueryError> {
+        let field = self
+            .schema
+            .get_field(self.resolve_alias(name))
+            .map_err(|_| QueryError::UnknownField(name.to_string()))?;
+        Ok((field, self.schema.get_field_entry(field)))
+    }
+
+    /// Resolves a dotted path to a key within a `flattened` field.
+    ///
+    /// Returns `None` if the path does not address a flattened field.
+    pub fn resolve_flattened_key<'a>(
+        &self,
+        path: &'a str,
+    ) -> Option<(Field, &FieldEntry, &'a str)> {
+        let (name, key) = path.split_once('.')?;
+        let name = self.resolve_alias(name);
+        if !self.flattened_fields.contains(name) {
+            return None;
+        }
+
+        let (field, entry) = self.resolve_field(name).ok()?;
+        Some((field, entry, key))
+    }
+
+    /// Resolves a field name to its field ID, ensuring it is an indexed text field.
+    pub fn resolve_text_field(
+        &self,
+        name: &str,
+        query: &'static str,
+    ) -> Result<Field, QueryError> {
+        let (field, entry) = self.resolve_field(name)?;
+
+        if !matches!(entry.field_type(), FieldType::Str(_)) {
+            return Err(QueryError::unsupported(
+                name,
+                query,
+                "only text fields can be used",
+            ));
+        }
+
+        if !entry.is_indexed() {
+            return Err(QueryError::unsupported(
+                name,
+                query,
+                "the field is not indexed",
+            ));
+        }
+
+        Ok(field)
+    }
+
+    /// Gets the text analyzer used to tokenize values of the given field.
+    ///
+    /// Returns `None` if the field is not an indexed text field or the
+    /// tokenizer is not registered with the context.
+    pub fn text_analyzer(&self, entry: &FieldEntry) -> Option<TextAnalyzer> {
+        let FieldType::Str(options) = entry.field_type() else {
+            return None;
+        };
+        let indexing = options.get_indexing_options()?;
+        self.tokenizers.get(indexing.tokenizer())
+    }
+
+    /// Creates a new query parser targeting the given fields.
+    ///
+    /// If no fields are provided the context's default fields are used.
+    pub fn query_parser(&self, fields: Vec<Field>) -> QueryParser {
+        let fields = if fields.is_empty() {
+            self.default_fields.clone()
+        } else {
+            fields
+        };
+
+        QueryParser::new(self.schema.clone(), fields, self.tokenizers.clone())
+    }
+
+    /// Creates a new query parser targeting the given fields which analyzes the
+    /// query text with the given analyzer instead of each field's own analyzer.
+    ///
+    /// This only affects the query side, i.e. using the `raw` analyzer to match
+    /// a keyword exactly against a text field, or an analyzer without stop-word
+    /// removal so stop-words within the query are kept.
+    pub fn query_parser_with_analyzer(
+        &self,
+        fields: Vec<Field>,
+        analyzer: &str,
+    ) -> Result<QueryParser, QueryError> {
+        let fields = if fields.is_empty() {
+            self.default_fields.clone()
+        } else {
+            fields
+        };
+
+        let analyzer = self.tokenizers.get(analyzer).ok_or_else(|| {
+            QueryError::Invalid(format!("Unknown analyzer {analyzer:?}"))
+        })?;
+
+        // The parser looks up analyzers by the tokenizer name of each field, so every
+        // tokenizer name used within the schema is mapped onto the override.
+        let tokenizers = TokenizerManager::new();
+        for (_, entry) in self.schema.fields() {
+            let indexing = match entry.field_type() {
+                FieldType::Str(options) => options.get_indexing_options(),
+                FieldType::JsonObject(options) => options.get_text_indexing_options(),
+                _ => None,
+            };
+
+            if let Some(indexing) = indexing {
+                tokenizers.register(indexing.tokenizer(), analyzer.clone());
+            }
+        }
+
+        Ok(QueryParser::new(self.schema.clone(), fields, tokenizers))
+    }
+
+    /// Casts a user provided value to the type of the given field and
+    /// creates a term from it.
+    pub fn build_term(
+        &self,
+        field: Field,
+        entry: &FieldEntry,
+        value: Value,
+    ) -> Result<Term, QueryError> {
+        let term = match self.cast_value(entry, value)? {
+            Value::Null if self.indexed_null_fields.contains(entry.name()) => {
+                Term::from_field_text(field, NULL_TOKEN)
+            },
+            Value::Str(v) if self.normalized_fields.contains(entry.name()) => {
+                Term::from_field_text(field, &self.normalize_keyword(entry, &v))
+            },
+            Value::Str(v) => Term::from_field_text(field, &v),
+            Value::U64(v) => Term::from_field_u64(field, v),
+            Value::I64(v) => Term::from_field_i64(field, v),
+            Value::F64(v) => Term::from_field_f64(field, v),
+            Value::Bool(v) => Term::from_field_bool(field, v),
+            Value::DateTime(v) => Term::from_field_date(field, v.as_tantivy_value()),
+            Value::IpAddr(v) => Term::from_field_ip_addr(field, v),
+            Value::Bytes(v) => Term::from_field_bytes(field, &v),
+            Value::Facet(v) => {
+                let facet = v.to_tantivy_facet().map_err(|e| {
+                    QueryError::invalid_value(entry.name(), e.to_string())
+                })?;
+                Term::from_facet(field, &facet)
+            },
+            other => {
+                return Err(QueryError::unsupported(
+                    entry.name(),
+                    "term",
+                    format!(
+                        "`{}` values cannot be matched as a term",
+                        other.type_name()
+                    ),
+                ))
+            },
+        };
+
+        Ok(term)
+    }
+
+    /// Normalizes a keyword with the tokenizer of the given field.
+    ///
+    /// The value is kept as-is if the tokenizer is not registered.
+    fn normalize_keyword(&self, entry: &FieldEntry, value: &str) -> String {
+        let Some(analyzer) = self.text_analyzer(entry) else {
+            return value.to_string();
+        };
+
+        let mut stream = analyzer.token_stream(value);
+        if stream.advance() {
+            stream.token().text.clone()
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// Creates a term matching the value of the given key within a `flattened` field.
+    pub fn build_flattened_term(
+        &self,
+        field: Field,
+        entry: &FieldEntry,
+        key: &str,
+        value: Value,
+    ) -> Result<Term, QueryError> {
+        match TypeCast::String.try_cast_value(value) {
+            Ok(Value::Str(value)) => {
+                Ok(Term::from_field_text(field, &flattened_token(key, &value)))
+            },
+            Ok(other) => Err(QueryError::unsupported(
+                entry.name(),
+                "term",
+                format!("`{}` values cannot be matched as a term", other.type_name()),
+            )),
+            Err(e) => Err(QueryError::invalid_value(entry.name(), e.to_string())),
+        }
+    }
+
+    /// Casts a user provided value to the type of the given field.
+    pub fn cast_value<'a>(
+        &self,
+        entry: &FieldEntry,
+        value: Value<'a>,
+    ) -> Result<Value<'a>, QueryError> {
+        let result = match entry.field_type() {
+            FieldType::Str(_) => TypeCast::String.try_cast_value(value),
+            FieldType::U64(_) => TypeCast::U64.try_cast_value(value),
+            FieldType::I64(_) => TypeCast::I64.try_cast_value(value),
+            FieldType::F64(_) => TypeCast::F64.try_cast_value(value),
+            FieldType::Bool(_) => TypeCast::Bool.try_cast_value(value),
+            FieldType::Facet(_) => TypeCast::Facet.try_cast_value(value),
+            FieldType::Bytes(_) => self.cast_bytes(entry.name(), value),
+            FieldType::IpAddr(_) => TypeCast::IpAddr.try_cast_value(value),
+            FieldType::Date(_) => self.cast_datetime(value),
+            FieldType::JsonObject(_) => Ok(value),
+        };
+
+        result.map_err(|e| QueryError::invalid_value(entry.name(), e.to_string()))
+    }
+
+    /// Parses a datetime string using the context's datetime parser.
+    pub(crate) fn parse_datetime(&self, value: &str) -> anyhow::Result<DateTime> {
+        match self.cast_datetime(Value::from(value))? {
+            Value::DateTime(dt) => Ok(dt),
+            _ => unreachable!(),
+        }
+    }
+
+    fn cast_bytes<'a>(
+        &self,
+        field: &str,
+        value: Value<'a>,
+    ) -> anyhow::Result<Value<'a>> {
+        match (self.bytes_encodings.get(field), value) {
+            (Some(encoding), Value::Str(s)) => encoding.decode(&s).map(Value::Bytes),
+            (_, value) => TypeCast::Bytes.try_cast_value(value),
+        }
+    }
+
+    fn cast_datetime<'a>(&self, value: Value<'a>) -> anyhow::Result<Value<'a>> {
+        match value {
+            Value::DateTime(dt) => Ok(Value::DateTime(dt)),
+            Value::U64(ts) => {
+                let ts = i64::try_from(ts).map_err(|_| {
+                    anyhow::anyhow!(
+                        "Cannot cast timestamp to `datetime` as it goes beyond the bounds of the supported `datetime` range"
+                    )
+                })?;
+                self.datetime_parser
+                    .try_convert_timestamp(ts)
+                    .map(Value::DateTime)
+            },
+            other => self
+                .datetime_parser
+                .try_parse_json(other)
+                .map(Value::DateTime),
+        }
+    }
+}
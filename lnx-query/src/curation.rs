@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+
+use lnx_metastore::Metastore;
+use serde::{Deserialize, Serialize};
+use tantivy::query::{BooleanQuery, ConstScoreQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{Score, Term};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+/// The metastore database the curations of each index are stored in.
+const CURATIONS_DATABASE: &str = "lnx_curations";
+
+/// The score of the first pinned document, each following document scores less.
+///
+/// This leaves enough headroom for the pinned scores to be combined with other
+/// scores without overflowing.
+const PINNED_SCORE: Score = Score::MAX / 16.0;
+
+/// Normalizes a query string so that queries only differing in case or whitespace,
+/// i.e. `Running  Shoes` and `running shoes`, share the same curation.
+pub fn normalize_curation_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+/// The curated results of a single query.
+pub struct Curation {
+    /// The IDs of the documents pinned to the top of the results, in order.
+    pub pinned: Vec<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+/// The curated results of an index keyed by their normalized query string.
+///
+/// Pinned documents are injected at the top of the results of the matching query in
+/// the order they are listed and excluded from the organic hits, so they are never
+/// returned twice and pagination stays consistent.
+pub struct Curations {
+    queries: BTreeMap<String, Curation>,
+}
+
+impl Curations {
+    /// Sets the curation of the given query, replacing any existing curation.
+    pub fn set(&mut self, query: &str, curation: Curation) {
+        self.queries
+            .insert(normalize_curation_query(query), curation);
+    }
+
+    /// Removes the curation of the given query, returning if it existed.
+    pub fn remove(&mut self, query: &str) -> bool {
+        self.queries
+            .remove(&normalize_curation_query(query))
+            .is_some()
+    }
+
+    /// Returns the curation of the given query if it exists.
+    pub fn get(&self, query: &str) -> Option<&Curation> {
+        self.queries.get(&normalize_curation_query(query))
+    }
+
+    /// The number of curated queries.
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns if there are no curated queries.
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Applies the curation of the given query string to the compiled query, if any.
+    ///
+    /// Requires the context to have a document ID field, see
+    /// [QueryContext::with_document_id_field].
+    pub fn apply(
+        &self,
+        ctx: &QueryContext,
+        query_string: &str,
+        query: Box<dyn Query>,
+    ) -> Result<Box<dyn Query>, QueryError> {
+        let Some(curation) = self.get(query_string).filter(|c| !c.pinned.is_empty())
+        else {
+            return Ok(query);
+        };
+
+        let Some(id_field) = ctx.document_id_field() else {
+            return Err(QueryError::Invalid(
+                "Curated results require the document ID field to be set".to_string(),
+            ));
+        };
+
+        Ok(pin_documents(id_field, &curation.pinned, query))
+    }
+}
+
+/// Injects the pinned documents at the top of the results of the query, in order,
+/// and excludes them from the organic hits.
+fn pin_documents(
+    id_field: Field,
+    pinned: &[u64],
+    query: Box<dyn Query>,
+) -> Box<dyn Query> {
+    let id_query = |id: u64| -> Box<dyn Query> {
+        let term = Term::from_field_u64(id_field, id);
+        Box::new(TermQuery::new(term, IndexRecordOption::Basic))
+    };
+
+    let mut organic = vec![(Occur::Must, query)];
+    organic.extend(pinned.iter().map(|&id| (Occur::MustNot, id_query(id))));
+
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> =
+        vec![(Occur::Should, Box::new(BooleanQuery::new(organic)))];
+    for (pos, &id) in pinned.iter().enumerate() {
+        let score = PINNED_SCORE / (pos + 1) as Score;
+        clauses.push((
+            Occur::Should,
+            Box::new(ConstScoreQuery::new(id_query(id), score)),
+        ));
+    }
+
+    Box::new(BooleanQuery::new(clauses))
+}
+
+/// The persisted curations of every index.
+pub struct CurationStore {
+    metastore: Metastore,
+}
+
+impl CurationStore {
+    /// Opens the curation store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(CURATIONS_DATABASE)?;
+        Ok(Self { metastore })
+    }
+
+    /// Persists the curations of the index as a JSON string.
+    pub fn save(&self, index: &str, curations: &Curations) -> anyhow::Result<()> {
+        let source = serde_json::to_string(curations)?;
+        self.metastore.put(&index, &source)
+    }
+
+    /// Loads the curations of the index.
+    pub fn load(&self, index: &str) -> anyhow::Result<Curations> {
+        let Some(source) = self.metastore.get::<_, String>(&index)? else {
+            return Ok(Curations::default());
+        };
+        Ok(serde_json::from_str(&source)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{SchemaBuilder, INDEXED, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::search::SearchRequest;
+
+    #[test]
+    fn test_normalize_curation_query() {
+        assert_eq!(
+            normalize_curation_query("  Running\tSHOES "),
+            "running shoes"
+        );
+
+        let mut curations = Curations::default();
+        curations.set("Running Shoes", Curation { pinned: vec![1] });
+        assert!(curations.get("running  shoes").is_some());
+        assert!(curations.remove("RUNNING SHOES"));
+        assert!(curations.is_empty());
+    }
+
+    #[test]
+    fn test_pinned_results() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let id = schema.add_u64_field("_id", INDEXED);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(id => 10u64, title => "running shoes"))
+            .unwrap();
+        writer
+            .add_document(doc!(id => 11u64, title => "trail running shoes"))
+            .unwrap();
+        writer
+            .add_document(doc!(id => 12u64, title => "gift card"))
+            .unwrap();
+        writer
+            .add_document(doc!(id => 13u64, title => "running socks"))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let mut curations = Curations::default();
+        curations.set(
+            "Running Shoes",
+            Curation {
+                pinned: vec![12, 11],
+            },
+        );
+        let ctx = QueryContext::new(schema)
+            .with_document_id_field(id)
+            .with_curations(Arc::new(curations));
+
+        let searcher = index.reader().unwrap().searcher();
+        let search = |json: &str| {
+            let request: SearchRequest = serde_json::from_str(json).unwrap();
+            let query = request.build_query(&ctx).unwrap();
+            let hits = searcher.search(&query, &TopDocs::with_limit(10)).unwrap();
+            hits.into_iter()
+                .map(|(_, address)| address.doc_id)
+                .collect::<Vec<_>>()
+        };
+
+        // The pinned documents come first in order, even if they don't match,
+        // and are never repeated within the organic hits.
+        let hits = search(r#"{"query": {"query_string": {"query": "running  SHOES"}}}"#);
+        assert_eq!(&hits[..2], [2, 1]);
+        assert_eq!(hits.len(), 4);
+
+        let hits = search(r#"{"query": {"query_string": {"query": "socks"}}}"#);
+        assert_eq!(hits, [3]);
+    }
+}
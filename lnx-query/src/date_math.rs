@@ -0,0 +1,342 @@
+use lnx_document::DateTime;
+use time::util::days_in_year_month;
+use time::{Date, Duration, Month, OffsetDateTime, Time, UtcOffset};
+
+use crate::context::QueryContext;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A unit of time used within a date math expression.
+pub(crate) enum Unit {
+    Year,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl Unit {
+    fn parse(c: Option<char>) -> Result<Self, String> {
+        let unit = match c {
+            Some('y') => Self::Year,
+            Some('M') => Self::Month,
+            Some('w') => Self::Week,
+            Some('d') => Self::Day,
+            Some('h' | 'H') => Self::Hour,
+            Some('m') => Self::Minute,
+            Some('s') => Self::Second,
+            Some(other) => {
+                return Err(format!(
+                    "Unknown date math unit {other:?}, expected one of `y`, `M`, `w`, `d`, `h`, `m` or `s`"
+                ))
+            },
+            None => return Err("Date math expression is missing a unit".to_string()),
+        };
+
+        Ok(unit)
+    }
+
+    /// Parses the full name of a unit, i.e. `day` or `month`.
+    pub(crate) fn from_name(name: &str) -> Result<Self, String> {
+        let unit = match name {
+            "year" => Self::Year,
+            "month" => Self::Month,
+            "week" => Self::Week,
+            "day" => Self::Day,
+            "hour" => Self::Hour,
+            "minute" => Self::Minute,
+            "second" => Self::Second,
+            other => {
+                return Err(format!(
+                    "Unknown unit {other:?}, expected one of `year`, `month`, `week`, `day`, `hour`, `minute` or `second`"
+                ))
+            },
+        };
+
+        Ok(unit)
+    }
+}
+
+/// Truncates a datetime to the start of the given unit.
+///
+/// Weeks start on Monday and all units are truncated in UTC.
+pub(crate) fn truncate_datetime(dt: DateTime, unit: Unit) -> Result<DateTime, String> {
+    from_offset_datetime(round_down(to_offset_datetime(dt)?, unit)?)
+}
+
+/// Truncates a timestamp in microseconds to the start of the given unit in the
+/// local time of the given UTC offset, i.e. the start of the local day.
+pub(crate) fn truncate_micros(
+    micros: i64,
+    unit: Unit,
+    offset: UtcOffset,
+) -> Option<i64> {
+    let dt = micros_to_offset_datetime(micros, offset)?;
+    offset_datetime_to_micros(round_down(dt, unit).ok()?)
+}
+
+/// Adds an amount of the given unit to a timestamp in microseconds, calendar units
+/// are added in the local time of the given UTC offset.
+pub(crate) fn add_micros(
+    micros: i64,
+    unit: Unit,
+    amount: i64,
+    offset: UtcOffset,
+) -> Option<i64> {
+    let dt = micros_to_offset_datetime(micros, offset)?;
+    offset_datetime_to_micros(add(dt, unit, amount).ok()?)
+}
+
+/// Converts a timestamp in microseconds to a datetime in the given UTC offset.
+pub(crate) fn micros_to_offset_datetime(
+    micros: i64,
+    offset: UtcOffset,
+) -> Option<OffsetDateTime> {
+    // Shifting the timestamp before replacing the offset keeps the conversion checked.
+    let local = micros as i128 * 1000 + offset.whole_seconds() as i128 * 1_000_000_000;
+    let dt = OffsetDateTime::from_unix_timestamp_nanos(local).ok()?;
+    Some(dt.replace_offset(offset))
+}
+
+fn offset_datetime_to_micros(dt: OffsetDateTime) -> Option<i64> {
+    i64::try_from(dt.unix_timestamp_nanos() / 1000).ok()
+}
+
+/// Returns if the value should be interpreted as a date math expression.
+pub(crate) fn is_date_math(value: &str) -> bool {
+    let value = value.trim();
+    value.starts_with("now") || value.contains("||")
+}
+
+/// Resolves a date math expression, i.e. `now-7d/d`, to a datetime.
+///
+/// The expression is anchored to either `now`, which is the context's pinned time,
+/// or an explicit date followed by `||`, i.e. `2023-01-01T00:00:00Z||+1M`.
+///
+/// When `round_up` is `true`, rounding (`/d`) resolves to the last microsecond of the unit
+/// rather than the first, this is used for `gt` and `lte` bounds so the entire unit is
+/// excluded or included respectively.
+pub(crate) fn resolve_date_math(
+    ctx: &QueryContext,
+    expr: &str,
+    round_up: bool,
+) -> Result<DateTime, String> {
+    evaluate(expr.trim(), ctx.now(), round_up, |anchor| {
+        ctx.parse_datetime(anchor).map_err(|e| e.to_string())
+    })
+}
+
+fn evaluate(
+    expr: &str,
+    now: DateTime,
+    round_up: bool,
+    parse_anchor: impl FnOnce(&str) -> Result<DateTime, String>,
+) -> Result<DateTime, String> {
+    let (anchor, ops) = if let Some(ops) = expr.strip_prefix("now") {
+        (now, ops)
+    } else if let Some((anchor, ops)) = expr.split_once("||") {
+        (parse_anchor(anchor)?, ops)
+    } else {
+        return Err(format!(
+            "Invalid date math expression {expr:?}, expected it to start with `now` or `<date>||`"
+        ));
+    };
+
+    let mut dt = to_offset_datetime(anchor)?;
+    let mut chars = ops.chars().peekable();
+    while let Some(op) = chars.next() {
+        match op {
+            '+' | '-' => {
+                let mut amount = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                    amount.push(c);
+                }
+
+                // Amounts are limited to `i32` so the durations cannot overflow.
+                let amount = amount.parse::<i32>().map_err(|_| {
+                    format!("Invalid date math expression {expr:?}, expected a number after `{op}`")
+                })? as i64;
+                let amount = if op == '-' { -amount } else { amount };
+                let unit = Unit::parse(chars.next())?;
+                dt = add(dt, unit, amount)?;
+            },
+            '/' => {
+                let unit = Unit::parse(chars.next())?;
+                dt = round(dt, unit, round_up)?;
+            },
+            other => {
+                return Err(format!(
+                    "Invalid date math expression {expr:?}, unexpected {other:?}"
+                ))
+            },
+        }
+    }
+
+    from_offset_datetime(dt)
+}
+
+fn add(dt: OffsetDateTime, unit: Unit, amount: i64) -> Result<OffsetDateTime, String> {
+    let result = match unit {
+        Unit::Year => add_months(dt, amount.saturating_mul(12)),
+        Unit::Month => add_months(dt, amount),
+        Unit::Week => dt.checked_add(Duration::weeks(amount)),
+        Unit::Day => dt.checked_add(Duration::days(amount)),
+        Unit::Hour => dt.checked_add(Duration::hours(amount)),
+        Unit::Minute => dt.checked_add(Duration::minutes(amount)),
+        Unit::Second => dt.checked_add(Duration::seconds(amount)),
+    };
+
+    result.ok_or_else(|| {
+        "Date math expression goes beyond the supported `datetime` range".to_string()
+    })
+}
+
+/// Adds a number of calendar months, clamping the day to the end of the resulting month.
+fn add_months(dt: OffsetDateTime, months: i64) -> Option<OffsetDateTime> {
+    let total = (dt.year() as i64 * 12 + dt.month() as i64 - 1).checked_add(months)?;
+    let year = i32::try_from(total.div_euclid(12)).ok()?;
+    let month = Month::try_from(total.rem_euclid(12) as u8 + 1).ok()?;
+    let day = dt.day().min(days_in_year_month(year, month));
+
+    let date = Date::from_calendar_date(year, month, day).ok()?;
+    Some(dt.replace_date(date))
+}
+
+fn round(
+    dt: OffsetDateTime,
+    unit: Unit,
+    round_up: bool,
+) -> Result<OffsetDateTime, String> {
+    let start = round_down(dt, unit)?;
+    if !round_up {
+        return Ok(start);
+    }
+
+    add(start, unit, 1)?
+        .checked_sub(Duration::microseconds(1))
+        .ok_or_else(|| {
+            "Date math expression goes beyond the supported `datetime` range".to_string()
+        })
+}
+
+fn round_down(dt: OffsetDateTime, unit: Unit) -> Result<OffsetDateTime, String> {
+    let midnight = dt.replace_time(Time::MIDNIGHT);
+    let rounded = match unit {
+        Unit::Year => Date::from_calendar_date(dt.year(), Month::January, 1)
+            .map(|date| midnight.replace_date(date)),
+        Unit::Month => Date::from_calendar_date(dt.year(), dt.month(), 1)
+            .map(|date| midnight.replace_date(date)),
+        Unit::Week => {
+            let days_since_monday = dt.weekday().number_days_from_monday();
+            return midnight
+                .checked_sub(Duration::days(days_since_monday as i64))
+                .ok_or_else(|| {
+                    "Date math expression goes beyond the supported `datetime` range"
+                        .to_string()
+                });
+        },
+        Unit::Day => Ok(midnight),
+        Unit::Hour => Time::from_hms(dt.hour(), 0, 0).map(|time| dt.replace_time(time)),
+        Unit::Minute => {
+            Time::from_hms(dt.hour(), dt.minute(), 0).map(|time| dt.replace_time(time))
+        },
+        Unit::Second => Time::from_hms(dt.hour(), dt.minute(), dt.second())
+            .map(|time| dt.replace_time(time)),
+    };
+
+    rounded.map_err(|e| format!("Unable to round date math expression: {e}"))
+}
+
+fn to_offset_datetime(dt: DateTime) -> Result<OffsetDateTime, String> {
+    OffsetDateTime::from_unix_timestamp_nanos(dt.as_micros() as i128 * 1000).map_err(
+        |_| "Date math anchor goes beyond the supported `datetime` range".to_string(),
+    )
+}
+
+fn from_offset_datetime(dt: OffsetDateTime) -> Result<DateTime, String> {
+    i64::try_from(dt.unix_timestamp_nanos() / 1000)
+        .ok()
+        .and_then(DateTime::from_micros)
+        .ok_or_else(|| {
+            "Date math expression goes beyond the supported `datetime` range".to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use time::format_description::well_known::Rfc3339;
+
+    use super::*;
+
+    fn datetime(s: &str) -> DateTime {
+        let dt = OffsetDateTime::parse(s, &Rfc3339).unwrap();
+        from_offset_datetime(dt).unwrap()
+    }
+
+    fn eval(expr: &str, round_up: bool) -> Result<DateTime, String> {
+        // A wednesday.
+        let now = datetime("2023-03-15T13:45:30.5Z");
+        evaluate(expr, now, round_up, |anchor| Ok(datetime(anchor)))
+    }
+
+    #[test]
+    fn test_is_date_math() {
+        assert!(is_date_math("now"));
+        assert!(is_date_math("now-7d/d"));
+        assert!(is_date_math("2023-01-01T00:00:00Z||+1M"));
+        assert!(!is_date_math("2023-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_date_math_arithmetic() {
+        assert_eq!(eval("now", false), Ok(datetime("2023-03-15T13:45:30.5Z")));
+        assert_eq!(
+            eval("now-7d", false),
+            Ok(datetime("2023-03-08T13:45:30.5Z"))
+        );
+        assert_eq!(
+            eval("now+1h", false),
+            Ok(datetime("2023-03-15T14:45:30.5Z"))
+        );
+        assert_eq!(
+            eval("now-1y+2M-30m", false),
+            Ok(datetime("2022-05-15T13:15:30.5Z")),
+        );
+        assert_eq!(
+            eval("2023-01-31T00:00:00Z||+1M", false),
+            Ok(datetime("2023-02-28T00:00:00Z")),
+        );
+    }
+
+    #[test]
+    fn test_date_math_rounding() {
+        assert_eq!(eval("now/d", false), Ok(datetime("2023-03-15T00:00:00Z")));
+        assert_eq!(
+            eval("now/d", true),
+            Ok(datetime("2023-03-15T23:59:59.999999Z")),
+        );
+        assert_eq!(
+            eval("now-7d/d", false),
+            Ok(datetime("2023-03-08T00:00:00Z"))
+        );
+        assert_eq!(eval("now/w", false), Ok(datetime("2023-03-13T00:00:00Z")));
+        assert_eq!(eval("now/M", false), Ok(datetime("2023-03-01T00:00:00Z")));
+        assert_eq!(
+            eval("now/M", true),
+            Ok(datetime("2023-03-31T23:59:59.999999Z")),
+        );
+        assert_eq!(eval("now/y", false), Ok(datetime("2023-01-01T00:00:00Z")));
+        assert_eq!(eval("now/h", false), Ok(datetime("2023-03-15T13:00:00Z")));
+        assert_eq!(eval("now/s", false), Ok(datetime("2023-03-15T13:45:30Z")));
+    }
+
+    #[test]
+    fn test_invalid_date_math() {
+        assert!(eval("now-d", false).is_err());
+        assert!(eval("now-1x", false).is_err());
+        assert!(eval("now*2d", false).is_err());
+        assert!(eval("now/", false).is_err());
+        assert!(eval("yesterday", false).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use lnx_document::{DateTime, DynamicDocument, Value};
+use lnx_schema::schema::IndexSchema;
+use lnx_transforms::{DateTimeFormat, DateTimeOutputFormat};
+use serde::Deserialize;
+use time::UtcOffset;
+
+use crate::error::QueryError;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// How `datetime` values should be rendered within a search response.
+pub struct DateTimeOutput {
+    #[serde(default)]
+    /// The format values are rendered in, i.e. `rfc3339`, `unix_millis` or a custom
+    /// format description.
+    ///
+    /// Defaults to the output format of each field within the schema.
+    pub format: Option<String>,
+    #[serde(default)]
+    /// The UTC offset formatted values are rendered in, i.e. `+02:00` or `Z`.
+    ///
+    /// Defaults to `UTC`.
+    pub timezone: Option<String>,
+}
+
+impl DateTimeOutput {
+    /// Compiles the output options into a renderer for documents of the given schema.
+    pub fn compile(&self, schema: &IndexSchema) -> Result<DateTimeRenderer, QueryError> {
+        let format = self
+            .format
+            .as_deref()
+            .map(DateTimeOutputFormat::from_name)
+            .transpose()
+            .map_err(|e| QueryError::Invalid(e.to_string()))?;
+
+        let offset = match self.timezone.as_deref() {
+            None => UtcOffset::UTC,
+            Some(timezone) => parse_utc_offset(timezone)?,
+        };
+
+        let field_formats = schema
+            .fields
+            .iter()
+            .filter_map(|(name, field)| {
+                let format = field.datetime_output_format()?;
+                Some((name.clone(), format))
+            })
+            .collect();
+
+        Ok(DateTimeRenderer {
+            format,
+            offset,
+            field_formats,
+        })
+    }
+}
+
+/// Renders the `datetime` values of documents returned by a search.
+pub struct DateTimeRenderer {
+    format: Option<DateTimeOutputFormat>,
+    offset: UtcOffset,
+    field_formats: HashMap<String, DateTimeOutputFormat>,
+}
+
+impl DateTimeRenderer {
+    /// Renders a single datetime value of the given field.
+    pub fn render<'a>(
+        &self,
+        field: &str,
+        dt: DateTime,
+    ) -> Result<Value<'a>, QueryError> {
+        let format = self
+            .format
+            .as_ref()
+            .or_else(|| self.field_formats.get(field));
+
+        let rendered = match format {
+            Some(format) => format.render_with_offset(dt, self.offset),
+            None => DateTimeOutputFormat::String(DateTimeFormat::Rfc3339)
+                .render_with_offset(dt, self.offset),
+        };
+
+        rendered.map_err(|e| QueryError::InvalidValue {
+            field: field.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Renders every `datetime` value within the document, including values within
+    /// arrays and nested objects which use the format of their top-level field.
+    pub fn render_document(
+        &self,
+        document: &mut DynamicDocument,
+    ) -> Result<(), QueryError> {
+        for (key, value) in document.iter_mut() {
+            self.render_value(key, value)?;
+        }
+        Ok(())
+    }
+
+    fn render_value(&self, field: &str, value: &mut Value) -> Result<(), QueryError> {
+        match value {
+            Value::DateTime(dt) => *value = self.render(field, *dt)?,
+            Value::Array(values) => {
+                for value in values {
+                    self.render_value(field, value)?;
+                }
+            },
+            Value::Object(entries) => {
+                for (_, value) in entries {
+                    self.render_value(field, value)?;
+                }
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a UTC offset, i.e. `Z`, `UTC`, `+02:00`, `-0530` or `+02`.
+pub(crate) fn parse_utc_offset(timezone: &str) -> Result<UtcOffset, QueryError> {
+    let invalid = || {
+        QueryError::Invalid(format!(
+            "Invalid timezone {timezone:?}, expected a UTC offset like `+02:00`"
+        ))
+    };
+
+    if timezone.eq_ignore_ascii_case("z") || timezone.eq_ignore_ascii_case("utc") {
+        return Ok(UtcOffset::UTC);
+    }
+
+    let (sign, offset) = match timezone.as_bytes().first() {
+        Some(b'+') => (1, &timezone[1..]),
+        Some(b'-') => (-1, &timezone[1..]),
+        _ => return Err(invalid()),
+    };
+
+    let digits = offset.replace(':', "");
+    if !matches!(digits.len(), 2 | 4) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let hours: i8 = digits[..2].parse().map_err(|_| invalid())?;
+    let minutes: i8 = match digits.len() {
+        4 => digits[2..].parse().map_err(|_| invalid())?,
+        _ => 0,
+    };
+
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> IndexSchema {
+        serde_json::from_str(
+            r#"{
+                "fields": {
+                    "created_at": {"type": "datetime"},
+                    "updated_at": {"type": "datetime", "output_format": "unix_seconds"}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn document() -> DynamicDocument<'static> {
+        let dt = DateTime::from_secs(1_675_247_400).unwrap();
+        DynamicDocument(vec![
+            ("created_at".into(), Value::DateTime(dt)),
+            ("updated_at".into(), Value::Array(vec![Value::DateTime(dt)])),
+        ])
+    }
+
+    #[test]
+    fn test_render_field_formats() {
+        let renderer = DateTimeOutput::default().compile(&schema()).unwrap();
+
+        let mut document = document();
+        renderer.render_document(&mut document).unwrap();
+        assert_eq!(document[0].1, Value::from("2023-02-01T10:30:00Z"));
+        assert_eq!(document[1].1, Value::Array(vec![Value::I64(1_675_247_400)]));
+    }
+
+    #[test]
+    fn test_render_with_timezone() {
+        let output: DateTimeOutput = serde_json::from_str(
+            r#"{"format": "[year]-[month]-[day] [hour]:[minute]", "timezone": "-05:30"}"#,
+        )
+        .unwrap();
+        let renderer = output.compile(&schema()).unwrap();
+
+        let mut document = document();
+        renderer.render_document(&mut document).unwrap();
+        assert_eq!(document[0].1, Value::from("2023-02-01 05:00"));
+        assert_eq!(
+            document[1].1,
+            Value::Array(vec![Value::from("2023-02-01 05:00")])
+        );
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("Z").unwrap(), UtcOffset::UTC);
+        assert_eq!(
+            parse_utc_offset("+02").unwrap(),
+            UtcOffset::from_hms(2, 0, 0).unwrap()
+        );
+        assert_eq!(
+            parse_utc_offset("-0530").unwrap(),
+            UtcOffset::from_hms(-5, -30, 0).unwrap()
+        );
+        assert!(parse_utc_offset("Europe/London").is_err());
+        assert!(parse_utc_offset("+2").is_err());
+        assert!(parse_utc_offset("+99:00").is_err());
+    }
+}
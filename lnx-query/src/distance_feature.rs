@@ -0,0 +1,286 @@
+use serde::Deserialize;
+use tantivy::query::{EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term, TERMINATED};
+
+use crate::column::{FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::function_score::{
+    resolve_date_origin,
+    resolve_fast_column,
+    DecayDistance,
+    DecayOrigin,
+    DistanceUnit,
+};
+
+fn default_boost() -> Score {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Matches every document with a value for a numeric or `datetime` fast field,
+/// scoring documents by how close their value is to an `origin`.
+///
+/// Documents score `boost * pivot / (pivot + distance)`, so a document at the origin
+/// scores the full `boost` and a document `pivot` away from it scores half of it.
+/// Unlike a decay function no inner query is scored, which makes it cheap to add as a
+/// `should` clause boosting the results of a filter, i.e. recent or nearby listings.
+pub struct DistanceFeatureQuery {
+    /// The numeric or `datetime` fast field holding the value of each document.
+    pub field: String,
+    #[serde(default)]
+    /// The value distances are measured from, a datetime or date math expression for
+    /// `datetime` fields.
+    ///
+    /// Required for numeric fields, defaults to `now` for `datetime` fields.
+    pub origin: Option<DecayOrigin>,
+    /// The distance from the origin at which documents score half of the `boost`,
+    /// a duration like `7d` for `datetime` fields.
+    pub pivot: DecayDistance,
+    #[serde(default = "default_boost")]
+    /// The score of a document at the origin.
+    ///
+    /// Defaults to `1.0`.
+    pub boost: Score,
+}
+
+impl DistanceFeatureQuery {
+    /// Compiles the query into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let invalid =
+            |reason: String| QueryError::Invalid(format!("distance_feature {reason}"));
+
+        let (column, entry) = resolve_fast_column(ctx, &self.field, "distance_feature")?;
+        let (origin, unit) = match (self.origin, column.is_date()) {
+            (None, true) => (ctx.now().as_micros() as f64, DistanceUnit::Duration),
+            (Some(origin), true) => (
+                resolve_date_origin(ctx, entry, origin)?,
+                DistanceUnit::Duration,
+            ),
+            (Some(DecayOrigin::Number(origin)), false) => (origin, DistanceUnit::Number),
+            _ => {
+                return Err(invalid(format!(
+                    "on {:?} requires a number origin",
+                    self.field
+                )))
+            },
+        };
+
+        let pivot = unit.resolve(&self.pivot).map_err(invalid)?;
+        if !(pivot.is_finite() && pivot > 0.0) {
+            return Err(invalid(format!("must have a positive pivot, got {pivot}")));
+        }
+        if !(self.boost.is_finite() && self.boost >= 0.0) {
+            return Err(invalid(format!(
+                "must have a finite, non-negative boost, got {}",
+                self.boost
+            )));
+        }
+
+        Ok(Box::new(DistanceFeatureWrapper {
+            column,
+            origin,
+            pivot,
+            boost: self.boost,
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DistanceFeatureWrapper {
+    column: FastColumn,
+    origin: f64,
+    pivot: f64,
+    boost: Score,
+}
+
+impl Query for DistanceFeatureWrapper {
+    fn weight(
+        &self,
+        _enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn query_terms<'a>(&'a self, _visitor: &mut dyn FnMut(&'a Term, bool)) {}
+}
+
+impl Weight for DistanceFeatureWrapper {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        let mut scorer = DistanceFeatureScorer {
+            column: self.column.open(reader),
+            max_doc: reader.max_doc(),
+            doc: 0,
+            value: None,
+            origin: self.origin,
+            pivot: self.pivot,
+            boost: self.boost * boost,
+        };
+        scorer.seek(0);
+        Ok(Box::new(scorer))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        let mut explanation = Explanation::new(
+            "DistanceFeature, computed as boost * pivot / (pivot + distance)",
+            scorer.score(),
+        );
+        explanation.add_const("boost", self.boost);
+        explanation.add_const("pivot", self.pivot as Score);
+        Ok(explanation)
+    }
+}
+
+/// Visits every document of the segment with a value for the column.
+struct DistanceFeatureScorer {
+    column: Option<SegmentColumn>,
+    max_doc: DocId,
+    doc: DocId,
+    value: Option<f64>,
+    origin: f64,
+    pivot: f64,
+    boost: Score,
+}
+
+impl DocSet for DistanceFeatureScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        self.seek(self.doc + 1)
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if let Some(column) = self.column.as_ref() {
+            for doc in target..self.max_doc {
+                if let Some(value) = column.first(doc) {
+                    self.doc = doc;
+                    self.value = Some(value);
+                    return doc;
+                }
+            }
+        }
+
+        self.doc = TERMINATED;
+        self.value = None;
+        TERMINATED
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.max_doc
+    }
+}
+
+impl Scorer for DistanceFeatureScorer {
+    fn score(&mut self) -> Score {
+        let Some(value) = self.value else {
+            return 0.0;
+        };
+
+        let distance = (value - self.origin).abs();
+        let score = self.boost as f64 * self.pivot / (self.pivot + distance);
+        if score.is_finite() {
+            score as Score
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{DateOptions, SchemaBuilder, FAST, TEXT};
+    use tantivy::{doc, DateTime, Index};
+
+    use super::*;
+    use crate::query::QueryKind;
+
+    #[test]
+    fn test_distance_feature_query() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let price = schema.add_f64_field("price", FAST);
+        let published =
+            schema.add_date_field("published", DateOptions::default().set_fast());
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let day = 86_400;
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(
+                title => "lamp",
+                price => 50.0,
+                published => DateTime::from_timestamp_secs(90 * day),
+            ))
+            .unwrap();
+        writer
+            .add_document(doc!(
+                title => "lamp",
+                price => 10.0,
+                published => DateTime::from_timestamp_secs(99 * day),
+            ))
+            .unwrap();
+        writer.add_document(doc!(title => "lamp")).unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let search = |json: &str| {
+            let query: QueryKind = serde_json::from_str(json).unwrap();
+            let query = query.build(&ctx).unwrap();
+            searcher.search(&query, &TopDocs::with_limit(3)).unwrap()
+        };
+
+        let hits = search(
+            r#"{"distance_feature": {"field": "price", "origin": 40, "pivot": 10, "boost": 2}}"#,
+        );
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].1.doc_id, 0);
+        assert!((hits[0].0 - 1.0).abs() < 1e-6);
+        assert!((hits[1].0 - 0.5).abs() < 1e-6);
+
+        let origin = 100 * day;
+        let hits = search(&format!(
+            r#"{{"distance_feature": {{"field": "published", "origin": {origin}, "pivot": "1d"}}}}"#
+        ));
+        assert_eq!(hits[0].1.doc_id, 1);
+        assert!((hits[0].0 - 0.5).abs() < 1e-6);
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{"distance_feature": {"field": "price", "pivot": 10}}"#,
+        )
+        .unwrap();
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{"distance_feature": {"field": "title", "origin": 1, "pivot": 10}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+use tantivy::columnar::Column;
+use tantivy::query::{EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
+
+#[derive(Debug)]
+/// Multiplies the score of each document matching the inner query by the
+/// document's boost, read from an `f64` fast field.
+///
+/// Documents without a boost keep their original score.
+pub(crate) struct DocumentBoostQuery {
+    query: Box<dyn Query>,
+    field: String,
+}
+
+impl Clone for DocumentBoostQuery {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.box_clone(),
+            field: self.field.clone(),
+        }
+    }
+}
+
+impl DocumentBoostQuery {
+    /// Creates a new document boost query reading boosts from the given fast field.
+    pub(crate) fn new(query: Box<dyn Query>, field: String) -> Self {
+        Self { query, field }
+    }
+}
+
+impl Query for DocumentBoostQuery {
+    fn weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        let weight = self.query.weight(enable_scoring)?;
+        if !enable_scoring.is_scoring_enabled() {
+            return Ok(weight);
+        }
+
+        Ok(Box::new(DocumentBoostWeight {
+            weight,
+            field: self.field.clone(),
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.query.query_terms(visitor);
+    }
+}
+
+struct DocumentBoostWeight {
+    weight: Box<dyn Weight>,
+    field: String,
+}
+
+impl Weight for DocumentBoostWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        let scorer = self.weight.scorer(reader, boost)?;
+
+        // Segments written before document boosts were enabled have no column.
+        match reader.fast_fields().f64(&self.field) {
+            Ok(boosts) => Ok(Box::new(DocumentBoostScorer { scorer, boosts })),
+            Err(_) => Ok(scorer),
+        }
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        let mut explanation = Explanation::new("DocumentBoost", scorer.score());
+        explanation.add_detail(self.weight.explain(reader, doc)?);
+        Ok(explanation)
+    }
+}
+
+struct DocumentBoostScorer {
+    scorer: Box<dyn Scorer>,
+    boosts: Column<f64>,
+}
+
+impl DocSet for DocumentBoostScorer {
+    fn advance(&mut self) -> DocId {
+        self.scorer.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.scorer.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+}
+
+impl Scorer for DocumentBoostScorer {
+    fn score(&mut self) -> Score {
+        let score = self.scorer.score();
+        match self.boosts.first(self.scorer.doc()) {
+            Some(boost) if boost.is_finite() && boost > 0.0 => score * boost as Score,
+            _ => score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{SchemaBuilder, FAST, TEXT};
+    use tantivy::{doc, Index};
+
+    use crate::context::QueryContext;
+    use crate::search::SearchRequest;
+
+    #[test]
+    fn test_document_boost() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let boost = schema.add_f64_field("_boost", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer.add_document(doc!(title => "running shoes")).unwrap();
+        writer
+            .add_document(doc!(title => "running shoes sale", boost => 3.0))
+            .unwrap();
+        writer.add_document(doc!(title => "shoes")).unwrap();
+        writer.commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        let search = |ctx: &QueryContext| {
+            let request: SearchRequest = serde_json::from_str(
+                r#"{"query": {"query_string": {"query": "running shoes"}}}"#,
+            )
+            .unwrap();
+            let query = request.build_query(ctx).unwrap();
+            searcher.search(&query, &TopDocs::with_limit(3)).unwrap()
+        };
+
+        let hits = search(&QueryContext::new(schema.clone()));
+        assert_eq!(hits[0].1.doc_id, 0);
+
+        let ctx = QueryContext::new(schema).with_document_boost_field(boost);
+        let boosted = search(&ctx);
+        assert_eq!(boosted[0].1.doc_id, 1);
+
+        // Documents without a boost keep their original score.
+        let unboosted = |hits: &[(f32, tantivy::DocAddress)]| {
+            hits.iter().find(|(_, addr)| addr.doc_id == 0).unwrap().0
+        };
+        assert_eq!(unboosted(&hits), unboosted(&boosted));
+    }
+}
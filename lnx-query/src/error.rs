@@ -0,0 +1,55 @@
+#[derive(Debug, thiserror::Error)]
+/// An error that occurred while compiling a query for a given index.
+pub enum QueryError {
+    #[error("Unknown field {0:?}, the field does not exist in the schema")]
+    /// The query targets a field that does not exist in the schema.
+    UnknownField(String),
+    #[error("Field {field:?} cannot be used for a {query} query: {reason}")]
+    /// The query targets a field which cannot be used with the given query kind.
+    UnsupportedField {
+        field: String,
+        query: &'static str,
+        reason: String,
+    },
+    #[error("Unknown index {0:?}, the index does not exist")]
+    /// The search targets an index that does not exist.
+    UnknownIndex(String),
+    #[error("Unknown document {0}, the document does not exist")]
+    /// The request targets a document that does not exist.
+    UnknownDocument(u64),
+    #[error("Invalid value provided for field {field:?}: {message}")]
+    /// A value provided as part of the query could not be cast to the field type.
+    InvalidValue { field: String, message: String },
+    #[error("Invalid query: {0}")]
+    /// The query itself is malformed.
+    Invalid(String),
+    #[error("Unable to read index data: {0}")]
+    /// The query required reading data from the index which failed.
+    Index(#[from] tantivy::TantivyError),
+}
+
+impl QueryError {
+    /// Creates a new unsupported field error.
+    pub(crate) fn unsupported(
+        field: impl Into<String>,
+        query: &'static str,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self::UnsupportedField {
+            field: field.into(),
+            query,
+            reason: reason.into(),
+        }
+    }
+
+    /// Creates a new invalid value error.
+    pub(crate) fn invalid_value(
+        field: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::InvalidValue {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
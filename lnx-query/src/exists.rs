@@ -0,0 +1,194 @@
+use lnx_schema::indexing::field_presence_key;
+use serde::Deserialize;
+use tantivy::query::{AllQuery, BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, FieldType, IndexRecordOption};
+use tantivy::Term;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+#[derive(Debug, Deserialize)]
+/// Matches documents which have a non-null value for the given field.
+///
+/// The field can be a path into a dynamic object field i.e. `meta.author`.
+pub struct ExistsQuery {
+    /// The field to check the presence of.
+    pub field: String,
+}
+
+impl ExistsQuery {
+    /// Compiles the exists query into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        build_presence_query(ctx, &self.field, "exists")
+            .map(|query| Box::new(query) as Box<dyn Query>)
+    }
+
+    /// Compiles the query into a tantivy query matching documents
+    /// which are *missing* the field.
+    pub fn build_missing(
+        self,
+        ctx: &QueryContext,
+    ) -> Result<Box<dyn Query>, QueryError> {
+        let exists = build_presence_query(ctx, &self.field, "missing")?;
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(AllQuery) as Box<dyn Query>),
+            (Occur::MustNot, Box::new(exists)),
+        ]);
+        Ok(Box::new(query))
+    }
+}
+
+fn build_presence_query(
+    ctx: &QueryContext,
+    path: &str,
+    kind: &'static str,
+) -> Result<TermQuery, QueryError> {
+    let Some(presence_field) = ctx.field_presence_field() else {
+        return Err(QueryError::unsupported(
+            path,
+            kind,
+            "field presence is not tracked for this index",
+        ));
+    };
+
+    let (field, key) = resolve_path(ctx, path)?;
+    let term = Term::from_field_u64(presence_field, field_presence_key(field, key));
+    Ok(TermQuery::new(term, IndexRecordOption::Basic))
+}
+
+/// Resolves a field path into the schema field and the key within the field.
+///
+/// If the path does not directly reference a schema field the longest prefix
+/// of the path which references a dynamic object field is used instead.
+/// Field aliases are resolved for the path and each prefix.
+fn resolve_path<'a>(
+    ctx: &QueryContext,
+    path: &'a str,
+) -> Result<(Field, Option<&'a str>), QueryError> {
+    if let Ok(field) = ctx.schema().get_field(ctx.resolve_alias(path)) {
+        return Ok((field, None));
+    }
+
+    for (pos, _) in path.rmatch_indices('.') {
+        let Ok(field) = ctx.schema().get_field(ctx.resolve_alias(&path[..pos])) else {
+            continue;
+        };
+
+        let entry = ctx.schema().get_field_entry(field);
+        if matches!(entry.field_type(), FieldType::JsonObject(_)) {
+            return Ok((field, Some(&path[pos + 1..])));
+        }
+    }
+
+    Err(QueryError::UnknownField(path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use lnx_document::{DynamicDocument, Value};
+    use lnx_schema::presence::{add_field_presence_field, apply_field_presence};
+    use tantivy::collector::Count;
+    use tantivy::schema::{Schema, SchemaBuilder, INDEXED, STORED, TEXT};
+    use tantivy::{Document, Index};
+
+    use super::*;
+
+    /// Converts the document into a tantivy document, object values are skipped
+    /// as only their presence is needed.
+    fn to_tantivy_document(schema: &Schema, document: &DynamicDocument) -> Document {
+        fn add_value(doc: &mut Document, field: Field, value: &Value) {
+            match value {
+                Value::Str(text) => doc.add_text(field, text),
+                Value::U64(v) => doc.add_u64(field, *v),
+                Value::Array(values) => {
+                    for value in values {
+                        add_value(doc, field, value);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let mut doc = Document::default();
+        for (key, value) in document.iter() {
+            add_value(&mut doc, schema.get_field(key).unwrap(), value);
+        }
+        doc
+    }
+
+    #[test]
+    fn test_exists_indexed_documents() {
+        let mut builder = SchemaBuilder::new();
+        builder.add_text_field("title", TEXT);
+        builder.add_text_field("subtitle", TEXT);
+        builder.add_json_field("meta", STORED);
+        let presence = add_field_presence_field(&mut builder);
+        let schema = builder.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let documents = [
+            r#"{"title": "first", "meta": {"author": "bob"}}"#,
+            r#"{"title": "second", "subtitle": null}"#,
+            r#"{"title": "third", "subtitle": "present"}"#,
+        ];
+        let mut writer = index.writer(15_000_000).unwrap();
+        for json in documents {
+            let mut document: DynamicDocument = serde_json::from_str(json).unwrap();
+            apply_field_presence(&schema, &mut document);
+            writer
+                .add_document(to_tantivy_document(&schema, &document))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema).with_field_presence_field(presence);
+        let searcher = index.reader().unwrap().searcher();
+        let count = |field: &str, missing: bool| {
+            let query = ExistsQuery {
+                field: field.to_string(),
+            };
+            let query = if missing {
+                query.build_missing(&ctx).unwrap()
+            } else {
+                query.build(&ctx).unwrap()
+            };
+            searcher.search(&query, &Count).unwrap()
+        };
+
+        assert_eq!(count("title", false), 3);
+        assert_eq!(count("subtitle", false), 1);
+        assert_eq!(count("subtitle", true), 2);
+        assert_eq!(count("meta", false), 1);
+        assert_eq!(count("meta.author", false), 1);
+        assert_eq!(count("meta.author", true), 2);
+        assert_eq!(count("meta.editor", false), 0);
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let meta = schema.add_json_field("meta", STORED);
+        let presence = schema.add_u64_field("_presence", INDEXED);
+        let ctx = QueryContext::new(schema.build())
+            .with_field_presence_field(presence)
+            .with_field_alias("heading", "title")
+            .with_field_alias("metadata", "meta");
+
+        assert_eq!(resolve_path(&ctx, "title").unwrap(), (title, None));
+        assert_eq!(resolve_path(&ctx, "heading").unwrap(), (title, None));
+        assert_eq!(
+            resolve_path(&ctx, "metadata.author").unwrap(),
+            (meta, Some("author"))
+        );
+        assert_eq!(resolve_path(&ctx, "meta").unwrap(), (meta, None));
+        assert_eq!(
+            resolve_path(&ctx, "meta.author.name").unwrap(),
+            (meta, Some("author.name"))
+        );
+        assert!(matches!(
+            resolve_path(&ctx, "title.nested"),
+            Err(QueryError::UnknownField(_))
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::BTreeMap;
+
+use lnx_metastore::Metastore;
+use lnx_schema::similarity::Bm25Params;
+use serde::{Deserialize, Serialize};
+use tantivy::query::AllQuery;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::freshness::Freshness;
+use crate::query_string::check_boost;
+use crate::search::SearchRequest;
+
+/// The metastore database the experiment variants of each index are stored in.
+const EXPERIMENTS_DATABASE: &str = "lnx_experiments";
+
+fn default_traffic() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A named ranking configuration compared against the other variants of an index.
+pub struct RankingVariant {
+    #[serde(default = "default_traffic")]
+    /// The share of user tokens routed to the variant relative to the other variants.
+    ///
+    /// Variants with no traffic are only used when explicitly requested.
+    /// Defaults to `1`.
+    pub traffic: u32,
+    #[serde(default)]
+    /// The boosts applied to free-text matches on each field, replacing the boosts
+    /// of the index's schema.
+    pub field_boosts: BTreeMap<String, f32>,
+    #[serde(default)]
+    /// The BM25 parameters used to score free-text matches on each field, replacing
+    /// the parameters of the index's schema.
+    pub bm25: BTreeMap<String, Bm25Params>,
+    #[serde(default)]
+    /// Ranks recent documents higher unless the request sets its own `freshness`.
+    pub freshness: Option<Freshness>,
+}
+
+impl RankingVariant {
+    fn validate(&self, ctx: &QueryContext) -> Result<(), QueryError> {
+        for (name, boost) in self.field_boosts.iter() {
+            check_boost(name, *boost)?;
+            ctx.resolve_text_field(name, "experiment")?;
+        }
+
+        for (name, params) in self.bm25.iter() {
+            params.validate().map_err(|reason| {
+                QueryError::Invalid(format!(
+                    "BM25 parameters for field {name:?} are invalid: {reason}"
+                ))
+            })?;
+            ctx.resolve_text_field(name, "experiment")?;
+        }
+
+        if let Some(freshness) = self.freshness.clone() {
+            freshness.apply(ctx, Box::new(AllQuery))?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the field boosts and BM25 parameters of the variant to the context.
+    pub fn configure(&self, mut ctx: QueryContext) -> QueryContext {
+        for (name, boost) in self.field_boosts.iter() {
+            ctx = ctx.with_field_boost(name, *boost);
+        }
+        for (name, params) in self.bm25.iter() {
+            ctx = ctx.with_field_bm25(name, *params);
+        }
+        ctx
+    }
+
+    /// Applies the variant's options to the request which it does not set itself.
+    pub fn apply(&self, request: &mut SearchRequest) {
+        if request.freshness.is_none() {
+            request.freshness = self.freshness.clone();
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+/// How the variant of a search was picked.
+pub enum AssignmentSource {
+    /// The request named the variant via `experiment`.
+    Requested,
+    /// The variant was picked by hashing the request's `user_token`.
+    UserToken,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// The variant a search was ranked with, returned alongside the hits so results can
+/// be compared between variants offline.
+pub struct ExperimentAssignment {
+    /// The name of the variant.
+    pub variant: String,
+    /// How the variant was picked.
+    pub source: AssignmentSource,
+}
+
+/// A registered variant, kept alongside its source so it can be persisted.
+struct RegisteredVariant {
+    source: String,
+    variant: RankingVariant,
+}
+
+#[derive(Default)]
+/// The ranking variants of an index compared by an A/B experiment, keyed by their name.
+///
+/// Each search is ranked by the variant it names via `experiment`, otherwise its
+/// `user_token` is hashed so a user consistently sees the same variant, with tokens
+/// split between the variants proportionally to their `traffic`. Searches without
+/// either use the index's own configuration.
+pub struct Experiments {
+    variants: BTreeMap<String, RegisteredVariant>,
+}
+
+impl Experiments {
+    /// Registers a variant under the given name, replacing any existing variant.
+    pub fn set(
+        &mut self,
+        ctx: &QueryContext,
+        name: impl Into<String>,
+        source: impl Into<String>,
+    ) -> Result<(), QueryError> {
+        let source = source.into();
+        let variant: RankingVariant = serde_json::from_str(&source)
+            .map_err(|e| QueryError::Invalid(format!("Unable to parse variant: {e}")))?;
+        variant.validate(ctx)?;
+
+        self.variants
+            .insert(name.into(), RegisteredVariant { source, variant });
+        Ok(())
+    }
+
+    /// Removes a variant, returning if it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.variants.remove(name).is_some()
+    }
+
+    /// Returns the variant with the given name if it exists.
+    pub fn get(&self, name: &str) -> Option<&RankingVariant> {
+        self.variants
+            .get(name)
+            .map(|registered| &registered.variant)
+    }
+
+    /// The number of registered variants.
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    /// Returns if there are no registered variants.
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Picks the variant of the request, if any.
+    pub fn assign(
+        &self,
+        request: &SearchRequest,
+    ) -> Result<Option<(ExperimentAssignment, &RankingVariant)>, QueryError> {
+        if let Some(name) = request.experiment.as_deref() {
+            let Some(variant) = self.get(name) else {
+                return Err(QueryError::Invalid(format!(
+                    "Unknown experiment variant {name:?}"
+                )));
+            };
+            let assignment = ExperimentAssignment {
+                variant: name.to_string(),
+                source: AssignmentSource::Requested,
+            };
+            return Ok(Some((assignment, variant)));
+        }
+
+        let Some(token) = request.user_token.as_deref() else {
+            return Ok(None);
+        };
+
+        let total = self
+            .variants
+            .values()
+            .map(|registered| registered.variant.traffic as u64)
+            .sum::<u64>();
+        if total == 0 {
+            return Ok(None);
+        }
+
+        let mut bucket = token_hash(token) % total;
+        for (name, registered) in self.variants.iter() {
+            let traffic = registered.variant.traffic as u64;
+            if bucket < traffic {
+                let assignment = ExperimentAssignment {
+                    variant: name.clone(),
+                    source: AssignmentSource::UserToken,
+                };
+                return Ok(Some((assignment, &registered.variant)));
+            }
+            bucket -= traffic;
+        }
+
+        unreachable!("the bucket is always less than the total traffic")
+    }
+}
+
+/// Hashes a user token with FNV-1a, which unlike the std hashers is stable across
+/// versions so users keep their variant between upgrades.
+fn token_hash(token: &str) -> u64 {
+    let hash = token
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    // The low bits of FNV-1a are poorly mixed, so fold the high bits into them.
+    hash ^ (hash >> 32)
+}
+
+/// The persisted experiment variants of every index.
+pub struct ExperimentStore {
+    metastore: Metastore,
+}
+
+impl ExperimentStore {
+    /// Opens the experiment store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(EXPERIMENTS_DATABASE)?;
+        Ok(Self { metastore })
+    }
+
+    /// Persists the variants of the index.
+    ///
+    /// The variants are stored as a JSON object of each variant's name to its source.
+    pub fn save(&self, index: &str, experiments: &Experiments) -> anyhow::Result<()> {
+        let variants = experiments
+            .variants
+            .iter()
+            .map(|(name, registered)| (name.as_str(), registered.source.as_str()))
+            .collect::<BTreeMap<_, _>>();
+        let source = serde_json::to_string(&variants)?;
+        self.metastore.put(&index, &source)
+    }
+
+    /// Loads and validates the variants of the index.
+    pub fn load(&self, index: &str, ctx: &QueryContext) -> anyhow::Result<Experiments> {
+        let variants = match self.metastore.get::<_, String>(&index)? {
+            Some(source) => serde_json::from_str::<BTreeMap<String, String>>(&source)?,
+            None => BTreeMap::new(),
+        };
+
+        let mut experiments = Experiments::default();
+        for (name, source) in variants {
+            experiments.set(ctx, name, source)?;
+        }
+        Ok(experiments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{SchemaBuilder, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_experiment_assignment() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let body = schema.add_text_field("body", TEXT);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "trail shoes", body => "for running"))
+            .unwrap();
+        writer
+            .add_document(doc!(title => "running shoes", body => "for trails"))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema.clone());
+        let mut experiments = Experiments::default();
+        experiments
+            .set(
+                &ctx,
+                "title_boost",
+                r#"{"traffic": 3, "field_boosts": {"title": 5}}"#,
+            )
+            .unwrap();
+        experiments
+            .set(&ctx, "body_boost", r#"{"field_boosts": {"body": 5}}"#)
+            .unwrap();
+        experiments
+            .set(&ctx, "shadow", r#"{"traffic": 0}"#)
+            .unwrap();
+        assert!(experiments
+            .set(&ctx, "invalid", r#"{"field_boosts": {"missing": 2}}"#)
+            .is_err());
+
+        fn request(json: &str) -> SearchRequest<'_> {
+            serde_json::from_str(json).unwrap()
+        }
+
+        // Tokens are consistently routed to a variant, proportionally to its traffic.
+        let mut counts = BTreeMap::<String, usize>::new();
+        for i in 0..1_000 {
+            let json = format!(r#"{{"user_token": "user-{i}"}}"#);
+            let request = request(&json);
+            let (assignment, _) = experiments.assign(&request).unwrap().unwrap();
+            assert_eq!(assignment.source, AssignmentSource::UserToken);
+            let (again, _) = experiments.assign(&request).unwrap().unwrap();
+            assert_eq!(assignment, again);
+            *counts.entry(assignment.variant).or_default() += 1;
+        }
+        assert!(counts["title_boost"] > counts["body_boost"]);
+        assert!(!counts.contains_key("shadow"));
+
+        assert!(experiments.assign(&request("{}")).unwrap().is_none());
+        assert!(experiments
+            .assign(&request(r#"{"experiment": "missing"}"#))
+            .is_err());
+
+        let searcher = index.reader().unwrap().searcher();
+        let search = |json: &str| {
+            let mut request = request(json);
+            let ctx = QueryContext::new(schema.clone());
+            let (assignment, variant) = experiments.assign(&request).unwrap().unwrap();
+            variant.apply(&mut request);
+            let ctx = variant.configure(ctx);
+            let query = request.build_query(&ctx).unwrap();
+            let hits = searcher.search(&query, &TopDocs::with_limit(1)).unwrap();
+            (assignment, hits[0].1.doc_id)
+        };
+
+        let query = r#""query": {"query_string": {"query": "running"}}"#;
+        let (assignment, doc) =
+            search(&format!(r#"{{{query}, "experiment": "title_boost"}}"#));
+        assert_eq!(assignment.variant, "title_boost");
+        assert_eq!(assignment.source, AssignmentSource::Requested);
+        assert_eq!(doc, 1);
+
+        let (_, doc) = search(&format!(r#"{{{query}, "experiment": "body_boost"}}"#));
+        assert_eq!(doc, 0);
+    }
+}
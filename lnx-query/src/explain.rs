@@ -0,0 +1,110 @@
+use serde::Serialize;
+use tantivy::collector::DocSetCollector;
+use tantivy::query::{Explanation, Query, TermQuery};
+use tantivy::schema::IndexRecordOption;
+use tantivy::{Score, Searcher, Term};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+#[derive(Debug, Serialize)]
+/// Describes how the score of a single document was computed for a query.
+pub struct ExplainResponse {
+    /// The ID of the explained document.
+    pub doc_id: u64,
+    /// If the document matches the query.
+    pub matched: bool,
+    /// The score of the document, `0.0` if it does not match.
+    pub score: Score,
+    /// The tree of score contributions, i.e. the term frequency, inverse document
+    /// frequency, field norms and boosts of each matching term.
+    ///
+    /// This is `None` if the document does not match.
+    pub explanation: Option<Explanation>,
+}
+
+/// Explains the score of the document with the given ID for the query.
+///
+/// Requires the context to have a document ID field, see
+/// [QueryContext::with_document_id_field].
+pub fn explain_document(
+    ctx: &QueryContext,
+    searcher: &Searcher,
+    query: &dyn Query,
+    doc_id: u64,
+) -> Result<ExplainResponse, QueryError> {
+    let Some(id_field) = ctx.document_id_field() else {
+        return Err(QueryError::Invalid(
+            "Explaining a document requires the document ID field to be set".to_string(),
+        ));
+    };
+
+    let term = Term::from_field_u64(id_field, doc_id);
+    let id_query = TermQuery::new(term, IndexRecordOption::Basic);
+    let Some(address) = searcher
+        .search(&id_query, &DocSetCollector)?
+        .into_iter()
+        .next()
+    else {
+        return Err(QueryError::UnknownDocument(doc_id));
+    };
+
+    // The explanation of a document which does not match is an error.
+    let explanation = query.explain(searcher, address).ok();
+    Ok(ExplainResponse {
+        doc_id,
+        matched: explanation.is_some(),
+        score: explanation.as_ref().map(|e| e.value()).unwrap_or_default(),
+        explanation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, INDEXED, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::search::SearchRequest;
+
+    #[test]
+    fn test_explain_document() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let id = schema.add_u64_field("_id", INDEXED);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(id => 1u64, title => "running shoes"))
+            .unwrap();
+        writer
+            .add_document(doc!(id => 2u64, title => "gift card"))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema).with_document_id_field(id);
+        let searcher = index.reader().unwrap().searcher();
+        let request: SearchRequest = serde_json::from_str(
+            r#"{"query": {"query_string": {"query": "running shoes", "boosts": {"title": 2}}}}"#,
+        )
+        .unwrap();
+        let query = request.build_query(&ctx).unwrap();
+
+        let explained = explain_document(&ctx, &searcher, query.as_ref(), 1).unwrap();
+        assert!(explained.matched);
+        assert!(explained.score > 0.0);
+        let json = serde_json::to_string(&explained.explanation).unwrap();
+        assert!(json.contains("idf"));
+
+        let explained = explain_document(&ctx, &searcher, query.as_ref(), 2).unwrap();
+        assert!(!explained.matched);
+        assert_eq!(explained.score, 0.0);
+
+        assert!(matches!(
+            explain_document(&ctx, &searcher, query.as_ref(), 3),
+            Err(QueryError::UnknownDocument(3))
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use tantivy::query::{BooleanQuery, Occur, Query, RegexQuery, TermQuery};
+use tantivy::schema::{Facet, Field, FieldType, IndexRecordOption};
+use tantivy::Term;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query_string::Operator;
+use crate::regex::{compile_regex, push_escaped};
+
+/// The separator used between the segments of an encoded facet.
+const FACET_SEP: char = '\u{0}';
+
+#[derive(Debug, Deserialize)]
+/// Matches documents with a facet under any (or all) of the given facet paths.
+///
+/// I.e. the path `/electronics/phones` matches documents with the facet
+/// `/electronics/phones` and `/electronics/phones/android`.
+pub struct FacetQuery {
+    /// The facet field to match against.
+    pub field: String,
+    /// The facet paths to match.
+    pub paths: Vec<String>,
+    #[serde(default)]
+    /// The maximum number of levels below each path a facet may be.
+    ///
+    /// A depth of `0` only matches documents with the exact facet path, by default
+    /// there is no limit.
+    pub depth: Option<usize>,
+    #[serde(default)]
+    /// If documents must be under any (`Or`) or all (`And`) of the paths.
+    pub operator: Operator,
+}
+
+impl FacetQuery {
+    /// Compiles the facet paths into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let (field, entry) = ctx.resolve_field(&self.field)?;
+
+        if !matches!(entry.field_type(), FieldType::Facet(_)) {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "facet",
+                "only facet fields can be used",
+            ));
+        }
+
+        if self.paths.is_empty() {
+            return Err(QueryError::Invalid(format!(
+                "Facet query on field {:?} must specify at least one path",
+                self.field,
+            )));
+        }
+
+        let occur = match self.operator {
+            Operator::Or => Occur::Should,
+            Operator::And => Occur::Must,
+        };
+
+        let mut clauses = Vec::with_capacity(self.paths.len());
+        for path in self.paths.iter() {
+            let facet = Facet::from_text(path)
+                .map_err(|e| QueryError::invalid_value(&self.field, e.to_string()))?;
+            let query = self.build_path_query(ctx, field, &facet)?;
+            clauses.push((occur, query));
+        }
+
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    fn build_path_query(
+        &self,
+        ctx: &QueryContext,
+        field: Field,
+        facet: &Facet,
+    ) -> Result<Box<dyn Query>, QueryError> {
+        let term = Term::from_facet(field, facet);
+        let under_path = Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+
+        let Some(depth) = self.depth else {
+            return Ok(under_path);
+        };
+
+        // Facets are indexed along with all of their ancestors, so any document with
+        // a facet deeper than the limit will also have a facet at exactly `depth + 1`
+        // levels below the path, which is what we exclude.
+        let pattern = too_deep_pattern(facet.encoded_str(), depth);
+        let regex = compile_regex(ctx.regex_size_limit(), &pattern).map_err(|e| {
+            QueryError::Invalid(format!(
+                "Unable to compile facet depth limit for path {facet}: {e}"
+            ))
+        })?;
+
+        Ok(Box::new(BooleanQuery::new(vec![
+            (Occur::Must, under_path as Box<dyn Query>),
+            (
+                Occur::MustNot,
+                Box::new(RegexQuery::from_regex(regex, field)),
+            ),
+        ])))
+    }
+}
+
+/// Produces a regex matching encoded facets exactly `depth + 1` levels below the path.
+fn too_deep_pattern(encoded_path: &str, depth: usize) -> String {
+    let mut pattern = String::with_capacity(encoded_path.len() + 16);
+    for c in encoded_path.chars() {
+        if c == FACET_SEP {
+            pattern.push_str("\\x00");
+        } else {
+            push_escaped(&mut pattern, c);
+        }
+    }
+
+    pattern.push_str(&format!("(\\x00[^\\x00]*){{{}}}", depth + 1));
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_too_deep_pattern() {
+        let facet = Facet::from_text("/electronics/phones").unwrap();
+        assert_eq!(
+            too_deep_pattern(facet.encoded_str(), 0),
+            "electronics\\x00phones(\\x00[^\\x00]*){1}",
+        );
+        assert_eq!(
+            too_deep_pattern(facet.encoded_str(), 2),
+            "electronics\\x00phones(\\x00[^\\x00]*){3}",
+        );
+
+        let facet = Facet::from_text("/a.b").unwrap();
+        assert_eq!(
+            too_deep_pattern(facet.encoded_str(), 0),
+            "a\\.b(\\x00[^\\x00]*){1}",
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use serde::Deserialize;
+use tantivy::query::Query;
+use tantivy::schema::FieldType;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::function_score::{
+    apply_functions,
+    BoostMode,
+    DecayDistance,
+    DecayFunction,
+    DecayOrigin,
+    FunctionScoreMode,
+    ScoreFunction,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Ranks recent documents higher by decaying the score of each document by the age
+/// of a `datetime` fast field, i.e. for news or log search.
+///
+/// The score halves every `half_life`, so with a `half_life` of `1d` a document
+/// published a day ago scores half as much as an otherwise equal document published
+/// now, and a document published two days ago a quarter as much.
+///
+/// This is a shorthand for a `function_score` query with a single `exp` decay.
+pub struct Freshness {
+    /// The `datetime` fast field holding the age of each document.
+    pub field: String,
+    /// The duration after which the score of a document is halved, i.e. `12h` or `7d`.
+    pub half_life: DecayDistance,
+    #[serde(default)]
+    /// The time ages are measured from, either a datetime or date math expression.
+    ///
+    /// Defaults to `now`.
+    pub origin: Option<DecayOrigin>,
+}
+
+impl Freshness {
+    /// Wraps the query so its scores decay with the age of each document.
+    pub fn apply(
+        self,
+        ctx: &QueryContext,
+        query: Box<dyn Query>,
+    ) -> Result<Box<dyn Query>, QueryError> {
+        let (_, entry) = ctx.resolve_field(&self.field)?;
+        if !matches!(entry.field_type(), FieldType::Date(_)) {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "freshness",
+                "only datetime fields can be used",
+            ));
+        }
+
+        let decay = DecayFunction {
+            field: Some(self.field),
+            lat_field: None,
+            lon_field: None,
+            origin: self.origin,
+            scale: self.half_life,
+            offset: None,
+            decay: 0.5,
+        };
+
+        apply_functions(
+            ctx,
+            query,
+            vec![ScoreFunction::Exp(decay)],
+            FunctionScoreMode::Multiply,
+            BoostMode::Multiply,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{DateOptions, SchemaBuilder, FAST, TEXT};
+    use tantivy::{doc, DateTime, Index};
+
+    use super::*;
+    use crate::search::SearchRequest;
+
+    #[test]
+    fn test_freshness() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let published =
+            schema.add_date_field("published", DateOptions::default().set_fast());
+        schema.add_u64_field("views", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let day = 86_400;
+        let now = 100 * day;
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(
+                title => "rust rust release",
+                published => DateTime::from_timestamp_secs(now - 10 * day),
+            ))
+            .unwrap();
+        writer
+            .add_document(doc!(
+                title => "rust release",
+                published => DateTime::from_timestamp_secs(now - day),
+            ))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let search = |json: &str| {
+            let request: SearchRequest = serde_json::from_str(json).unwrap();
+            let query = request.build_query(&ctx).unwrap();
+            searcher.search(&query, &TopDocs::with_limit(2)).unwrap()
+        };
+
+        let hits = search(r#"{"query": {"query_string": {"query": "rust"}}}"#);
+        assert_eq!(hits[0].1.doc_id, 0);
+
+        let origin = now.to_string();
+        let hits = search(&format!(
+            r#"{{
+                "query": {{"query_string": {{"query": "rust"}}}},
+                "freshness": {{"field": "published", "half_life": "1d", "origin": {origin}}}
+            }}"#
+        ));
+        assert_eq!(hits[0].1.doc_id, 1);
+
+        let request: SearchRequest = serde_json::from_str(
+            r#"{"freshness": {"field": "views", "half_life": "1d"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            request.build_query(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+    }
+}
@@ -0,0 +1,1123 @@
+use std::sync::Arc;
+
+use lnx_document::Value;
+use serde::Deserialize;
+use tantivy::query::{EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::schema::FieldEntry;
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
+
+use crate::column::{ColumnType, FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::date_math::{is_date_math, resolve_date_math};
+use crate::error::QueryError;
+use crate::query::QueryKind;
+
+/// The mean radius of the earth in meters.
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+fn default_factor() -> f64 {
+    1.0
+}
+
+fn default_decay() -> f64 {
+    0.5
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the scores produced by each function are combined.
+pub enum FunctionScoreMode {
+    #[default]
+    /// The scores are multiplied together.
+    Multiply,
+    /// The scores are summed.
+    Sum,
+    /// The average of the scores.
+    Avg,
+    /// The highest score.
+    Max,
+    /// The lowest score.
+    Min,
+    /// The score of the first function.
+    First,
+}
+
+impl FunctionScoreMode {
+    fn combine(&self, scores: impl Iterator<Item = f64>) -> f64 {
+        let mut num_scores = 0;
+        let mut combined: Option<f64> = None;
+        for score in scores {
+            num_scores += 1;
+            combined = Some(match (self, combined) {
+                (_, None) => score,
+                (Self::Multiply, Some(acc)) => acc * score,
+                (Self::Sum | Self::Avg, Some(acc)) => acc + score,
+                (Self::Max, Some(acc)) => acc.max(score),
+                (Self::Min, Some(acc)) => acc.min(score),
+                (Self::First, Some(acc)) => acc,
+            });
+        }
+
+        match (self, combined) {
+            (Self::Avg, Some(sum)) => sum / num_scores as f64,
+            (_, combined) => combined.unwrap_or(1.0),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the combined function score is applied to the score of the query.
+pub enum BoostMode {
+    #[default]
+    /// The query score is multiplied by the function score.
+    Multiply,
+    /// The function score is added to the query score.
+    Sum,
+    /// The function score replaces the query score.
+    Replace,
+    /// The highest of the query score and function score.
+    Max,
+    /// The lowest of the query score and function score.
+    Min,
+    /// The average of the query score and function score.
+    Avg,
+}
+
+impl BoostMode {
+    fn combine(&self, query_score: f64, function_score: f64) -> f64 {
+        match self {
+            Self::Multiply => query_score * function_score,
+            Self::Sum => query_score + function_score,
+            Self::Replace => function_score,
+            Self::Max => query_score.max(function_score),
+            Self::Min => query_score.min(function_score),
+            Self::Avg => (query_score + function_score) / 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches the same documents as the inner query with their scores adjusted by a set
+/// of functions, i.e. to blend the popularity or freshness of a document into its
+/// relevance.
+pub struct FunctionScoreQuery<'a> {
+    #[serde(borrow)]
+    /// The query used to match and score documents.
+    pub query: Box<QueryKind<'a>>,
+    /// The functions computing a score for each matching document.
+    pub functions: Vec<ScoreFunction>,
+    #[serde(default)]
+    /// How the scores of the functions are combined.
+    pub score_mode: FunctionScoreMode,
+    #[serde(default)]
+    /// How the combined function score is applied to the score of the query.
+    pub boost_mode: BoostMode,
+}
+
+impl<'a> FunctionScoreQuery<'a> {
+    /// Compiles the inner query and functions into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        if self.functions.is_empty() {
+            return Err(QueryError::Invalid(
+                "A function_score query must specify at least one function".to_string(),
+            ));
+        }
+
+        let query = self.query.build(ctx)?;
+        apply_functions(ctx, query, self.functions, self.score_mode, self.boost_mode)
+    }
+}
+
+/// Adjusts the scores of an already compiled query with a set of functions.
+pub(crate) fn apply_functions(
+    ctx: &QueryContext,
+    query: Box<dyn Query>,
+    functions: Vec<ScoreFunction>,
+    score_mode: FunctionScoreMode,
+    boost_mode: BoostMode,
+) -> Result<Box<dyn Query>, QueryError> {
+    let functions = functions
+        .into_iter()
+        .map(|function| function.compile(ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Box::new(FunctionScoreWrapper {
+        query,
+        functions: Arc::new(functions),
+        score_mode,
+        boost_mode,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A function computing a score for each matching document.
+pub enum ScoreFunction {
+    /// Scores documents by the value of a numeric fast field.
+    FieldValueFactor(FieldValueFactor),
+    /// Scores documents by their distance from an origin with a gaussian curve.
+    Gauss(DecayFunction),
+    /// Scores documents by their distance from an origin with a linear curve.
+    Linear(DecayFunction),
+    /// Scores documents by their distance from an origin with an exponential curve.
+    Exp(DecayFunction),
+    /// Scores documents with a deterministic pseudo-random value.
+    RandomScore(RandomScore),
+}
+
+impl ScoreFunction {
+    fn compile(self, ctx: &QueryContext) -> Result<CompiledFunction, QueryError> {
+        match self {
+            Self::FieldValueFactor(function) => function.compile(ctx),
+            Self::Gauss(function) => function.compile(ctx, DecayCurve::Gauss),
+            Self::Linear(function) => function.compile(ctx, DecayCurve::Linear),
+            Self::Exp(function) => function.compile(ctx, DecayCurve::Exp),
+            Self::RandomScore(function) => function.compile(ctx),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A modifier applied to the value of a field by the `field_value_factor` function.
+pub enum FieldValueModifier {
+    #[default]
+    /// The value is used as-is.
+    None,
+    /// The base 10 logarithm of the value.
+    Log,
+    /// The base 10 logarithm of the value plus one.
+    Log1p,
+    /// The base 10 logarithm of the value plus two.
+    Log2p,
+    /// The natural logarithm of the value.
+    Ln,
+    /// The natural logarithm of the value plus one.
+    Ln1p,
+    /// The natural logarithm of the value plus two.
+    Ln2p,
+    /// The value squared.
+    Square,
+    /// The square root of the value.
+    Sqrt,
+    /// The reciprocal of the value.
+    Reciprocal,
+}
+
+impl FieldValueModifier {
+    fn apply(&self, value: f64) -> f64 {
+        match self {
+            Self::None => value,
+            Self::Log => value.log10(),
+            Self::Log1p => (value + 1.0).log10(),
+            Self::Log2p => (value + 2.0).log10(),
+            Self::Ln => value.ln(),
+            Self::Ln1p => value.ln_1p(),
+            Self::Ln2p => (value + 2.0).ln(),
+            Self::Square => value * value,
+            Self::Sqrt => value.sqrt(),
+            Self::Reciprocal => value.recip(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Scores documents by `modifier(factor * value)` of a numeric fast field, i.e. the
+/// popularity or rating of a document.
+///
+/// Results which are negative or not a number, i.e. the logarithm of `0`, score `0`.
+pub struct FieldValueFactor {
+    /// The `u64`, `i64`, `f64` or `datetime` fast field to read the value from.
+    pub field: String,
+    #[serde(default = "default_factor")]
+    /// The factor the value is multiplied by.
+    ///
+    /// Defaults to `1.0`.
+    pub factor: f64,
+    #[serde(default)]
+    /// The modifier applied to the value after multiplying it by the factor.
+    pub modifier: FieldValueModifier,
+    #[serde(default)]
+    /// The value used for documents without a value for the field.
+    ///
+    /// Documents without a value score `1.0` if this is not set.
+    pub missing: Option<f64>,
+}
+
+impl FieldValueFactor {
+    fn compile(self, ctx: &QueryContext) -> Result<CompiledFunction, QueryError> {
+        let (column, _) = resolve_fast_column(ctx, &self.field, "function_score")?;
+
+        if !self.factor.is_finite() {
+            return Err(QueryError::Invalid(format!(
+                "The factor of a field_value_factor function must be a finite number, got {}",
+                self.factor
+            )));
+        }
+
+        Ok(CompiledFunction::FieldValueFactor {
+            column,
+            factor: self.factor,
+            modifier: self.modifier,
+            missing: self.missing,
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Scores documents with a pseudo-random value between `0.0` and `1.0` derived from
+/// a hash of the `seed` and each document, i.e. to shuffle results per user or session
+/// so every listing of a marketplace gets a fair share of exposure.
+///
+/// The same seed always produces the same order, so results can be paginated.
+pub struct RandomScore {
+    #[serde(default)]
+    /// The seed of the random values, i.e. the ID of the user or session.
+    pub seed: u64,
+    #[serde(default)]
+    /// The numeric or `datetime` fast field the random value of each document is
+    /// derived from, i.e. the unique ID of each document.
+    ///
+    /// If this is not set the position of the document within its segment is used,
+    /// which is only stable until the segments of the index are merged.
+    pub field: Option<String>,
+}
+
+impl RandomScore {
+    fn compile(self, ctx: &QueryContext) -> Result<CompiledFunction, QueryError> {
+        let column = match self.field {
+            Some(field) => Some(resolve_fast_column(ctx, &field, "function_score")?.0),
+            None => None,
+        };
+
+        Ok(CompiledFunction::Random {
+            seed: self.seed,
+            column,
+        })
+    }
+}
+
+/// Maps the seed and a value onto a uniformly distributed float in `[0, 1)`.
+///
+/// This is the finalizer of SplitMix64, so values are stable across versions and
+/// platforms unlike the std hashers.
+fn random_unit(seed: u64, value: u64) -> f64 {
+    let mut z = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(value);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    // The top 53 bits fill the mantissa of the float exactly.
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+/// The origin a decay function measures the distance of each document from.
+pub enum DecayOrigin {
+    /// A number for numeric fields, or a unix timestamp for `datetime` fields.
+    Number(f64),
+    /// A geo point for decays on a pair of latitude and longitude fields.
+    Point { lat: f64, lon: f64 },
+    /// A datetime or date math expression for `datetime` fields, i.e. `now-1d`.
+    Text(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+/// The `scale` or `offset` of a decay function.
+pub enum DecayDistance {
+    /// A number for numeric fields, or a number of meters for geo points.
+    Number(f64),
+    /// A duration for `datetime` fields, i.e. `7d` or `12h`, or a distance for geo
+    /// points, i.e. `10km` or `500m`.
+    Text(String),
+}
+
+#[derive(Debug, Deserialize)]
+/// Scores documents by how far the value of a field is from an origin, documents
+/// at the origin score `1.0` and documents `scale` away from it score `decay`.
+///
+/// The value is either read from a numeric or `datetime` fast field via `field`, or
+/// from a pair of `f64` fast fields holding a geo point via `lat_field` and `lon_field`.
+pub struct DecayFunction {
+    #[serde(default)]
+    /// The `u64`, `i64`, `f64` or `datetime` fast field to read the value from.
+    pub field: Option<String>,
+    #[serde(default)]
+    /// The `f64` fast field holding the latitude of a geo point.
+    pub lat_field: Option<String>,
+    #[serde(default)]
+    /// The `f64` fast field holding the longitude of a geo point.
+    pub lon_field: Option<String>,
+    #[serde(default)]
+    /// The origin distances are measured from.
+    ///
+    /// Defaults to `now` for `datetime` fields and is required otherwise.
+    pub origin: Option<DecayOrigin>,
+    /// The distance from the origin (plus the offset) at which documents score `decay`.
+    pub scale: DecayDistance,
+    #[serde(default)]
+    /// The distance from the origin within which documents are not decayed.
+    pub offset: Option<DecayDistance>,
+    #[serde(default = "default_decay")]
+    /// The score of documents `scale` away from the origin, between `0` and `1`.
+    ///
+    /// Defaults to `0.5`.
+    pub decay: f64,
+}
+
+impl DecayFunction {
+    fn compile(
+        self,
+        ctx: &QueryContext,
+        curve: DecayCurve,
+    ) -> Result<CompiledFunction, QueryError> {
+        let invalid =
+            |reason: String| QueryError::Invalid(format!("{curve:?} decay {reason}"));
+
+        if !(self.decay > 0.0 && self.decay < 1.0) {
+            return Err(invalid(format!(
+                "must have a decay between 0 and 1 exclusive, got {}",
+                self.decay
+            )));
+        }
+
+        let (target, unit) = match (self.field, self.lat_field, self.lon_field) {
+            (Some(field), None, None) => {
+                let (column, entry) =
+                    resolve_fast_column(ctx, &field, "function_score")?;
+                let is_date = column.is_date();
+                let origin = match (self.origin, is_date) {
+                    (None, true) => ctx.now().as_micros() as f64,
+                    (Some(origin), true) => resolve_date_origin(ctx, entry, origin)?,
+                    (Some(DecayOrigin::Number(origin)), false) => origin,
+                    _ => {
+                        return Err(invalid(format!(
+                            "on {field:?} requires a number origin"
+                        )))
+                    },
+                };
+                let unit = if is_date {
+                    DistanceUnit::Duration
+                } else {
+                    DistanceUnit::Number
+                };
+                (DecayTarget::Value { column, origin }, unit)
+            },
+            (None, Some(lat), Some(lon)) => {
+                let Some(DecayOrigin::Point {
+                    lat: origin_lat,
+                    lon: origin_lon,
+                }) = self.origin
+                else {
+                    return Err(invalid(
+                        "on a geo point requires a `{\"lat\": .., \"lon\": ..}` origin"
+                            .to_string(),
+                    ));
+                };
+                let target = DecayTarget::Geo {
+                    lat: resolve_geo_column(ctx, &lat)?,
+                    lon: resolve_geo_column(ctx, &lon)?,
+                    origin: (origin_lat, origin_lon),
+                };
+                (target, DistanceUnit::Meters)
+            },
+            _ => {
+                return Err(invalid(
+                    "must set either `field` or both `lat_field` and `lon_field`"
+                        .to_string(),
+                ))
+            },
+        };
+
+        let scale = unit.resolve(&self.scale).map_err(invalid)?;
+        let offset = match self.offset.as_ref() {
+            Some(offset) => unit.resolve(offset).map_err(invalid)?,
+            None => 0.0,
+        };
+        if scale <= 0.0 {
+            return Err(invalid(format!("must have a positive scale, got {scale}")));
+        }
+        if offset < 0.0 {
+            return Err(invalid(format!(
+                "must have a non-negative offset, got {offset}"
+            )));
+        }
+
+        Ok(CompiledFunction::Decay {
+            target,
+            params: DecayParams {
+                curve,
+                scale,
+                offset,
+                decay: self.decay,
+            },
+        })
+    }
+}
+
+/// Resolves the origin of a decay on a `datetime` field to a timestamp in microseconds.
+pub(crate) fn resolve_date_origin(
+    ctx: &QueryContext,
+    entry: &FieldEntry,
+    origin: DecayOrigin,
+) -> Result<f64, QueryError> {
+    let value = match origin {
+        DecayOrigin::Text(expr) if is_date_math(&expr) => {
+            let dt = resolve_date_math(ctx, &expr, false)
+                .map_err(|e| QueryError::invalid_value(entry.name(), e))?;
+            return Ok(dt.as_micros() as f64);
+        },
+        DecayOrigin::Text(text) => {
+            let dt = ctx
+                .parse_datetime(&text)
+                .map_err(|e| QueryError::invalid_value(entry.name(), e.to_string()))?;
+            return Ok(dt.as_micros() as f64);
+        },
+        DecayOrigin::Number(timestamp) => Value::I64(timestamp as i64),
+        DecayOrigin::Point { .. } => {
+            return Err(QueryError::invalid_value(
+                entry.name(),
+                "a geo point cannot be used as the origin of a `datetime` field",
+            ))
+        },
+    };
+
+    match ctx.cast_value(entry, value)? {
+        Value::DateTime(dt) => Ok(dt.as_micros() as f64),
+        _ => unreachable!(),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The unit the `scale` and `offset` of a decay are resolved in.
+pub(crate) enum DistanceUnit {
+    /// Plain numbers for numeric fields.
+    Number,
+    /// Durations in microseconds for `datetime` fields.
+    Duration,
+    /// Distances in meters for geo points.
+    Meters,
+}
+
+impl DistanceUnit {
+    pub(crate) fn resolve(&self, distance: &DecayDistance) -> Result<f64, String> {
+        let text = match (self, distance) {
+            (Self::Number | Self::Meters, DecayDistance::Number(value)) => {
+                return Ok(*value)
+            },
+            (Self::Number, DecayDistance::Text(text)) => {
+                return Err(format!("expects a number, got {text:?}"))
+            },
+            (Self::Duration, DecayDistance::Number(value)) => {
+                return Err(format!("expects a duration like `7d`, got {value}"))
+            },
+            (_, DecayDistance::Text(text)) => text.trim(),
+        };
+
+        let split = text
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(text.len());
+        let (amount, unit) = text.split_at(split);
+        let amount: f64 = amount
+            .parse()
+            .map_err(|_| format!("has an invalid distance {text:?}"))?;
+
+        let multiplier = match (self, unit.trim()) {
+            (Self::Duration, "ms") => 1_000.0,
+            (Self::Duration, "s") => 1_000_000.0,
+            (Self::Duration, "m") => 60.0 * 1_000_000.0,
+            (Self::Duration, "h") => 3_600.0 * 1_000_000.0,
+            (Self::Duration, "d") => 86_400.0 * 1_000_000.0,
+            (Self::Duration, "w") => 7.0 * 86_400.0 * 1_000_000.0,
+            (Self::Meters, "" | "m") => 1.0,
+            (Self::Meters, "km") => 1_000.0,
+            (Self::Meters, "mi") => 1_609.344,
+            (Self::Duration, _) => {
+                return Err(format!(
+                    "has an unknown duration unit in {text:?}, expected one of `ms`, `s`, `m`, `h`, `d` or `w`"
+                ))
+            },
+            _ => {
+                return Err(format!(
+                    "has an unknown distance unit in {text:?}, expected one of `m`, `km` or `mi`"
+                ))
+            },
+        };
+
+        Ok(amount * multiplier)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The shape of the curve a decay function follows.
+enum DecayCurve {
+    Gauss,
+    Linear,
+    Exp,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct DecayParams {
+    curve: DecayCurve,
+    scale: f64,
+    offset: f64,
+    decay: f64,
+}
+
+impl DecayParams {
+    /// Computes the score of a document the given distance from the origin.
+    fn score(&self, distance: f64) -> f64 {
+        let distance = (distance.abs() - self.offset).max(0.0);
+        match self.curve {
+            DecayCurve::Gauss => {
+                let variance = -self.scale.powi(2) / (2.0 * self.decay.ln());
+                (-distance.powi(2) / (2.0 * variance)).exp()
+            },
+            DecayCurve::Linear => {
+                let width = self.scale / (1.0 - self.decay);
+                ((width - distance) / width).max(0.0)
+            },
+            DecayCurve::Exp => (self.decay.ln() / self.scale * distance).exp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The value a decay function measures the distance of from its origin.
+enum DecayTarget {
+    Value {
+        column: FastColumn,
+        origin: f64,
+    },
+    Geo {
+        lat: FastColumn,
+        lon: FastColumn,
+        origin: (f64, f64),
+    },
+}
+
+/// Resolves a numeric or `datetime` fast field used by a score function.
+pub(crate) fn resolve_fast_column<'a>(
+    ctx: &'a QueryContext,
+    name: &str,
+    query: &'static str,
+) -> Result<(FastColumn, &'a FieldEntry), QueryError> {
+    let (_, entry) = ctx.resolve_field(name)?;
+
+    let column = FastColumn::new(entry)
+        .filter(|column| column.is_numeric() || column.is_date())
+        .ok_or_else(|| {
+            QueryError::unsupported(
+                name,
+                query,
+                "only numeric and datetime fields can be used",
+            )
+        })?;
+
+    if !entry.is_fast() {
+        return Err(QueryError::unsupported(
+            name,
+            query,
+            "the field is not a fast field",
+        ));
+    }
+
+    Ok((column, entry))
+}
+
+/// Resolves an `f64` fast field holding the latitude or longitude of a geo point.
+fn resolve_geo_column(ctx: &QueryContext, name: &str) -> Result<FastColumn, QueryError> {
+    let (column, _) = resolve_fast_column(ctx, name, "function_score")?;
+    if column.kind() != ColumnType::F64 {
+        return Err(QueryError::unsupported(
+            name,
+            "function_score",
+            "geo points must be stored as `f64` fields",
+        ));
+    }
+    Ok(column)
+}
+
+/// The great-circle distance in meters between two points.
+fn haversine_distance((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let delta_lat = lat2 - lat1;
+    let delta_lon = (lon2 - lon1).to_radians();
+
+    let a = (delta_lat / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * (delta_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+}
+
+#[derive(Debug, Clone)]
+/// A compiled score function.
+enum CompiledFunction {
+    FieldValueFactor {
+        column: FastColumn,
+        factor: f64,
+        modifier: FieldValueModifier,
+        missing: Option<f64>,
+    },
+    Decay {
+        target: DecayTarget,
+        params: DecayParams,
+    },
+    Random {
+        seed: u64,
+        column: Option<FastColumn>,
+    },
+}
+
+impl CompiledFunction {
+    /// Opens the fast field columns used by the function for a segment.
+    fn for_segment(&self, reader: &SegmentReader) -> SegmentFunction {
+        match self {
+            Self::FieldValueFactor {
+                column,
+                factor,
+                modifier,
+                missing,
+            } => SegmentFunction::FieldValueFactor {
+                column: column.open(reader),
+                factor: *factor,
+                modifier: *modifier,
+                missing: *missing,
+            },
+            Self::Decay {
+                target: DecayTarget::Value { column, origin },
+                params,
+            } => SegmentFunction::Decay {
+                column: column.open(reader),
+                origin: *origin,
+                params: *params,
+            },
+            Self::Decay {
+                target: DecayTarget::Geo { lat, lon, origin },
+                params,
+            } => SegmentFunction::GeoDecay {
+                lat: lat.open(reader),
+                lon: lon.open(reader),
+                origin: *origin,
+                params: *params,
+            },
+            Self::Random { seed, column: None } => {
+                // Salt the seed with the segment so documents at the same position
+                // within different segments score differently.
+                let uuid = reader.segment_id().uuid_string();
+                let uuid = u128::from_str_radix(&uuid, 16).unwrap_or(0);
+                SegmentFunction::RandomDoc {
+                    seed: *seed ^ (uuid as u64) ^ ((uuid >> 64) as u64),
+                }
+            },
+            Self::Random {
+                seed,
+                column: Some(column),
+            } => SegmentFunction::RandomValue {
+                seed: *seed,
+                column: column.open(reader),
+            },
+        }
+    }
+}
+
+/// A score function bound to the columns of a single segment.
+enum SegmentFunction {
+    FieldValueFactor {
+        column: Option<SegmentColumn>,
+        factor: f64,
+        modifier: FieldValueModifier,
+        missing: Option<f64>,
+    },
+    Decay {
+        column: Option<SegmentColumn>,
+        origin: f64,
+        params: DecayParams,
+    },
+    GeoDecay {
+        lat: Option<SegmentColumn>,
+        lon: Option<SegmentColumn>,
+        origin: (f64, f64),
+        params: DecayParams,
+    },
+    RandomDoc {
+        seed: u64,
+    },
+    RandomValue {
+        seed: u64,
+        column: Option<SegmentColumn>,
+    },
+}
+
+impl SegmentFunction {
+    /// Computes the score of the function for the given document.
+    ///
+    /// Documents without a value for the fields of the function score `1.0`.
+    fn score(&self, doc: DocId) -> f64 {
+        let first = |column: &Option<SegmentColumn>| column.as_ref()?.first(doc);
+
+        match self {
+            Self::FieldValueFactor {
+                column,
+                factor,
+                modifier,
+                missing,
+            } => match first(column).or(*missing) {
+                Some(value) => {
+                    let score = modifier.apply(factor * value);
+                    if score.is_finite() && score > 0.0 {
+                        score
+                    } else {
+                        0.0
+                    }
+                },
+                None => 1.0,
+            },
+            Self::Decay {
+                column,
+                origin,
+                params,
+            } => match first(column) {
+                Some(value) => params.score(value - origin),
+                None => 1.0,
+            },
+            Self::GeoDecay {
+                lat,
+                lon,
+                origin,
+                params,
+            } => match first(lat).zip(first(lon)) {
+                Some(point) => params.score(haversine_distance(*origin, point)),
+                None => 1.0,
+            },
+            Self::RandomDoc { seed } => random_unit(*seed, doc as u64),
+            Self::RandomValue { seed, column } => match first(column) {
+                Some(value) => random_unit(*seed, value.to_bits()),
+                None => 1.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Adjusts the scores of the inner query with a set of compiled score functions.
+struct FunctionScoreWrapper {
+    query: Box<dyn Query>,
+    functions: Arc<Vec<CompiledFunction>>,
+    score_mode: FunctionScoreMode,
+    boost_mode: BoostMode,
+}
+
+impl Clone for FunctionScoreWrapper {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.box_clone(),
+            functions: self.functions.clone(),
+            score_mode: self.score_mode,
+            boost_mode: self.boost_mode,
+        }
+    }
+}
+
+impl Query for FunctionScoreWrapper {
+    fn weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(FunctionScoreWeight {
+            weight: self.query.weight(enable_scoring)?,
+            functions: self.functions.clone(),
+            score_mode: self.score_mode,
+            boost_mode: self.boost_mode,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.query.query_terms(visitor);
+    }
+}
+
+struct FunctionScoreWeight {
+    weight: Box<dyn Weight>,
+    functions: Arc<Vec<CompiledFunction>>,
+    score_mode: FunctionScoreMode,
+    boost_mode: BoostMode,
+}
+
+impl Weight for FunctionScoreWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        Ok(Box::new(FunctionScoreScorer {
+            scorer: self.weight.scorer(reader, boost)?,
+            functions: self
+                .functions
+                .iter()
+                .map(|function| function.for_segment(reader))
+                .collect(),
+            score_mode: self.score_mode,
+            boost_mode: self.boost_mode,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        let mut explanation = Explanation::new(
+            format!(
+                "FunctionScore(score_mode={:?}, boost_mode={:?})",
+                self.score_mode, self.boost_mode
+            ),
+            scorer.score(),
+        );
+        explanation.add_detail(self.weight.explain(reader, doc)?);
+        for (i, function) in self.functions.iter().enumerate() {
+            let score = function.for_segment(reader).score(doc);
+            explanation.add_const(format!("function #{i}"), score as Score);
+        }
+
+        Ok(explanation)
+    }
+}
+
+struct FunctionScoreScorer {
+    scorer: Box<dyn Scorer>,
+    functions: Vec<SegmentFunction>,
+    score_mode: FunctionScoreMode,
+    boost_mode: BoostMode,
+}
+
+impl DocSet for FunctionScoreScorer {
+    fn advance(&mut self) -> DocId {
+        self.scorer.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.scorer.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+}
+
+impl Scorer for FunctionScoreScorer {
+    fn score(&mut self) -> Score {
+        let doc = self.scorer.doc();
+        let query_score = self.scorer.score() as f64;
+        let function_score = self
+            .score_mode
+            .combine(self.functions.iter().map(|function| function.score(doc)));
+
+        let score = self.boost_mode.combine(query_score, function_score);
+        if score.is_finite() {
+            score.max(0.0) as Score
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{SchemaBuilder, FAST, INDEXED, TEXT};
+    use tantivy::{doc, DateTime as TantivyDateTime, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_decay_curves() {
+        for curve in [DecayCurve::Gauss, DecayCurve::Linear, DecayCurve::Exp] {
+            let params = DecayParams {
+                curve,
+                scale: 10.0,
+                offset: 2.0,
+                decay: 0.5,
+            };
+            assert_eq!(params.score(0.0), 1.0);
+            assert_eq!(params.score(-2.0), 1.0);
+            assert!((params.score(12.0) - 0.5).abs() < 1e-9, "{curve:?}");
+            assert!(params.score(-20.0) < 0.5);
+        }
+
+        let params = DecayParams {
+            curve: DecayCurve::Linear,
+            scale: 10.0,
+            offset: 0.0,
+            decay: 0.5,
+        };
+        assert_eq!(params.score(25.0), 0.0);
+    }
+
+    #[test]
+    fn test_distance_units() {
+        let number = |v: f64| DecayDistance::Number(v);
+        let text = |v: &str| DecayDistance::Text(v.to_string());
+
+        assert_eq!(DistanceUnit::Number.resolve(&number(5.0)), Ok(5.0));
+        assert!(DistanceUnit::Number.resolve(&text("5d")).is_err());
+        assert_eq!(
+            DistanceUnit::Duration.resolve(&text("2d")),
+            Ok(2.0 * 86_400.0 * 1_000_000.0)
+        );
+        assert!(DistanceUnit::Duration.resolve(&number(5.0)).is_err());
+        assert!(DistanceUnit::Duration.resolve(&text("5y")).is_err());
+        assert_eq!(DistanceUnit::Meters.resolve(&text("1.5km")), Ok(1_500.0));
+        assert_eq!(DistanceUnit::Meters.resolve(&number(200.0)), Ok(200.0));
+
+        // London to Paris is roughly 344km.
+        let distance = haversine_distance((51.5074, -0.1278), (48.8566, 2.3522));
+        assert!((distance - 343_500.0).abs() < 1_000.0);
+    }
+
+    #[test]
+    fn test_function_score_query() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let popularity = schema.add_u64_field("popularity", FAST | INDEXED);
+        let published = schema.add_date_field("published", FAST | INDEXED);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let day = 86_400;
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(
+                title => "rust",
+                popularity => 1u64,
+                published => TantivyDateTime::from_timestamp_secs(30 * day),
+            ))
+            .unwrap();
+        writer
+            .add_document(doc!(
+                title => "rust",
+                popularity => 100u64,
+                published => TantivyDateTime::from_timestamp_secs(0),
+            ))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let top_doc = |json: &str| {
+            let query: QueryKind = serde_json::from_str(json).unwrap();
+            let query = query.build(&ctx).unwrap();
+            searcher.search(&query, &TopDocs::with_limit(1)).unwrap()[0].1
+        };
+
+        let doc = top_doc(
+            r#"{"function_score": {
+                "query": {"query_string": {"query": "rust"}},
+                "functions": [{"field_value_factor": {"field": "popularity", "modifier": "log1p"}}]
+            }}"#,
+        );
+        assert_eq!(doc.doc_id, 1);
+
+        let doc = top_doc(
+            r#"{"function_score": {
+                "query": {"query_string": {"query": "rust"}},
+                "functions": [{"gauss": {
+                    "field": "published",
+                    "origin": "1970-01-31T00:00:00Z",
+                    "scale": "7d"
+                }}],
+                "boost_mode": "replace"
+            }}"#,
+        );
+        assert_eq!(doc.doc_id, 0);
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{"function_score": {
+                "query": {"query_string": {"query": "rust"}},
+                "functions": [{"exp": {"field": "title", "origin": 0, "scale": 1}}]
+            }}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{"function_score": {
+                "query": {"query_string": {"query": "rust"}},
+                "functions": [{"linear": {"field": "popularity", "scale": 1, "decay": 1.5}}]
+            }}"#,
+        )
+        .unwrap();
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_random_score() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let id = schema.add_u64_field("id", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for i in 0..20u64 {
+            writer.add_document(doc!(title => "rust", id => i)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let shuffle = |function: &str| {
+            let json = format!(
+                r#"{{"function_score": {{
+                    "query": {{"query_string": {{"query": "rust"}}}},
+                    "functions": [{{"random_score": {function}}}],
+                    "boost_mode": "replace"
+                }}}}"#
+            );
+            let query: QueryKind = serde_json::from_str(&json).unwrap();
+            let query = query.build(&ctx).unwrap();
+            let hits = searcher.search(&query, &TopDocs::with_limit(20)).unwrap();
+            assert!(hits.iter().all(|(score, _)| (0.0..1.0).contains(score)));
+            hits.into_iter()
+                .map(|(_, address)| address.doc_id)
+                .collect::<Vec<_>>()
+        };
+
+        let order = shuffle(r#"{"seed": 42, "field": "id"}"#);
+        assert_eq!(order, shuffle(r#"{"seed": 42, "field": "id"}"#));
+        assert_ne!(order, shuffle(r#"{"seed": 7, "field": "id"}"#));
+        assert_ne!(order, (0..20).collect::<Vec<_>>());
+
+        let order = shuffle(r#"{"seed": 42}"#);
+        assert_eq!(order, shuffle(r#"{"seed": 42}"#));
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{"function_score": {
+                "query": {"query_string": {"query": "rust"}},
+                "functions": [{"random_score": {"seed": 1, "field": "title"}}]
+            }}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+    }
+}
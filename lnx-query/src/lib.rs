@@ -0,0 +1,170 @@
+mod aggregations;
+mod bm25;
+mod cidr;
+mod column;
+mod context;
+mod curation;
+mod date_math;
+mod datetime_output;
+mod distance_feature;
+mod document_boost;
+mod error;
+mod exists;
+mod experiment;
+mod explain;
+mod facet;
+mod freshness;
+mod function_score;
+mod ltr;
+mod min_score;
+mod min_should_match;
+mod multi_index;
+mod nested;
+mod percolate;
+mod query;
+mod query_string;
+mod range;
+mod regex;
+mod rescore;
+mod runtime_fields;
+mod scoring;
+mod script_score;
+mod search;
+mod search_defaults;
+mod similar;
+mod span;
+mod synonyms;
+mod template;
+mod term;
+mod timeout;
+mod validate;
+
+pub use self::aggregations::{
+    aggregation_collector,
+    Aggregation,
+    AggregationCollector,
+    AggregationKey,
+    AggregationKind,
+    AggregationResult,
+    AggregationResults,
+    AggregationSegmentCollector,
+    BucketOrder,
+    CardinalityAggregation,
+    CardinalityResult,
+    DateHistogramAggregation,
+    DateHistogramBounds,
+    DateHistogramBucket,
+    DateHistogramResult,
+    HistogramAggregation,
+    HistogramBounds,
+    HistogramBucket,
+    HistogramResult,
+    IntermediateAggregation,
+    MetricAggregation,
+    MetricResult,
+    PercentileValue,
+    PercentilesAggregation,
+    PercentilesResult,
+    StatsResult,
+    TermsAggregation,
+    TermsBucket,
+    TermsResult,
+    MAX_AGGREGATION_BUCKETS,
+};
+pub use self::cidr::CidrQuery;
+pub use self::context::{QueryContext, DEFAULT_REGEX_SIZE_LIMIT};
+pub use self::curation::{normalize_curation_query, Curation, CurationStore, Curations};
+pub use self::datetime_output::{DateTimeOutput, DateTimeRenderer};
+pub use self::distance_feature::DistanceFeatureQuery;
+pub use self::error::QueryError;
+pub use self::exists::ExistsQuery;
+pub use self::experiment::{
+    AssignmentSource,
+    ExperimentAssignment,
+    ExperimentStore,
+    Experiments,
+    RankingVariant,
+};
+pub use self::explain::{explain_document, ExplainResponse};
+pub use self::facet::FacetQuery;
+pub use self::freshness::Freshness;
+pub use self::function_score::{
+    BoostMode,
+    DecayDistance,
+    DecayFunction,
+    DecayOrigin,
+    FieldValueFactor,
+    FieldValueModifier,
+    FunctionScoreMode,
+    FunctionScoreQuery,
+    RandomScore,
+    ScoreFunction,
+};
+pub use self::ltr::{
+    LtrFeature,
+    LtrFeatureKind,
+    LtrModel,
+    LtrModelDefinition,
+    LtrModelFormat,
+    LtrModelStore,
+    LtrModels,
+    LtrRescore,
+};
+pub use self::min_score::{MinScoreCollector, MinScoreSegmentCollector};
+pub use self::min_should_match::{MinShouldMatchQuery, MinimumShouldMatch};
+pub use self::multi_index::{merge_index_hits, resolve_index_patterns, IndexHit};
+pub use self::nested::{NestedQuery, ScoreMode};
+pub use self::percolate::{Percolator, PercolatorStore};
+pub use self::query::QueryKind;
+pub use self::query_string::{
+    MultiFieldMode,
+    Operator,
+    QueryStringQuery,
+    MAX_FUZZINESS,
+};
+pub use self::range::RangeQuery;
+pub use self::regex::{RegexQuery, WildcardQuery};
+pub use self::rescore::{
+    RescoreMode,
+    RescoreRequest,
+    Rescorer,
+    DEFAULT_RESCORE_WINDOW,
+    MAX_RESCORE_WINDOW,
+};
+pub use self::runtime_fields::{
+    MathFunction,
+    RuntimeExpression,
+    RuntimeField,
+    RuntimeFieldStats,
+    RuntimeSegmentEvaluator,
+    RuntimeStatsCollector,
+    RuntimeStatsSegmentCollector,
+    RuntimeValue,
+    MAX_EXPRESSION_LENGTH,
+};
+pub use self::scoring::{BoostQuery, BoostingQuery, ConstantScoreQuery};
+pub use self::script_score::ScriptScoreQuery;
+pub use self::search::SearchRequest;
+pub use self::search_defaults::{
+    SearchDefaults,
+    SearchDefaultsStore,
+    DEFAULT_SEARCH_LIMIT,
+    MAX_SEARCH_LIMIT,
+};
+pub use self::similar::SimilarDocumentsRequest;
+pub use self::span::{SpanFirstQuery, SpanNearQuery};
+pub use self::synonyms::expand_query_synonyms;
+pub use self::template::{SearchTemplate, SearchTemplateStore};
+pub use self::term::{TermQuery, TermsQuery, MAX_TERMS_QUERY_VALUES};
+pub use self::timeout::{
+    SegmentProgress,
+    TimeoutCollector,
+    TimeoutFruit,
+    TimeoutSegmentCollector,
+};
+pub use self::validate::{
+    validate_query,
+    ErrorPosition,
+    ValidationError,
+    ValidationReport,
+};
@@ -0,0 +1,728 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use lnx_metastore::Metastore;
+use serde::Deserialize;
+use tantivy::query::Query;
+use tantivy::{DocAddress, Score, Searcher};
+
+use crate::column::FastColumn;
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::function_score::resolve_fast_column;
+use crate::query_string::QueryStringQuery;
+use crate::rescore::query_scores;
+
+/// The metastore database the learning to rank models of each index are stored in.
+const LTR_MODELS_DATABASE: &str = "lnx_ltr_models";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+/// How the value of a feature is computed for each document.
+pub enum LtrFeatureKind {
+    /// The BM25 score of the rescore keywords matched against a single field,
+    /// `0.0` if the field does not match.
+    Bm25 {
+        /// The text field the keywords are matched against.
+        field: String,
+    },
+    /// The value of a numeric or `datetime` fast field.
+    FastField {
+        /// The fast field to read the value from.
+        field: String,
+        #[serde(default)]
+        /// The value used for documents without a value, otherwise the feature is
+        /// treated as missing by the model.
+        missing: Option<f64>,
+    },
+    /// The score of the document from the first phase of the search.
+    QueryScore,
+    /// The number of terms within the rescore keywords.
+    QueryLength,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// A named input of a learning to rank model.
+pub struct LtrFeature {
+    /// The name of the feature, trees can refer to features either by their name
+    /// or by their position, i.e. `f0`.
+    pub name: String,
+    #[serde(flatten)]
+    /// How the value of the feature is computed.
+    pub kind: LtrFeatureKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// The format of an uploaded tree model.
+pub enum LtrModelFormat {
+    /// A JSON dump of an XGBoost model, i.e. `booster.get_dump(dump_format="json")`,
+    /// as an array of trees.
+    Xgboost,
+    /// A LightGBM model dumped as JSON, i.e. `booster.dump_model()`.
+    Lightgbm,
+}
+
+#[derive(Debug, Deserialize)]
+/// The definition of a learning to rank model uploaded for an index.
+pub struct LtrModelDefinition {
+    /// The features the model was trained on, in order.
+    pub features: Vec<LtrFeature>,
+    /// The format of the model.
+    pub format: LtrModelFormat,
+    /// The model itself in the given format.
+    pub model: serde_json::Value,
+    #[serde(default)]
+    /// A constant added to the prediction of every document.
+    pub base_score: f64,
+}
+
+#[derive(Debug)]
+/// A compiled gradient boosted tree model, whose prediction is the sum of its trees.
+pub struct LtrModel {
+    features: Vec<LtrFeature>,
+    trees: Vec<TreeNode>,
+    base_score: f64,
+}
+
+impl LtrModel {
+    /// Parses and validates a model definition.
+    pub fn from_definition(definition: LtrModelDefinition) -> Result<Self, QueryError> {
+        let invalid =
+            |reason: String| QueryError::Invalid(format!("Invalid model: {reason}"));
+
+        if definition.features.is_empty() {
+            return Err(invalid("the model must define at least one feature".into()));
+        }
+
+        let mut names = HashSet::new();
+        for feature in definition.features.iter() {
+            if !names.insert(feature.name.as_str()) {
+                return Err(invalid(format!("duplicate feature {:?}", feature.name)));
+            }
+        }
+
+        let features = &definition.features;
+        let trees = match definition.format {
+            LtrModelFormat::Xgboost => {
+                let trees: Vec<XgboostNode> = serde_json::from_value(definition.model)
+                    .map_err(|e| invalid(e.to_string()))?;
+                trees
+                    .into_iter()
+                    .map(|tree| tree.compile(features))
+                    .collect::<Result<Vec<_>, _>>()
+            },
+            LtrModelFormat::Lightgbm => {
+                let model: LightgbmModel = serde_json::from_value(definition.model)
+                    .map_err(|e| invalid(e.to_string()))?;
+                model
+                    .tree_info
+                    .into_iter()
+                    .map(|tree| tree.tree_structure.compile(features))
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        }
+        .map_err(invalid)?;
+
+        if trees.is_empty() {
+            return Err(invalid("the model must contain at least one tree".into()));
+        }
+
+        Ok(Self {
+            features: definition.features,
+            trees,
+            base_score: definition.base_score,
+        })
+    }
+
+    #[inline]
+    /// The features the model was trained on, in order.
+    pub fn features(&self) -> &[LtrFeature] {
+        &self.features
+    }
+
+    /// Predicts the score of a document from its feature values.
+    ///
+    /// Missing values are represented as `NaN` and follow each split's default branch.
+    pub fn predict(&self, values: &[f64]) -> f64 {
+        self.base_score
+            + self
+                .trees
+                .iter()
+                .map(|tree| tree.evaluate(values))
+                .sum::<f64>()
+    }
+}
+
+#[derive(Debug)]
+/// A node of a compiled decision tree.
+enum TreeNode {
+    Leaf(f64),
+    Split {
+        feature: usize,
+        threshold: f64,
+        /// If values equal to the threshold take the left branch.
+        inclusive: bool,
+        /// If missing values take the left branch.
+        default_left: bool,
+        left: Box<TreeNode>,
+        right: Box<TreeNode>,
+    },
+}
+
+impl TreeNode {
+    fn evaluate(&self, values: &[f64]) -> f64 {
+        let mut node = self;
+        loop {
+            match node {
+                Self::Leaf(value) => return *value,
+                Self::Split {
+                    feature,
+                    threshold,
+                    inclusive,
+                    default_left,
+                    left,
+                    right,
+                } => {
+                    let value = values[*feature];
+                    let go_left = if value.is_nan() {
+                        *default_left
+                    } else if *inclusive {
+                        value <= *threshold
+                    } else {
+                        value < *threshold
+                    };
+                    node = if go_left { left } else { right };
+                },
+            }
+        }
+    }
+}
+
+/// Resolves the feature a split refers to, either by name or as `f{position}`.
+fn resolve_feature(features: &[LtrFeature], split: &str) -> Result<usize, String> {
+    if let Some(pos) = features.iter().position(|f| f.name == split) {
+        return Ok(pos);
+    }
+
+    split
+        .strip_prefix('f')
+        .and_then(|pos| pos.parse::<usize>().ok())
+        .filter(|pos| *pos < features.len())
+        .ok_or_else(|| format!("unknown feature {split:?}"))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum XgboostNode {
+    Split {
+        nodeid: u32,
+        split: String,
+        split_condition: f64,
+        yes: u32,
+        no: u32,
+        #[serde(default)]
+        missing: Option<u32>,
+        children: Vec<XgboostNode>,
+    },
+    Leaf {
+        nodeid: u32,
+        leaf: f64,
+    },
+}
+
+impl XgboostNode {
+    fn node_id(&self) -> u32 {
+        match self {
+            Self::Split { nodeid, .. } | Self::Leaf { nodeid, .. } => *nodeid,
+        }
+    }
+
+    /// XGBoost takes the `yes` branch if the value is less than the split condition.
+    fn compile(self, features: &[LtrFeature]) -> Result<TreeNode, String> {
+        let (split, split_condition, yes, no, missing, children) = match self {
+            Self::Leaf { leaf, .. } => return Ok(TreeNode::Leaf(leaf)),
+            Self::Split {
+                split,
+                split_condition,
+                yes,
+                no,
+                missing,
+                children,
+                ..
+            } => (split, split_condition, yes, no, missing, children),
+        };
+
+        let mut left = None;
+        let mut right = None;
+        for child in children {
+            match child.node_id() {
+                id if id == yes => left = Some(child.compile(features)?),
+                id if id == no => right = Some(child.compile(features)?),
+                id => return Err(format!("unexpected child node {id}")),
+            }
+        }
+        let (Some(left), Some(right)) = (left, right) else {
+            return Err(format!("the split on {split:?} is missing a child node"));
+        };
+
+        Ok(TreeNode::Split {
+            feature: resolve_feature(features, &split)?,
+            threshold: split_condition,
+            inclusive: false,
+            default_left: missing.map_or(true, |missing| missing == yes),
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct LightgbmModel {
+    tree_info: Vec<LightgbmTree>,
+}
+
+#[derive(Deserialize)]
+struct LightgbmTree {
+    tree_structure: LightgbmNode,
+}
+
+fn default_decision_type() -> String {
+    "<=".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LightgbmNode {
+    Split {
+        split_feature: usize,
+        threshold: f64,
+        #[serde(default = "default_decision_type")]
+        decision_type: String,
+        #[serde(default)]
+        default_left: bool,
+        left_child: Box<LightgbmNode>,
+        right_child: Box<LightgbmNode>,
+    },
+    Leaf {
+        leaf_value: f64,
+    },
+}
+
+impl LightgbmNode {
+    /// LightGBM takes the left branch if the value is less than or equal to the threshold.
+    fn compile(self, features: &[LtrFeature]) -> Result<TreeNode, String> {
+        match self {
+            Self::Leaf { leaf_value } => Ok(TreeNode::Leaf(leaf_value)),
+            Self::Split {
+                split_feature,
+                threshold,
+                decision_type,
+                default_left,
+                left_child,
+                right_child,
+            } => {
+                if decision_type != "<=" {
+                    return Err(format!(
+                        "unsupported decision type {decision_type:?}, only numerical splits are supported"
+                    ));
+                }
+                if split_feature >= features.len() {
+                    return Err(format!("unknown feature f{split_feature}"));
+                }
+
+                Ok(TreeNode::Split {
+                    feature: split_feature,
+                    threshold,
+                    inclusive: true,
+                    default_left,
+                    left: Box::new(left_child.compile(features)?),
+                    right: Box::new(right_child.compile(features)?),
+                })
+            },
+        }
+    }
+}
+
+/// A registered model, kept alongside its source so it can be persisted.
+struct RegisteredModel {
+    source: String,
+    model: Arc<LtrModel>,
+}
+
+#[derive(Default)]
+/// The learning to rank models of an index, keyed by their name.
+///
+/// Models are used to rescore the top hits of a search, see
+/// [RescoreRequest](crate::RescoreRequest).
+pub struct LtrModels {
+    models: BTreeMap<String, RegisteredModel>,
+}
+
+impl LtrModels {
+    /// Registers a model definition under the given name, replacing any existing model.
+    ///
+    /// The features are checked against the context immediately so invalid models are
+    /// rejected when they are uploaded rather than when searching.
+    pub fn register(
+        &mut self,
+        ctx: &QueryContext,
+        name: impl Into<String>,
+        source: impl Into<String>,
+    ) -> Result<(), QueryError> {
+        let source = source.into();
+        let definition: LtrModelDefinition = serde_json::from_str(&source)
+            .map_err(|e| QueryError::Invalid(format!("Unable to parse model: {e}")))?;
+        let model = LtrModel::from_definition(definition)?;
+        for feature in model.features() {
+            compile_feature(ctx, feature, "")?;
+        }
+
+        let model = Arc::new(model);
+        self.models
+            .insert(name.into(), RegisteredModel { source, model });
+        Ok(())
+    }
+
+    /// Removes a registered model, returning if it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.models.remove(name).is_some()
+    }
+
+    /// Returns the model with the given name if it exists.
+    pub fn get(&self, name: &str) -> Option<Arc<LtrModel>> {
+        self.models
+            .get(name)
+            .map(|registered| registered.model.clone())
+    }
+
+    /// The number of registered models.
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    /// Returns if there are no registered models.
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+}
+
+/// The persisted learning to rank models of every index.
+pub struct LtrModelStore {
+    metastore: Metastore,
+}
+
+impl LtrModelStore {
+    /// Opens the model store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(LTR_MODELS_DATABASE)?;
+        Ok(Self { metastore })
+    }
+
+    /// Persists the registered models of the index.
+    ///
+    /// The models are stored as a JSON object of each model's name to its source.
+    pub fn save(&self, index: &str, models: &LtrModels) -> anyhow::Result<()> {
+        let models = models
+            .models
+            .iter()
+            .map(|(name, registered)| (name.as_str(), registered.source.as_str()))
+            .collect::<BTreeMap<_, _>>();
+        let source = serde_json::to_string(&models)?;
+        self.metastore.put(&index, &source)
+    }
+
+    /// Loads and compiles the registered models of the index.
+    pub fn load(&self, index: &str, ctx: &QueryContext) -> anyhow::Result<LtrModels> {
+        let models = match self.metastore.get::<_, String>(&index)? {
+            Some(source) => serde_json::from_str::<BTreeMap<String, String>>(&source)?,
+            None => BTreeMap::new(),
+        };
+
+        let mut registered = LtrModels::default();
+        for (name, source) in models {
+            registered.register(ctx, name, source)?;
+        }
+        Ok(registered)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Rescores the top hits of a search with a learning to rank model.
+pub struct LtrRescore {
+    /// The name of the model registered on the index.
+    pub model: String,
+    /// The keywords of the search, used by the `bm25` and `query_length` features.
+    #[serde(default)]
+    pub keywords: String,
+}
+
+impl LtrRescore {
+    /// Resolves the model and compiles its features.
+    pub(crate) fn build(self, ctx: &QueryContext) -> Result<LtrScorer, QueryError> {
+        let model = ctx
+            .ltr_models()
+            .and_then(|models| models.get(&self.model))
+            .ok_or_else(|| {
+                QueryError::Invalid(format!("Unknown model {:?}", self.model))
+            })?;
+
+        let features = model
+            .features()
+            .iter()
+            .map(|feature| compile_feature(ctx, feature, &self.keywords))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(LtrScorer { model, features })
+    }
+}
+
+/// A feature compiled for the keywords of a single search.
+enum CompiledFeature {
+    Query(Box<dyn Query>),
+    FastField {
+        column: FastColumn,
+        missing: Option<f64>,
+    },
+    QueryScore,
+    Constant(f64),
+}
+
+fn compile_feature(
+    ctx: &QueryContext,
+    feature: &LtrFeature,
+    keywords: &str,
+) -> Result<CompiledFeature, QueryError> {
+    match &feature.kind {
+        LtrFeatureKind::Bm25 { field } => {
+            ctx.resolve_text_field(field, "learning to rank")?;
+            if keywords.trim().is_empty() {
+                return Ok(CompiledFeature::Constant(0.0));
+            }
+
+            let query = QueryStringQuery {
+                query: keywords.to_string(),
+                fields: vec![field.clone()],
+                ..Default::default()
+            };
+            Ok(CompiledFeature::Query(query.build(ctx)?))
+        },
+        LtrFeatureKind::FastField { field, missing } => {
+            let (column, _) = resolve_fast_column(ctx, field, "learning to rank")?;
+            Ok(CompiledFeature::FastField {
+                column,
+                missing: *missing,
+            })
+        },
+        LtrFeatureKind::QueryScore => Ok(CompiledFeature::QueryScore),
+        LtrFeatureKind::QueryLength => Ok(CompiledFeature::Constant(
+            keywords.split_whitespace().count() as f64,
+        )),
+    }
+}
+
+/// Scores hits with a learning to rank model.
+pub(crate) struct LtrScorer {
+    model: Arc<LtrModel>,
+    features: Vec<CompiledFeature>,
+}
+
+impl LtrScorer {
+    /// Predicts the score of each hit, computing each feature for every hit in turn.
+    pub(crate) fn score_hits(
+        &self,
+        searcher: &Searcher,
+        hits: &[(Score, DocAddress)],
+    ) -> tantivy::Result<Vec<Option<Score>>> {
+        let mut values = vec![vec![f64::NAN; self.features.len()]; hits.len()];
+
+        for (pos, feature) in self.features.iter().enumerate() {
+            match feature {
+                CompiledFeature::Query(query) => {
+                    let scores = query_scores(searcher, query.as_ref(), hits)?;
+                    for (doc_values, score) in values.iter_mut().zip(scores) {
+                        doc_values[pos] = score.unwrap_or_default() as f64;
+                    }
+                },
+                CompiledFeature::FastField { column, missing } => {
+                    let mut columns = HashMap::new();
+                    for (doc_values, (_, address)) in values.iter_mut().zip(hits) {
+                        let column =
+                            columns.entry(address.segment_ord).or_insert_with(|| {
+                                column.open(searcher.segment_reader(address.segment_ord))
+                            });
+                        let value =
+                            column.as_ref().and_then(|c| c.first(address.doc_id));
+                        doc_values[pos] = value.or(*missing).unwrap_or(f64::NAN);
+                    }
+                },
+                CompiledFeature::QueryScore => {
+                    for (doc_values, (score, _)) in values.iter_mut().zip(hits) {
+                        doc_values[pos] = *score as f64;
+                    }
+                },
+                CompiledFeature::Constant(value) => {
+                    for doc_values in values.iter_mut() {
+                        doc_values[pos] = *value;
+                    }
+                },
+            }
+        }
+
+        Ok(values
+            .iter()
+            .map(|doc_values| {
+                let score = self.model.predict(doc_values) as Score;
+                score.is_finite().then_some(score)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, FAST, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::search::SearchRequest;
+
+    const XGBOOST_MODEL: &str = r#"{
+        "format": "xgboost",
+        "features": [
+            {"name": "title_bm25", "type": "bm25", "field": "title"},
+            {"name": "rating", "type": "fast_field", "field": "rating"}
+        ],
+        "model": [
+            {
+                "nodeid": 0, "split": "rating", "split_condition": 3.0,
+                "yes": 1, "no": 2, "missing": 1,
+                "children": [{"nodeid": 1, "leaf": 0.1}, {"nodeid": 2, "leaf": 2.0}]
+            },
+            {
+                "nodeid": 0, "split": "f0", "split_condition": 0.01,
+                "yes": 1, "no": 2,
+                "children": [{"nodeid": 1, "leaf": 0.0}, {"nodeid": 2, "leaf": 0.5}]
+            }
+        ]
+    }"#;
+
+    fn features(names: &[&str]) -> Vec<LtrFeature> {
+        names
+            .iter()
+            .map(|name| LtrFeature {
+                name: name.to_string(),
+                kind: LtrFeatureKind::QueryScore,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lightgbm_model() {
+        let model: LightgbmModel = serde_json::from_str(
+            r#"{
+                "tree_info": [{
+                    "tree_structure": {
+                        "split_feature": 1, "threshold": 10.0, "decision_type": "<=",
+                        "default_left": false,
+                        "left_child": {"leaf_value": -1.0},
+                        "right_child": {"leaf_value": 1.0}
+                    }
+                }]
+            }"#,
+        )
+        .unwrap();
+        let features = features(&["a", "b"]);
+        let tree = model
+            .tree_info
+            .into_iter()
+            .next()
+            .unwrap()
+            .tree_structure
+            .compile(&features)
+            .unwrap();
+
+        assert_eq!(tree.evaluate(&[0.0, 10.0]), -1.0);
+        assert_eq!(tree.evaluate(&[0.0, 10.5]), 1.0);
+        assert_eq!(tree.evaluate(&[0.0, f64::NAN]), 1.0);
+    }
+
+    #[test]
+    fn test_invalid_models() {
+        let definition = |model: &str| -> LtrModelDefinition {
+            serde_json::from_str(&format!(
+                r#"{{"format": "xgboost", "features": [{{"name": "a", "type": "query_score"}}], "model": {model}}}"#
+            ))
+            .unwrap()
+        };
+
+        assert!(LtrModel::from_definition(definition("[]")).is_err());
+        assert!(LtrModel::from_definition(definition(
+            r#"[{"nodeid": 0, "split": "f3", "split_condition": 1, "yes": 1, "no": 2,
+                "children": [{"nodeid": 1, "leaf": 0}, {"nodeid": 2, "leaf": 1}]}]"#
+        ))
+        .is_err());
+        assert!(LtrModel::from_definition(definition(
+            r#"[{"nodeid": 0, "leaf": 1.5}]"#
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_ltr_rescore() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let rating = schema.add_f64_field("rating", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "running shoes running", rating => 1.0))
+            .unwrap();
+        writer
+            .add_document(doc!(title => "shoes for running", rating => 4.5))
+            .unwrap();
+        writer.add_document(doc!(title => "running socks")).unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema.clone());
+        let mut models = LtrModels::default();
+        models.register(&ctx, "ranker", XGBOOST_MODEL).unwrap();
+        assert!(models
+            .register(&ctx, "invalid", XGBOOST_MODEL.replace("rating", "missing"))
+            .is_err());
+
+        let ctx = QueryContext::new(schema).with_ltr_models(Arc::new(models));
+        let searcher = index.reader().unwrap().searcher();
+
+        let mut request: SearchRequest = serde_json::from_str(
+            r#"{
+                "query": {"query_string": {"query": "running"}},
+                "rescore": {
+                    "ltr": {"model": "ranker", "keywords": "running"},
+                    "query_weight": 0
+                }
+            }"#,
+        )
+        .unwrap();
+        let rescorer = request.build_rescorer(&ctx).unwrap().unwrap();
+        let query = request.build_query(&ctx).unwrap();
+        let hits = searcher.search(&query, &rescorer.collector(10)).unwrap();
+        assert_eq!(hits[0].1.doc_id, 0);
+
+        // The highly rated document wins, documents without a rating take the
+        // `missing` branch of the first tree.
+        let hits = rescorer.rescore(&searcher, hits).unwrap();
+        let docs = hits.iter().map(|(_, addr)| addr.doc_id).collect::<Vec<_>>();
+        assert_eq!(docs[0], 1);
+        assert!((hits[0].0 - 2.5).abs() < 1e-6);
+        assert!((hits[1].0 - 0.6).abs() < 1e-6);
+
+        let mut request: SearchRequest =
+            serde_json::from_str(r#"{"rescore": {"ltr": {"model": "missing"}}}"#)
+                .unwrap();
+        assert!(request.build_rescorer(&ctx).is_err());
+    }
+}
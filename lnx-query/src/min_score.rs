@@ -0,0 +1,113 @@
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// Wraps a collector so that documents scoring below a threshold are never collected.
+///
+/// Because the threshold is applied before the inner collector sees the document,
+/// weak matches are excluded from top-k selection, pagination and hit counts alike.
+pub struct MinScoreCollector<C> {
+    inner: C,
+    min_score: Score,
+}
+
+impl<C> MinScoreCollector<C> {
+    /// Creates a new collector which drops documents scoring below `min_score`.
+    pub fn new(inner: C, min_score: Score) -> Self {
+        Self { inner, min_score }
+    }
+}
+
+impl<C: Collector> Collector for MinScoreCollector<C> {
+    type Fruit = C::Fruit;
+    type Child = MinScoreSegmentCollector<C::Child>;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(MinScoreSegmentCollector {
+            inner: self.inner.for_segment(segment_ord, reader)?,
+            min_score: self.min_score,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        // The threshold can only be applied if documents are scored.
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        self.inner.merge_fruits(segment_fruits)
+    }
+}
+
+/// The per-segment collector of a [MinScoreCollector].
+pub struct MinScoreSegmentCollector<C> {
+    inner: C,
+    min_score: Score,
+}
+
+impl<C: SegmentCollector> SegmentCollector for MinScoreSegmentCollector<C> {
+    type Fruit = C::Fruit;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if score >= self.min_score {
+            self.inner.collect(doc, score);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.inner.harvest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::{Count, TopDocs};
+    use tantivy::query::{BooleanQuery, ConstScoreQuery, Occur, Query, TermQuery};
+    use tantivy::schema::{IndexRecordOption, SchemaBuilder, INDEXED};
+    use tantivy::{doc, Index, Term};
+
+    use super::*;
+
+    #[test]
+    fn test_min_score_collector() {
+        let mut schema = SchemaBuilder::new();
+        let id = schema.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for i in 0..10u64 {
+            writer.add_document(doc!(id => i)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let const_score = |i: u64, score: Score| -> (Occur, Box<dyn Query>) {
+            let term = Term::from_field_u64(id, i);
+            let query = TermQuery::new(term, IndexRecordOption::Basic);
+            (
+                Occur::Should,
+                Box::new(ConstScoreQuery::new(Box::new(query), score)),
+            )
+        };
+
+        // Documents `0..5` score `1.0` and document `9` scores `5.0`.
+        let mut clauses = (0..5).map(|i| const_score(i, 1.0)).collect::<Vec<_>>();
+        clauses.push(const_score(9, 5.0));
+        let query = BooleanQuery::new(clauses);
+
+        let searcher = index.reader().unwrap().searcher();
+        let collector = MinScoreCollector::new((TopDocs::with_limit(10), Count), 2.0);
+        let (top_docs, count) = searcher.search(&query, &collector).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(top_docs.len(), 1);
+
+        let collector = MinScoreCollector::new(Count, 1.0);
+        let count = searcher.search(&query, &collector).unwrap();
+        assert_eq!(count, 6);
+    }
+}
@@ -0,0 +1,304 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use tantivy::query::{EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, TERMINATED};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// The minimum number of optional clauses which must match for a document to match.
+pub enum MinimumShouldMatch {
+    /// An absolute number of clauses.
+    Absolute(usize),
+    /// A percentage of the total number of clauses, rounded down.
+    Percentage(u8),
+}
+
+impl MinimumShouldMatch {
+    /// Resolves the minimum number of required clauses for the given number of clauses.
+    ///
+    /// At least one clause is always required and at most `num_clauses` are required.
+    pub fn resolve(&self, num_clauses: usize) -> usize {
+        let required = match self {
+            Self::Absolute(n) => *n,
+            Self::Percentage(pct) => (num_clauses * *pct as usize) / 100,
+        };
+
+        required.clamp(1, num_clauses.max(1))
+    }
+}
+
+impl Display for MinimumShouldMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Absolute(n) => write!(f, "{n}"),
+            Self::Percentage(pct) => write!(f, "{pct}%"),
+        }
+    }
+}
+
+impl FromStr for MinimumShouldMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(pct) = s.strip_suffix('%') {
+            return match pct.trim().parse::<u8>() {
+                Ok(pct) if pct <= 100 => Ok(Self::Percentage(pct)),
+                _ => Err(format!(
+                    "Invalid minimum should match percentage {s:?}, expected a value between 0% and 100%"
+                )),
+            };
+        }
+
+        s.parse::<usize>().map(Self::Absolute).map_err(|_| {
+            format!(
+                "Invalid minimum should match {s:?}, expected a positive integer or percentage"
+            )
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for MinimumShouldMatch {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Absolute(usize),
+            Str(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Absolute(n) => Ok(Self::Absolute(n)),
+            Raw::Str(s) => s.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// A disjunction which requires at least `minimum` of its clauses to match.
+///
+/// The score of a matching document is the sum of the scores of its matching clauses.
+pub struct MinShouldMatchQuery {
+    clauses: Vec<Box<dyn Query>>,
+    minimum: usize,
+}
+
+impl Clone for MinShouldMatchQuery {
+    fn clone(&self) -> Self {
+        Self {
+            clauses: self.clauses.iter().map(|clause| clause.box_clone()).collect(),
+            minimum: self.minimum,
+        }
+    }
+}
+
+impl MinShouldMatchQuery {
+    /// Creates a new query requiring `minimum` of the given clauses to match.
+    pub fn new(clauses: Vec<Box<dyn Query>>, minimum: usize) -> Self {
+        Self { clauses, minimum }
+    }
+}
+
+impl Query for MinShouldMatchQuery {
+    fn weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        let weights = self
+            .clauses
+            .iter()
+            .map(|clause| clause.weight(enable_scoring))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+
+        Ok(Box::new(MinShouldMatchWeight {
+            weights,
+            minimum: self.minimum,
+        }))
+    }
+}
+
+struct MinShouldMatchWeight {
+    weights: Vec<Box<dyn Weight>>,
+    minimum: usize,
+}
+
+impl Weight for MinShouldMatchWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        let scorers = self
+            .weights
+            .iter()
+            .map(|weight| weight.scorer(reader, boost))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+
+        Ok(Box::new(MinShouldMatchScorer::new(scorers, self.minimum)))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        let mut explanation = Explanation::new(
+            format!("MinShouldMatch(minimum={})", self.minimum),
+            scorer.score(),
+        );
+        for weight in self.weights.iter() {
+            if let Ok(child) = weight.explain(reader, doc) {
+                explanation.add_detail(child);
+            }
+        }
+
+        Ok(explanation)
+    }
+}
+
+struct MinShouldMatchScorer {
+    scorers: Vec<Box<dyn Scorer>>,
+    minimum: usize,
+    doc: DocId,
+    score: Score,
+}
+
+impl MinShouldMatchScorer {
+    fn new(scorers: Vec<Box<dyn Scorer>>, minimum: usize) -> Self {
+        let mut slf = Self {
+            scorers,
+            minimum,
+            doc: 0,
+            score: 0.0,
+        };
+        slf.find_next_match();
+        slf
+    }
+
+    /// Moves to the next document where at least `minimum` scorers match,
+    /// starting from the current positions of the scorers.
+    fn find_next_match(&mut self) -> DocId {
+        loop {
+            let candidate = self
+                .scorers
+                .iter()
+                .map(|scorer| scorer.doc())
+                .min()
+                .unwrap_or(TERMINATED);
+
+            if candidate == TERMINATED {
+                self.doc = TERMINATED;
+                return TERMINATED;
+            }
+
+            let mut num_matches = 0;
+            let mut score = 0.0;
+            for scorer in self.scorers.iter_mut() {
+                if scorer.doc() == candidate {
+                    num_matches += 1;
+                    score += scorer.score();
+                }
+            }
+
+            if num_matches >= self.minimum {
+                self.doc = candidate;
+                self.score = score;
+                return candidate;
+            }
+
+            self.advance_scorers_at(candidate);
+        }
+    }
+
+    fn advance_scorers_at(&mut self, doc: DocId) {
+        for scorer in self.scorers.iter_mut() {
+            if scorer.doc() == doc {
+                scorer.advance();
+            }
+        }
+    }
+}
+
+impl DocSet for MinShouldMatchScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+
+        self.advance_scorers_at(self.doc);
+        self.find_next_match()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+
+        for scorer in self.scorers.iter_mut() {
+            if scorer.doc() < target {
+                scorer.seek(target);
+            }
+        }
+        self.find_next_match()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorers
+            .iter()
+            .map(|scorer| scorer.size_hint())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Scorer for MinShouldMatchScorer {
+    fn score(&mut self) -> Score {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_minimum_should_match() {
+        assert_eq!("2".parse(), Ok(MinimumShouldMatch::Absolute(2)));
+        assert_eq!("75%".parse(), Ok(MinimumShouldMatch::Percentage(75)));
+        assert!("101%".parse::<MinimumShouldMatch>().is_err());
+        assert!("-1".parse::<MinimumShouldMatch>().is_err());
+        assert!("abc".parse::<MinimumShouldMatch>().is_err());
+
+        let value: MinimumShouldMatch = serde_json::from_str("3").unwrap();
+        assert_eq!(value, MinimumShouldMatch::Absolute(3));
+        let value: MinimumShouldMatch = serde_json::from_str(r#""50%""#).unwrap();
+        assert_eq!(value, MinimumShouldMatch::Percentage(50));
+    }
+
+    #[test]
+    fn test_resolve_minimum_should_match() {
+        assert_eq!(MinimumShouldMatch::Absolute(2).resolve(4), 2);
+        assert_eq!(MinimumShouldMatch::Absolute(8).resolve(4), 4);
+        assert_eq!(MinimumShouldMatch::Absolute(0).resolve(4), 1);
+        assert_eq!(MinimumShouldMatch::Percentage(75).resolve(4), 3);
+        assert_eq!(MinimumShouldMatch::Percentage(50).resolve(3), 1);
+        assert_eq!(MinimumShouldMatch::Percentage(100).resolve(3), 3);
+    }
+}
@@ -0,0 +1,184 @@
+use std::cmp::Ordering;
+
+use serde::Serialize;
+use tantivy::Score;
+
+use crate::error::QueryError;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// A single hit produced by a search across multiple indexes.
+pub struct IndexHit<T> {
+    /// The name of the index the hit came from.
+    pub index: String,
+    /// The score of the hit within its own index.
+    pub score: Score,
+    /// The hit itself, normally the document or its address.
+    pub doc: T,
+}
+
+/// Resolves a comma separated list of index names and wildcard patterns
+/// against the set of existing indexes.
+///
+/// Patterns may contain any number of `*` wildcards, i.e. `logs-*`, a pattern which
+/// matches no indexes is ignored but an exact index name which does not exist is rejected.
+///
+/// The resolved indexes are returned in the order they were first matched without duplicates.
+pub fn resolve_index_patterns<'a>(
+    patterns: &str,
+    indexes: &[&'a str],
+) -> Result<Vec<&'a str>, QueryError> {
+    let mut resolved = Vec::new();
+
+    for pattern in patterns.split(',').map(str::trim) {
+        if pattern.is_empty() {
+            continue;
+        }
+
+        if !pattern.contains('*') {
+            let index = indexes
+                .iter()
+                .find(|index| **index == pattern)
+                .ok_or_else(|| QueryError::UnknownIndex(pattern.to_string()))?;
+
+            if !resolved.contains(index) {
+                resolved.push(*index);
+            }
+            continue;
+        }
+
+        for index in indexes {
+            if wildcard_matches(pattern, index) && !resolved.contains(index) {
+                resolved.push(*index);
+            }
+        }
+    }
+
+    if resolved.is_empty() && !patterns.contains('*') {
+        return Err(QueryError::Invalid(
+            "At least one index must be provided".to_string(),
+        ));
+    }
+
+    Ok(resolved)
+}
+
+/// Merges the top hits of several indexes into a single set of hits ordered by score.
+///
+/// Ties are broken by the order of the indexes and then the order of the hits within
+/// their index, so merging is deterministic across requests.
+pub fn merge_index_hits<T>(
+    results: Vec<(String, Vec<(Score, T)>)>,
+    offset: usize,
+    limit: usize,
+) -> Vec<IndexHit<T>> {
+    let mut hits = Vec::new();
+    for (index, docs) in results {
+        for (score, doc) in docs {
+            hits.push(IndexHit {
+                index: index.clone(),
+                score,
+                doc,
+            });
+        }
+    }
+
+    // `sort_by` is stable, so equal scores keep their index & hit order.
+    hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+
+    hits.into_iter().skip(offset).take(limit).collect()
+}
+
+/// Checks if the name matches a pattern containing `*` wildcards.
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+
+    // There is always at least one part, even for an empty pattern.
+    let first = parts.next().unwrap_or_default();
+    let Some(mut remaining) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.collect::<Vec<_>>();
+    let last = match parts.pop() {
+        Some(last) => last,
+        // No wildcards, the name must match exactly.
+        None => return remaining.is_empty(),
+    };
+
+    for part in parts {
+        match remaining.find(part) {
+            Some(pos) => remaining = &remaining[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEXES: &[&str] = &["logs-2023-01", "logs-2023-02", "metrics", "products"];
+
+    #[test]
+    fn test_wildcard_matches() {
+        assert!(wildcard_matches("logs-*", "logs-2023-01"));
+        assert!(wildcard_matches("*", "metrics"));
+        assert!(wildcard_matches("logs-*-01", "logs-2023-01"));
+        assert!(wildcard_matches("*-01", "logs-2023-01"));
+        assert!(!wildcard_matches("logs-*-01", "logs-2023-02"));
+        assert!(!wildcard_matches("logs-*", "metrics"));
+        assert!(!wildcard_matches("a*a", "a"));
+    }
+
+    #[test]
+    fn test_resolve_index_patterns() {
+        let resolved = resolve_index_patterns("products, logs-*", INDEXES).unwrap();
+        assert_eq!(resolved, ["products", "logs-2023-01", "logs-2023-02"]);
+
+        let resolved = resolve_index_patterns("metrics,metrics,*", INDEXES).unwrap();
+        assert_eq!(
+            resolved,
+            ["metrics", "logs-2023-01", "logs-2023-02", "products"]
+        );
+
+        let resolved = resolve_index_patterns("traces-*", INDEXES).unwrap();
+        assert!(resolved.is_empty());
+
+        assert!(matches!(
+            resolve_index_patterns("metrics,missing", INDEXES),
+            Err(QueryError::UnknownIndex(index)) if index == "missing",
+        ));
+        assert!(resolve_index_patterns("", INDEXES).is_err());
+    }
+
+    #[test]
+    fn test_merge_index_hits() {
+        let hits = merge_index_hits(
+            vec![
+                ("a".to_string(), vec![(3.0, 1), (1.0, 2)]),
+                ("b".to_string(), vec![(2.0, 1), (1.0, 2)]),
+            ],
+            0,
+            10,
+        );
+        let hits = hits
+            .iter()
+            .map(|hit| (hit.index.as_str(), hit.doc))
+            .collect::<Vec<_>>();
+        assert_eq!(hits, [("a", 1), ("b", 1), ("a", 2), ("b", 2)]);
+
+        let hits = merge_index_hits(
+            vec![
+                ("a".to_string(), vec![(3.0, 1), (1.0, 2)]),
+                ("b".to_string(), vec![(2.0, 1), (1.0, 2)]),
+            ],
+            1,
+            2,
+        );
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].index, "b");
+        assert_eq!(hits[1].index, "a");
+    }
+}
@@ -0,0 +1,336 @@
+use lnx_transforms::{NESTED_CHILD_FIELD, NESTED_PATH_FIELD};
+use serde::Deserialize;
+use tantivy::columnar::Column;
+use tantivy::query::{
+    BooleanQuery,
+    EnableScoring,
+    Explanation,
+    Occur,
+    Query,
+    Scorer,
+    TermQuery,
+    Weight,
+};
+use tantivy::schema::IndexRecordOption;
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term, TERMINATED};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query::QueryKind;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the scores of matching child documents are combined into the parent's score.
+pub enum ScoreMode {
+    #[default]
+    /// The average score of all matching children.
+    Avg,
+    /// The highest score of all matching children.
+    Max,
+    /// The lowest score of all matching children.
+    Min,
+    /// The sum of the scores of all matching children.
+    Sum,
+    /// Children do not contribute to the score.
+    None,
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches parent documents where at least one object within a nested field matches
+/// the inner query.
+///
+/// Unlike querying a flattened array of objects, every condition of the inner query
+/// must match the *same* object within the array.
+pub struct NestedQuery<'a> {
+    /// The path of the nested field.
+    pub path: String,
+    #[serde(borrow)]
+    /// The query to run against each nested object.
+    ///
+    /// Fields within the nested objects are referenced by their full path,
+    /// i.e. `comments.author`.
+    pub query: Box<QueryKind<'a>>,
+    #[serde(default)]
+    /// How the scores of matching children are combined.
+    pub score_mode: ScoreMode,
+}
+
+impl<'a> NestedQuery<'a> {
+    /// Compiles the nested query into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let path_field = ctx.schema().get_field(NESTED_PATH_FIELD).map_err(|_| {
+            QueryError::unsupported(
+                &self.path,
+                "nested",
+                "nested documents are not enabled for this index",
+            )
+        })?;
+
+        let path_term = Term::from_field_text(path_field, &self.path);
+        let child_query = BooleanQuery::new(vec![
+            (Occur::Must, self.query.build(ctx)?),
+            (
+                Occur::Must,
+                Box::new(TermQuery::new(path_term, IndexRecordOption::Basic)),
+            ),
+        ]);
+
+        Ok(Box::new(BlockJoinQuery {
+            child_query: Box::new(child_query),
+            score_mode: self.score_mode,
+        }))
+    }
+}
+
+#[derive(Debug)]
+/// Joins matching child documents onto their parent document.
+///
+/// This relies on the block-join layout produced by `split_nested_documents`,
+/// where the children of a parent are always indexed directly before the parent.
+struct BlockJoinQuery {
+    child_query: Box<dyn Query>,
+    score_mode: ScoreMode,
+}
+
+impl Clone for BlockJoinQuery {
+    fn clone(&self) -> Self {
+        Self {
+            child_query: self.child_query.box_clone(),
+            score_mode: self.score_mode,
+        }
+    }
+}
+
+impl Query for BlockJoinQuery {
+    fn weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(BlockJoinWeight {
+            child_weight: self.child_query.weight(enable_scoring)?,
+            score_mode: self.score_mode,
+        }))
+    }
+}
+
+struct BlockJoinWeight {
+    child_weight: Box<dyn Weight>,
+    score_mode: ScoreMode,
+}
+
+impl Weight for BlockJoinWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        let child_scorer = self.child_weight.scorer(reader, boost)?;
+        let is_child = reader.fast_fields().u64(NESTED_CHILD_FIELD)?;
+
+        Ok(Box::new(BlockJoinScorer::new(
+            child_scorer,
+            is_child,
+            reader.max_doc(),
+            self.score_mode,
+        )))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        Ok(Explanation::new(
+            format!("BlockJoin(score_mode={:?})", self.score_mode),
+            scorer.score(),
+        ))
+    }
+}
+
+struct BlockJoinScorer {
+    child_scorer: Box<dyn Scorer>,
+    is_child: Column<u64>,
+    max_doc: DocId,
+    score_mode: ScoreMode,
+    doc: DocId,
+    score: Score,
+}
+
+impl BlockJoinScorer {
+    fn new(
+        child_scorer: Box<dyn Scorer>,
+        is_child: Column<u64>,
+        max_doc: DocId,
+        score_mode: ScoreMode,
+    ) -> Self {
+        let mut slf = Self {
+            child_scorer,
+            is_child,
+            max_doc,
+            score_mode,
+            doc: 0,
+            score: 0.0,
+        };
+        slf.join_next_parent();
+        slf
+    }
+
+    fn is_child(&self, doc: DocId) -> bool {
+        self.is_child.first(doc).unwrap_or(0) == 1
+    }
+
+    /// Finds the parent of the next matching child and scores it.
+    fn join_next_parent(&mut self) -> DocId {
+        let mut child = self.child_scorer.doc();
+        if child == TERMINATED {
+            self.doc = TERMINATED;
+            return TERMINATED;
+        }
+
+        let mut parent = child + 1;
+        while parent < self.max_doc && self.is_child(parent) {
+            parent += 1;
+        }
+
+        if parent >= self.max_doc {
+            // Orphaned children without a parent can never match.
+            self.doc = TERMINATED;
+            return TERMINATED;
+        }
+
+        let mut num_children = 0;
+        let mut score = match self.score_mode {
+            ScoreMode::Min => Score::MAX,
+            _ => 0.0,
+        };
+        while child < parent {
+            let child_score = self.child_scorer.score();
+            score = match self.score_mode {
+                ScoreMode::Avg | ScoreMode::Sum => score + child_score,
+                ScoreMode::Max => score.max(child_score),
+                ScoreMode::Min => score.min(child_score),
+                ScoreMode::None => 0.0,
+            };
+            num_children += 1;
+            child = self.child_scorer.advance();
+        }
+
+        if self.score_mode == ScoreMode::Avg {
+            score /= num_children as Score;
+        }
+
+        self.doc = parent;
+        self.score = score;
+        parent
+    }
+}
+
+impl DocSet for BlockJoinScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        self.join_next_parent()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.child_scorer.size_hint()
+    }
+}
+
+impl Scorer for BlockJoinScorer {
+    fn score(&mut self) -> Score {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lnx_schema::schema::IndexSchema;
+    use tantivy::collector::Count;
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::search::SearchRequest;
+
+    #[test]
+    fn test_nested_query_parse() {
+        let query: NestedQuery = serde_json::from_str(
+            r#"{
+                "path": "comments",
+                "query": {"term": {"field": "comments.author", "value": "bob"}},
+                "score_mode": "max"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(query.path, "comments");
+        assert_eq!(query.score_mode, ScoreMode::Max);
+        assert!(matches!(*query.query, QueryKind::Term(_)));
+    }
+
+    #[test]
+    fn test_nested_children_excluded_from_search() {
+        let definition: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {"title": {"type": "text"}},
+                "nested": {"comments": {"author": {"type": "string"}}}
+            }"#,
+        )
+        .unwrap();
+        let (schema, _) = definition.build().unwrap();
+        let title = schema.get_field("title").unwrap();
+        let author = schema.get_field("comments.author").unwrap();
+        let path = schema.get_field(NESTED_PATH_FIELD).unwrap();
+        let child = schema.get_field(NESTED_CHILD_FIELD).unwrap();
+
+        let index = Index::create_in_ram(schema.clone());
+        let mut writer = index.writer(15_000_000).unwrap();
+        for (name, comments) in [("first", &["bob", "tim"][..]), ("second", &["alice"])] {
+            for comment_author in comments {
+                writer
+                    .add_document(doc!(
+                        path => "comments",
+                        child => 1u64,
+                        author => *comment_author,
+                    ))
+                    .unwrap();
+            }
+            writer.add_document(doc!(title => name)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let count = |json: &str| {
+            let request: SearchRequest = serde_json::from_str(json).unwrap();
+            let query = request.build_query(&ctx).unwrap();
+            searcher.search(&query, &Count).unwrap()
+        };
+
+        assert_eq!(count("{}"), 2);
+        assert_eq!(
+            count(r#"{"query": {"term": {"field": "comments.author", "value": "bob"}}}"#),
+            0,
+        );
+        assert_eq!(
+            count(
+                r#"{"query": {"nested": {
+                    "path": "comments",
+                    "query": {"term": {"field": "comments.author", "value": "bob"}}
+                }}}"#
+            ),
+            1,
+        );
+    }
+}
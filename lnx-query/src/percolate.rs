@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use lnx_metastore::Metastore;
+use tantivy::collector::Count;
+use tantivy::query::Query;
+use tantivy::Searcher;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query::QueryKind;
+
+/// The metastore database registered percolator queries are stored in.
+const PERCOLATOR_DATABASE: &str = "lnx_percolator_queries";
+
+/// A registered query, kept alongside its source so it can be persisted.
+struct RegisteredQuery {
+    source: String,
+    query: Box<dyn Query>,
+}
+
+#[derive(Default)]
+/// A set of registered queries which incoming documents are matched against.
+///
+/// This is a reverse search, rather than finding the documents matching a query
+/// it finds the queries matching a document, which powers alerting and saved
+/// search notifications.
+///
+/// Queries are compiled once when they are registered, documents are percolated by
+/// indexing them into a temporary in-memory index and executing each query against it.
+pub struct Percolator {
+    queries: BTreeMap<String, RegisteredQuery>,
+}
+
+impl Percolator {
+    /// Registers a query DSL object under the given ID, replacing any existing query.
+    ///
+    /// The query is compiled against the context immediately so invalid
+    /// queries are rejected at registration time rather than when percolating.
+    pub fn register(
+        &mut self,
+        ctx: &QueryContext,
+        id: impl Into<String>,
+        source: impl Into<String>,
+    ) -> Result<(), QueryError> {
+        let source = source.into();
+        let query = compile(ctx, &source)?;
+        self.queries
+            .insert(id.into(), RegisteredQuery { source, query });
+        Ok(())
+    }
+
+    /// Removes a registered query, returning if it existed.
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.queries.remove(id).is_some()
+    }
+
+    /// The number of registered queries.
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns if there are no registered queries.
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Returns the IDs of every registered query which matches at least one document
+    /// within the searcher.
+    ///
+    /// The searcher should be over a temporary index containing only the documents
+    /// being percolated.
+    pub fn percolate(&self, searcher: &Searcher) -> Result<Vec<&str>, QueryError> {
+        let mut matches = Vec::new();
+        for (id, registered) in self.queries.iter() {
+            if searcher.search(registered.query.as_ref(), &Count)? > 0 {
+                matches.push(id.as_str());
+            }
+        }
+        Ok(matches)
+    }
+}
+
+/// The persisted set of percolator queries for every index.
+pub struct PercolatorStore {
+    metastore: Metastore,
+}
+
+impl PercolatorStore {
+    /// Opens the percolator store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(PERCOLATOR_DATABASE)?;
+        Ok(Self { metastore })
+    }
+
+    /// Persists the registered queries of the index.
+    ///
+    /// The queries are stored as a JSON object of each query's ID to its source.
+    pub fn save(&self, index: &str, percolator: &Percolator) -> anyhow::Result<()> {
+        let queries = percolator
+            .queries
+            .iter()
+            .map(|(id, registered)| (id.as_str(), registered.source.as_str()))
+            .collect::<BTreeMap<_, _>>();
+        let source = serde_json::to_string(&queries)?;
+        self.metastore.put(&index, &source)
+    }
+
+    /// Loads and compiles the registered queries of the index.
+    pub fn load(&self, index: &str, ctx: &QueryContext) -> anyhow::Result<Percolator> {
+        let queries = match self.metastore.get::<_, String>(&index)? {
+            Some(source) => serde_json::from_str::<BTreeMap<String, String>>(&source)?,
+            None => BTreeMap::new(),
+        };
+
+        let mut percolator = Percolator::default();
+        for (id, source) in queries {
+            percolator.register(ctx, id, source)?;
+        }
+        Ok(percolator)
+    }
+}
+
+fn compile(ctx: &QueryContext, source: &str) -> Result<Box<dyn Query>, QueryError> {
+    let query: QueryKind = serde_json::from_str(source)
+        .map_err(|e| QueryError::Invalid(format!("Unable to parse query: {e}")))?;
+    query.build(ctx)
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, FAST, INDEXED, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_percolate() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let price = schema.add_u64_field("price", FAST | INDEXED);
+        let schema = schema.build();
+        let ctx = QueryContext::new(schema.clone());
+
+        let mut percolator = Percolator::default();
+        percolator
+            .register(
+                &ctx,
+                "cheap-shoes",
+                r#"{"range": {"field": "price", "lt": 50}}"#,
+            )
+            .unwrap();
+        percolator
+            .register(&ctx, "laptops", r#"{"query_string": {"query": "laptop"}}"#)
+            .unwrap();
+        assert!(percolator
+            .register(&ctx, "invalid", r#"{"range": {"field": "missing"}}"#)
+            .is_err());
+        assert_eq!(percolator.len(), 2);
+
+        let index = Index::create_in_ram(schema);
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "Running shoes", price => 30u64))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        assert_eq!(percolator.percolate(&searcher).unwrap(), ["cheap-shoes"]);
+
+        assert!(percolator.remove("cheap-shoes"));
+        assert!(percolator.percolate(&searcher).unwrap().is_empty());
+    }
+}
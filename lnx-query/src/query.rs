@@ -0,0 +1,100 @@
+use serde::Deserialize;
+use tantivy::query::Query;
+
+use crate::cidr::CidrQuery;
+use crate::context::QueryContext;
+use crate::distance_feature::DistanceFeatureQuery;
+use crate::error::QueryError;
+use crate::exists::ExistsQuery;
+use crate::facet::FacetQuery;
+use crate::function_score::FunctionScoreQuery;
+use crate::nested::NestedQuery;
+use crate::query_string::QueryStringQuery;
+use crate::range::RangeQuery;
+use crate::regex::{RegexQuery, WildcardQuery};
+use crate::scoring::{BoostQuery, BoostingQuery, ConstantScoreQuery};
+use crate::script_score::ScriptScoreQuery;
+use crate::span::{SpanFirstQuery, SpanNearQuery};
+use crate::term::{TermQuery, TermsQuery};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A single query within the query DSL.
+///
+/// Queries are externally tagged, i.e. `{"range": {"field": "price", "gte": 10}}`.
+pub enum QueryKind<'a> {
+    #[serde(borrow)]
+    /// Match documents matching the inner query with their score multiplied by a factor.
+    Boost(BoostQuery<'a>),
+    #[serde(borrow)]
+    /// Match documents matching a positive query, demoting those matching a negative query.
+    Boosting(BoostingQuery<'a>),
+    /// Match documents with an ip within a CIDR block.
+    Cidr(CidrQuery),
+    #[serde(borrow)]
+    /// Match documents matching the inner query with a constant score.
+    ConstantScore(ConstantScoreQuery<'a>),
+    /// Match documents with a value for a field, scored by their closeness to an origin.
+    DistanceFeature(DistanceFeatureQuery),
+    /// Match documents which have a value for the given field.
+    Exists(ExistsQuery),
+    /// Match documents with a facet under a set of facet paths.
+    Facet(FacetQuery),
+    #[serde(borrow)]
+    /// Match documents matching the inner query with their score adjusted by functions.
+    FunctionScore(FunctionScoreQuery<'a>),
+    /// Match documents which do not have a value for the given field.
+    Missing(ExistsQuery),
+    #[serde(borrow)]
+    /// Match documents with a nested object matching the inner query.
+    Nested(NestedQuery<'a>),
+    /// Match documents using a free-text query string.
+    QueryString(QueryStringQuery),
+    #[serde(borrow)]
+    /// Match documents within a given range of values.
+    Range(RangeQuery<'a>),
+    /// Match documents with terms matching a regex pattern.
+    Regex(RegexQuery),
+    #[serde(borrow)]
+    /// Match documents matching the inner query scored by an expression.
+    ScriptScore(ScriptScoreQuery<'a>),
+    /// Match documents with a term within the first positions of a field.
+    SpanFirst(SpanFirstQuery),
+    /// Match documents with a set of terms within a window of each other.
+    SpanNear(SpanNearQuery),
+    #[serde(borrow)]
+    /// Match documents containing an exact term.
+    Term(TermQuery<'a>),
+    #[serde(borrow)]
+    /// Match documents containing any of a set of exact terms.
+    Terms(TermsQuery<'a>),
+    /// Match documents with terms matching a wildcard pattern.
+    Wildcard(WildcardQuery),
+}
+
+impl<'a> QueryKind<'a> {
+    /// Compiles the query into a tantivy query for the given context.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        match self {
+            QueryKind::Boost(query) => query.build(ctx),
+            QueryKind::Boosting(query) => query.build(ctx),
+            QueryKind::Cidr(query) => query.build(ctx),
+            QueryKind::ConstantScore(query) => query.build(ctx),
+            QueryKind::DistanceFeature(query) => query.build(ctx),
+            QueryKind::Exists(query) => query.build(ctx),
+            QueryKind::Facet(query) => query.build(ctx),
+            QueryKind::FunctionScore(query) => query.build(ctx),
+            QueryKind::Missing(query) => query.build_missing(ctx),
+            QueryKind::Nested(query) => query.build(ctx),
+            QueryKind::QueryString(query) => query.build(ctx),
+            QueryKind::Range(query) => query.build(ctx),
+            QueryKind::Regex(query) => query.build(ctx),
+            QueryKind::ScriptScore(query) => query.build(ctx),
+            QueryKind::SpanFirst(query) => query.build(ctx),
+            QueryKind::SpanNear(query) => query.build(ctx),
+            QueryKind::Term(query) => query.build(ctx),
+            QueryKind::Terms(query) => query.build(ctx),
+            QueryKind::Wildcard(query) => query.build(ctx),
+        }
+    }
+}
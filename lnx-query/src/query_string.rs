@@ -0,0 +1,603 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use lnx_schema::similarity::Bm25Params;
+use serde::{Deserialize, Deserializer};
+use tantivy::query::{BooleanQuery, DisjunctionMaxQuery, Occur, Query};
+use tantivy::Score;
+
+use crate::bm25::rescore_bm25;
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::min_should_match::{MinShouldMatchQuery, MinimumShouldMatch};
+use crate::synonyms::expand_query_synonyms;
+
+/// The maximum edit distance supported by fuzzy free-text queries.
+pub const MAX_FUZZINESS: u8 = 2;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+/// The operator used to combine free-text terms which do not have
+/// an explicit operator between them.
+pub enum Operator {
+    #[default]
+    #[serde(alias = "or", alias = "OR")]
+    /// Documents matching any of the terms are returned.
+    Or,
+    #[serde(alias = "and", alias = "AND")]
+    /// Only documents matching all of the terms are returned.
+    And,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the scores of a term matching several fields are combined.
+pub enum MultiFieldMode {
+    #[default]
+    /// The scores of every field the term matches are summed, so documents matching
+    /// the term in more fields score higher.
+    MostFields,
+    /// Only the score of the best matching field is used, plus the scores of the other
+    /// matching fields multiplied by the `tie_breaker`.
+    BestFields,
+}
+
+#[derive(Debug, Default, Deserialize)]
+/// A free-text query which is parsed by the query parser.
+///
+/// By default the query searches all the default fields of the index,
+/// this can be restricted to a specific set of fields via `fields`, and
+/// each field can be given a boost to weight matches on that field.
+pub struct QueryStringQuery {
+    /// The query string to parse.
+    pub query: String,
+    #[serde(default, deserialize_with = "deserialize_field_list")]
+    /// The fields to search.
+    ///
+    /// This can either be provided as an array of field names or a
+    /// comma-separated string, i.e. `"title,body"`. Each field can be given a boost
+    /// with a `^` suffix, i.e. `"title^3,body"`.
+    pub fields: Vec<String>,
+    #[serde(default)]
+    /// A per-field boost to apply to the score of matches on the given field.
+    ///
+    /// This takes priority over the boosts within `fields` and the default boosts
+    /// of the schema.
+    pub boosts: BTreeMap<String, f32>,
+    #[serde(default)]
+    /// Per-field BM25 parameters used to score matches on the given field,
+    /// overriding the field's parameters within the schema for this query only.
+    pub bm25: BTreeMap<String, Bm25Params>,
+    #[serde(default)]
+    /// The operator used to combine terms by default.
+    ///
+    /// Defaults to the default operator of the context, see
+    /// [QueryContext::with_default_operator].
+    pub default_operator: Option<Operator>,
+    #[serde(default)]
+    /// The maximum edit distance, up to `2`, a term may be from a term within the
+    /// searched fields and still match, i.e. to tolerate typos.
+    ///
+    /// Defaults to the default fuzziness of the context, see
+    /// [QueryContext::with_default_fuzziness].
+    pub fuzziness: Option<u8>,
+    #[serde(default)]
+    /// The minimum number of optional terms which must match.
+    ///
+    /// This can either be an absolute number or a percentage i.e. `"75%"`,
+    /// it only applies when the default operator is `Or`.
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    #[serde(default)]
+    /// The analyzer used to tokenize the query text instead of each field's analyzer.
+    ///
+    /// This only affects how the query is analyzed, i.e. `"raw"` matches the query text
+    /// as a single exact keyword even against a tokenized text field.
+    pub analyzer: Option<String>,
+    #[serde(default)]
+    /// How the scores of a term matching several of the searched fields are combined.
+    pub multi_field_mode: MultiFieldMode,
+    #[serde(default)]
+    /// The weight of the fields other than the best matching field when using
+    /// [MultiFieldMode::BestFields], between `0` and `1`.
+    ///
+    /// Defaults to `0.0`, only the best matching field contributes to the score.
+    pub tie_breaker: Option<Score>,
+}
+
+impl QueryStringQuery {
+    /// Compiles the query string into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let tie_breaker = self.tie_breaker.unwrap_or_default();
+        let default_operator = self.default_operator.unwrap_or(ctx.default_operator());
+        let fuzziness = self.fuzziness.or(ctx.default_fuzziness()).unwrap_or(0);
+        check_fuzziness(fuzziness)?;
+        if !(0.0..=1.0).contains(&tie_breaker) {
+            return Err(QueryError::Invalid(format!(
+                "The tie_breaker must be between 0 and 1, got {tie_breaker}"
+            )));
+        }
+
+        let mut boosts = HashMap::new();
+        for (name, boost) in ctx.field_boosts() {
+            if let Ok((field, _)) = ctx.resolve_field(name) {
+                boosts.insert(field, *boost);
+            }
+        }
+
+        let mut fields = Vec::with_capacity(self.fields.len());
+        for name in self.fields.iter() {
+            let (name, boost) = split_field_boost(name)?;
+            let field = ctx.resolve_text_field(name, "query_string")?;
+            if let Some(boost) = boost {
+                check_boost(name, boost)?;
+                boosts.insert(field, boost);
+            }
+            fields.push(field);
+        }
+
+        for (name, boost) in self.boosts.iter() {
+            check_boost(name, *boost)?;
+            let field = ctx.resolve_text_field(name, "query_string")?;
+            boosts.insert(field, *boost);
+        }
+
+        let fuzzy_fields = match (fuzziness, fields.is_empty()) {
+            (0, _) => Vec::new(),
+            (_, true) => ctx.default_fields().to_vec(),
+            (_, false) => fields.clone(),
+        };
+
+        let mut parser = match self.analyzer.as_deref() {
+            Some(analyzer) => ctx.query_parser_with_analyzer(fields, analyzer)?,
+            None => ctx.query_parser(fields),
+        };
+
+        let mut bm25 = HashMap::new();
+        for (name, params) in ctx.field_bm25_params() {
+            if let Ok((field, _)) = ctx.resolve_field(name) {
+                bm25.insert(field, *params);
+            }
+        }
+
+        for (name, params) in self.bm25 {
+            params.validate().map_err(|reason| {
+                QueryError::Invalid(format!(
+                    "BM25 parameters for field {name:?} are invalid: {reason}"
+                ))
+            })?;
+
+            let field = ctx.resolve_text_field(&name, "query_string")?;
+            bm25.insert(field, params);
+        }
+
+        let mut rescored_boosts = HashMap::new();
+        for (field, boost) in boosts {
+            if bm25.contains_key(&field) {
+                rescored_boosts.insert(field, boost);
+            } else {
+                parser.set_field_boost(field, boost);
+            }
+        }
+
+        if default_operator == Operator::And {
+            parser.set_conjunction_by_default();
+        }
+
+        for field in fuzzy_fields {
+            parser.set_field_fuzzy(field, false, fuzziness, true);
+        }
+
+        let query = match ctx.query_synonyms() {
+            Some(synonyms) => expand_query_synonyms(&self.query, synonyms),
+            None => self.query,
+        };
+
+        let query = parser
+            .parse_query(&query)
+            .map_err(|e| QueryError::Invalid(format!("Unable to parse query: {e}")))?;
+
+        let query = if bm25.is_empty() {
+            query
+        } else {
+            rescore_bm25(query, &bm25, &rescored_boosts)
+        };
+
+        let query = match self.multi_field_mode {
+            MultiFieldMode::MostFields => query,
+            MultiFieldMode::BestFields => apply_best_fields(query, tie_breaker),
+        };
+
+        match self.minimum_should_match {
+            Some(minimum) if default_operator == Operator::Or => {
+                Ok(apply_minimum_should_match(query, minimum))
+            },
+            _ => Ok(query),
+        }
+    }
+}
+
+/// Splits a field name from its optional boost suffix, i.e. `title^3`.
+fn split_field_boost(name: &str) -> Result<(&str, Option<f32>), QueryError> {
+    let Some((name, boost)) = name.rsplit_once('^') else {
+        return Ok((name, None));
+    };
+
+    let boost = boost.trim().parse().map_err(|_| {
+        QueryError::Invalid(format!("Invalid boost {boost:?} for field {name:?}"))
+    })?;
+    Ok((name.trim(), Some(boost)))
+}
+
+/// Ensures a fuzziness is within the edit distances supported by the query parser.
+pub(crate) fn check_fuzziness(fuzziness: u8) -> Result<(), QueryError> {
+    if fuzziness <= MAX_FUZZINESS {
+        return Ok(());
+    }
+
+    Err(QueryError::Invalid(format!(
+        "The fuzziness must be between 0 and {MAX_FUZZINESS}, got {fuzziness}"
+    )))
+}
+
+/// Ensures a field boost is a positive number.
+pub(crate) fn check_boost(name: &str, boost: f32) -> Result<(), QueryError> {
+    if boost.is_finite() && boost > 0.0 {
+        return Ok(());
+    }
+
+    Err(QueryError::Invalid(format!(
+        "Boost for field {name:?} must be a positive number, got {boost}"
+    )))
+}
+
+/// Rewrites each term of a parsed query which is expanded across several fields into a
+/// dis-max query, so only the best matching field (plus the other matching fields
+/// multiplied by the tie breaker) contributes to the score.
+fn apply_best_fields(query: Box<dyn Query>, tie_breaker: Score) -> Box<dyn Query> {
+    let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() else {
+        return query;
+    };
+
+    if let Some(disjuncts) = field_disjuncts(boolean_query) {
+        return Box::new(DisjunctionMaxQuery::with_tie_breaker(
+            disjuncts,
+            tie_breaker,
+        ));
+    }
+
+    let clauses = boolean_query
+        .clauses()
+        .iter()
+        .map(|(occur, clause)| {
+            (*occur, apply_best_fields(clause.box_clone(), tie_breaker))
+        })
+        .collect();
+    Box::new(BooleanQuery::new(clauses))
+}
+
+/// Returns the clauses of a boolean query produced by expanding a single term across
+/// several fields, i.e. optional clauses which each target a different field.
+fn field_disjuncts(query: &BooleanQuery) -> Option<Vec<Box<dyn Query>>> {
+    let clauses = query.clauses();
+    if clauses.len() < 2 {
+        return None;
+    }
+
+    let mut fields = HashSet::new();
+    for (occur, clause) in clauses {
+        if *occur != Occur::Should || clause.is::<BooleanQuery>() {
+            return None;
+        }
+
+        let mut clause_fields = HashSet::new();
+        clause.query_terms(&mut |term, _| {
+            clause_fields.insert(term.field());
+        });
+        let field = match (clause_fields.len(), clause_fields.into_iter().next()) {
+            (1, Some(field)) => field,
+            _ => return None,
+        };
+        if !fields.insert(field) {
+            return None;
+        }
+    }
+
+    Some(
+        clauses
+            .iter()
+            .map(|(_, clause)| clause.box_clone())
+            .collect(),
+    )
+}
+
+/// Rewrites the top level optional clauses of a parsed query so that
+/// at least the minimum number of them must match.
+fn apply_minimum_should_match(
+    query: Box<dyn Query>,
+    minimum: MinimumShouldMatch,
+) -> Box<dyn Query> {
+    let Some(boolean_query) = query.downcast_ref::<BooleanQuery>() else {
+        return query;
+    };
+
+    let mut should = Vec::new();
+    let mut clauses = Vec::new();
+    for (occur, clause) in boolean_query.clauses() {
+        if *occur == Occur::Should {
+            should.push(clause.box_clone());
+        } else {
+            clauses.push((*occur, clause.box_clone()));
+        }
+    }
+
+    if should.is_empty() {
+        return query;
+    }
+
+    let required = minimum.resolve(should.len());
+    let should: Box<dyn Query> = Box::new(MinShouldMatchQuery::new(should, required));
+    clauses.push((Occur::Must, should));
+
+    Box::new(BooleanQuery::new(clauses))
+}
+
+/// Deserializes a list of field names from either an array or a comma-separated string.
+pub(crate) fn deserialize_field_list<'de, D>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FieldList {
+        Joined(String),
+        List(Vec<String>),
+    }
+
+    let fields = match FieldList::deserialize(deserializer)? {
+        FieldList::Joined(fields) => fields
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect(),
+        FieldList::List(fields) => fields,
+    };
+
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use lnx_schema::analysis::{SynonymMode, SynonymSet};
+    use tantivy::query::{BoostQuery, TermQuery};
+    use tantivy::schema::{SchemaBuilder, STORED, STRING, TEXT};
+
+    use super::*;
+    use crate::bm25::Bm25TermQuery;
+
+    fn test_context() -> QueryContext {
+        let mut schema = SchemaBuilder::new();
+        schema.add_text_field("title", TEXT);
+        schema.add_text_field("body", TEXT);
+        schema.add_text_field("tag", STRING);
+        schema.add_text_field("stored_only", STORED);
+        QueryContext::new(schema.build())
+    }
+
+    #[test]
+    fn test_field_list_parse() {
+        let query: QueryStringQuery =
+            serde_json::from_str(r#"{"query": "hello", "fields": "title, body"}"#)
+                .unwrap();
+        assert_eq!(query.fields, ["title", "body"]);
+
+        let query: QueryStringQuery =
+            serde_json::from_str(r#"{"query": "hello", "fields": ["title"]}"#).unwrap();
+        assert_eq!(query.fields, ["title"]);
+
+        let query: QueryStringQuery =
+            serde_json::from_str(r#"{"query": "hello"}"#).unwrap();
+        assert!(query.fields.is_empty());
+    }
+
+    #[test]
+    fn test_query_string_build() {
+        let ctx = test_context();
+
+        let query = QueryStringQuery {
+            query: "hello world".to_string(),
+            ..Default::default()
+        };
+        assert!(query.build(&ctx).is_ok());
+
+        let query = QueryStringQuery {
+            query: "hello world".to_string(),
+            fields: vec!["title".to_string()],
+            boosts: BTreeMap::from_iter([("title".to_string(), 2.0)]),
+            ..Default::default()
+        };
+        assert!(query.build(&ctx).is_ok());
+
+        let query = QueryStringQuery {
+            query: "hello world".to_string(),
+            fields: vec!["stored_only".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+
+        let query = QueryStringQuery {
+            query: "hello world".to_string(),
+            boosts: BTreeMap::from_iter([("title".to_string(), -1.0)]),
+            ..Default::default()
+        };
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+
+        let query = QueryStringQuery {
+            query: "hello world".to_string(),
+            default_operator: Some(Operator::And),
+            ..Default::default()
+        };
+        assert!(query.build(&ctx).is_ok());
+    }
+
+    #[test]
+    fn test_field_boosts() {
+        let query: QueryStringQuery =
+            serde_json::from_str(r#"{"query": "hello", "fields": "title^3, body"}"#)
+                .unwrap();
+        assert_eq!(query.fields, ["title^3", "body"]);
+        assert_eq!(split_field_boost("title^3").unwrap(), ("title", Some(3.0)));
+        assert_eq!(split_field_boost("body").unwrap(), ("body", None));
+        assert!(split_field_boost("title^x").is_err());
+
+        let ctx = test_context().with_field_boost("body", 0.5);
+        let query = query.build(&ctx).unwrap();
+        let query = query.downcast_ref::<BooleanQuery>().unwrap();
+        assert!(query
+            .clauses()
+            .iter()
+            .all(|(_, clause)| clause.is::<BoostQuery>()));
+
+        let query = QueryStringQuery {
+            query: "hello".to_string(),
+            fields: vec!["title^0".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_analyzer_override() {
+        let ctx = test_context();
+
+        let query = QueryStringQuery {
+            query: "title:\"Hello World\"".to_string(),
+            analyzer: Some("raw".to_string()),
+            ..Default::default()
+        };
+        let query = query.build(&ctx).unwrap();
+        let query = query.downcast_ref::<TermQuery>().unwrap();
+        assert_eq!(query.term().as_str(), Some("Hello World"));
+
+        let query = QueryStringQuery {
+            query: "hello".to_string(),
+            analyzer: Some("missing".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_query_synonyms() {
+        let set = SynonymSet {
+            rules: vec!["ny, new york".to_string()],
+            mode: SynonymMode::Query,
+            ..Default::default()
+        };
+        let ctx =
+            test_context().with_query_synonyms(Arc::new(set.load("cities").unwrap()));
+
+        let query = QueryStringQuery {
+            query: "ny".to_string(),
+            fields: vec!["title".to_string()],
+            ..Default::default()
+        };
+        let query = query.build(&ctx).unwrap();
+        assert!(format!("{query:?}").contains("PhraseQuery"));
+    }
+
+    #[test]
+    fn test_bm25_params() {
+        let ctx =
+            test_context().with_field_bm25("title", Bm25Params { k1: 1.2, b: 0.2 });
+
+        let query = QueryStringQuery {
+            query: "hello".to_string(),
+            fields: vec!["title".to_string()],
+            ..Default::default()
+        };
+        let query = query.build(&ctx).unwrap();
+        assert!(query.is::<Bm25TermQuery>());
+
+        let query: QueryStringQuery = serde_json::from_str(
+            r#"{"query": "hello", "fields": "body", "bm25": {"body": {"k1": 2.0}}}"#,
+        )
+        .unwrap();
+        let query = query.build(&ctx).unwrap();
+        assert!(query.is::<Bm25TermQuery>());
+
+        let query: QueryStringQuery =
+            serde_json::from_str(r#"{"query": "hello", "bm25": {"body": {"b": 2.0}}}"#)
+                .unwrap();
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_multi_field_mode() {
+        let ctx = test_context();
+
+        let query: QueryStringQuery =
+            serde_json::from_str(r#"{"query": "hello", "fields": "title, body"}"#)
+                .unwrap();
+        let query = query.build(&ctx).unwrap();
+        assert!(query.is::<BooleanQuery>());
+
+        let query: QueryStringQuery = serde_json::from_str(
+            r#"{
+                "query": "hello",
+                "fields": "title, body",
+                "multi_field_mode": "best_fields",
+                "tie_breaker": 0.3
+            }"#,
+        )
+        .unwrap();
+        let query = query.build(&ctx).unwrap();
+        assert!(query.is::<DisjunctionMaxQuery>());
+
+        // Each term is rewritten separately, terms on a single field are left as-is.
+        let query: QueryStringQuery = serde_json::from_str(
+            r#"{
+                "query": "hello world title:rust",
+                "fields": "title, body",
+                "multi_field_mode": "best_fields"
+            }"#,
+        )
+        .unwrap();
+        let query = query.build(&ctx).unwrap();
+        let query = query.downcast_ref::<BooleanQuery>().unwrap();
+        let kinds = query
+            .clauses()
+            .iter()
+            .map(|(_, clause)| clause.is::<DisjunctionMaxQuery>())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [true, true, false]);
+
+        let query: QueryStringQuery = serde_json::from_str(
+            r#"{"query": "hello", "multi_field_mode": "best_fields", "tie_breaker": 2}"#,
+        )
+        .unwrap();
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_minimum_should_match_rewrite() {
+        let ctx = test_context();
+
+        let query: QueryStringQuery = serde_json::from_str(
+            r#"{"query": "quick brown fox", "fields": "title", "minimum_should_match": "66%"}"#,
+        )
+        .unwrap();
+        assert_eq!(query.default_operator, None);
+
+        let query = query.build(&ctx).unwrap();
+        let query = query.downcast_ref::<BooleanQuery>().unwrap();
+        assert_eq!(query.clauses().len(), 1);
+        assert_eq!(query.clauses()[0].0, Occur::Must);
+        assert!(query.clauses()[0].1.is::<MinShouldMatchQuery>());
+    }
+}
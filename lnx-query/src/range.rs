@@ -0,0 +1,373 @@
+use std::ops::Bound;
+
+use lnx_document::{UserDisplayType, Value};
+use serde::Deserialize;
+use tantivy::query::{Query, RangeQuery as TantivyRangeQuery};
+use tantivy::schema::{FieldEntry, FieldType, Type};
+use tantivy::Term;
+
+use crate::context::QueryContext;
+use crate::date_math::{is_date_math, resolve_date_math};
+use crate::error::QueryError;
+
+#[derive(Debug, Default, Deserialize)]
+/// Matches documents where the value of a field falls within a given range.
+///
+/// The range is executed against the field's fast field, which means
+/// the field must be a `fast` field of type `u64`, `i64`, `f64`, `datetime` or `ip`.
+pub struct RangeQuery<'a> {
+    /// The field to apply the range to.
+    pub field: String,
+    #[serde(default, borrow)]
+    /// Values must be greater than this value.
+    pub gt: Option<Value<'a>>,
+    #[serde(default, borrow)]
+    /// Values must be greater than or equal to this value.
+    pub gte: Option<Value<'a>>,
+    #[serde(default, borrow)]
+    /// Values must be less than this value.
+    pub lt: Option<Value<'a>>,
+    #[serde(default, borrow)]
+    /// Values must be less than or equal to this value.
+    pub lte: Option<Value<'a>>,
+}
+
+impl<'a> RangeQuery<'a> {
+    /// Compiles the range into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let (field, entry) = ctx.resolve_field(&self.field)?;
+
+        if !entry.is_fast() {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "range",
+                "the field is not a fast field",
+            ));
+        }
+
+        let lower = resolve_bound(ctx, entry, "gt", self.gt, "gte", self.gte)?;
+        let upper = resolve_bound(ctx, entry, "lt", self.lt, "lte", self.lte)?;
+
+        if matches!((&lower, &upper), (Bound::Unbounded, Bound::Unbounded)) {
+            return Err(QueryError::Invalid(format!(
+                "Range query on field {:?} must specify at least one bound",
+                self.field,
+            )));
+        }
+
+        // tantivy resolves the field by name again, so the resolved name of the
+        // field is used rather than an alias.
+        let name = entry.name().to_string();
+        let query = match entry.field_type() {
+            FieldType::U64(_) => TantivyRangeQuery::new_u64_bounds(
+                name,
+                map_bound(lower, as_u64),
+                map_bound(upper, as_u64),
+            ),
+            FieldType::I64(_) => TantivyRangeQuery::new_i64_bounds(
+                name,
+                map_bound(lower, as_i64),
+                map_bound(upper, as_i64),
+            ),
+            FieldType::F64(_) => TantivyRangeQuery::new_f64_bounds(
+                name,
+                map_bound(lower, as_f64),
+                map_bound(upper, as_f64),
+            ),
+            FieldType::Date(_) => TantivyRangeQuery::new_date_bounds(
+                name,
+                map_bound(lower, as_datetime),
+                map_bound(upper, as_datetime),
+            ),
+            FieldType::IpAddr(_) => {
+                let to_term = |value: Value| {
+                    let Value::IpAddr(ip) = value else {
+                        unreachable!()
+                    };
+                    Term::from_field_ip_addr(field, ip)
+                };
+                TantivyRangeQuery::new_term_bounds(
+                    name,
+                    Type::IpAddr,
+                    &map_bound(lower, to_term),
+                    &map_bound(upper, to_term),
+                )
+            },
+            other => {
+                return Err(QueryError::unsupported(
+                    &self.field,
+                    "range",
+                    format!(
+                        "fields of type {:?} do not support ranges",
+                        other.value_type()
+                    ),
+                ))
+            },
+        };
+
+        Ok(Box::new(query))
+    }
+}
+
+/// Resolves the exclusive and inclusive variants of a bound into a single bound.
+///
+/// Values are cast to the type of the field as part of this step.
+///
+/// Date math expressions on `datetime` fields, i.e. `now-7d/d`, are resolved against
+/// the context's `now`. Rounding goes up for `gt` and `lte` bounds and down for
+/// `gte` and `lt` bounds so the entire rounded unit is excluded or included.
+fn resolve_bound<'a>(
+    ctx: &QueryContext,
+    entry: &FieldEntry,
+    exclusive_name: &str,
+    exclusive: Option<Value<'a>>,
+    inclusive_name: &str,
+    inclusive: Option<Value<'a>>,
+) -> Result<Bound<Value<'a>>, QueryError> {
+    match (exclusive, inclusive) {
+        (Some(_), Some(_)) => Err(QueryError::Invalid(format!(
+            "Range query on field {:?} cannot specify both `{exclusive_name}` and `{inclusive_name}`",
+            entry.name(),
+        ))),
+        (Some(value), None) => {
+            cast_bound_value(ctx, entry, exclusive_name, value).map(Bound::Excluded)
+        },
+        (None, Some(value)) => {
+            cast_bound_value(ctx, entry, inclusive_name, value).map(Bound::Included)
+        },
+        (None, None) => Ok(Bound::Unbounded),
+    }
+}
+
+fn cast_bound_value<'a>(
+    ctx: &QueryContext,
+    entry: &FieldEntry,
+    name: &str,
+    value: Value<'a>,
+) -> Result<Value<'a>, QueryError> {
+    if matches!(value, Value::Null | Value::Array(_) | Value::Object(_)) {
+        return Err(QueryError::invalid_value(
+            entry.name(),
+            format!(
+                "Range bound `{name}` must be a single value, got {}",
+                value.type_name(),
+            ),
+        ));
+    }
+
+    match (entry.field_type(), &value) {
+        (FieldType::Date(_), Value::Str(expr)) if is_date_math(expr) => {
+            let round_up = matches!(name, "gt" | "lte");
+            resolve_date_math(ctx, expr, round_up)
+                .map(Value::DateTime)
+                .map_err(|e| QueryError::invalid_value(entry.name(), e))
+        },
+        _ => ctx.cast_value(entry, value),
+    }
+}
+
+fn map_bound<T, O>(bound: Bound<T>, cb: impl Fn(T) -> O) -> Bound<O> {
+    match bound {
+        Bound::Included(v) => Bound::Included(cb(v)),
+        Bound::Excluded(v) => Bound::Excluded(cb(v)),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+// The following helpers are only ever called on values which have
+// already been cast to the field's type by the `QueryContext`.
+
+fn as_u64(value: Value) -> u64 {
+    let Value::U64(v) = value else { unreachable!() };
+    v
+}
+
+fn as_i64(value: Value) -> i64 {
+    let Value::I64(v) = value else { unreachable!() };
+    v
+}
+
+fn as_f64(value: Value) -> f64 {
+    let Value::F64(v) = value else { unreachable!() };
+    v
+}
+
+fn as_datetime(value: Value) -> tantivy::DateTime {
+    let Value::DateTime(v) = value else {
+        unreachable!()
+    };
+    v.as_tantivy_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use tantivy::collector::Count;
+    use tantivy::schema::{SchemaBuilder, FAST, INDEXED, STRING};
+    use tantivy::{Document, Index};
+
+    use super::*;
+
+    fn test_context() -> QueryContext {
+        let mut schema = SchemaBuilder::new();
+        schema.add_u64_field("count", FAST | INDEXED);
+        schema.add_i64_field("not_fast", INDEXED);
+        schema.add_date_field("created_at", FAST | INDEXED);
+        schema.add_text_field("name", STRING | FAST);
+        QueryContext::new(schema.build())
+    }
+
+    #[test]
+    fn test_range_parse() {
+        let query: RangeQuery =
+            serde_json::from_str(r#"{"field": "count", "gte": 5, "lt": 10}"#).unwrap();
+        assert_eq!(query.field, "count");
+        assert_eq!(query.gte, Some(Value::U64(5)));
+        assert_eq!(query.lt, Some(Value::U64(10)));
+        assert!(query.gt.is_none());
+        assert!(query.lte.is_none());
+
+        let json = r#"{"field": "created_at", "gt": "now-1d"}"#;
+        let query: RangeQuery = serde_json::from_str(json).unwrap();
+        assert!(matches!(query.gt, Some(Value::Str(Cow::Borrowed("now-1d")))));
+    }
+
+    #[test]
+    fn test_range_build() {
+        let ctx = test_context();
+
+        let query = RangeQuery {
+            field: "count".to_string(),
+            gte: Some(Value::U64(5)),
+            ..Default::default()
+        };
+        assert!(query.build(&ctx).is_ok());
+
+        let query = RangeQuery {
+            field: "created_at".to_string(),
+            gt: Some(Value::from("2002-10-02T15:00:00Z")),
+            lte: Some(Value::I64(1_033_570_900)),
+            ..Default::default()
+        };
+        assert!(query.build(&ctx).is_ok());
+
+        let query = RangeQuery {
+            field: "created_at".to_string(),
+            gte: Some(Value::from("now-7d/d")),
+            lt: Some(Value::from("now/d")),
+            ..Default::default()
+        };
+        assert!(query.build(&ctx).is_ok());
+
+        let query = RangeQuery {
+            field: "created_at".to_string(),
+            gte: Some(Value::from("now-7x")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_range_errors() {
+        let ctx = test_context();
+
+        let query = RangeQuery {
+            field: "count".to_string(),
+            ..Default::default()
+        };
+        let err = query.build(&ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid query: Range query on field \"count\" must specify at least one bound",
+        );
+
+        let query = RangeQuery {
+            field: "count".to_string(),
+            gt: Some(Value::U64(1)),
+            gte: Some(Value::U64(1)),
+            ..Default::default()
+        };
+        let err = query.build(&ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid query: Range query on field \"count\" cannot specify both `gt` and `gte`",
+        );
+
+        let query = RangeQuery {
+            field: "count".to_string(),
+            gt: Some(Value::I64(-1)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::InvalidValue { .. })
+        ));
+
+        let query = RangeQuery {
+            field: "not_fast".to_string(),
+            gt: Some(Value::I64(-1)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+
+        let query = RangeQuery {
+            field: "name".to_string(),
+            gt: Some(Value::from("a")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+
+        let query: RangeQuery =
+            serde_json::from_str(r#"{"field": "count", "gte": [1]}"#).unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::InvalidValue { .. })
+        ));
+
+        let query = RangeQuery {
+            field: "missing".to_string(),
+            gt: Some(Value::U64(1)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnknownField(_))
+        ));
+    }
+
+    #[test]
+    fn test_range_alias_search() {
+        let mut schema = SchemaBuilder::new();
+        let count = schema.add_u64_field("count", FAST | INDEXED);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for value in [1, 5, 10] {
+            let mut doc = Document::default();
+            doc.add_u64(count, value);
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(index.schema()).with_field_alias("total", "count");
+        let query = RangeQuery {
+            field: "total".to_string(),
+            gte: Some(Value::U64(5)),
+            ..Default::default()
+        };
+        let query = query.build(&ctx).unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        assert_eq!(searcher.search(&query, &Count).unwrap(), 2);
+    }
+}
@@ -0,0 +1,150 @@
+use serde::Deserialize;
+use tantivy::query::{Query, RegexQuery as TantivyRegexQuery};
+use tantivy_fst::Regex;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+/// Characters which have a special meaning within a regex pattern.
+const REGEX_META_CHARACTERS: &str = r"\.+*?()|[]{}^$#&-~";
+
+#[derive(Debug, Deserialize)]
+/// Matches documents containing a term which matches the given regex pattern.
+///
+/// The pattern must match the entire term, i.e. `foo` will not match `foobar`
+/// but `foo.*` will.
+pub struct RegexQuery {
+    /// The text field to match terms on.
+    pub field: String,
+    /// The regex pattern to match terms against.
+    pub pattern: String,
+}
+
+impl RegexQuery {
+    /// Compiles the regex into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        build_regex_query(ctx, &self.field, "regex", &self.pattern)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches documents containing a term which matches the given wildcard pattern.
+///
+/// A `*` matches any sequence of characters (including none) and a `?` matches
+/// exactly one character, i.e. `prefix*`, `*suffix` and `mid*dle` are all valid patterns.
+/// All other characters are matched literally.
+pub struct WildcardQuery {
+    /// The text field to match terms on.
+    pub field: String,
+    /// The wildcard pattern to match terms against.
+    pub pattern: String,
+}
+
+impl WildcardQuery {
+    /// Compiles the wildcard pattern into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let pattern = wildcard_to_regex(&self.pattern);
+        build_regex_query(ctx, &self.field, "wildcard", &pattern)
+    }
+}
+
+fn build_regex_query(
+    ctx: &QueryContext,
+    field_name: &str,
+    kind: &'static str,
+    pattern: &str,
+) -> Result<Box<dyn Query>, QueryError> {
+    let field = ctx.resolve_text_field(field_name, kind)?;
+
+    let regex = compile_regex(ctx.regex_size_limit(), pattern).map_err(|e| {
+        let msg = format!(
+            "Unable to compile {kind} pattern {pattern:?} for field {field_name:?}: {e}"
+        );
+        QueryError::Invalid(msg)
+    })?;
+
+    Ok(Box::new(TantivyRegexQuery::from_regex(regex, field)))
+}
+
+/// Compiles the pattern into an automaton, rejecting it if the compiled
+/// program would exceed `size_limit` bytes.
+///
+/// The size limit prevents pathological patterns from consuming large amounts
+/// of memory and CPU time. tantivy-fst does not expose a configurable limit, so the
+/// pattern is first compiled with the `regex` crate which enforces the limit on its
+/// own program before the automaton is built.
+///
+/// The limit is therefore approximate, the size of the `regex` program is only a
+/// proxy for the size of the DFA tantivy-fst builds. Patterns whose DFA explodes
+/// despite a small program, i.e. `[ab]*a[ab]{20}`, are still rejected by
+/// tantivy-fst's own fixed limit on the number of DFA states.
+pub(crate) fn compile_regex(size_limit: usize, pattern: &str) -> Result<Regex, String> {
+    ::regex::RegexBuilder::new(pattern)
+        .size_limit(size_limit)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    Regex::new(pattern).map_err(|e| e.to_string())
+}
+
+/// Converts a wildcard pattern into the equivalent regex pattern.
+fn wildcard_to_regex(pattern: &str) -> String {
+    let mut regex = String::with_capacity(pattern.len() * 2);
+
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => push_escaped(&mut regex, c),
+        }
+    }
+
+    regex
+}
+
+/// Pushes a character onto the regex pattern, escaping it if it is a meta character.
+pub(crate) fn push_escaped(regex: &mut String, c: char) {
+    if REGEX_META_CHARACTERS.contains(c) {
+        regex.push('\\');
+    }
+    regex.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_REGEX_SIZE_LIMIT;
+
+    #[test]
+    fn test_wildcard_to_regex() {
+        assert_eq!(wildcard_to_regex("prefix*"), "prefix.*");
+        assert_eq!(wildcard_to_regex("*suffix"), ".*suffix");
+        assert_eq!(wildcard_to_regex("mid*dle"), "mid.*dle");
+        assert_eq!(wildcard_to_regex("h?llo"), "h.llo");
+        assert_eq!(wildcard_to_regex("a.b+c"), "a\\.b\\+c");
+        assert_eq!(wildcard_to_regex("[x]"), "\\[x\\]");
+    }
+
+    #[test]
+    fn test_compile_regex_size_limit() {
+        assert!(compile_regex(DEFAULT_REGEX_SIZE_LIMIT, "foo.*").is_ok());
+        assert!(compile_regex(DEFAULT_REGEX_SIZE_LIMIT, "[a-z]{3}").is_ok());
+
+        let err = compile_regex(DEFAULT_REGEX_SIZE_LIMIT, r"\w{1000}").unwrap_err();
+        assert!(err.contains("size limit"), "unexpected error: {err}");
+
+        assert!(compile_regex(1 << 10, "[a-z]{100}").is_err());
+    }
+
+    #[test]
+    fn test_compile_regex_dfa_state_limit() {
+        let pattern = "[ab]*a[ab]{20}";
+        assert!(::regex::RegexBuilder::new(pattern)
+            .size_limit(DEFAULT_REGEX_SIZE_LIMIT)
+            .build()
+            .is_ok());
+
+        let err = compile_regex(DEFAULT_REGEX_SIZE_LIMIT, pattern).unwrap_err();
+        assert!(err.contains("states"), "unexpected error: {err}");
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use tantivy::collector::TopDocs;
+use tantivy::query::{EnableScoring, Query};
+use tantivy::{DocAddress, DocId, DocSet, Score, Searcher, SegmentOrdinal};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::ltr::{LtrRescore, LtrScorer};
+use crate::query::QueryKind;
+
+/// The default number of top hits rescored by the second phase of a search.
+pub const DEFAULT_RESCORE_WINDOW: usize = 100;
+/// The maximum number of top hits a search may rescore.
+pub const MAX_RESCORE_WINDOW: usize = 10_000;
+
+fn default_window_size() -> usize {
+    DEFAULT_RESCORE_WINDOW
+}
+
+fn default_weight() -> Score {
+    1.0
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the original score of a hit is combined with its rescore query score.
+pub enum RescoreMode {
+    #[default]
+    /// The weighted scores are added together.
+    Total,
+    /// The weighted scores are multiplied together.
+    Multiply,
+    /// The average of the weighted scores.
+    Avg,
+    /// The highest of the weighted scores.
+    Max,
+    /// The lowest of the weighted scores.
+    Min,
+}
+
+impl RescoreMode {
+    fn combine(&self, score: Score, rescore: Score) -> Score {
+        match self {
+            Self::Total => score + rescore,
+            Self::Multiply => score * rescore,
+            Self::Avg => (score + rescore) / 2.0,
+            Self::Max => score.max(rescore),
+            Self::Min => score.min(rescore),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A second search phase which rescores the top hits of the main query with a more
+/// expensive query, i.e. a phrase proximity or `function_score` query, before the
+/// hits are paginated.
+///
+/// Only the top `window_size` hits are rescored, so the rescore query is never run
+/// against the entire index. Hits which do not match the rescore query keep their
+/// weighted original score.
+///
+/// Exactly one of `query` or `ltr` must be set.
+pub struct RescoreRequest<'a> {
+    #[serde(default = "default_window_size")]
+    /// The number of top hits from the main query to rescore.
+    ///
+    /// Defaults to `100`.
+    pub window_size: usize,
+    #[serde(default, borrow)]
+    /// The query used to rescore the hits.
+    pub query: Option<QueryKind<'a>>,
+    #[serde(default)]
+    /// The learning to rank model used to rescore the hits.
+    pub ltr: Option<LtrRescore>,
+    #[serde(default = "default_weight")]
+    /// The weight of the original score of each hit.
+    pub query_weight: Score,
+    #[serde(default = "default_weight")]
+    /// The weight of the rescore query's score of each hit.
+    pub rescore_query_weight: Score,
+    #[serde(default)]
+    /// How the weighted scores are combined.
+    pub score_mode: RescoreMode,
+}
+
+impl<'a> RescoreRequest<'a> {
+    /// Compiles the rescore query into a [Rescorer].
+    pub fn build(self, ctx: &QueryContext) -> Result<Rescorer, QueryError> {
+        if self.window_size == 0 || self.window_size > MAX_RESCORE_WINDOW {
+            return Err(QueryError::Invalid(format!(
+                "The rescore `window_size` must be between 1 and {MAX_RESCORE_WINDOW}, got {}",
+                self.window_size
+            )));
+        }
+
+        for (name, weight) in [
+            ("query_weight", self.query_weight),
+            ("rescore_query_weight", self.rescore_query_weight),
+        ] {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(QueryError::Invalid(format!(
+                    "The rescore `{name}` must be a non-negative number, got {weight}"
+                )));
+            }
+        }
+
+        let scorer = match (self.query, self.ltr) {
+            (Some(query), None) => RescoreScorer::Query(query.build(ctx)?),
+            (None, Some(ltr)) => RescoreScorer::Model(ltr.build(ctx)?),
+            _ => {
+                return Err(QueryError::Invalid(
+                    "Exactly one of the rescore `query` or `ltr` must be set"
+                        .to_string(),
+                ))
+            },
+        };
+
+        Ok(Rescorer {
+            scorer,
+            window_size: self.window_size,
+            query_weight: self.query_weight,
+            rescore_query_weight: self.rescore_query_weight,
+            score_mode: self.score_mode,
+        })
+    }
+}
+
+/// How the hits within the rescore window are scored.
+enum RescoreScorer {
+    Query(Box<dyn Query>),
+    Model(LtrScorer),
+}
+
+/// Rescores the top hits of a search with a compiled rescore query or model.
+///
+/// Searching with a rescorer happens in two phases, the candidates are first collected
+/// via [Rescorer::collector] and then rescored via [Rescorer::rescore] before any
+/// pagination is applied.
+pub struct Rescorer {
+    scorer: RescoreScorer,
+    window_size: usize,
+    query_weight: Score,
+    rescore_query_weight: Score,
+    score_mode: RescoreMode,
+}
+
+impl Rescorer {
+    #[inline]
+    /// The number of top hits which are rescored.
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Creates the collector gathering the candidates of the first phase.
+    ///
+    /// At least `limit` hits are collected so a window smaller than the requested
+    /// page still returns a full page, only the top `window_size` are rescored.
+    pub fn collector(&self, limit: usize) -> TopDocs {
+        TopDocs::with_limit(self.window_size.max(limit).max(1))
+    }
+
+    /// Rescores the top `window_size` hits, returning the hits sorted by their new
+    /// score followed by any hits outside of the window in their original order.
+    pub fn rescore(
+        &self,
+        searcher: &Searcher,
+        mut hits: Vec<(Score, DocAddress)>,
+    ) -> tantivy::Result<Vec<(Score, DocAddress)>> {
+        let remaining = hits.split_off(self.window_size.min(hits.len()));
+
+        let rescores = match &self.scorer {
+            RescoreScorer::Query(query) => {
+                query_scores(searcher, query.as_ref(), &hits)?
+            },
+            RescoreScorer::Model(model) => model.score_hits(searcher, &hits)?,
+        };
+
+        let mut rescored = hits
+            .into_iter()
+            .zip(rescores)
+            .map(|((score, address), rescore)| {
+                let score = score * self.query_weight;
+                let score = match rescore {
+                    Some(rescore) => self
+                        .score_mode
+                        .combine(score, rescore * self.rescore_query_weight),
+                    None => score,
+                };
+                (score, address)
+            })
+            .collect::<Vec<_>>();
+
+        rescored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        rescored.extend(remaining);
+        Ok(rescored)
+    }
+}
+
+/// Scores each hit against the query, `None` if the hit does not match.
+pub(crate) fn query_scores(
+    searcher: &Searcher,
+    query: &dyn Query,
+    hits: &[(Score, DocAddress)],
+) -> tantivy::Result<Vec<Option<Score>>> {
+    let mut segments: BTreeMap<SegmentOrdinal, Vec<(DocId, usize)>> = BTreeMap::new();
+    for (pos, (_, address)) in hits.iter().enumerate() {
+        segments
+            .entry(address.segment_ord)
+            .or_default()
+            .push((address.doc_id, pos));
+    }
+
+    let mut scores = vec![None; hits.len()];
+    let weight = query.weight(EnableScoring::enabled_from_searcher(searcher))?;
+    for (segment_ord, mut docs) in segments {
+        let reader = searcher.segment_reader(segment_ord);
+        let mut scorer = weight.scorer(reader, 1.0)?;
+
+        // Scorers can only move forwards, so docs must be visited in order.
+        docs.sort_unstable();
+        for (doc, pos) in docs {
+            if scorer.doc() < doc {
+                scorer.seek(doc);
+            }
+            if scorer.doc() == doc {
+                scores[pos] = Some(scorer.score());
+            }
+        }
+    }
+
+    Ok(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+    use crate::search::SearchRequest;
+
+    #[test]
+    fn test_rescore() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "shoes running running running"))
+            .unwrap();
+        writer.add_document(doc!(title => "running shoes")).unwrap();
+        writer.add_document(doc!(title => "shoes")).unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+
+        let mut request: SearchRequest = serde_json::from_str(
+            r#"{
+                "query": {"query_string": {"query": "running shoes"}},
+                "rescore": {
+                    "window_size": 2,
+                    "query": {"query_string": {"query": "\"running shoes\""}},
+                    "rescore_query_weight": 10
+                }
+            }"#,
+        )
+        .unwrap();
+        let rescorer = request.build_rescorer(&ctx).unwrap().unwrap();
+        let query = request.build_query(&ctx).unwrap();
+
+        let hits = searcher.search(&query, &rescorer.collector(3)).unwrap();
+        assert_eq!(hits[0].1.doc_id, 0);
+
+        // Only the exact phrase matches the rescore query and the third hit falls
+        // outside of the window, keeping its position.
+        let rescored = rescorer.rescore(&searcher, hits.clone()).unwrap();
+        assert_eq!(rescored[0].1.doc_id, 1);
+        assert_eq!(rescored[1], hits[0]);
+        assert_eq!(rescored[2], hits[2]);
+
+        let request: RescoreRequest = serde_json::from_str(
+            r#"{"window_size": 0, "query": {"query_string": {"query": "shoes"}}}"#,
+        )
+        .unwrap();
+        assert!(matches!(request.build(&ctx), Err(QueryError::Invalid(_))));
+
+        let request: RescoreRequest =
+            serde_json::from_str(r#"{"window_size": 10}"#).unwrap();
+        assert!(matches!(request.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+}
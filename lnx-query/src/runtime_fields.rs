@@ -0,0 +1,984 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::CharIndices;
+use std::sync::Arc;
+
+use lnx_document::{DateTime, Value};
+use serde::Deserialize;
+use tantivy::collector::{Collector, SegmentCollector, TopDocs};
+use tantivy::{DocAddress, DocId, Score, Searcher, SegmentOrdinal, SegmentReader};
+use time::format_description::well_known;
+
+use crate::column::{FastColumn, SegmentColumn};
+use crate::context::QueryContext;
+use crate::date_math::{truncate_datetime, Unit};
+use crate::error::QueryError;
+
+/// The maximum length (in bytes) of a runtime field or script score expression.
+pub const MAX_EXPRESSION_LENGTH: usize = 4096;
+/// The maximum nesting depth of an expression, i.e. parentheses and function calls.
+const MAX_EXPRESSION_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A field computed at query time from the fast fields of each document.
+///
+/// The expression supports number, string and field literals, the arithmetic
+/// operators `+`, `-`, `*`, `/` and `%`, the functions `concat(a, b, ...)` and
+/// `date_trunc('day', field)`, and the math functions listed by [MathFunction].
+/// Using `+` with a string concatenates the values.
+///
+/// Fields which are missing a value, type mismatches, division by zero and math
+/// functions producing a non-finite number evaluate to `null` rather than failing
+/// the search.
+pub struct RuntimeField {
+    /// The expression used to compute the value of the field.
+    pub expression: String,
+}
+
+impl RuntimeField {
+    /// Parses the expression and resolves the fast fields it references.
+    pub fn compile(
+        &self,
+        name: &str,
+        ctx: &QueryContext,
+    ) -> Result<RuntimeExpression, QueryError> {
+        if ctx.schema().get_field(name).is_ok() {
+            return Err(QueryError::Invalid(format!(
+                "Runtime field {name:?} cannot have the same name as a schema field"
+            )));
+        }
+
+        RuntimeExpression::parse(ctx, name, &self.expression, false).map_err(|reason| {
+            QueryError::Invalid(format!("Invalid runtime field {name:?}: {reason}"))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The value of a runtime field for a single document.
+pub enum RuntimeValue {
+    /// The expression has no value for the document.
+    Null,
+    /// A boolean value.
+    Bool(bool),
+    /// A signed integer value.
+    I64(i64),
+    /// A float value.
+    F64(f64),
+    /// A string value.
+    Str(String),
+    /// A datetime value.
+    DateTime(DateTime),
+}
+
+impl RuntimeValue {
+    /// The numeric value used to sort and aggregate on the runtime field.
+    ///
+    /// `datetime` values are represented as their timestamp in microseconds.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::I64(v) => Some(*v as f64),
+            Self::F64(v) => Some(*v),
+            Self::DateTime(dt) => Some(dt.as_micros() as f64),
+            _ => None,
+        }
+    }
+
+    /// Converts the value into a document value so it can be returned with a hit.
+    pub fn into_value(self) -> Value<'static> {
+        match self {
+            Self::Null => Value::Null,
+            Self::Bool(v) => Value::Bool(v),
+            Self::I64(v) => Value::I64(v),
+            Self::F64(v) => Value::F64(v),
+            Self::Str(v) => Value::Str(Cow::Owned(v)),
+            Self::DateTime(v) => Value::DateTime(v),
+        }
+    }
+
+    fn to_text(&self) -> Option<String> {
+        match self {
+            Self::Null => None,
+            Self::Bool(v) => Some(v.to_string()),
+            Self::I64(v) => Some(v.to_string()),
+            Self::F64(v) => Some(v.to_string()),
+            Self::Str(v) => Some(v.clone()),
+            Self::DateTime(v) => v.format(&well_known::Rfc3339).ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A compiled runtime field which can be evaluated against the documents of a searcher.
+pub struct RuntimeExpression {
+    name: String,
+    expr: Arc<Expr>,
+    fields: Arc<Vec<FastColumn>>,
+}
+
+impl RuntimeExpression {
+    /// Parses an expression and resolves the fast fields it references.
+    ///
+    /// The `_score` of a document can only be referenced if `allow_score` is `true`.
+    pub(crate) fn parse(
+        ctx: &QueryContext,
+        name: &str,
+        expression: &str,
+        allow_score: bool,
+    ) -> Result<Self, String> {
+        if expression.len() > MAX_EXPRESSION_LENGTH {
+            return Err(format!(
+                "the expression exceeds the maximum length of {MAX_EXPRESSION_LENGTH} bytes"
+            ));
+        }
+
+        let mut parser = Parser {
+            ctx,
+            source: expression,
+            chars: expression.char_indices().peekable(),
+            fields: Vec::new(),
+            allow_score,
+            depth: 0,
+        };
+        let expr = parser.parse()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            expr: Arc::new(expr),
+            fields: Arc::new(parser.fields),
+        })
+    }
+
+    #[inline]
+    /// The name of the runtime field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Opens the fast field columns referenced by the expression for a segment.
+    ///
+    /// Fields without a column in the segment evaluate to `null`.
+    pub fn for_segment(&self, reader: &SegmentReader) -> RuntimeSegmentEvaluator {
+        let columns = self
+            .fields
+            .iter()
+            .map(|column| column.open(reader))
+            .collect();
+
+        RuntimeSegmentEvaluator {
+            expr: self.expr.clone(),
+            columns,
+        }
+    }
+
+    /// Evaluates the runtime field for each of the given documents, i.e. the hits
+    /// being returned by a search.
+    pub fn evaluate_docs(
+        &self,
+        searcher: &Searcher,
+        docs: &[DocAddress],
+    ) -> Vec<RuntimeValue> {
+        let mut evaluators = HashMap::new();
+
+        docs.iter()
+            .map(|address| {
+                let evaluator =
+                    evaluators.entry(address.segment_ord).or_insert_with(|| {
+                        self.for_segment(searcher.segment_reader(address.segment_ord))
+                    });
+                evaluator.evaluate(address.doc_id)
+            })
+            .collect()
+    }
+
+    /// Creates a collector returning the top documents sorted by the runtime field.
+    ///
+    /// Only numeric and `datetime` values can be sorted on, documents with any other
+    /// value are sorted last regardless of the order.
+    pub fn top_docs(
+        &self,
+        limit: usize,
+        descending: bool,
+    ) -> impl Collector<Fruit = Vec<(f64, DocAddress)>> {
+        let expression = self.clone();
+        TopDocs::with_limit(limit).custom_score(move |reader: &SegmentReader| {
+            let evaluator = expression.for_segment(reader);
+            move |doc: DocId| match evaluator.evaluate(doc).as_f64() {
+                Some(v) if descending => v,
+                Some(v) => -v,
+                None => f64::NEG_INFINITY,
+            }
+        })
+    }
+
+    /// Creates a collector aggregating the numeric values of the runtime field.
+    pub fn stats(&self) -> RuntimeStatsCollector {
+        RuntimeStatsCollector {
+            expression: self.clone(),
+        }
+    }
+}
+
+/// Evaluates a runtime field against the documents of a single segment.
+pub struct RuntimeSegmentEvaluator {
+    expr: Arc<Expr>,
+    columns: Vec<Option<SegmentColumn>>,
+}
+
+impl RuntimeSegmentEvaluator {
+    /// Evaluates the runtime field for the given document.
+    pub fn evaluate(&self, doc: DocId) -> RuntimeValue {
+        self.eval(&self.expr, doc, None)
+    }
+
+    /// Evaluates the expression for the given document with `_score` resolving to
+    /// the given score.
+    pub fn evaluate_scored(&self, doc: DocId, score: Score) -> RuntimeValue {
+        self.eval(&self.expr, doc, Some(score))
+    }
+
+    fn eval(&self, expr: &Expr, doc: DocId, score: Option<Score>) -> RuntimeValue {
+        match expr {
+            Expr::Literal(value) => value.clone(),
+            Expr::Score => {
+                score.map_or(RuntimeValue::Null, |score| RuntimeValue::F64(score as f64))
+            },
+            Expr::Field(idx) => match self.columns[*idx].as_ref() {
+                Some(column) => first_value(column, doc),
+                None => RuntimeValue::Null,
+            },
+            Expr::Neg(expr) => match self.eval(expr, doc, score) {
+                RuntimeValue::I64(v) => v
+                    .checked_neg()
+                    .map_or(RuntimeValue::F64(-(v as f64)), RuntimeValue::I64),
+                RuntimeValue::F64(v) => RuntimeValue::F64(-v),
+                _ => RuntimeValue::Null,
+            },
+            Expr::Binary(op, left, right) => binary(
+                *op,
+                self.eval(left, doc, score),
+                self.eval(right, doc, score),
+            ),
+            Expr::Concat(args) => {
+                let text = args
+                    .iter()
+                    .filter_map(|arg| self.eval(arg, doc, score).to_text())
+                    .collect::<String>();
+                RuntimeValue::Str(text)
+            },
+            Expr::DateTrunc(unit, expr) => match self.eval(expr, doc, score) {
+                RuntimeValue::DateTime(dt) => truncate_datetime(dt, *unit)
+                    .map_or(RuntimeValue::Null, RuntimeValue::DateTime),
+                _ => RuntimeValue::Null,
+            },
+            Expr::Math(function, args) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    match self.eval(arg, doc, score).as_numeric() {
+                        Some(value) => values.push(value),
+                        None => return RuntimeValue::Null,
+                    }
+                }
+
+                let result = function.apply(&values);
+                if result.is_finite() {
+                    RuntimeValue::F64(result)
+                } else {
+                    RuntimeValue::Null
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+/// The statistics of the numeric values of a runtime field.
+pub struct RuntimeFieldStats {
+    /// The number of documents with a numeric value.
+    pub count: u64,
+    /// The smallest value.
+    pub min: Option<f64>,
+    /// The largest value.
+    pub max: Option<f64>,
+    /// The sum of all values.
+    pub sum: f64,
+}
+
+impl RuntimeFieldStats {
+    /// The average of all values.
+    pub fn avg(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn merge(&mut self, other: RuntimeFieldStats) {
+        self.count += other.count;
+        self.sum += other.sum;
+        for value in [other.min, other.max].into_iter().flatten() {
+            self.min = Some(self.min.map_or(value, |min| min.min(value)));
+            self.max = Some(self.max.map_or(value, |max| max.max(value)));
+        }
+    }
+}
+
+/// Aggregates the numeric values of a runtime field across all matching documents.
+pub struct RuntimeStatsCollector {
+    expression: RuntimeExpression,
+}
+
+impl Collector for RuntimeStatsCollector {
+    type Fruit = RuntimeFieldStats;
+    type Child = RuntimeStatsSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(RuntimeStatsSegmentCollector {
+            evaluator: self.expression.for_segment(reader),
+            stats: RuntimeFieldStats::default(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<RuntimeFieldStats>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut stats = RuntimeFieldStats::default();
+        for fruit in segment_fruits {
+            stats.merge(fruit);
+        }
+        Ok(stats)
+    }
+}
+
+/// The per-segment collector of a [RuntimeStatsCollector].
+pub struct RuntimeStatsSegmentCollector {
+    evaluator: RuntimeSegmentEvaluator,
+    stats: RuntimeFieldStats,
+}
+
+impl SegmentCollector for RuntimeStatsSegmentCollector {
+    type Fruit = RuntimeFieldStats;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if let Some(value) = self.evaluator.evaluate(doc).as_f64() {
+            self.stats.add(value);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.stats
+    }
+}
+
+/// Reads the first value of the document, multi-valued fields use their first value.
+fn first_value(column: &SegmentColumn, doc: DocId) -> RuntimeValue {
+    let value = match column {
+        SegmentColumn::U64(column) => {
+            column.first(doc).map(|v| match i64::try_from(v) {
+                Ok(v) => RuntimeValue::I64(v),
+                Err(_) => RuntimeValue::F64(v as f64),
+            })
+        },
+        SegmentColumn::I64(column) => column.first(doc).map(RuntimeValue::I64),
+        SegmentColumn::F64(column) => column.first(doc).map(RuntimeValue::F64),
+        SegmentColumn::Bool(column) => column.first(doc).map(RuntimeValue::Bool),
+        SegmentColumn::Date(column) => column
+            .first(doc)
+            .and_then(|dt| DateTime::from_micros(dt.into_timestamp_micros()))
+            .map(RuntimeValue::DateTime),
+        SegmentColumn::Str(column) => column.ords().first(doc).and_then(|ord| {
+            let mut text = String::new();
+            match column.ord_to_str(ord, &mut text) {
+                Ok(true) => Some(RuntimeValue::Str(text)),
+                _ => None,
+            }
+        }),
+    };
+
+    value.unwrap_or(RuntimeValue::Null)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A math function available within expressions.
+pub enum MathFunction {
+    /// `abs(x)`, the absolute value.
+    Abs,
+    /// `ceil(x)`, rounds up to the nearest integer.
+    Ceil,
+    /// `floor(x)`, rounds down to the nearest integer.
+    Floor,
+    /// `exp(x)`, `e` raised to the power of the value.
+    Exp,
+    /// `ln(x)`, the natural logarithm.
+    Ln,
+    /// `log(x)`, the base 10 logarithm.
+    Log,
+    /// `log1p(x)`, the natural logarithm of the value plus one.
+    Log1p,
+    /// `sqrt(x)`, the square root.
+    Sqrt,
+    /// `pow(x, y)`, the value raised to the power of `y`.
+    Pow,
+    /// `min(a, b, ...)`, the smallest value.
+    Min,
+    /// `max(a, b, ...)`, the largest value.
+    Max,
+    /// `saturation(x, k)`, `x / (x + k)` which approaches `1` as the value grows.
+    Saturation,
+    /// `sigmoid(x, k, a)`, `x^a / (x^a + k^a)`.
+    Sigmoid,
+}
+
+impl MathFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        let function = match name {
+            "abs" => Self::Abs,
+            "ceil" => Self::Ceil,
+            "floor" => Self::Floor,
+            "exp" => Self::Exp,
+            "ln" => Self::Ln,
+            "log" => Self::Log,
+            "log1p" => Self::Log1p,
+            "sqrt" => Self::Sqrt,
+            "pow" => Self::Pow,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "saturation" => Self::Saturation,
+            "sigmoid" => Self::Sigmoid,
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    /// Checks the function is called with a supported number of arguments.
+    fn check_args(&self, name: &str, num_args: usize) -> Result<(), String> {
+        let expected = match self {
+            Self::Pow | Self::Saturation => 2,
+            Self::Sigmoid => 3,
+            Self::Min | Self::Max if num_args > 0 => return Ok(()),
+            Self::Min | Self::Max => {
+                return Err(format!("`{name}` expects at least one argument"))
+            },
+            _ => 1,
+        };
+
+        if num_args != expected {
+            return Err(format!(
+                "`{name}` expects {expected} argument(s) but got {num_args}"
+            ));
+        }
+        Ok(())
+    }
+
+    fn apply(&self, args: &[f64]) -> f64 {
+        match self {
+            Self::Abs => args[0].abs(),
+            Self::Ceil => args[0].ceil(),
+            Self::Floor => args[0].floor(),
+            Self::Exp => args[0].exp(),
+            Self::Ln => args[0].ln(),
+            Self::Log => args[0].log10(),
+            Self::Log1p => args[0].ln_1p(),
+            Self::Sqrt => args[0].sqrt(),
+            Self::Pow => args[0].powf(args[1]),
+            Self::Min => args.iter().copied().fold(f64::INFINITY, f64::min),
+            Self::Max => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Self::Saturation => args[0] / (args[0] + args[1]),
+            Self::Sigmoid => {
+                let (x, k) = (args[0].powf(args[2]), args[1].powf(args[2]));
+                x / (x + k)
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(RuntimeValue),
+    Field(usize),
+    Score,
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Concat(Vec<Expr>),
+    DateTrunc(Unit, Box<Expr>),
+    Math(MathFunction, Vec<Expr>),
+}
+
+fn binary(op: Op, left: RuntimeValue, right: RuntimeValue) -> RuntimeValue {
+    use RuntimeValue::{F64, I64};
+
+    if op == Op::Add
+        && (matches!(left, RuntimeValue::Str(_))
+            || matches!(right, RuntimeValue::Str(_)))
+    {
+        return match (left.to_text(), right.to_text()) {
+            (Some(left), Some(right)) => RuntimeValue::Str(left + &right),
+            _ => RuntimeValue::Null,
+        };
+    }
+
+    let (left, right) = match (left, right) {
+        (I64(l), I64(r)) if op != Op::Div => {
+            let result = match op {
+                Op::Add => l.checked_add(r),
+                Op::Sub => l.checked_sub(r),
+                Op::Mul => l.checked_mul(r),
+                Op::Rem if r == 0 => return RuntimeValue::Null,
+                Op::Rem => l.checked_rem(r),
+                Op::Div => unreachable!(),
+            };
+
+            // Integer overflow falls back to float arithmetic.
+            match result {
+                Some(v) => return I64(v),
+                None => (l as f64, r as f64),
+            }
+        },
+        (l, r) => match (l.as_numeric(), r.as_numeric()) {
+            (Some(l), Some(r)) => (l, r),
+            _ => return RuntimeValue::Null,
+        },
+    };
+
+    let result = match op {
+        Op::Add => left + right,
+        Op::Sub => left - right,
+        Op::Mul => left * right,
+        Op::Div | Op::Rem if right == 0.0 => return RuntimeValue::Null,
+        Op::Div => left / right,
+        Op::Rem => left % right,
+    };
+    F64(result)
+}
+
+impl RuntimeValue {
+    fn as_numeric(&self) -> Option<f64> {
+        match self {
+            Self::I64(v) => Some(*v as f64),
+            Self::F64(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+/// A recursive descent parser for runtime field expressions.
+struct Parser<'a> {
+    ctx: &'a QueryContext,
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    fields: Vec<FastColumn>,
+    allow_score: bool,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(&mut self) -> Result<Expr, String> {
+        let expr = self.parse_sum()?;
+        self.skip_whitespace();
+        match self.chars.peek() {
+            None => Ok(expr),
+            Some((pos, c)) => Err(format!("unexpected {c:?} at position {pos}")),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_product()?;
+        loop {
+            let op = match self.peek_char() {
+                Some('+') => Op::Add,
+                Some('-') => Op::Sub,
+                _ => return Ok(expr),
+            };
+            self.chars.next();
+            let right = self.parse_product()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            let op = match self.peek_char() {
+                Some('*') => Op::Mul,
+                Some('/') => Op::Div,
+                Some('%') => Op::Rem,
+                _ => return Ok(expr),
+            };
+            self.chars.next();
+            let right = self.parse_unary()?;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(format!(
+                "the expression exceeds the maximum nesting depth of {MAX_EXPRESSION_DEPTH}"
+            ));
+        }
+
+        let expr = if self.peek_char() == Some('-') {
+            self.chars.next();
+            self.parse_unary().map(|expr| Expr::Neg(Box::new(expr)))
+        } else {
+            self.parse_primary()
+        };
+
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.peek_char() {
+            Some('(') => {
+                self.chars.next();
+                let expr = self.parse_sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            },
+            Some(quote @ ('\'' | '"')) => {
+                self.chars.next();
+                Ok(Expr::Literal(RuntimeValue::Str(self.parse_string(quote)?)))
+            },
+            Some(c) if c.is_ascii_digit() || c == '.' => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let ident = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                if self.peek_char() == Some('(') {
+                    self.chars.next();
+                    self.parse_call(ident)
+                } else {
+                    self.resolve_field(ident)
+                }
+            },
+            Some(c) => Err(format!("unexpected {c:?}")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn parse_call(&mut self, function: &str) -> Result<Expr, String> {
+        let mut args = Vec::new();
+        if self.peek_char() == Some(')') {
+            self.chars.next();
+        } else {
+            loop {
+                args.push(self.parse_sum()?);
+                match self.peek_char() {
+                    Some(',') => {
+                        self.chars.next();
+                    },
+                    _ => {
+                        self.expect(')')?;
+                        break;
+                    },
+                }
+            }
+        }
+
+        match function {
+            "concat" => Ok(Expr::Concat(args)),
+            "date_trunc" => {
+                let mut args = args.into_iter();
+                let (Some(Expr::Literal(RuntimeValue::Str(unit))), Some(expr), None) =
+                    (args.next(), args.next(), args.next())
+                else {
+                    return Err(
+                        "`date_trunc` expects a unit string and a datetime, i.e. `date_trunc('day', field)`"
+                            .to_string(),
+                    );
+                };
+
+                Ok(Expr::DateTrunc(Unit::from_name(&unit)?, Box::new(expr)))
+            },
+            other => match MathFunction::from_name(other) {
+                Some(function) => {
+                    function.check_args(other, args.len())?;
+                    Ok(Expr::Math(function, args))
+                },
+                None => Err(format!(
+                    "unknown function {other:?}, expected `concat`, `date_trunc` or a math function"
+                )),
+            },
+        }
+    }
+
+    fn resolve_field(&mut self, name: &str) -> Result<Expr, String> {
+        if name == "_score" {
+            return if self.allow_score {
+                Ok(Expr::Score)
+            } else {
+                Err("`_score` can only be used within a script score".to_string())
+            };
+        }
+
+        let name = self.ctx.resolve_alias(name);
+        if let Some(idx) = self.fields.iter().position(|column| column.name() == name) {
+            return Ok(Expr::Field(idx));
+        }
+
+        let (_, entry) = self
+            .ctx
+            .resolve_field(name)
+            .map_err(|_| format!("unknown field {name:?}"))?;
+        if !entry.is_fast() {
+            return Err(format!("the field {name:?} is not a fast field"));
+        }
+
+        let column = FastColumn::new(entry).ok_or_else(|| {
+            format!(
+                "the field {name:?} has an unsupported type {:?}",
+                entry.field_type().value_type()
+            )
+        })?;
+
+        self.fields.push(column);
+        Ok(Expr::Field(self.fields.len() - 1))
+    }
+
+    fn parse_number(&mut self) -> Result<Expr, String> {
+        let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+        if let Ok(v) = number.parse::<i64>() {
+            return Ok(Expr::Literal(RuntimeValue::I64(v)));
+        }
+
+        number
+            .parse::<f64>()
+            .map(|v| Expr::Literal(RuntimeValue::F64(v)))
+            .map_err(|_| format!("invalid number {number:?}"))
+    }
+
+    fn parse_string(&mut self, quote: char) -> Result<String, String> {
+        let mut text = String::new();
+        while let Some((_, c)) = self.chars.next() {
+            match c {
+                '\\' => match self.chars.next() {
+                    Some((_, c)) => text.push(c),
+                    None => break,
+                },
+                c if c == quote => return Ok(text),
+                c => text.push(c),
+            }
+        }
+        Err("unterminated string".to_string())
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek_char() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                Ok(())
+            },
+            Some(c) => Err(format!("expected {expected:?} but got {c:?}")),
+            None => Err(format!(
+                "expected {expected:?} but got the end of expression"
+            )),
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.chars.peek().map_or(self.source.len(), |(pos, _)| *pos);
+        let mut end = start;
+        while let Some((pos, c)) = self.chars.next_if(|(_, c)| predicate(*c)) {
+            end = pos + c.len_utf8();
+        }
+        &self.source[start..end]
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{DateOptions, SchemaBuilder, FAST, STRING};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    fn index() -> (Index, QueryContext) {
+        let mut schema = SchemaBuilder::new();
+        let price = schema.add_f64_field("price", FAST);
+        let quantity = schema.add_u64_field("quantity", FAST);
+        let brand = schema.add_text_field("brand", STRING | FAST);
+        let created_at =
+            schema.add_date_field("created_at", DateOptions::default().set_fast());
+        schema.add_text_field("title", STRING);
+        let schema = schema.build();
+
+        let index = Index::create_in_ram(schema.clone());
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        let dt = tantivy::DateTime::from_timestamp_secs(1_675_247_400);
+        writer
+            .add_document(doc!(
+                price => 2.5,
+                quantity => 4u64,
+                brand => "acme",
+                created_at => dt,
+            ))
+            .unwrap();
+        writer
+            .add_document(doc!(price => 12.0, quantity => 1u64))
+            .unwrap();
+        writer.commit().unwrap();
+
+        (index, QueryContext::new(schema))
+    }
+
+    fn compile(ctx: &QueryContext, expression: &str) -> RuntimeExpression {
+        let field = RuntimeField {
+            expression: expression.to_string(),
+        };
+        field.compile("computed", ctx).unwrap()
+    }
+
+    fn evaluate_all(index: &Index, expression: &RuntimeExpression) -> Vec<RuntimeValue> {
+        let searcher = index.reader().unwrap().searcher();
+        let docs = (0..2)
+            .map(|doc| DocAddress::new(0, doc))
+            .collect::<Vec<_>>();
+        expression.evaluate_docs(&searcher, &docs)
+    }
+
+    #[test]
+    fn test_evaluate_expressions() {
+        let (index, ctx) = index();
+
+        let total = compile(&ctx, "price * quantity + 1");
+        assert_eq!(
+            evaluate_all(&index, &total),
+            [RuntimeValue::F64(11.0), RuntimeValue::F64(13.0)]
+        );
+
+        let label = compile(&ctx, "concat(brand, '-', quantity)");
+        assert_eq!(
+            evaluate_all(&index, &label),
+            [
+                RuntimeValue::Str("acme-4".to_string()),
+                RuntimeValue::Str("-1".to_string())
+            ]
+        );
+
+        let day = compile(&ctx, "date_trunc('day', created_at)");
+        assert_eq!(
+            evaluate_all(&index, &day),
+            [
+                RuntimeValue::DateTime(DateTime::from_secs(1_675_209_600).unwrap()),
+                RuntimeValue::Null,
+            ]
+        );
+
+        let rounded = compile(&ctx, "max(floor(price), pow(quantity, 2)) + ln(0)");
+        assert_eq!(
+            evaluate_all(&index, &rounded),
+            [RuntimeValue::Null, RuntimeValue::Null]
+        );
+
+        let rounded = compile(&ctx, "max(floor(price), pow(quantity, 2))");
+        assert_eq!(
+            evaluate_all(&index, &rounded),
+            [RuntimeValue::F64(16.0), RuntimeValue::F64(12.0)]
+        );
+
+        let ratio = compile(&ctx, "-(quantity - 1) / (quantity - 1)");
+        assert_eq!(
+            evaluate_all(&index, &ratio),
+            [RuntimeValue::F64(-1.0), RuntimeValue::Null]
+        );
+    }
+
+    #[test]
+    fn test_sort_and_stats() {
+        let (index, ctx) = index();
+        let searcher = index.reader().unwrap().searcher();
+        let total = compile(&ctx, "price * quantity");
+
+        let top = searcher
+            .search(&AllQuery, &total.top_docs(2, false))
+            .unwrap();
+        let docs = top.iter().map(|(_, doc)| doc.doc_id).collect::<Vec<_>>();
+        assert_eq!(docs, [0, 1]);
+
+        let top = searcher
+            .search(&AllQuery, &total.top_docs(2, true))
+            .unwrap();
+        let docs = top.iter().map(|(_, doc)| doc.doc_id).collect::<Vec<_>>();
+        assert_eq!(docs, [1, 0]);
+
+        let (stats, count) =
+            searcher.search(&AllQuery, &(total.stats(), Count)).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.min, Some(10.0));
+        assert_eq!(stats.max, Some(12.0));
+        assert_eq!(stats.avg(), Some(11.0));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        let (_, ctx) = index();
+        let cases = [
+            "price *",
+            "missing + 1",
+            "title + 1",
+            "unknown(price)",
+            "date_trunc('fortnight', created_at)",
+            "'unterminated",
+            "(price + 1",
+            "pow(price)",
+            "max()",
+            "_score * 2",
+        ];
+
+        for case in cases {
+            let field = RuntimeField {
+                expression: case.to_string(),
+            };
+            assert!(
+                matches!(field.compile("computed", &ctx), Err(QueryError::Invalid(_))),
+                "{case} should be rejected"
+            );
+        }
+
+        let field = RuntimeField {
+            expression: "price".to_string(),
+        };
+        assert!(field.compile("price", &ctx).is_err());
+
+        let field = RuntimeField {
+            expression: format!("{}price{}", "(".repeat(100), ")".repeat(100)),
+        };
+        assert!(field.compile("computed", &ctx).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use serde::Deserialize;
+use tantivy::query::{
+    BoostQuery as TantivyBoostQuery,
+    ConstScoreQuery,
+    EnableScoring,
+    Explanation,
+    Query,
+    Scorer,
+    Weight,
+};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query::QueryKind;
+
+fn default_score() -> Score {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches the same documents as the inner query but gives every match the same score.
+pub struct ConstantScoreQuery<'a> {
+    #[serde(borrow)]
+    /// The query used to match documents.
+    pub query: Box<QueryKind<'a>>,
+    #[serde(default = "default_score")]
+    /// The score given to every matching document.
+    ///
+    /// Defaults to `1.0`.
+    pub score: Score,
+}
+
+impl<'a> ConstantScoreQuery<'a> {
+    /// Compiles the inner query and wraps it with a constant score.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        check_score("constant_score", "score", self.score)?;
+
+        let query = self.query.build(ctx)?;
+        Ok(Box::new(ConstScoreQuery::new(query, self.score)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches the same documents as the inner query with their scores multiplied by a factor.
+pub struct BoostQuery<'a> {
+    #[serde(borrow)]
+    /// The query used to match and score documents.
+    pub query: Box<QueryKind<'a>>,
+    /// The factor the score of each matching document is multiplied by.
+    pub factor: Score,
+}
+
+impl<'a> BoostQuery<'a> {
+    /// Compiles the inner query and wraps it with a boost.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        check_score("boost", "factor", self.factor)?;
+
+        let query = self.query.build(ctx)?;
+        Ok(Box::new(TantivyBoostQuery::new(query, self.factor)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches the same documents as the `positive` query, demoting documents which also
+/// match the `negative` query rather than excluding them, i.e. to push out-of-stock
+/// products down the results.
+pub struct BoostingQuery<'a> {
+    #[serde(borrow)]
+    /// The query used to match and score documents.
+    pub positive: Box<QueryKind<'a>>,
+    #[serde(borrow)]
+    /// The query matching documents which should be demoted.
+    pub negative: Box<QueryKind<'a>>,
+    /// The factor the score of documents matching the `negative` query is multiplied
+    /// by, between `0` and `1`.
+    pub negative_boost: Score,
+}
+
+impl<'a> BoostingQuery<'a> {
+    /// Compiles the positive and negative queries into a demoting query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        check_score("boosting", "negative_boost", self.negative_boost)?;
+        if self.negative_boost > 1.0 {
+            return Err(QueryError::Invalid(format!(
+                "The negative_boost of a boosting query must be between 0 and 1, got {}",
+                self.negative_boost
+            )));
+        }
+
+        Ok(Box::new(DemoteQuery {
+            positive: self.positive.build(ctx)?,
+            negative: self.negative.build(ctx)?,
+            negative_boost: self.negative_boost,
+        }))
+    }
+}
+
+#[derive(Debug)]
+/// Multiplies the score of documents matching the positive query which also match the
+/// negative query by the negative boost.
+struct DemoteQuery {
+    positive: Box<dyn Query>,
+    negative: Box<dyn Query>,
+    negative_boost: Score,
+}
+
+impl Clone for DemoteQuery {
+    fn clone(&self) -> Self {
+        Self {
+            positive: self.positive.box_clone(),
+            negative: self.negative.box_clone(),
+            negative_boost: self.negative_boost,
+        }
+    }
+}
+
+impl Query for DemoteQuery {
+    fn weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(DemoteWeight {
+            positive: self.positive.weight(enable_scoring)?,
+            negative: self.negative.weight(enable_scoring)?,
+            negative_boost: self.negative_boost,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.positive.query_terms(visitor);
+    }
+}
+
+struct DemoteWeight {
+    positive: Box<dyn Weight>,
+    negative: Box<dyn Weight>,
+    negative_boost: Score,
+}
+
+impl Weight for DemoteWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        Ok(Box::new(DemoteScorer {
+            positive: self.positive.scorer(reader, boost)?,
+            negative: self.negative.scorer(reader, 1.0)?,
+            negative_boost: self.negative_boost,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        let mut explanation = Explanation::new("Boosting", scorer.score());
+        explanation.add_detail(self.positive.explain(reader, doc)?);
+        if let Ok(negative) = self.negative.explain(reader, doc) {
+            explanation.add_const("negative_boost", self.negative_boost);
+            explanation.add_detail(negative);
+        }
+        Ok(explanation)
+    }
+}
+
+struct DemoteScorer {
+    positive: Box<dyn Scorer>,
+    negative: Box<dyn Scorer>,
+    negative_boost: Score,
+}
+
+impl DocSet for DemoteScorer {
+    fn advance(&mut self) -> DocId {
+        self.positive.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.positive.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.positive.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.positive.size_hint()
+    }
+}
+
+impl Scorer for DemoteScorer {
+    fn score(&mut self) -> Score {
+        let doc = self.positive.doc();
+        let score = self.positive.score();
+
+        if self.negative.doc() < doc {
+            self.negative.seek(doc);
+        }
+        if self.negative.doc() == doc {
+            score * self.negative_boost
+        } else {
+            score
+        }
+    }
+}
+
+/// Ensures the score or factor is a finite, non-negative number.
+fn check_score(query: &str, name: &str, value: Score) -> Result<(), QueryError> {
+    if value.is_finite() && value >= 0.0 {
+        return Ok(());
+    }
+
+    Err(QueryError::Invalid(format!(
+        "The {name} of a {query} query must be a finite, non-negative number, got {value}"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{SchemaBuilder, INDEXED, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    fn test_context() -> QueryContext {
+        let mut schema = SchemaBuilder::new();
+        schema.add_text_field("title", TEXT);
+        QueryContext::new(schema.build())
+    }
+
+    #[test]
+    fn test_score_wrappers_build() {
+        let ctx = test_context();
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{"constant_score": {"query": {"query_string": {"query": "hello"}}}}"#,
+        )
+        .unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{
+                "boost": {
+                    "factor": 2.5,
+                    "query": {"constant_score": {
+                        "score": 3,
+                        "query": {"query_string": {"query": "hello"}}
+                    }}
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{"boost": {"factor": -1, "query": {"query_string": {"query": "hello"}}}}"#,
+        )
+        .unwrap();
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_boosting_query() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let stock = schema.add_u64_field("stock", INDEXED);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "running shoes", stock => 0u64))
+            .unwrap();
+        writer
+            .add_document(doc!(title => "trail running shoes", stock => 5u64))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let search = |json: &str| {
+            let query: QueryKind = serde_json::from_str(json).unwrap();
+            let query = query.build(&ctx).unwrap();
+            searcher.search(&query, &TopDocs::with_limit(2)).unwrap()
+        };
+
+        let hits = search(r#"{"query_string": {"query": "running shoes"}}"#);
+        assert_eq!(hits[0].1.doc_id, 0);
+
+        // Out of stock products are demoted but still returned.
+        let hits = search(
+            r#"{
+                "boosting": {
+                    "positive": {"query_string": {"query": "running shoes"}},
+                    "negative": {"term": {"field": "stock", "value": 0}},
+                    "negative_boost": 0.1
+                }
+            }"#,
+        );
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].1.doc_id, 1);
+
+        let query: QueryKind = serde_json::from_str(
+            r#"{
+                "boosting": {
+                    "positive": {"query_string": {"query": "shoes"}},
+                    "negative": {"query_string": {"query": "trail"}},
+                    "negative_boost": 2
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+}
@@ -0,0 +1,195 @@
+use serde::Deserialize;
+use tantivy::query::{EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query::QueryKind;
+use crate::runtime_fields::{RuntimeExpression, RuntimeSegmentEvaluator};
+
+#[derive(Debug, Deserialize)]
+/// Matches the same documents as the inner query, scored by an expression.
+///
+/// The expression uses the same sandboxed language as runtime fields, with `_score`
+/// resolving to the score of the inner query, i.e.
+/// `_score * log1p(popularity) + saturation(rating, 3)`. Expressions cannot loop or
+/// have side effects and are capped in length and nesting depth.
+///
+/// Documents where the expression is `null`, negative or not a number score `0`.
+pub struct ScriptScoreQuery<'a> {
+    #[serde(borrow)]
+    /// The query used to match documents.
+    pub query: Box<QueryKind<'a>>,
+    /// The expression computing the score of each matching document.
+    pub script: String,
+}
+
+impl<'a> ScriptScoreQuery<'a> {
+    /// Compiles the inner query and the script into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let expression = RuntimeExpression::parse(ctx, "_score", &self.script, true)
+            .map_err(|reason| {
+                QueryError::Invalid(format!("Invalid script: {reason}"))
+            })?;
+        let query = self.query.build(ctx)?;
+
+        Ok(Box::new(ScriptScoreWrapper { query, expression }))
+    }
+}
+
+#[derive(Debug)]
+/// Scores the documents matching the inner query with a compiled expression.
+struct ScriptScoreWrapper {
+    query: Box<dyn Query>,
+    expression: RuntimeExpression,
+}
+
+impl Clone for ScriptScoreWrapper {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.box_clone(),
+            expression: self.expression.clone(),
+        }
+    }
+}
+
+impl Query for ScriptScoreWrapper {
+    fn weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(ScriptScoreWeight {
+            weight: self.query.weight(enable_scoring)?,
+            expression: self.expression.clone(),
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.query.query_terms(visitor);
+    }
+}
+
+struct ScriptScoreWeight {
+    weight: Box<dyn Weight>,
+    expression: RuntimeExpression,
+}
+
+impl Weight for ScriptScoreWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        Ok(Box::new(ScriptScoreScorer {
+            scorer: self.weight.scorer(reader, boost)?,
+            evaluator: self.expression.for_segment(reader),
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        let mut explanation = Explanation::new("ScriptScore", scorer.score());
+        explanation.add_detail(self.weight.explain(reader, doc)?);
+        Ok(explanation)
+    }
+}
+
+struct ScriptScoreScorer {
+    scorer: Box<dyn Scorer>,
+    evaluator: RuntimeSegmentEvaluator,
+}
+
+impl DocSet for ScriptScoreScorer {
+    fn advance(&mut self) -> DocId {
+        self.scorer.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.scorer.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.scorer.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.scorer.size_hint()
+    }
+}
+
+impl Scorer for ScriptScoreScorer {
+    fn score(&mut self) -> Score {
+        let doc = self.scorer.doc();
+        let score = self.scorer.score();
+
+        match self.evaluator.evaluate_scored(doc, score).as_f64() {
+            Some(score) if score.is_finite() && score > 0.0 => score as Score,
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{SchemaBuilder, FAST, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_script_score_query() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let rating = schema.add_f64_field("rating", FAST);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "rust rust", rating => 1.0))
+            .unwrap();
+        writer
+            .add_document(doc!(title => "rust", rating => 5.0))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema);
+        let searcher = index.reader().unwrap().searcher();
+        let search = |script: &str| {
+            let query = ScriptScoreQuery {
+                query: Box::new(
+                    serde_json::from_str(r#"{"query_string": {"query": "rust"}}"#)
+                        .unwrap(),
+                ),
+                script: script.to_string(),
+            };
+            let query = query.build(&ctx).unwrap();
+            searcher.search(&query, &TopDocs::with_limit(2)).unwrap()
+        };
+
+        let hits = search("_score * saturation(rating, 1)");
+        assert_eq!(hits[0].1.doc_id, 1);
+
+        let hits = search("rating - 10");
+        assert!(hits.iter().all(|(score, _)| *score == 0.0));
+
+        let query = ScriptScoreQuery {
+            query: Box::new(
+                serde_json::from_str(r#"{"query_string": {"query": "rust"}}"#).unwrap(),
+            ),
+            script: "_score * missing".to_string(),
+        };
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use lnx_transforms::NESTED_CHILD_FIELD;
+use serde::Deserialize;
+use tantivy::query::{
+    AllQuery,
+    BooleanQuery,
+    ConstScoreQuery,
+    Occur,
+    Query,
+    TermQuery,
+};
+use tantivy::schema::IndexRecordOption;
+use tantivy::{Score, Term};
+
+use crate::aggregations::{aggregation_collector, Aggregation, AggregationCollector};
+use crate::context::QueryContext;
+use crate::datetime_output::DateTimeOutput;
+use crate::document_boost::DocumentBoostQuery;
+use crate::error::QueryError;
+use crate::freshness::Freshness;
+use crate::query::QueryKind;
+use crate::rescore::{RescoreRequest, Rescorer};
+use crate::runtime_fields::{RuntimeExpression, RuntimeField};
+
+#[derive(Debug, Default, Deserialize)]
+/// A search request made against a single index.
+pub struct SearchRequest<'a> {
+    #[serde(default, borrow)]
+    /// The query used to match and score documents.
+    ///
+    /// If no query is provided all documents are matched.
+    pub query: Option<QueryKind<'a>>,
+    #[serde(default, borrow)]
+    /// A set of filters that matching documents must also match.
+    ///
+    /// Filters do not contribute to the score of a document.
+    pub filters: Vec<QueryKind<'a>>,
+    #[serde(default)]
+    /// The number of hits to return.
+    ///
+    /// Defaults to the index's default limit, see
+    /// [SearchDefaults::limit](crate::SearchDefaults::limit).
+    pub limit: Option<usize>,
+    #[serde(default)]
+    /// The maximum time in milliseconds the search may spend collecting results.
+    ///
+    /// Once exceeded the hits gathered so far are returned and the response
+    /// is marked as timed out, see [TimeoutCollector](crate::TimeoutCollector).
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    /// The minimum score a document must have to be returned.
+    ///
+    /// Documents below this score are dropped before pagination and counting,
+    /// see [MinScoreCollector](crate::MinScoreCollector).
+    pub min_score: Option<Score>,
+    #[serde(default)]
+    /// How `datetime` values within the returned documents are rendered,
+    /// see [DateTimeOutput].
+    pub datetime_output: DateTimeOutput,
+    #[serde(default)]
+    /// Fields computed at query time from existing fast fields, keyed by their name.
+    ///
+    /// Runtime fields can be returned, sorted and aggregated on without reindexing,
+    /// see [RuntimeField].
+    pub runtime_fields: BTreeMap<String, RuntimeField>,
+    #[serde(default)]
+    /// Ranks recent documents higher by decaying scores with the age of a `datetime`
+    /// field, see [Freshness].
+    pub freshness: Option<Freshness>,
+    #[serde(default, borrow)]
+    /// A second phase rescoring the top hits of the query before pagination,
+    /// see [RescoreRequest].
+    pub rescore: Option<RescoreRequest<'a>>,
+    #[serde(default)]
+    /// The experiment variant the search is ranked with, see
+    /// [Experiments](crate::Experiments).
+    pub experiment: Option<String>,
+    #[serde(default)]
+    /// An opaque token identifying the user or session, hashed to consistently pick
+    /// an experiment variant when no `experiment` is set.
+    pub user_token: Option<String>,
+    #[serde(default, alias = "aggs")]
+    /// Aggregations computed over every matching document alongside the hits,
+    /// keyed by their name, see [Aggregation].
+    pub aggregations: BTreeMap<String, Aggregation>,
+}
+
+impl<'a> SearchRequest<'a> {
+    /// The maximum time the search may spend collecting results, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Compiles the runtime fields of the request.
+    pub fn compile_runtime_fields(
+        &self,
+        ctx: &QueryContext,
+    ) -> Result<Vec<RuntimeExpression>, QueryError> {
+        self.runtime_fields
+            .iter()
+            .map(|(name, field)| field.compile(name, ctx))
+            .collect()
+    }
+
+    /// Compiles the rescore phase of the request, if any.
+    ///
+    /// This must be called before [SearchRequest::build_query] consumes the request.
+    pub fn build_rescorer(
+        &mut self,
+        ctx: &QueryContext,
+    ) -> Result<Option<Rescorer>, QueryError> {
+        self.rescore
+            .take()
+            .map(|rescore| rescore.build(ctx))
+            .transpose()
+    }
+
+    /// Compiles the aggregations of the request, if any.
+    ///
+    /// This must be called before [SearchRequest::build_query] consumes the request.
+    pub fn build_aggregations(
+        &mut self,
+        ctx: &QueryContext,
+    ) -> Result<Option<AggregationCollector>, QueryError> {
+        if self.aggregations.is_empty() {
+            return Ok(None);
+        }
+
+        let aggregations = std::mem::take(&mut self.aggregations);
+        aggregation_collector(ctx, aggregations).map(Some)
+    }
+
+    /// Compiles the request into a single tantivy query.
+    ///
+    /// The scores of the query are decayed by the request's [Freshness] and, if the
+    /// context has a document boost field, multiplied by the boost of each document.
+    /// Any curated results of a `query_string` query are pinned to the top.
+    pub fn build_query(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let query_string = match &self.query {
+            Some(QueryKind::QueryString(query)) => Some(query.query.clone()),
+            _ => None,
+        };
+
+        let mut query = match self.query {
+            Some(query) => query.build(ctx)?,
+            None => Box::new(AllQuery),
+        };
+
+        if let Some(freshness) = self.freshness {
+            query = freshness.apply(ctx, query)?;
+        }
+
+        if let Some(field) = ctx.document_boost_field() {
+            let name = ctx.schema().get_field_name(field).to_string();
+            query = Box::new(DocumentBoostQuery::new(query, name));
+        }
+
+        if let (Some(curations), Some(query_string)) = (ctx.curations(), query_string) {
+            query = curations.apply(ctx, &query_string, query)?;
+        }
+
+        let mut clauses = Vec::with_capacity(self.filters.len() + 2);
+        clauses.push((Occur::Must, query));
+
+        for filter in self.filters {
+            let filter = filter.build(ctx)?;
+            // A score of `0` means the filter only restricts which documents
+            // match without affecting the score produced by the main query.
+            let filter: Box<dyn Query> = Box::new(ConstScoreQuery::new(filter, 0.0));
+            clauses.push((Occur::Must, filter));
+        }
+
+        // Nested child documents can only be matched through a `nested` query which
+        // joins them onto their parent, they are never hits on their own.
+        if let Ok(field) = ctx.schema().get_field(NESTED_CHILD_FIELD) {
+            let term = Term::from_field_u64(field, 1);
+            let is_child = TermQuery::new(term, IndexRecordOption::Basic);
+            clauses.push((Occur::MustNot, Box::new(is_child)));
+        }
+
+        if clauses.len() == 1 {
+            let (_, query) = clauses.remove(0);
+            return Ok(query);
+        }
+
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{FacetOptions, SchemaBuilder, FAST, INDEXED, TEXT};
+
+    use super::*;
+
+    #[test]
+    fn test_search_request_with_filters() {
+        let mut schema = SchemaBuilder::new();
+        schema.add_text_field("title", TEXT);
+        schema.add_u64_field("price", FAST | INDEXED);
+        schema.add_facet_field("category", FacetOptions::default());
+        let ctx = QueryContext::new(schema.build());
+
+        let request: SearchRequest = serde_json::from_str(
+            r#"{
+                "query": {"query_string": {"query": "running shoes"}},
+                "filters": [
+                    {"range": {"field": "price", "lt": 100}},
+                    {"term": {"field": "category", "value": "/footwear/running"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(request.filters.len(), 2);
+        assert!(request.build_query(&ctx).is_ok());
+
+        let request: SearchRequest = serde_json::from_str(
+            r#"{"filters": [{"term": {"field": "missing", "value": 1}}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            request.build_query(&ctx),
+            Err(QueryError::UnknownField(_))
+        ));
+    }
+
+    #[test]
+    fn test_search_request_runtime_fields() {
+        let mut schema = SchemaBuilder::new();
+        schema.add_u64_field("price", FAST | INDEXED);
+        let ctx = QueryContext::new(schema.build());
+
+        let request: SearchRequest = serde_json::from_str(
+            r#"{"runtime_fields": {"price_with_tax": {"expression": "price * 1.2"}}}"#,
+        )
+        .unwrap();
+        let fields = request.compile_runtime_fields(&ctx).unwrap();
+        assert_eq!(fields[0].name(), "price_with_tax");
+
+        let request: SearchRequest = serde_json::from_str(
+            r#"{"runtime_fields": {"invalid": {"expression": "price *"}}}"#,
+        )
+        .unwrap();
+        assert!(request.compile_runtime_fields(&ctx).is_err());
+    }
+}
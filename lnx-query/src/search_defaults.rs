@@ -0,0 +1,213 @@
+use lnx_metastore::Metastore;
+use serde::Deserialize;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query_string::{check_fuzziness, Operator};
+use crate::search::SearchRequest;
+
+/// The metastore database the default search settings of each index are stored in.
+const SEARCH_DEFAULTS_DATABASE: &str = "lnx_search_defaults";
+
+/// The number of hits returned when neither the request nor the index set a limit.
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+/// The maximum number of hits a single search may return.
+pub const MAX_SEARCH_LIMIT: usize = 10_000;
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The default search behaviour of an index, so every client does not have to
+/// repeat the same options.
+///
+/// Options set on a request or query always take priority over these defaults.
+pub struct SearchDefaults {
+    #[serde(default)]
+    /// The text fields searched by free-text queries which do not target any fields.
+    ///
+    /// Defaults to every indexed text field.
+    pub default_fields: Vec<String>,
+    #[serde(default)]
+    /// The operator used to combine free-text terms.
+    pub default_operator: Option<Operator>,
+    #[serde(default)]
+    /// The fuzziness of free-text terms, up to `2`.
+    pub fuzziness: Option<u8>,
+    #[serde(default)]
+    /// The number of hits returned by a search.
+    pub limit: Option<usize>,
+}
+
+impl SearchDefaults {
+    /// Parses and validates the defaults against the context.
+    pub fn parse(ctx: &QueryContext, source: &str) -> Result<Self, QueryError> {
+        let defaults: Self = serde_json::from_str(source).map_err(|e| {
+            QueryError::Invalid(format!("Unable to parse search defaults: {e}"))
+        })?;
+
+        for name in defaults.default_fields.iter() {
+            ctx.resolve_text_field(name, "query_string")?;
+        }
+        if let Some(fuzziness) = defaults.fuzziness {
+            check_fuzziness(fuzziness)?;
+        }
+        if let Some(limit) = defaults.limit {
+            check_limit(limit)?;
+        }
+
+        Ok(defaults)
+    }
+
+    /// Applies the defaults for free-text queries to the context.
+    pub fn configure(&self, mut ctx: QueryContext) -> Result<QueryContext, QueryError> {
+        if !self.default_fields.is_empty() {
+            ctx = ctx.with_default_fields(&self.default_fields)?;
+        }
+        if let Some(operator) = self.default_operator {
+            ctx = ctx.with_default_operator(operator);
+        }
+        if let Some(fuzziness) = self.fuzziness {
+            ctx = ctx.with_default_fuzziness(fuzziness);
+        }
+        Ok(ctx)
+    }
+
+    /// The number of hits the request returns, its own `limit` or the index's default.
+    pub fn limit(&self, request: &SearchRequest) -> Result<usize, QueryError> {
+        let limit = request.limit.or(self.limit).unwrap_or(DEFAULT_SEARCH_LIMIT);
+        check_limit(limit)?;
+        Ok(limit)
+    }
+}
+
+fn check_limit(limit: usize) -> Result<(), QueryError> {
+    if limit > 0 && limit <= MAX_SEARCH_LIMIT {
+        return Ok(());
+    }
+
+    Err(QueryError::Invalid(format!(
+        "The limit must be between 1 and {MAX_SEARCH_LIMIT}, got {limit}"
+    )))
+}
+
+/// The persisted default search settings of every index.
+pub struct SearchDefaultsStore {
+    metastore: Metastore,
+}
+
+impl SearchDefaultsStore {
+    /// Opens the search defaults store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(SEARCH_DEFAULTS_DATABASE)?;
+        Ok(Self { metastore })
+    }
+
+    /// Persists the source of the index's defaults, which must have been parsed
+    /// successfully via [SearchDefaults::parse].
+    pub fn save(&self, index: &str, source: &str) -> anyhow::Result<()> {
+        self.metastore.put(&index, &source.to_string())
+    }
+
+    /// Loads and validates the defaults of the index.
+    pub fn load(
+        &self,
+        index: &str,
+        ctx: &QueryContext,
+    ) -> anyhow::Result<SearchDefaults> {
+        let Some(source) = self.metastore.get::<_, String>(&index)? else {
+            return Ok(SearchDefaults::default());
+        };
+        Ok(SearchDefaults::parse(ctx, &source)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::TopDocs;
+    use tantivy::schema::{SchemaBuilder, TEXT};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    #[test]
+    fn test_search_defaults() {
+        let mut schema = SchemaBuilder::new();
+        let title = schema.add_text_field("title", TEXT);
+        let body = schema.add_text_field("body", TEXT);
+        let schema = schema.build();
+        let index = Index::create_in_ram(schema.clone());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        writer
+            .add_document(doc!(title => "running shoes", body => "trail"))
+            .unwrap();
+        writer
+            .add_document(doc!(title => "runing socks", body => "running"))
+            .unwrap();
+        writer
+            .add_document(doc!(title => "gift card", body => "shoes"))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let ctx = QueryContext::new(schema.clone());
+        let defaults = SearchDefaults::parse(
+            &ctx,
+            r#"{"default_fields": ["title"], "default_operator": "and", "fuzziness": 1, "limit": 5}"#,
+        )
+        .unwrap();
+        assert!(
+            SearchDefaults::parse(&ctx, r#"{"default_fields": ["missing"]}"#).is_err()
+        );
+        assert!(SearchDefaults::parse(&ctx, r#"{"fuzziness": 3}"#).is_err());
+        assert!(SearchDefaults::parse(&ctx, r#"{"limit": 0}"#).is_err());
+
+        let searcher = index.reader().unwrap().searcher();
+        let search = |json: &str| {
+            let request: SearchRequest = serde_json::from_str(json).unwrap();
+            let limit = defaults.limit(&request).unwrap();
+            let ctx = defaults
+                .configure(QueryContext::new(schema.clone()))
+                .unwrap();
+            let query = request.build_query(&ctx).unwrap();
+            let mut hits = searcher
+                .search(&query, &TopDocs::with_limit(limit))
+                .unwrap()
+                .into_iter()
+                .map(|(_, address)| address.doc_id)
+                .collect::<Vec<_>>();
+            hits.sort_unstable();
+            hits
+        };
+
+        // Only the title is searched, with a typo tolerated.
+        assert_eq!(
+            search(r#"{"query": {"query_string": {"query": "running"}}}"#),
+            [0, 1]
+        );
+        // Every term must match by default, unless the query overrides the operator.
+        assert_eq!(
+            search(r#"{"query": {"query_string": {"query": "running shoes"}}}"#),
+            [0]
+        );
+        assert_eq!(
+            search(
+                r#"{"query": {"query_string": {"query": "running shoes", "default_operator": "or"}}}"#
+            ),
+            [0, 1]
+        );
+        assert_eq!(
+            search(
+                r#"{"query": {"query_string": {"query": "runing", "fuzziness": 0}}}"#
+            ),
+            [1]
+        );
+
+        let request: SearchRequest = serde_json::from_str(r#"{"limit": 2}"#).unwrap();
+        assert_eq!(defaults.limit(&request).unwrap(), 2);
+        let request: SearchRequest = serde_json::from_str("{}").unwrap();
+        assert_eq!(defaults.limit(&request).unwrap(), 5);
+        assert_eq!(
+            SearchDefaults::default().limit(&request).unwrap(),
+            DEFAULT_SEARCH_LIMIT
+        );
+    }
+}
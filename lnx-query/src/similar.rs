@@ -0,0 +1,207 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use lnx_document::{DynamicDocument, Value};
+use serde::Deserialize;
+use tantivy::query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{Searcher, Term};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query_string::deserialize_field_list;
+
+#[derive(Debug, Deserialize)]
+/// Finds documents which are similar to a given source document.
+///
+/// The most significant terms of the source document's text fields are
+/// extracted and weighted by their TF-IDF score, these terms are then
+/// executed as a disjunction where each term is boosted by its weight.
+pub struct SimilarDocumentsRequest {
+    #[serde(default, deserialize_with = "deserialize_field_list")]
+    /// The text fields to extract terms from.
+    ///
+    /// If no fields are provided the default fields of the index are used.
+    pub fields: Vec<String>,
+    #[serde(default = "SimilarDocumentsRequest::default_max_query_terms")]
+    /// The maximum number of terms to include in the query.
+    pub max_query_terms: usize,
+    #[serde(default = "SimilarDocumentsRequest::default_min_term_frequency")]
+    /// The minimum number of times a term must appear in the source document.
+    pub min_term_frequency: usize,
+    #[serde(default = "SimilarDocumentsRequest::default_min_doc_frequency")]
+    /// The minimum number of documents in the index a term must appear in.
+    pub min_doc_frequency: u64,
+    #[serde(default)]
+    /// The maximum number of documents in the index a term may appear in.
+    pub max_doc_frequency: Option<u64>,
+    #[serde(default)]
+    /// The minimum length of a term in bytes.
+    pub min_term_length: usize,
+}
+
+impl Default for SimilarDocumentsRequest {
+    fn default() -> Self {
+        Self {
+            fields: Vec::new(),
+            max_query_terms: Self::default_max_query_terms(),
+            min_term_frequency: Self::default_min_term_frequency(),
+            min_doc_frequency: Self::default_min_doc_frequency(),
+            max_doc_frequency: None,
+            min_term_length: 0,
+        }
+    }
+}
+
+impl SimilarDocumentsRequest {
+    fn default_max_query_terms() -> usize {
+        25
+    }
+
+    fn default_min_term_frequency() -> usize {
+        1
+    }
+
+    fn default_min_doc_frequency() -> u64 {
+        1
+    }
+
+    /// Builds a query matching documents similar to the provided source document.
+    ///
+    /// The searcher is used to look up the document frequencies of each term.
+    pub fn build_query(
+        &self,
+        ctx: &QueryContext,
+        searcher: &Searcher,
+        document: &DynamicDocument,
+    ) -> Result<Box<dyn Query>, QueryError> {
+        let fields = if self.fields.is_empty() {
+            ctx.default_fields().to_vec()
+        } else {
+            self.fields
+                .iter()
+                .map(|name| ctx.resolve_text_field(name, "similar"))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let term_frequencies = self.collect_term_frequencies(ctx, &fields, document);
+        let num_docs = searcher.num_docs() as f32;
+
+        let mut scored_terms = Vec::with_capacity(term_frequencies.len());
+        for (term, tf) in term_frequencies {
+            if tf < self.min_term_frequency {
+                continue;
+            }
+
+            let doc_freq = searcher.doc_freq(&term)?;
+            if doc_freq < self.min_doc_frequency {
+                continue;
+            }
+            if self
+                .max_doc_frequency
+                .map(|max| doc_freq > max)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let doc_freq = doc_freq as f32;
+            let idf = (1.0 + (num_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln();
+            scored_terms.push((term, tf as f32 * idf));
+        }
+
+        scored_terms.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        scored_terms.truncate(self.max_query_terms);
+
+        let clauses = scored_terms
+            .into_iter()
+            .map(|(term, score)| {
+                let query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+                let query: Box<dyn Query> =
+                    Box::new(BoostQuery::new(Box::new(query), score));
+                (Occur::Should, query)
+            })
+            .collect();
+
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// Tokenizes the text values of the target fields and counts the frequency of each term.
+    fn collect_term_frequencies(
+        &self,
+        ctx: &QueryContext,
+        fields: &[Field],
+        document: &DynamicDocument,
+    ) -> HashMap<Term, usize> {
+        let mut frequencies = HashMap::new();
+
+        for &field in fields {
+            let entry = ctx.schema().get_field_entry(field);
+            let Some(mut analyzer) = ctx.text_analyzer(entry) else {
+                continue;
+            };
+
+            let mut texts = Vec::new();
+            for (key, value) in document.iter() {
+                if key.as_ref() == entry.name() {
+                    collect_text_values(value, &mut texts);
+                }
+            }
+
+            for text in texts {
+                let mut stream = analyzer.token_stream(text);
+                stream.process(&mut |token| {
+                    if token.text.len() < self.min_term_length {
+                        return;
+                    }
+
+                    let term = Term::from_field_text(field, &token.text);
+                    *frequencies.entry(term).or_insert(0) += 1;
+                });
+            }
+        }
+
+        frequencies
+    }
+}
+
+/// Collects all string values, including those nested within arrays.
+fn collect_text_values<'a>(value: &'a Value, texts: &mut Vec<&'a str>) {
+    match value {
+        Value::Str(text) => texts.push(text.as_ref()),
+        Value::Array(elements) => {
+            for element in elements {
+                collect_text_values(element, texts);
+            }
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_text_values() {
+        let value = Value::Array(vec![
+            Value::from("hello"),
+            Value::U64(1),
+            Value::Array(vec![Value::from("world")]),
+        ]);
+
+        let mut texts = Vec::new();
+        collect_text_values(&value, &mut texts);
+        assert_eq!(texts, ["hello", "world"]);
+    }
+
+    #[test]
+    fn test_request_defaults() {
+        let request: SimilarDocumentsRequest = serde_json::from_str("{}").unwrap();
+        assert!(request.fields.is_empty());
+        assert_eq!(request.max_query_terms, 25);
+        assert_eq!(request.min_term_frequency, 1);
+        assert_eq!(request.min_doc_frequency, 1);
+        assert_eq!(request.max_doc_frequency, None);
+    }
+}
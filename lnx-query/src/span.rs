@@ -0,0 +1,434 @@
+use serde::Deserialize;
+use tantivy::postings::{Postings, SegmentPostings};
+use tantivy::query::{EmptyScorer, EnableScoring, Explanation, Query, Scorer, Weight};
+use tantivy::schema::{Field, FieldType, IndexRecordOption};
+use tantivy::{DocId, DocSet, Score, SegmentReader, TantivyError, Term, TERMINATED};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+#[derive(Debug, Deserialize)]
+/// Matches documents where a set of terms occur within a window of each other.
+///
+/// Terms are matched exactly, they are not analyzed.
+pub struct SpanNearQuery {
+    /// The text field to match against.
+    pub field: String,
+    /// The terms which must occur near each other.
+    pub terms: Vec<String>,
+    #[serde(default)]
+    /// The maximum number of positions allowed between the terms.
+    pub slop: u32,
+    #[serde(default)]
+    /// If the terms must occur in the order they are given.
+    pub in_order: bool,
+}
+
+impl SpanNearQuery {
+    /// Compiles the span into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        if self.terms.is_empty() {
+            return Err(QueryError::Invalid(format!(
+                "Span near query on field {:?} must specify at least one term",
+                self.field,
+            )));
+        }
+
+        let field = resolve_positional_field(ctx, &self.field, "span_near")?;
+        let terms = self
+            .terms
+            .iter()
+            .map(|term| Term::from_field_text(field, term))
+            .collect();
+
+        Ok(Box::new(SpanQuery {
+            terms,
+            matcher: SpanMatcher::Near {
+                slop: self.slop,
+                in_order: self.in_order,
+            },
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches documents where a term occurs within the first `end` positions of a field.
+///
+/// The term is matched exactly, it is not analyzed.
+pub struct SpanFirstQuery {
+    /// The text field to match against.
+    pub field: String,
+    /// The term which must occur near the start of the field.
+    pub term: String,
+    /// The number of positions from the start of the field the term must occur within.
+    pub end: u32,
+}
+
+impl SpanFirstQuery {
+    /// Compiles the span into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        let field = resolve_positional_field(ctx, &self.field, "span_first")?;
+
+        Ok(Box::new(SpanQuery {
+            terms: vec![Term::from_field_text(field, &self.term)],
+            matcher: SpanMatcher::First { end: self.end },
+        }))
+    }
+}
+
+/// Resolves a text field, ensuring it is indexed with positions.
+fn resolve_positional_field(
+    ctx: &QueryContext,
+    name: &str,
+    query: &'static str,
+) -> Result<Field, QueryError> {
+    let field = ctx.resolve_text_field(name, query)?;
+    let entry = ctx.schema().get_field_entry(field);
+
+    let has_positions = match entry.field_type() {
+        FieldType::Str(options) => options
+            .get_indexing_options()
+            .map_or(false, |indexing| indexing.index_option().has_positions()),
+        _ => false,
+    };
+
+    if !has_positions {
+        return Err(QueryError::unsupported(
+            name,
+            query,
+            "the field is not indexed with positions",
+        ));
+    }
+
+    Ok(field)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// How the positions of the terms within a document must line up.
+enum SpanMatcher {
+    Near { slop: u32, in_order: bool },
+    First { end: u32 },
+}
+
+impl SpanMatcher {
+    /// Checks if the positions of each term match the span.
+    ///
+    /// Returns the number of positions between the terms of the tightest match.
+    fn matches(&self, positions: &[Vec<u32>]) -> Option<u32> {
+        match *self {
+            Self::First { end } => {
+                let first = *positions.first()?.first()?;
+                (first < end).then_some(0)
+            },
+            Self::Near { slop, in_order } => {
+                let used = if in_order {
+                    ordered_slop(positions)?
+                } else {
+                    unordered_slop(positions)?
+                };
+                (used <= slop).then_some(used)
+            },
+        }
+    }
+}
+
+/// Finds the smallest gap between the terms occurring in order.
+fn ordered_slop(positions: &[Vec<u32>]) -> Option<u32> {
+    let (first, rest) = positions.split_first()?;
+    let num_gaps = rest.len() as u32;
+
+    let mut best = None;
+    for &start in first {
+        let mut prev = start;
+        for term_positions in rest {
+            // Picking the earliest position after the previous term always
+            // produces the tightest span for a given start.
+            match term_positions.iter().find(|&&pos| pos > prev) {
+                Some(&pos) => prev = pos,
+                // A later start can never find a match either.
+                None => return best,
+            }
+        }
+
+        let used = prev - start - num_gaps;
+        best = Some(best.map_or(used, |best: u32| best.min(used)));
+    }
+
+    best
+}
+
+/// Finds the smallest window containing every term in any order.
+fn unordered_slop(positions: &[Vec<u32>]) -> Option<u32> {
+    let num_terms = positions.len();
+    let mut merged = positions
+        .iter()
+        .enumerate()
+        .flat_map(|(term, term_positions)| {
+            term_positions.iter().map(move |&pos| (pos, term))
+        })
+        .collect::<Vec<_>>();
+    merged.sort_unstable();
+
+    let mut counts = vec![0usize; num_terms];
+    let mut covered = 0;
+    let mut left = 0;
+    let mut best = None;
+    for &(pos, term) in merged.iter() {
+        if counts[term] == 0 {
+            covered += 1;
+        }
+        counts[term] += 1;
+
+        while covered == num_terms {
+            let (start, start_term) = merged[left];
+            let used = (pos - start).saturating_sub(num_terms as u32 - 1);
+            best = Some(best.map_or(used, |best: u32| best.min(used)));
+
+            counts[start_term] -= 1;
+            if counts[start_term] == 0 {
+                covered -= 1;
+            }
+            left += 1;
+        }
+    }
+
+    best
+}
+
+#[derive(Debug, Clone)]
+/// Matches documents based on the positions of a set of terms.
+struct SpanQuery {
+    terms: Vec<Term>,
+    matcher: SpanMatcher,
+}
+
+impl Query for SpanQuery {
+    fn weight(
+        &self,
+        _enable_scoring: EnableScoring<'_>,
+    ) -> tantivy::Result<Box<dyn Weight>> {
+        Ok(Box::new(SpanWeight {
+            terms: self.terms.clone(),
+            matcher: self.matcher,
+        }))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for term in self.terms.iter() {
+            visitor(term, true);
+        }
+    }
+}
+
+struct SpanWeight {
+    terms: Vec<Term>,
+    matcher: SpanMatcher,
+}
+
+impl Weight for SpanWeight {
+    fn scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> tantivy::Result<Box<dyn Scorer>> {
+        let mut postings = Vec::with_capacity(self.terms.len());
+        for term in self.terms.iter() {
+            let inverted_index = reader.inverted_index(term.field())?;
+            match inverted_index
+                .read_postings(term, IndexRecordOption::WithFreqsAndPositions)?
+            {
+                Some(term_postings) => postings.push(term_postings),
+                None => return Ok(Box::new(EmptyScorer)),
+            }
+        }
+
+        Ok(Box::new(SpanScorer::new(postings, self.matcher, boost)))
+    }
+
+    fn explain(
+        &self,
+        reader: &SegmentReader,
+        doc: DocId,
+    ) -> tantivy::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Document #({doc}) does not match"
+            )));
+        }
+
+        Ok(Explanation::new(
+            format!("Span({:?})", self.matcher),
+            scorer.score(),
+        ))
+    }
+}
+
+/// Scores documents where the positions of every term match the span.
+///
+/// Tighter matches score higher, a match with no positions between
+/// the terms scores `1.0` (multiplied by the boost).
+struct SpanScorer {
+    postings: Vec<SegmentPostings>,
+    positions: Vec<Vec<u32>>,
+    matcher: SpanMatcher,
+    boost: Score,
+    doc: DocId,
+    score: Score,
+}
+
+impl SpanScorer {
+    fn new(postings: Vec<SegmentPostings>, matcher: SpanMatcher, boost: Score) -> Self {
+        let mut slf = Self {
+            positions: vec![Vec::new(); postings.len()],
+            postings,
+            matcher,
+            boost,
+            doc: 0,
+            score: 0.0,
+        };
+        slf.find_next_match();
+        slf
+    }
+
+    /// Moves to the next document containing every term where the span matches,
+    /// starting from the current positions of the postings.
+    fn find_next_match(&mut self) -> DocId {
+        loop {
+            let candidate = self.align_postings();
+            if candidate == TERMINATED {
+                self.doc = TERMINATED;
+                return TERMINATED;
+            }
+
+            for (postings, positions) in
+                self.postings.iter_mut().zip(self.positions.iter_mut())
+            {
+                postings.positions(positions);
+            }
+
+            if let Some(used) = self.matcher.matches(&self.positions) {
+                self.doc = candidate;
+                self.score = self.boost / (1.0 + used as Score);
+                return candidate;
+            }
+
+            self.postings[0].advance();
+        }
+    }
+
+    /// Advances the postings until they are all positioned on the same document.
+    fn align_postings(&mut self) -> DocId {
+        let mut candidate = self.postings[0].doc();
+        'align: loop {
+            for postings in self.postings.iter_mut() {
+                let doc = postings.seek(candidate);
+                if doc > candidate {
+                    candidate = doc;
+                    continue 'align;
+                }
+            }
+            return candidate;
+        }
+    }
+}
+
+impl DocSet for SpanScorer {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+
+        self.postings[0].advance();
+        self.find_next_match()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings
+            .iter()
+            .map(|postings| postings.size_hint())
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+impl Scorer for SpanScorer {
+    fn score(&mut self) -> Score {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, STRING, TEXT};
+
+    use super::*;
+
+    #[test]
+    fn test_ordered_slop() {
+        assert_eq!(ordered_slop(&[vec![0], vec![1]]), Some(0));
+        assert_eq!(ordered_slop(&[vec![0], vec![3]]), Some(2));
+        assert_eq!(ordered_slop(&[vec![0, 5], vec![3, 6]]), Some(0));
+        assert_eq!(ordered_slop(&[vec![4], vec![1]]), None);
+        assert_eq!(ordered_slop(&[vec![0], vec![2], vec![3]]), Some(1));
+    }
+
+    #[test]
+    fn test_unordered_slop() {
+        assert_eq!(unordered_slop(&[vec![1], vec![0]]), Some(0));
+        assert_eq!(unordered_slop(&[vec![4], vec![1]]), Some(2));
+        assert_eq!(unordered_slop(&[vec![0, 9], vec![5, 10]]), Some(0));
+        assert_eq!(unordered_slop(&[vec![0], vec![]]), None);
+    }
+
+    #[test]
+    fn test_span_matcher() {
+        let first = SpanMatcher::First { end: 3 };
+        assert_eq!(first.matches(&[vec![2, 8]]), Some(0));
+        assert_eq!(first.matches(&[vec![3]]), None);
+
+        let near = SpanMatcher::Near {
+            slop: 1,
+            in_order: true,
+        };
+        assert_eq!(near.matches(&[vec![0], vec![2]]), Some(1));
+        assert_eq!(near.matches(&[vec![0], vec![3]]), None);
+        assert_eq!(near.matches(&[vec![2], vec![0]]), None);
+
+        let near = SpanMatcher::Near {
+            slop: 1,
+            in_order: false,
+        };
+        assert_eq!(near.matches(&[vec![2], vec![0]]), Some(1));
+    }
+
+    #[test]
+    fn test_span_requires_positions() {
+        let mut schema = SchemaBuilder::new();
+        schema.add_text_field("body", TEXT);
+        schema.add_text_field("tag", STRING);
+        let ctx = QueryContext::new(schema.build());
+
+        let query = SpanNearQuery {
+            field: "body".to_string(),
+            terms: vec!["patent".to_string(), "claim".to_string()],
+            slop: 2,
+            in_order: true,
+        };
+        assert!(query.build(&ctx).is_ok());
+
+        let query = SpanFirstQuery {
+            field: "tag".to_string(),
+            term: "patent".to_string(),
+            end: 2,
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+    }
+}
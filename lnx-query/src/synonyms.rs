@@ -0,0 +1,167 @@
+use lnx_schema::analysis::SynonymMap;
+
+/// The boolean operators of the query syntax, which are never expanded.
+const OPERATORS: [&str; 3] = ["AND", "OR", "NOT"];
+
+enum Segment<'a> {
+    /// Text which is copied through as-is, i.e. whitespace, phrases or field terms.
+    Verbatim(&'a str),
+    /// A bare word which may be expanded.
+    Word(&'a str),
+}
+
+/// Expands the synonyms of a query string.
+///
+/// Each run of bare words matching a rule is replaced with a group of its
+/// alternatives, i.e. `ny pizza` becomes `(ny OR "new york") pizza`. Quoted
+/// phrases, field-targeted terms, operators and any word using query syntax are
+/// left untouched.
+pub fn expand_query_synonyms(query: &str, synonyms: &SynonymMap) -> String {
+    let segments = split_segments(query);
+    let mut expanded = String::with_capacity(query.len());
+    let mut i = 0;
+
+    while i < segments.len() {
+        if let Segment::Verbatim(text) = segments[i] {
+            expanded.push_str(text);
+            i += 1;
+            continue;
+        }
+
+        // Words separated by whitespace only form a run which can match a phrase.
+        let mut words = Vec::new();
+        let mut indices = Vec::new();
+        let mut j = i;
+        while j < segments.len() {
+            match segments[j] {
+                Segment::Word(word) => {
+                    words.push(word);
+                    indices.push(j);
+                },
+                Segment::Verbatim(text) if text.trim().is_empty() => {},
+                Segment::Verbatim(_) => break,
+            }
+            j += 1;
+        }
+
+        let Some((len, outputs)) = synonyms.longest_match(&words) else {
+            expanded.push_str(words[0]);
+            i += 1;
+            continue;
+        };
+
+        push_alternatives(&mut expanded, outputs);
+        i = indices[len - 1] + 1;
+    }
+
+    expanded
+}
+
+fn push_alternatives(expanded: &mut String, outputs: &[Vec<String>]) {
+    let alternatives = outputs
+        .iter()
+        .map(|words| match words.as_slice() {
+            [word] => word.clone(),
+            words => format!("\"{}\"", words.join(" ")),
+        })
+        .collect::<Vec<_>>();
+
+    if let [alternative] = alternatives.as_slice() {
+        expanded.push_str(alternative);
+    } else {
+        expanded.push('(');
+        expanded.push_str(&alternatives.join(" OR "));
+        expanded.push(')');
+    }
+}
+
+fn split_segments(query: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+
+        if c == '"' {
+            for (offset, c) in chars.by_ref() {
+                end = offset + c.len_utf8();
+                if c == '"' {
+                    break;
+                }
+            }
+            segments.push(Segment::Verbatim(&query[start..end]));
+        } else if c.is_whitespace() || c == '(' || c == ')' {
+            segments.push(Segment::Verbatim(&query[start..end]));
+        } else {
+            while let Some(&(offset, c)) = chars.peek() {
+                if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                    break;
+                }
+                end = offset + c.len_utf8();
+                chars.next();
+            }
+
+            let word = &query[start..end];
+            if is_bare_word(word) {
+                segments.push(Segment::Word(word));
+            } else {
+                segments.push(Segment::Verbatim(word));
+            }
+        }
+    }
+
+    segments
+}
+
+fn is_bare_word(word: &str) -> bool {
+    !OPERATORS.contains(&word) && word.chars().all(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use lnx_schema::analysis::SynonymSet;
+
+    use super::*;
+
+    fn synonyms(rules: &[&str]) -> SynonymMap {
+        let set = SynonymSet {
+            rules: rules.iter().map(|rule| rule.to_string()).collect(),
+            ..Default::default()
+        };
+        set.load("test").unwrap()
+    }
+
+    #[test]
+    fn test_expand_query_synonyms() {
+        let map = synonyms(&["ny, new york", "colour => color"]);
+
+        assert_eq!(
+            expand_query_synonyms("NY pizza", &map),
+            r#"(ny OR "new york") pizza"#
+        );
+        assert_eq!(
+            expand_query_synonyms("best new  york bagels", &map),
+            r#"best (ny OR "new york") bagels"#
+        );
+        assert_eq!(
+            expand_query_synonyms("colour AND size", &map),
+            "color AND size"
+        );
+    }
+
+    #[test]
+    fn test_query_syntax_is_kept() {
+        let map = synonyms(&["ny, new york"]);
+
+        for query in [
+            r#""ny" pizza"#,
+            "city:ny",
+            "+ny -pizza",
+            "new AND york",
+            "(new) york",
+            "ny*",
+        ] {
+            assert_eq!(expand_query_synonyms(query, &map), query);
+        }
+    }
+}
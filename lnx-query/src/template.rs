@@ -0,0 +1,280 @@
+use std::collections::BTreeSet;
+
+use lnx_metastore::Metastore;
+use serde_json::{Map, Value as JsonValue};
+
+use crate::error::QueryError;
+
+/// The metastore database search templates are stored in.
+const TEMPLATES_DATABASE: &str = "lnx_search_templates";
+
+#[derive(Debug, Clone, PartialEq)]
+/// A stored search request containing `{{placeholder}}` parameters.
+///
+/// A string which consists of only a placeholder, i.e. `"{{limit}}"`, is replaced by the
+/// parameter value as-is, which allows numbers, arrays and objects to be substituted.
+/// Placeholders embedded within a larger string are replaced with the parameter's text.
+///
+/// Substitution happens on the parsed JSON rather than the raw text so parameter values
+/// can never change the structure of the template.
+pub struct SearchTemplate {
+    source: JsonValue,
+}
+
+impl SearchTemplate {
+    /// Parses a new template from its JSON source.
+    pub fn parse(source: &str) -> Result<Self, QueryError> {
+        let source = serde_json::from_str(source)
+            .map_err(|e| QueryError::Invalid(format!("Invalid search template: {e}")))?;
+        Ok(Self { source })
+    }
+
+    /// The parsed template source.
+    pub fn source(&self) -> &JsonValue {
+        &self.source
+    }
+
+    /// The names of all parameters used by the template.
+    pub fn params(&self) -> BTreeSet<&str> {
+        let mut params = BTreeSet::new();
+        collect_params(&self.source, &mut params);
+        params
+    }
+
+    /// Renders the template with the given parameters, producing the search request body.
+    pub fn render(&self, params: &Map<String, JsonValue>) -> Result<String, QueryError> {
+        let rendered = render_value(&self.source, params)?;
+        Ok(rendered.to_string())
+    }
+}
+
+/// The persisted set of search templates for every index.
+pub struct SearchTemplateStore {
+    metastore: Metastore,
+}
+
+impl SearchTemplateStore {
+    /// Opens the template store within the given metastore.
+    pub fn open(metastore: &Metastore) -> anyhow::Result<Self> {
+        let metastore = metastore.open_database(TEMPLATES_DATABASE)?;
+        Ok(Self { metastore })
+    }
+
+    /// Registers a named template for the index, replacing any existing template.
+    pub fn register(
+        &self,
+        index: &str,
+        name: &str,
+        template: &SearchTemplate,
+    ) -> anyhow::Result<()> {
+        let key = template_key(index, name);
+        self.metastore.put(&key, &template.source.to_string())
+    }
+
+    /// Gets a named template for the index if it exists.
+    pub fn get(
+        &self,
+        index: &str,
+        name: &str,
+    ) -> anyhow::Result<Option<SearchTemplate>> {
+        let key = template_key(index, name);
+        let Some(source) = self.metastore.get::<_, String>(&key)? else {
+            return Ok(None);
+        };
+
+        let template = SearchTemplate::parse(&source)?;
+        Ok(Some(template))
+    }
+
+    /// Removes a named template from the index.
+    pub fn remove(&self, index: &str, name: &str) -> anyhow::Result<()> {
+        let key = template_key(index, name);
+        self.metastore.del(&key)
+    }
+}
+
+fn template_key(index: &str, name: &str) -> String {
+    format!("{index}/{name}")
+}
+
+fn collect_params<'a>(value: &'a JsonValue, params: &mut BTreeSet<&'a str>) {
+    match value {
+        JsonValue::String(s) => {
+            let mut remaining = s.as_str();
+            while let Some((name, rest)) = next_placeholder(remaining) {
+                params.insert(name);
+                remaining = rest;
+            }
+        },
+        JsonValue::Array(values) => {
+            for value in values {
+                collect_params(value, params);
+            }
+        },
+        JsonValue::Object(object) => {
+            for value in object.values() {
+                collect_params(value, params);
+            }
+        },
+        _ => {},
+    }
+}
+
+fn render_value(
+    value: &JsonValue,
+    params: &Map<String, JsonValue>,
+) -> Result<JsonValue, QueryError> {
+    let rendered = match value {
+        JsonValue::String(s) => render_string(s, params)?,
+        JsonValue::Array(values) => values
+            .iter()
+            .map(|value| render_value(value, params))
+            .collect::<Result<Vec<_>, _>>()?
+            .into(),
+        JsonValue::Object(object) => object
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), render_value(value, params)?)))
+            .collect::<Result<Map<_, _>, QueryError>>()?
+            .into(),
+        other => other.clone(),
+    };
+
+    Ok(rendered)
+}
+
+fn render_string(
+    s: &str,
+    params: &Map<String, JsonValue>,
+) -> Result<JsonValue, QueryError> {
+    let get_param = |name: &str| {
+        params.get(name).ok_or_else(|| {
+            QueryError::Invalid(format!("Missing value for template parameter {name:?}"))
+        })
+    };
+
+    // A string made up of a single placeholder is replaced by the value itself.
+    if let Some(name) = whole_placeholder(s) {
+        return get_param(name).cloned();
+    }
+
+    let mut rendered = String::with_capacity(s.len());
+    let mut remaining = s;
+    while let Some(start) = remaining.find("{{") {
+        let Some((name, rest)) = next_placeholder(remaining) else {
+            break;
+        };
+
+        rendered.push_str(&remaining[..start]);
+        match get_param(name)? {
+            JsonValue::String(value) => rendered.push_str(value),
+            value => rendered.push_str(&value.to_string()),
+        }
+        remaining = rest;
+    }
+    rendered.push_str(remaining);
+
+    Ok(JsonValue::String(rendered))
+}
+
+/// Finds the next `{{name}}` placeholder, returning the name and the text after it.
+fn next_placeholder(s: &str) -> Option<(&str, &str)> {
+    let start = s.find("{{")?;
+    let end = s[start..].find("}}")? + start;
+    let name = s[start + 2..end].trim();
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some((name, &s[end + 2..]))
+}
+
+/// Returns the placeholder name if the string consists of only a single placeholder.
+fn whole_placeholder(s: &str) -> Option<&str> {
+    let inner = s.trim().strip_prefix("{{")?.strip_suffix("}}")?;
+    if inner.contains("{{") || inner.contains("}}") {
+        return None;
+    }
+
+    let name = inner.trim();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn params(value: JsonValue) -> Map<String, JsonValue> {
+        let JsonValue::Object(params) = value else {
+            unreachable!()
+        };
+        params
+    }
+
+    #[test]
+    fn test_template_params() {
+        let template = SearchTemplate::parse(
+            r#"{
+                "query": {"query_string": {"query": "{{ text }} AND lang:{{lang}}"}},
+                "filters": [{"range": {"field": "price", "lt": "{{max_price}}"}}]
+            }"#,
+        )
+        .unwrap();
+
+        let params = template.params().into_iter().collect::<Vec<_>>();
+        assert_eq!(params, ["lang", "max_price", "text"]);
+    }
+
+    #[test]
+    fn test_template_render() {
+        let template = SearchTemplate::parse(
+            r#"{
+                "query": {"query_string": {"query": "{{ text }} AND lang:{{lang}}"}},
+                "filters": [{"range": {"field": "price", "lt": "{{max_price}}"}}]
+            }"#,
+        )
+        .unwrap();
+
+        let rendered = template
+            .render(&params(json!({
+                "text": "running shoes",
+                "lang": "en",
+                "max_price": 100,
+            })))
+            .unwrap();
+        let rendered: JsonValue = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(
+            rendered,
+            json!({
+                "query": {"query_string": {"query": "running shoes AND lang:en"}},
+                "filters": [{"range": {"field": "price", "lt": 100}}]
+            }),
+        );
+
+        // Parameters cannot escape the string they are substituted into.
+        let rendered = template
+            .render(&params(json!({
+                "text": "\"}}, \"filters\": []",
+                "lang": "en",
+                "max_price": 100,
+            })))
+            .unwrap();
+        let rendered: JsonValue = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(rendered["filters"][0]["range"]["lt"], json!(100));
+    }
+
+    #[test]
+    fn test_template_missing_param() {
+        let template = SearchTemplate::parse(
+            r#"{"query": {"query_string": {"query": "{{text}}"}}}"#,
+        )
+        .unwrap();
+        let err = template.render(&Map::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid query: Missing value for template parameter \"text\"",
+        );
+    }
+}
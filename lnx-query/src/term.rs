@@ -0,0 +1,295 @@
+use lnx_document::Value;
+use serde::Deserialize;
+use tantivy::query::{Query, TermQuery as TantivyTermQuery, TermSetQuery};
+use tantivy::schema::IndexRecordOption;
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+
+/// The maximum number of values a single `terms` query can match against.
+pub const MAX_TERMS_QUERY_VALUES: usize = 65_536;
+
+#[derive(Debug, Deserialize)]
+/// Matches documents containing the exact term in the given field.
+///
+/// The value is not analyzed, it is cast to the type of the field
+/// and matched as-is. This makes it useful for matching ids, tags and facets.
+pub struct TermQuery<'a> {
+    /// The field to match the term on.
+    pub field: String,
+    #[serde(borrow)]
+    /// The value of the term.
+    pub value: Value<'a>,
+}
+
+impl<'a> TermQuery<'a> {
+    /// Compiles the term into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        if let Some((field, entry, key)) = ctx.resolve_flattened_key(&self.field) {
+            let term = ctx.build_flattened_term(field, entry, key, self.value)?;
+            return Ok(Box::new(TantivyTermQuery::new(
+                term,
+                IndexRecordOption::Basic,
+            )));
+        }
+
+        let (field, entry) = ctx.resolve_field(&self.field)?;
+
+        if !entry.is_indexed() {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "term",
+                "the field is not indexed",
+            ));
+        }
+
+        let term = ctx.build_term(field, entry, self.value)?;
+        Ok(Box::new(TantivyTermQuery::new(
+            term,
+            IndexRecordOption::WithFreqs,
+        )))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Matches documents containing any of the given exact terms in the given field.
+///
+/// The terms are sorted and matched against the term dictionary as a set, which makes
+/// this significantly cheaper than a large disjunction of term queries when matching
+/// against thousands of values, i.e. filtering by a large set of user IDs.
+pub struct TermsQuery<'a> {
+    /// The field to match the terms on.
+    pub field: String,
+    #[serde(borrow)]
+    /// The values of the terms.
+    pub values: Vec<Value<'a>>,
+}
+
+impl<'a> TermsQuery<'a> {
+    /// Compiles the term set into a tantivy query.
+    pub fn build(self, ctx: &QueryContext) -> Result<Box<dyn Query>, QueryError> {
+        if self.values.len() > MAX_TERMS_QUERY_VALUES {
+            return Err(QueryError::Invalid(format!(
+                "Terms query on field {:?} has {} values which exceeds the maximum of {MAX_TERMS_QUERY_VALUES}",
+                self.field,
+                self.values.len(),
+            )));
+        }
+
+        if let Some((field, entry, key)) = ctx.resolve_flattened_key(&self.field) {
+            let terms = self
+                .values
+                .into_iter()
+                .map(|value| ctx.build_flattened_term(field, entry, key, value))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(Box::new(TermSetQuery::new(terms)));
+        }
+
+        let (field, entry) = ctx.resolve_field(&self.field)?;
+
+        if !entry.is_indexed() {
+            return Err(QueryError::unsupported(
+                &self.field,
+                "terms",
+                "the field is not indexed",
+            ));
+        }
+
+        let terms = self
+            .values
+            .into_iter()
+            .map(|value| ctx.build_term(field, entry, value))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Box::new(TermSetQuery::new(terms)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use lnx_schema::analysis::{Analyzer, TokenFilter};
+    use lnx_transforms::BytesEncoding;
+    use tantivy::schema::{
+        SchemaBuilder,
+        TextFieldIndexing,
+        TextOptions,
+        INDEXED,
+        STORED,
+        STRING,
+    };
+    use tantivy::tokenizer::{RawTokenizer, TextAnalyzer, TokenizerManager};
+    use tantivy::Term;
+
+    use super::*;
+
+    fn test_context() -> QueryContext {
+        let mut schema = SchemaBuilder::new();
+        schema.add_u64_field("user_id", INDEXED);
+        schema.add_text_field("tag", STRING);
+        schema.add_text_field("stored_only", STORED);
+        schema.add_bytes_field("payload", INDEXED);
+        schema.add_bytes_field("checksum", INDEXED);
+        schema.add_text_field("attrs", STRING);
+        QueryContext::new(schema.build())
+            .with_bytes_encoding("checksum", BytesEncoding::Hex)
+            .with_flattened_field("attrs")
+            .with_indexed_nulls("tag")
+            .with_field_alias("uid", "user_id")
+    }
+
+    #[test]
+    fn test_term_query_parse() {
+        let json = r#"{"field": "tag", "value": "rust"}"#;
+        let query: TermQuery = serde_json::from_str(json).unwrap();
+        assert_eq!(query.field, "tag");
+        assert!(matches!(query.value, Value::Str(Cow::Borrowed("rust"))));
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "user_id", "value": 42}"#).unwrap();
+        assert_eq!(query.value, Value::U64(42));
+    }
+
+    #[test]
+    fn test_null_term_query_build() {
+        let ctx = test_context();
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "tag", "value": null}"#).unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "user_id", "value": null}"#).unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+    }
+
+    #[test]
+    fn test_normalized_term_query_build() {
+        let mut schema = SchemaBuilder::new();
+        let indexing = TextFieldIndexing::default().set_tokenizer("raw+lowercase");
+        let sku = schema.add_text_field(
+            "sku",
+            TextOptions::default().set_indexing_options(indexing),
+        );
+
+        let tokenizers = TokenizerManager::default();
+        let analyzer = Analyzer::new(TextAnalyzer::from(RawTokenizer))
+            .with_filter(TokenFilter::Lowercase);
+        tokenizers.register("raw+lowercase", TextAnalyzer::from(analyzer));
+
+        let ctx = QueryContext::new(schema.build())
+            .with_tokenizers(tokenizers)
+            .with_normalized_field("sku");
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "sku", "value": "AB-12"}"#).unwrap();
+        let query = query.build(&ctx).unwrap();
+        let query = query.downcast_ref::<TantivyTermQuery>().unwrap();
+        assert_eq!(query.term(), &Term::from_field_text(sku, "ab-12"));
+    }
+
+    #[test]
+    fn test_flattened_term_query_build() {
+        let ctx = test_context();
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "attrs.color", "value": "red"}"#).unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: TermsQuery = serde_json::from_str(
+            r#"{"field": "attrs.shipping.free", "values": [true, 8]}"#,
+        )
+        .unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "tag.color", "value": "red"}"#).unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnknownField(_))
+        ));
+    }
+
+    #[test]
+    fn test_bytes_term_query_build() {
+        let ctx = test_context();
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "payload", "value": "aGVsbG8="}"#)
+                .unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "checksum", "value": "00ff"}"#).unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: TermsQuery =
+            serde_json::from_str(r#"{"field": "checksum", "values": ["00ff", "zz"]}"#)
+                .unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::InvalidValue { .. })
+        ));
+
+        let query: TermQuery =
+            serde_json::from_str(r#"{"field": "payload", "value": "not base64!"}"#)
+                .unwrap();
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_terms_query_parse() {
+        let json = r#"{"field": "tag", "values": ["rust", 42]}"#;
+        let query: TermsQuery = serde_json::from_str(json).unwrap();
+        assert_eq!(query.field, "tag");
+        assert!(matches!(query.values[0], Value::Str(Cow::Borrowed("rust"))));
+        assert_eq!(query.values[1], Value::U64(42));
+    }
+
+    #[test]
+    fn test_terms_query_build() {
+        let ctx = test_context();
+
+        let query: TermsQuery =
+            serde_json::from_str(r#"{"field": "user_id", "values": [1, 2, "3"]}"#)
+                .unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query: TermsQuery =
+            serde_json::from_str(r#"{"field": "uid", "values": [1, 2]}"#).unwrap();
+        assert!(query.build(&ctx).is_ok());
+
+        let query = TermsQuery {
+            field: "user_id".to_string(),
+            values: (0..=MAX_TERMS_QUERY_VALUES as u64)
+                .map(Value::U64)
+                .collect(),
+        };
+        assert!(matches!(query.build(&ctx), Err(QueryError::Invalid(_))));
+
+        let query = TermsQuery {
+            field: "user_id".to_string(),
+            values: vec![Value::from("not-a-number")],
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::InvalidValue { .. })
+        ));
+
+        let query = TermsQuery {
+            field: "stored_only".to_string(),
+            values: vec![Value::from("a")],
+        };
+        assert!(matches!(
+            query.build(&ctx),
+            Err(QueryError::UnsupportedField { .. })
+        ));
+    }
+}
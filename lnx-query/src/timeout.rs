@@ -0,0 +1,202 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::query::Weight;
+use tantivy::{DocId, DocSet, Score, SegmentOrdinal, SegmentReader, TERMINATED};
+
+/// The number of documents collected between each check of the deadline.
+///
+/// Checking the clock for every document is measurably slower for large result sets.
+const DEADLINE_CHECK_INTERVAL: u64 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+/// The progress made collecting a single segment before the search completed or timed out.
+pub struct SegmentProgress {
+    /// The ordinal of the segment within the searcher.
+    pub segment_ord: SegmentOrdinal,
+    /// The number of matching documents which were collected.
+    pub docs_collected: u64,
+    /// If every matching document within the segment was collected.
+    pub completed: bool,
+}
+
+#[derive(Debug)]
+/// The results of a search which may have been cut short by its timeout.
+pub struct TimeoutFruit<F> {
+    /// The (possibly partial) results produced by the inner collector.
+    pub fruit: F,
+    /// If the timeout was exceeded before every segment was collected.
+    pub timed_out: bool,
+    /// The collection progress of each segment.
+    pub segments: Vec<SegmentProgress>,
+}
+
+/// Wraps a collector so that collection stops once the timeout is exceeded.
+///
+/// Cancellation is cooperative, the deadline is checked periodically while iterating
+/// over each segment, once it has passed the remaining documents and segments are skipped
+/// and the inner collector produces its results from the documents gathered so far.
+pub struct TimeoutCollector<C> {
+    inner: C,
+    deadline: Instant,
+}
+
+impl<C> TimeoutCollector<C> {
+    /// Creates a new collector which times out after the given duration.
+    ///
+    /// The timeout starts from when the collector is created.
+    pub fn new(inner: C, timeout: Duration) -> Self {
+        Self::with_deadline(inner, Instant::now() + timeout)
+    }
+
+    /// Creates a new collector which times out at the given deadline.
+    pub fn with_deadline(inner: C, deadline: Instant) -> Self {
+        Self { inner, deadline }
+    }
+
+    fn is_expired(&self) -> bool {
+        Instant::now() >= self.deadline
+    }
+}
+
+impl<C: Collector> Collector for TimeoutCollector<C> {
+    type Fruit = TimeoutFruit<C::Fruit>;
+    type Child = TimeoutSegmentCollector<C::Child>;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(TimeoutSegmentCollector {
+            inner: self.inner.for_segment(segment_ord, reader)?,
+            progress: SegmentProgress {
+                segment_ord,
+                docs_collected: 0,
+                completed: false,
+            },
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.inner.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut fruits = Vec::with_capacity(segment_fruits.len());
+        let mut segments = Vec::with_capacity(segment_fruits.len());
+        for (fruit, progress) in segment_fruits {
+            fruits.push(fruit);
+            segments.push(progress);
+        }
+
+        Ok(TimeoutFruit {
+            fruit: self.inner.merge_fruits(fruits)?,
+            timed_out: segments.iter().any(|progress| !progress.completed),
+            segments,
+        })
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let mut collector = self.for_segment(segment_ord, reader)?;
+
+        if self.is_expired() {
+            return Ok(collector.harvest());
+        }
+
+        let alive_bitset = reader.alive_bitset();
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let mut doc = scorer.doc();
+        while doc != TERMINATED {
+            if alive_bitset.map_or(true, |bitset| bitset.is_alive(doc)) {
+                collector.collect(doc, scorer.score());
+
+                if collector.progress.docs_collected % DEADLINE_CHECK_INTERVAL == 0
+                    && self.is_expired()
+                {
+                    return Ok(collector.harvest());
+                }
+            }
+            doc = scorer.advance();
+        }
+
+        collector.progress.completed = true;
+        Ok(collector.harvest())
+    }
+}
+
+/// The per-segment collector of a [TimeoutCollector].
+pub struct TimeoutSegmentCollector<C> {
+    inner: C,
+    progress: SegmentProgress,
+}
+
+impl<C: SegmentCollector> SegmentCollector for TimeoutSegmentCollector<C> {
+    type Fruit = (C::Fruit, SegmentProgress);
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.inner.collect(doc, score);
+        self.progress.docs_collected += 1;
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        (self.inner.harvest(), self.progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::collector::Count;
+    use tantivy::query::AllQuery;
+    use tantivy::schema::{SchemaBuilder, INDEXED};
+    use tantivy::{doc, Index};
+
+    use super::*;
+
+    fn test_index() -> Index {
+        let mut schema = SchemaBuilder::new();
+        let id = schema.add_u64_field("id", INDEXED);
+        let index = Index::create_in_ram(schema.build());
+
+        let mut writer = index.writer(15_000_000).unwrap();
+        for i in 0..1_000u64 {
+            writer.add_document(doc!(id => i)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        index
+    }
+
+    #[test]
+    fn test_collect_within_timeout() {
+        let index = test_index();
+        let searcher = index.reader().unwrap().searcher();
+
+        let collector = TimeoutCollector::new(Count, Duration::from_secs(30));
+        let result = searcher.search(&AllQuery, &collector).unwrap();
+        assert!(!result.timed_out);
+        assert_eq!(result.fruit, 1_000);
+        assert!(result.segments.iter().all(|progress| progress.completed));
+    }
+
+    #[test]
+    fn test_collect_after_deadline() {
+        let index = test_index();
+        let searcher = index.reader().unwrap().searcher();
+
+        let collector = TimeoutCollector::with_deadline(Count, Instant::now());
+        let result = searcher.search(&AllQuery, &collector).unwrap();
+        assert!(result.timed_out);
+        assert_eq!(result.fruit, 0);
+        assert!(result.segments.iter().all(|progress| !progress.completed));
+    }
+}
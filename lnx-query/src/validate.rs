@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+use crate::context::QueryContext;
+use crate::error::QueryError;
+use crate::query::QueryKind;
+use crate::query_string::QueryStringQuery;
+
+#[derive(Debug, Serialize)]
+/// The result of validating a query against an index.
+pub struct ValidationReport {
+    /// If the query is valid.
+    pub valid: bool,
+    /// The normalized representation of the compiled query if it is valid.
+    pub query: Option<String>,
+    /// Any errors that occurred while parsing or compiling the query.
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationReport {
+    fn valid(query: String) -> Self {
+        Self {
+            valid: true,
+            query: Some(query),
+            errors: Vec::new(),
+        }
+    }
+
+    fn invalid(error: ValidationError) -> Self {
+        Self {
+            valid: false,
+            query: None,
+            errors: vec![error],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+/// A single validation error.
+pub struct ValidationError {
+    /// A human readable description of the error.
+    pub message: String,
+    /// The field the error relates to, if any.
+    pub field: Option<String>,
+    /// The position of the error within the request body, if known.
+    pub position: Option<ErrorPosition>,
+}
+
+impl From<QueryError> for ValidationError {
+    fn from(error: QueryError) -> Self {
+        let field = match &error {
+            QueryError::UnknownField(field) => Some(field.clone()),
+            QueryError::UnsupportedField { field, .. } => Some(field.clone()),
+            QueryError::InvalidValue { field, .. } => Some(field.clone()),
+            _ => None,
+        };
+
+        Self {
+            message: error.to_string(),
+            field,
+            position: None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ValidationError {
+    fn from(error: serde_json::Error) -> Self {
+        let position = ErrorPosition {
+            line: error.line(),
+            column: error.column(),
+        };
+
+        Self {
+            message: error.to_string(),
+            field: None,
+            position: Some(position),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+/// The position of an error within the request body.
+pub struct ErrorPosition {
+    /// The line number, starting at `1`.
+    pub line: usize,
+    /// The column number, starting at `1`.
+    pub column: usize,
+}
+
+#[derive(Deserialize)]
+struct QueryStringRequest {
+    query: String,
+}
+
+#[derive(Deserialize)]
+struct DslRequest<'a> {
+    #[serde(borrow)]
+    query: QueryKind<'a>,
+}
+
+/// Validates a query against the index without executing it.
+///
+/// The body must be an object with a `query` key containing either a
+/// query string or a query DSL object, i.e. `{"query": "title:hello"}`.
+pub fn validate_query(ctx: &QueryContext, body: &str) -> ValidationReport {
+    let result = if let Ok(request) = serde_json::from_str::<QueryStringRequest>(body) {
+        let query = QueryStringQuery {
+            query: request.query,
+            ..Default::default()
+        };
+        query.build(ctx)
+    } else {
+        match serde_json::from_str::<DslRequest>(body) {
+            Ok(request) => request.query.build(ctx),
+            Err(e) => return ValidationReport::invalid(e.into()),
+        }
+    };
+
+    match result {
+        Ok(query) => ValidationReport::valid(format!("{query:?}")),
+        Err(e) => ValidationReport::invalid(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, FAST, INDEXED, TEXT};
+
+    use super::*;
+
+    fn test_context() -> QueryContext {
+        let mut schema = SchemaBuilder::new();
+        schema.add_text_field("title", TEXT);
+        schema.add_u64_field("count", FAST | INDEXED);
+        QueryContext::new(schema.build())
+    }
+
+    #[test]
+    fn test_validate_query_string() {
+        let ctx = test_context();
+
+        let report = validate_query(&ctx, r#"{"query": "title:hello"}"#);
+        assert!(report.valid);
+        assert!(report.query.is_some());
+
+        let report = validate_query(&ctx, r#"{"query": "missing:hello"}"#);
+        assert!(!report.valid);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_dsl() {
+        let ctx = test_context();
+
+        let report = validate_query(
+            &ctx,
+            r#"{"query": {"range": {"field": "count", "gte": 1}}}"#,
+        );
+        assert!(report.valid);
+
+        let report = validate_query(
+            &ctx,
+            r#"{"query": {"range": {"field": "missing", "gte": 1}}}"#,
+        );
+        assert!(!report.valid);
+        assert_eq!(report.errors[0].field.as_deref(), Some("missing"));
+
+        let report = validate_query(&ctx, "{\n  \"query\": {\"unknown\": {}}\n}");
+        assert!(!report.valid);
+        let position = report.errors[0].position.unwrap();
+        assert_eq!(position.line, 2);
+    }
+}
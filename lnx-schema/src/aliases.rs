@@ -0,0 +1,73 @@
+use crate::error::SchemaError;
+use crate::schema::{validate_field_name, IndexSchema};
+
+impl IndexSchema {
+    /// Resolves a field name to the name of the concrete field if it is an alias.
+    ///
+    /// Names which are not aliases are returned as-is.
+    pub fn resolve_alias<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// Checks that every alias has a valid name which does not shadow a field and
+    /// targets a field within the schema.
+    pub(crate) fn validate_aliases(&self) -> Result<(), SchemaError> {
+        for (alias, target) in self.aliases.iter() {
+            validate_field_name(alias)?;
+
+            let name = format!("alias {alias:?}");
+            if self.fields.contains_key(alias) {
+                return Err(SchemaError::invalid_options(
+                    &name,
+                    "an alias cannot have the same name as a field",
+                ));
+            }
+
+            if !self.fields.contains_key(target) {
+                return Err(SchemaError::invalid_options(
+                    &name,
+                    format!("the target field {target:?} does not exist"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_alias() {
+        let schema = parse(
+            r#"{
+                "fields": {"created_at": {"type": "datetime"}},
+                "aliases": {"ts": "created_at"}
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        assert_eq!(schema.resolve_alias("ts"), "created_at");
+        assert_eq!(schema.resolve_alias("created_at"), "created_at");
+        assert_eq!(schema.resolve_alias("missing"), "missing");
+    }
+
+    #[test]
+    fn test_invalid_aliases() {
+        let cases = [
+            r#"{"fields": {"a": {"type": "u64"}}, "aliases": {"a": "a"}}"#,
+            r#"{"fields": {"a": {"type": "u64"}}, "aliases": {"b": "missing"}}"#,
+            r#"{"fields": {"a": {"type": "u64"}}, "aliases": {"_b": "a"}}"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+}
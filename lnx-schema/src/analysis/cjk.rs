@@ -0,0 +1,192 @@
+use tantivy::tokenizer::{Token, Tokenizer};
+
+#[cfg(feature = "cjk-dictionary")]
+pub use self::dictionary::ChineseTokenizer;
+use super::AnalyzerTokenStream;
+
+/// The tokenizer which splits CJK text into overlapping bigrams.
+pub const CJK_TOKENIZER: &str = "cjk";
+#[cfg(feature = "cjk-dictionary")]
+/// The tokenizer which segments Chinese text into words using a dictionary.
+pub const CHINESE_TOKENIZER: &str = "chinese";
+
+/// Returns if the character is a Chinese, Japanese or Korean character.
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{11FF}'     // Hangul Jamo
+        | '\u{3040}'..='\u{309F}'   // Hiragana
+        | '\u{30A0}'..='\u{30FF}'   // Katakana
+        | '\u{3130}'..='\u{318F}'   // Hangul Compatibility Jamo
+        | '\u{31F0}'..='\u{31FF}'   // Katakana Phonetic Extensions
+        | '\u{3400}'..='\u{4DBF}'   // CJK Unified Ideographs Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}'   // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+        | '\u{FF66}'..='\u{FF9F}'   // Halfwidth Katakana
+        | '\u{20000}'..='\u{2A6DF}' // CJK Unified Ideographs Extension B
+    )
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+/// Tokenizes runs of CJK characters into overlapping bigrams, i.e. `東京都` becomes
+/// `東京` and `京都`, while any other text is split into lowercased words like the
+/// `default` tokenizer.
+///
+/// CJK languages do not separate words with whitespace, so without this an entire
+/// sentence is indexed as a single token. Bigrams are emitted at consecutive
+/// positions so phrase queries for longer words still match, a run of a single
+/// character is emitted as a unigram.
+pub struct CjkTokenizer;
+
+impl Tokenizer for CjkTokenizer {
+    type TokenStream<'a> = AnalyzerTokenStream;
+
+    fn token_stream<'a>(&self, text: &'a str) -> Self::TokenStream<'a> {
+        AnalyzerTokenStream::new(cjk_tokens(text))
+    }
+}
+
+fn cjk_tokens(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if is_cjk(c) {
+            let mut run = vec![(start, c)];
+            while let Some(&(offset, c)) = chars.peek() {
+                if !is_cjk(c) {
+                    break;
+                }
+                run.push((offset, c));
+                chars.next();
+            }
+
+            push_bigrams(&mut tokens, &run);
+        } else if c.is_alphanumeric() {
+            let mut end = start + c.len_utf8();
+            while let Some(&(offset, c)) = chars.peek() {
+                if !c.is_alphanumeric() || is_cjk(c) {
+                    break;
+                }
+                end = offset + c.len_utf8();
+                chars.next();
+            }
+
+            push_token(&mut tokens, start, end, text[start..end].to_lowercase());
+        }
+    }
+
+    tokens
+}
+
+fn push_bigrams(tokens: &mut Vec<Token>, run: &[(usize, char)]) {
+    if let [(start, c)] = run {
+        push_token(tokens, *start, start + c.len_utf8(), c.to_string());
+        return;
+    }
+
+    for pair in run.windows(2) {
+        let (start, first) = pair[0];
+        let (offset, second) = pair[1];
+        let text = [first, second].iter().collect();
+        push_token(tokens, start, offset + second.len_utf8(), text);
+    }
+}
+
+fn push_token(
+    tokens: &mut Vec<Token>,
+    offset_from: usize,
+    offset_to: usize,
+    text: String,
+) {
+    tokens.push(Token {
+        offset_from,
+        offset_to,
+        position: tokens.len(),
+        text,
+        position_length: 1,
+    });
+}
+
+#[cfg(feature = "cjk-dictionary")]
+mod dictionary {
+    use std::sync::Arc;
+
+    use jieba_rs::Jieba;
+    use tantivy::tokenizer::Tokenizer;
+
+    use super::{push_token, AnalyzerTokenStream};
+
+    #[derive(Clone)]
+    /// Segments Chinese text into words using the bundled jieba dictionary.
+    ///
+    /// This produces better matches than bigrams at the cost of loading the
+    /// dictionary, so it is only available with the `cjk-dictionary` feature.
+    pub struct ChineseTokenizer {
+        jieba: Arc<Jieba>,
+    }
+
+    impl Default for ChineseTokenizer {
+        fn default() -> Self {
+            Self {
+                jieba: Arc::new(Jieba::new()),
+            }
+        }
+    }
+
+    impl Tokenizer for ChineseTokenizer {
+        type TokenStream<'a> = AnalyzerTokenStream;
+
+        fn token_stream<'a>(&self, text: &'a str) -> Self::TokenStream<'a> {
+            let mut tokens = Vec::new();
+
+            for word in self.jieba.cut_for_search(text, true) {
+                if !word.chars().any(char::is_alphanumeric) {
+                    continue;
+                }
+
+                // Words are slices of the input, so their offsets can be recovered.
+                let start = word.as_ptr() as usize - text.as_ptr() as usize;
+                push_token(&mut tokens, start, start + word.len(), word.to_lowercase());
+            }
+
+            AnalyzerTokenStream::new(tokens)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::TokenStream;
+
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<(String, usize)> {
+        let mut stream = CjkTokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            let token = stream.token();
+            tokens.push((token.text.clone(), token.position));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_cjk_bigrams() {
+        let expected = [("東京", 0), ("京都", 1), ("に", 2), ("tokyo", 3)];
+        let tokens = tokens("東京都、に Tokyo");
+        assert_eq!(
+            tokens,
+            expected.map(|(text, position)| (text.to_string(), position))
+        );
+
+        let mut stream = CjkTokenizer.token_stream("a 한국어");
+        assert!(stream.advance());
+        assert!(stream.advance());
+        assert_eq!(stream.token().text, "한국");
+        assert_eq!(
+            (stream.token().offset_from, stream.token().offset_to),
+            (2, 8)
+        );
+    }
+}
@@ -0,0 +1,853 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use regex::{Regex, RegexBuilder};
+use rust_stemmers::Stemmer;
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::{
+    RawTokenizer,
+    SimpleTokenizer,
+    TextAnalyzer,
+    WhitespaceTokenizer,
+};
+
+use super::{
+    AnalysisResources,
+    Analyzer,
+    ApostropheMode,
+    CharFilter,
+    CjkTokenizer,
+    Language,
+    PathHierarchyTokenizer,
+    PhoneticEncoder,
+    StopWords,
+    TokenFilter,
+    UrlEmailTokenizer,
+    DEFAULT_ELISION_ARTICLES,
+    DEFAULT_PATH_DELIMITER,
+};
+use crate::error::SchemaError;
+use crate::warnings::SchemaWarning;
+
+/// The maximum number of character and token filters within an analyzer.
+pub const MAX_ANALYZER_FILTERS: usize = 32;
+/// The maximum size in bytes of the compiled regex of a pattern replace filter.
+pub const MAX_PATTERN_SIZE: usize = 1 << 20;
+/// The maximum size in characters of the grams produced by n-gram filters.
+pub const MAX_GRAM_SIZE: usize = 32;
+/// The maximum size in characters of the subwords matched by a compound word filter.
+pub const MAX_SUBWORD_SIZE: usize = 64;
+/// The maximum number of words within the shingles produced by a shingle filter.
+pub const MAX_SHINGLE_SIZE: usize = 3;
+/// The number of gram sizes an `ngram` filter can produce before a warning about
+/// the size of the index is returned.
+pub const NGRAM_SIZES_WARNING_THRESHOLD: usize = 2;
+/// The number of gram sizes an `edge_ngram` filter can produce before a warning
+/// about the size of the index is returned.
+pub const EDGE_NGRAM_SIZES_WARNING_THRESHOLD: usize = 16;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// The tokenizer which splits the text of a custom analyzer into tokens.
+pub enum TokenizerKind {
+    #[default]
+    /// Splits text on any non-alphanumeric characters.
+    Simple,
+    /// Splits text on whitespace.
+    Whitespace,
+    /// Keeps the entire text as a single token.
+    Raw,
+    /// Splits CJK text into bigrams, see [CjkTokenizer].
+    Cjk,
+    /// Splits a path into itself and its ancestors, see [PathHierarchyTokenizer].
+    PathHierarchy,
+    /// Keeps URLs and emails intact along with their components, see
+    /// [UrlEmailTokenizer].
+    UrlEmail,
+}
+
+impl TokenizerKind {
+    fn build(&self, path_delimiter: Option<&str>) -> TextAnalyzer {
+        match self {
+            TokenizerKind::Simple => TextAnalyzer::from(SimpleTokenizer),
+            TokenizerKind::Whitespace => TextAnalyzer::from(WhitespaceTokenizer),
+            TokenizerKind::Raw => TextAnalyzer::from(RawTokenizer),
+            TokenizerKind::Cjk => TextAnalyzer::from(CjkTokenizer),
+            TokenizerKind::PathHierarchy => {
+                let delimiter = path_delimiter.unwrap_or(DEFAULT_PATH_DELIMITER);
+                TextAnalyzer::from(PathHierarchyTokenizer::new(delimiter))
+            },
+            TokenizerKind::UrlEmail => TextAnalyzer::from(UrlEmailTokenizer),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+/// A character filter of a custom analyzer.
+pub enum CharFilterDefinition {
+    /// Replaces every occurrence of each key with its value.
+    Mapping { mappings: BTreeMap<String, String> },
+    /// Removes any HTML tags and the contents of `script` and `style` elements, and
+    /// decodes any character references.
+    HtmlStrip,
+    /// Replaces every match of the regex pattern with the replacement, which can
+    /// refer to capture groups, i.e. `$1`.
+    PatternReplace {
+        pattern: String,
+        #[serde(default)]
+        replacement: String,
+    },
+}
+
+impl CharFilterDefinition {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Mapping { mappings } => {
+                if mappings.is_empty() {
+                    return Err(
+                        "a mapping filter must define at least one mapping".into()
+                    );
+                }
+
+                if mappings.keys().any(String::is_empty) {
+                    return Err("mapping keys cannot be empty".into());
+                }
+            },
+            Self::HtmlStrip => {},
+            Self::PatternReplace { pattern, .. } => {
+                compile_pattern(pattern)
+                    .map_err(|e| format!("invalid pattern {pattern:?}: {e}"))?;
+            },
+        }
+
+        Ok(())
+    }
+
+    fn build(&self) -> CharFilter {
+        match self {
+            Self::Mapping { mappings } => {
+                let mappings = mappings
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                CharFilter::Mapping(Arc::new(mappings))
+            },
+            Self::HtmlStrip => CharFilter::HtmlStrip,
+            Self::PatternReplace {
+                pattern,
+                replacement,
+            } => CharFilter::PatternReplace {
+                // The pattern is compiled when the analyzer is validated.
+                pattern: compile_pattern(pattern).expect("Pattern should be valid"),
+                replacement: Arc::from(replacement.as_str()),
+            },
+        }
+    }
+}
+
+/// Compiles the pattern of a pattern replace filter, limiting the size of the
+/// compiled regex as the pattern is user provided.
+fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .size_limit(MAX_PATTERN_SIZE)
+        .build()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+/// A token filter of a custom analyzer.
+pub enum TokenFilterDefinition {
+    /// Lowercases each token.
+    Lowercase,
+    /// Folds any characters with diacritics into their ASCII equivalents.
+    AsciiFolding,
+    /// Stems each token using the snowball stemmer of the language.
+    Stemmer { language: Language },
+    #[cfg(feature = "lemmatization")]
+    /// Replaces each token with its lemma using the named lemma dictionary of the
+    /// index, i.e. `mice` becomes `mouse`, tokens which are not within the dictionary
+    /// are kept as-is.
+    ///
+    /// The dictionary is matched against the text of the token, so this is usually
+    /// preceded by a `lowercase` filter.
+    Lemmatizer { dictionary: String },
+    /// Removes stop words, defaulting to the stop words of the index.
+    StopWords {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stop_words: Option<StopWords>,
+    },
+    /// Removes elided articles from the start of tokens, i.e. `l'avion` becomes
+    /// `avion`, defaulting to the French and Italian articles.
+    ///
+    /// Requires a tokenizer which keeps apostrophes within tokens, i.e. `whitespace`.
+    Elision {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        articles: Option<Vec<String>>,
+    },
+    /// Removes possessives, i.e. `john's` becomes `john`, or otherwise handles the
+    /// apostrophes within tokens according to the `mode`.
+    ///
+    /// Requires a tokenizer which keeps apostrophes within tokens, i.e. `whitespace`.
+    Apostrophe {
+        #[serde(default)]
+        mode: ApostropheMode,
+    },
+    /// Removes any tokens whose length in characters is outside of the range.
+    Length {
+        #[serde(default)]
+        min: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<usize>,
+    },
+    /// Expands synonyms using the named synonym set of the index, which must use
+    /// the `index` mode.
+    Synonyms { set: String },
+    /// Replaces each token with its n-grams, i.e. `fox` becomes `f`, `fo`, `o`, `ox`
+    /// and `x` with a `min_gram` of 1 and `max_gram` of 2.
+    Ngram {
+        min_gram: usize,
+        max_gram: usize,
+        #[serde(default)]
+        preserve_original: bool,
+    },
+    /// Replaces each token with its prefixes, i.e. `fox` becomes `f`, `fo` and `fox`
+    /// with a `min_gram` of 1 and `max_gram` of 3.
+    EdgeNgram {
+        min_gram: usize,
+        max_gram: usize,
+        #[serde(default)]
+        preserve_original: bool,
+    },
+    /// Adds shingles, the word n-grams of between `min_shingle_size` and
+    /// `max_shingle_size` adjacent tokens joined by the `separator`, i.e. `quick fox`
+    /// for `quick` and `fox`.
+    Shingle {
+        #[serde(default = "default_shingle_size")]
+        min_shingle_size: usize,
+        #[serde(default = "default_shingle_size")]
+        max_shingle_size: usize,
+        #[serde(default = "default_true")]
+        output_unigrams: bool,
+        #[serde(default = "default_shingle_separator")]
+        separator: String,
+    },
+    /// Replaces each token with its phonetic code, so names which sound alike match,
+    /// i.e. `Smith` and `Smyth`.
+    Phonetic {
+        encoder: PhoneticEncoder,
+        #[serde(default)]
+        preserve_original: bool,
+    },
+    /// Adds the subwords of compound words which are within the named word list of
+    /// the index, i.e. `dampf` and `schiff` for `Donaudampfschiff`.
+    DictionaryDecompounder {
+        word_list: String,
+        #[serde(default = "default_min_word_size")]
+        min_word_size: usize,
+        #[serde(default = "default_min_subword_size")]
+        min_subword_size: usize,
+        #[serde(default = "default_max_subword_size")]
+        max_subword_size: usize,
+        #[serde(default)]
+        only_longest_match: bool,
+    },
+}
+
+fn default_min_word_size() -> usize {
+    5
+}
+
+fn default_min_subword_size() -> usize {
+    2
+}
+
+fn default_max_subword_size() -> usize {
+    15
+}
+
+fn default_shingle_size() -> usize {
+    2
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_shingle_separator() -> String {
+    " ".to_string()
+}
+
+impl TokenFilterDefinition {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::StopWords {
+                stop_words: Some(stop_words),
+            } => stop_words.validate().map_err(|e| e.to_string()),
+            Self::Length {
+                min,
+                max: Some(max),
+            } if max < min || *max == 0 => {
+                Err(format!("invalid token length range {min}..={max}"))
+            },
+            Self::Elision {
+                articles: Some(articles),
+            } => {
+                if articles.is_empty() {
+                    return Err(
+                        "an elision filter must define at least one article".into()
+                    );
+                }
+
+                if articles.iter().any(|article| article.trim().is_empty()) {
+                    return Err("elision articles cannot be empty".into());
+                }
+
+                Ok(())
+            },
+            Self::Ngram {
+                min_gram, max_gram, ..
+            }
+            | Self::EdgeNgram {
+                min_gram, max_gram, ..
+            } => {
+                if *min_gram == 0 || max_gram < min_gram {
+                    return Err(format!(
+                        "invalid gram size range {min_gram}..={max_gram}"
+                    ));
+                }
+
+                if *max_gram > MAX_GRAM_SIZE {
+                    return Err(format!(
+                        "the maximum gram size cannot be greater than {MAX_GRAM_SIZE}"
+                    ));
+                }
+
+                Ok(())
+            },
+            Self::Shingle {
+                min_shingle_size,
+                max_shingle_size,
+                ..
+            } => {
+                if *min_shingle_size < 2 || max_shingle_size < min_shingle_size {
+                    return Err(format!(
+                        "invalid shingle size range {min_shingle_size}..={max_shingle_size}"
+                    ));
+                }
+
+                if *max_shingle_size > MAX_SHINGLE_SIZE {
+                    return Err(format!(
+                        "the maximum shingle size cannot be greater than {MAX_SHINGLE_SIZE}"
+                    ));
+                }
+
+                Ok(())
+            },
+            Self::DictionaryDecompounder {
+                min_subword_size,
+                max_subword_size,
+                ..
+            } => {
+                if *min_subword_size == 0 || max_subword_size < min_subword_size {
+                    return Err(format!(
+                        "invalid subword size range {min_subword_size}..={max_subword_size}"
+                    ));
+                }
+
+                if *max_subword_size > MAX_SUBWORD_SIZE {
+                    return Err(format!(
+                        "the maximum subword size cannot be greater than {MAX_SUBWORD_SIZE}"
+                    ));
+                }
+
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns a warning if the filter is likely to significantly increase the size
+    /// of the index.
+    fn size_warning(&self) -> Option<String> {
+        let (kind, min_gram, max_gram, threshold) = match self {
+            Self::Ngram {
+                min_gram, max_gram, ..
+            } => ("ngram", min_gram, max_gram, NGRAM_SIZES_WARNING_THRESHOLD),
+            Self::EdgeNgram {
+                min_gram, max_gram, ..
+            } => (
+                "edge_ngram",
+                min_gram,
+                max_gram,
+                EDGE_NGRAM_SIZES_WARNING_THRESHOLD,
+            ),
+            _ => return None,
+        };
+
+        let sizes = max_gram - min_gram + 1;
+        if sizes <= threshold {
+            return None;
+        }
+
+        Some(format!(
+            "the `{kind}` filter produces {sizes} gram sizes for every token, which can \
+             significantly increase the size of the index, consider narrowing the range \
+             between `min_gram` and `max_gram`"
+        ))
+    }
+
+    fn build(&self, resources: &AnalysisResources) -> TokenFilter {
+        match self {
+            Self::Lowercase => TokenFilter::Lowercase,
+            Self::AsciiFolding => TokenFilter::AsciiFolding,
+            Self::Stemmer { language } => TokenFilter::Stemmer {
+                stemmer: Arc::new(Stemmer::create(language.algorithm())),
+                exclusions: resources.stem_exclusions.clone(),
+            },
+            #[cfg(feature = "lemmatization")]
+            Self::Lemmatizer { dictionary } => TokenFilter::Lemmatizer(
+                resources
+                    .lemma_dictionaries
+                    .get(dictionary)
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+            Self::StopWords { stop_words } => {
+                let stop_words = stop_words.as_ref().unwrap_or(&resources.stop_words);
+                TokenFilter::StopWords(stop_words.word_set())
+            },
+            Self::Synonyms { set } => {
+                let synonyms = resources.synonyms.get(set).cloned().unwrap_or_default();
+                TokenFilter::Synonyms(synonyms)
+            },
+            Self::Elision { articles } => {
+                let articles = match articles {
+                    Some(articles) => articles
+                        .iter()
+                        .map(|article| article.trim().to_lowercase())
+                        .collect(),
+                    None => DEFAULT_ELISION_ARTICLES
+                        .iter()
+                        .map(|article| article.to_string())
+                        .collect(),
+                };
+                TokenFilter::Elision(Arc::new(articles))
+            },
+            Self::Apostrophe { mode } => TokenFilter::Apostrophe(*mode),
+            Self::Length { min, max } => TokenFilter::Length {
+                min: *min,
+                max: max.unwrap_or(usize::MAX),
+            },
+            Self::Ngram {
+                min_gram,
+                max_gram,
+                preserve_original,
+            } => TokenFilter::Ngram {
+                min: *min_gram,
+                max: *max_gram,
+                edge: false,
+                preserve_original: *preserve_original,
+            },
+            Self::EdgeNgram {
+                min_gram,
+                max_gram,
+                preserve_original,
+            } => TokenFilter::Ngram {
+                min: *min_gram,
+                max: *max_gram,
+                edge: true,
+                preserve_original: *preserve_original,
+            },
+            Self::Shingle {
+                min_shingle_size,
+                max_shingle_size,
+                output_unigrams,
+                separator,
+            } => TokenFilter::Shingle {
+                min: *min_shingle_size,
+                max: *max_shingle_size,
+                output_unigrams: *output_unigrams,
+                separator: Arc::from(separator.as_str()),
+            },
+            Self::Phonetic {
+                encoder,
+                preserve_original,
+            } => TokenFilter::Phonetic {
+                encoder: *encoder,
+                preserve_original: *preserve_original,
+            },
+            Self::DictionaryDecompounder {
+                word_list,
+                min_word_size,
+                min_subword_size,
+                max_subword_size,
+                only_longest_match,
+            } => TokenFilter::Decompound {
+                words: resources
+                    .word_lists
+                    .get(word_list)
+                    .cloned()
+                    .unwrap_or_default(),
+                min_word_size: *min_word_size,
+                min_subword_size: *min_subword_size,
+                max_subword_size: *max_subword_size,
+                only_longest_match: *only_longest_match,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A user defined analyzer, made up of a pipeline of character filters, a tokenizer
+/// and token filters which are applied in order.
+pub struct AnalyzerDefinition {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The filters applied to the text before it is tokenized.
+    pub char_filters: Vec<CharFilterDefinition>,
+    #[serde(default)]
+    /// The tokenizer which splits the text into tokens.
+    pub tokenizer: TokenizerKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The delimiter of the `path_hierarchy` tokenizer.
+    ///
+    /// Defaults to [DEFAULT_PATH_DELIMITER].
+    pub path_delimiter: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The filters applied to the produced tokens.
+    pub filters: Vec<TokenFilterDefinition>,
+}
+
+impl AnalyzerDefinition {
+    /// Checks the filters of the analyzer are valid.
+    pub fn validate(&self, name: &str) -> Result<(), SchemaError> {
+        let invalid = |reason: String| {
+            SchemaError::InvalidAnalysis(format!("analyzer {name:?}: {reason}"))
+        };
+
+        if self.char_filters.len() + self.filters.len() > MAX_ANALYZER_FILTERS {
+            return Err(invalid(format!(
+                "an analyzer can have at most {MAX_ANALYZER_FILTERS} filters"
+            )));
+        }
+
+        if let Some(delimiter) = self.path_delimiter.as_deref() {
+            if self.tokenizer != TokenizerKind::PathHierarchy {
+                return Err(invalid(
+                    "a path delimiter can only be set for the `path_hierarchy` tokenizer"
+                        .to_string(),
+                ));
+            }
+
+            if delimiter.is_empty() {
+                return Err(invalid("the path delimiter cannot be empty".to_string()));
+            }
+        }
+
+        for filter in self.char_filters.iter() {
+            filter.validate().map_err(invalid)?;
+        }
+
+        for filter in self.filters.iter() {
+            filter.validate().map_err(invalid)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns any warnings about filters which are valid but are likely to
+    /// significantly increase the size of the index.
+    pub fn warnings(&self, name: &str) -> Vec<SchemaWarning> {
+        self.filters
+            .iter()
+            .enumerate()
+            .filter_map(|(i, filter)| {
+                let message = filter.size_warning()?;
+                Some(SchemaWarning {
+                    path: format!("analysis.analyzers.{name}.filters[{i}]"),
+                    message,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the names of the synonym sets used by the analyzer.
+    pub fn synonym_sets(&self) -> impl Iterator<Item = &str> {
+        self.filters.iter().filter_map(|filter| match filter {
+            TokenFilterDefinition::Synonyms { set } => Some(set.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the names of the word lists used by the analyzer.
+    pub fn word_lists(&self) -> impl Iterator<Item = &str> {
+        self.filters.iter().filter_map(|filter| match filter {
+            TokenFilterDefinition::DictionaryDecompounder { word_list, .. } => {
+                Some(word_list.as_str())
+            },
+            _ => None,
+        })
+    }
+
+    #[cfg(feature = "lemmatization")]
+    /// Returns the names of the lemma dictionaries used by the analyzer.
+    pub fn lemma_dictionaries(&self) -> impl Iterator<Item = &str> {
+        self.filters.iter().filter_map(|filter| match filter {
+            TokenFilterDefinition::Lemmatizer { dictionary } => {
+                Some(dictionary.as_str())
+            },
+            _ => None,
+        })
+    }
+
+    /// Builds the analysis pipeline of the analyzer.
+    ///
+    /// Stop-word filters which do not specify their own list use the stop words of
+    /// the index. The analyzer must have been validated by [Self::validate].
+    pub fn build(&self, resources: &AnalysisResources) -> Analyzer {
+        let mut analyzer =
+            Analyzer::new(self.tokenizer.build(self.path_delimiter.as_deref()));
+
+        for filter in self.char_filters.iter() {
+            analyzer = analyzer.with_char_filter(filter.build());
+        }
+
+        for filter in self.filters.iter() {
+            analyzer = analyzer.with_filter(filter.build(resources));
+        }
+
+        analyzer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> AnalyzerDefinition {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_custom_analyzer() {
+        let definition = parse(
+            r#"{
+                "char_filters": [{"type": "mapping", "mappings": {"+": " plus "}}],
+                "tokenizer": "whitespace",
+                "filters": [
+                    {"type": "lowercase"},
+                    {"type": "stop_words"},
+                    {"type": "ascii_folding"},
+                    {"type": "stemmer", "language": "english"},
+                    {"type": "length", "max": 8}
+                ]
+            }"#,
+        );
+        definition.validate("products").unwrap();
+
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("The Cafés C+ Programming")
+            .into_iter()
+            .map(|token| (token.text, token.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                ("cafe".to_string(), 1),
+                ("c".to_string(), 2),
+                ("plus".to_string(), 3),
+                ("program".to_string(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_char_filters() {
+        let definition = parse(
+            r#"{
+                "char_filters": [
+                    {"type": "html_strip"},
+                    {"type": "pattern_replace", "pattern": "(\\d+)-(\\d+)", "replacement": "$1$2"}
+                ],
+                "filters": [{"type": "lowercase"}]
+            }"#,
+        );
+        definition.validate("content").unwrap();
+
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("<p>Call <b>555-1234</b> &amp; ask</p>")
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, ["call", "5551234", "ask"]);
+    }
+
+    #[test]
+    fn test_path_hierarchy_analyzer() {
+        let definition = parse(
+            r#"{
+                "tokenizer": "path_hierarchy",
+                "path_delimiter": ">",
+                "filters": [{"type": "lowercase"}]
+            }"#,
+        );
+        definition.validate("categories").unwrap();
+
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("Books>Fiction")
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, ["books", "books>fiction"]);
+    }
+
+    #[test]
+    fn test_phonetic_filter() {
+        let definition = parse(
+            r#"{"filters": [{"type": "phonetic", "encoder": "metaphone", "preserve_original": true}]}"#,
+        );
+        definition.validate("names").unwrap();
+
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("Jon Smyth 42")
+            .into_iter()
+            .map(|token| (token.text, token.position))
+            .collect::<Vec<_>>();
+        let expected = [("Jon", 0), ("JN", 0), ("Smyth", 1), ("SM0", 1), ("42", 2)];
+        assert_eq!(tokens, expected.map(|(text, pos)| (text.to_string(), pos)));
+    }
+
+    #[test]
+    fn test_elision_and_apostrophe_filters() {
+        let definition = parse(
+            r#"{
+                "tokenizer": "whitespace",
+                "filters": [{"type": "elision"}, {"type": "lowercase"}]
+            }"#,
+        );
+        definition.validate("french").unwrap();
+
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("L'avion d’Italie aujourd'hui")
+            .into_iter()
+            .map(|token| (token.text, token.offset_from))
+            .collect::<Vec<_>>();
+        let expected = [("avion", 2), ("italie", 12), ("aujourd'hui", 19)];
+        assert_eq!(
+            tokens,
+            expected.map(|(text, from)| (text.to_string(), from))
+        );
+
+        let definition = parse(
+            r#"{
+                "tokenizer": "whitespace",
+                "filters": [{"type": "lowercase"}, {"type": "apostrophe"}]
+            }"#,
+        );
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("John's students’ don't")
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, ["john", "students", "don't"]);
+    }
+
+    #[test]
+    fn test_shingles() {
+        let definition =
+            parse(r#"{"filters": [{"type": "shingle", "max_shingle_size": 3}]}"#);
+        definition.validate("shingles").unwrap();
+
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("quick brown fox")
+            .into_iter()
+            .map(|token| (token.text, token.position))
+            .collect::<Vec<_>>();
+        let expected = [
+            ("quick", 0),
+            ("quick brown", 0),
+            ("quick brown fox", 0),
+            ("brown", 1),
+            ("brown fox", 1),
+            ("fox", 2),
+        ];
+        assert_eq!(tokens, expected.map(|(text, pos)| (text.to_string(), pos)));
+
+        let definition = parse(
+            r#"{
+                "filters": [
+                    {"type": "stop_words"},
+                    {"type": "shingle", "output_unigrams": false, "separator": "_"}
+                ]
+            }"#,
+        );
+        let tokens = definition
+            .build(&AnalysisResources::default())
+            .analyze("quick and the fox jumps")
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, ["fox_jumps"]);
+    }
+
+    #[test]
+    fn test_invalid_analyzers() {
+        let cases = [
+            r#"{"char_filters": [{"type": "mapping", "mappings": {}}]}"#,
+            r#"{"path_delimiter": ">"}"#,
+            r#"{"tokenizer": "path_hierarchy", "path_delimiter": ""}"#,
+            r#"{"char_filters": [{"type": "pattern_replace", "pattern": "(unclosed"}]}"#,
+            r#"{"filters": [{"type": "length", "min": 5, "max": 2}]}"#,
+            r#"{"filters": [{"type": "stop_words", "stop_words": [""]}]}"#,
+            r#"{"filters": [{"type": "ngram", "min_gram": 0, "max_gram": 2}]}"#,
+            r#"{"filters": [{"type": "edge_ngram", "min_gram": 3, "max_gram": 2}]}"#,
+            r#"{"filters": [{"type": "edge_ngram", "min_gram": 1, "max_gram": 64}]}"#,
+            r#"{"filters": [{"type": "shingle", "min_shingle_size": 1}]}"#,
+            r#"{"filters": [{"type": "elision", "articles": []}]}"#,
+            r#"{"filters": [{"type": "dictionary_decompounder", "word_list": "a", "max_subword_size": 1}]}"#,
+            r#"{"filters": [{"type": "shingle", "max_shingle_size": 4}]}"#,
+        ];
+
+        for case in cases {
+            assert!(
+                parse(case).validate("a").is_err(),
+                "{case} should be rejected"
+            );
+        }
+
+        assert!(serde_json::from_str::<AnalyzerDefinition>(
+            r#"{"filters": [{"type": "unknown"}]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_ngram_size_warnings() {
+        let definition = parse(
+            r#"{
+                "filters": [
+                    {"type": "lowercase"},
+                    {"type": "ngram", "min_gram": 1, "max_gram": 5},
+                    {"type": "edge_ngram", "min_gram": 2, "max_gram": 10}
+                ]
+            }"#,
+        );
+        definition.validate("autocomplete").unwrap();
+
+        let warnings = definition.warnings("autocomplete");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].path,
+            "analysis.analyzers.autocomplete.filters[1]"
+        );
+        assert!(warnings[0].message.contains("5 gram sizes"));
+    }
+}
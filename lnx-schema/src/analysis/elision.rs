@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// The articles removed by the `elision` filter by default, covering French and
+/// Italian, i.e. `l` of `l'avion`.
+pub const DEFAULT_ELISION_ARTICLES: &[&str] = &[
+    // French
+    "l", "m", "t", "qu", "n", "s", "j", "d", "c", "jusqu", "quoiqu", "lorsqu", "puisqu",
+    // Italian
+    "all", "dall", "dell", "nell", "sull", "coll", "pell", "gl", "agl", "dagl", "degl",
+    "negl", "sugl", "un", "v",
+];
+
+const APOSTROPHES: [char; 2] = ['\'', '\u{2019}'];
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the `apostrophe` filter handles tokens containing apostrophes.
+pub enum ApostropheMode {
+    #[default]
+    /// Removes trailing possessives, i.e. `john's` becomes `john` and `students'`
+    /// becomes `students`.
+    Possessive,
+    /// Removes the first apostrophe and everything after it, i.e. `türkiye'de`
+    /// becomes `türkiye`.
+    Remove,
+    /// Removes the apostrophes while keeping the rest of the token, i.e. `o'neil`
+    /// becomes `oneil`.
+    Join,
+}
+
+/// Returns the length in bytes of the elided article and its apostrophe at the
+/// start of the word, i.e. `l'` of `l'avion`, if the article is within the set.
+///
+/// The articles are expected to be lowercase.
+pub(crate) fn elided_prefix_len(
+    word: &str,
+    is_article: impl Fn(&str) -> bool,
+) -> Option<usize> {
+    let (start, apostrophe) = word.match_indices(APOSTROPHES).next()?;
+    let end = start + apostrophe.len();
+    if start == 0 || end == word.len() {
+        return None;
+    }
+
+    is_article(&word[..start].to_lowercase()).then_some(end)
+}
+
+impl ApostropheMode {
+    /// Applies the mode to the word, returning the new word and the number of bytes
+    /// removed from its end, or `None` if the word is unchanged.
+    pub(crate) fn apply(&self, word: &str) -> Option<(String, usize)> {
+        let (start, apostrophe) = word.match_indices(APOSTROPHES).next()?;
+
+        let kept = match self {
+            Self::Possessive => {
+                let suffix = &word[start + apostrophe.len()..];
+                if !(suffix.is_empty() || suffix.eq_ignore_ascii_case("s")) {
+                    return None;
+                }
+                &word[..start]
+            },
+            Self::Remove => &word[..start],
+            Self::Join => {
+                let joined = word.replace(APOSTROPHES, "");
+                return Some((joined, 0));
+            },
+        };
+
+        if kept.is_empty() {
+            return None;
+        }
+
+        Some((kept.to_string(), word.len() - kept.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elided_prefix_len() {
+        let is_article = |article: &str| DEFAULT_ELISION_ARTICLES.contains(&article);
+        assert_eq!(elided_prefix_len("l'avion", is_article), Some(2));
+        assert_eq!(elided_prefix_len("L’avion", is_article), Some(4));
+        assert_eq!(elided_prefix_len("dell'acqua", is_article), Some(5));
+        assert_eq!(elided_prefix_len("aujourd'hui", is_article), None);
+        assert_eq!(elided_prefix_len("l'", is_article), None);
+        assert_eq!(elided_prefix_len("'avion", is_article), None);
+    }
+
+    #[test]
+    fn test_apostrophe_modes() {
+        let apply = |mode: ApostropheMode, word: &str| mode.apply(word);
+
+        assert_eq!(
+            apply(ApostropheMode::Possessive, "John's"),
+            Some(("John".to_string(), 2))
+        );
+        assert_eq!(
+            apply(ApostropheMode::Possessive, "students’"),
+            Some(("students".to_string(), 3))
+        );
+        assert_eq!(apply(ApostropheMode::Possessive, "don't"), None);
+        assert_eq!(
+            apply(ApostropheMode::Remove, "türkiye'de"),
+            Some(("türkiye".to_string(), 3))
+        );
+        assert_eq!(
+            apply(ApostropheMode::Join, "o'neil"),
+            Some(("oneil".to_string(), 0))
+        );
+        assert_eq!(apply(ApostropheMode::Remove, "plain"), None);
+    }
+}
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+
+/// The elements whose contents are removed along with their tags.
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style"];
+/// The elements which separate words, so their tags are replaced with a space
+/// rather than removed, i.e. `one<br>two` becomes `one two`.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// Removes any HTML tags, comments, and the contents of `script` and `style`
+/// elements from the text, and decodes any character references.
+///
+/// Inline tags are removed entirely so `<b>He</b>llo` becomes `Hello`, while block
+/// tags are replaced with a space.
+pub fn strip_html(text: &str) -> Cow<str> {
+    if !text.contains(['<', '&']) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut remaining = text;
+
+    while let Some(start) = remaining.find(['<', '&']) {
+        output.push_str(&remaining[..start]);
+        remaining = &remaining[start..];
+
+        if remaining.starts_with('&') {
+            match decode_reference(remaining) {
+                Some((decoded, len)) => {
+                    output.push(decoded);
+                    remaining = &remaining[len..];
+                },
+                None => {
+                    output.push('&');
+                    remaining = &remaining[1..];
+                },
+            }
+            continue;
+        }
+
+        if let Some(comment) = remaining.strip_prefix("<!--") {
+            remaining = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let Some(end) = remaining.find('>') else {
+            // A `<` which never closes is not a tag, i.e. `a < b`.
+            output.push('<');
+            remaining = &remaining[1..];
+            continue;
+        };
+
+        let contents = &remaining[1..end];
+        let Some(name) = tag_name(contents) else {
+            output.push('<');
+            remaining = &remaining[1..];
+            continue;
+        };
+
+        remaining = &remaining[end + 1..];
+        let is_closing = contents.starts_with('/');
+        if SKIPPED_ELEMENTS.contains(&name.as_str()) && !is_closing {
+            let closing = format!("</{name}");
+            remaining = find_ignore_case(remaining, &closing)
+                .and_then(|offset| {
+                    let rest = &remaining[offset..];
+                    rest.find('>').map(|end| &rest[end + 1..])
+                })
+                .unwrap_or("");
+        }
+
+        if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            output.push(' ');
+        }
+    }
+
+    output.push_str(remaining);
+    Cow::Owned(output)
+}
+
+/// Returns the lowercased name of a tag from its contents, i.e. `div class="a"`
+/// or `/div`, or `None` if the contents are not a tag.
+///
+/// Declarations like `<!DOCTYPE html>` have an empty name.
+fn tag_name(contents: &str) -> Option<String> {
+    let contents = contents.strip_prefix('/').unwrap_or(contents);
+    if contents.starts_with(['!', '?']) {
+        return Some(String::new());
+    }
+
+    let name = contents
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    Some(name.to_ascii_lowercase())
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+/// Decodes the character reference at the start of the text, returning the
+/// character and the length of the reference.
+fn decode_reference(text: &str) -> Option<(char, usize)> {
+    let end = text[1..].find(';').filter(|&end| end <= 10)? + 1;
+    let name = &text[1..end];
+
+    let decoded = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let code = match name.strip_prefix('#')? {
+                hex if hex.starts_with(['x', 'X']) => {
+                    u32::from_str_radix(&hex[1..], 16).ok()?
+                },
+                decimal => decimal.parse().ok()?,
+            };
+            char::from_u32(code)?
+        },
+    };
+
+    Some((decoded, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_html() {
+        assert!(matches!(strip_html("plain text"), Cow::Borrowed(_)));
+        assert_eq!(strip_html("<p>Hello <b>Wor</b>ld</p>"), " Hello World ");
+        assert_eq!(strip_html("one<br/>two"), "one two");
+        assert_eq!(strip_html("<!DOCTYPE html><TITLE>Hi</TITLE>"), "Hi");
+        assert_eq!(
+            strip_html("a<script>var x = '<p>';</script>b<!-- note -->c"),
+            "abc"
+        );
+        assert_eq!(
+            strip_html("Fish &amp; Chips &#233;&#x41;"),
+            "Fish & Chips éA"
+        );
+        assert_eq!(strip_html("1 < 2 & 3 > 2"), "1 < 2 & 3 > 2");
+    }
+}
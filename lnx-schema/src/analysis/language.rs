@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::TextAnalyzer;
+
+use super::{stop_words, Analyzer, StopWords, TokenFilter};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A language with bundled analysis support.
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+    Russian,
+    Portuguese,
+    Italian,
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Language {
+    /// Every supported language.
+    pub const ALL: &'static [Language] = &[
+        Language::English,
+        Language::German,
+        Language::French,
+        Language::Spanish,
+        Language::Russian,
+        Language::Portuguese,
+        Language::Italian,
+    ];
+
+    /// The name of the language as it appears within the schema.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "english",
+            Language::German => "german",
+            Language::French => "french",
+            Language::Spanish => "spanish",
+            Language::Russian => "russian",
+            Language::Portuguese => "portuguese",
+            Language::Italian => "italian",
+        }
+    }
+
+    /// Gets the language from its name, i.e. `english`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|language| language.name() == name)
+    }
+
+    /// The bundled stop words of the language.
+    pub fn stop_words(&self) -> &'static [&'static str] {
+        match self {
+            Language::English => stop_words::ENGLISH,
+            Language::German => stop_words::GERMAN,
+            Language::French => stop_words::FRENCH,
+            Language::Spanish => stop_words::SPANISH,
+            Language::Russian => stop_words::RUSSIAN,
+            Language::Portuguese => stop_words::PORTUGUESE,
+            Language::Italian => stop_words::ITALIAN,
+        }
+    }
+
+    /// The snowball stemming algorithm of the language.
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Language::English => Algorithm::English,
+            Language::German => Algorithm::German,
+            Language::French => Algorithm::French,
+            Language::Spanish => Algorithm::Spanish,
+            Language::Russian => Algorithm::Russian,
+            Language::Portuguese => Algorithm::Portuguese,
+            Language::Italian => Algorithm::Italian,
+        }
+    }
+
+    /// Builds the analyzer of the language, which removes the language's stop words
+    /// from the tokens of the given tokenizer and then stems them.
+    ///
+    /// The tokenizer is expected to lowercase its tokens, tokens within the stem
+    /// exclusions are not stemmed.
+    pub fn analyzer(
+        &self,
+        tokenizer: TextAnalyzer,
+        stem_exclusions: Arc<HashSet<String>>,
+    ) -> Analyzer {
+        let stop_words = StopWords::Language(*self).word_set();
+        let stemmer = Stemmer::create(self.algorithm());
+
+        Analyzer::new(tokenizer)
+            .with_filter(TokenFilter::StopWords(stop_words))
+            .with_filter(TokenFilter::Stemmer {
+                stemmer: Arc::new(stemmer),
+                exclusions: stem_exclusions,
+            })
+    }
+}
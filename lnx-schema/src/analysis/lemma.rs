@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SchemaError;
+
+/// The maximum number of entries within a lemma dictionary.
+pub const MAX_LEMMA_DICTIONARY_SIZE: usize = 5_000_000;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A named dictionary mapping the inflected forms of words to their lemma, i.e.
+/// `mice` to `mouse` or `лучше` to `хороший`, used by the `lemmatizer` filter.
+pub struct LemmaDictionary {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The inflected forms of words mapped to their lemma.
+    pub lemmas: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// A file containing additional entries, one `lemma<TAB>form` pair per line as
+    /// used by the common lemmatization lists for English and Russian.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub path: Option<PathBuf>,
+}
+
+impl LemmaDictionary {
+    /// Loads the lowercased entries of the dictionary, including the entries within
+    /// its file, keyed by the inflected form.
+    pub fn load(&self, name: &str) -> Result<HashMap<String, String>, SchemaError> {
+        let invalid = |reason: String| {
+            SchemaError::InvalidAnalysis(format!("lemma dictionary {name:?}: {reason}"))
+        };
+
+        let mut lemmas = HashMap::with_capacity(self.lemmas.len());
+        for (form, lemma) in self.lemmas.iter() {
+            insert_lemma(&mut lemmas, form, lemma).map_err(invalid)?;
+        }
+
+        if let Some(path) = self.path.as_ref() {
+            let file = std::fs::read_to_string(path).map_err(|e| {
+                invalid(format!("unable to read {}: {e}", path.display()))
+            })?;
+
+            for (i, line) in file.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let Some((lemma, form)) = line.split_once('\t') else {
+                    return Err(invalid(format!(
+                        "line {} must be a `lemma<TAB>form` pair",
+                        i + 1
+                    )));
+                };
+                insert_lemma(&mut lemmas, form, lemma).map_err(invalid)?;
+            }
+        }
+
+        if lemmas.is_empty() {
+            return Err(invalid(
+                "the dictionary must contain at least one entry".to_string(),
+            ));
+        }
+
+        Ok(lemmas)
+    }
+}
+
+fn insert_lemma(
+    lemmas: &mut HashMap<String, String>,
+    form: &str,
+    lemma: &str,
+) -> Result<(), String> {
+    let (form, lemma) = (form.trim(), lemma.trim());
+    if form.is_empty() || lemma.is_empty() {
+        return Err("forms and lemmas cannot be empty".to_string());
+    }
+
+    lemmas.insert(form.to_lowercase(), lemma.to_lowercase());
+    if lemmas.len() > MAX_LEMMA_DICTIONARY_SIZE {
+        return Err(format!(
+            "a dictionary can have at most {MAX_LEMMA_DICTIONARY_SIZE} entries"
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_lemma_dictionary() {
+        let path = std::env::temp_dir().join("lnx-lemma-dictionary-test.txt");
+        std::fs::write(&path, "# English\nmouse\tmice\ngo\twent\n\n").unwrap();
+
+        let dictionary = LemmaDictionary {
+            lemmas: BTreeMap::from([("Geese".to_string(), "goose".to_string())]),
+            path: Some(path.clone()),
+        };
+        let lemmas = dictionary.load("english").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(lemmas.len(), 3);
+        assert_eq!(lemmas["mice"], "mouse");
+        assert_eq!(lemmas["went"], "go");
+        assert_eq!(lemmas["geese"], "goose");
+
+        assert!(LemmaDictionary::default().load("empty").is_err());
+
+        let dictionary = LemmaDictionary {
+            lemmas: BTreeMap::from([(" ".to_string(), "a".to_string())]),
+            ..Default::default()
+        };
+        assert!(dictionary.load("blank").is_err());
+    }
+}
@@ -0,0 +1,545 @@
+//! The text analysis settings of an index.
+//!
+//! Analyzers built from these settings are registered on the index's tokenizer
+//! manager via [crate::schema::IndexSchema::register_tokenizers], so they can be
+//! selected as the `tokenizer` of a field like any built-in tokenizer.
+//!
+//! Every index also registers an analyzer for each [Language], named after the
+//! language, i.e. `german`, the `cjk` tokenizer for Chinese, Japanese and Korean
+//! text, the `path_hierarchy` tokenizer for paths and the `url_email` tokenizer for
+//! URLs and emails. Indexes can define their own analyzers as a pipeline of
+//! filters, see [AnalyzerDefinition].
+
+mod cjk;
+mod custom;
+mod elision;
+mod folding;
+mod html;
+mod language;
+#[cfg(feature = "lemmatization")]
+mod lemma;
+mod normalizer;
+mod path;
+mod phonetic;
+mod pipeline;
+mod stop_words;
+mod synonyms;
+mod url_email;
+mod word_list;
+
+#[cfg(feature = "lemmatization")]
+use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::{TextAnalyzer, TokenizerManager};
+
+pub use self::cjk::{is_cjk, CjkTokenizer, CJK_TOKENIZER};
+#[cfg(feature = "cjk-dictionary")]
+pub use self::cjk::{ChineseTokenizer, CHINESE_TOKENIZER};
+pub use self::custom::{
+    AnalyzerDefinition,
+    CharFilterDefinition,
+    TokenFilterDefinition,
+    TokenizerKind,
+    EDGE_NGRAM_SIZES_WARNING_THRESHOLD,
+    MAX_ANALYZER_FILTERS,
+    MAX_GRAM_SIZE,
+    MAX_PATTERN_SIZE,
+    MAX_SHINGLE_SIZE,
+    MAX_SUBWORD_SIZE,
+    NGRAM_SIZES_WARNING_THRESHOLD,
+};
+pub use self::elision::{ApostropheMode, DEFAULT_ELISION_ARTICLES};
+pub use self::folding::fold_to_ascii;
+pub use self::html::strip_html;
+pub use self::language::Language;
+#[cfg(feature = "lemmatization")]
+pub use self::lemma::{LemmaDictionary, MAX_LEMMA_DICTIONARY_SIZE};
+pub use self::normalizer::KeywordNormalizer;
+pub use self::path::{
+    PathHierarchyTokenizer,
+    DEFAULT_PATH_DELIMITER,
+    PATH_HIERARCHY_TOKENIZER,
+};
+pub use self::phonetic::PhoneticEncoder;
+pub use self::pipeline::{Analyzer, AnalyzerTokenStream, CharFilter, TokenFilter};
+pub use self::stop_words::{StopWords, MAX_CUSTOM_STOP_WORDS};
+pub use self::synonyms::{SynonymMap, SynonymMode, SynonymSet, MAX_SYNONYM_RULES};
+pub use self::url_email::{UrlEmailTokenizer, URL_EMAIL_TOKENIZER};
+pub use self::word_list::{WordList, MAX_WORD_LIST_SIZE};
+use crate::error::SchemaError;
+use crate::schema::BUILTIN_TOKENIZERS;
+use crate::warnings::SchemaWarning;
+
+/// The maximum number of stem exclusions of an index.
+pub const MAX_STEM_EXCLUSIONS: usize = 10_000;
+
+/// The tokenizer which applies the `default` tokenizer and then removes the
+/// index's stop words.
+pub const STOP_TOKENIZER: &str = "stop";
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The analysis settings of an index.
+pub struct AnalysisSettings {
+    #[serde(default)]
+    /// The stop words removed by the `stop` tokenizer and by default by the
+    /// stop-word filters of custom analyzers.
+    pub stop_words: StopWords,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The custom analyzers of the index keyed by their name.
+    pub analyzers: BTreeMap<String, AnalyzerDefinition>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The synonym sets of the index keyed by their name.
+    pub synonyms: BTreeMap<String, SynonymSet>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The word lists used by the token filters of custom analyzers keyed by their
+    /// name, i.e. the dictionary of a compound word filter.
+    pub word_lists: BTreeMap<String, WordList>,
+    #[cfg(feature = "lemmatization")]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The dictionaries used by the lemmatizer filters of custom analyzers keyed by
+    /// their name.
+    pub lemma_dictionaries: BTreeMap<String, LemmaDictionary>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The words which are never stemmed by the language analyzers or the stemmer
+    /// filters of custom analyzers, i.e. brand names and product codes.
+    pub stem_exclusions: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+/// The loaded resources shared by the analyzers of an index.
+pub struct AnalysisResources {
+    /// The stop words of the index.
+    pub stop_words: StopWords,
+    /// The loaded synonym sets of the index keyed by their name.
+    pub synonyms: BTreeMap<String, Arc<SynonymMap>>,
+    /// The loaded word lists of the index keyed by their name.
+    pub word_lists: BTreeMap<String, Arc<HashSet<String>>>,
+    #[cfg(feature = "lemmatization")]
+    /// The loaded lemma dictionaries of the index keyed by their name.
+    pub lemma_dictionaries: BTreeMap<String, Arc<HashMap<String, String>>>,
+    /// The lowercased words of the index which are never stemmed.
+    pub stem_exclusions: Arc<HashSet<String>>,
+}
+
+impl AnalysisSettings {
+    /// Returns if the settings are the defaults.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Checks the settings are valid.
+    pub fn validate(&self) -> Result<(), SchemaError> {
+        self.stop_words.validate()?;
+
+        if self.stem_exclusions.len() > MAX_STEM_EXCLUSIONS {
+            return Err(SchemaError::InvalidAnalysis(format!(
+                "an index can have at most {MAX_STEM_EXCLUSIONS} stem exclusions"
+            )));
+        }
+
+        if self
+            .stem_exclusions
+            .iter()
+            .any(|word| word.trim().is_empty())
+        {
+            return Err(SchemaError::InvalidAnalysis(
+                "stem exclusions cannot be empty".to_string(),
+            ));
+        }
+
+        for (name, analyzer) in self.analyzers.iter() {
+            if name.is_empty() {
+                return Err(SchemaError::InvalidAnalysis(
+                    "analyzer names cannot be empty".to_string(),
+                ));
+            }
+
+            if BUILTIN_TOKENIZERS.contains(&name.as_str()) || is_builtin_analyzer(name) {
+                return Err(SchemaError::InvalidAnalysis(format!(
+                    "analyzer {name:?} conflicts with a built-in tokenizer"
+                )));
+            }
+
+            analyzer.validate(name)?;
+
+            for set in analyzer.synonym_sets() {
+                match self.synonyms.get(set) {
+                    None => {
+                        return Err(SchemaError::InvalidAnalysis(format!(
+                            "analyzer {name:?}: unknown synonym set {set:?}"
+                        )))
+                    },
+                    Some(synonyms) if synonyms.mode != SynonymMode::Index => {
+                        return Err(SchemaError::InvalidAnalysis(format!(
+                            "analyzer {name:?}: synonym set {set:?} is only expanded at query time"
+                        )))
+                    },
+                    Some(_) => {},
+                }
+            }
+
+            for list in analyzer.word_lists() {
+                if !self.word_lists.contains_key(list) {
+                    return Err(SchemaError::InvalidAnalysis(format!(
+                        "analyzer {name:?}: unknown word list {list:?}"
+                    )));
+                }
+            }
+
+            #[cfg(feature = "lemmatization")]
+            for dictionary in analyzer.lemma_dictionaries() {
+                if !self.lemma_dictionaries.contains_key(dictionary) {
+                    return Err(SchemaError::InvalidAnalysis(format!(
+                        "analyzer {name:?}: unknown lemma dictionary {dictionary:?}"
+                    )));
+                }
+            }
+        }
+
+        for (name, synonyms) in self.synonyms.iter() {
+            synonyms.load(name)?;
+        }
+
+        for (name, list) in self.word_lists.iter() {
+            list.load(name)?;
+        }
+
+        #[cfg(feature = "lemmatization")]
+        for (name, dictionary) in self.lemma_dictionaries.iter() {
+            dictionary.load(name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the stop words, synonym sets, word lists and lemma dictionaries used by
+    /// the analyzers of the index.
+    pub fn load_resources(&self) -> Result<AnalysisResources, SchemaError> {
+        let mut synonyms = BTreeMap::new();
+        for (name, set) in self.synonyms.iter() {
+            if set.mode == SynonymMode::Index {
+                synonyms.insert(name.clone(), Arc::new(set.load(name)?));
+            }
+        }
+
+        let mut word_lists = BTreeMap::new();
+        for (name, list) in self.word_lists.iter() {
+            word_lists.insert(name.clone(), Arc::new(list.load(name)?));
+        }
+
+        #[cfg(feature = "lemmatization")]
+        let mut lemma_dictionaries = BTreeMap::new();
+        #[cfg(feature = "lemmatization")]
+        for (name, dictionary) in self.lemma_dictionaries.iter() {
+            lemma_dictionaries.insert(name.clone(), Arc::new(dictionary.load(name)?));
+        }
+
+        Ok(AnalysisResources {
+            stop_words: self.stop_words.clone(),
+            synonyms,
+            word_lists,
+            #[cfg(feature = "lemmatization")]
+            lemma_dictionaries,
+            stem_exclusions: Arc::new(self.stem_exclusion_set()),
+        })
+    }
+
+    /// Returns the lowercased stem exclusions of the index.
+    fn stem_exclusion_set(&self) -> HashSet<String> {
+        self.stem_exclusions
+            .iter()
+            .map(|word| word.trim().to_lowercase())
+            .collect()
+    }
+
+    /// Loads the synonym sets which are expanded within free-text queries, merged
+    /// into a single map.
+    ///
+    /// Returns `None` if the index does not have any query-time synonyms.
+    pub fn query_synonyms(&self) -> Result<Option<SynonymMap>, SchemaError> {
+        let mut merged = SynonymMap::default();
+        for (name, set) in self.synonyms.iter() {
+            if set.mode == SynonymMode::Query {
+                merged.merge(&set.load(name)?);
+            }
+        }
+
+        Ok((!merged.is_empty()).then_some(merged))
+    }
+
+    /// Replaces a synonym set of the index, i.e. with an uploaded set of rules.
+    ///
+    /// Analyzers only pick up the change once the tokenizers are registered again.
+    pub fn put_synonym_set(
+        &mut self,
+        name: impl Into<String>,
+        set: SynonymSet,
+    ) -> Result<(), SchemaError> {
+        let name = name.into();
+        set.load(&name)?;
+        self.synonyms.insert(name, set);
+        Ok(())
+    }
+
+    /// Returns any warnings about the custom analyzers of the index.
+    pub fn warnings(&self) -> Vec<SchemaWarning> {
+        self.analyzers
+            .iter()
+            .flat_map(|(name, analyzer)| analyzer.warnings(name))
+            .collect()
+    }
+
+    /// Returns if the tokenizer is registered by the settings.
+    pub fn has_tokenizer(&self, name: &str) -> bool {
+        is_builtin_analyzer(name) || self.analyzers.contains_key(name)
+    }
+
+    /// Replaces the stop words of the index, i.e. with an uploaded custom list.
+    pub fn set_stop_words(&mut self, stop_words: StopWords) -> Result<(), SchemaError> {
+        stop_words.validate()?;
+        self.stop_words = stop_words;
+        Ok(())
+    }
+
+    /// Registers the analyzers defined by the settings and the language analyzers.
+    ///
+    /// This is called when the index is opened, before any documents are indexed,
+    /// an error is returned if a synonym set cannot be loaded.
+    pub fn register_tokenizers(
+        &self,
+        manager: &TokenizerManager,
+    ) -> Result<(), SchemaError> {
+        let resources = self.load_resources()?;
+        let Some(default) = manager.get("default") else {
+            return Ok(());
+        };
+
+        let filter = TokenFilter::StopWords(self.stop_words.word_set());
+        let analyzer = Analyzer::new(default.clone()).with_filter(filter);
+        manager.register(STOP_TOKENIZER, TextAnalyzer::from(analyzer));
+
+        for language in Language::ALL {
+            let exclusions = resources.stem_exclusions.clone();
+            let analyzer = language.analyzer(default.clone(), exclusions);
+            manager.register(language.name(), TextAnalyzer::from(analyzer));
+        }
+
+        manager.register(CJK_TOKENIZER, TextAnalyzer::from(CjkTokenizer));
+        manager.register(
+            PATH_HIERARCHY_TOKENIZER,
+            TextAnalyzer::from(PathHierarchyTokenizer::default()),
+        );
+        let analyzer = Analyzer::new(TextAnalyzer::from(UrlEmailTokenizer))
+            .with_filter(TokenFilter::Lowercase);
+        manager.register(URL_EMAIL_TOKENIZER, TextAnalyzer::from(analyzer));
+        #[cfg(feature = "cjk-dictionary")]
+        manager.register(
+            CHINESE_TOKENIZER,
+            TextAnalyzer::from(ChineseTokenizer::default()),
+        );
+
+        for (name, analyzer) in self.analyzers.iter() {
+            analyzer.validate(name)?;
+            let analyzer = analyzer.build(&resources);
+            manager.register(name, TextAnalyzer::from(analyzer));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns if the tokenizer is registered by [AnalysisSettings::register_tokenizers]
+/// on every index.
+pub fn is_builtin_analyzer(name: &str) -> bool {
+    #[cfg(feature = "cjk-dictionary")]
+    if name == CHINESE_TOKENIZER {
+        return true;
+    }
+
+    name == STOP_TOKENIZER
+        || name == CJK_TOKENIZER
+        || name == PATH_HIERARCHY_TOKENIZER
+        || name == URL_EMAIL_TOKENIZER
+        || Language::from_name(name).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(manager: &TokenizerManager, tokenizer: &str, text: &str) -> Vec<String> {
+        let analyzer = manager.get(tokenizer).unwrap();
+        let mut stream = analyzer.token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            tokens.push(stream.token().text.clone());
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_register_stop_tokenizer() {
+        let manager = TokenizerManager::default();
+        let mut settings = AnalysisSettings::default();
+        settings.register_tokenizers(&manager).unwrap();
+        assert_eq!(
+            tokens(&manager, STOP_TOKENIZER, "The Quick Fox"),
+            ["quick", "fox"]
+        );
+
+        settings
+            .set_stop_words(StopWords::Custom(vec!["Quick".to_string()]))
+            .unwrap();
+        settings.register_tokenizers(&manager).unwrap();
+        assert_eq!(
+            tokens(&manager, STOP_TOKENIZER, "The Quick Fox"),
+            ["the", "fox"]
+        );
+
+        settings.set_stop_words(StopWords::None).unwrap();
+        settings.register_tokenizers(&manager).unwrap();
+        assert_eq!(
+            tokens(&manager, STOP_TOKENIZER, "The Quick Fox"),
+            ["the", "quick", "fox"]
+        );
+    }
+
+    #[test]
+    fn test_language_analyzers() {
+        let manager = TokenizerManager::default();
+        AnalysisSettings::default()
+            .register_tokenizers(&manager)
+            .unwrap();
+
+        assert_eq!(
+            tokens(&manager, "english", "The runners were running"),
+            ["runner", "were", "run"]
+        );
+        assert_eq!(tokens(&manager, "german", "Die Häuser"), ["haus"]);
+        assert_eq!(tokens(&manager, "spanish", "Los gatos"), ["gat"]);
+        assert!(is_builtin_analyzer("russian"));
+        assert!(!is_builtin_analyzer("klingon"));
+    }
+
+    #[test]
+    fn test_register_url_email_tokenizer() {
+        let manager = TokenizerManager::default();
+        AnalysisSettings::default()
+            .register_tokenizers(&manager)
+            .unwrap();
+
+        assert_eq!(
+            tokens(&manager, URL_EMAIL_TOKENIZER, "Ask Jane@Example.com"),
+            ["ask", "jane@example.com", "jane", "example.com"]
+        );
+        assert!(is_builtin_analyzer(URL_EMAIL_TOKENIZER));
+    }
+
+    #[cfg(feature = "lemmatization")]
+    #[test]
+    fn test_lemmatizer() {
+        let mut settings: AnalysisSettings = serde_json::from_str(
+            r#"{
+                "lemma_dictionaries": {
+                    "english": {"lemmas": {"mice": "mouse", "went": "go"}}
+                },
+                "analyzers": {
+                    "lemmatized": {
+                        "filters": [
+                            {"type": "lowercase"},
+                            {"type": "lemmatizer", "dictionary": "english"}
+                        ]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        settings.validate().unwrap();
+
+        let manager = TokenizerManager::default();
+        settings.register_tokenizers(&manager).unwrap();
+        assert_eq!(
+            tokens(&manager, "lemmatized", "Mice went running"),
+            ["mouse", "go", "running"]
+        );
+
+        settings.lemma_dictionaries.clear();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_stem_exclusions() {
+        let settings: AnalysisSettings = serde_json::from_str(
+            r#"{
+                "stem_exclusions": ["Apples"],
+                "analyzers": {
+                    "stemmed": {
+                        "filters": [
+                            {"type": "lowercase"},
+                            {"type": "stemmer", "language": "english"}
+                        ]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        settings.validate().unwrap();
+
+        let manager = TokenizerManager::default();
+        settings.register_tokenizers(&manager).unwrap();
+        assert_eq!(
+            tokens(&manager, "english", "apples running"),
+            ["apples", "run"]
+        );
+        assert_eq!(
+            tokens(&manager, "stemmed", "Apples Pears"),
+            ["apples", "pear"]
+        );
+
+        let settings = AnalysisSettings {
+            stem_exclusions: vec![" ".to_string()],
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_synonym_set_validation() {
+        let mut settings: AnalysisSettings = serde_json::from_str(
+            r#"{
+                "analyzers": {
+                    "cities": {
+                        "filters": [{"type": "lowercase"}, {"type": "synonyms", "set": "cities"}]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(settings.validate().is_err());
+
+        let set = SynonymSet {
+            rules: vec!["ny, new york".to_string()],
+            mode: SynonymMode::Query,
+            ..Default::default()
+        };
+        settings.put_synonym_set("cities", set.clone()).unwrap();
+        assert!(settings.validate().is_err());
+        assert!(settings.query_synonyms().unwrap().is_some());
+
+        let set = SynonymSet {
+            mode: SynonymMode::Index,
+            ..set
+        };
+        settings.put_synonym_set("cities", set).unwrap();
+        assert!(settings.validate().is_ok());
+        assert!(settings.query_synonyms().unwrap().is_none());
+
+        let manager = TokenizerManager::default();
+        settings.register_tokenizers(&manager).unwrap();
+        assert_eq!(tokens(&manager, "cities", "NY"), ["ny", "new", "york"]);
+    }
+}
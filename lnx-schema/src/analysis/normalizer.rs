@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use super::TokenFilter;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A normalizer applied to the whole value of a `string` field before it is indexed
+/// as a single keyword, and to the values of term queries against the field.
+pub enum KeywordNormalizer {
+    /// Lowercases the value.
+    Lowercase,
+    /// Removes any leading and trailing whitespace from the value.
+    Trim,
+}
+
+impl KeywordNormalizer {
+    /// The name of the normalizer as it appears within the schema.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lowercase => "lowercase",
+            Self::Trim => "trim",
+        }
+    }
+
+    /// The token filter which applies the normalizer.
+    pub fn filter(&self) -> TokenFilter {
+        match self {
+            Self::Lowercase => TokenFilter::Lowercase,
+            Self::Trim => TokenFilter::Trim,
+        }
+    }
+}
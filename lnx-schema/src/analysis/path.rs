@@ -0,0 +1,92 @@
+use tantivy::tokenizer::{Token, Tokenizer};
+
+use super::AnalyzerTokenStream;
+
+/// The tokenizer which splits paths delimited by `/` into their ancestors.
+pub const PATH_HIERARCHY_TOKENIZER: &str = "path_hierarchy";
+/// The delimiter used by the `path_hierarchy` tokenizer.
+pub const DEFAULT_PATH_DELIMITER: &str = "/";
+
+#[derive(Debug, Clone)]
+/// Tokenizes a path into itself and each of its ancestors, i.e. `/a/b/c` becomes
+/// `/a`, `/a/b` and `/a/b/c`, so a term query for any ancestor matches the path.
+///
+/// Every token starts at the beginning of the text and shares the same position.
+pub struct PathHierarchyTokenizer {
+    delimiter: String,
+}
+
+impl PathHierarchyTokenizer {
+    /// Creates a new tokenizer splitting paths on the given delimiter.
+    pub fn new(delimiter: impl Into<String>) -> Self {
+        Self {
+            delimiter: delimiter.into(),
+        }
+    }
+}
+
+impl Default for PathHierarchyTokenizer {
+    fn default() -> Self {
+        Self::new(DEFAULT_PATH_DELIMITER)
+    }
+}
+
+impl Tokenizer for PathHierarchyTokenizer {
+    type TokenStream<'a> = AnalyzerTokenStream;
+
+    fn token_stream<'a>(&self, text: &'a str) -> Self::TokenStream<'a> {
+        let mut ends = text
+            .match_indices(self.delimiter.as_str())
+            .map(|(offset, _)| offset)
+            .filter(|&offset| offset > 0)
+            .collect::<Vec<_>>();
+        if !text.is_empty() && ends.last() != Some(&text.len()) {
+            ends.push(text.len());
+        }
+
+        let tokens = ends
+            .into_iter()
+            .map(|end| Token {
+                offset_from: 0,
+                offset_to: end,
+                position: 0,
+                text: text[..end].to_string(),
+                position_length: 1,
+            })
+            .collect();
+
+        AnalyzerTokenStream::new(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::TokenStream;
+
+    use super::*;
+
+    fn tokens(tokenizer: &PathHierarchyTokenizer, text: &str) -> Vec<String> {
+        let mut stream = tokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            assert_eq!(stream.token().position, 0);
+            tokens.push(stream.token().text.clone());
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_path_hierarchy_tokenizer() {
+        let tokenizer = PathHierarchyTokenizer::default();
+        assert_eq!(tokens(&tokenizer, "/a/b/c"), ["/a", "/a/b", "/a/b/c"]);
+        assert_eq!(tokens(&tokenizer, "a/b"), ["a", "a/b"]);
+        assert_eq!(tokens(&tokenizer, "/"), ["/"]);
+        assert!(tokens(&tokenizer, "").is_empty());
+
+        let tokenizer = PathHierarchyTokenizer::new(" > ");
+        assert_eq!(
+            tokens(&tokenizer, "Books > Fiction > Fantasy"),
+            ["Books", "Books > Fiction", "Books > Fiction > Fantasy"]
+        );
+    }
+}
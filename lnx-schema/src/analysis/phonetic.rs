@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+
+use super::fold_to_ascii;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// An algorithm encoding words by how they sound, so differently spelled names
+/// which sound alike, i.e. `Smith` and `Smyth`, produce the same code.
+pub enum PhoneticEncoder {
+    /// The American Soundex algorithm, i.e. `S530` for `Smith`.
+    Soundex,
+    /// The original Metaphone algorithm, i.e. `SM0` for `Smith`, which is more
+    /// accurate than Soundex for English names.
+    Metaphone,
+}
+
+impl PhoneticEncoder {
+    /// Encodes the word, returning `None` if it does not contain any letters.
+    ///
+    /// Characters with diacritics are folded to ASCII and any other non-letters are
+    /// ignored.
+    pub fn encode(&self, word: &str) -> Option<String> {
+        let letters = fold_to_ascii(word)
+            .bytes()
+            .filter(u8::is_ascii_alphabetic)
+            .map(|c| c.to_ascii_uppercase())
+            .collect::<Vec<_>>();
+        if letters.is_empty() {
+            return None;
+        }
+
+        let code = match self {
+            Self::Soundex => soundex(&letters),
+            Self::Metaphone => metaphone(&letters),
+        };
+        Some(code)
+    }
+}
+
+fn soundex(letters: &[u8]) -> String {
+    let digit = |c: u8| match c {
+        b'B' | b'F' | b'P' | b'V' => Some(b'1'),
+        b'C' | b'G' | b'J' | b'K' | b'Q' | b'S' | b'X' | b'Z' => Some(b'2'),
+        b'D' | b'T' => Some(b'3'),
+        b'L' => Some(b'4'),
+        b'M' | b'N' => Some(b'5'),
+        b'R' => Some(b'6'),
+        // `H` and `W` do not separate letters with the same digit.
+        b'H' | b'W' => None,
+        _ => Some(b'0'),
+    };
+
+    let mut code = vec![letters[0]];
+    let mut last = digit(letters[0]);
+    for &c in &letters[1..] {
+        let Some(d) = digit(c) else {
+            continue;
+        };
+
+        if d != b'0' && Some(d) != last {
+            code.push(d);
+            if code.len() == 4 {
+                break;
+            }
+        }
+        last = Some(d);
+    }
+
+    code.resize(4, b'0');
+    String::from_utf8(code).unwrap_or_default()
+}
+
+fn is_vowel(c: Option<&u8>) -> bool {
+    matches!(c, Some(b'A' | b'E' | b'I' | b'O' | b'U'))
+}
+
+fn metaphone(letters: &[u8]) -> String {
+    let mut word = letters.to_vec();
+    match word.as_slice() {
+        [b'A', b'E', ..]
+        | [b'G', b'N', ..]
+        | [b'K', b'N', ..]
+        | [b'P', b'N', ..]
+        | [b'W', b'R', ..] => {
+            word.remove(0);
+        },
+        [b'X', ..] => word[0] = b'S',
+        [b'W', b'H', ..] => {
+            word.remove(1);
+        },
+        _ => {},
+    }
+
+    let at = |i: usize| word.get(i);
+    let mut code = String::with_capacity(word.len());
+
+    for (i, &c) in word.iter().enumerate() {
+        let prev = i.checked_sub(1).and_then(|i| word.get(i));
+        let next = at(i + 1);
+        if prev == Some(&c) && c != b'C' {
+            continue;
+        }
+
+        match c {
+            b'A' | b'E' | b'I' | b'O' | b'U' => {
+                if i == 0 {
+                    code.push(c as char);
+                }
+            },
+            b'B' => {
+                if !(prev == Some(&b'M') && next.is_none()) {
+                    code.push('B');
+                }
+            },
+            b'C' => {
+                if next == Some(&b'I') && at(i + 2) == Some(&b'A') {
+                    code.push('X');
+                } else if next == Some(&b'H') {
+                    code.push(if prev == Some(&b'S') { 'K' } else { 'X' });
+                } else if matches!(next, Some(b'I' | b'E' | b'Y')) {
+                    if prev != Some(&b'S') {
+                        code.push('S');
+                    }
+                } else {
+                    code.push('K');
+                }
+            },
+            b'D' => {
+                if next == Some(&b'G') && matches!(at(i + 2), Some(b'E' | b'I' | b'Y')) {
+                    code.push('J');
+                } else {
+                    code.push('T');
+                }
+            },
+            b'G' => {
+                let silent =
+                    (next == Some(&b'H') && !is_vowel(at(i + 2)) && at(i + 2).is_some())
+                        || (next == Some(&b'N')
+                            && (at(i + 2).is_none() || &word[i + 2..] == b"ED"));
+                if silent {
+                    continue;
+                }
+
+                if matches!(next, Some(b'I' | b'E' | b'Y')) {
+                    code.push('J');
+                } else {
+                    code.push('K');
+                }
+            },
+            b'H' => {
+                let after_silent =
+                    matches!(prev, Some(b'C' | b'S' | b'P' | b'T' | b'G'));
+                if !after_silent && !(is_vowel(prev) && !is_vowel(next)) {
+                    code.push('H');
+                }
+            },
+            b'K' => {
+                if prev != Some(&b'C') {
+                    code.push('K');
+                }
+            },
+            b'P' => code.push(if next == Some(&b'H') { 'F' } else { 'P' }),
+            b'Q' => code.push('K'),
+            b'S' => {
+                if next == Some(&b'H')
+                    || (next == Some(&b'I') && matches!(at(i + 2), Some(b'O' | b'A')))
+                {
+                    code.push('X');
+                } else {
+                    code.push('S');
+                }
+            },
+            b'T' => {
+                if next == Some(&b'I') && matches!(at(i + 2), Some(b'O' | b'A')) {
+                    code.push('X');
+                } else if next == Some(&b'H') {
+                    code.push('0');
+                } else if !(next == Some(&b'C') && at(i + 2) == Some(&b'H')) {
+                    code.push('T');
+                }
+            },
+            b'V' => code.push('F'),
+            b'W' | b'Y' => {
+                if is_vowel(next) {
+                    code.push(c as char);
+                }
+            },
+            b'X' => code.push_str("KS"),
+            b'Z' => code.push('S'),
+            _ => code.push(c as char),
+        }
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soundex() {
+        let encode = |word| PhoneticEncoder::Soundex.encode(word).unwrap();
+        assert_eq!(encode("Robert"), "R163");
+        assert_eq!(encode("Rupert"), "R163");
+        assert_eq!(encode("Ashcraft"), "A261");
+        assert_eq!(encode("Tymczak"), "T522");
+        assert_eq!(encode("Pfister"), "P236");
+        assert_eq!(encode("Lee"), "L000");
+        assert_eq!(encode("Smith"), encode("Smyth"));
+        assert_eq!(PhoneticEncoder::Soundex.encode("42"), None);
+    }
+
+    #[test]
+    fn test_metaphone() {
+        let encode = |word| PhoneticEncoder::Metaphone.encode(word).unwrap();
+        assert_eq!(encode("John"), "JN");
+        assert_eq!(encode("Jon"), "JN");
+        assert_eq!(encode("Smith"), "SM0");
+        assert_eq!(encode("Smyth"), "SM0");
+        assert_eq!(encode("Knight"), "NT");
+        assert_eq!(encode("Philip"), "FLP");
+        assert_eq!(encode("Xavier"), "SFR");
+    }
+}
@@ -0,0 +1,652 @@
+use std::borrow::Cow;
+#[cfg(feature = "lemmatization")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use regex::Regex;
+use rust_stemmers::Stemmer;
+use tantivy::tokenizer::{TextAnalyzer, Token, TokenStream, Tokenizer};
+
+use super::elision::elided_prefix_len;
+use super::{fold_to_ascii, strip_html, ApostropheMode, PhoneticEncoder, SynonymMap};
+
+#[derive(Clone)]
+/// A filter applied to the text of a value before it is tokenized.
+///
+/// The offsets of the produced tokens refer to the filtered text.
+pub enum CharFilter {
+    /// Replaces every occurrence of each key with its value, where keys overlap
+    /// the longest key is replaced.
+    Mapping(Arc<Vec<(String, String)>>),
+    /// Removes any HTML markup, see [strip_html].
+    HtmlStrip,
+    /// Replaces every match of the pattern with the replacement, which can refer
+    /// to capture groups, i.e. `$1`.
+    PatternReplace {
+        pattern: Regex,
+        replacement: Arc<str>,
+    },
+}
+
+impl CharFilter {
+    /// Applies the filter to the text of a single value.
+    pub fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        match self {
+            Self::Mapping(mappings) => {
+                if !mappings.iter().any(|(key, _)| text.contains(key.as_str())) {
+                    return text;
+                }
+
+                let mut output = String::with_capacity(text.len());
+                let mut remaining = text.as_ref();
+                while let Some(c) = remaining.chars().next() {
+                    let mapping = mappings
+                        .iter()
+                        .filter(|(key, _)| remaining.starts_with(key.as_str()))
+                        .max_by_key(|(key, _)| key.len());
+
+                    match mapping {
+                        Some((key, value)) => {
+                            output.push_str(value);
+                            remaining = &remaining[key.len()..];
+                        },
+                        None => {
+                            output.push(c);
+                            remaining = &remaining[c.len_utf8()..];
+                        },
+                    }
+                }
+
+                Cow::Owned(output)
+            },
+            Self::HtmlStrip => match strip_html(&text) {
+                Cow::Borrowed(_) => text,
+                Cow::Owned(stripped) => Cow::Owned(stripped),
+            },
+            Self::PatternReplace {
+                pattern,
+                replacement,
+            } => match pattern.replace_all(&text, replacement.as_ref()) {
+                Cow::Borrowed(_) => text,
+                Cow::Owned(replaced) => Cow::Owned(replaced),
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+/// A filter applied to the tokens produced by an [Analyzer].
+pub enum TokenFilter {
+    /// Lowercases each token.
+    Lowercase,
+    /// Folds any characters with diacritics into their ASCII equivalents.
+    AsciiFolding,
+    /// Removes any leading and trailing whitespace from each token, adjusting its
+    /// offsets to match.
+    Trim,
+    /// Keeps only the first tokens up to the limit, removing any later tokens.
+    Limit(usize),
+    /// Removes any elided article within the set from the start of each token, i.e.
+    /// `l'avion` becomes `avion`.
+    Elision(Arc<HashSet<String>>),
+    /// Handles the apostrophes within each token according to the mode, adjusting
+    /// its offsets to match.
+    Apostrophe(ApostropheMode),
+    /// Removes any tokens whose length in characters is outside of the range.
+    Length { min: usize, max: usize },
+    /// Removes any tokens within the set, the positions of the remaining tokens
+    /// are kept so phrase queries do not match across removed words.
+    StopWords(Arc<HashSet<String>>),
+    /// Reduces each token to its stem, i.e. `running` to `run`.
+    ///
+    /// Tokens within the exclusions are kept as-is, i.e. brand names.
+    Stemmer {
+        stemmer: Arc<Stemmer>,
+        exclusions: Arc<HashSet<String>>,
+    },
+    #[cfg(feature = "lemmatization")]
+    /// Replaces each token within the dictionary with its lemma, i.e. `mice` with
+    /// `mouse`, keeping any other tokens as-is.
+    Lemmatizer(Arc<HashMap<String, String>>),
+    /// Expands the synonyms of the tokens, see [SynonymMap::expand].
+    Synonyms(Arc<SynonymMap>),
+    /// Replaces each token with its n-grams of between `min` and `max` characters,
+    /// or only the n-grams starting at the beginning of the token if `edge` is set.
+    ///
+    /// The n-grams keep the position and offsets of their token.
+    Ngram {
+        min: usize,
+        max: usize,
+        edge: bool,
+        preserve_original: bool,
+    },
+    /// Adds shingles of between `min` and `max` tokens at adjacent positions joined
+    /// by the separator, optionally keeping the original tokens.
+    ///
+    /// Shingles take the position of their first token, so they never span the gap
+    /// left by a removed stop word.
+    Shingle {
+        min: usize,
+        max: usize,
+        output_unigrams: bool,
+        separator: Arc<str>,
+    },
+    /// Adds the subwords of each token of at least `min_word_size` characters which
+    /// are within the dictionary, keeping the original token.
+    ///
+    /// Subwords keep the position and offsets of their token, if `only_longest_match`
+    /// is set only the longest subword starting at each character is added.
+    Decompound {
+        words: Arc<HashSet<String>>,
+        min_word_size: usize,
+        min_subword_size: usize,
+        max_subword_size: usize,
+        only_longest_match: bool,
+    },
+    /// Replaces each token with its phonetic code, optionally keeping the original
+    /// token at the same position.
+    ///
+    /// Tokens without any letters are kept as-is.
+    Phonetic {
+        encoder: PhoneticEncoder,
+        preserve_original: bool,
+    },
+}
+
+impl TokenFilter {
+    /// Applies the filter to the tokens of a single value.
+    pub fn apply(&self, tokens: &mut Vec<Token>) {
+        match self {
+            Self::Lowercase => {
+                for token in tokens.iter_mut() {
+                    if token.text.chars().any(char::is_uppercase) {
+                        token.text = token.text.to_lowercase();
+                    }
+                }
+            },
+            Self::AsciiFolding => {
+                for token in tokens.iter_mut() {
+                    if let Cow::Owned(folded) = fold_to_ascii(&token.text) {
+                        token.text = folded;
+                    }
+                }
+            },
+            Self::Trim => {
+                for token in tokens.iter_mut() {
+                    let start = token.text.len() - token.text.trim_start().len();
+                    let trimmed = token.text.trim();
+                    if trimmed.len() == token.text.len() {
+                        continue;
+                    }
+
+                    token.offset_from += start;
+                    token.offset_to = token.offset_from + trimmed.len();
+                    token.text = trimmed.to_string();
+                }
+            },
+            Self::Limit(max) => tokens.truncate(*max),
+            Self::Elision(articles) => {
+                for token in tokens.iter_mut() {
+                    let Some(len) = elided_prefix_len(&token.text, |article| {
+                        articles.contains(article)
+                    }) else {
+                        continue;
+                    };
+
+                    token.offset_from = (token.offset_from + len).min(token.offset_to);
+                    token.text.replace_range(..len, "");
+                }
+            },
+            Self::Apostrophe(mode) => {
+                for token in tokens.iter_mut() {
+                    if let Some((text, removed)) = mode.apply(&token.text) {
+                        token.offset_to = token
+                            .offset_to
+                            .saturating_sub(removed)
+                            .max(token.offset_from);
+                        token.text = text;
+                    }
+                }
+            },
+            Self::Length { min, max } => tokens.retain(|token| {
+                let length = token.text.chars().count();
+                length >= *min && length <= *max
+            }),
+            Self::StopWords(words) => {
+                tokens.retain(|token| !words.contains(&token.text))
+            },
+            Self::Stemmer {
+                stemmer,
+                exclusions,
+            } => {
+                for token in tokens.iter_mut() {
+                    if exclusions.contains(&token.text) {
+                        continue;
+                    }
+
+                    let stemmed = stemmer.stem(&token.text);
+                    if stemmed != token.text {
+                        token.text = stemmed.into_owned();
+                    }
+                }
+            },
+            #[cfg(feature = "lemmatization")]
+            Self::Lemmatizer(lemmas) => {
+                for token in tokens.iter_mut() {
+                    if let Some(lemma) = lemmas.get(&token.text) {
+                        token.text.clone_from(lemma);
+                    }
+                }
+            },
+            Self::Synonyms(synonyms) => {
+                *tokens = synonyms.expand(std::mem::take(tokens));
+            },
+            Self::Ngram {
+                min,
+                max,
+                edge,
+                preserve_original,
+            } => {
+                let mut grams = Vec::with_capacity(tokens.len());
+                for token in tokens.drain(..) {
+                    push_ngrams(&mut grams, &token, *min, *max, *edge);
+                    if *preserve_original && !grams.iter().any(|g| g.text == token.text)
+                    {
+                        grams.push(token);
+                    }
+                }
+                *tokens = grams;
+            },
+            Self::Shingle {
+                min,
+                max,
+                output_unigrams,
+                separator,
+            } => {
+                let mut shingles = Vec::with_capacity(tokens.len() * 2);
+                for (i, token) in tokens.iter().enumerate() {
+                    if *output_unigrams {
+                        shingles.push(token.clone());
+                    }
+                    push_shingles(&mut shingles, &tokens[i..], *min, *max, separator);
+                }
+                *tokens = shingles;
+            },
+            Self::Decompound {
+                words,
+                min_word_size,
+                min_subword_size,
+                max_subword_size,
+                only_longest_match,
+            } => {
+                let mut decompounded = Vec::with_capacity(tokens.len());
+                for token in tokens.drain(..) {
+                    let subwords = if token.text.chars().count() >= *min_word_size {
+                        find_subwords(
+                            &token.text,
+                            words,
+                            *min_subword_size,
+                            *max_subword_size,
+                            *only_longest_match,
+                        )
+                    } else {
+                        Vec::new()
+                    };
+
+                    decompounded.push(token.clone());
+                    for subword in subwords {
+                        decompounded.push(Token {
+                            text: subword,
+                            ..token.clone()
+                        });
+                    }
+                }
+                *tokens = decompounded;
+            },
+            Self::Phonetic {
+                encoder,
+                preserve_original,
+            } => {
+                let mut encoded = Vec::with_capacity(tokens.len());
+                for token in tokens.drain(..) {
+                    let Some(code) = encoder.encode(&token.text) else {
+                        encoded.push(token);
+                        continue;
+                    };
+
+                    if *preserve_original && code != token.text {
+                        encoded.push(token.clone());
+                    }
+                    encoded.push(Token {
+                        text: code,
+                        ..token
+                    });
+                }
+                *tokens = encoded;
+            },
+        }
+    }
+}
+
+/// Finds the subwords of a word which are within the dictionary.
+fn find_subwords(
+    word: &str,
+    dictionary: &HashSet<String>,
+    min: usize,
+    max: usize,
+    only_longest_match: bool,
+) -> Vec<String> {
+    let lowercased = word.to_lowercase();
+    let boundaries = lowercased
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(lowercased.len()))
+        .collect::<Vec<_>>();
+    let num_chars = boundaries.len() - 1;
+
+    let mut subwords = Vec::new();
+    for start in 0..num_chars {
+        let mut longest = None;
+        for length in min..=max {
+            let Some(&end) = boundaries.get(start + length) else {
+                break;
+            };
+
+            // The whole word is not a subword of itself.
+            if length == num_chars {
+                break;
+            }
+
+            let subword = &lowercased[boundaries[start]..end];
+            if dictionary.contains(subword) {
+                if only_longest_match {
+                    longest = Some(subword);
+                } else {
+                    subwords.push(subword.to_string());
+                }
+            }
+        }
+
+        subwords.extend(longest.map(String::from));
+    }
+
+    subwords
+}
+
+fn push_shingles(
+    shingles: &mut Vec<Token>,
+    tokens: &[Token],
+    min: usize,
+    max: usize,
+    separator: &str,
+) {
+    let first = &tokens[0];
+    let mut text = first.text.clone();
+
+    for (length, token) in (2..=max).zip(&tokens[1..]) {
+        if token.position != first.position + length - 1 {
+            break;
+        }
+
+        text.push_str(separator);
+        text.push_str(&token.text);
+        if length >= min {
+            shingles.push(Token {
+                offset_to: token.offset_to,
+                text: text.clone(),
+                ..first.clone()
+            });
+        }
+    }
+}
+
+fn push_ngrams(
+    grams: &mut Vec<Token>,
+    token: &Token,
+    min: usize,
+    max: usize,
+    edge: bool,
+) {
+    let boundaries = token
+        .text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(token.text.len()))
+        .collect::<Vec<_>>();
+    let num_chars = boundaries.len() - 1;
+
+    let starts = if edge { 0..1 } else { 0..num_chars };
+    for start in starts {
+        for length in min..=max {
+            let Some(&end) = boundaries.get(start + length) else {
+                break;
+            };
+
+            grams.push(Token {
+                text: token.text[boundaries[start]..end].to_string(),
+                ..token.clone()
+            });
+        }
+    }
+}
+
+#[derive(Clone)]
+/// An analysis pipeline which runs a set of character filters over the text of a
+/// value, tokenizes it with a tantivy analyzer and then runs a set of token filters
+/// over the produced tokens.
+///
+/// The tokens of each value are collected before the filters are applied, which
+/// allows filters to remove, replace or emit additional tokens freely.
+pub struct Analyzer {
+    char_filters: Vec<CharFilter>,
+    tokenizer: TextAnalyzer,
+    filters: Vec<TokenFilter>,
+}
+
+impl Analyzer {
+    /// Creates a new pipeline tokenizing text with the given analyzer.
+    pub fn new(tokenizer: TextAnalyzer) -> Self {
+        Self {
+            char_filters: Vec::new(),
+            tokenizer,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Adds a character filter to the end of the pipeline's character filters.
+    pub fn with_char_filter(mut self, filter: CharFilter) -> Self {
+        self.char_filters.push(filter);
+        self
+    }
+
+    /// Adds a filter to the end of the pipeline.
+    pub fn with_filter(mut self, filter: TokenFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Produces the tokens of the given text.
+    pub fn analyze(&self, text: &str) -> Vec<Token> {
+        let mut text = Cow::Borrowed(text);
+        for filter in self.char_filters.iter() {
+            text = filter.apply(text);
+        }
+
+        let mut tokens = Vec::new();
+        let mut stream = self.tokenizer.token_stream(&text);
+        while stream.advance() {
+            tokens.push(stream.token().clone());
+        }
+
+        for filter in self.filters.iter() {
+            filter.apply(&mut tokens);
+        }
+
+        tokens
+    }
+}
+
+impl Tokenizer for Analyzer {
+    type TokenStream<'a> = AnalyzerTokenStream;
+
+    fn token_stream<'a>(&self, text: &'a str) -> Self::TokenStream<'a> {
+        AnalyzerTokenStream::new(self.analyze(text))
+    }
+}
+
+/// The token stream produced by an [Analyzer].
+pub struct AnalyzerTokenStream {
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+impl AnalyzerTokenStream {
+    /// Creates a stream over an already produced set of tokens.
+    pub(crate) fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, cursor: 0 }
+    }
+}
+
+impl TokenStream for AnalyzerTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.cursor >= self.tokens.len() {
+            return false;
+        }
+
+        self.cursor += 1;
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.cursor - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.cursor - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::SimpleTokenizer;
+
+    use super::*;
+
+    #[test]
+    fn test_stop_word_filter_keeps_positions() {
+        let words = HashSet::from(["the".to_string()]);
+        let analyzer = Analyzer::new(TextAnalyzer::from(SimpleTokenizer))
+            .with_filter(TokenFilter::StopWords(Arc::new(words)));
+
+        let mut tokens = Vec::new();
+        let mut stream = analyzer.token_stream("the quick the fox");
+        while stream.advance() {
+            let token = stream.token();
+            tokens.push((token.text.clone(), token.position));
+        }
+
+        assert_eq!(tokens, [("quick".to_string(), 1), ("fox".to_string(), 3)]);
+    }
+
+    #[test]
+    fn test_ngram_filters() {
+        let analyze = |filter: TokenFilter, text: &str| {
+            Analyzer::new(TextAnalyzer::from(SimpleTokenizer))
+                .with_filter(filter)
+                .analyze(text)
+                .into_iter()
+                .map(|token| (token.text, token.position))
+                .collect::<Vec<_>>()
+        };
+
+        let tokens = analyze(
+            TokenFilter::Ngram {
+                min: 2,
+                max: 3,
+                edge: false,
+                preserve_original: false,
+            },
+            "a café",
+        );
+        let expected = [("ca", 1), ("caf", 1), ("af", 1), ("afé", 1), ("fé", 1)];
+        assert_eq!(tokens, expected.map(|(text, pos)| (text.to_string(), pos)));
+
+        let tokens = analyze(
+            TokenFilter::Ngram {
+                min: 1,
+                max: 3,
+                edge: true,
+                preserve_original: true,
+            },
+            "search",
+        );
+        let expected = ["s", "se", "sea", "search"];
+        assert_eq!(tokens, expected.map(|text| (text.to_string(), 0)));
+    }
+
+    #[test]
+    fn test_decompound_filter() {
+        let words = ["donau", "dampf", "schiff", "dampfschiff"]
+            .map(String::from)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let analyze = |only_longest_match: bool| {
+            Analyzer::new(TextAnalyzer::from(SimpleTokenizer))
+                .with_filter(TokenFilter::Lowercase)
+                .with_filter(TokenFilter::Decompound {
+                    words: Arc::new(words.clone()),
+                    min_word_size: 5,
+                    min_subword_size: 2,
+                    max_subword_size: 15,
+                    only_longest_match,
+                })
+                .analyze("Donaudampfschiff fährt")
+                .into_iter()
+                .map(|token| token.text)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            analyze(false),
+            [
+                "donaudampfschiff",
+                "donau",
+                "dampf",
+                "dampfschiff",
+                "schiff",
+                "fährt"
+            ]
+        );
+        assert_eq!(
+            analyze(true),
+            [
+                "donaudampfschiff",
+                "donau",
+                "dampfschiff",
+                "schiff",
+                "fährt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_char_and_token_filters() {
+        let mappings = vec![
+            ("&".to_string(), " and ".to_string()),
+            ("&amp;".to_string(), " and ".to_string()),
+        ];
+        let analyzer = Analyzer::new(TextAnalyzer::from(SimpleTokenizer))
+            .with_char_filter(CharFilter::Mapping(Arc::new(mappings)))
+            .with_filter(TokenFilter::Lowercase)
+            .with_filter(TokenFilter::AsciiFolding)
+            .with_filter(TokenFilter::Length { min: 2, max: 5 });
+
+        let tokens = analyzer
+            .analyze("Café &amp; Crème & a Croissant")
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, ["cafe", "and", "creme", "and"]);
+    }
+}
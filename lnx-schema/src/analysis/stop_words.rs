@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::Language;
+use crate::error::SchemaError;
+
+/// The maximum number of words within a custom stop-word list.
+pub const MAX_CUSTOM_STOP_WORDS: usize = 10_000;
+/// The name used to disable stop words.
+const DISABLED: &str = "none";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "StopWordsRepr", into = "StopWordsRepr")]
+/// The stop words removed by the stop-word filters of an index.
+///
+/// This is either the name of a bundled language list, i.e. `"english"`, a custom
+/// list of words or `"none"` to disable stop words entirely.
+pub enum StopWords {
+    /// The bundled list of the language.
+    Language(Language),
+    /// A user provided list of words.
+    Custom(Vec<String>),
+    /// No words are removed.
+    None,
+}
+
+impl Default for StopWords {
+    fn default() -> Self {
+        Self::Language(Language::English)
+    }
+}
+
+impl StopWords {
+    /// Checks a custom list is within the allowed limits.
+    pub fn validate(&self) -> Result<(), SchemaError> {
+        let Self::Custom(words) = self else {
+            return Ok(());
+        };
+
+        if words.len() > MAX_CUSTOM_STOP_WORDS {
+            return Err(SchemaError::InvalidAnalysis(format!(
+                "a custom stop-word list can have at most {MAX_CUSTOM_STOP_WORDS} words"
+            )));
+        }
+
+        if let Some(word) = words
+            .iter()
+            .find(|word| word.is_empty() || word.contains(char::is_whitespace))
+        {
+            return Err(SchemaError::InvalidAnalysis(format!(
+                "invalid stop word {word:?}, stop words must be a single non-empty word"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Builds the set of words which are removed.
+    ///
+    /// Words are lowercased as stop words are matched against lowercased tokens.
+    pub fn word_set(&self) -> Arc<HashSet<String>> {
+        let words = match self {
+            Self::Language(language) => language
+                .stop_words()
+                .iter()
+                .map(|word| word.to_string())
+                .collect(),
+            Self::Custom(words) => {
+                words.iter().map(|word| word.to_lowercase()).collect()
+            },
+            Self::None => HashSet::new(),
+        };
+
+        Arc::new(words)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StopWordsRepr {
+    Named(String),
+    Custom(Vec<String>),
+}
+
+impl TryFrom<StopWordsRepr> for StopWords {
+    type Error = String;
+
+    fn try_from(repr: StopWordsRepr) -> Result<Self, Self::Error> {
+        match repr {
+            StopWordsRepr::Custom(words) => Ok(Self::Custom(words)),
+            StopWordsRepr::Named(name) if name == DISABLED => Ok(Self::None),
+            StopWordsRepr::Named(name) => Language::from_name(&name)
+                .map(Self::Language)
+                .ok_or_else(|| format!("unknown stop-word list {name:?}")),
+        }
+    }
+}
+
+impl From<StopWords> for StopWordsRepr {
+    fn from(stop_words: StopWords) -> Self {
+        match stop_words {
+            StopWords::Language(language) => Self::Named(language.name().to_string()),
+            StopWords::Custom(words) => Self::Custom(words),
+            StopWords::None => Self::Named(DISABLED.to_string()),
+        }
+    }
+}
+
+pub(super) const ENGLISH: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into",
+    "is", "it", "no", "not", "of", "on", "or", "such", "that", "the", "their", "then",
+    "there", "these", "they", "this", "to", "was", "will", "with",
+];
+
+pub(super) const GERMAN: &[&str] = &[
+    "aber", "alle", "als", "also", "am", "an", "auch", "auf", "aus", "bei", "bin",
+    "bis", "bist", "da", "dann", "das", "dass", "dem", "den", "der", "des", "die",
+    "doch", "du", "durch", "ein", "eine", "einem", "einen", "einer", "eines", "er",
+    "es", "für", "hat", "ich", "ihr", "im", "in", "ist", "ja", "mit", "nach", "nicht",
+    "noch", "nur", "oder", "sich", "sie", "sind", "so", "um", "und", "uns", "von",
+    "vor", "war", "was", "wie", "wir", "zu", "zum", "zur",
+];
+
+pub(super) const FRENCH: &[&str] = &[
+    "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "et",
+    "eux", "il", "je", "la", "le", "les", "leur", "lui", "ma", "mais", "me", "mes",
+    "moi", "mon", "ne", "nos", "notre", "nous", "on", "ou", "par", "pas", "pour", "qu",
+    "que", "qui", "sa", "se", "ses", "son", "sur", "ta", "te", "tes", "toi", "ton",
+    "tu", "un", "une", "vos", "votre", "vous",
+];
+
+pub(super) const SPANISH: &[&str] = &[
+    "a", "al", "algo", "como", "con", "de", "del", "el", "ella", "ellos", "en", "entre",
+    "era", "es", "esta", "este", "fue", "ha", "hay", "la", "las", "le", "les", "lo",
+    "los", "mas", "me", "mi", "muy", "no", "nos", "o", "para", "pero", "por", "que",
+    "se", "sin", "sobre", "su", "sus", "también", "te", "tu", "un", "una", "uno", "y",
+    "ya", "yo",
+];
+
+pub(super) const RUSSIAN: &[&str] = &[
+    "а",
+    "без",
+    "бы",
+    "был",
+    "была",
+    "были",
+    "было",
+    "в",
+    "вот",
+    "вы",
+    "да",
+    "для",
+    "до",
+    "его",
+    "ее",
+    "если",
+    "есть",
+    "же",
+    "за",
+    "и",
+    "из",
+    "или",
+    "им",
+    "их",
+    "к",
+    "как",
+    "когда",
+    "ли",
+    "мы",
+    "на",
+    "не",
+    "него",
+    "нет",
+    "но",
+    "о",
+    "он",
+    "она",
+    "они",
+    "оно",
+    "от",
+    "по",
+    "при",
+    "с",
+    "так",
+    "то",
+    "только",
+    "ты",
+    "у",
+    "уже",
+    "что",
+    "это",
+    "я",
+];
+
+pub(super) const PORTUGUESE: &[&str] = &[
+    "a", "ao", "aos", "as", "com", "como", "da", "das", "de", "do", "dos", "e", "ela",
+    "ele", "em", "entre", "era", "essa", "esse", "esta", "este", "eu", "foi", "há",
+    "isso", "já", "mais", "mas", "me", "na", "nas", "no", "nos", "não", "o", "os", "ou",
+    "para", "pela", "pelo", "por", "que", "se", "sem", "seu", "sua", "são", "também",
+    "um", "uma",
+];
+
+pub(super) const ITALIAN: &[&str] = &[
+    "a", "ad", "al", "alla", "anche", "che", "chi", "ci", "come", "con", "da", "dal",
+    "dei", "del", "della", "di", "e", "è", "era", "gli", "ha", "i", "il", "in", "io",
+    "la", "le", "lo", "ma", "mi", "ne", "nel", "nella", "non", "o", "per", "più", "se",
+    "si", "sono", "su", "sua", "suo", "tra", "un", "una", "uno",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_words_serde() {
+        let cases = [
+            (r#""german""#, StopWords::Language(Language::German)),
+            (r#""none""#, StopWords::None),
+            (
+                r#"["Foo", "bar"]"#,
+                StopWords::Custom(vec!["Foo".to_string(), "bar".to_string()]),
+            ),
+        ];
+
+        for (json, expected) in cases {
+            let stop_words: StopWords = serde_json::from_str(json).unwrap();
+            assert_eq!(stop_words, expected);
+            assert_eq!(
+                serde_json::to_string(&stop_words).unwrap(),
+                json.replace(", ", ",")
+            );
+        }
+
+        assert!(serde_json::from_str::<StopWords>(r#""klingon""#).is_err());
+    }
+
+    #[test]
+    fn test_stop_word_sets() {
+        assert!(StopWords::default().word_set().contains("the"));
+        assert!(StopWords::None.word_set().is_empty());
+
+        let custom = StopWords::Custom(vec!["Foo".to_string()]);
+        assert!(custom.word_set().contains("foo"));
+
+        assert!(StopWords::Custom(vec!["two words".to_string()])
+            .validate()
+            .is_err());
+        assert!(StopWords::Custom(vec![String::new()]).validate().is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::Token;
+
+use crate::error::SchemaError;
+
+/// The maximum number of rules within a synonym set.
+pub const MAX_SYNONYM_RULES: usize = 100_000;
+/// The separator between the input and output words of an explicit mapping.
+const MAPPING_SEPARATOR: &str = "=>";
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// When the synonyms of a set are expanded.
+pub enum SynonymMode {
+    #[default]
+    /// Synonyms are expanded by the `synonyms` filter of an analyzer when documents
+    /// are indexed, this supports phrase queries but requires a reindex to change.
+    Index,
+    /// Synonyms are expanded within free-text queries only, so the set can be
+    /// changed without a reindex.
+    Query,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A named set of synonym rules.
+///
+/// Each rule is either a comma-separated list of equivalent words or phrases, i.e.
+/// `ny, new york`, or an explicit mapping, i.e. `colour, color => color`, which
+/// replaces any of the words on the left with the words on the right.
+pub struct SynonymSet {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The rules of the set.
+    pub rules: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// A file containing additional rules, one per line.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    /// When the synonyms are expanded.
+    pub mode: SynonymMode,
+}
+
+impl SynonymSet {
+    /// Loads the rules of the set, including the rules within its file.
+    pub fn load(&self, name: &str) -> Result<SynonymMap, SchemaError> {
+        let invalid = |reason: String| {
+            SchemaError::InvalidAnalysis(format!("synonym set {name:?}: {reason}"))
+        };
+
+        let file = match self.path.as_ref() {
+            None => String::new(),
+            Some(path) => std::fs::read_to_string(path).map_err(|e| {
+                invalid(format!("unable to read {}: {e}", path.display()))
+            })?,
+        };
+
+        let mut map = SynonymMap::default();
+        let rules = self.rules.iter().map(String::as_str).chain(file.lines());
+        for rule in rules {
+            let rule = rule.trim();
+            if rule.is_empty() || rule.starts_with('#') {
+                continue;
+            }
+
+            map.add_rule(rule).map_err(invalid)?;
+            if map.rules.len() > MAX_SYNONYM_RULES {
+                return Err(invalid(format!(
+                    "a synonym set can have at most {MAX_SYNONYM_RULES} rules"
+                )));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+/// The parsed rules of one or more synonym sets.
+///
+/// Words are lowercased, so the map should be applied to lowercased tokens.
+pub struct SynonymMap {
+    rules: HashMap<Vec<String>, Arc<Vec<Vec<String>>>>,
+    max_words: usize,
+}
+
+impl SynonymMap {
+    /// Returns if the map has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Merges the rules of another map into this map.
+    pub fn merge(&mut self, other: &SynonymMap) {
+        for (input, outputs) in other.rules.iter() {
+            self.insert(input.clone(), outputs.as_ref().clone());
+        }
+    }
+
+    /// Finds the longest rule matching the start of the given words, returning the
+    /// number of words matched and the words they expand to.
+    pub fn longest_match<S: AsRef<str>>(
+        &self,
+        words: &[S],
+    ) -> Option<(usize, &[Vec<String>])> {
+        let max_words = self.max_words.min(words.len());
+        let mut key = Vec::with_capacity(max_words);
+        for word in &words[..max_words] {
+            key.push(word.as_ref().to_lowercase());
+        }
+
+        (1..=max_words).rev().find_map(|len| {
+            let outputs = self.rules.get(&key[..len])?;
+            Some((len, outputs.as_slice()))
+        })
+    }
+
+    fn add_rule(&mut self, rule: &str) -> Result<(), String> {
+        let (inputs, outputs) = match rule.split_once(MAPPING_SEPARATOR) {
+            Some((inputs, outputs)) => (parse_phrases(inputs), parse_phrases(outputs)),
+            None => {
+                let phrases = parse_phrases(rule);
+                (phrases.clone(), phrases)
+            },
+        };
+
+        if inputs.is_empty() || outputs.is_empty() {
+            return Err(format!("invalid synonym rule {rule:?}"));
+        }
+
+        for input in inputs {
+            self.insert(input, outputs.clone());
+        }
+
+        Ok(())
+    }
+
+    fn insert(&mut self, input: Vec<String>, outputs: Vec<Vec<String>>) {
+        self.max_words = self.max_words.max(input.len());
+        let existing = self.rules.entry(input).or_default();
+        let merged = Arc::make_mut(existing);
+        for output in outputs {
+            if !merged.contains(&output) {
+                merged.push(output);
+            }
+        }
+    }
+
+    /// Expands the synonyms of a value's tokens.
+    ///
+    /// The tokens of each output phrase are emitted at consecutive positions from
+    /// the position of the first matched token, covering the offsets of the matched
+    /// tokens. Matched tokens which are not part of the outputs are removed.
+    pub fn expand(&self, tokens: Vec<Token>) -> Vec<Token> {
+        let mut expanded = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+
+        while i < tokens.len() {
+            let words = tokens[i..]
+                .iter()
+                .map(|t| t.text.as_str())
+                .collect::<Vec<_>>();
+            let Some((len, outputs)) = self.longest_match(&words) else {
+                expanded.push(tokens[i].clone());
+                i += 1;
+                continue;
+            };
+
+            let matched = &tokens[i..i + len];
+            let (first, last) = (&matched[0], &matched[len - 1]);
+            for output in outputs {
+                let is_original = output.len() == len
+                    && output
+                        .iter()
+                        .zip(matched)
+                        .all(|(word, token)| *word == token.text.to_lowercase());
+                if is_original {
+                    expanded.extend(matched.iter().cloned());
+                    continue;
+                }
+
+                for (offset, word) in output.iter().enumerate() {
+                    expanded.push(Token {
+                        offset_from: first.offset_from,
+                        offset_to: last.offset_to,
+                        position: first.position + offset,
+                        text: word.clone(),
+                        position_length: 1,
+                    });
+                }
+            }
+
+            i += len;
+        }
+
+        expanded.sort_by_key(|token| token.position);
+        expanded
+    }
+}
+
+/// Parses a comma-separated list of words or phrases.
+fn parse_phrases(s: &str) -> Vec<Vec<String>> {
+    s.split(',')
+        .map(|phrase| {
+            phrase
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+        })
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::{SimpleTokenizer, TextAnalyzer};
+
+    use super::*;
+    use crate::analysis::{Analyzer, TokenFilter};
+
+    fn set(rules: &[&str]) -> SynonymMap {
+        let set = SynonymSet {
+            rules: rules.iter().map(|rule| rule.to_string()).collect(),
+            ..Default::default()
+        };
+        set.load("test").unwrap()
+    }
+
+    fn expand(map: SynonymMap, text: &str) -> Vec<(String, usize)> {
+        Analyzer::new(TextAnalyzer::from(SimpleTokenizer))
+            .with_filter(TokenFilter::Lowercase)
+            .with_filter(TokenFilter::Synonyms(Arc::new(map)))
+            .analyze(text)
+            .into_iter()
+            .map(|token| (token.text, token.position))
+            .collect()
+    }
+
+    #[test]
+    fn test_equivalent_synonyms() {
+        let map = set(&["# comment", "ny, new york", "tv, television"]);
+
+        let tokens = expand(map.clone(), "NY pizza");
+        let expected = [("ny", 0), ("new", 0), ("york", 1), ("pizza", 1)];
+        assert_eq!(tokens, expected.map(|(text, pos)| (text.to_string(), pos)));
+
+        let tokens = expand(map, "new york tv");
+        let expected = [
+            ("ny", 0),
+            ("new", 0),
+            ("york", 1),
+            ("tv", 2),
+            ("television", 2),
+        ];
+        assert_eq!(tokens, expected.map(|(text, pos)| (text.to_string(), pos)));
+    }
+
+    #[test]
+    fn test_explicit_mappings() {
+        let map = set(&["colour, color => color"]);
+        let tokens = expand(map, "Colour chart");
+        assert_eq!(tokens, [("color".to_string(), 0), ("chart".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_invalid_synonym_sets() {
+        let set = SynonymSet {
+            rules: vec!["=> color".to_string()],
+            ..Default::default()
+        };
+        assert!(set.load("colors").is_err());
+
+        let set = SynonymSet {
+            path: Some(PathBuf::from("/does/not/exist.txt")),
+            ..Default::default()
+        };
+        assert!(set.load("missing").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::ops::Range;
+
+use tantivy::tokenizer::{Token, Tokenizer};
+
+use super::AnalyzerTokenStream;
+
+/// The tokenizer which keeps URLs and emails intact along with their components.
+pub const URL_EMAIL_TOKENIZER: &str = "url_email";
+
+/// The punctuation removed from either side of a word before it is checked for a
+/// URL or email, i.e. the trailing `.` of a sentence.
+const TRIMMED_PUNCTUATION: &[char] = &[
+    '(', ')', '[', ']', '{', '}', '<', '>', '"', '\'', ',', '.', ';', ':', '!', '?',
+];
+
+#[derive(Debug, Default, Copy, Clone)]
+/// Tokenizes text like the `simple` tokenizer, except URLs and emails are kept as a
+/// single token followed by their components at the same position.
+///
+/// Emails produce their user and domain, i.e. `jane@example.com` also produces
+/// `jane` and `example.com`, while URLs produce their host and each segment of
+/// their path, i.e. `https://lnx.rs/docs/query` also produces `lnx.rs`, `docs` and
+/// `query`.
+pub struct UrlEmailTokenizer;
+
+impl Tokenizer for UrlEmailTokenizer {
+    type TokenStream<'a> = AnalyzerTokenStream;
+
+    fn token_stream<'a>(&self, text: &'a str) -> Self::TokenStream<'a> {
+        let mut tokens = Vec::new();
+        let mut position = 0;
+        let mut push = |range: Range<usize>, position: usize| {
+            tokens.push(Token {
+                offset_from: range.start,
+                offset_to: range.end,
+                position,
+                text: text[range].to_string(),
+                position_length: 1,
+            });
+        };
+
+        for word in runs(text, |c| !c.is_whitespace()) {
+            let trimmed = text[word.clone()].trim_start_matches(TRIMMED_PUNCTUATION);
+            let start = word.end - trimmed.len();
+            let trimmed = trimmed.trim_end_matches(TRIMMED_PUNCTUATION);
+            let value = &text[start..start + trimmed.len()];
+
+            let components = email_components(value).or_else(|| url_components(value));
+            let Some(components) = components else {
+                for part in runs(&text[word.clone()], char::is_alphanumeric) {
+                    push(word.start + part.start..word.start + part.end, position);
+                    position += 1;
+                }
+                continue;
+            };
+
+            push(start..start + value.len(), position);
+            for component in components {
+                if component.len() < value.len() {
+                    push(start + component.start..start + component.end, position);
+                }
+            }
+            position += 1;
+        }
+
+        AnalyzerTokenStream::new(tokens)
+    }
+}
+
+/// Returns the byte ranges of each run of characters matching the predicate.
+fn runs(text: &str, is_part: impl Fn(char) -> bool) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (offset, c) in text.char_indices() {
+        match (is_part(c), start) {
+            (true, None) => start = Some(offset),
+            (false, Some(from)) => {
+                ranges.push(from..offset);
+                start = None;
+            },
+            _ => {},
+        }
+    }
+
+    if let Some(from) = start {
+        ranges.push(from..text.len());
+    }
+
+    ranges
+}
+
+/// Returns the ranges of the user and domain of the value if it is an email.
+fn email_components(value: &str) -> Option<Vec<Range<usize>>> {
+    let (user, domain) = value.split_once('@')?;
+
+    let is_user = !user.is_empty()
+        && user
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-'));
+    let is_domain = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        });
+    if !is_user || !is_domain {
+        return None;
+    }
+
+    Some(vec![0..user.len(), user.len() + 1..value.len()])
+}
+
+/// Returns the ranges of the host and path segments of the value if it is a URL,
+/// either with a scheme, i.e. `https://`, or starting with `www.`.
+fn url_components(value: &str) -> Option<Vec<Range<usize>>> {
+    let rest_start = match value.find("://") {
+        Some(end) => {
+            let scheme = &value[..end];
+            let is_scheme = !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '.' | '-'));
+            if !is_scheme {
+                return None;
+            }
+            end + 3
+        },
+        None if value.len() > 4 && value[..4].eq_ignore_ascii_case("www.") => 0,
+        None => return None,
+    };
+
+    let rest = &value[rest_start..];
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+
+    // Skip any credentials and port, i.e. `user:pass@host:8080`.
+    let host_start = authority.rfind('@').map_or(0, |at| at + 1);
+    let host_end = authority[host_start..]
+        .find(':')
+        .map_or(authority.len(), |colon| host_start + colon);
+    if host_start == host_end {
+        return None;
+    }
+
+    let mut components = vec![rest_start + host_start..rest_start + host_end];
+
+    let path_start = rest_start + authority_end;
+    let path = &value[path_start..];
+    let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+    for segment in runs(path, |c| c != '/') {
+        components.push(path_start + segment.start..path_start + segment.end);
+    }
+
+    Some(components)
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::TokenStream;
+
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<(String, usize)> {
+        let mut stream = UrlEmailTokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            let token = stream.token();
+            assert_eq!(&text[token.offset_from..token.offset_to], token.text);
+            tokens.push((token.text.clone(), token.position));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_url_email_tokenizer() {
+        let expected = [
+            ("Mail", 0),
+            ("Jane.Doe@example.com", 1),
+            ("Jane.Doe", 1),
+            ("example.com", 1),
+            ("or", 2),
+            ("see", 3),
+            ("https://lnx.rs:8000/docs/getting-started?ref=mail", 4),
+            ("lnx.rs", 4),
+            ("docs", 4),
+            ("getting-started", 4),
+        ];
+        assert_eq!(
+            tokens(
+                "Mail <Jane.Doe@example.com> or see \
+                 https://lnx.rs:8000/docs/getting-started?ref=mail."
+            ),
+            expected.map(|(text, pos)| (text.to_string(), pos))
+        );
+
+        let expected = [("www.lnx.rs", 0), ("a", 1), ("b", 2), ("c", 3)];
+        assert_eq!(
+            tokens("www.lnx.rs a@b ://c"),
+            expected.map(|(text, pos)| (text.to_string(), pos))
+        );
+    }
+}
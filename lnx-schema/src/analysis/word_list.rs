@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SchemaError;
+
+/// The maximum number of words within a word list.
+pub const MAX_WORD_LIST_SIZE: usize = 1_000_000;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A named list of words used by token filters, i.e. the dictionary of a compound
+/// word filter.
+pub struct WordList {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The words of the list.
+    pub words: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// A file containing additional words, one per line.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub path: Option<PathBuf>,
+}
+
+impl WordList {
+    /// Loads the lowercased words of the list, including the words within its file.
+    pub fn load(&self, name: &str) -> Result<HashSet<String>, SchemaError> {
+        let invalid = |reason: String| {
+            SchemaError::InvalidAnalysis(format!("word list {name:?}: {reason}"))
+        };
+
+        let file = match self.path.as_ref() {
+            None => String::new(),
+            Some(path) => std::fs::read_to_string(path).map_err(|e| {
+                invalid(format!("unable to read {}: {e}", path.display()))
+            })?,
+        };
+
+        let mut words = HashSet::new();
+        for word in self.words.iter().map(String::as_str).chain(file.lines()) {
+            let word = word.trim();
+            if word.is_empty() || word.starts_with('#') {
+                continue;
+            }
+
+            words.insert(word.to_lowercase());
+            if words.len() > MAX_WORD_LIST_SIZE {
+                return Err(invalid(format!(
+                    "a word list can have at most {MAX_WORD_LIST_SIZE} words"
+                )));
+            }
+        }
+
+        if words.is_empty() {
+            return Err(invalid(
+                "the list must contain at least one word".to_string(),
+            ));
+        }
+
+        Ok(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_word_list() {
+        let list = WordList {
+            words: vec![
+                "Dampf".to_string(),
+                "# comment".to_string(),
+                " schiff ".to_string(),
+            ],
+            ..Default::default()
+        };
+        let words = list.load("german").unwrap();
+        assert_eq!(
+            words,
+            HashSet::from(["dampf".to_string(), "schiff".to_string()])
+        );
+
+        assert!(WordList::default().load("empty").is_err());
+
+        let list = WordList {
+            path: Some(PathBuf::from("/does/not/exist.txt")),
+            ..Default::default()
+        };
+        assert!(list.load("missing").is_err());
+    }
+}
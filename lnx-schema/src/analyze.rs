@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::{TokenStream, TokenizerManager};
+
+use crate::error::SchemaError;
+use crate::schema::IndexSchema;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A request to analyze some text, exactly one of `field` or `analyzer` must be set.
+pub struct AnalyzeRequest {
+    /// The text to analyze.
+    pub text: String,
+    #[serde(default)]
+    /// The field whose analyzer is used, including any ASCII folding and position
+    /// gap applied to the field.
+    pub field: Option<String>,
+    #[serde(default)]
+    /// The name of a built-in tokenizer or custom analyzer of the index.
+    pub analyzer: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A single token produced by analyzing some text.
+pub struct AnalyzedToken {
+    /// The text of the token which is indexed or searched for.
+    pub token: String,
+    /// The position of the token within the text.
+    pub position: usize,
+    /// The byte offset the token starts at within the original text.
+    pub start_offset: usize,
+    /// The byte offset the token ends at within the original text.
+    pub end_offset: usize,
+}
+
+impl IndexSchema {
+    /// Analyzes the text of the request, returning the tokens which would be
+    /// indexed, i.e. to debug why a query does not match a document.
+    ///
+    /// The tokenizers of the schema must already be registered on the given manager,
+    /// see [IndexSchema::register_tokenizers].
+    pub fn analyze(
+        &self,
+        manager: &TokenizerManager,
+        request: &AnalyzeRequest,
+    ) -> Result<Vec<AnalyzedToken>, SchemaError> {
+        let tokenizer = match (request.field.as_deref(), request.analyzer.as_deref()) {
+            (Some(name), None) => {
+                let field = self.field(self.resolve_alias(name)).ok_or_else(|| {
+                    SchemaError::InvalidAnalyzeRequest(format!("unknown field {name:?}"))
+                })?;
+
+                field.indexing_tokenizer().ok_or_else(|| {
+                    SchemaError::InvalidAnalyzeRequest(format!(
+                        "the field {name:?} is not tokenized"
+                    ))
+                })?
+            },
+            (None, Some(analyzer)) => analyzer.to_string(),
+            _ => {
+                return Err(SchemaError::InvalidAnalyzeRequest(
+                    "exactly one of `field` or `analyzer` must be provided".to_string(),
+                ))
+            },
+        };
+
+        let analyzer = manager.get(&tokenizer).ok_or_else(|| {
+            SchemaError::InvalidAnalyzeRequest(format!("unknown analyzer {tokenizer:?}"))
+        })?;
+
+        let mut stream = analyzer.token_stream(&request.text);
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            let token = stream.token();
+            tokens.push(AnalyzedToken {
+                token: token.text.clone(),
+                position: token.position,
+                start_offset: token.offset_from,
+                end_offset: token.offset_to,
+            });
+        }
+
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(json: &str) -> AnalyzeRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_analyze() {
+        let schema: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {
+                    "name": {"type": "text", "ascii_folding": true},
+                    "count": {"type": "u64"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let manager = TokenizerManager::default();
+        schema.register_tokenizers(&manager).unwrap();
+
+        let tokens = schema
+            .analyze(
+                &manager,
+                &request(r#"{"text": "Café Müller", "field": "name"}"#),
+            )
+            .unwrap();
+        assert_eq!(
+            tokens,
+            [
+                AnalyzedToken {
+                    token: "cafe".to_string(),
+                    position: 0,
+                    start_offset: 0,
+                    end_offset: 5,
+                },
+                AnalyzedToken {
+                    token: "muller".to_string(),
+                    position: 1,
+                    start_offset: 6,
+                    end_offset: 13,
+                },
+            ]
+        );
+
+        let tokens = schema
+            .analyze(
+                &manager,
+                &request(r#"{"text": "Café Müller", "analyzer": "raw"}"#),
+            )
+            .unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token, "Café Müller");
+
+        let cases = [
+            r#"{"text": "a", "field": "missing"}"#,
+            r#"{"text": "a", "field": "count"}"#,
+            r#"{"text": "a", "analyzer": "missing"}"#,
+            r#"{"text": "a", "field": "name", "analyzer": "raw"}"#,
+            r#"{"text": "a"}"#,
+        ];
+        for case in cases {
+            assert!(
+                schema.analyze(&manager, &request(case)).is_err(),
+                "{case} should be rejected"
+            );
+        }
+    }
+}
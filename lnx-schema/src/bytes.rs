@@ -0,0 +1,187 @@
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, Value};
+use lnx_transforms::BytesEncoding;
+
+use crate::error::SchemaError;
+use crate::schema::{FieldDefinition, FieldKind, IndexSchema};
+
+impl FieldDefinition {
+    /// The encoding values of a `bytes` field are provided and returned in.
+    ///
+    /// Returns `None` if the field is not a `bytes` field or the encoding is invalid.
+    pub fn bytes_encoding(&self) -> Option<BytesEncoding> {
+        if self.kind != FieldKind::Bytes {
+            return None;
+        }
+
+        match self.encoding.as_deref() {
+            None => Some(BytesEncoding::default()),
+            Some(name) => BytesEncoding::from_name(name),
+        }
+    }
+
+    /// Checks the `encoding` option of the field.
+    pub(crate) fn validate_bytes_encoding(&self, name: &str) -> Result<(), SchemaError> {
+        let Some(encoding) = self.encoding.as_deref() else {
+            return Ok(());
+        };
+
+        if self.kind != FieldKind::Bytes {
+            return Err(SchemaError::invalid_options(
+                name,
+                "an encoding can only be set on `bytes` fields",
+            ));
+        }
+
+        if BytesEncoding::from_name(encoding).is_none() {
+            return Err(SchemaError::invalid_options(
+                name,
+                format!("unknown encoding {encoding:?}, expected `base64` or `hex`"),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl IndexSchema {
+    /// Decodes the string values of any `bytes` fields within the document using the
+    /// encoding of the field.
+    pub fn decode_bytes<'a>(
+        &self,
+        document: &mut DynamicDocument<'a>,
+    ) -> Result<(), SchemaError> {
+        for (key, value) in document.iter_mut() {
+            let Some(encoding) = self
+                .fields
+                .get(key.as_ref())
+                .and_then(|field| field.bytes_encoding())
+            else {
+                continue;
+            };
+
+            decode_value(key, encoding, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the values of any `bytes` fields within the document as strings using
+    /// the encoding of the field.
+    ///
+    /// This is applied to documents before they are returned.
+    pub fn encode_bytes(&self, document: &mut DynamicDocument) {
+        for (key, value) in document.iter_mut() {
+            let Some(encoding) = self
+                .fields
+                .get(key.as_ref())
+                .and_then(|field| field.bytes_encoding())
+            else {
+                continue;
+            };
+
+            encode_value(encoding, value);
+        }
+    }
+}
+
+fn decode_value(
+    field: &str,
+    encoding: BytesEncoding,
+    value: &mut Value,
+) -> Result<(), SchemaError> {
+    match value {
+        Value::Str(s) => {
+            let bytes = encoding.decode(s).map_err(|e| SchemaError::InvalidValue {
+                field: field.to_string(),
+                reason: format!("invalid {encoding} value: {e}"),
+            })?;
+            *value = Value::Bytes(bytes);
+        },
+        Value::Array(values) => {
+            for value in values {
+                decode_value(field, encoding, value)?;
+            }
+        },
+        _ => {},
+    }
+
+    Ok(())
+}
+
+fn encode_value(encoding: BytesEncoding, value: &mut Value) {
+    match value {
+        Value::Bytes(bytes) => *value = Value::Str(Cow::Owned(encoding.encode(bytes))),
+        Value::Array(values) => {
+            for value in values {
+                encode_value(encoding, value);
+            }
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn schema() -> IndexSchema {
+        parse(
+            r#"{
+                "fields": {
+                    "checksum": {"type": "bytes", "encoding": "hex"},
+                    "payload": {"type": "bytes"}
+                }
+            }"#,
+        )
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let schema = schema();
+        schema.validate().unwrap();
+
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{"checksum": "00ff", "payload": ["aGVsbG8="], "other": "00ff"}"#,
+        )
+        .unwrap();
+        schema.decode_bytes(&mut document).unwrap();
+        assert_eq!(document[0].1, Value::Bytes(vec![0x00, 0xFF]));
+        assert_eq!(
+            document[1].1,
+            Value::Array(vec![Value::Bytes(b"hello".to_vec())])
+        );
+        assert_eq!(document[2].1, Value::from("00ff"));
+
+        schema.encode_bytes(&mut document);
+        assert_eq!(document[0].1, Value::from("00ff"));
+        assert_eq!(document[1].1, Value::Array(vec![Value::from("aGVsbG8=")]));
+    }
+
+    #[test]
+    fn test_invalid_bytes_value() {
+        let mut document: DynamicDocument =
+            serde_json::from_str(r#"{"checksum": "zz"}"#).unwrap();
+        assert!(matches!(
+            schema().decode_bytes(&mut document),
+            Err(SchemaError::InvalidValue { field, .. }) if field == "checksum"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let cases = [
+            r#"{"fields": {"a": {"type": "bytes", "encoding": "base32"}}}"#,
+            r#"{"fields": {"a": {"type": "text", "encoding": "hex"}}}"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+}
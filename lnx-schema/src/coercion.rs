@@ -0,0 +1,164 @@
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, UserDisplayType, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::error::SchemaError;
+use crate::schema::{FieldKind, IndexSchema};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How ingested values which do not match the type of their field are handled.
+pub enum CoercionMode {
+    #[default]
+    /// Values must already be of the field's type and are otherwise rejected.
+    ///
+    /// Strings are still accepted by types without a JSON representation,
+    /// i.e. `datetime`, `ip`, `facet` and `bytes` fields.
+    Strict,
+    /// Values are coerced to the field's type where it is lossless, i.e. `"42"` to a
+    /// `u64`, `1` to a `bool` and numbers to `string` fields.
+    Lenient,
+}
+
+impl IndexSchema {
+    /// Coerces the values of the document to the types of their fields when the
+    /// schema uses [CoercionMode::Lenient].
+    ///
+    /// Values which cannot be coerced are left as-is, except strings which fail to
+    /// parse as numbers or booleans which are rejected. Any remaining mismatches are
+    /// rejected by [IndexSchema::validate_document].
+    pub fn coerce_document<'a>(
+        &self,
+        document: &mut DynamicDocument<'a>,
+    ) -> Result<(), SchemaError> {
+        if self.coercion == CoercionMode::Strict {
+            return Ok(());
+        }
+
+        for (key, value) in document.iter_mut() {
+            if let Some(field) = self.fields.get(key.as_ref()) {
+                coerce_value(key, field.kind, value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn coerce_value(
+    field: &str,
+    kind: FieldKind,
+    value: &mut Value,
+) -> Result<(), SchemaError> {
+    if let Value::Array(values) = value {
+        for value in values {
+            coerce_value(field, kind, value)?;
+        }
+        return Ok(());
+    }
+
+    let coerced = match (kind, &*value) {
+        (FieldKind::U64, Value::Str(s)) => s.trim().parse().ok().map(Value::U64),
+        (FieldKind::U64, Value::I64(v)) => u64::try_from(*v).ok().map(Value::U64),
+        (FieldKind::U64, Value::F64(v)) if is_integral(*v) && *v >= 0.0 => {
+            Some(Value::U64(*v as u64))
+        },
+        (FieldKind::I64, Value::Str(s)) => s.trim().parse().ok().map(Value::I64),
+        (FieldKind::I64, Value::F64(v)) if is_integral(*v) => {
+            Some(Value::I64(*v as i64))
+        },
+        (FieldKind::F64, Value::Str(s)) => s.trim().parse().ok().map(Value::F64),
+        (FieldKind::Bool, Value::Str(s)) => s.trim().parse().ok().map(Value::Bool),
+        (FieldKind::Bool, Value::U64(v @ (0 | 1))) => Some(Value::Bool(*v == 1)),
+        (FieldKind::Bool, Value::I64(v @ (0 | 1))) => Some(Value::Bool(*v == 1)),
+        (FieldKind::Text | FieldKind::String, Value::U64(v)) => Some(text(v)),
+        (FieldKind::Text | FieldKind::String, Value::I64(v)) => Some(text(v)),
+        (FieldKind::Text | FieldKind::String, Value::F64(v)) => Some(text(v)),
+        (FieldKind::Text | FieldKind::String, Value::Bool(v)) => Some(text(v)),
+        _ => return Ok(()),
+    };
+
+    match coerced {
+        Some(coerced) => {
+            *value = coerced;
+            Ok(())
+        },
+        None => Err(SchemaError::InvalidValue {
+            field: field.to_string(),
+            reason: format!(
+                "cannot coerce `{}` value {value:?} to `{}`",
+                value.type_name(),
+                kind.type_name(),
+            ),
+        }),
+    }
+}
+
+/// Returns if the float is a whole number which fits within a 64-bit integer.
+fn is_integral(v: f64) -> bool {
+    v.fract() == 0.0 && v >= i64::MIN as f64 && v <= u64::MAX as f64
+}
+
+fn text(v: impl ToString) -> Value<'static> {
+    Value::Str(Cow::Owned(v.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(coercion: &str) -> IndexSchema {
+        serde_json::from_str(&format!(
+            r#"{{
+                "fields": {{
+                    "views": {{"type": "u64"}},
+                    "price": {{"type": "f64"}},
+                    "active": {{"type": "bool"}},
+                    "code": {{"type": "string"}}
+                }},
+                "coercion": "{coercion}"
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lenient_coercion() {
+        let schema = schema("lenient");
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{"views": ["42", 3.0], "price": "9.5", "active": 1, "code": 12}"#,
+        )
+        .unwrap();
+        schema.coerce_document(&mut document).unwrap();
+        schema.validate_document(&document).unwrap();
+
+        assert_eq!(
+            document[0].1,
+            Value::Array(vec![Value::U64(42), Value::U64(3)])
+        );
+        assert_eq!(document[1].1, Value::F64(9.5));
+        assert_eq!(document[2].1, Value::Bool(true));
+        assert_eq!(document[3].1, Value::from("12"));
+
+        let mut document: DynamicDocument =
+            serde_json::from_str(r#"{"views": "many"}"#).unwrap();
+        let err = schema.coerce_document(&mut document).unwrap_err();
+        assert!(err.to_string().contains("`string`"), "{err}");
+        assert!(err.to_string().contains("`u64`"), "{err}");
+    }
+
+    #[test]
+    fn test_strict_coercion() {
+        let schema = schema("strict");
+        let mut document: DynamicDocument =
+            serde_json::from_str(r#"{"views": "42", "active": 1}"#).unwrap();
+        schema.coerce_document(&mut document).unwrap();
+
+        assert_eq!(document[0].1, Value::from("42"));
+        let err = schema.validate_document(&document).unwrap_err();
+        assert!(
+            matches!(err, SchemaError::InvalidValue { field, .. } if field == "views")
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, Value};
+
+use crate::error::SchemaError;
+use crate::schema::{FieldKind, IndexSchema};
+
+impl IndexSchema {
+    /// Checks that every `copy_to` target is a multi-valued `text` or `string` field
+    /// which does not copy its own values elsewhere.
+    pub(crate) fn validate_copy_to(&self) -> Result<(), SchemaError> {
+        for (name, field) in self.fields.iter() {
+            for target_name in field.copy_to.iter() {
+                let invalid = |reason: &str| {
+                    SchemaError::invalid_options(
+                        name,
+                        format!("cannot copy to {target_name:?}, {reason}"),
+                    )
+                };
+
+                if target_name == name {
+                    return Err(invalid("a field cannot copy to itself"));
+                }
+
+                let Some(target) = self.fields.get(target_name) else {
+                    return Err(invalid("the field does not exist"));
+                };
+
+                if !matches!(target.kind, FieldKind::Text | FieldKind::String) {
+                    return Err(invalid(
+                        "only `text` and `string` fields can be copied to",
+                    ));
+                }
+
+                if !target.multi_value {
+                    return Err(invalid("the field must be multi-valued"));
+                }
+
+                if !target.copy_to.is_empty() {
+                    return Err(invalid("the field copies its values to other fields"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the values of any fields with a `copy_to` target into their targets.
+    ///
+    /// Values are appended to the document as additional entries of the target
+    /// field, numbers and booleans are copied as their text representation while
+    /// other values, i.e. bytes and objects, are not copied.
+    pub fn apply_copy_to<'a>(&self, document: &mut DynamicDocument<'a>) {
+        let mut copied = Vec::new();
+
+        for (key, value) in document.iter() {
+            let Some(field) = self.fields.get(key.as_ref()) else {
+                continue;
+            };
+
+            if field.copy_to.is_empty() {
+                continue;
+            }
+
+            let mut texts = Vec::new();
+            collect_text(value, &mut texts);
+
+            for target in field.copy_to.iter() {
+                for text in texts.iter() {
+                    copied.push((Cow::Owned(target.clone()), Value::Str(text.clone())));
+                }
+            }
+        }
+
+        document.extend(copied);
+    }
+}
+
+fn collect_text<'a>(value: &Value<'a>, texts: &mut Vec<Cow<'a, str>>) {
+    match value {
+        Value::Str(text) => texts.push(text.clone()),
+        Value::U64(v) => texts.push(Cow::Owned(v.to_string())),
+        Value::I64(v) => texts.push(Cow::Owned(v.to_string())),
+        Value::F64(v) => texts.push(Cow::Owned(v.to_string())),
+        Value::Bool(v) => texts.push(Cow::Owned(v.to_string())),
+        Value::Array(values) => {
+            for value in values {
+                collect_text(value, texts);
+            }
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_apply_copy_to() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "title": {"type": "text", "copy_to": ["all"]},
+                    "tags": {"type": "string", "copy_to": ["all"]},
+                    "views": {"type": "u64", "copy_to": ["all"]},
+                    "all": {"type": "text", "stored": false}
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{"title": "Hello world", "tags": ["a", "b"], "views": 3}"#,
+        )
+        .unwrap();
+        schema.apply_copy_to(&mut document);
+
+        let all = document
+            .iter()
+            .filter(|(key, _)| key == "all")
+            .map(|(_, value)| match value {
+                Value::Str(text) => text.as_ref(),
+                other => panic!("Unexpected value {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(all, ["Hello world", "a", "b", "3"]);
+    }
+
+    #[test]
+    fn test_invalid_copy_to() {
+        let cases = [
+            r#"{"fields": {"a": {"type": "text", "copy_to": ["a"]}}}"#,
+            r#"{"fields": {"a": {"type": "text", "copy_to": ["missing"]}}}"#,
+            r#"{"fields": {"a": {"type": "text", "copy_to": ["b"]}, "b": {"type": "u64"}}}"#,
+            r#"{
+                "fields": {
+                    "a": {"type": "text", "copy_to": ["b"]},
+                    "b": {"type": "text", "copy_to": ["c"]},
+                    "c": {"type": "text"}
+                }
+            }"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+}
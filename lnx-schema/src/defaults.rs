@@ -0,0 +1,188 @@
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, UserDisplayType, Value};
+
+use crate::error::SchemaError;
+use crate::schema::{FieldDefinition, IndexSchema};
+
+impl FieldDefinition {
+    /// Checks the `required` and `default` options of the field.
+    pub(crate) fn validate_default(&self, name: &str) -> Result<(), SchemaError> {
+        let Some(default) = self.default.as_ref() else {
+            return Ok(());
+        };
+
+        if self.required {
+            return Err(SchemaError::invalid_options(
+                name,
+                "a required field cannot have a default value",
+            ));
+        }
+
+        let value = json_to_value(default);
+        let values = match &value {
+            Value::Null => {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    "the default value cannot be null",
+                ))
+            },
+            Value::Array(_) if !self.multi_value => {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    "the default value of a single-valued field cannot be an array",
+                ))
+            },
+            Value::Array(values) => values.as_slice(),
+            value => std::slice::from_ref(value),
+        };
+
+        for value in values {
+            if !self.kind.accepts(value.as_field_type()) {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    format!(
+                        "the default value must be a `{}` value but got `{}`",
+                        self.kind.type_name(),
+                        value.type_name(),
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl IndexSchema {
+    /// Fills in the default values of any fields missing from the document.
+    ///
+    /// Returns an error if the document is missing a required field, a field whose
+    /// values are all `null` is treated as missing.
+    pub fn apply_defaults<'a>(
+        &self,
+        document: &mut DynamicDocument<'a>,
+    ) -> Result<(), SchemaError> {
+        for (name, field) in self.fields.iter() {
+            if !field.required && field.default.is_none() {
+                continue;
+            }
+
+            let is_present = document
+                .iter()
+                .any(|(key, value)| key == name && has_value(value));
+            if is_present {
+                continue;
+            }
+
+            if field.required {
+                return Err(SchemaError::MissingField(name.clone()));
+            }
+
+            if let Some(default) = field.default.as_ref() {
+                document.push((Cow::Owned(name.clone()), json_to_value(default)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn has_value(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Array(values) => values.iter().any(has_value),
+        _ => true,
+    }
+}
+
+/// Converts a JSON value from the schema into a document value.
+pub(crate) fn json_to_value(value: &serde_json::Value) -> Value<'static> {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(v) => Value::Bool(*v),
+        serde_json::Value::Number(v) => {
+            if let Some(v) = v.as_u64() {
+                Value::U64(v)
+            } else if let Some(v) = v.as_i64() {
+                Value::I64(v)
+            } else {
+                Value::F64(v.as_f64().unwrap_or_default())
+            }
+        },
+        serde_json::Value::String(v) => Value::Str(Cow::Owned(v.clone())),
+        serde_json::Value::Array(values) => {
+            Value::Array(values.iter().map(json_to_value).collect())
+        },
+        serde_json::Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (Cow::Owned(key.clone()), json_to_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn schema() -> IndexSchema {
+        parse(
+            r#"{
+                "fields": {
+                    "id": {"type": "string", "required": true},
+                    "status": {"type": "string", "default": "active"},
+                    "views": {"type": "u64", "default": 0}
+                }
+            }"#,
+        )
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let schema = schema();
+        schema.validate().unwrap();
+
+        let mut document: DynamicDocument =
+            serde_json::from_str(r#"{"id": "a", "views": null}"#).unwrap();
+        schema.apply_defaults(&mut document).unwrap();
+
+        let status = document.iter().find(|(key, _)| key == "status").unwrap();
+        assert_eq!(status.1, Value::Str("active".into()));
+
+        let views = document
+            .iter()
+            .filter(|(key, _)| key == "views")
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(views, [Value::Null, Value::U64(0)]);
+    }
+
+    #[test]
+    fn test_missing_required_field() {
+        let mut document: DynamicDocument =
+            serde_json::from_str(r#"{"id": [null], "status": "deleted"}"#).unwrap();
+        assert!(matches!(
+            schema().apply_defaults(&mut document),
+            Err(SchemaError::MissingField(field)) if field == "id"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_defaults() {
+        let cases = [
+            r#"{"fields": {"a": {"type": "u64", "default": -1}}}"#,
+            r#"{"fields": {"a": {"type": "u64", "required": true, "default": 1}}}"#,
+            r#"{"fields": {"a": {"type": "u64", "multi_value": false, "default": [1]}}}"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+}
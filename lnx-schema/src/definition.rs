@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SchemaError;
+use crate::schema::IndexSchema;
+
+/// The current version of the index definition format.
+pub const DEFINITION_VERSION: u32 = 1;
+/// The maximum number of metadata entries an index can have.
+pub const MAX_METADATA_ENTRIES: usize = 64;
+/// The maximum length in bytes of a metadata key.
+pub const MAX_METADATA_KEY_LENGTH: usize = 128;
+/// The maximum length in bytes of a metadata value.
+pub const MAX_METADATA_VALUE_LENGTH: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The complete, portable definition of an index.
+///
+/// This is the document returned when exporting an index and the same document is
+/// accepted when creating an index, so definitions can be kept in version control
+/// and promoted between environments unchanged.
+pub struct IndexDefinition {
+    #[serde(default = "default_version")]
+    /// The version of the definition format.
+    pub version: u32,
+    /// The name of the index.
+    pub name: String,
+    /// The schema of the index.
+    pub schema: IndexSchema,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// Arbitrary key/value metadata attached to the index, i.e. its owner or
+    /// environment, which is returned when listing indexes.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl IndexDefinition {
+    /// Creates a new definition for the given index.
+    pub fn new(name: impl Into<String>, schema: IndexSchema) -> Self {
+        Self {
+            version: DEFINITION_VERSION,
+            name: name.into(),
+            schema,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the metadata of the index.
+    pub fn set_metadata(
+        &mut self,
+        metadata: BTreeMap<String, String>,
+    ) -> Result<(), SchemaError> {
+        validate_metadata(&metadata)?;
+        self.metadata = metadata;
+        Ok(())
+    }
+
+    /// Returns the summary of the index used when listing indexes.
+    pub fn summary(&self) -> IndexSummary {
+        IndexSummary {
+            name: &self.name,
+            metadata: &self.metadata,
+        }
+    }
+
+    /// Parses and validates an exported index definition.
+    pub fn from_json(json: &[u8]) -> Result<Self, SchemaError> {
+        let definition: Self = serde_json::from_slice(json)
+            .map_err(|e| SchemaError::InvalidDefinition(e.to_string()))?;
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    /// Exports the definition as a JSON document.
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self)
+            .expect("Index definitions should be serializable")
+    }
+
+    /// Checks the version, index name and schema of the definition are valid.
+    pub fn validate(&self) -> Result<(), SchemaError> {
+        if self.version == 0 || self.version > DEFINITION_VERSION {
+            return Err(SchemaError::InvalidDefinition(format!(
+                "unsupported definition version {}, expected at most {DEFINITION_VERSION}",
+                self.version,
+            )));
+        }
+
+        validate_index_name(&self.name)?;
+        validate_metadata(&self.metadata)?;
+        self.schema.validate()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+/// The summary of an index returned when listing indexes.
+pub struct IndexSummary<'a> {
+    /// The name of the index.
+    pub name: &'a str,
+    /// The metadata attached to the index.
+    pub metadata: &'a BTreeMap<String, String>,
+}
+
+impl<'a> IndexSummary<'a> {
+    /// Returns if the index has every one of the given metadata entries, this is
+    /// used to filter index listings, i.e. by `team` or `environment`.
+    pub fn matches(&self, filter: &BTreeMap<String, String>) -> bool {
+        filter
+            .iter()
+            .all(|(key, value)| self.metadata.get(key) == Some(value))
+    }
+}
+
+fn validate_metadata(metadata: &BTreeMap<String, String>) -> Result<(), SchemaError> {
+    if metadata.len() > MAX_METADATA_ENTRIES {
+        return Err(SchemaError::InvalidDefinition(format!(
+            "an index can have at most {MAX_METADATA_ENTRIES} metadata entries"
+        )));
+    }
+
+    for (key, value) in metadata {
+        if key.is_empty() || key.len() > MAX_METADATA_KEY_LENGTH {
+            return Err(SchemaError::InvalidDefinition(format!(
+                "invalid metadata key {key:?}, keys must be between 1 and {MAX_METADATA_KEY_LENGTH} bytes"
+            )));
+        }
+
+        if value.len() > MAX_METADATA_VALUE_LENGTH {
+            return Err(SchemaError::InvalidDefinition(format!(
+                "the value of metadata key {key:?} exceeds {MAX_METADATA_VALUE_LENGTH} bytes"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the name of an index is allowed.
+///
+/// `*` and `,` are used when searching multiple indexes so cannot appear in names.
+fn validate_index_name(name: &str) -> Result<(), SchemaError> {
+    if name.is_empty() {
+        return Err(SchemaError::InvalidDefinition(
+            "the index name cannot be empty".to_string(),
+        ));
+    }
+
+    if name.contains(['*', ',']) || name.contains(char::is_whitespace) {
+        return Err(SchemaError::InvalidDefinition(format!(
+            "invalid index name {name:?}, names cannot contain `*`, `,` or whitespace"
+        )));
+    }
+
+    Ok(())
+}
+
+fn default_version() -> u32 {
+    DEFINITION_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definition_round_trip() {
+        let schema: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {
+                    "title": {"type": "text", "copy_to": ["all"]},
+                    "all": {"type": "text", "stored": false},
+                    "created_at": {"type": "datetime", "formats": ["unix_seconds"]}
+                },
+                "dynamic": {"mode": "strict"},
+                "aliases": {"ts": "created_at"}
+            }"#,
+        )
+        .unwrap();
+        let mut definition = IndexDefinition::new("products", schema);
+        definition
+            .set_metadata(BTreeMap::from([(
+                "owner".to_string(),
+                "search".to_string(),
+            )]))
+            .unwrap();
+
+        let exported = definition.to_json();
+        let imported = IndexDefinition::from_json(&exported).unwrap();
+        assert_eq!(imported, definition);
+        assert_eq!(imported.to_json(), exported);
+    }
+
+    #[test]
+    fn test_index_metadata() {
+        let schema: IndexSchema =
+            serde_json::from_str(r#"{"fields": {"a": {"type": "u64"}}}"#).unwrap();
+        let mut definition = IndexDefinition::new("products", schema);
+
+        let metadata = BTreeMap::from([
+            ("environment".to_string(), "staging".to_string()),
+            ("owner".to_string(), "search-team".to_string()),
+        ]);
+        definition.set_metadata(metadata).unwrap();
+
+        let summary = definition.summary();
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            serde_json::json!({
+                "name": "products",
+                "metadata": {"environment": "staging", "owner": "search-team"}
+            })
+        );
+        assert!(summary.matches(&BTreeMap::from([(
+            "owner".to_string(),
+            "search-team".to_string()
+        )])));
+        assert!(!summary.matches(&BTreeMap::from([(
+            "environment".to_string(),
+            "production".to_string()
+        )])));
+
+        let invalid = BTreeMap::from([(String::new(), "value".to_string())]);
+        assert!(definition.set_metadata(invalid).is_err());
+        let invalid = (0..=MAX_METADATA_ENTRIES)
+            .map(|i| (i.to_string(), String::new()))
+            .collect();
+        assert!(definition.set_metadata(invalid).is_err());
+        assert_eq!(definition.metadata.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let cases = [
+            r#"{"name": "products"}"#,
+            r#"{"name": "", "schema": {"fields": {"a": {"type": "u64"}}}}"#,
+            r#"{"name": "logs-*", "schema": {"fields": {"a": {"type": "u64"}}}}"#,
+            r#"{"version": 2, "name": "a", "schema": {"fields": {"a": {"type": "u64"}}}}"#,
+            r#"{"name": "products", "schema": {"fields": {}}}"#,
+        ];
+
+        for case in cases {
+            assert!(
+                IndexDefinition::from_json(case.as_bytes()).is_err(),
+                "{case} should be rejected"
+            );
+        }
+    }
+}
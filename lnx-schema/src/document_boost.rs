@@ -0,0 +1,125 @@
+use lnx_document::{DynamicDocument, UserDisplayType, Value};
+use tantivy::schema::{Field, SchemaBuilder, FAST};
+
+use crate::error::SchemaError;
+use crate::indexing::{FieldType, IndexingSchema};
+use crate::schema::IndexSchema;
+
+/// The reserved document key holding the boost of a document.
+///
+/// When [IndexSchema::document_boost] is enabled the value is stored in a hidden `f64`
+/// fast field of the same name, which is multiplied into the score of the document at
+/// query time.
+pub const DOCUMENT_BOOST_FIELD: &str = "_boost";
+
+impl IndexSchema {
+    /// Adds the hidden document boost field to the schema if document boosts are enabled.
+    pub(crate) fn add_document_boost_field(
+        &self,
+        builder: &mut SchemaBuilder,
+    ) -> Option<Field> {
+        self.document_boost
+            .then(|| builder.add_f64_field(DOCUMENT_BOOST_FIELD, FAST))
+    }
+
+    /// Registers the document boost field so the reserved key is indexed into it.
+    pub(crate) fn register_document_boost_field(
+        &self,
+        indexing: &mut IndexingSchema,
+        field_id: Field,
+    ) {
+        indexing.add_field(DOCUMENT_BOOST_FIELD, FieldType::F64 { field_id });
+    }
+
+    /// Checks the `_boost` value of a document is a single positive number.
+    ///
+    /// Documents without a boost, or with a `null` boost, keep a boost of `1.0`.
+    /// The key is rejected entirely if document boosts are not enabled on the index.
+    pub(crate) fn validate_document_boost(
+        &self,
+        document: &DynamicDocument,
+    ) -> Result<(), SchemaError> {
+        let mut num_values = 0;
+
+        for (key, value) in document.iter() {
+            if key.as_ref() != DOCUMENT_BOOST_FIELD || matches!(value, Value::Null) {
+                continue;
+            }
+
+            if !self.document_boost {
+                return Err(invalid_boost(
+                    "document boosts are not enabled on the index".to_string(),
+                ));
+            }
+
+            let boost = match value {
+                Value::U64(v) => *v as f64,
+                Value::I64(v) => *v as f64,
+                Value::F64(v) => *v,
+                other => {
+                    return Err(invalid_boost(format!(
+                        "expected a number but got `{}`",
+                        other.type_name()
+                    )))
+                },
+            };
+
+            if !boost.is_finite() || boost <= 0.0 {
+                return Err(invalid_boost(format!(
+                    "the boost must be a positive number, got {boost}"
+                )));
+            }
+
+            num_values += 1;
+            if num_values > 1 {
+                return Err(invalid_boost(
+                    "a document can only have a single boost".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_boost(reason: String) -> SchemaError {
+    SchemaError::InvalidValue {
+        field: DOCUMENT_BOOST_FIELD.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(schema: &IndexSchema, json: &str) -> Result<(), SchemaError> {
+        let document: DynamicDocument = serde_json::from_str(json).unwrap();
+        schema.validate_document(&document)
+    }
+
+    #[test]
+    fn test_document_boost() {
+        let schema: IndexSchema = serde_json::from_str(
+            r#"{"fields": {"title": {"type": "text"}}, "document_boost": true}"#,
+        )
+        .unwrap();
+        let (tantivy_schema, _) = schema.build().unwrap();
+        let field = tantivy_schema.get_field(DOCUMENT_BOOST_FIELD).unwrap();
+        assert!(tantivy_schema.get_field_entry(field).is_fast());
+
+        assert!(validate(&schema, r#"{"title": "a", "_boost": 2}"#).is_ok());
+        assert!(validate(&schema, r#"{"title": "a", "_boost": 0.5}"#).is_ok());
+        assert!(validate(&schema, r#"{"title": "a", "_boost": null}"#).is_ok());
+        assert!(validate(&schema, r#"{"_boost": 0}"#).is_err());
+        assert!(validate(&schema, r#"{"_boost": -1.5}"#).is_err());
+        assert!(validate(&schema, r#"{"_boost": "high"}"#).is_err());
+        assert!(validate(&schema, r#"{"_boost": [1, 2]}"#).is_err());
+
+        let disabled: IndexSchema =
+            serde_json::from_str(r#"{"fields": {"title": {"type": "text"}}}"#).unwrap();
+        let (tantivy_schema, _) = disabled.build().unwrap();
+        assert!(tantivy_schema.get_field(DOCUMENT_BOOST_FIELD).is_err());
+        assert!(validate(&disabled, r#"{"_boost": 2}"#).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use lnx_document::{FieldType as DocumentFieldType, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::error::SchemaError;
+use crate::schema::{FieldDefinition, FieldKind};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How document keys which are not part of the schema are handled.
+pub enum DynamicMode {
+    #[default]
+    /// Unknown keys are ignored and not indexed.
+    Ignore,
+    /// Unknown keys are added to the schema based on the type of their value.
+    Map,
+    /// Documents containing unknown keys are rejected.
+    Strict,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The dynamic mapping settings of an index.
+pub struct DynamicMapping {
+    #[serde(default)]
+    /// How unknown keys are handled.
+    pub mode: DynamicMode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The templates used to map unknown keys, the first matching template is used.
+    ///
+    /// Keys which do not match any template use the default mapping for their type.
+    pub templates: Vec<DynamicTemplate>,
+}
+
+impl DynamicMapping {
+    /// Checks that every template is valid.
+    pub fn validate(&self) -> Result<(), SchemaError> {
+        for template in self.templates.iter() {
+            let name = format!("dynamic template {:?}", template.pattern);
+
+            if template.pattern.is_empty() {
+                return Err(SchemaError::invalid_options(
+                    &name,
+                    "the match pattern cannot be empty",
+                ));
+            }
+
+            template.mapping.validate(&name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the definition of an unknown field from the type of its value.
+    ///
+    /// Returns `None` if the field cannot be mapped, i.e. the value is `null` or an
+    /// empty array, in which case the field is mapped once a typed value is seen.
+    pub fn resolve(&self, name: &str, value: &Value) -> Option<FieldDefinition> {
+        let value_type = inferred_value_type(value)?;
+
+        let template = self.templates.iter().find(|template| {
+            wildcard_matches(&template.pattern, name)
+                && template
+                    .match_type
+                    .map_or(true, |kind| Some(kind) == default_kind(value_type))
+                && template.mapping.kind.accepts(value_type)
+        });
+
+        if let Some(template) = template {
+            return Some(template.mapping.clone());
+        }
+
+        default_kind(value_type).map(FieldDefinition::new)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A template describing how matching unknown keys should be mapped.
+pub struct DynamicTemplate {
+    #[serde(rename = "match")]
+    /// The pattern the key must match, `*` matches any sequence of characters,
+    /// i.e. `*_at`.
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Only match keys whose value would be mapped to this type by default,
+    /// i.e. `text` to only match string values.
+    pub match_type: Option<FieldKind>,
+    /// The definition of the field added to the schema.
+    pub mapping: FieldDefinition,
+}
+
+/// The type of field used for a value when no template matches.
+fn default_kind(value_type: DocumentFieldType) -> Option<FieldKind> {
+    let kind = match value_type {
+        DocumentFieldType::String => FieldKind::Text,
+        DocumentFieldType::Bytes => FieldKind::Bytes,
+        DocumentFieldType::Bool => FieldKind::Bool,
+        DocumentFieldType::U64 => FieldKind::U64,
+        DocumentFieldType::I64 => FieldKind::I64,
+        DocumentFieldType::F64 => FieldKind::F64,
+        DocumentFieldType::IpAddr => FieldKind::Ip,
+        DocumentFieldType::DateTime => FieldKind::Datetime,
+        DocumentFieldType::Facet => FieldKind::Facet,
+        DocumentFieldType::Object => FieldKind::Dynamic,
+        DocumentFieldType::Null | DocumentFieldType::Array => return None,
+    };
+
+    Some(kind)
+}
+
+/// The type used to map a value, arrays are mapped by their first non-null element.
+fn inferred_value_type(value: &Value) -> Option<DocumentFieldType> {
+    match value {
+        Value::Null => None,
+        Value::Array(values) => values.iter().find_map(inferred_value_type),
+        other => Some(other.as_field_type()),
+    }
+}
+
+/// Matches a name against a pattern where `*` matches any sequence of characters.
+pub(crate) fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+
+    // There is always at least one part, even for an empty pattern.
+    let first = parts.next().unwrap_or_default();
+    let Some(mut remaining) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.collect::<Vec<_>>();
+    let last = match parts.pop() {
+        Some(last) => last,
+        None => return remaining.is_empty(),
+    };
+
+    for part in parts {
+        match remaining.find(part) {
+            Some(pos) => remaining = &remaining[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping() -> DynamicMapping {
+        serde_json::from_str(
+            r#"{
+                "mode": "map",
+                "templates": [
+                    {"match": "*_at", "mapping": {"type": "datetime", "fast": true}},
+                    {"match": "*", "match_type": "text", "mapping": {"type": "string"}}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_templates() {
+        let mapping = mapping();
+        mapping.validate().unwrap();
+
+        let field = mapping.resolve("created_at", &Value::U64(1)).unwrap();
+        assert_eq!(field.kind, FieldKind::Datetime);
+        assert!(field.fast);
+
+        let field = mapping
+            .resolve("level", &Value::Str("info".into()))
+            .unwrap();
+        assert_eq!(field.kind, FieldKind::String);
+
+        let field = mapping.resolve("count", &Value::U64(1)).unwrap();
+        assert_eq!(field, FieldDefinition::new(FieldKind::U64));
+
+        // Booleans cannot be indexed as a datetime so fall back to the default.
+        let field = mapping.resolve("deleted_at", &Value::Bool(true)).unwrap();
+        assert_eq!(field.kind, FieldKind::Bool);
+    }
+
+    #[test]
+    fn test_resolve_untyped_values() {
+        let mapping = mapping();
+        assert!(mapping.resolve("a", &Value::Null).is_none());
+        assert!(mapping.resolve("a", &Value::Array(vec![])).is_none());
+
+        let values = Value::Array(vec![Value::Null, Value::F64(1.5)]);
+        let field = mapping.resolve("a", &values).unwrap();
+        assert_eq!(field.kind, FieldKind::F64);
+    }
+
+    #[test]
+    fn test_wildcard_matches() {
+        assert!(wildcard_matches("*_at", "created_at"));
+        assert!(!wildcard_matches("*_at", "created"));
+        assert!(wildcard_matches("meta_*_id", "meta_user_id"));
+        assert!(wildcard_matches("name", "name"));
+        assert!(!wildcard_matches("name", "names"));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::update::DestructiveChange;
+
+#[derive(Debug, thiserror::Error)]
+/// An error which prevents a schema definition from being used.
+pub enum SchemaError {
+    #[error("Schema must define at least one field")]
+    /// The schema does not define any fields.
+    Empty,
+    #[error("Invalid field name {name:?}: {reason}")]
+    /// The name of the field is not allowed.
+    InvalidFieldName { name: String, reason: String },
+    #[error("Unknown field {0:?}, the index does not allow dynamic fields")]
+    /// A document contains a key which is not part of a strict schema.
+    UnknownField(String),
+    #[error("Document is missing the required field {0:?}")]
+    /// A document does not contain a value for a required field.
+    MissingField(String),
+    #[error("Invalid value for field {field:?}: {reason}")]
+    /// A document value cannot be indexed in its field.
+    InvalidValue { field: String, reason: String },
+    #[error(
+        "Schema update requires a reindex, {} changes are not additive",
+        .0.len()
+    )]
+    /// A schema update changes or removes existing fields.
+    DestructiveChange(Vec<DestructiveChange>),
+    #[error("Invalid options for field {field:?}: {reason}")]
+    /// The options of the field are not supported by its type.
+    InvalidFieldOptions { field: String, reason: String },
+    #[error("Invalid index definition: {0}")]
+    /// An exported index definition cannot be imported.
+    InvalidDefinition(String),
+    #[error("Invalid analysis settings: {0}")]
+    /// The analysis settings of the index are not valid.
+    InvalidAnalysis(String),
+    #[error("Invalid analyze request: {0}")]
+    /// Text cannot be analyzed with the requested field or analyzer.
+    InvalidAnalyzeRequest(String),
+}
+
+impl SchemaError {
+    pub(crate) fn invalid_options(field: &str, reason: impl Into<String>) -> Self {
+        Self::InvalidFieldOptions {
+            field: field.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
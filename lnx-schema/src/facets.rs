@@ -0,0 +1,158 @@
+use lnx_document::{DynamicDocument, Value};
+use tantivy::schema::Facet;
+
+use crate::error::SchemaError;
+use crate::schema::{FieldDefinition, FieldKind, IndexSchema};
+
+impl FieldDefinition {
+    /// Checks the `path_delimiter` option of the field.
+    pub(crate) fn validate_path_delimiter(&self, name: &str) -> Result<(), SchemaError> {
+        let Some(delimiter) = self.path_delimiter.as_deref() else {
+            return Ok(());
+        };
+
+        if self.kind != FieldKind::Facet {
+            return Err(SchemaError::invalid_options(
+                name,
+                "a path delimiter can only be set on `facet` fields",
+            ));
+        }
+
+        if delimiter.is_empty() {
+            return Err(SchemaError::invalid_options(
+                name,
+                "the path delimiter cannot be empty",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl IndexSchema {
+    /// Derives the facets of any `facet` fields with a path delimiter from their
+    /// string values, i.e. `clothing>men>shirts` becomes `/clothing/men/shirts`.
+    ///
+    /// Each segment is trimmed and empty segments are skipped, any `/` within a
+    /// segment is escaped rather than starting a new level of the hierarchy.
+    pub fn derive_facets<'a>(
+        &self,
+        document: &mut DynamicDocument<'a>,
+    ) -> Result<(), SchemaError> {
+        for (key, value) in document.iter_mut() {
+            let Some(delimiter) = self
+                .fields
+                .get(key.as_ref())
+                .and_then(|field| field.path_delimiter.as_deref())
+            else {
+                continue;
+            };
+
+            derive_facet(key, delimiter, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn derive_facet(
+    field: &str,
+    delimiter: &str,
+    value: &mut Value,
+) -> Result<(), SchemaError> {
+    match value {
+        Value::Str(path) => {
+            let segments = path
+                .split(delimiter)
+                .map(str::trim)
+                .filter(|segment| !segment.is_empty())
+                .collect::<Vec<_>>();
+
+            if segments.is_empty() {
+                return Err(SchemaError::InvalidValue {
+                    field: field.to_string(),
+                    reason: format!("the facet path {path:?} has no segments"),
+                });
+            }
+
+            *value = Value::from(Facet::from_path(segments));
+        },
+        Value::Array(values) => {
+            for value in values {
+                derive_facet(field, delimiter, value)?;
+            }
+        },
+        _ => {},
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_derive_facets() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "category": {"type": "facet", "path_delimiter": ">"},
+                    "tags": {"type": "facet"}
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{
+                "category": ["clothing > men > shirts", "sale>>a/b"],
+                "tags": "/new"
+            }"#,
+        )
+        .unwrap();
+        schema.derive_facets(&mut document).unwrap();
+
+        let Value::Array(values) = &document[0].1 else {
+            panic!("Expected an array of facets");
+        };
+        let facets = values
+            .iter()
+            .map(|value| match value {
+                Value::Facet(facet) => facet.to_tantivy_facet().unwrap(),
+                other => panic!("Unexpected value {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            facets,
+            [
+                Facet::from_path(["clothing", "men", "shirts"]),
+                Facet::from_path(["sale", "a/b"]),
+            ]
+        );
+        assert_eq!(document[1].1, Value::from("/new"));
+
+        let mut document: DynamicDocument =
+            serde_json::from_str(r#"{"category": " > "}"#).unwrap();
+        assert!(matches!(
+            schema.derive_facets(&mut document),
+            Err(SchemaError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_path_delimiter() {
+        let cases = [
+            r#"{"fields": {"a": {"type": "string", "path_delimiter": ">"}}}"#,
+            r#"{"fields": {"a": {"type": "facet", "path_delimiter": ""}}}"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+}
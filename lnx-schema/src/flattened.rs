@@ -0,0 +1,129 @@
+//! Flattened object fields.
+//!
+//! A `flattened` field indexes an entire object as a set of keyword tokens, one
+//! per leaf value in the form `key=value`, where nested keys are joined with `.`.
+//!
+//! Unlike `dynamic` fields, no per-key configuration is created within the index,
+//! which keeps objects with thousands of sparse keys cheap to index. Leaves are
+//! matched with exact term queries on the dotted path, i.e. `attrs.color` = `red`.
+
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, Value};
+use lnx_transforms::TypeCast;
+
+use crate::schema::{FieldKind, IndexSchema};
+
+/// The separator between the key and the value of a flattened token.
+pub const KEY_VALUE_SEPARATOR: char = '=';
+
+/// Produces the token a leaf value is indexed as within a flattened field.
+pub fn flattened_token(key: &str, value: &str) -> String {
+    format!("{key}{KEY_VALUE_SEPARATOR}{value}")
+}
+
+impl IndexSchema {
+    /// Replaces the objects of any `flattened` fields within the document with
+    /// their `key=value` tokens.
+    ///
+    /// Values are indexed as their text representation, matching how term values are
+    /// cast when querying, array elements are indexed under the same key and `null`
+    /// values are skipped.
+    pub fn flatten_objects<'a>(&self, document: &mut DynamicDocument<'a>) {
+        for (key, value) in document.iter_mut() {
+            let is_flattened = self
+                .fields
+                .get(key.as_ref())
+                .map_or(false, |field| field.kind == FieldKind::Flattened);
+            if !is_flattened {
+                continue;
+            }
+
+            let mut tokens = Vec::new();
+            collect_tokens("", value, &mut tokens);
+            *value = Value::Array(tokens);
+        }
+    }
+}
+
+fn collect_tokens(path: &str, value: &Value, tokens: &mut Vec<Value<'static>>) {
+    match value {
+        Value::Null => {},
+        Value::Array(values) => {
+            for value in values {
+                collect_tokens(path, value, tokens);
+            }
+        },
+        Value::Object(entries) => {
+            for (key, value) in entries {
+                let path = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{path}.{key}")
+                };
+                collect_tokens(&path, value, tokens);
+            }
+        },
+        // Values outside of an object have no key to be addressed by.
+        _ if path.is_empty() => {},
+        value => {
+            if let Ok(Value::Str(text)) = TypeCast::String.try_cast_value(value.clone())
+            {
+                tokens.push(Value::Str(Cow::Owned(flattened_token(path, &text))));
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> IndexSchema {
+        serde_json::from_str(r#"{"fields": {"attrs": {"type": "flattened"}}}"#).unwrap()
+    }
+
+    #[test]
+    fn test_flatten_objects() {
+        let schema = schema();
+        schema.validate().unwrap();
+
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{
+                "attrs": {
+                    "color": "red",
+                    "sizes": [8, 9],
+                    "shipping": {"free": true, "region": null}
+                },
+                "title": "Shoes"
+            }"#,
+        )
+        .unwrap();
+        schema.flatten_objects(&mut document);
+
+        assert_eq!(
+            document[0].1,
+            Value::Array(vec![
+                Value::from("color=red"),
+                Value::from("sizes=8"),
+                Value::from("sizes=9"),
+                Value::from("shipping.free=true"),
+            ])
+        );
+        assert_eq!(document[1].1, Value::from("Shoes"));
+    }
+
+    #[test]
+    fn test_flattened_field_options() {
+        let (schema, _) = schema().build().unwrap();
+        let entry = schema.get_field_entry(schema.get_field("attrs").unwrap());
+        assert!(entry.is_indexed());
+        assert!(entry.is_stored());
+
+        let invalid: IndexSchema = serde_json::from_str(
+            r#"{"fields": {"attrs": {"type": "flattened", "tokenizer": "en_stem"}}}"#,
+        )
+        .unwrap();
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -2,9 +2,28 @@ use std::collections::hash_map::{DefaultHasher, RandomState};
 use std::collections::{HashMap, HashSet};
 use std::hash::{BuildHasher, Hasher};
 
+use lnx_tools::consistent_hash;
 use lnx_tools::hashers::NoOpRandomState;
 use tantivy::schema::Field;
 
+/// Produces the key used to track the presence of a field on a document.
+///
+/// The `key` is the flattened object key for values nested within a dynamic field,
+/// unlike the hashes produced by the [IndexingSchema] this key is stable across restarts
+/// as it is persisted within the index.
+pub fn field_presence_key(field: Field, key: Option<&str>) -> u64 {
+    let key = key.unwrap_or_default();
+    let mut buffer = Vec::with_capacity(5 + key.len());
+    buffer.extend_from_slice(&field.field_id().to_le_bytes());
+
+    if !key.is_empty() {
+        buffer.push(b'.');
+        buffer.extend_from_slice(key.as_bytes());
+    }
+
+    consistent_hash(buffer)
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct IndexingSchema {
     /// The random state used for
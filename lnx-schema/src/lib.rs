@@ -1 +1,28 @@
+mod aliases;
+pub mod analysis;
+pub mod analyze;
+mod bytes;
+pub mod coercion;
+mod copy_to;
+mod defaults;
+pub mod definition;
+pub mod document_boost;
+pub mod dynamic;
+mod error;
+mod facets;
+pub mod flattened;
 pub mod indexing;
+pub mod limits;
+pub mod nested;
+pub mod null_handling;
+pub mod presence;
+pub mod schema;
+pub mod similarity;
+mod stored;
+pub mod templates;
+pub mod tokenizer;
+pub mod update;
+mod validate;
+pub mod warnings;
+
+pub use self::error::SchemaError;
@@ -0,0 +1,269 @@
+use std::fmt::{Display, Formatter};
+
+use lnx_document::{DynamicDocument, Value};
+use serde::{Deserialize, Serialize};
+use tantivy::tokenizer::{TokenStream, TokenizerManager};
+
+use crate::analysis::TokenFilter;
+use crate::error::SchemaError;
+use crate::schema::{FieldKind, IndexSchema};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How values exceeding the token limits of their field are handled.
+pub enum TokenLimitPolicy {
+    #[default]
+    /// Tokens beyond the limits are not indexed and the document is accepted, the
+    /// stored value is kept as-is.
+    Truncate,
+    /// Documents containing values beyond the limits are rejected.
+    Reject,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Caps on the tokens indexed for each value of a `text` or `string` field, which
+/// protect the index from pathological documents, i.e. huge base64 blobs.
+pub struct TokenLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The maximum length of a token in characters, longer tokens are not indexed.
+    pub max_token_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The maximum number of tokens indexed for each value, any later tokens are
+    /// not indexed.
+    pub max_tokens: Option<usize>,
+    #[serde(default)]
+    /// How values exceeding the limits are handled.
+    pub policy: TokenLimitPolicy,
+}
+
+impl TokenLimits {
+    pub(crate) fn validate(
+        &self,
+        name: &str,
+        kind: FieldKind,
+    ) -> Result<(), SchemaError> {
+        if !matches!(kind, FieldKind::Text | FieldKind::String) {
+            return Err(SchemaError::invalid_options(
+                name,
+                format!(
+                    "token limits cannot be set on `{}` fields",
+                    kind.type_name()
+                ),
+            ));
+        }
+
+        if self.max_token_length.is_none() && self.max_tokens.is_none() {
+            return Err(SchemaError::invalid_options(
+                name,
+                "token limits must set at least one of `max_token_length` or `max_tokens`",
+            ));
+        }
+
+        if self.max_token_length == Some(0) || self.max_tokens == Some(0) {
+            return Err(SchemaError::invalid_options(
+                name,
+                "token limits must be greater than 0",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The filters which drop the tokens beyond the limits when a value is indexed.
+    pub fn filters(&self) -> Vec<TokenFilter> {
+        let mut filters = Vec::new();
+        if let Some(max) = self.max_token_length {
+            filters.push(TokenFilter::Length { min: 0, max });
+        }
+        if let Some(max) = self.max_tokens {
+            filters.push(TokenFilter::Limit(max));
+        }
+        filters
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A field of a document which had tokens dropped by its token limits.
+pub struct TruncatedField {
+    /// The name of the field.
+    pub field: String,
+    /// The number of tokens longer than the maximum token length.
+    pub long_tokens: usize,
+    /// The number of tokens beyond the maximum number of tokens of their value.
+    pub excess_tokens: usize,
+}
+
+impl Display for TruncatedField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Field {:?} was truncated, {} tokens exceeded the maximum token length and \
+             {} tokens exceeded the maximum number of tokens",
+            self.field, self.long_tokens, self.excess_tokens,
+        )
+    }
+}
+
+impl IndexSchema {
+    /// Checks the values of the document against the token limits of their fields,
+    /// returning the fields which had tokens dropped so they can be reported
+    /// alongside the ingested document.
+    ///
+    /// Documents exceeding the limits of a field using [TokenLimitPolicy::Reject] are
+    /// rejected. The tokenizers of the schema must already be registered on the given
+    /// manager, see [IndexSchema::register_tokenizers].
+    pub fn check_token_limits(
+        &self,
+        manager: &TokenizerManager,
+        document: &DynamicDocument,
+    ) -> Result<Vec<TruncatedField>, SchemaError> {
+        let mut truncated: Vec<TruncatedField> = Vec::new();
+
+        for (key, value) in document.iter() {
+            let Some(field) = self.fields.get(key.as_ref()) else {
+                continue;
+            };
+            let Some(limits) = field.token_limits.as_ref() else {
+                continue;
+            };
+            let Some(analyzer) = field
+                .analysis_tokenizer()
+                .and_then(|tokenizer| manager.get(&tokenizer))
+            else {
+                continue;
+            };
+
+            let mut long_tokens = 0;
+            let mut excess_tokens = 0;
+            for text in text_values(value) {
+                let mut num_tokens: usize = 0;
+                let mut stream = analyzer.token_stream(text);
+                while stream.advance() {
+                    let length = stream.token().text.chars().count();
+                    if matches!(limits.max_token_length, Some(max) if length > max) {
+                        long_tokens += 1;
+                    } else {
+                        num_tokens += 1;
+                    }
+                }
+
+                let max_tokens = limits.max_tokens.unwrap_or(usize::MAX);
+                excess_tokens += num_tokens.saturating_sub(max_tokens);
+            }
+
+            if long_tokens == 0 && excess_tokens == 0 {
+                continue;
+            }
+
+            if limits.policy == TokenLimitPolicy::Reject {
+                return Err(SchemaError::InvalidValue {
+                    field: key.to_string(),
+                    reason: format!(
+                        "{long_tokens} tokens exceed the maximum token length and \
+                         {excess_tokens} tokens exceed the maximum number of tokens"
+                    ),
+                });
+            }
+
+            match truncated.iter_mut().find(|t| t.field == key.as_ref()) {
+                Some(existing) => {
+                    existing.long_tokens += long_tokens;
+                    existing.excess_tokens += excess_tokens;
+                },
+                None => truncated.push(TruncatedField {
+                    field: key.to_string(),
+                    long_tokens,
+                    excess_tokens,
+                }),
+            }
+        }
+
+        Ok(truncated)
+    }
+}
+
+/// Returns the string values of a value, flattening any nested arrays.
+fn text_values<'a>(value: &'a Value) -> Vec<&'a str> {
+    match value {
+        Value::Str(text) => vec![text.as_ref()],
+        Value::Array(values) => values.iter().flat_map(text_values).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(policy: &str) -> IndexSchema {
+        serde_json::from_str(&format!(
+            r#"{{
+                "fields": {{
+                    "body": {{
+                        "type": "text",
+                        "token_limits": {{
+                            "max_token_length": 5,
+                            "max_tokens": 2,
+                            "policy": "{policy}"
+                        }}
+                    }},
+                    "title": {{"type": "text"}}
+                }}
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_truncate_token_limits() {
+        let schema = schema("truncate");
+        schema.validate().unwrap();
+        let manager = TokenizerManager::default();
+        schema.register_tokenizers(&manager).unwrap();
+
+        let document: DynamicDocument = serde_json::from_str(
+            r#"{"body": ["a b c", "Supercalifragilistic d"], "title": "a b c d"}"#,
+        )
+        .unwrap();
+        let truncated = schema.check_token_limits(&manager, &document).unwrap();
+        assert_eq!(
+            truncated,
+            [TruncatedField {
+                field: "body".to_string(),
+                long_tokens: 1,
+                excess_tokens: 1,
+            }]
+        );
+
+        let tokenizer = schema.field("body").unwrap().indexing_tokenizer().unwrap();
+        let analyzer = manager.get(&tokenizer).unwrap();
+        let mut stream = analyzer.token_stream("one two three toolongtoken");
+        let mut tokens = Vec::new();
+        while stream.advance() {
+            tokens.push(stream.token().text.clone());
+        }
+        assert_eq!(tokens, ["one", "two"]);
+    }
+
+    #[test]
+    fn test_reject_token_limits() {
+        let schema = schema("reject");
+        let manager = TokenizerManager::default();
+        schema.register_tokenizers(&manager).unwrap();
+
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"body": "a b"}"#).unwrap();
+        assert!(schema
+            .check_token_limits(&manager, &document)
+            .unwrap()
+            .is_empty());
+
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"body": "a b c"}"#).unwrap();
+        let err = schema.check_token_limits(&manager, &document).unwrap_err();
+        assert!(
+            matches!(err, SchemaError::InvalidValue { field, .. } if field == "body")
+        );
+    }
+}
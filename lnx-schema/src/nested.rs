@@ -0,0 +1,207 @@
+//! Nested fields index each object of an array as a separate child document.
+//!
+//! The child documents are produced by [lnx_transforms::split_nested_documents] and
+//! carry the hidden [NESTED_PATH_FIELD], [NESTED_CHILD_FIELD] and [NESTED_PARENT_FIELD]
+//! keys, the keys of each object are prefixed by the path of the nested field
+//! i.e. `comments.author`.
+use lnx_document::DynamicDocument;
+pub use lnx_transforms::{NESTED_CHILD_FIELD, NESTED_PARENT_FIELD, NESTED_PATH_FIELD};
+use tantivy::schema::{Field, SchemaBuilder, FAST, INDEXED, STRING};
+
+use crate::error::SchemaError;
+use crate::indexing::{FieldType, IndexingSchema};
+use crate::schema::{validate_field_name, FieldDefinition, IndexSchema};
+
+#[derive(Debug, Copy, Clone)]
+/// The hidden fields of nested child documents.
+pub(crate) struct NestedFields {
+    pub path: Field,
+    pub child: Field,
+    pub parent: Field,
+}
+
+impl IndexSchema {
+    /// The paths of the schema's nested fields, as accepted by
+    /// [lnx_transforms::split_nested_documents].
+    pub fn nested_paths(&self) -> Vec<&str> {
+        self.nested.keys().map(String::as_str).collect()
+    }
+
+    /// Splits the nested fields of the document into their child documents.
+    ///
+    /// The `parent_key` should be the document's primary key so the children are
+    /// deleted along with their parent.
+    pub fn split_nested_documents<'a>(
+        &self,
+        document: DynamicDocument<'a>,
+        parent_key: Option<&str>,
+    ) -> Vec<DynamicDocument<'a>> {
+        lnx_transforms::split_nested_documents(
+            document,
+            &self.nested_paths(),
+            parent_key,
+        )
+    }
+
+    /// Returns if the key is one of the fields of a nested child document.
+    pub(crate) fn is_nested_key(&self, key: &str) -> bool {
+        if [NESTED_PATH_FIELD, NESTED_CHILD_FIELD, NESTED_PARENT_FIELD].contains(&key) {
+            return true;
+        }
+
+        key.split_once('.').map_or(false, |(path, name)| {
+            self.nested
+                .get(path)
+                .map_or(false, |fields| fields.contains_key(name))
+        })
+    }
+
+    /// The fields of every nested child document along with their full path.
+    pub(crate) fn nested_fields(
+        &self,
+    ) -> impl Iterator<Item = (String, &FieldDefinition)> + '_ {
+        self.nested.iter().flat_map(|(path, fields)| {
+            fields
+                .iter()
+                .map(move |(name, field)| (format!("{path}.{name}"), field))
+        })
+    }
+
+    /// Checks the nested paths and the fields of their child documents are valid.
+    pub(crate) fn validate_nested(&self) -> Result<(), SchemaError> {
+        for (path, fields) in self.nested.iter() {
+            validate_field_name(path)?;
+
+            if self.fields.contains_key(path) {
+                return Err(SchemaError::invalid_options(
+                    path,
+                    "a nested path cannot also be a field of the schema",
+                ));
+            }
+
+            if fields.is_empty() {
+                return Err(SchemaError::invalid_options(
+                    path,
+                    "a nested path must define at least one field",
+                ));
+            }
+
+            for name in fields.keys() {
+                validate_field_name(name)?;
+            }
+        }
+
+        for (name, field) in self.nested_fields() {
+            field.validate(&name)?;
+            self.validate_tokenizer(&name, field)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the hidden fields of nested child documents to the schema if the schema
+    /// has any nested fields.
+    ///
+    /// Returns the hidden fields which were added.
+    pub(crate) fn add_nested_fields(
+        &self,
+        builder: &mut SchemaBuilder,
+    ) -> Option<NestedFields> {
+        if self.nested.is_empty() {
+            return None;
+        }
+
+        Some(NestedFields {
+            path: builder.add_text_field(NESTED_PATH_FIELD, STRING),
+            child: builder.add_u64_field(NESTED_CHILD_FIELD, FAST | INDEXED),
+            parent: builder.add_text_field(NESTED_PARENT_FIELD, STRING),
+        })
+    }
+
+    /// Registers the hidden nested fields so child documents are indexed into them.
+    pub(crate) fn register_nested_fields(
+        &self,
+        indexing: &mut IndexingSchema,
+        fields: NestedFields,
+    ) {
+        let NestedFields {
+            path,
+            child,
+            parent,
+        } = fields;
+        indexing.add_field(NESTED_PATH_FIELD, FieldType::RawStr { field_id: path });
+        indexing.add_field(NESTED_CHILD_FIELD, FieldType::U64 { field_id: child });
+        indexing.add_field(NESTED_PARENT_FIELD, FieldType::RawStr { field_id: parent });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::FieldType as TantivyFieldType;
+
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_build_nested_fields() {
+        let schema = parse(
+            r#"{
+                "fields": {"title": {"type": "text"}},
+                "nested": {
+                    "comments": {
+                        "author": {"type": "string"},
+                        "stars": {"type": "u64", "fast": true}
+                    }
+                }
+            }"#,
+        );
+        assert_eq!(schema.nested_paths(), ["comments"]);
+        assert!(schema.is_nested_key("comments.author"));
+        assert!(schema.is_nested_key(NESTED_CHILD_FIELD));
+        assert!(schema.is_nested_key(NESTED_PARENT_FIELD));
+        assert!(!schema.is_nested_key("comments.unknown"));
+        assert!(!schema.is_nested_key("title"));
+
+        let (tantivy_schema, _) = schema.build().unwrap();
+        let stars = tantivy_schema.get_field("comments.stars").unwrap();
+        assert!(tantivy_schema.get_field_entry(stars).is_fast());
+
+        let child = tantivy_schema.get_field(NESTED_CHILD_FIELD).unwrap();
+        let entry = tantivy_schema.get_field_entry(child);
+        assert!(entry.is_fast() && entry.is_indexed());
+
+        for name in [NESTED_PATH_FIELD, NESTED_PARENT_FIELD] {
+            let field = tantivy_schema.get_field(name).unwrap();
+            assert!(matches!(
+                tantivy_schema.get_field_entry(field).field_type(),
+                TantivyFieldType::Str(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_build_without_nested_fields() {
+        let schema = parse(r#"{"fields": {"title": {"type": "text"}}}"#);
+        let (tantivy_schema, _) = schema.build().unwrap();
+        assert!(tantivy_schema.get_field(NESTED_CHILD_FIELD).is_err());
+        assert!(tantivy_schema.get_field(NESTED_PATH_FIELD).is_err());
+        assert!(tantivy_schema.get_field(NESTED_PARENT_FIELD).is_err());
+    }
+
+    #[test]
+    fn test_invalid_nested_fields() {
+        let cases = [
+            r#"{"fields": {"comments": {"type": "text"}}, "nested": {"comments": {"a": {"type": "text"}}}}"#,
+            r#"{"fields": {"title": {"type": "text"}}, "nested": {"comments": {}}}"#,
+            r#"{"fields": {"title": {"type": "text"}}, "nested": {"_comments": {"a": {"type": "text"}}}}"#,
+            r#"{"fields": {"title": {"type": "text"}}, "nested": {"comments": {"a.b": {"type": "text"}}}}"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be invalid");
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::defaults::json_to_value;
+use crate::error::SchemaError;
+use crate::schema::{FieldDefinition, FieldKind, IndexSchema};
+
+/// The token `null` values are indexed as by fields using [NullHandling::Index].
+pub const NULL_TOKEN: &str = "__null__";
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How `null` values of a field are handled when a document is indexed.
+pub enum NullHandling {
+    #[default]
+    /// `null` values are not indexed, a field with only `null` values is treated
+    /// as missing.
+    Skip,
+    /// `null` values are indexed as the [NULL_TOKEN], so the field exists on the
+    /// document and can be matched by a `term` query with a `null` value.
+    ///
+    /// Only `text` and `string` fields can index `null` values.
+    Index,
+    /// `null` values are replaced with the default value of the field.
+    Default,
+}
+
+impl FieldDefinition {
+    /// Checks the `null_handling` option of the field.
+    pub(crate) fn validate_null_handling(&self, name: &str) -> Result<(), SchemaError> {
+        match self.null_handling {
+            NullHandling::Skip => Ok(()),
+            NullHandling::Index => {
+                if matches!(self.kind, FieldKind::Text | FieldKind::String) {
+                    Ok(())
+                } else {
+                    Err(SchemaError::invalid_options(
+                        name,
+                        "only `text` and `string` fields can index null values",
+                    ))
+                }
+            },
+            NullHandling::Default => {
+                if self.default.is_some() {
+                    Ok(())
+                } else {
+                    Err(SchemaError::invalid_options(
+                        name,
+                        "replacing null values requires a default value",
+                    ))
+                }
+            },
+        }
+    }
+}
+
+impl IndexSchema {
+    /// Applies the null handling of each field to the `null` values of the document.
+    ///
+    /// This should be applied before [IndexSchema::apply_defaults] so that indexed
+    /// and replaced `null` values are not treated as missing.
+    pub fn apply_null_handling<'a>(&self, document: &mut DynamicDocument<'a>) {
+        document.retain_mut(|(key, value)| {
+            let Some(field) = self.fields.get(key.as_ref()) else {
+                return true;
+            };
+
+            replace_nulls(field, value);
+            !matches!(value, Value::Null)
+        });
+    }
+
+    /// Returns the names of the fields which index `null` values as the [NULL_TOKEN].
+    pub fn indexed_null_fields(&self) -> impl Iterator<Item = &str> {
+        self.fields
+            .iter()
+            .filter(|(_, field)| field.null_handling == NullHandling::Index)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+fn replace_nulls(field: &FieldDefinition, value: &mut Value) {
+    match value {
+        Value::Null => match field.null_handling {
+            NullHandling::Skip => {},
+            NullHandling::Index => *value = Value::Str(Cow::Borrowed(NULL_TOKEN)),
+            NullHandling::Default => {
+                if let Some(default) = field.default.as_ref() {
+                    *value = json_to_value(default);
+                }
+            },
+        },
+        Value::Array(values) => {
+            for value in values.iter_mut() {
+                replace_nulls(field, value);
+            }
+            values.retain(|value| !matches!(value, Value::Null));
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_apply_null_handling() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "author": {"type": "string", "null_handling": "index"},
+                    "status": {
+                        "type": "string",
+                        "null_handling": "default",
+                        "default": "active"
+                    },
+                    "tags": {"type": "string"}
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{"author": null, "status": [null, "draft"], "tags": null, "other": null}"#,
+        )
+        .unwrap();
+        schema.apply_null_handling(&mut document);
+
+        let keys = document
+            .iter()
+            .map(|(key, _)| key.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["author", "status", "other"]);
+        assert_eq!(document[0].1, Value::from(NULL_TOKEN));
+        assert_eq!(
+            document[1].1,
+            Value::Array(vec![Value::from("active"), Value::from("draft")])
+        );
+        assert_eq!(schema.indexed_null_fields().collect::<Vec<_>>(), ["author"]);
+    }
+
+    #[test]
+    fn test_invalid_null_handling() {
+        let cases = [
+            r#"{"fields": {"a": {"type": "u64", "null_handling": "index"}}}"#,
+            r#"{"fields": {"a": {"type": "string", "null_handling": "default"}}}"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, Value};
+use tantivy::schema::{
+    Field,
+    FieldType as TantivyFieldType,
+    Schema,
+    SchemaBuilder,
+    INDEXED,
+};
+
+use crate::indexing::{field_presence_key, FieldType, IndexingSchema};
+
+/// The hidden field tracking which fields have a non-null value on each document.
+///
+/// Each document indexes the [field_presence_key] of every field it contains, which
+/// is what `exists` and `missing` queries match against.
+pub const FIELD_PRESENCE_FIELD: &str = "_lnx_field_presence";
+
+/// Adds the hidden field presence field to the schema.
+pub fn add_field_presence_field(builder: &mut SchemaBuilder) -> Field {
+    builder.add_u64_field(FIELD_PRESENCE_FIELD, INDEXED)
+}
+
+/// Registers the field presence field so the reserved key is indexed into it.
+pub fn register_field_presence_field(indexing: &mut IndexingSchema, field_id: Field) {
+    indexing.add_field(FIELD_PRESENCE_FIELD, FieldType::U64 { field_id });
+}
+
+/// Records which fields of the document have a non-null value under the
+/// reserved [FIELD_PRESENCE_FIELD] key.
+///
+/// Values nested within a JSON object field also mark each of their parent keys
+/// as present, i.e. a value at `meta.author.name` marks `meta`, `meta.author`
+/// and `meta.author.name` as present. Any presence values provided by the
+/// document itself are discarded.
+///
+/// This should be applied after all other transformations of the document,
+/// nothing is recorded if the schema has no field presence field.
+pub fn apply_field_presence(schema: &Schema, document: &mut DynamicDocument) {
+    document.retain(|(key, _)| key != FIELD_PRESENCE_FIELD);
+
+    if schema.get_field(FIELD_PRESENCE_FIELD).is_err() {
+        return;
+    }
+
+    let mut keys = Vec::new();
+    for (key, value) in document.iter() {
+        let Ok(field) = schema.get_field(key) else {
+            continue;
+        };
+
+        let entry = schema.get_field_entry(field);
+        let is_dynamic = matches!(entry.field_type(), TantivyFieldType::JsonObject(_));
+        collect_presence_keys(field, None, is_dynamic, value, &mut keys);
+    }
+
+    if keys.is_empty() {
+        return;
+    }
+
+    keys.sort_unstable();
+    keys.dedup();
+
+    let keys = keys.into_iter().map(Value::U64).collect();
+    document.push((Cow::Borrowed(FIELD_PRESENCE_FIELD), Value::Array(keys)));
+}
+
+/// Collects the presence keys of the value, returning if the value is present.
+///
+/// Arrays are present if any of their elements are and objects of dynamic fields
+/// are present if any of their keys are.
+fn collect_presence_keys(
+    field: Field,
+    path: Option<&str>,
+    is_dynamic: bool,
+    value: &Value,
+    keys: &mut Vec<u64>,
+) -> bool {
+    let is_present = match value {
+        Value::Null => false,
+        Value::Array(values) => values.iter().fold(false, |is_present, value| {
+            collect_presence_keys(field, path, is_dynamic, value, keys) | is_present
+        }),
+        Value::Object(entries) if is_dynamic => {
+            let mut is_present = false;
+            for (key, value) in entries {
+                let path = match path {
+                    None => key.to_string(),
+                    Some(path) => format!("{path}.{key}"),
+                };
+                is_present |=
+                    collect_presence_keys(field, Some(&path), true, value, keys);
+            }
+            is_present
+        },
+        _ => true,
+    };
+
+    if is_present {
+        keys.push(field_presence_key(field, path));
+    }
+
+    is_present
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{STORED, STRING, TEXT};
+
+    use super::*;
+
+    fn presence_keys(document: &DynamicDocument) -> Vec<u64> {
+        let (_, value) = document
+            .iter()
+            .find(|(key, _)| key == FIELD_PRESENCE_FIELD)
+            .expect("Document should have presence keys");
+
+        match value {
+            Value::Array(values) => values
+                .iter()
+                .map(|value| match value {
+                    Value::U64(key) => *key,
+                    other => panic!("Unexpected value {other:?}"),
+                })
+                .collect(),
+            other => panic!("Unexpected value {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_apply_field_presence() {
+        let mut builder = SchemaBuilder::new();
+        let title = builder.add_text_field("title", TEXT);
+        builder.add_text_field("subtitle", TEXT);
+        builder.add_text_field("tags", STRING);
+        let meta = builder.add_json_field("meta", STORED);
+        add_field_presence_field(&mut builder);
+        let schema = builder.build();
+
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{
+                "title": "Hello, world!",
+                "subtitle": null,
+                "tags": [null],
+                "meta": {"author": {"name": "bob"}, "editor": null},
+                "unknown": "value",
+                "_lnx_field_presence": 1
+            }"#,
+        )
+        .unwrap();
+        apply_field_presence(&schema, &mut document);
+
+        let mut expected = vec![
+            field_presence_key(title, None),
+            field_presence_key(meta, None),
+            field_presence_key(meta, Some("author")),
+            field_presence_key(meta, Some("author.name")),
+        ];
+        expected.sort_unstable();
+        assert_eq!(presence_keys(&document), expected);
+        assert_eq!(
+            document
+                .iter()
+                .filter(|(key, _)| key == FIELD_PRESENCE_FIELD)
+                .count(),
+            1,
+        );
+    }
+}
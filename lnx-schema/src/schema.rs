@@ -0,0 +1,1147 @@
+use std::collections::BTreeMap;
+
+use lnx_document::{DynamicDocument, FieldType as DocumentFieldType};
+use lnx_transforms::{DateTimeOutputFormat, DateTimeParser};
+use serde::{Deserialize, Serialize};
+use tantivy::schema::{
+    BytesOptions,
+    DateOptions,
+    FacetOptions,
+    Field,
+    IndexRecordOption,
+    IpAddrOptions,
+    JsonObjectOptions,
+    NumericOptions,
+    Schema,
+    SchemaBuilder,
+    TextFieldIndexing,
+    TextOptions,
+};
+use tantivy::tokenizer::{TextAnalyzer, TokenizerManager};
+
+use crate::analysis::{AnalysisSettings, Analyzer, KeywordNormalizer, TokenFilter};
+use crate::coercion::CoercionMode;
+use crate::document_boost::DOCUMENT_BOOST_FIELD;
+use crate::dynamic::{DynamicMapping, DynamicMode};
+use crate::error::SchemaError;
+use crate::indexing::{FieldType, IndexingSchema};
+use crate::limits::TokenLimits;
+use crate::null_handling::NullHandling;
+use crate::presence::{add_field_presence_field, register_field_presence_field};
+use crate::similarity::Bm25Params;
+use crate::tokenizer::{
+    ascii_folding_tokenizer_name,
+    normalized_tokenizer_name,
+    position_gap_tokenizer_name,
+    token_limits_tokenizer_name,
+    PositionGapTokenizer,
+    DEFAULT_POSITION_GAP,
+};
+
+/// The formats accepted by `datetime` fields which do not specify their own formats.
+pub const DEFAULT_DATETIME_FORMATS: &[&str] = &["rfc3339", "unix_micros"];
+/// The format `datetime` values are rendered in if the field does not specify a format.
+pub const DEFAULT_DATETIME_OUTPUT_FORMAT: &str = "rfc3339";
+
+/// The tokenizers which are registered on every index by tantivy.
+///
+/// The analyzers of [crate::analysis] are also available on every index once
+/// registered via [IndexSchema::register_tokenizers].
+pub const BUILTIN_TOKENIZERS: &[&str] = &["default", "raw", "en_stem", "whitespace"];
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The user provided definition of an index's schema.
+///
+/// This is the schema accepted when creating an index, each field describes how
+/// it should be configured within tantivy.
+pub struct IndexSchema {
+    #[serde(default)]
+    /// The fields of the index keyed by their name.
+    pub fields: BTreeMap<String, FieldDefinition>,
+    #[serde(default)]
+    /// How document keys which are not part of the schema are handled.
+    pub dynamic: DynamicMapping,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// Alternative names which resolve to a field at query time, keyed by the alias.
+    pub aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    /// If ingested values are coerced to the type of their field, i.e. `"42"` to a
+    /// `u64`, or rejected.
+    pub coercion: CoercionMode,
+    #[serde(default, skip_serializing_if = "AnalysisSettings::is_default")]
+    /// The text analysis settings of the index, i.e. its stop words.
+    pub analysis: AnalysisSettings,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// If documents may set a reserved `_boost` value which is multiplied into
+    /// their score at query time, see [crate::document_boost].
+    pub document_boost: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    /// The fields of nested child documents keyed by the path of the nested field,
+    /// each object of the field is indexed as a separate document, see [crate::nested].
+    pub nested: BTreeMap<String, BTreeMap<String, FieldDefinition>>,
+}
+
+impl IndexSchema {
+    #[inline]
+    /// Returns the definition of the given field if it exists.
+    pub fn field(&self, name: &str) -> Option<&FieldDefinition> {
+        self.fields.get(name)
+    }
+
+    /// Checks that every field within the schema is valid.
+    pub fn validate(&self) -> Result<(), SchemaError> {
+        if self.fields.is_empty() && self.dynamic.mode != DynamicMode::Map {
+            return Err(SchemaError::Empty);
+        }
+
+        for (name, field) in self.fields.iter() {
+            validate_field_name(name)?;
+            field.validate(name)?;
+            self.validate_tokenizer(name, field)?;
+        }
+
+        for template in self.dynamic.templates.iter() {
+            let name = format!("dynamic template {:?}", template.pattern);
+            self.validate_tokenizer(&name, &template.mapping)?;
+        }
+
+        self.validate_nested()?;
+        self.validate_copy_to()?;
+        self.validate_aliases()?;
+        self.analysis.validate()?;
+        self.dynamic.validate()
+    }
+
+    /// Checks the tokenizer of the field is either built-in or one of the index's
+    /// custom analyzers.
+    pub(crate) fn validate_tokenizer(
+        &self,
+        name: &str,
+        field: &FieldDefinition,
+    ) -> Result<(), SchemaError> {
+        let Some(tokenizer) = field.tokenizer.as_deref() else {
+            return Ok(());
+        };
+
+        if BUILTIN_TOKENIZERS.contains(&tokenizer)
+            || self.analysis.has_tokenizer(tokenizer)
+        {
+            return Ok(());
+        }
+
+        Err(SchemaError::invalid_options(
+            name,
+            format!("unknown tokenizer {tokenizer:?}"),
+        ))
+    }
+
+    /// Registers the tokenizers required by the schema's fields which are not
+    /// built into tantivy, i.e. the analyzers of the index's analysis settings and
+    /// fields with keyword normalizers, ASCII folding, token limits or a custom
+    /// position gap.
+    ///
+    /// This must be called on the index's tokenizer manager before documents
+    /// are indexed or queries are parsed, and on its fast field tokenizer manager
+    /// if any fast `string` fields have normalizers.
+    pub fn register_tokenizers(
+        &self,
+        manager: &TokenizerManager,
+    ) -> Result<(), SchemaError> {
+        self.analysis.register_tokenizers(manager)?;
+
+        let nested_fields = self.nested.values().flat_map(BTreeMap::values);
+        for field in self.fields.values().chain(nested_fields) {
+            let (Some(tokenizer), Some(indexing_tokenizer)) =
+                (field.tokenizer(), field.indexing_tokenizer())
+            else {
+                continue;
+            };
+
+            let mut tokenizer = tokenizer.to_string();
+            if !field.normalizers.is_empty() {
+                let normalized =
+                    normalized_tokenizer_name(&tokenizer, &field.normalizers);
+                if let Some(analyzer) = manager.get(&tokenizer) {
+                    let analyzer = field
+                        .normalizers
+                        .iter()
+                        .fold(Analyzer::new(analyzer), |analyzer, normalizer| {
+                            analyzer.with_filter(normalizer.filter())
+                        });
+                    manager.register(&normalized, TextAnalyzer::from(analyzer));
+                }
+                tokenizer = normalized;
+            }
+
+            if field.ascii_folding {
+                let folded = ascii_folding_tokenizer_name(&tokenizer);
+                if let Some(analyzer) = manager.get(&tokenizer) {
+                    let analyzer =
+                        Analyzer::new(analyzer).with_filter(TokenFilter::AsciiFolding);
+                    manager.register(&folded, TextAnalyzer::from(analyzer));
+                }
+                tokenizer = folded;
+            }
+
+            if let Some(limits) = field.token_limits.as_ref() {
+                let limited = token_limits_tokenizer_name(&tokenizer, limits);
+                if let Some(analyzer) = manager.get(&tokenizer) {
+                    let analyzer = limits
+                        .filters()
+                        .into_iter()
+                        .fold(Analyzer::new(analyzer), Analyzer::with_filter);
+                    manager.register(&limited, TextAnalyzer::from(analyzer));
+                }
+                tokenizer = limited;
+            }
+
+            if tokenizer == indexing_tokenizer {
+                continue;
+            }
+
+            if let Some(analyzer) = manager.get(&tokenizer) {
+                let position_gap = field.position_gap.unwrap_or(DEFAULT_POSITION_GAP);
+                let tokenizer = PositionGapTokenizer::new(analyzer, position_gap);
+                manager.register(&indexing_tokenizer, TextAnalyzer::from(tokenizer));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the keys of the document which are not part of the schema and resolves
+    /// the fields they should be mapped to using the dynamic mapping.
+    ///
+    /// In `strict` mode the document is rejected if it contains any unknown keys,
+    /// in `ignore` mode no fields are ever returned. Keys which cannot be used as a
+    /// field name, or whose values are all `null`, are left unmapped.
+    pub fn discover_fields(
+        &self,
+        document: &DynamicDocument,
+    ) -> Result<Vec<(String, FieldDefinition)>, SchemaError> {
+        let mut discovered: Vec<(String, FieldDefinition)> = Vec::new();
+
+        for (key, value) in document.iter() {
+            if self.fields.contains_key(key.as_ref())
+                || (self.document_boost && key.as_ref() == DOCUMENT_BOOST_FIELD)
+                || self.is_nested_key(key)
+                || discovered.iter().any(|(name, _)| name == key)
+            {
+                continue;
+            }
+
+            match self.dynamic.mode {
+                DynamicMode::Ignore => return Ok(Vec::new()),
+                DynamicMode::Strict => {
+                    return Err(SchemaError::UnknownField(key.to_string()))
+                },
+                DynamicMode::Map => {},
+            }
+
+            if validate_field_name(key).is_err() {
+                continue;
+            }
+
+            if let Some(field) = self.dynamic.resolve(key, value) {
+                discovered.push((key.to_string(), field));
+            }
+        }
+
+        Ok(discovered)
+    }
+
+    /// Validates the schema and produces the tantivy schema along with the
+    /// [IndexingSchema] used to map document keys to their fields.
+    pub fn build(&self) -> Result<(Schema, IndexingSchema), SchemaError> {
+        self.validate()?;
+
+        let mut builder = SchemaBuilder::new();
+        let mut indexing = IndexingSchema::default();
+
+        for (name, field) in self.fields.iter() {
+            let field_id = field.add_to_schema(name, &mut builder);
+            indexing.add_field(name, field.indexing_type(field_id));
+        }
+
+        for (name, field) in self.nested_fields() {
+            let field_id = field.add_to_schema(&name, &mut builder);
+            indexing.add_field(&name, field.indexing_type(field_id));
+        }
+
+        if let Some(fields) = self.add_nested_fields(&mut builder) {
+            self.register_nested_fields(&mut indexing, fields);
+        }
+
+        if let Some(field_id) = self.add_document_boost_field(&mut builder) {
+            self.register_document_boost_field(&mut indexing, field_id);
+        }
+
+        let field_id = add_field_presence_field(&mut builder);
+        register_field_presence_field(&mut indexing, field_id);
+
+        Ok((builder.build(), indexing))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// The type of a field within the schema.
+pub enum FieldKind {
+    /// A tokenized text field.
+    Text,
+    #[serde(alias = "keyword")]
+    /// A string field which is indexed as a single token.
+    String,
+    /// A u64 integer field.
+    U64,
+    /// A i64 integer field.
+    I64,
+    /// A f64 float field.
+    F64,
+    /// A boolean field.
+    Bool,
+    /// A raw bytes field.
+    Bytes,
+    /// An IPv4 or IPv6 address field.
+    Ip,
+    /// A hierarchical facet field.
+    Facet,
+    /// A datetime field.
+    Datetime,
+    /// A dynamic object field which indexes any nested keys.
+    Dynamic,
+    /// An object field which indexes every nested key and value as a single
+    /// `key=value` keyword, see [crate::flattened].
+    Flattened,
+}
+
+impl FieldKind {
+    /// The name of the type as it appears within the schema.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            FieldKind::Text => "text",
+            FieldKind::String => "string",
+            FieldKind::U64 => "u64",
+            FieldKind::I64 => "i64",
+            FieldKind::F64 => "f64",
+            FieldKind::Bool => "bool",
+            FieldKind::Bytes => "bytes",
+            FieldKind::Ip => "ip",
+            FieldKind::Facet => "facet",
+            FieldKind::Datetime => "datetime",
+            FieldKind::Dynamic => "dynamic",
+            FieldKind::Flattened => "flattened",
+        }
+    }
+
+    /// Returns if a document value of the given type can be indexed in this field.
+    ///
+    /// Values which can be cast to the type of the field are accepted, i.e. a string
+    /// can be parsed as a `datetime` or `ip`, `null` values are accepted by every type.
+    /// Arrays should be checked element by element.
+    pub fn accepts(&self, value_type: DocumentFieldType) -> bool {
+        use DocumentFieldType as Doc;
+
+        match (self, value_type) {
+            (_, Doc::Null) => true,
+            (FieldKind::Text | FieldKind::String, Doc::String) => true,
+            (FieldKind::U64, Doc::U64) => true,
+            (FieldKind::I64, Doc::I64 | Doc::U64) => true,
+            (FieldKind::F64, Doc::F64 | Doc::I64 | Doc::U64) => true,
+            (FieldKind::Bool, Doc::Bool) => true,
+            (FieldKind::Bytes, Doc::Bytes | Doc::String) => true,
+            (FieldKind::Ip, Doc::IpAddr | Doc::String) => true,
+            (FieldKind::Facet, Doc::Facet | Doc::String) => true,
+            (FieldKind::Datetime, Doc::DateTime | Doc::String | Doc::I64 | Doc::U64) => {
+                true
+            },
+            (FieldKind::Dynamic | FieldKind::Flattened, Doc::Object) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The definition of a single field within the schema.
+pub struct FieldDefinition {
+    #[serde(rename = "type")]
+    /// The type of the field.
+    pub kind: FieldKind,
+    #[serde(default = "default_true")]
+    /// If the field's values should be stored and returned with the document.
+    pub stored: bool,
+    #[serde(default = "default_true")]
+    /// If the field should be indexed so it can be searched.
+    pub indexed: bool,
+    #[serde(default)]
+    /// If the field should be a fast field, used for sorting and aggregations.
+    pub fast: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The tokenizer used for `text` and `dynamic` fields.
+    ///
+    /// Defaults to the `default` tokenizer.
+    pub tokenizer: Option<String>,
+    #[serde(default = "default_true")]
+    /// If the field accepts multiple values, i.e. an array of values.
+    ///
+    /// Documents containing more than one value for a single-valued field are rejected.
+    pub multi_value: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The number of positions left between the values of a multi-valued `text` field,
+    /// a large gap prevents phrase queries from matching across distinct values.
+    ///
+    /// Defaults to tantivy's gap of `1` position.
+    pub position_gap: Option<u32>,
+    #[serde(default)]
+    /// If characters with diacritics are folded into their ASCII equivalents when
+    /// the `text`, `string` or `dynamic` field is indexed and queried, i.e. so `café`
+    /// matches `cafe`.
+    pub ascii_folding: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The normalizers applied in order to the values of a `string` field, which are
+    /// still indexed as a single keyword, i.e. `lowercase` for case-insensitive
+    /// exact matches.
+    pub normalizers: Vec<KeywordNormalizer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// Caps on the length and number of tokens indexed for each value of a `text` or
+    /// `string` field.
+    pub token_limits: Option<TokenLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The BM25 parameters used to score matches on a `text`, `string` or `dynamic`
+    /// field instead of the defaults, see [Bm25Params].
+    pub bm25: Option<Bm25Params>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The default boost applied to free-text matches on a `text`, `string` or
+    /// `dynamic` field when a query is expanded across several fields, i.e. `3.0`
+    /// for a title field.
+    pub boost: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The `text` or `string` fields the values of this field are copied into when
+    /// a document is indexed, i.e. a combined catch-all field for free-text search.
+    pub copy_to: Vec<String>,
+    #[serde(default)]
+    /// If documents must contain a non-null value for the field.
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The value used when a document does not contain a value for the field.
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    /// How `null` values of the field are handled when a document is indexed.
+    pub null_handling: NullHandling,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    /// The formats accepted by a `datetime` field.
+    ///
+    /// Defaults to [DEFAULT_DATETIME_FORMATS].
+    pub formats: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The format `datetime` values are rendered in when returned.
+    ///
+    /// Defaults to [DEFAULT_DATETIME_OUTPUT_FORMAT].
+    pub output_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The encoding `bytes` values are provided and returned in, `base64` or `hex`.
+    ///
+    /// Defaults to `base64`.
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The delimiter string values of a `facet` field are split on to derive the
+    /// facet's path, i.e. `>` turns `clothing>men>shirts` into `/clothing/men/shirts`.
+    pub path_delimiter: Option<String>,
+}
+
+impl FieldDefinition {
+    /// Creates a new field definition with the default options for the type.
+    pub fn new(kind: FieldKind) -> Self {
+        Self {
+            kind,
+            stored: true,
+            indexed: true,
+            fast: false,
+            tokenizer: None,
+            multi_value: true,
+            position_gap: None,
+            ascii_folding: false,
+            normalizers: Vec::new(),
+            token_limits: None,
+            bm25: None,
+            boost: None,
+            copy_to: Vec::new(),
+            required: false,
+            default: None,
+            null_handling: NullHandling::default(),
+            formats: Vec::new(),
+            output_format: None,
+            encoding: None,
+            path_delimiter: None,
+        }
+    }
+
+    /// The tokenizer used by the field, if the field is tokenized.
+    pub fn tokenizer(&self) -> Option<&str> {
+        match self.kind {
+            FieldKind::Text | FieldKind::Dynamic => {
+                Some(self.tokenizer.as_deref().unwrap_or("default"))
+            },
+            FieldKind::String | FieldKind::Flattened => Some("raw"),
+            _ => None,
+        }
+    }
+
+    /// Checks that the options of the field are supported by its type.
+    pub fn validate(&self, name: &str) -> Result<(), SchemaError> {
+        let kind = self.kind.type_name();
+
+        if !self.stored && !self.indexed && !self.fast {
+            return Err(SchemaError::invalid_options(
+                name,
+                "the field must be at least one of stored, indexed or fast",
+            ));
+        }
+
+        self.validate_default(name)?;
+        self.validate_null_handling(name)?;
+        self.validate_datetime_formats(name)?;
+        self.validate_bytes_encoding(name)?;
+        self.validate_path_delimiter(name)?;
+        self.validate_bm25(name)?;
+        self.validate_boost(name)?;
+
+        if self.tokenizer.is_some() {
+            if !matches!(self.kind, FieldKind::Text | FieldKind::Dynamic) {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    format!("a tokenizer cannot be set on `{kind}` fields"),
+                ));
+            }
+        }
+
+        if !self.normalizers.is_empty() && self.kind != FieldKind::String {
+            return Err(SchemaError::invalid_options(
+                name,
+                format!("normalizers cannot be set on `{kind}` fields"),
+            ));
+        }
+
+        if self.ascii_folding
+            && !matches!(
+                self.kind,
+                FieldKind::Text | FieldKind::String | FieldKind::Dynamic
+            )
+        {
+            return Err(SchemaError::invalid_options(
+                name,
+                format!("ASCII folding cannot be enabled on `{kind}` fields"),
+            ));
+        }
+
+        if let Some(limits) = self.token_limits.as_ref() {
+            limits.validate(name, self.kind)?;
+        }
+
+        if let Some(position_gap) = self.position_gap {
+            if !matches!(self.kind, FieldKind::Text | FieldKind::Dynamic) {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    format!("a position gap cannot be set on `{kind}` fields"),
+                ));
+            }
+
+            if !self.multi_value {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    "a position gap cannot be set on single-valued fields",
+                ));
+            }
+
+            if position_gap < DEFAULT_POSITION_GAP {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    format!("the position gap must be at least {DEFAULT_POSITION_GAP}"),
+                ));
+            }
+        }
+
+        match self.kind {
+            FieldKind::Text if self.fast => Err(SchemaError::invalid_options(
+                name,
+                "`text` fields cannot be fast fields, use a `string` field instead",
+            )),
+            FieldKind::Dynamic if self.fast => Err(SchemaError::invalid_options(
+                name,
+                "`dynamic` fields cannot be fast fields",
+            )),
+            FieldKind::Facet if self.fast || !self.indexed => {
+                Err(SchemaError::invalid_options(
+                    name,
+                    "`facet` fields are always indexed and cannot be fast fields",
+                ))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns how documents should be mapped to the given tantivy field.
+    pub fn indexing_type(&self, field_id: Field) -> FieldType {
+        match self.kind {
+            FieldKind::Text => FieldType::Text { field_id },
+            FieldKind::String | FieldKind::Flattened => FieldType::RawStr { field_id },
+            FieldKind::U64 => FieldType::U64 { field_id },
+            FieldKind::I64 => FieldType::I64 { field_id },
+            FieldKind::F64 => FieldType::F64 { field_id },
+            FieldKind::Bool => FieldType::Bool { field_id },
+            FieldKind::Bytes => FieldType::Bytes { field_id },
+            FieldKind::Ip => FieldType::Ip { field_id },
+            FieldKind::Facet => FieldType::Facet { field_id },
+            FieldKind::Datetime => FieldType::Datetime { field_id },
+            FieldKind::Dynamic => FieldType::DynamicObject { field_id },
+        }
+    }
+
+    /// Adds the field to the tantivy schema.
+    pub(crate) fn add_to_schema(
+        &self,
+        name: &str,
+        builder: &mut SchemaBuilder,
+    ) -> Field {
+        match self.kind {
+            FieldKind::Text | FieldKind::String | FieldKind::Flattened => {
+                builder.add_text_field(name, self.text_options())
+            },
+            FieldKind::U64 => builder.add_u64_field(name, self.numeric_options()),
+            FieldKind::I64 => builder.add_i64_field(name, self.numeric_options()),
+            FieldKind::F64 => builder.add_f64_field(name, self.numeric_options()),
+            FieldKind::Bool => builder.add_bool_field(name, self.numeric_options()),
+            FieldKind::Bytes => {
+                let mut options = BytesOptions::default();
+                if self.stored {
+                    options = options.set_stored();
+                }
+                if self.indexed {
+                    options = options.set_indexed();
+                }
+                if self.fast {
+                    options = options.set_fast();
+                }
+                builder.add_bytes_field(name, options)
+            },
+            FieldKind::Ip => {
+                let mut options = IpAddrOptions::default();
+                if self.stored {
+                    options = options.set_stored();
+                }
+                if self.indexed {
+                    options = options.set_indexed();
+                }
+                if self.fast {
+                    options = options.set_fast();
+                }
+                builder.add_ip_addr_field(name, options)
+            },
+            FieldKind::Facet => {
+                let mut options = FacetOptions::default();
+                if self.stored {
+                    options = options.set_stored();
+                }
+                builder.add_facet_field(name, options)
+            },
+            FieldKind::Datetime => {
+                let mut options = DateOptions::default();
+                if self.stored {
+                    options = options.set_stored();
+                }
+                if self.indexed {
+                    options = options.set_indexed();
+                }
+                if self.fast {
+                    options = options.set_fast();
+                }
+                builder.add_date_field(name, options)
+            },
+            FieldKind::Dynamic => {
+                let mut options = JsonObjectOptions::default();
+                if self.stored {
+                    options = options.set_stored();
+                }
+                if let Some(indexing) = self.text_indexing() {
+                    options = options.set_indexing_options(indexing);
+                }
+                builder.add_json_field(name, options)
+            },
+        }
+    }
+
+    /// The parser used to cast values of a `datetime` field.
+    ///
+    /// Returns `None` if the field is not a `datetime` field or the formats are invalid.
+    pub fn datetime_parser(&self) -> Option<DateTimeParser> {
+        if self.kind != FieldKind::Datetime {
+            return None;
+        }
+
+        if self.formats.is_empty() {
+            DateTimeParser::from_formats(DEFAULT_DATETIME_FORMATS).ok()
+        } else {
+            DateTimeParser::from_formats(&self.formats).ok()
+        }
+    }
+
+    /// The format values of a `datetime` field are rendered in.
+    ///
+    /// Returns `None` if the field is not a `datetime` field or the format is invalid.
+    pub fn datetime_output_format(&self) -> Option<DateTimeOutputFormat> {
+        if self.kind != FieldKind::Datetime {
+            return None;
+        }
+
+        let format = self
+            .output_format
+            .as_deref()
+            .unwrap_or(DEFAULT_DATETIME_OUTPUT_FORMAT);
+        DateTimeOutputFormat::from_name(format).ok()
+    }
+
+    fn validate_datetime_formats(&self, name: &str) -> Result<(), SchemaError> {
+        let has_formats = !self.formats.is_empty() || self.output_format.is_some();
+        if self.kind != FieldKind::Datetime {
+            if has_formats {
+                return Err(SchemaError::invalid_options(
+                    name,
+                    "formats can only be set on `datetime` fields",
+                ));
+            }
+            return Ok(());
+        }
+
+        if !self.formats.is_empty() {
+            DateTimeParser::from_formats(&self.formats)
+                .map_err(|e| SchemaError::invalid_options(name, e.to_string()))?;
+        }
+
+        if let Some(format) = self.output_format.as_deref() {
+            DateTimeOutputFormat::from_name(format)
+                .map_err(|e| SchemaError::invalid_options(name, e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// The name of the tokenizer registered for the field within tantivy.
+    ///
+    /// This differs from the field's tokenizer when normalizers, ASCII folding, token
+    /// limits or a custom position gap are set, see [IndexSchema::register_tokenizers].
+    pub fn indexing_tokenizer(&self) -> Option<String> {
+        let mut tokenizer = self.analysis_tokenizer()?;
+
+        if let Some(limits) = self.token_limits.as_ref() {
+            tokenizer = token_limits_tokenizer_name(&tokenizer, limits);
+        }
+
+        match self.position_gap {
+            Some(position_gap) if position_gap != DEFAULT_POSITION_GAP => {
+                Some(position_gap_tokenizer_name(&tokenizer, position_gap))
+            },
+            _ => Some(tokenizer),
+        }
+    }
+
+    /// The name of the tokenizer producing the tokens of the field before any token
+    /// limits or position gap are applied.
+    pub(crate) fn analysis_tokenizer(&self) -> Option<String> {
+        let mut tokenizer = self.tokenizer()?.to_string();
+
+        if !self.normalizers.is_empty() {
+            tokenizer = normalized_tokenizer_name(&tokenizer, &self.normalizers);
+        }
+
+        if self.ascii_folding {
+            tokenizer = ascii_folding_tokenizer_name(&tokenizer);
+        }
+
+        Some(tokenizer)
+    }
+
+    fn text_indexing(&self) -> Option<TextFieldIndexing> {
+        if !self.indexed {
+            return None;
+        }
+
+        let record = match self.kind {
+            FieldKind::String | FieldKind::Flattened => IndexRecordOption::Basic,
+            _ => IndexRecordOption::WithFreqsAndPositions,
+        };
+
+        let indexing = TextFieldIndexing::default()
+            .set_tokenizer(self.indexing_tokenizer().as_deref().unwrap_or("default"))
+            .set_index_option(record);
+
+        Some(indexing)
+    }
+
+    fn text_options(&self) -> TextOptions {
+        let mut options = TextOptions::default();
+        if self.stored {
+            options = options.set_stored();
+        }
+        if let Some(indexing) = self.text_indexing() {
+            options = options.set_indexing_options(indexing);
+        }
+        if self.fast {
+            // Normalized keywords are aggregated by their normalized value, so the
+            // tokenizer must also be registered for the index's fast fields.
+            let tokenizer = self.indexing_tokenizer();
+            let normalizer = tokenizer
+                .as_deref()
+                .filter(|_| !self.normalizers.is_empty());
+            options = options.set_fast(normalizer);
+        }
+        options
+    }
+
+    fn numeric_options(&self) -> NumericOptions {
+        let mut options = NumericOptions::default();
+        if self.stored {
+            options = options.set_stored();
+        }
+        if self.indexed {
+            options = options.set_indexed();
+        }
+        if self.fast {
+            options = options.set_fast();
+        }
+        options
+    }
+}
+
+/// Checks the name of a field is allowed.
+///
+/// Names starting with `_` are reserved for internal fields and `.` is used to
+/// address keys nested within dynamic objects.
+pub(crate) fn validate_field_name(name: &str) -> Result<(), SchemaError> {
+    let invalid = |reason: &str| SchemaError::InvalidFieldName {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    };
+
+    if name.is_empty() {
+        return Err(invalid("the name cannot be empty"));
+    }
+
+    if name.starts_with('_') {
+        return Err(invalid("names starting with `_` are reserved"));
+    }
+
+    if name.contains('.') {
+        return Err(invalid("names cannot contain `.`"));
+    }
+
+    Ok(())
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::TokenStream;
+
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_schema_defaults() {
+        let schema = parse(r#"{"fields": {"title": {"type": "text"}}}"#);
+        assert_eq!(
+            schema.field("title"),
+            Some(&FieldDefinition::new(FieldKind::Text))
+        );
+        assert_eq!(schema.field("title").unwrap().tokenizer(), Some("default"));
+    }
+
+    #[test]
+    fn test_build_schema() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "title": {"type": "text", "tokenizer": "en_stem"},
+                    "id": {"type": "string", "fast": true},
+                    "views": {"type": "u64", "stored": false, "fast": true},
+                    "body": {"type": "text", "indexed": false},
+                    "created_at": {"type": "datetime", "fast": true},
+                    "tags": {"type": "facet"},
+                    "meta": {"type": "dynamic"}
+                }
+            }"#,
+        );
+
+        let (schema, _) = schema.build().unwrap();
+        // The defined fields along with the hidden field presence field.
+        assert_eq!(schema.fields().count(), 8);
+
+        let views = schema.get_field_entry(schema.get_field("views").unwrap());
+        assert!(!views.is_stored());
+        assert!(views.is_fast());
+        assert!(views.is_indexed());
+
+        let body = schema.get_field_entry(schema.get_field("body").unwrap());
+        assert!(body.is_stored());
+        assert!(!body.is_indexed());
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(matches!(
+            IndexSchema::default().validate(),
+            Err(SchemaError::Empty)
+        ));
+
+        let cases = [
+            r#"{"fields": {"_id": {"type": "u64"}}}"#,
+            r#"{"fields": {"a.b": {"type": "u64"}}}"#,
+            r#"{"fields": {"a": {"type": "u64", "tokenizer": "raw"}}}"#,
+            r#"{"fields": {"a": {"type": "text", "tokenizer": "unknown"}}}"#,
+            r#"{"fields": {"a": {"type": "text", "fast": true}}}"#,
+            r#"{"fields": {"a": {"type": "facet", "indexed": false}}}"#,
+            r#"{"fields": {"a": {"type": "u64", "stored": false, "indexed": false}}}"#,
+            r#"{"fields": {"a": {"type": "u64", "position_gap": 100}}}"#,
+            r#"{"fields": {"a": {"type": "text", "position_gap": 0}}}"#,
+            r#"{"fields": {"a": {"type": "text", "multi_value": false, "position_gap": 10}}}"#,
+        ];
+
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+
+        let unknown_option = r#"{"fields": {"a": {"type": "u64", "sorted": true}}}"#;
+        assert!(serde_json::from_str::<IndexSchema>(unknown_option).is_err());
+    }
+
+    #[test]
+    fn test_datetime_formats() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "created_at": {
+                        "type": "datetime",
+                        "formats": ["unix_seconds", "rfc2822"],
+                        "output_format": "unix_millis"
+                    },
+                    "updated_at": {"type": "datetime"}
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let created_at = schema.field("created_at").unwrap();
+        let parser = created_at.datetime_parser().unwrap();
+        let dt = parser.try_convert_timestamp(1).unwrap();
+        assert_eq!(
+            dt,
+            parser
+                .try_parse_str("Thu, 01 Jan 1970 00:00:01 +0000")
+                .unwrap()
+        );
+        assert_eq!(
+            created_at
+                .datetime_output_format()
+                .unwrap()
+                .render(dt)
+                .unwrap(),
+            lnx_document::Value::I64(1_000),
+        );
+
+        let updated_at = schema.field("updated_at").unwrap();
+        let parser = updated_at.datetime_parser().unwrap();
+        assert_eq!(parser.supported_formats(), "unix_micros,rfc3339");
+        assert!(schema
+            .field("created_at")
+            .unwrap()
+            .datetime_parser()
+            .is_some());
+
+        let cases = [
+            r#"{"fields": {"a": {"type": "u64", "formats": ["rfc3339"]}}}"#,
+            r#"{"fields": {"a": {"type": "datetime", "formats": ["[nope]"]}}}"#,
+            r#"{"fields": {"a": {"type": "datetime", "output_format": "[nope]"}}}"#,
+        ];
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_register_position_gap_tokenizers() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "tags": {"type": "text", "position_gap": 100},
+                    "title": {"type": "text"}
+                }
+            }"#,
+        );
+
+        let manager = TokenizerManager::default();
+        schema.register_tokenizers(&manager).unwrap();
+
+        let tags = schema.field("tags").unwrap();
+        assert_eq!(tags.indexing_tokenizer().as_deref(), Some("default+gap100"));
+        assert!(manager.get("default+gap100").is_some());
+
+        let title = schema.field("title").unwrap();
+        assert_eq!(title.indexing_tokenizer().as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn test_register_ascii_folding_tokenizers() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "name": {"type": "text", "ascii_folding": true, "position_gap": 10},
+                    "city": {"type": "string", "ascii_folding": true}
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let manager = TokenizerManager::default();
+        schema.register_tokenizers(&manager).unwrap();
+
+        let name = schema.field("name").unwrap();
+        assert_eq!(
+            name.indexing_tokenizer().as_deref(),
+            Some("default+folded+gap10")
+        );
+        assert!(manager.get("default+folded").is_some());
+        assert!(manager.get("default+folded+gap10").is_some());
+
+        let city = schema.field("city").unwrap();
+        let analyzer = manager.get(&city.indexing_tokenizer().unwrap()).unwrap();
+        let mut stream = analyzer.token_stream("Müller Café");
+        assert!(stream.advance());
+        assert_eq!(stream.token().text, "Muller Cafe");
+
+        let schema =
+            parse(r#"{"fields": {"a": {"type": "u64", "ascii_folding": true}}}"#);
+        assert!(schema.validate().is_err());
+    }
+
+    #[test]
+    fn test_keyword_normalizers() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "tag": {"type": "keyword", "normalizers": ["trim", "lowercase"]}
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let tag = schema.field("tag").unwrap();
+        assert_eq!(tag.kind, FieldKind::String);
+        assert_eq!(
+            tag.indexing_tokenizer().as_deref(),
+            Some("raw+trim+lowercase")
+        );
+
+        let manager = TokenizerManager::default();
+        schema.register_tokenizers(&manager).unwrap();
+        let analyzer = manager.get("raw+trim+lowercase").unwrap();
+        let mut stream = analyzer.token_stream("  Rust Lang ");
+        assert!(stream.advance());
+        assert_eq!(stream.token().text, "rust lang");
+        assert_eq!(
+            (stream.token().offset_from, stream.token().offset_to),
+            (2, 11)
+        );
+        assert!(!stream.advance());
+
+        let schema = parse(
+            r#"{"fields": {"a": {"type": "text", "normalizers": ["lowercase"]}}}"#,
+        );
+        assert!(schema.validate().is_err());
+    }
+
+    #[test]
+    fn test_custom_analyzer_tokenizers() {
+        let schema = parse(
+            r#"{
+                "fields": {
+                    "tags": {"type": "text", "tokenizer": "tags", "position_gap": 10}
+                },
+                "analysis": {
+                    "analyzers": {"tags": {"filters": [{"type": "lowercase"}]}}
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let manager = TokenizerManager::default();
+        schema.register_tokenizers(&manager).unwrap();
+        assert!(manager.get("tags").is_some());
+        assert!(manager.get("tags+gap10").is_some());
+
+        let cases = [
+            r#"{"fields": {"a": {"type": "text", "tokenizer": "missing"}}}"#,
+            r#"{"fields": {"a": {"type": "text"}}, "analysis": {"analyzers": {"raw": {}}}}"#,
+            r#"{"fields": {"a": {"type": "text"}}, "analysis": {"analyzers": {"english": {}}}}"#,
+        ];
+        for case in cases {
+            assert!(parse(case).validate().is_err(), "{case} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_discover_fields() {
+        let schema = parse(
+            r#"{
+                "fields": {"message": {"type": "text"}},
+                "dynamic": {
+                    "mode": "map",
+                    "templates": [{"match": "*_at", "mapping": {"type": "datetime"}}]
+                }
+            }"#,
+        );
+        schema.validate().unwrap();
+
+        let document: DynamicDocument = serde_json::from_str(
+            r#"{"message": "hello", "seen_at": 1, "status": 200, "_id": 1, "empty": null}"#,
+        )
+        .unwrap();
+
+        let discovered = schema.discover_fields(&document).unwrap();
+        let kinds = discovered
+            .iter()
+            .map(|(name, field)| (name.as_str(), field.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [("seen_at", FieldKind::Datetime), ("status", FieldKind::U64)]
+        );
+
+        let mut strict = schema.clone();
+        strict.dynamic.mode = DynamicMode::Strict;
+        assert!(matches!(
+            strict.discover_fields(&document),
+            Err(SchemaError::UnknownField(_))
+        ));
+
+        let mut ignore = schema;
+        ignore.dynamic.mode = DynamicMode::Ignore;
+        assert!(ignore.discover_fields(&document).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_field_kind_accepts() {
+        assert!(FieldKind::I64.accepts(DocumentFieldType::U64));
+        assert!(!FieldKind::U64.accepts(DocumentFieldType::I64));
+        assert!(FieldKind::Datetime.accepts(DocumentFieldType::String));
+        assert!(FieldKind::Bool.accepts(DocumentFieldType::Null));
+        assert!(!FieldKind::Text.accepts(DocumentFieldType::Object));
+        assert!(FieldKind::Dynamic.accepts(DocumentFieldType::Object));
+    }
+}
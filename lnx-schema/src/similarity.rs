@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::SchemaError;
+use crate::schema::{FieldDefinition, FieldKind};
+
+/// The default term frequency saturation of BM25.
+pub const DEFAULT_BM25_K1: f32 = 1.2;
+/// The default length normalization of BM25.
+pub const DEFAULT_BM25_B: f32 = 0.75;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The parameters of the BM25 similarity used to score matches on a field.
+///
+/// These only affect scoring at query time, so changing them never requires a reindex.
+pub struct Bm25Params {
+    #[serde(default = "Bm25Params::default_k1")]
+    /// How quickly the score saturates as a term occurs more often within a value,
+    /// lower values reward repeated terms less.
+    ///
+    /// Defaults to `1.2`.
+    pub k1: f32,
+    #[serde(default = "Bm25Params::default_b")]
+    /// How strongly scores are normalized by the length of the value, from `0.0`
+    /// (no normalization) to `1.0` (full normalization), i.e. short titles
+    /// generally benefit from a lower value than long bodies.
+    ///
+    /// Defaults to `0.75`.
+    pub b: f32,
+}
+
+impl Default for Bm25Params {
+    fn default() -> Self {
+        Self {
+            k1: DEFAULT_BM25_K1,
+            b: DEFAULT_BM25_B,
+        }
+    }
+}
+
+impl Bm25Params {
+    fn default_k1() -> f32 {
+        DEFAULT_BM25_K1
+    }
+
+    fn default_b() -> f32 {
+        DEFAULT_BM25_B
+    }
+
+    /// Checks the parameters are within their supported ranges.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.k1.is_finite() || self.k1 < 0.0 {
+            return Err(format!(
+                "`k1` must be a finite, non-negative number, got {}",
+                self.k1
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.b) {
+            return Err(format!("`b` must be between 0 and 1, got {}", self.b));
+        }
+
+        Ok(())
+    }
+}
+
+impl FieldDefinition {
+    /// Checks the `bm25` option of the field.
+    pub(crate) fn validate_bm25(&self, name: &str) -> Result<(), SchemaError> {
+        let Some(params) = self.bm25.as_ref() else {
+            return Ok(());
+        };
+
+        if !matches!(
+            self.kind,
+            FieldKind::Text | FieldKind::String | FieldKind::Dynamic
+        ) {
+            return Err(SchemaError::invalid_options(
+                name,
+                format!(
+                    "BM25 parameters cannot be set on `{}` fields",
+                    self.kind.type_name()
+                ),
+            ));
+        }
+
+        if !self.indexed {
+            return Err(SchemaError::invalid_options(
+                name,
+                "BM25 parameters cannot be set on fields which are not indexed",
+            ));
+        }
+
+        params
+            .validate()
+            .map_err(|reason| SchemaError::invalid_options(name, reason))
+    }
+}
+
+impl FieldDefinition {
+    /// Checks the `boost` option of the field.
+    pub(crate) fn validate_boost(&self, name: &str) -> Result<(), SchemaError> {
+        let Some(boost) = self.boost else {
+            return Ok(());
+        };
+
+        if !matches!(
+            self.kind,
+            FieldKind::Text | FieldKind::String | FieldKind::Dynamic
+        ) || !self.indexed
+        {
+            return Err(SchemaError::invalid_options(
+                name,
+                "a boost can only be set on indexed `text`, `string` or `dynamic` fields",
+            ));
+        }
+
+        if !boost.is_finite() || boost <= 0.0 {
+            return Err(SchemaError::invalid_options(
+                name,
+                format!("the boost must be a positive number, got {boost}"),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::IndexSchema;
+
+    #[test]
+    fn test_bm25_params() {
+        let schema: IndexSchema = serde_json::from_str(
+            r#"{
+                "fields": {
+                    "title": {"type": "text", "bm25": {"b": 0.3}},
+                    "body": {"type": "text"}
+                }
+            }"#,
+        )
+        .unwrap();
+        schema.validate().unwrap();
+
+        let params = schema.field("title").unwrap().bm25.unwrap();
+        assert_eq!(params, Bm25Params { k1: 1.2, b: 0.3 });
+        assert!(schema.field("body").unwrap().bm25.is_none());
+
+        let mut field = FieldDefinition::new(FieldKind::Text);
+        field.bm25 = Some(Bm25Params { k1: 1.2, b: 1.5 });
+        assert!(field.validate("title").is_err());
+
+        let mut field = FieldDefinition::new(FieldKind::U64);
+        field.bm25 = Some(Bm25Params::default());
+        assert!(field.validate("views").is_err());
+    }
+
+    #[test]
+    fn test_field_boost() {
+        let mut field = FieldDefinition::new(FieldKind::Text);
+        field.boost = Some(3.0);
+        assert!(field.validate("title").is_ok());
+
+        field.boost = Some(0.0);
+        assert!(field.validate("title").is_err());
+
+        let mut field = FieldDefinition::new(FieldKind::Bool);
+        field.boost = Some(2.0);
+        assert!(field.validate("active").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use lnx_document::DynamicDocument;
+
+use crate::schema::IndexSchema;
+
+impl IndexSchema {
+    /// Returns if the values of the given key are returned with documents.
+    ///
+    /// Keys which are not part of the schema are kept as part of the document's source.
+    pub fn is_stored(&self, key: &str) -> bool {
+        self.fields.get(key).map_or(true, |field| field.stored)
+    }
+
+    /// Removes the values of any fields which are not stored from the document.
+    ///
+    /// This is applied to documents before they are returned, fields which are only
+    /// indexed (i.e. internal join keys) or only fast fields are still searchable and
+    /// usable in sorts and aggregations but never returned.
+    pub fn retain_stored(&self, document: &mut DynamicDocument) {
+        document.retain(|(key, _)| self.is_stored(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lnx_document::Value;
+
+    use super::*;
+
+    fn schema() -> IndexSchema {
+        serde_json::from_str(
+            r#"{
+                "fields": {
+                    "html": {"type": "text", "indexed": false},
+                    "tenant_id": {"type": "string", "stored": false},
+                    "title": {"type": "text"}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stored_indexed_split() {
+        let (schema, _) = schema().build().unwrap();
+
+        let html = schema.get_field_entry(schema.get_field("html").unwrap());
+        assert!(html.is_stored());
+        assert!(!html.is_indexed());
+
+        let tenant_id = schema.get_field_entry(schema.get_field("tenant_id").unwrap());
+        assert!(!tenant_id.is_stored());
+        assert!(tenant_id.is_indexed());
+    }
+
+    #[test]
+    fn test_retain_stored() {
+        let mut document: DynamicDocument = serde_json::from_str(
+            r#"{"html": "<p>Hello</p>", "tenant_id": "a", "title": "Hello", "extra": 1}"#,
+        )
+        .unwrap();
+        schema().retain_stored(&mut document);
+
+        let keys = document
+            .iter()
+            .map(|(key, _)| key.as_ref())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["html", "title", "extra"]);
+        assert_eq!(document[1].1, Value::from("Hello"));
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dynamic::wildcard_matches;
+use crate::error::SchemaError;
+use crate::schema::IndexSchema;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// A template used to create indexes automatically when documents are written to an
+/// index which does not exist.
+pub struct IndexTemplate {
+    /// The index name patterns the template applies to, `*` matches any sequence
+    /// of characters, i.e. `logs-*`.
+    pub index_patterns: Vec<String>,
+    #[serde(default)]
+    /// The priority of the template when several templates match the same index,
+    /// the template with the highest priority is used.
+    pub priority: u32,
+    /// The schema of indexes created from the template.
+    pub schema: IndexSchema,
+}
+
+impl IndexTemplate {
+    /// Checks the patterns and schema of the template are valid.
+    pub fn validate(&self) -> Result<(), SchemaError> {
+        if self.index_patterns.is_empty() {
+            return Err(SchemaError::invalid_options(
+                "index_patterns",
+                "a template must have at least one index pattern",
+            ));
+        }
+
+        if self.index_patterns.iter().any(String::is_empty) {
+            return Err(SchemaError::invalid_options(
+                "index_patterns",
+                "index patterns cannot be empty",
+            ));
+        }
+
+        self.schema.validate()
+    }
+
+    /// Returns if the template applies to the given index.
+    pub fn matches(&self, index: &str) -> bool {
+        self.index_patterns
+            .iter()
+            .any(|pattern| wildcard_matches(pattern, index))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+/// The index templates of a cluster keyed by their name.
+pub struct IndexTemplates {
+    templates: BTreeMap<String, IndexTemplate>,
+}
+
+impl IndexTemplates {
+    /// Adds or replaces the template with the given name.
+    ///
+    /// Returns the previous template if one existed.
+    pub fn put(
+        &mut self,
+        name: &str,
+        template: IndexTemplate,
+    ) -> Result<Option<IndexTemplate>, SchemaError> {
+        template.validate()?;
+        Ok(self.templates.insert(name.to_string(), template))
+    }
+
+    /// Removes the template with the given name.
+    pub fn remove(&mut self, name: &str) -> Option<IndexTemplate> {
+        self.templates.remove(name)
+    }
+
+    #[inline]
+    /// Returns the template with the given name if it exists.
+    pub fn get(&self, name: &str) -> Option<&IndexTemplate> {
+        self.templates.get(name)
+    }
+
+    /// Returns an iterator over the templates and their names.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &IndexTemplate)> {
+        self.templates
+            .iter()
+            .map(|(name, template)| (name.as_str(), template))
+    }
+
+    /// Resolves the template used to create the given index if it does not exist.
+    ///
+    /// The matching template with the highest priority is used, ties are broken by
+    /// the name of the template so the resolved template is deterministic.
+    pub fn resolve(&self, index: &str) -> Option<(&str, &IndexTemplate)> {
+        self.iter()
+            .filter(|(_, template)| template.matches(index))
+            .fold(None, |best, (name, template)| match best {
+                Some((_, current)) if current.priority >= template.priority => best,
+                _ => Some((name, template)),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(json: &str) -> IndexTemplate {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_template() {
+        let mut templates = IndexTemplates::default();
+        templates
+            .put(
+                "logs",
+                template(
+                    r#"{
+                        "index_patterns": ["logs-*"],
+                        "schema": {"fields": {"message": {"type": "text"}}}
+                    }"#,
+                ),
+            )
+            .unwrap();
+        templates
+            .put(
+                "nginx",
+                template(
+                    r#"{
+                        "index_patterns": ["logs-nginx-*", "nginx"],
+                        "priority": 10,
+                        "schema": {"fields": {"status": {"type": "u64"}}}
+                    }"#,
+                ),
+            )
+            .unwrap();
+
+        let (name, _) = templates.resolve("logs-app-2023.02.01").unwrap();
+        assert_eq!(name, "logs");
+        let (name, template) = templates.resolve("logs-nginx-2023.02.01").unwrap();
+        assert_eq!(name, "nginx");
+        assert!(template.schema.field("status").is_some());
+        assert!(templates.resolve("metrics-2023.02.01").is_none());
+
+        templates.remove("nginx");
+        let (name, _) = templates.resolve("logs-nginx-2023.02.01").unwrap();
+        assert_eq!(name, "logs");
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let cases = [
+            r#"{"index_patterns": [], "schema": {"fields": {"a": {"type": "u64"}}}}"#,
+            r#"{"index_patterns": [""], "schema": {"fields": {"a": {"type": "u64"}}}}"#,
+            r#"{"index_patterns": ["logs-*"], "schema": {"fields": {}}}"#,
+        ];
+
+        let mut templates = IndexTemplates::default();
+        for case in cases {
+            assert!(
+                templates.put("invalid", template(case)).is_err(),
+                "{case} should be rejected"
+            );
+        }
+    }
+}
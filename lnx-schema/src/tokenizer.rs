@@ -0,0 +1,137 @@
+use tantivy::tokenizer::{BoxTokenStream, TextAnalyzer, Token, TokenStream, Tokenizer};
+
+use crate::analysis::KeywordNormalizer;
+use crate::limits::TokenLimits;
+
+/// The number of positions tantivy leaves between the values of a multi-valued field.
+pub const DEFAULT_POSITION_GAP: u32 = 1;
+
+/// The name a tokenizer is registered under when its tokens are normalized by the
+/// given keyword normalizers.
+pub fn normalized_tokenizer_name(
+    tokenizer: &str,
+    normalizers: &[KeywordNormalizer],
+) -> String {
+    let mut name = tokenizer.to_string();
+    for normalizer in normalizers {
+        name.push('+');
+        name.push_str(normalizer.name());
+    }
+    name
+}
+
+/// The name a tokenizer is registered under when its tokens are folded to ASCII.
+pub fn ascii_folding_tokenizer_name(tokenizer: &str) -> String {
+    format!("{tokenizer}+folded")
+}
+
+/// The name a tokenizer is registered under when its tokens are capped by the
+/// given token limits.
+pub fn token_limits_tokenizer_name(tokenizer: &str, limits: &TokenLimits) -> String {
+    let mut name = tokenizer.to_string();
+    if let Some(max) = limits.max_token_length {
+        name.push_str(&format!("+maxlen{max}"));
+    }
+    if let Some(max) = limits.max_tokens {
+        name.push_str(&format!("+maxtokens{max}"));
+    }
+    name
+}
+
+/// The name a tokenizer is registered under when used with a custom position gap.
+pub fn position_gap_tokenizer_name(tokenizer: &str, position_gap: u32) -> String {
+    format!("{tokenizer}+gap{position_gap}")
+}
+
+#[derive(Clone)]
+/// Wraps an analyzer, increasing the gap between the values of a multi-valued field.
+///
+/// Each value of a field is tokenized separately with tantivy continuing the positions
+/// of the next value after the previous value, leaving a gap of `1` position. Offsetting
+/// the positions of every value by `position_gap - 1` widens the gap so phrase and slop
+/// queries do not match across distinct values, without affecting positions within a value.
+pub struct PositionGapTokenizer {
+    analyzer: TextAnalyzer,
+    offset: usize,
+}
+
+impl PositionGapTokenizer {
+    /// Creates a new tokenizer leaving `position_gap` positions between values.
+    pub fn new(analyzer: TextAnalyzer, position_gap: u32) -> Self {
+        let offset = position_gap.saturating_sub(DEFAULT_POSITION_GAP) as usize;
+        Self { analyzer, offset }
+    }
+}
+
+impl Tokenizer for PositionGapTokenizer {
+    type TokenStream<'a> = PositionGapTokenStream<'a>;
+
+    fn token_stream<'a>(&self, text: &'a str) -> Self::TokenStream<'a> {
+        PositionGapTokenStream {
+            inner: self.analyzer.token_stream(text),
+            offset: self.offset,
+            token: Token::default(),
+        }
+    }
+}
+
+/// The token stream produced by a [PositionGapTokenizer].
+///
+/// The offset is applied to a copy of the inner stream's current token, as the inner
+/// stream may derive the position of its next token from the current token's position.
+pub struct PositionGapTokenStream<'a> {
+    inner: BoxTokenStream<'a>,
+    offset: usize,
+    token: Token,
+}
+
+impl<'a> TokenStream for PositionGapTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if !self.inner.advance() {
+            return false;
+        }
+
+        self.token.clone_from(self.inner.token());
+        self.token.position += self.offset;
+        true
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::tokenizer::SimpleTokenizer;
+
+    use super::*;
+
+    #[test]
+    fn test_position_gap_tokenizer() {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer);
+        let tokenizer = PositionGapTokenizer::new(analyzer, 100);
+
+        let mut positions = Vec::new();
+        let mut stream = tokenizer.token_stream("hello big world");
+        while stream.advance() {
+            positions.push(stream.token().position);
+        }
+
+        assert_eq!(positions, [99, 100, 101]);
+    }
+
+    #[test]
+    fn test_default_position_gap() {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer);
+        let tokenizer = PositionGapTokenizer::new(analyzer, DEFAULT_POSITION_GAP);
+
+        let mut stream = tokenizer.token_stream("hello");
+        assert!(stream.advance());
+        assert_eq!(stream.token().position, 0);
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tantivy::schema::{Schema, SchemaBuilder};
+
+use crate::document_boost::DOCUMENT_BOOST_FIELD;
+use crate::error::SchemaError;
+use crate::indexing::IndexingSchema;
+use crate::nested::{
+    NestedFields,
+    NESTED_CHILD_FIELD,
+    NESTED_PARENT_FIELD,
+    NESTED_PATH_FIELD,
+};
+use crate::presence::{
+    add_field_presence_field,
+    register_field_presence_field,
+    FIELD_PRESENCE_FIELD,
+};
+use crate::schema::{FieldDefinition, FieldKind, IndexSchema};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+/// A change to an existing field which cannot be applied without a reindex.
+pub enum DestructiveChange {
+    /// The field was removed from the schema.
+    Removed { field: String },
+    /// The type of the field was changed.
+    TypeChanged {
+        field: String,
+        from: FieldKind,
+        to: FieldKind,
+    },
+    /// An option of the field was changed.
+    OptionChanged {
+        field: String,
+        option: &'static str,
+        from: String,
+        to: String,
+    },
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+/// The difference between the current schema of an index and an updated schema.
+pub struct SchemaDiff {
+    /// The fields which are added by the update.
+    pub added: Vec<String>,
+    /// The changes which would require the index to be rebuilt.
+    pub destructive: Vec<DestructiveChange>,
+}
+
+impl SchemaDiff {
+    #[inline]
+    /// Returns if the update only adds fields and can be applied to a live index.
+    pub fn is_additive(&self) -> bool {
+        self.destructive.is_empty()
+    }
+}
+
+/// The result of applying an additive schema update.
+pub struct SchemaUpdate {
+    /// The updated schema definition.
+    pub definition: IndexSchema,
+    /// The updated tantivy schema, existing fields keep their field IDs.
+    pub schema: Schema,
+    /// The indexing schema for the updated definition.
+    pub indexing: IndexingSchema,
+    /// The fields which were added.
+    pub added: Vec<String>,
+}
+
+impl IndexSchema {
+    /// Compares the schema against an updated schema.
+    ///
+    /// Changes to the dynamic mapping settings only affect documents ingested after
+    /// the update so are never destructive. Enabling document boosts adds the hidden
+    /// `_boost` field while disabling them removes it.
+    pub fn diff(&self, updated: &IndexSchema) -> SchemaDiff {
+        let mut diff = SchemaDiff::default();
+        let current_fields = self.all_fields();
+        let updated_fields = updated.all_fields();
+
+        for (name, field) in current_fields.iter() {
+            match updated_fields.get(name) {
+                None => diff.destructive.push(DestructiveChange::Removed {
+                    field: name.clone(),
+                }),
+                Some(new) => diff_field(name, field, new, &mut diff.destructive),
+            }
+        }
+
+        for name in updated_fields.keys() {
+            if !current_fields.contains_key(name) {
+                diff.added.push(name.clone());
+            }
+        }
+
+        match (self.document_boost, updated.document_boost) {
+            (true, false) => diff.destructive.push(DestructiveChange::Removed {
+                field: DOCUMENT_BOOST_FIELD.to_string(),
+            }),
+            (false, true) => diff.added.push(DOCUMENT_BOOST_FIELD.to_string()),
+            _ => {},
+        }
+
+        diff
+    }
+
+    /// Applies an updated schema to an index whose current tantivy schema is `schema`.
+    ///
+    /// The update is rejected with the list of destructive changes if it does anything
+    /// other than add new fields, the new fields are appended to the tantivy schema so
+    /// existing segments remain valid.
+    pub fn apply_update(
+        &self,
+        schema: &Schema,
+        updated: IndexSchema,
+    ) -> Result<SchemaUpdate, SchemaError> {
+        updated.validate()?;
+
+        let diff = self.diff(&updated);
+        if !diff.is_additive() {
+            return Err(SchemaError::DestructiveChange(diff.destructive));
+        }
+
+        let mut builder = SchemaBuilder::new();
+        for (_, entry) in schema.fields() {
+            builder.add_field(entry.clone());
+        }
+
+        let updated_fields = updated.all_fields();
+        for (name, field) in updated_fields.iter() {
+            if schema.get_field(name).is_err() {
+                field.add_to_schema(name, &mut builder);
+            }
+        }
+
+        if schema.get_field(NESTED_CHILD_FIELD).is_err() {
+            updated.add_nested_fields(&mut builder);
+        }
+
+        if schema.get_field(DOCUMENT_BOOST_FIELD).is_err() {
+            updated.add_document_boost_field(&mut builder);
+        }
+
+        if schema.get_field(FIELD_PRESENCE_FIELD).is_err() {
+            add_field_presence_field(&mut builder);
+        }
+
+        let schema = builder.build();
+        let mut indexing = IndexingSchema::default();
+        for (name, field) in updated_fields.iter() {
+            let field_id = schema
+                .get_field(name)
+                .expect("Field should exist within the updated schema");
+            indexing.add_field(name, field.indexing_type(field_id));
+        }
+
+        if !updated.nested.is_empty() {
+            let nested_field = |name: &str| {
+                schema
+                    .get_field(name)
+                    .expect("Nested fields should exist within the updated schema")
+            };
+            let fields = NestedFields {
+                path: nested_field(NESTED_PATH_FIELD),
+                child: nested_field(NESTED_CHILD_FIELD),
+                parent: nested_field(NESTED_PARENT_FIELD),
+            };
+            updated.register_nested_fields(&mut indexing, fields);
+        }
+
+        if updated.document_boost {
+            let field_id = schema
+                .get_field(DOCUMENT_BOOST_FIELD)
+                .expect("Document boost field should exist within the updated schema");
+            updated.register_document_boost_field(&mut indexing, field_id);
+        }
+
+        let field_id = schema
+            .get_field(FIELD_PRESENCE_FIELD)
+            .expect("Field presence field should exist within the updated schema");
+        register_field_presence_field(&mut indexing, field_id);
+
+        Ok(SchemaUpdate {
+            definition: updated,
+            schema,
+            indexing,
+            added: diff.added,
+        })
+    }
+}
+
+impl IndexSchema {
+    /// The fields of the schema and of its nested child documents keyed by their
+    /// full name.
+    fn all_fields(&self) -> BTreeMap<String, &FieldDefinition> {
+        self.fields
+            .iter()
+            .map(|(name, field)| (name.clone(), field))
+            .chain(self.nested_fields())
+            .collect()
+    }
+}
+
+fn diff_field(
+    name: &str,
+    current: &FieldDefinition,
+    updated: &FieldDefinition,
+    changes: &mut Vec<DestructiveChange>,
+) {
+    if current.kind != updated.kind {
+        changes.push(DestructiveChange::TypeChanged {
+            field: name.to_string(),
+            from: current.kind,
+            to: updated.kind,
+        });
+        return;
+    }
+
+    let mut changed = |option: &'static str, from: String, to: String| {
+        if from != to {
+            changes.push(DestructiveChange::OptionChanged {
+                field: name.to_string(),
+                option,
+                from,
+                to,
+            });
+        }
+    };
+
+    changed(
+        "stored",
+        current.stored.to_string(),
+        updated.stored.to_string(),
+    );
+    changed(
+        "indexed",
+        current.indexed.to_string(),
+        updated.indexed.to_string(),
+    );
+    changed("fast", current.fast.to_string(), updated.fast.to_string());
+    changed(
+        "tokenizer",
+        current.tokenizer().unwrap_or_default().to_string(),
+        updated.tokenizer().unwrap_or_default().to_string(),
+    );
+    changed(
+        "normalizers",
+        format!("{:?}", current.normalizers),
+        format!("{:?}", updated.normalizers),
+    );
+    changed(
+        "ascii_folding",
+        current.ascii_folding.to_string(),
+        updated.ascii_folding.to_string(),
+    );
+    changed(
+        "token_limits",
+        format!("{:?}", current.token_limits),
+        format!("{:?}", updated.token_limits),
+    );
+    changed(
+        "position_gap",
+        format!("{:?}", current.position_gap),
+        format!("{:?}", updated.position_gap),
+    );
+
+    changed(
+        "copy_to",
+        current.copy_to.join(","),
+        updated.copy_to.join(","),
+    );
+
+    // BM25 parameters and boosts only apply at query time so are never destructive.
+
+    // Allowing multiple values is safe, existing documents only have a single value.
+    if current.multi_value && !updated.multi_value {
+        changed("multi_value", true.to_string(), false.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> IndexSchema {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn current() -> IndexSchema {
+        parse(
+            r#"{
+                "fields": {
+                    "title": {"type": "text"},
+                    "views": {"type": "u64", "fast": true}
+                }
+            }"#,
+        )
+    }
+
+    #[test]
+    fn test_additive_update() {
+        let current = current();
+        let (schema, _) = current.build().unwrap();
+
+        let updated = parse(
+            r#"{
+                "fields": {
+                    "title": {"type": "text"},
+                    "views": {"type": "u64", "fast": true},
+                    "author": {"type": "string"}
+                }
+            }"#,
+        );
+
+        let update = current.apply_update(&schema, updated).unwrap();
+        assert_eq!(update.added, ["author"]);
+
+        // Existing fields must keep their IDs so existing segments are still valid.
+        for name in ["title", "views"] {
+            assert_eq!(
+                update.schema.get_field(name).unwrap(),
+                schema.get_field(name).unwrap(),
+            );
+        }
+        assert!(update.schema.get_field("author").is_ok());
+
+        let mut boosted = update.definition.clone();
+        boosted.document_boost = true;
+        let update = update
+            .definition
+            .apply_update(&update.schema, boosted.clone())
+            .unwrap();
+        assert_eq!(update.added, [DOCUMENT_BOOST_FIELD]);
+        assert!(update.schema.get_field(DOCUMENT_BOOST_FIELD).is_ok());
+
+        // Disabling document boosts drops the field, so it requires a reindex.
+        let mut unboosted = boosted.clone();
+        unboosted.document_boost = false;
+        assert!(!boosted.diff(&unboosted).is_additive());
+    }
+
+    #[test]
+    fn test_nested_update() {
+        let current = current();
+        let (schema, _) = current.build().unwrap();
+
+        let mut updated = current.clone();
+        updated.nested = parse(
+            r#"{"nested": {"comments": {"author": {"type": "string"}}}}"#,
+        )
+        .nested;
+
+        let update = current.apply_update(&schema, updated.clone()).unwrap();
+        assert_eq!(update.added, ["comments.author"]);
+        assert!(update.schema.get_field("comments.author").is_ok());
+        assert!(update.schema.get_field(NESTED_CHILD_FIELD).is_ok());
+        assert!(update.schema.get_field(NESTED_PATH_FIELD).is_ok());
+        assert!(update.schema.get_field(NESTED_PARENT_FIELD).is_ok());
+
+        // Removing a nested field drops its indexed values, so it requires a reindex.
+        assert!(!updated.diff(&current).is_additive());
+    }
+
+    #[test]
+    fn test_destructive_update() {
+        let current = current();
+        let (schema, _) = current.build().unwrap();
+
+        let updated = parse(
+            r#"{
+                "fields": {
+                    "title": {"type": "string"},
+                    "author": {"type": "string"}
+                }
+            }"#,
+        );
+
+        let diff = current.diff(&updated);
+        assert_eq!(diff.added, ["author"]);
+        assert_eq!(
+            diff.destructive,
+            [
+                DestructiveChange::TypeChanged {
+                    field: "title".to_string(),
+                    from: FieldKind::Text,
+                    to: FieldKind::String,
+                },
+                DestructiveChange::Removed {
+                    field: "views".to_string(),
+                },
+            ]
+        );
+
+        assert!(matches!(
+            current.apply_update(&schema, updated),
+            Err(SchemaError::DestructiveChange(changes)) if changes.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_option_changes() {
+        let current = current();
+
+        let updated = parse(
+            r#"{
+                "fields": {
+                    "title": {"type": "text", "tokenizer": "en_stem"},
+                    "views": {"type": "u64", "fast": false}
+                }
+            }"#,
+        );
+
+        let options = current
+            .diff(&updated)
+            .destructive
+            .into_iter()
+            .map(|change| match change {
+                DestructiveChange::OptionChanged { option, .. } => option,
+                other => panic!("Unexpected change {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(options, ["tokenizer", "fast"]);
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use lnx_document::{DynamicDocument, UserDisplayType, Value};
+
+use crate::error::SchemaError;
+use crate::schema::{FieldDefinition, IndexSchema};
+
+impl IndexSchema {
+    /// Checks the values of a document against the schema before it is indexed.
+    ///
+    /// Each value must be castable to the type of its field and single-valued fields
+    /// must not contain more than one value, either as an array or as duplicate keys.
+    /// Keys which are not part of the schema are left to the dynamic mapping, except
+    /// the reserved `_boost` key, see [crate::document_boost].
+    pub fn validate_document(
+        &self,
+        document: &DynamicDocument,
+    ) -> Result<(), SchemaError> {
+        self.validate_document_boost(document)?;
+
+        let mut num_values: HashMap<&str, usize> = HashMap::new();
+
+        for (key, value) in document.iter() {
+            let Some(field) = self.fields.get(key.as_ref()) else {
+                continue;
+            };
+
+            let count = count_values(key, field, value)?;
+            if field.multi_value {
+                continue;
+            }
+
+            let total = num_values.entry(key.as_ref()).or_default();
+            *total += count;
+            if *total > 1 {
+                return Err(SchemaError::InvalidValue {
+                    field: key.to_string(),
+                    reason: "the field only accepts a single value".to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Counts the number of non-null values, checking each value is accepted by the field.
+///
+/// Nested arrays are flattened, matching how they are indexed.
+fn count_values(
+    name: &str,
+    field: &FieldDefinition,
+    value: &Value,
+) -> Result<usize, SchemaError> {
+    match value {
+        Value::Null => Ok(0),
+        Value::Array(values) => {
+            let mut count = 0;
+            for value in values {
+                count += count_values(name, field, value)?;
+            }
+            Ok(count)
+        },
+        other if field.kind.accepts(other.as_field_type()) => Ok(1),
+        other => Err(SchemaError::InvalidValue {
+            field: name.to_string(),
+            reason: format!(
+                "expected a `{}` value but got `{}`",
+                field.kind.type_name(),
+                other.type_name(),
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> IndexSchema {
+        serde_json::from_str(
+            r#"{
+                "fields": {
+                    "tags": {"type": "string"},
+                    "views": {"type": "u64", "multi_value": false}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn validate(json: &str) -> Result<(), SchemaError> {
+        let document: DynamicDocument = serde_json::from_str(json).unwrap();
+        schema().validate_document(&document)
+    }
+
+    #[test]
+    fn test_multi_value_fields() {
+        assert!(validate(r#"{"tags": ["a", ["b", "c"]], "views": 1}"#).is_ok());
+        assert!(validate(r#"{"views": [null, 1]}"#).is_ok());
+        assert!(validate(r#"{"unknown": [1, 2]}"#).is_ok());
+    }
+
+    #[test]
+    fn test_single_value_fields() {
+        assert!(matches!(
+            validate(r#"{"views": [1, 2]}"#),
+            Err(SchemaError::InvalidValue { field, .. }) if field == "views"
+        ));
+        assert!(validate(r#"{"views": 1, "views": 2}"#).is_err());
+    }
+
+    #[test]
+    fn test_value_types() {
+        assert!(validate(r#"{"views": -1}"#).is_err());
+        assert!(validate(r#"{"tags": ["a", true]}"#).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use serde::Serialize;
+
+use crate::schema::IndexSchema;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+/// A part of a schema which is valid but likely to cause problems, returned
+/// alongside a successful validation.
+pub struct SchemaWarning {
+    /// The path to the setting within the schema, i.e.
+    /// `analysis.analyzers.autocomplete.filters[1]`.
+    pub path: String,
+    /// The description of the problem.
+    pub message: String,
+}
+
+impl IndexSchema {
+    /// Returns any warnings about the schema.
+    ///
+    /// This should be called once the schema has been validated, the warnings are
+    /// returned to the user but do not prevent the schema from being used.
+    pub fn warnings(&self) -> Vec<SchemaWarning> {
+        self.analysis.warnings()
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::{bail, Result};
+use base64::Engine;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// The text encoding used to provide and return `bytes` values via JSON.
+pub enum BytesEncoding {
+    #[default]
+    /// Standard, padded base64.
+    Base64,
+    /// Hex encoded bytes, decoding is case insensitive.
+    Hex,
+}
+
+impl Display for BytesEncoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Base64 => write!(f, "base64"),
+            Self::Hex => write!(f, "hex"),
+        }
+    }
+}
+
+impl BytesEncoding {
+    /// Gets the encoding from its name, i.e. `base64` or `hex`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "base64" => Some(Self::Base64),
+            "hex" => Some(Self::Hex),
+            _ => None,
+        }
+    }
+
+    /// Decodes a string into its bytes.
+    pub fn decode(&self, s: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Base64 => {
+                let engine = base64::engine::general_purpose::STANDARD;
+                Ok(engine.decode(s)?)
+            },
+            Self::Hex => decode_hex(s),
+        }
+    }
+
+    /// Encodes the bytes as a string.
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+            Self::Hex => {
+                const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+                let mut s = String::with_capacity(bytes.len() * 2);
+                for byte in bytes {
+                    s.push(DIGITS[(byte >> 4) as usize] as char);
+                    s.push(DIGITS[(byte & 0x0F) as usize] as char);
+                }
+                s
+            },
+        }
+    }
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        bail!("Hex string must contain an even number of digits");
+    }
+
+    let digit = |b: u8| match b {
+        b'0'..=b'9' => Ok(b - b'0'),
+        b'a'..=b'f' => Ok(b - b'a' + 10),
+        b'A'..=b'F' => Ok(b - b'A' + 10),
+        other => bail!("Invalid hex digit {:?}", other as char),
+    };
+
+    s.as_bytes()
+        .chunks_exact(2)
+        .map(|pair| Ok((digit(pair[0])? << 4) | digit(pair[1])?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = vec![0x00, 0x0F, 0xAB, 0xFF];
+        let encoded = BytesEncoding::Hex.encode(&bytes);
+        assert_eq!(encoded, "000fabff");
+        assert_eq!(BytesEncoding::Hex.decode(&encoded).unwrap(), bytes);
+        assert_eq!(BytesEncoding::Hex.decode("000FABFF").unwrap(), bytes);
+
+        assert!(BytesEncoding::Hex.decode("abc").is_err());
+        assert!(BytesEncoding::Hex.decode("zz").is_err());
+    }
+
+    #[test]
+    fn test_base64_round_trip() {
+        let encoded = BytesEncoding::Base64.encode(b"hello world");
+        assert_eq!(encoded, "aGVsbG8gd29ybGQ=");
+        assert_eq!(
+            BytesEncoding::Base64.decode(&encoded).unwrap(),
+            b"hello world"
+        );
+        assert!(BytesEncoding::Base64.decode("hello, world!").is_err());
+    }
+}
@@ -1,5 +1,20 @@
+mod bytes;
+mod nested;
 mod pipeline;
 mod transformers;
 mod type_cast;
 
-pub use type_cast::{DateTimeFormat, DateTimeParser, TimestampResolution, TypeCast};
+pub use bytes::BytesEncoding;
+pub use nested::{
+    split_nested_documents,
+    NESTED_CHILD_FIELD,
+    NESTED_PARENT_FIELD,
+    NESTED_PATH_FIELD,
+};
+pub use type_cast::{
+    DateTimeFormat,
+    DateTimeOutputFormat,
+    DateTimeParser,
+    TimestampResolution,
+    TypeCast,
+};
@@ -0,0 +1,160 @@
+use std::borrow::Cow;
+
+use lnx_document::{DynamicDocument, Value};
+
+/// The hidden field containing the path of the nested field a child document was created from.
+pub const NESTED_PATH_FIELD: &str = "_lnx_nested_path";
+/// The hidden fast field marking a document as a nested child document.
+pub const NESTED_CHILD_FIELD: &str = "_lnx_nested_child";
+/// The hidden field containing the primary key of a child document's parent.
+///
+/// Deleting a parent by its primary key must also delete this term, otherwise its
+/// children remain and are joined onto a different parent once segments are merged.
+pub const NESTED_PARENT_FIELD: &str = "_lnx_nested_parent";
+
+/// Splits any nested arrays of objects out of a document into their own child documents.
+///
+/// Each object within a nested field becomes a separate document with its keys
+/// prefixed by the nested field path, i.e. `{"comments": [{"author": "bob"}]}` produces a
+/// child document of `{"comments.author": "bob"}`, and the nested field is removed from
+/// the parent document.
+///
+/// The documents are returned in a block-join layout, the child documents come first and
+/// the parent document is *always* the last document, this order must be preserved when
+/// the documents are indexed so that children can be joined back onto their parent.
+///
+/// If a `parent_key` is given, i.e. the parent's primary key, each child records it under
+/// [NESTED_PARENT_FIELD] so the whole block can be deleted by the parent's key.
+pub fn split_nested_documents<'a>(
+    document: DynamicDocument<'a>,
+    nested_paths: &[&str],
+    parent_key: Option<&str>,
+) -> Vec<DynamicDocument<'a>> {
+    if nested_paths.is_empty() {
+        return vec![document];
+    }
+
+    let mut documents = Vec::new();
+    let mut parent = Vec::with_capacity(document.len());
+
+    for (key, value) in document.0 {
+        if !nested_paths.contains(&key.as_ref()) {
+            parent.push((key, value));
+            continue;
+        }
+
+        let elements = match value {
+            Value::Array(elements) => elements,
+            object @ Value::Object(_) => vec![object],
+            other => {
+                // Non-object values cannot be nested so they remain on the parent.
+                parent.push((key, other));
+                continue;
+            },
+        };
+
+        for element in elements {
+            let Value::Object(object) = element else {
+                continue;
+            };
+
+            let mut child = Vec::with_capacity(object.len() + 3);
+            child.push((
+                Cow::Borrowed(NESTED_PATH_FIELD),
+                Value::Str(Cow::Owned(key.to_string())),
+            ));
+            child.push((Cow::Borrowed(NESTED_CHILD_FIELD), Value::U64(1)));
+            if let Some(parent_key) = parent_key {
+                child.push((
+                    Cow::Borrowed(NESTED_PARENT_FIELD),
+                    Value::Str(Cow::Owned(parent_key.to_string())),
+                ));
+            }
+
+            for (child_key, child_value) in object {
+                child.push((Cow::Owned(format!("{key}.{child_key}")), child_value));
+            }
+
+            documents.push(DynamicDocument(child));
+        }
+    }
+
+    documents.push(DynamicDocument(parent));
+    documents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_nested_documents() {
+        let document: DynamicDocument = serde_json::from_str(
+            r#"{
+                "id": 42,
+                "title": "Hello, world!",
+                "comments": [
+                    {"author": "bob", "stars": 5},
+                    {"author": "tim", "stars": 1}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let documents = split_nested_documents(document, &["comments"], Some("42"));
+        assert_eq!(documents.len(), 3);
+
+        assert_eq!(
+            documents[0].0,
+            vec![
+                (Cow::Borrowed(NESTED_PATH_FIELD), Value::from("comments")),
+                (Cow::Borrowed(NESTED_CHILD_FIELD), Value::U64(1)),
+                (Cow::Borrowed(NESTED_PARENT_FIELD), Value::from("42")),
+                (Cow::Borrowed("comments.author"), Value::from("bob")),
+                (Cow::Borrowed("comments.stars"), Value::U64(5)),
+            ],
+        );
+        assert_eq!(
+            documents[1].0,
+            vec![
+                (Cow::Borrowed(NESTED_PATH_FIELD), Value::from("comments")),
+                (Cow::Borrowed(NESTED_CHILD_FIELD), Value::U64(1)),
+                (Cow::Borrowed(NESTED_PARENT_FIELD), Value::from("42")),
+                (Cow::Borrowed("comments.author"), Value::from("tim")),
+                (Cow::Borrowed("comments.stars"), Value::U64(1)),
+            ],
+        );
+        assert_eq!(
+            documents[2].0,
+            vec![
+                (Cow::Borrowed("id"), Value::U64(42)),
+                (Cow::Borrowed("title"), Value::from("Hello, world!")),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_split_without_parent_key() {
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"comments": [{"author": "bob"}]}"#).unwrap();
+
+        let documents = split_nested_documents(document, &["comments"], None);
+        assert_eq!(documents.len(), 2);
+        assert!(documents[0]
+            .iter()
+            .all(|(key, _)| key != NESTED_PARENT_FIELD));
+    }
+
+    #[test]
+    fn test_split_without_nested_fields() {
+        let document: DynamicDocument =
+            serde_json::from_str(r#"{"title": "Hello, world!"}"#).unwrap();
+
+        let documents = split_nested_documents(document, &["comments"], None);
+        assert_eq!(documents.len(), 1);
+        assert_eq!(
+            documents[0].0,
+            vec![(Cow::Borrowed("title"), Value::from("Hello, world!"))],
+        );
+    }
+}
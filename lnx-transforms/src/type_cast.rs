@@ -3,10 +3,11 @@ use std::fmt::{Debug, Display, Formatter};
 use std::net::{Ipv4Addr, Ipv6Addr};
 
 use anyhow::{anyhow, bail, Result};
-use base64::Engine;
 use lnx_document::{DateTime, Facet, UserDisplayType, Value};
 use time::format_description::{well_known, OwnedFormatItem};
-use time::OffsetDateTime;
+use time::{OffsetDateTime, UtcOffset};
+
+use crate::BytesEncoding;
 
 /// The core types values can be casted to.
 pub enum TypeCast {
@@ -147,8 +148,7 @@ impl TypeCast {
                 Err(self.err_invalid_value(string))
             },
             Self::Bytes => {
-                let engine = base64::engine::general_purpose::STANDARD;
-                if let Ok(bytes) = engine.decode(string.as_ref()) {
+                if let Ok(bytes) = BytesEncoding::Base64.decode(string.as_ref()) {
                     Ok(Value::Bytes(bytes))
                 } else {
                     Err(self.err_invalid_value(string))
@@ -314,6 +314,16 @@ impl Display for TimestampResolution {
 }
 
 impl TimestampResolution {
+    /// Gets the resolution from its name, i.e. `unix_seconds`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unix_seconds" => Some(Self::Seconds),
+            "unix_millis" => Some(Self::Millis),
+            "unix_micros" => Some(Self::Micros),
+            _ => None,
+        }
+    }
+
     pub fn cast(&self, ts: i64) -> Option<DateTime> {
         match self {
             TimestampResolution::Seconds => DateTime::from_secs(ts),
@@ -321,6 +331,17 @@ impl TimestampResolution {
             TimestampResolution::Micros => DateTime::from_micros(ts),
         }
     }
+
+    /// Converts a datetime to a timestamp with the given resolution.
+    ///
+    /// Any remainder beyond the resolution is truncated towards the past.
+    pub fn timestamp(&self, dt: DateTime) -> i64 {
+        match self {
+            TimestampResolution::Seconds => dt.as_micros().div_euclid(1_000_000),
+            TimestampResolution::Millis => dt.as_micros().div_euclid(1_000),
+            TimestampResolution::Micros => dt.as_micros(),
+        }
+    }
 }
 
 /// A format that can be used to parse a string into a datetime.
@@ -353,6 +374,45 @@ impl Debug for DateTimeFormat {
 }
 
 impl DateTimeFormat {
+    /// Gets a format from its name, i.e. `rfc3339`.
+    ///
+    /// Any other name is parsed as a custom `time` format description,
+    /// i.e. `[year]-[month]-[day]`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "rfc3339" => Ok(Self::Rfc3339),
+            "rfc2822" => Ok(Self::Rfc2822),
+            custom => {
+                let format = time::format_description::parse_owned::<2>(custom)
+                    .map_err(|e| anyhow!("Invalid datetime format {custom:?}: {e}"))?;
+                Ok(Self::Custom {
+                    format,
+                    display: custom.to_string(),
+                })
+            },
+        }
+    }
+
+    /// Formats a datetime as a string with the given format.
+    pub fn format(&self, dt: DateTime) -> Result<String> {
+        self.format_with_offset(dt, UtcOffset::UTC)
+    }
+
+    /// Formats a datetime as a string with the given format, in the given UTC offset.
+    pub fn format_with_offset(&self, dt: DateTime, offset: UtcOffset) -> Result<String> {
+        match self {
+            DateTimeFormat::Rfc2822 => {
+                dt.format_with_offset(&well_known::Rfc2822, offset)
+            },
+            DateTimeFormat::Rfc3339 => {
+                dt.format_with_offset(&well_known::Rfc3339, offset)
+            },
+            DateTimeFormat::Custom { format, .. } => {
+                dt.format_with_offset(format, offset)
+            },
+        }
+    }
+
     /// Attempts to parse a string to a given format.
     pub fn parse(&self, s: &str) -> Result<DateTime> {
         let dt = match self {
@@ -375,6 +435,30 @@ pub struct DateTimeParser {
 }
 
 impl DateTimeParser {
+    /// Creates a parser accepting each of the named formats.
+    ///
+    /// Timestamps are accepted with one of the `unix_seconds`, `unix_millis` or
+    /// `unix_micros` resolutions, strings are accepted in the `rfc3339` and `rfc2822`
+    /// formats or as a custom format description (see [DateTimeFormat::from_name]).
+    pub fn from_formats(formats: &[impl AsRef<str>]) -> Result<Self> {
+        let mut parser = Self::default();
+
+        for format in formats {
+            let format = format.as_ref();
+            match TimestampResolution::from_name(format) {
+                Some(_) if parser.integer_timestamp_resolution.is_some() => {
+                    bail!("Only a single timestamp resolution can be accepted");
+                },
+                Some(resolution) => {
+                    parser.integer_timestamp_resolution = Some(resolution);
+                },
+                None => parser.add_string(DateTimeFormat::from_name(format)?),
+            }
+        }
+
+        Ok(parser)
+    }
+
     pub fn with_timestamp_resolution(mut self, res: TimestampResolution) -> Self {
         self.integer_timestamp_resolution = Some(res);
         self
@@ -456,6 +540,60 @@ impl DateTimeParser {
     }
 }
 
+/// A format used to render a datetime, i.e. within a search response.
+pub enum DateTimeOutputFormat {
+    /// Render the datetime as an integer timestamp.
+    Timestamp(TimestampResolution),
+    /// Render the datetime as a formatted string.
+    String(DateTimeFormat),
+}
+
+impl Display for DateTimeOutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timestamp(resolution) => write!(f, "{resolution}"),
+            Self::String(format) => write!(f, "{format}"),
+        }
+    }
+}
+
+impl Debug for DateTimeOutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl DateTimeOutputFormat {
+    /// Gets an output format from its name, accepting the same names as
+    /// [DateTimeParser::from_formats].
+    pub fn from_name(name: &str) -> Result<Self> {
+        match TimestampResolution::from_name(name) {
+            Some(resolution) => Ok(Self::Timestamp(resolution)),
+            None => DateTimeFormat::from_name(name).map(Self::String),
+        }
+    }
+
+    /// Renders the datetime in the output format.
+    pub fn render<'a>(&self, dt: DateTime) -> Result<Value<'a>> {
+        self.render_with_offset(dt, UtcOffset::UTC)
+    }
+
+    /// Renders the datetime in the output format, formatted strings are rendered in
+    /// the given UTC offset while timestamps are unaffected by the offset.
+    pub fn render_with_offset<'a>(
+        &self,
+        dt: DateTime,
+        offset: UtcOffset,
+    ) -> Result<Value<'a>> {
+        match self {
+            Self::Timestamp(resolution) => Ok(Value::I64(resolution.timestamp(dt))),
+            Self::String(format) => format
+                .format_with_offset(dt, offset)
+                .map(|s| Value::Str(Cow::Owned(s))),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -976,4 +1114,56 @@ mod tests {
             "Cannot cast `facet` to `datetime<>`"
         );
     }
+
+    #[test]
+    fn test_parser_from_formats() {
+        let parser = DateTimeParser::from_formats(&[
+            "rfc3339",
+            "unix_millis",
+            "[day]/[month]/[year] [hour]:[minute]:[second] [offset_hour sign:mandatory]",
+        ])
+        .unwrap();
+        assert_eq!(
+            parser.supported_formats(),
+            "unix_millis,rfc3339,custom<\"[day]/[month]/[year] [hour]:[minute]:[second] [offset_hour sign:mandatory]\">",
+        );
+
+        let dt = parser.try_parse_str("01/02/2023 10:30:00 +00").unwrap();
+        assert_eq!(dt, parser.try_parse_str("2023-02-01T10:30:00Z").unwrap());
+        assert_eq!(
+            parser.try_convert_timestamp(1_000).unwrap(),
+            DateTime::from_secs(1).unwrap()
+        );
+
+        assert!(DateTimeParser::from_formats(&["[nope]"]).is_err());
+        assert!(DateTimeParser::from_formats(&["unix_seconds", "unix_millis"]).is_err());
+    }
+
+    #[test]
+    fn test_output_format() {
+        let dt = DateTime::from_millis(1_675_247_400_123).unwrap();
+
+        let format = DateTimeOutputFormat::from_name("rfc3339").unwrap();
+        assert_eq!(
+            format.render(dt).unwrap(),
+            Value::from("2023-02-01T10:30:00.123Z")
+        );
+
+        let format = DateTimeOutputFormat::from_name("unix_seconds").unwrap();
+        assert_eq!(format.render(dt).unwrap(), Value::I64(1_675_247_400));
+
+        let format = DateTimeOutputFormat::from_name("[year]-[month]-[day]").unwrap();
+        assert_eq!(format.render(dt).unwrap(), Value::from("2023-02-01"));
+
+        let offset = UtcOffset::from_hms(2, 0, 0).unwrap();
+        let format = DateTimeOutputFormat::from_name("rfc3339").unwrap();
+        assert_eq!(
+            format.render_with_offset(dt, offset).unwrap(),
+            Value::from("2023-02-01T12:30:00.123+02:00")
+        );
+
+        let before_epoch = DateTime::from_millis(-1).unwrap();
+        let format = DateTimeOutputFormat::from_name("unix_seconds").unwrap();
+        assert_eq!(format.render(before_epoch).unwrap(), Value::I64(-1));
+    }
 }